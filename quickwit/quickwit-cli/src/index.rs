@@ -41,7 +41,7 @@ use quickwit_proto::types::IndexId;
 use quickwit_rest_client::models::IngestSource;
 use quickwit_rest_client::rest_client::{CommitType, IngestEvent};
 use quickwit_search::SearchResponseRest;
-use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString, SortBy};
+use quickwit_serve::{ListSplitsQueryParams, ReindexRequest, SearchRequestQueryString, SortBy};
 use quickwit_storage::{load_file, StorageResolver};
 use tabled::settings::object::{FirstRow, Rows, Segment};
 use tabled::settings::panel::Footer;
@@ -195,6 +195,26 @@ pub fn build_index_command() -> Command {
                         .required(false),
                 ])
             )
+        .subcommand(
+            Command::new("reindex")
+                .display_order(9)
+                .about("Reindexes the documents of an index into another index.")
+                .long_about("Starts a background job rewriting the documents of an index, or of a subset of them, into another existing index. The job runs on the indexers as a reindex source of the target index.")
+                .args(&[
+                    arg!(--"source-index" <SOURCE_INDEX> "ID of the index whose documents are reindexed.")
+                        .display_order(1)
+                        .required(true),
+                    arg!(--"target-index" <TARGET_INDEX> "ID of the index receiving the documents.")
+                        .display_order(2)
+                        .required(true),
+                    arg!(--query <QUERY> "Query selecting the documents to reindex. All the documents are reindexed by default.")
+                        .required(false),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Filters out documents before that timestamp (time-series indexes only).")
+                        .required(false),
+                    arg!(--"end-timestamp" <TIMESTAMP> "Filters out documents after that timestamp (time-series indexes only).")
+                        .required(false),
+                ])
+            )
         .arg_required_else_help(true)
 }
 
@@ -251,6 +271,16 @@ pub struct SearchIndexArgs {
     pub sort_by_score: bool,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct ReindexArgs {
+    pub client_args: ClientArgs,
+    pub source_index_id: IndexId,
+    pub target_index_id: IndexId,
+    pub query: Option<String>,
+    pub start_timestamp: Option<i64>,
+    pub end_timestamp: Option<i64>,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct DeleteIndexArgs {
     pub client_args: ClientArgs,
@@ -273,6 +303,7 @@ pub enum IndexCliCommand {
     Describe(DescribeIndexArgs),
     Ingest(IngestDocsArgs),
     List(ListIndexesArgs),
+    Reindex(ReindexArgs),
     Search(SearchIndexArgs),
 }
 
@@ -295,6 +326,7 @@ impl IndexCliCommand {
             "describe" => Self::parse_describe_args(submatches),
             "ingest" => Self::parse_ingest_args(submatches),
             "list" => Self::parse_list_args(submatches),
+            "reindex" => Self::parse_reindex_args(submatches),
             "search" => Self::parse_search_args(submatches),
             "update" => Self::parse_update_args(submatches),
             _ => bail!("unknown index subcommand `{subcommand}`"),
@@ -452,6 +484,33 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_reindex_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let client_args = ClientArgs::parse(&mut matches)?;
+        let source_index_id = matches
+            .remove_one::<String>("source-index")
+            .expect("`source-index` should be a required arg.");
+        let target_index_id = matches
+            .remove_one::<String>("target-index")
+            .expect("`target-index` should be a required arg.");
+        let query = matches.remove_one::<String>("query");
+        let start_timestamp = matches
+            .remove_one::<String>("start-timestamp")
+            .map(|ts| ts.parse())
+            .transpose()?;
+        let end_timestamp = matches
+            .remove_one::<String>("end-timestamp")
+            .map(|ts| ts.parse())
+            .transpose()?;
+        Ok(Self::Reindex(ReindexArgs {
+            client_args,
+            source_index_id,
+            target_index_id,
+            query,
+            start_timestamp,
+            end_timestamp,
+        }))
+    }
+
     fn parse_delete_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
         let client_args = ClientArgs::parse(&mut matches)?;
         let index_id = matches
@@ -475,6 +534,7 @@ impl IndexCliCommand {
             Self::Describe(args) => describe_index_cli(args).await,
             Self::Ingest(args) => ingest_docs_cli(args).await,
             Self::List(args) => list_index_cli(args).await,
+            Self::Reindex(args) => reindex_cli(args).await,
             Self::Search(args) => search_index_cli(args).await,
             Self::Update(args) => update_index_cli(args).await,
         }
@@ -1101,6 +1161,28 @@ pub async fn search_index_cli(args: SearchIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn reindex_cli(args: ReindexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "reindex");
+    println!("❯ Starting reindex job...");
+    let reindex_request = ReindexRequest {
+        source_index_id: args.source_index_id,
+        target_index_id: args.target_index_id,
+        query: args.query,
+        start_timestamp: args.start_timestamp,
+        end_timestamp: args.end_timestamp,
+        transform: None,
+    };
+    let qw_client = args.client_args.client();
+    let reindex_status = qw_client.indexes().reindex(&reindex_request).await?;
+    println!(
+        "{} Reindex job `{}` successfully started on index `{}`.",
+        "✔".color(GREEN_COLOR),
+        reindex_status.source_id,
+        reindex_status.index_id
+    );
+    Ok(())
+}
+
 pub async fn delete_index_cli(args: DeleteIndexArgs) -> anyhow::Result<()> {
     debug!(args=?args, "delete-index");
     if !args.dry_run && !args.assume_yes {
@@ -136,7 +136,7 @@ mod tests {
     use quickwit_cli::cli::{build_cli, CliCommand};
     use quickwit_cli::index::{
         ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs, IndexCliCommand,
-        IngestDocsArgs, SearchIndexArgs,
+        IngestDocsArgs, ReindexArgs, SearchIndexArgs,
     };
     use quickwit_cli::split::{DescribeSplitArgs, SplitCliCommand};
     use quickwit_cli::tool::{
@@ -632,6 +632,39 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_reindex_args() {
+        let app = build_cli().no_binary_name(true);
+        let matches = app
+            .try_get_matches_from([
+                "index",
+                "reindex",
+                "--source-index",
+                "wikipedia",
+                "--target-index",
+                "wikipedia-v2",
+                "--query",
+                "title:apple",
+                "--start-timestamp",
+                "1700000000",
+            ])
+            .unwrap();
+        let command = CliCommand::parse_cli_args(matches).unwrap();
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Reindex(ReindexArgs {
+                source_index_id,
+                target_index_id,
+                query: Some(query),
+                start_timestamp: Some(1_700_000_000),
+                end_timestamp: None,
+                ..
+            })) if source_index_id == "wikipedia"
+                && target_index_id == "wikipedia-v2"
+                && query == "title:apple"
+        ));
+    }
+
     #[test]
     fn test_parse_describe_index_args() {
         let app = build_cli().no_binary_name(true);
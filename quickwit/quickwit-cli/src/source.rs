@@ -747,6 +747,7 @@ mod tests {
             source_params: SourceParams::file_from_str("path/to/file").unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         }];
         let expected_source = vec![SourceRow {
             source_id: "foo-source".to_string(),
@@ -808,6 +809,7 @@ mod tests {
                 source_params: SourceParams::stdin(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_queue_config: None,
             },
             SourceConfig {
                 source_id: "bar-source".to_string(),
@@ -816,6 +818,7 @@ mod tests {
                 source_params: SourceParams::stdin(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_queue_config: None,
             },
         ];
         let expected_sources = [
@@ -411,6 +411,12 @@ pub async fn local_ingest_docs_cli(args: LocalIngestDocsArgs) -> anyhow::Result<
     let source_params = if let Some(uri) = args.input_path_opt.as_ref() {
         SourceParams::file_from_uri(uri.clone())
     } else {
+        if matches!(
+            args.input_format,
+            SourceInputFormat::Csv | SourceInputFormat::Parquet
+        ) {
+            bail!("CSV and Parquet input formats cannot be ingested from stdin");
+        }
         SourceParams::stdin()
     };
     let transform_config = args
@@ -423,6 +429,7 @@ pub async fn local_ingest_docs_cli(args: LocalIngestDocsArgs) -> anyhow::Result<
         source_params,
         transform_config,
         input_format: args.input_format,
+        dead_letter_queue_config: None,
     };
     run_index_checklist(
         &mut metastore,
@@ -555,6 +562,8 @@ pub async fn local_search_cli(args: LocalSearchArgs) -> anyhow::Result<()> {
         sort_by,
         count_all: CountHits::CountAll,
         allow_failed_splits: false,
+        collapse_field: None,
+        runtime_mappings: None,
     };
     let search_request =
         search_request_from_api_request(vec![args.index_id], search_request_query_string)?;
@@ -613,6 +622,7 @@ pub async fn merge_cli(args: MergeArgs) -> anyhow::Result<()> {
                 source_params: SourceParams::Vec(VecSourceParams::default()),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_queue_config: None,
             },
             pipeline_uid: PipelineUid::random(),
         })
@@ -941,6 +951,8 @@ async fn create_empty_cluster(config: &NodeConfig) -> anyhow::Result<Cluster> {
         grpc_advertise_addr: config.grpc_advertise_addr,
         indexing_cpu_capacity: CpuCapacity::zero(),
         indexing_tasks: Vec::new(),
+        availability_zone: config.availability_zone.clone(),
+        rack: config.rack.clone(),
     };
     let cluster = Cluster::join(
         config.cluster_id.clone(),
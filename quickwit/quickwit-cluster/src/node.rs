@@ -51,6 +51,8 @@ impl ClusterNode {
             grpc_advertise_addr: member.grpc_advertise_addr,
             indexing_tasks: member.indexing_tasks,
             indexing_capacity: member.indexing_cpu_capacity,
+            availability_zone: member.availability_zone,
+            rack: member.rack,
             is_ready: member.is_ready,
             is_self_node,
         };
@@ -130,6 +132,14 @@ impl ClusterNode {
         self.inner.indexing_capacity
     }
 
+    pub fn availability_zone(&self) -> Option<&str> {
+        self.inner.availability_zone.as_deref()
+    }
+
+    pub fn rack(&self) -> Option<&str> {
+        self.inner.rack.as_deref()
+    }
+
     pub fn is_ready(&self) -> bool {
         self.inner.is_ready
     }
@@ -168,6 +178,8 @@ struct InnerNode {
     grpc_advertise_addr: SocketAddr,
     indexing_tasks: Vec<IndexingTask>,
     indexing_capacity: CpuCapacity,
+    availability_zone: Option<String>,
+    rack: Option<String>,
     is_ready: bool,
     is_self_node: bool,
 }
@@ -57,7 +57,10 @@ pub use rate_estimator::{RateEstimator, SmaRateEstimator};
 pub use rate_limit::{RateLimit, RateLimitLayer};
 pub use retry::{RetryLayer, RetryPolicy};
 pub use timeout::{Timeout, TimeoutExceeded, TimeoutLayer};
-pub use transport::{make_channel, warmup_channel, BalanceChannel};
+pub use transport::{
+    make_channel, set_client_tls_config_provider, warmup_channel, BalanceChannel,
+    ClientTlsConfigProvider,
+};
 
 pub type BoxError = Box<dyn error::Error + Send + Sync + 'static>;
 
@@ -29,14 +29,16 @@ use std::time::Duration;
 use futures::stream::once;
 use futures::{Stream, StreamExt};
 use http::Uri;
+use once_cell::sync::OnceCell;
 use tokio::sync::{mpsc, watch};
 use tokio_stream::wrappers::UnboundedReceiverStream;
-use tonic::transport::{Channel, Endpoint};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
 use tower::balance::p2c::Balance;
 use tower::buffer::Buffer;
 use tower::discover::Change as TowerChange;
 use tower::load::{CompleteOnResponse, PendingRequestsDiscover};
 use tower::{BoxError, Service, ServiceExt};
+use tracing::error;
 
 use super::{BoxFuture, Change};
 use crate::BoxStream;
@@ -197,19 +199,54 @@ where K: Hash + Eq + Clone + Send + Sync + 'static
     }
 }
 
+/// Builds the TLS configuration used to connect to the gRPC server listening on the given socket
+/// address.
+pub type ClientTlsConfigProvider =
+    Box<dyn Fn(SocketAddr) -> anyhow::Result<ClientTlsConfig> + Send + Sync + 'static>;
+
+static CLIENT_TLS_CONFIG_PROVIDER: OnceCell<ClientTlsConfigProvider> = OnceCell::new();
+
+/// Enables TLS for the channels created with [`make_channel`].
+///
+/// The provider is called every time a channel is created, so that rotated certificates are picked
+/// up by the new connections. The provider can only be set once per process: subsequent calls are
+/// ignored and return `false`.
+pub fn set_client_tls_config_provider(provider: ClientTlsConfigProvider) -> bool {
+    CLIENT_TLS_CONFIG_PROVIDER.set(provider).is_ok()
+}
+
 /// Creates a channel from a socket address.
 ///
 /// The function is marked as `async` because it requires an executor (`connect_lazy`).
 pub async fn make_channel(socket_addr: SocketAddr) -> Channel {
+    let scheme = if CLIENT_TLS_CONFIG_PROVIDER.get().is_some() {
+        "https"
+    } else {
+        "http"
+    };
     let uri = Uri::builder()
-        .scheme("http")
+        .scheme(scheme)
         .authority(socket_addr.to_string())
         .path_and_query("/")
         .build()
         .expect("provided arguments should be valid");
-    Endpoint::from(uri)
-        .connect_timeout(Duration::from_secs(5))
-        .connect_lazy()
+    let mut endpoint = Endpoint::from(uri).connect_timeout(Duration::from_secs(5));
+
+    if let Some(client_tls_config_provider) = CLIENT_TLS_CONFIG_PROVIDER.get() {
+        // On error, the endpoint is left without TLS configuration and connection attempts fail.
+        match client_tls_config_provider(socket_addr).and_then(|client_tls_config| {
+            endpoint
+                .clone()
+                .tls_config(client_tls_config)
+                .map_err(anyhow::Error::from)
+        }) {
+            Ok(tls_endpoint) => endpoint = tls_endpoint,
+            Err(error) => {
+                error!(%socket_addr, %error, "failed to configure TLS for gRPC channel");
+            }
+        }
+    }
+    endpoint.connect_lazy()
 }
 
 /// Forces a channel to initiate the underlying HTTP connection. Calling this function only makes
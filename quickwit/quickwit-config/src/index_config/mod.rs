@@ -26,11 +26,12 @@ use std::time::Duration;
 
 use anyhow::{ensure, Context};
 use bytesize::ByteSize;
-use chrono::Utc;
+use chrono::{NaiveTime, Timelike, Utc};
 use cron::Schedule;
 use humantime::parse_duration;
 use quickwit_common::uri::Uri;
 use quickwit_doc_mapper::{DocMapper, DocMapperBuilder, DocMapping};
+use quickwit_proto::ingest::IngestQuota;
 use quickwit_proto::types::IndexId;
 use serde::{Deserialize, Serialize};
 pub use serialize::{load_index_config_from_user_config, load_index_config_update};
@@ -173,6 +174,231 @@ pub struct SearchSettings {
     pub default_search_fields: Vec<String>,
 }
 
+/// Ingest settings of an index (ingest V2 only): the ingest quotas, enforced by the ingest
+/// routers, and the policy used by the control plane to scale the number of shards.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IngestSettings {
+    /// Maximum number of bytes per second ingested into the index by each router.
+    #[schema(value_type = Option<String>)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes_per_sec: Option<ByteSize>,
+    /// Maximum number of documents per second ingested into the index by each router.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_docs_per_sec: Option<u64>,
+    /// Maximum number of bytes the index can hold in the write-ahead logs of the ingesters,
+    /// i.e. the volume of documents persisted but not indexed yet.
+    #[schema(value_type = Option<String>)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_wal_bytes: Option<ByteSize>,
+    /// Policy driving the number of shards of the index's sources.
+    #[serde(default, skip_serializing_if = "ScalingPolicyConfig::is_default")]
+    pub scaling_policy: ScalingPolicyConfig,
+}
+
+impl IngestSettings {
+    /// Returns `true` if all the settings have their default value.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns `true` if no quota is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_bytes_per_sec.is_none()
+            && self.max_docs_per_sec.is_none()
+            && self.max_wal_bytes.is_none()
+    }
+
+    /// Returns the quotas in the format expected by the ingest routers, or `None` if no quota is
+    /// set.
+    pub fn ingest_quota(&self) -> Option<IngestQuota> {
+        if self.is_unlimited() {
+            return None;
+        }
+        let ingest_quota = IngestQuota {
+            max_bytes_per_sec: self.max_bytes_per_sec.map(|max_bytes| max_bytes.as_u64()),
+            max_docs_per_sec: self.max_docs_per_sec,
+            max_wal_bytes: self.max_wal_bytes.map(|max_bytes| max_bytes.as_u64()),
+        };
+        Some(ingest_quota)
+    }
+
+    pub(super) fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.max_bytes_per_sec != Some(ByteSize(0)),
+            "`ingest_settings.max_bytes_per_sec` must be strictly positive"
+        );
+        ensure!(
+            self.max_docs_per_sec != Some(0),
+            "`ingest_settings.max_docs_per_sec` must be strictly positive"
+        );
+        ensure!(
+            self.max_wal_bytes != Some(ByteSize(0)),
+            "`ingest_settings.max_wal_bytes` must be strictly positive"
+        );
+        self.scaling_policy.validate()?;
+        Ok(())
+    }
+}
+
+/// Algorithm used by the control plane to decide when to scale the number of shards up or down.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScalingPolicyMode {
+    /// Scales based on the current ingestion rate of the shards.
+    #[default]
+    Reactive,
+    /// Scales based on an exponentially weighted moving average of the ingestion rate and of its
+    /// trend, anticipating ramp-ups and holding shards until the rate durably decreases.
+    Predictive,
+}
+
+/// Time-of-day window during which the number of shards is kept within specific bounds.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ScalingSchedule {
+    /// Start of the window, inclusive, formatted as `HH:MM` (UTC).
+    pub start: String,
+    /// End of the window, exclusive, formatted as `HH:MM` (UTC). Windows ending before they start
+    /// span midnight.
+    pub end: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_shards: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_shards: Option<usize>,
+}
+
+impl ScalingSchedule {
+    /// Returns whether the window contains the given minute of the day (UTC).
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        let (Ok(start), Ok(end)) = (
+            parse_minute_of_day(&self.start),
+            parse_minute_of_day(&self.end),
+        ) else {
+            return false;
+        };
+        if start <= end {
+            start <= minute_of_day && minute_of_day < end
+        } else {
+            start <= minute_of_day || minute_of_day < end
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let start = parse_minute_of_day(&self.start)?;
+        let end = parse_minute_of_day(&self.end)?;
+        ensure!(
+            start != end,
+            "scaling schedule `{}-{}` is empty",
+            self.start,
+            self.end
+        );
+        ensure!(
+            self.min_shards.is_some() || self.max_shards.is_some(),
+            "scaling schedule `{}-{}` must set `min_shards` or `max_shards`",
+            self.start,
+            self.end
+        );
+        validate_shard_bounds(self.min_shards.unwrap_or(1), self.max_shards)
+    }
+}
+
+fn parse_minute_of_day(time_of_day: &str) -> anyhow::Result<u32> {
+    let time = NaiveTime::parse_from_str(time_of_day, "%H:%M").with_context(|| {
+        format!("failed to parse time of day `{time_of_day}`: expected format is `HH:MM`")
+    })?;
+    Ok(time.hour() * 60 + time.minute())
+}
+
+fn validate_shard_bounds(min_shards: usize, max_shards_opt: Option<usize>) -> anyhow::Result<()> {
+    ensure!(min_shards > 0, "`min_shards` must be strictly positive");
+
+    if let Some(max_shards) = max_shards_opt {
+        ensure!(
+            max_shards >= min_shards,
+            "`max_shards` ({max_shards}) must be greater than or equal to `min_shards` \
+             ({min_shards})"
+        );
+    }
+    Ok(())
+}
+
+/// Policy used by the control plane to scale the number of shards of the index's sources.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ScalingPolicyConfig {
+    #[serde(default)]
+    pub mode: ScalingPolicyMode,
+    /// Minimum number of open shards per source.
+    #[serde(default = "ScalingPolicyConfig::default_min_shards")]
+    pub min_shards: usize,
+    /// Maximum number of open shards per source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_shards: Option<usize>,
+    /// Time-of-day windows overriding the shard bounds above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<ScalingSchedule>,
+}
+
+impl Default for ScalingPolicyConfig {
+    fn default() -> Self {
+        Self {
+            mode: ScalingPolicyMode::default(),
+            min_shards: Self::default_min_shards(),
+            max_shards: None,
+            schedules: Vec::new(),
+        }
+    }
+}
+
+impl ScalingPolicyConfig {
+    fn default_min_shards() -> usize {
+        1
+    }
+
+    /// Returns `true` if the policy is the default one.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        validate_shard_bounds(self.min_shards, self.max_shards)
+            .context("invalid `ingest_settings.scaling_policy`")?;
+
+        for schedule in &self.schedules {
+            schedule
+                .validate()
+                .context("invalid `ingest_settings.scaling_policy.schedules`")?;
+
+            // The global bounds prevail over the schedules, so a schedule must fit within them.
+            if let (Some(schedule_min_shards), Some(max_shards)) =
+                (schedule.min_shards, self.max_shards)
+            {
+                ensure!(
+                    schedule_min_shards <= max_shards,
+                    "invalid `ingest_settings.scaling_policy.schedules`: `min_shards` \
+                     ({schedule_min_shards}) of schedule `{}-{}` must be less than or equal to \
+                     `max_shards` ({max_shards})",
+                    schedule.start,
+                    schedule.end
+                );
+            }
+            if let Some(schedule_max_shards) = schedule.max_shards {
+                ensure!(
+                    schedule_max_shards >= self.min_shards,
+                    "invalid `ingest_settings.scaling_policy.schedules`: `max_shards` \
+                     ({schedule_max_shards}) of schedule `{}-{}` must be greater than or equal to \
+                     `min_shards` ({})",
+                    schedule.start,
+                    schedule.end,
+                    self.min_shards
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct RetentionPolicy {
@@ -257,6 +483,7 @@ pub struct IndexConfig {
     pub doc_mapping: DocMapping,
     pub indexing_settings: IndexingSettings,
     pub search_settings: SearchSettings,
+    pub ingest_settings: IngestSettings,
     pub retention_policy_opt: Option<RetentionPolicy>,
 }
 
@@ -351,6 +578,7 @@ impl IndexConfig {
             doc_mapping,
             indexing_settings,
             search_settings,
+            ingest_settings: IngestSettings::default(),
             retention_policy_opt: Default::default(),
         }
     }
@@ -457,6 +685,7 @@ impl crate::TestableForRegression for IndexConfig {
             indexing_settings,
             retention_policy_opt: retention_policy,
             search_settings,
+            ingest_settings: IngestSettings::default(),
         }
     }
 
@@ -466,6 +695,7 @@ impl crate::TestableForRegression for IndexConfig {
         assert_eq!(self.doc_mapping, other.doc_mapping);
         assert_eq!(self.indexing_settings, other.indexing_settings);
         assert_eq!(self.search_settings, other.search_settings);
+        assert_eq!(self.ingest_settings, other.ingest_settings);
     }
 }
 
@@ -488,6 +718,7 @@ pub(super) fn validate_index_config(
     doc_mapping: &DocMapping,
     indexing_settings: &IndexingSettings,
     search_settings: &SearchSettings,
+    ingest_settings: &IngestSettings,
     retention_policy_opt: &Option<RetentionPolicy>,
 ) -> anyhow::Result<()> {
     // Note: this needs a deep refactoring to separate the doc mapping configuration,
@@ -497,6 +728,7 @@ pub(super) fn validate_index_config(
 
     indexing_settings.merge_policy.validate()?;
     indexing_settings.resources.validate()?;
+    ingest_settings.validate()?;
 
     if let Some(retention_policy) = retention_policy_opt {
         retention_policy.validate()?;
@@ -892,4 +1124,124 @@ mod tests {
         schedule_test_helper_fn("monthly");
         schedule_test_helper_fn("* * * ? * ?");
     }
+
+    #[test]
+    fn test_scaling_policy_config_deserialization() {
+        let scaling_policy_yaml = r#"
+            mode: predictive
+            min_shards: 2
+            max_shards: 16
+            schedules:
+              - start: "07:30"
+                end: "11:00"
+                min_shards: 8
+        "#;
+        let scaling_policy: ScalingPolicyConfig =
+            serde_yaml::from_str(scaling_policy_yaml).unwrap();
+        scaling_policy.validate().unwrap();
+
+        assert_eq!(scaling_policy.mode, ScalingPolicyMode::Predictive);
+        assert_eq!(scaling_policy.min_shards, 2);
+        assert_eq!(scaling_policy.max_shards, Some(16));
+        assert_eq!(scaling_policy.schedules.len(), 1);
+        assert_eq!(scaling_policy.schedules[0].min_shards, Some(8));
+        assert_eq!(scaling_policy.schedules[0].max_shards, None);
+
+        let scaling_policy: ScalingPolicyConfig = serde_yaml::from_str("{}").unwrap();
+        assert!(scaling_policy.is_default());
+    }
+
+    #[test]
+    fn test_scaling_policy_config_validate() {
+        let scaling_policy = ScalingPolicyConfig {
+            min_shards: 0,
+            ..Default::default()
+        };
+        scaling_policy.validate().unwrap_err();
+
+        let scaling_policy = ScalingPolicyConfig {
+            min_shards: 4,
+            max_shards: Some(2),
+            ..Default::default()
+        };
+        scaling_policy.validate().unwrap_err();
+
+        let schedule = ScalingSchedule {
+            start: "8:00".to_string(),
+            end: "25:00".to_string(),
+            min_shards: Some(4),
+            max_shards: None,
+        };
+        let scaling_policy = ScalingPolicyConfig {
+            schedules: vec![schedule],
+            ..Default::default()
+        };
+        scaling_policy.validate().unwrap_err();
+
+        let schedule = ScalingSchedule {
+            start: "08:00".to_string(),
+            end: "10:00".to_string(),
+            min_shards: None,
+            max_shards: None,
+        };
+        let scaling_policy = ScalingPolicyConfig {
+            schedules: vec![schedule],
+            ..Default::default()
+        };
+        scaling_policy.validate().unwrap_err();
+
+        let schedule = ScalingSchedule {
+            start: "08:00".to_string(),
+            end: "10:00".to_string(),
+            min_shards: Some(8),
+            max_shards: None,
+        };
+        let scaling_policy = ScalingPolicyConfig {
+            max_shards: Some(4),
+            schedules: vec![schedule],
+            ..Default::default()
+        };
+        let error = scaling_policy.validate().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("`min_shards` (8) of schedule `08:00-10:00`"));
+
+        let schedule = ScalingSchedule {
+            start: "08:00".to_string(),
+            end: "10:00".to_string(),
+            min_shards: None,
+            max_shards: Some(2),
+        };
+        let scaling_policy = ScalingPolicyConfig {
+            min_shards: 4,
+            schedules: vec![schedule],
+            ..Default::default()
+        };
+        scaling_policy.validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_scaling_schedule_contains() {
+        let schedule = ScalingSchedule {
+            start: "08:00".to_string(),
+            end: "10:30".to_string(),
+            min_shards: Some(4),
+            max_shards: None,
+        };
+        assert!(!schedule.contains(7 * 60 + 59));
+        assert!(schedule.contains(8 * 60));
+        assert!(schedule.contains(10 * 60 + 29));
+        assert!(!schedule.contains(10 * 60 + 30));
+
+        let schedule = ScalingSchedule {
+            start: "22:00".to_string(),
+            end: "02:00".to_string(),
+            min_shards: None,
+            max_shards: Some(2),
+        };
+        assert!(schedule.contains(23 * 60));
+        assert!(schedule.contains(60));
+        assert!(!schedule.contains(2 * 60));
+        assert!(!schedule.contains(12 * 60));
+    }
 }
@@ -28,8 +28,8 @@ use tracing::info;
 
 use super::validate_index_config;
 use crate::{
-    validate_identifier, ConfigFormat, DocMapping, IndexConfig, IndexingSettings, RetentionPolicy,
-    SearchSettings,
+    validate_identifier, ConfigFormat, DocMapping, IndexConfig, IndexingSettings, IngestSettings,
+    RetentionPolicy, SearchSettings,
 };
 
 /// Alias for the latest serialization format.
@@ -195,12 +195,14 @@ impl IndexConfigForSerialization {
             doc_mapping: self.doc_mapping,
             indexing_settings: self.indexing_settings,
             search_settings: self.search_settings,
+            ingest_settings: self.ingest_settings,
             retention_policy_opt: self.retention_policy_opt,
         };
         validate_index_config(
             &index_config.doc_mapping,
             &index_config.indexing_settings,
             &index_config.search_settings,
+            &index_config.ingest_settings,
             &index_config.retention_policy_opt,
         )?;
         Ok(index_config)
@@ -241,6 +243,8 @@ pub struct IndexConfigV0_8 {
     pub indexing_settings: IndexingSettings,
     #[serde(default)]
     pub search_settings: SearchSettings,
+    #[serde(default, skip_serializing_if = "IngestSettings::is_default")]
+    pub ingest_settings: IngestSettings,
     #[serde(rename = "retention")]
     #[serde(default)]
     pub retention_policy_opt: Option<RetentionPolicy>,
@@ -254,6 +258,7 @@ impl From<IndexConfig> for IndexConfigV0_8 {
             doc_mapping: index_config.doc_mapping,
             indexing_settings: index_config.indexing_settings,
             search_settings: index_config.search_settings,
+            ingest_settings: index_config.ingest_settings,
             retention_policy_opt: index_config.retention_policy_opt,
         }
     }
@@ -28,7 +28,7 @@ pub use serialize::{IndexTemplateV0_8, VersionedIndexTemplate};
 use crate::index_config::validate_index_config;
 use crate::{
     validate_identifier, validate_index_id_pattern, DocMapping, IndexConfig, IndexingSettings,
-    RetentionPolicy, SearchSettings,
+    IngestSettings, RetentionPolicy, SearchSettings,
 };
 
 pub type IndexTemplateId = String;
@@ -51,6 +51,8 @@ pub struct IndexTemplate {
     pub indexing_settings: IndexingSettings,
     #[serde(default)]
     pub search_settings: SearchSettings,
+    #[serde(default)]
+    pub ingest_settings: IngestSettings,
     #[serde(rename = "retention")]
     #[serde(default)]
     pub retention_policy_opt: Option<RetentionPolicy>,
@@ -78,6 +80,7 @@ impl IndexTemplate {
             doc_mapping,
             indexing_settings: self.indexing_settings.clone(),
             search_settings: self.search_settings.clone(),
+            ingest_settings: self.ingest_settings.clone(),
             retention_policy_opt: self.retention_policy_opt.clone(),
         };
         Ok(index_config)
@@ -97,6 +100,7 @@ impl IndexTemplate {
             &self.doc_mapping,
             &self.indexing_settings,
             &self.search_settings,
+            &self.ingest_settings,
             &self.retention_policy_opt,
         )?;
         Ok(())
@@ -134,6 +138,7 @@ impl IndexTemplate {
             doc_mapping,
             indexing_settings: IndexingSettings::default(),
             search_settings: SearchSettings::default(),
+            ingest_settings: IngestSettings::default(),
             retention_policy_opt: None,
         }
     }
@@ -174,6 +179,7 @@ impl crate::TestableForRegression for IndexTemplate {
             doc_mapping,
             indexing_settings: IndexingSettings::default(),
             search_settings: SearchSettings::default(),
+            ingest_settings: IngestSettings::default(),
             retention_policy_opt: Some(RetentionPolicy {
                 retention_period: "42 days".to_string(),
                 evaluation_schedule: "daily".to_string(),
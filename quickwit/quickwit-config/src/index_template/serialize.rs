@@ -21,7 +21,7 @@ use quickwit_common::uri::Uri;
 use serde::{Deserialize, Serialize};
 
 use super::{IndexIdPattern, IndexTemplate, IndexTemplateId};
-use crate::{DocMapping, IndexingSettings, RetentionPolicy, SearchSettings};
+use crate::{DocMapping, IndexingSettings, IngestSettings, RetentionPolicy, SearchSettings};
 
 #[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(tag = "version")]
@@ -51,6 +51,8 @@ pub struct IndexTemplateV0_8 {
     pub indexing_settings: IndexingSettings,
     #[serde(default)]
     pub search_settings: SearchSettings,
+    #[serde(default, skip_serializing_if = "IngestSettings::is_default")]
+    pub ingest_settings: IngestSettings,
     #[serde(default)]
     pub retention: Option<RetentionPolicy>,
 }
@@ -80,6 +82,7 @@ impl From<IndexTemplateV0_8> for IndexTemplate {
             doc_mapping: index_template_v0_8.doc_mapping,
             indexing_settings: index_template_v0_8.indexing_settings,
             search_settings: index_template_v0_8.search_settings,
+            ingest_settings: index_template_v0_8.ingest_settings,
             retention_policy_opt: index_template_v0_8.retention,
         }
     }
@@ -96,6 +99,7 @@ impl From<IndexTemplate> for IndexTemplateV0_8 {
             doc_mapping: index_template.doc_mapping,
             indexing_settings: index_template.indexing_settings,
             search_settings: index_template.search_settings,
+            ingest_settings: index_template.ingest_settings,
             retention: index_template.retention_policy_opt,
         }
     }
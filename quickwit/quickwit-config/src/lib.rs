@@ -49,7 +49,8 @@ pub use cluster_config::ClusterConfig;
 use index_config::serialize::{IndexConfigV0_8, VersionedIndexConfig};
 pub use index_config::{
     build_doc_mapper, load_index_config_from_user_config, load_index_config_update, IndexConfig,
-    IndexingResources, IndexingSettings, RetentionPolicy, SearchSettings,
+    IndexingResources, IndexingSettings, IngestSettings, RetentionPolicy, ScalingPolicyConfig,
+    ScalingPolicyMode, ScalingSchedule, SearchSettings,
 };
 pub use quickwit_doc_mapper::DocMapping;
 use serde::de::DeserializeOwned;
@@ -57,11 +58,12 @@ use serde::Serialize;
 use serde_json::Value as JsonValue;
 use source_config::FileSourceParamsForSerde;
 pub use source_config::{
-    load_source_config_from_user_config, FileSourceMessageType, FileSourceNotification,
-    FileSourceParams, FileSourceSqs, KafkaSourceParams, KinesisSourceParams, PubSubSourceParams,
-    PulsarSourceAuth, PulsarSourceParams, RegionOrEndpoint, SourceConfig, SourceInputFormat,
-    SourceParams, TransformConfig, VecSourceParams, VoidSourceParams, CLI_SOURCE_ID,
-    INGEST_API_SOURCE_ID, INGEST_V2_SOURCE_ID,
+    load_source_config_from_user_config, DeadLetterQueueConfig, FileSourceMessageType,
+    FileSourceNotification, FileSourceParams, FileSourceSqs, HttpSourceParams, KafkaSourceParams,
+    KinesisSourceParams, PubSubSourceParams, PulsarSourceAuth, PulsarSourceParams,
+    RegionOrEndpoint, ReindexSourceParams, SourceConfig, SourceInputFormat, SourceParams,
+    TransformConfig, VecSourceParams, VoidSourceParams, CLI_SOURCE_ID, INGEST_API_SOURCE_ID,
+    INGEST_V2_SOURCE_ID,
 };
 use tracing::warn;
 
@@ -74,8 +76,9 @@ pub use crate::metastore_config::{
     MetastoreBackend, MetastoreConfig, MetastoreConfigs, PostgresMetastoreConfig,
 };
 pub use crate::node_config::{
-    IndexerConfig, IngestApiConfig, JaegerConfig, NodeConfig, SearcherConfig, SplitCacheLimits,
-    StorageTimeoutPolicy, DEFAULT_QW_CONFIG_PATH,
+    ApiKeyConfig, AuthConfig, AuthPrivilege, BasicAuthConfig, IndexerConfig, IngestApiConfig,
+    JaegerConfig, JwtAuthConfig, NodeConfig, RoleConfig, SearcherConfig, SplitCacheLimits,
+    StorageTimeoutPolicy, TlsConfig, DEFAULT_QW_CONFIG_PATH,
 };
 use crate::source_config::serialize::{SourceConfigV0_7, SourceConfigV0_8, VersionedSourceConfig};
 pub use crate::storage_config::{
@@ -101,6 +104,10 @@ pub fn disable_ingest_v1() -> bool {
 #[openapi(components(schemas(
     IndexingResources,
     IndexingSettings,
+    IngestSettings,
+    ScalingPolicyConfig,
+    ScalingPolicyMode,
+    ScalingSchedule,
     SearchSettings,
     RetentionPolicy,
     MergePolicyConfig,
@@ -118,7 +125,9 @@ pub fn disable_ingest_v1() -> bool {
     FileSourceNotification,
     FileSourceParamsForSerde,
     FileSourceSqs,
+    HttpSourceParams,
     PubSubSourceParams,
+    ReindexSourceParams,
     KafkaSourceParams,
     KinesisSourceParams,
     PulsarSourceParams,
@@ -127,6 +136,7 @@ pub fn disable_ingest_v1() -> bool {
     ConstWriteAmplificationMergePolicyConfig,
     StableLogMergePolicyConfig,
     TransformConfig,
+    DeadLetterQueueConfig,
     VecSourceParams,
     VoidSourceParams,
 )))]
@@ -20,13 +20,13 @@
 mod serialize;
 
 use std::collections::{HashMap, HashSet};
-use std::env;
 use std::net::SocketAddr;
 use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
 use std::path::PathBuf;
 use std::time::Duration;
+use std::{env, fmt};
 
-use anyhow::{bail, ensure};
+use anyhow::{bail, ensure, Context};
 use bytesize::ByteSize;
 use http::HeaderMap;
 use quickwit_common::net::HostAddr;
@@ -51,6 +51,22 @@ pub struct RestConfig {
     pub cors_allow_origins: Vec<String>,
     #[serde(with = "http_serde::header_map")]
     pub extra_headers: HeaderMap,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+}
+
+impl RestConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(tls_config) = &self.tls {
+            tls_config.validate("rest.tls")?;
+            ensure!(
+                tls_config.expected_name.is_none(),
+                "`rest.tls.expected_name` is only supported for gRPC"
+            );
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -58,6 +74,9 @@ pub struct RestConfig {
 pub struct GrpcConfig {
     #[serde(default = "GrpcConfig::default_max_message_size")]
     pub max_message_size: ByteSize,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
 }
 
 impl GrpcConfig {
@@ -71,6 +90,15 @@ impl GrpcConfig {
             "max gRPC message size (`grpc.max_message_size`) must be at least 1MB, got `{}`",
             self.max_message_size
         );
+        if let Some(tls_config) = &self.tls {
+            tls_config.validate("grpc.tls")?;
+            // Nodes act as gRPC clients of each other, so they must be able to verify the
+            // certificates of their peers.
+            ensure!(
+                tls_config.ca_path.is_some(),
+                "`grpc.tls.ca_path` must be set when TLS is enabled for gRPC"
+            );
+        }
         Ok(())
     }
 }
@@ -79,10 +107,47 @@ impl Default for GrpcConfig {
     fn default() -> Self {
         Self {
             max_message_size: Self::default_max_message_size(),
+            tls: None,
         }
     }
 }
 
+/// TLS configuration of a REST or gRPC server.
+///
+/// The certificate, key, and CA files are watched and reloaded when they change, so that
+/// certificates can be rotated without restarting the node.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Path to the PEM-encoded certificate chain of the server.
+    pub cert_path: PathBuf,
+    /// Path to the PEM-encoded private key of the server.
+    pub key_path: PathBuf,
+    /// Path to the PEM-encoded CA certificates used to verify the certificates of the clients
+    /// and, for gRPC, the certificates of the other nodes of the cluster.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_path: Option<PathBuf>,
+    /// Requires clients to present a certificate signed by one of the CAs of `ca_path` (mTLS).
+    #[serde(default)]
+    pub validate_client: bool,
+    /// Name used by the gRPC clients to verify the certificates of the other nodes. Defaults to
+    /// the IP address of the node the client connects to.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_name: Option<String>,
+}
+
+impl TlsConfig {
+    fn validate(&self, config_key: &str) -> anyhow::Result<()> {
+        ensure!(
+            !self.validate_client || self.ca_path.is_some(),
+            "`{config_key}.ca_path` must be set when `{config_key}.validate_client` is enabled"
+        );
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct IndexerConfig {
@@ -352,23 +417,21 @@ impl IngestApiConfig {
     /// in that order (the environment variable can overrides the configuration).
     pub fn replication_factor(&self) -> anyhow::Result<NonZeroUsize> {
         if let Ok(replication_factor_str) = env::var("QW_INGEST_REPLICATION_FACTOR") {
-            let replication_factor = match replication_factor_str.trim() {
-                "1" => 1,
-                "2" => 2,
-                _ => bail!(
-                    "replication factor must be either 1 or 2, got `{replication_factor_str}`"
-                ),
-            };
-            return Ok(NonZeroUsize::new(replication_factor)
-                .expect("replication factor should be either 1 or 2"));
+            return replication_factor_str
+                .trim()
+                .parse::<NonZeroUsize>()
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "replication factor must be at least 1, got `{replication_factor_str}`"
+                    )
+                });
         }
-        ensure!(
-            self.replication_factor >= 1 && self.replication_factor <= 2,
-            "replication factor must be either 1 or 2, got `{}`",
-            self.replication_factor
-        );
-        Ok(NonZeroUsize::new(self.replication_factor)
-            .expect("replication factor should be either 1 or 2"))
+        NonZeroUsize::new(self.replication_factor).with_context(|| {
+            format!(
+                "replication factor must be at least 1, got `{}`",
+                self.replication_factor
+            )
+        })
     }
 
     fn validate(&self) -> anyhow::Result<()> {
@@ -467,6 +530,185 @@ impl Default for JaegerConfig {
     }
 }
 
+/// Privileges that can be granted on indexes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthPrivilege {
+    /// Search the documents of the indexes and read their metadata.
+    Read,
+    /// Ingest documents into the indexes.
+    Ingest,
+    /// Create, update, and delete the indexes, their sources, and their splits. Implies the
+    /// `read` and `ingest` privileges.
+    Admin,
+}
+
+impl AuthPrivilege {
+    /// Returns whether granting `self` also grants `privilege`.
+    pub fn implies(&self, privilege: AuthPrivilege) -> bool {
+        *self == AuthPrivilege::Admin || *self == privilege
+    }
+}
+
+/// A role grants a set of privileges on the indexes matching a set of index ID patterns.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleConfig {
+    pub name: String,
+    /// Index ID patterns the privileges apply to. Patterns may contain the `*` wildcard.
+    pub index_patterns: Vec<String>,
+    pub privileges: Vec<AuthPrivilege>,
+}
+
+/// A static API key, passed by clients with the `Authorization: ApiKey <key>` or `x-api-key`
+/// headers.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// Name of the principal authenticated by the key.
+    pub name: String,
+    pub key: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl fmt::Debug for ApiKeyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeyConfig")
+            .field("name", &self.name)
+            .field("key", &"***redacted***")
+            .field("roles", &self.roles)
+            .finish()
+    }
+}
+
+/// HTTP basic authentication.
+///
+/// Each line of the credentials file has the form `<username>:<bcrypt hash>[:<role>,<role>...]`,
+/// which makes files generated with `htpasswd -B` valid credentials files.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BasicAuthConfig {
+    pub credentials_path: PathBuf,
+}
+
+/// Authentication with JWT bearer tokens signed by one of the keys of a local JWKS file.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JwtAuthConfig {
+    pub jwks_path: PathBuf,
+    /// Expected value of the `iss` claim.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// Expected value of the `aud` claim.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+    /// Claim holding the roles of the principal, as an array of strings.
+    #[serde(default = "JwtAuthConfig::default_roles_claim")]
+    pub roles_claim: String,
+}
+
+impl JwtAuthConfig {
+    fn default_roles_claim() -> String {
+        "roles".to_string()
+    }
+}
+
+/// Authentication and authorization of the REST API.
+///
+/// Authentication is enabled as soon as one authentication method is configured. Principals are
+/// then only allowed to access the indexes their roles grant them privileges on.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub api_keys: Vec<ApiKeyConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub basic: Option<BasicAuthConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwt: Option<JwtAuthConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<RoleConfig>,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.basic.is_some() || self.jwt.is_some()
+    }
+
+    /// Redacts the API keys.
+    pub fn redact(&mut self) {
+        for api_key in &mut self.api_keys {
+            api_key.key = "***redacted***".to_string();
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let mut role_names = HashSet::with_capacity(self.roles.len());
+
+        for role in &self.roles {
+            ensure!(!role.name.is_empty(), "role name must not be empty");
+            ensure!(
+                role_names.insert(role.name.as_str()),
+                "role `{}` is defined more than once",
+                role.name
+            );
+            ensure!(
+                !role.index_patterns.is_empty(),
+                "role `{}` must define at least one index pattern",
+                role.name
+            );
+            ensure!(
+                !role.privileges.is_empty(),
+                "role `{}` must grant at least one privilege",
+                role.name
+            );
+            for index_pattern in &role.index_patterns {
+                ensure!(
+                    !index_pattern.is_empty(),
+                    "index patterns of role `{}` must not be empty",
+                    role.name
+                );
+            }
+        }
+        let mut api_key_names = HashSet::with_capacity(self.api_keys.len());
+        let mut api_keys = HashSet::with_capacity(self.api_keys.len());
+
+        for api_key in &self.api_keys {
+            ensure!(!api_key.name.is_empty(), "API key name must not be empty");
+            ensure!(
+                api_key_names.insert(api_key.name.as_str()),
+                "API key `{}` is defined more than once",
+                api_key.name
+            );
+            ensure!(
+                !api_key.key.is_empty(),
+                "key of API key `{}` must not be empty",
+                api_key.name
+            );
+            ensure!(
+                api_keys.insert(api_key.key.as_str()),
+                "key of API key `{}` is not unique",
+                api_key.name
+            );
+            for role_name in &api_key.roles {
+                ensure!(
+                    role_names.contains(role_name.as_str()),
+                    "API key `{}` references unknown role `{role_name}`",
+                    api_key.name
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct NodeConfig {
     pub cluster_id: String,
@@ -481,6 +723,10 @@ pub struct NodeConfig {
     pub data_dir_path: PathBuf,
     pub metastore_uri: Uri,
     pub default_index_root_uri: Uri,
+    /// Availability zone of the node, used to spread ingest replicas across failure domains.
+    pub availability_zone: Option<String>,
+    /// Rack of the node, used to spread ingest replicas across failure domains within a zone.
+    pub rack: Option<String>,
     pub rest_config: RestConfig,
     pub grpc_config: GrpcConfig,
     pub storage_configs: StorageConfigs,
@@ -489,6 +735,7 @@ pub struct NodeConfig {
     pub searcher_config: SearcherConfig,
     pub ingest_api_config: IngestApiConfig,
     pub jaeger_config: JaegerConfig,
+    pub auth_config: AuthConfig,
 }
 
 impl NodeConfig {
@@ -537,6 +784,7 @@ impl NodeConfig {
         self.metastore_configs.redact();
         self.metastore_uri.redact();
         self.storage_configs.redact();
+        self.auth_config.redact();
     }
 
     /// Creates a config with defaults suitable for testing.
@@ -555,6 +803,8 @@ impl NodeConfig {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use quickwit_proto::indexing::CpuCapacity;
 
     use super::*;
@@ -691,12 +941,146 @@ mod tests {
     fn test_grpc_config_validate() {
         let grpc_config = GrpcConfig {
             max_message_size: ByteSize::mb(1),
+            tls: None,
         };
         assert!(grpc_config.validate().is_ok());
 
         let grpc_config = GrpcConfig {
             max_message_size: ByteSize::kb(1),
+            tls: None,
         };
         assert!(grpc_config.validate().is_err());
     }
+
+    #[test]
+    fn test_grpc_config_tls() {
+        let grpc_config: GrpcConfig = serde_yaml::from_str(
+            r#"
+                tls:
+                    cert_path: /etc/quickwit/tls/node.crt
+                    key_path: /etc/quickwit/tls/node.key
+                    ca_path: /etc/quickwit/tls/ca.crt
+                    validate_client: true
+                    expected_name: quickwit.cluster.local
+            "#,
+        )
+        .unwrap();
+        grpc_config.validate().unwrap();
+
+        let tls_config = grpc_config.tls.unwrap();
+        assert_eq!(
+            tls_config.cert_path,
+            Path::new("/etc/quickwit/tls/node.crt")
+        );
+        assert_eq!(tls_config.key_path, Path::new("/etc/quickwit/tls/node.key"));
+        assert_eq!(
+            tls_config.ca_path.unwrap(),
+            Path::new("/etc/quickwit/tls/ca.crt")
+        );
+        assert!(tls_config.validate_client);
+        assert_eq!(tls_config.expected_name.unwrap(), "quickwit.cluster.local");
+    }
+
+    #[test]
+    fn test_grpc_config_tls_validate() {
+        let tls_config = TlsConfig {
+            cert_path: PathBuf::from("node.crt"),
+            key_path: PathBuf::from("node.key"),
+            ca_path: None,
+            validate_client: false,
+            expected_name: None,
+        };
+        let grpc_config = GrpcConfig {
+            max_message_size: ByteSize::mib(20),
+            tls: Some(tls_config.clone()),
+        };
+        let error = grpc_config.validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`grpc.tls.ca_path` must be set when TLS is enabled for gRPC"
+        );
+
+        let rest_config = RestConfig {
+            listen_addr: ([127, 0, 0, 1], 7280).into(),
+            cors_allow_origins: Vec::new(),
+            extra_headers: HeaderMap::new(),
+            tls: Some(TlsConfig {
+                validate_client: true,
+                ..tls_config
+            }),
+        };
+        let error = rest_config.validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`rest.tls.ca_path` must be set when `rest.tls.validate_client` is enabled"
+        );
+    }
+
+    #[test]
+    fn test_auth_config_validate() {
+        let role = RoleConfig {
+            name: "reader".to_string(),
+            index_patterns: vec!["logs-*".to_string()],
+            privileges: vec![AuthPrivilege::Read],
+        };
+        let api_key = ApiKeyConfig {
+            name: "dashboard".to_string(),
+            key: "my-secret-key".to_string(),
+            roles: vec!["reader".to_string()],
+        };
+        let mut auth_config = AuthConfig {
+            api_keys: vec![api_key.clone()],
+            roles: vec![role.clone()],
+            ..Default::default()
+        };
+        auth_config.validate().unwrap();
+
+        auth_config.roles.push(role.clone());
+        let error = auth_config.validate().unwrap_err();
+        assert_eq!(error.to_string(), "role `reader` is defined more than once");
+        auth_config.roles.pop();
+
+        auth_config.api_keys.push(ApiKeyConfig {
+            name: "dashboard-2".to_string(),
+            ..api_key
+        });
+        let error = auth_config.validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "key of API key `dashboard-2` is not unique"
+        );
+        auth_config.api_keys.pop();
+
+        auth_config.roles[0].privileges.clear();
+        let error = auth_config.validate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "role `reader` must grant at least one privilege"
+        );
+    }
+
+    #[test]
+    fn test_auth_config_redact() {
+        let mut auth_config = AuthConfig {
+            api_keys: vec![ApiKeyConfig {
+                name: "dashboard".to_string(),
+                key: "my-secret-key".to_string(),
+                roles: Vec::new(),
+            }],
+            ..Default::default()
+        };
+        assert!(!format!("{auth_config:?}").contains("my-secret-key"));
+
+        auth_config.redact();
+        assert_eq!(auth_config.api_keys[0].key, "***redacted***");
+    }
+
+    #[test]
+    fn test_auth_privilege_implies() {
+        assert!(AuthPrivilege::Admin.implies(AuthPrivilege::Read));
+        assert!(AuthPrivilege::Admin.implies(AuthPrivilege::Ingest));
+        assert!(AuthPrivilege::Read.implies(AuthPrivilege::Read));
+        assert!(!AuthPrivilege::Read.implies(AuthPrivilege::Ingest));
+        assert!(!AuthPrivilege::Ingest.implies(AuthPrivilege::Admin));
+    }
 }
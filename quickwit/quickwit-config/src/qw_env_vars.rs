@@ -60,7 +60,9 @@ qw_env_vars!(
     QW_PEER_SEEDS,
     QW_DATA_DIR,
     QW_METASTORE_URI,
-    QW_DEFAULT_INDEX_ROOT_URI
+    QW_DEFAULT_INDEX_ROOT_URI,
+    QW_AVAILABILITY_ZONE,
+    QW_RACK
 );
 
 #[cfg(test)]
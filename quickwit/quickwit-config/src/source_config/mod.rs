@@ -24,10 +24,11 @@ use std::num::NonZeroUsize;
 use std::str::FromStr;
 
 use bytes::Bytes;
+use bytesize::ByteSize;
 use quickwit_common::is_false;
 use quickwit_common::uri::Uri;
 use quickwit_proto::metastore::SourceType;
-use quickwit_proto::types::SourceId;
+use quickwit_proto::types::{IndexId, SourceId};
 use regex::Regex;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize};
@@ -70,12 +71,16 @@ pub struct SourceConfig {
     // Denotes the input data format.
     #[serde(default)]
     pub input_format: SourceInputFormat,
+
+    /// Destination of the documents rejected by the doc processor, if any.
+    pub dead_letter_queue_config: Option<DeadLetterQueueConfig>,
 }
 
 impl SourceConfig {
     pub fn source_type(&self) -> SourceType {
         match self.source_params {
             SourceParams::File(_) => SourceType::File,
+            SourceParams::Http(_) => SourceType::Http,
             SourceParams::Ingest => SourceType::IngestV2,
             SourceParams::IngestApi => SourceType::IngestV1,
             SourceParams::IngestCli => SourceType::Cli,
@@ -83,6 +88,7 @@ impl SourceConfig {
             SourceParams::Kinesis(_) => SourceType::Kinesis,
             SourceParams::PubSub(_) => SourceType::PubSub,
             SourceParams::Pulsar(_) => SourceType::Pulsar,
+            SourceParams::Reindex(_) => SourceType::Reindex,
             SourceParams::Stdin => SourceType::Stdin,
             SourceParams::Vec(_) => SourceType::Vec,
             SourceParams::Void(_) => SourceType::Void,
@@ -94,12 +100,14 @@ impl SourceConfig {
         match &self.source_params {
             SourceParams::File(params) => serde_json::to_value(params),
             SourceParams::PubSub(params) => serde_json::to_value(params),
+            SourceParams::Http(params) => serde_json::to_value(params),
             SourceParams::Ingest => serde_json::to_value(()),
             SourceParams::IngestApi => serde_json::to_value(()),
             SourceParams::IngestCli => serde_json::to_value(()),
             SourceParams::Kafka(params) => serde_json::to_value(params),
             SourceParams::Kinesis(params) => serde_json::to_value(params),
             SourceParams::Pulsar(params) => serde_json::to_value(params),
+            SourceParams::Reindex(params) => serde_json::to_value(params),
             SourceParams::Stdin => serde_json::to_value(()),
             SourceParams::Vec(params) => serde_json::to_value(params),
             SourceParams::Void(params) => serde_json::to_value(params),
@@ -116,6 +124,7 @@ impl SourceConfig {
             source_params: SourceParams::IngestCli,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         }
     }
 
@@ -128,6 +137,7 @@ impl SourceConfig {
             source_params: SourceParams::Ingest,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         }
     }
 
@@ -140,6 +150,7 @@ impl SourceConfig {
             source_params: SourceParams::IngestApi,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         }
     }
 
@@ -152,6 +163,7 @@ impl SourceConfig {
             source_params,
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         }
     }
 }
@@ -174,6 +186,7 @@ impl crate::TestableForRegression for SourceConfig {
                 timezone: default_timezone(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         }
     }
 
@@ -185,6 +198,7 @@ impl crate::TestableForRegression for SourceConfig {
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SourceInputFormat {
+    Csv,
     #[default]
     Json,
     OtlpLogsJson,
@@ -198,6 +212,7 @@ pub enum SourceInputFormat {
         alias = "otlp_traces_proto"
     )]
     OtlpTracesProtobuf,
+    Parquet,
     #[serde(alias = "plain")]
     PlainText,
 }
@@ -207,7 +222,9 @@ impl FromStr for SourceInputFormat {
 
     fn from_str(format_str: &str) -> Result<Self, String> {
         match format_str {
+            "csv" => Ok(Self::Csv),
             "json" => Ok(Self::Json),
+            "parquet" => Ok(Self::Parquet),
             "plain" => Ok(Self::PlainText),
             unknown => Err(format!("unknown source input format: `{unknown}`")),
         }
@@ -219,6 +236,7 @@ impl FromStr for SourceInputFormat {
 pub enum SourceParams {
     #[schema(value_type = FileSourceParamsForSerde)]
     File(FileSourceParams),
+    Http(HttpSourceParams),
     Ingest,
     #[serde(rename = "ingest-api")]
     IngestApi,
@@ -229,6 +247,7 @@ pub enum SourceParams {
     #[serde(rename = "pubsub")]
     PubSub(PubSubSourceParams),
     Pulsar(PulsarSourceParams),
+    Reindex(ReindexSourceParams),
     Stdin,
     Vec(VecSourceParams),
     Void(VoidSourceParams),
@@ -379,6 +398,52 @@ pub struct KafkaSourceParams {
     pub enable_backfill_mode: bool,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HttpSourceParams {
+    /// Maximum number of bytes of pushed documents waiting to be checkpointed. Push requests
+    /// exceeding this limit are rejected until the pending documents are published.
+    #[schema(value_type = String, default = "100MiB")]
+    #[serde(default = "HttpSourceParams::default_max_buffer_size")]
+    pub max_buffer_size: ByteSize,
+}
+
+impl HttpSourceParams {
+    fn default_max_buffer_size() -> ByteSize {
+        ByteSize::mib(100)
+    }
+}
+
+impl Default for HttpSourceParams {
+    fn default() -> Self {
+        Self {
+            max_buffer_size: Self::default_max_buffer_size(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReindexSourceParams {
+    /// ID of the index whose documents are reindexed.
+    pub source_index_id: String,
+    /// Query in the Quickwit query language selecting the documents to reindex. All the
+    /// documents are reindexed if not set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Only the documents with a timestamp greater than or equal to this timestamp (in seconds)
+    /// are reindexed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_timestamp: Option<i64>,
+    /// Only the documents with a timestamp strictly lower than this timestamp (in seconds) are
+    /// reindexed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_timestamp: Option<i64>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct PubSubSourceParams {
@@ -515,6 +580,24 @@ fn default_consumer_name() -> String {
     "quickwit".to_string()
 }
 
+/// Destination of the documents rejected by the doc processor of a source because they could not be
+/// parsed, transformed, or mapped. Along with its raw payload, each rejected document is recorded
+/// with the rejection reason, its position in the source, and a timestamp.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DeadLetterQueueConfig {
+    /// Ingests the rejected documents into another index with the ingest API.
+    Index {
+        #[schema(value_type = String)]
+        index_id: IndexId,
+    },
+    /// Writes the rejected documents as NDJSON files under a storage prefix.
+    Storage {
+        #[schema(value_type = String)]
+        uri: Uri,
+    },
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct TransformConfig {
@@ -650,6 +733,7 @@ mod tests {
                 timezone: "local".to_string(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         assert_eq!(source_config, expected_source_config);
         assert_eq!(source_config.num_pipelines.get(), 2);
@@ -745,6 +829,7 @@ mod tests {
                 timezone: "local".to_string(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         assert_eq!(source_config, expected_source_config);
         assert_eq!(source_config.num_pipelines.get(), 1);
@@ -1065,6 +1150,103 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_http_source_params_deserialization() {
+        {
+            let yaml = r#"
+                    max_buffer_size: 10MiB
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<HttpSourceParams>(yaml).unwrap(),
+                HttpSourceParams {
+                    max_buffer_size: ByteSize::mib(10),
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    max_buffer_size: 10MiB
+                    endpoint: /push
+                "#;
+            let error = serde_yaml::from_str::<HttpSourceParams>(yaml).unwrap_err();
+            assert!(error.to_string().contains("unknown field `endpoint`"));
+        }
+        {
+            let source_config_json = r#"
+                {
+                    "version": "0.8",
+                    "source_id": "my-http-source",
+                    "source_type": "http",
+                    "params": {},
+                    "input_format": "plain_text",
+                    "transform": {
+                        "script": ".message = downcase(string!(.message))"
+                    }
+                }
+                "#;
+            let source_config = load_source_config_from_user_config(
+                ConfigFormat::Json,
+                source_config_json.as_bytes(),
+            )
+            .unwrap();
+            assert_eq!(source_config.source_type(), SourceType::Http);
+            assert_eq!(
+                source_config.source_params,
+                SourceParams::Http(HttpSourceParams::default())
+            );
+            assert_eq!(source_config.input_format, SourceInputFormat::PlainText);
+            assert!(source_config.transform_config.is_some());
+        }
+    }
+
+    #[test]
+    fn test_reindex_source_params_deserialization() {
+        {
+            let yaml = r#"
+                    source_index_id: my-index
+                    query: "severity:ERROR"
+                    start_timestamp: 1700000000
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<ReindexSourceParams>(yaml).unwrap(),
+                ReindexSourceParams {
+                    source_index_id: "my-index".to_string(),
+                    query: Some("severity:ERROR".to_string()),
+                    start_timestamp: Some(1_700_000_000),
+                    end_timestamp: None,
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    source_index_id: my-index
+                    index: my-other-index
+                "#;
+            let error = serde_yaml::from_str::<ReindexSourceParams>(yaml).unwrap_err();
+            assert!(error.to_string().contains("unknown field `index`"));
+        }
+        {
+            let source_config_json = r#"
+                {
+                    "version": "0.8",
+                    "source_id": "my-reindex-source",
+                    "source_type": "reindex",
+                    "params": {
+                        "source_index_id": "my-index",
+                        "start_timestamp": 1700000000,
+                        "end_timestamp": 1600000000
+                    }
+                }
+                "#;
+            let error = load_source_config_from_user_config(
+                ConfigFormat::Json,
+                source_config_json.as_bytes(),
+            )
+            .unwrap_err();
+            assert!(error.to_string().contains("must be strictly lower"));
+        }
+    }
+
     #[test]
     fn test_pulsar_source_params_deserialization() {
         {
@@ -1249,11 +1431,81 @@ mod tests {
                 timezone: default_timezone(),
             }),
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         assert_eq!(source_config, expected_source_config);
         assert_eq!(source_config.num_pipelines.get(), 1);
     }
 
+    #[tokio::test]
+    async fn test_load_source_config_with_dead_letter_queue() {
+        {
+            let source_config_yaml = r#"
+                version: 0.8
+                source_id: my-kafka-source
+                source_type: kafka
+                params:
+                  topic: my-topic
+                dead_letter_queue:
+                  type: index
+                  index_id: my-dead-letters
+            "#;
+            let source_config = load_source_config_from_user_config(
+                ConfigFormat::Yaml,
+                source_config_yaml.as_bytes(),
+            )
+            .unwrap();
+            assert_eq!(
+                source_config.dead_letter_queue_config,
+                Some(DeadLetterQueueConfig::Index {
+                    index_id: "my-dead-letters".to_string()
+                })
+            );
+            let source_config_json = serde_json::to_value(&source_config).unwrap();
+            assert_eq!(
+                source_config_json["dead_letter_queue"],
+                json!({"type": "index", "index_id": "my-dead-letters"})
+            );
+        }
+        {
+            let source_config_yaml = r#"
+                version: 0.8
+                source_id: my-kafka-source
+                source_type: kafka
+                params:
+                  topic: my-topic
+                dead_letter_queue:
+                  type: storage
+                  uri: s3://my-bucket/dead-letters
+            "#;
+            let source_config = load_source_config_from_user_config(
+                ConfigFormat::Yaml,
+                source_config_yaml.as_bytes(),
+            )
+            .unwrap();
+            assert_eq!(
+                source_config.dead_letter_queue_config,
+                Some(DeadLetterQueueConfig::Storage {
+                    uri: Uri::for_test("s3://my-bucket/dead-letters")
+                })
+            );
+        }
+        {
+            let source_config_yaml = r#"
+                version: 0.8
+                source_id: my-kafka-source
+                source_type: kafka
+                params:
+                  topic: my-topic
+                dead_letter_queue:
+                  type: index
+                  index_id: "-invalid"
+            "#;
+            load_source_config_from_user_config(ConfigFormat::Yaml, source_config_yaml.as_bytes())
+                .unwrap_err();
+        }
+    }
+
     #[test]
     fn test_transform_config_serialization() {
         {
@@ -1370,4 +1622,50 @@ mod tests {
                 .unwrap();
         assert_eq!(source_config.input_format, SourceInputFormat::PlainText);
     }
+
+    #[tokio::test]
+    async fn test_source_config_csv_and_parquet_input_formats() {
+        for (input_format, expected_input_format) in [
+            ("csv", SourceInputFormat::Csv),
+            ("parquet", SourceInputFormat::Parquet),
+        ] {
+            let file_content = format!(
+                r#"{{
+                    "version": "0.8",
+                    "source_id": "file-source",
+                    "source_type": "file",
+                    "params": {{
+                        "filepath": "s3://mybucket/test_corpus"
+                    }},
+                    "input_format": "{input_format}"
+                }}"#
+            );
+            let source_config =
+                load_source_config_from_user_config(ConfigFormat::Json, file_content.as_bytes())
+                    .unwrap();
+            assert_eq!(source_config.input_format, expected_input_format);
+            assert_eq!(
+                SourceInputFormat::from_str(input_format).unwrap(),
+                expected_input_format
+            );
+
+            let file_content = format!(
+                r#"{{
+                    "version": "0.8",
+                    "source_id": "kafka-source",
+                    "source_type": "kafka",
+                    "params": {{
+                        "topic": "my-topic"
+                    }},
+                    "input_format": "{input_format}"
+                }}"#
+            );
+            let error =
+                load_source_config_from_user_config(ConfigFormat::Json, file_content.as_bytes())
+                    .unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("only supported by the file source"));
+        }
+    }
 }
@@ -23,7 +23,7 @@ use anyhow::bail;
 use quickwit_proto::types::SourceId;
 use serde::{Deserialize, Serialize};
 
-use super::{TransformConfig, RESERVED_SOURCE_IDS};
+use super::{DeadLetterQueueConfig, TransformConfig, RESERVED_SOURCE_IDS};
 use crate::{
     validate_identifier, ConfigFormat, FileSourceParams, SourceConfig, SourceInputFormat,
     SourceParams,
@@ -92,7 +92,20 @@ impl SourceConfigForSerialization {
             | SourceParams::Pulsar(_) => {
                 // TODO consider any validation opportunity
             }
-            SourceParams::PubSub(_)
+            SourceParams::Reindex(params) => {
+                if let (Some(start_timestamp), Some(end_timestamp)) =
+                    (params.start_timestamp, params.end_timestamp)
+                {
+                    if start_timestamp >= end_timestamp {
+                        bail!(
+                            "reindex source `start_timestamp` must be strictly lower than \
+                             `end_timestamp`"
+                        );
+                    }
+                }
+            }
+            SourceParams::Http(_)
+            | SourceParams::PubSub(_)
             | SourceParams::Ingest
             | SourceParams::IngestApi
             | SourceParams::IngestCli
@@ -110,6 +123,13 @@ impl SourceConfigForSerialization {
             }
         }
 
+        if matches!(
+            self.input_format,
+            SourceInputFormat::Csv | SourceInputFormat::Parquet
+        ) && !matches!(self.source_params, SourceParams::File(_))
+        {
+            bail!("CSV and Parquet input formats are only supported by the file source");
+        }
         if let Some(transform_config) = &self.transform {
             if matches!(
                 self.input_format,
@@ -122,6 +142,9 @@ impl SourceConfigForSerialization {
             }
             transform_config.validate_vrl_script()?;
         }
+        if let Some(DeadLetterQueueConfig::Index { index_id }) = &self.dead_letter_queue {
+            validate_identifier("dead letter queue index", index_id)?;
+        }
 
         Ok(SourceConfig {
             source_id: self.source_id,
@@ -130,6 +153,7 @@ impl SourceConfigForSerialization {
             source_params: self.source_params,
             transform_config: self.transform,
             input_format: self.input_format,
+            dead_letter_queue_config: self.dead_letter_queue,
         })
     }
 }
@@ -143,6 +167,7 @@ impl From<SourceConfig> for SourceConfigV0_8 {
             source_params: source_config.source_params,
             transform: source_config.transform_config,
             input_format: source_config.input_format,
+            dead_letter_queue: source_config.dead_letter_queue_config,
         }
     }
 }
@@ -226,6 +251,10 @@ pub struct SourceConfigV0_8 {
     // Denotes the input data format.
     #[serde(default)]
     pub input_format: SourceInputFormat,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_letter_queue: Option<DeadLetterQueueConfig>,
 }
 
 impl From<SourceConfigV0_7> for SourceConfigV0_8 {
@@ -246,6 +275,7 @@ impl From<SourceConfigV0_7> for SourceConfigV0_8 {
             source_params,
             transform,
             input_format,
+            dead_letter_queue: None,
         }
     }
 }
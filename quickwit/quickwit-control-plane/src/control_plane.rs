@@ -367,6 +367,7 @@ impl ControlPlane {
                     "shard_state": shard_entry.shard_state().as_json_str_name(),
                     "leader_id": shard_entry.leader_id.clone(),
                     "follower_id": shard_entry.follower_id.clone(),
+                    "additional_follower_ids": shard_entry.additional_follower_ids.clone(),
                     "publish_position_inclusive": shard_entry.publish_position_inclusive(),
                 })
             });
@@ -469,6 +470,13 @@ impl Handler<ControlPlanLoop> for ControlPlane {
         if self.disable_control_loop {
             return Ok(());
         }
+        if let Err(metastore_error) = self
+            .ingest_controller
+            .evaluate_scaling_policies(&mut self.model, ctx.progress())
+            .await
+        {
+            return convert_metastore_error::<()>(metastore_error).map(|_| ());
+        }
         if let Err(metastore_error) = self
             .ingest_controller
             .rebalance_shards(&mut self.model, ctx.mailbox(), ctx.progress())
@@ -640,6 +648,7 @@ impl Handler<DeleteIndexRequest> for ControlPlane {
             .collect();
 
         self.model.delete_index(&index_uid);
+        self.ingest_controller.remove_index(&index_uid);
 
         self.ingest_controller
             .sync_with_ingesters(&ingester_needing_resync, &self.model);
@@ -771,6 +780,7 @@ impl Handler<DeleteSourceRequest> for ControlPlane {
             .sync_with_ingesters(&ingesters_needing_resync, &self.model);
 
         self.model.delete_source(&source_uid);
+        self.ingest_controller.remove_source(&source_uid);
 
         let _rebuild_plan_waiter = self.rebuild_plan_debounced(ctx);
         let response = EmptyResponse {};
@@ -964,6 +974,7 @@ impl Handler<IndexerJoined> for ControlPlane {
             "indexer `{}` joined the cluster: rebalancing shards and rebuilding indexing plan",
             message.0.node_id()
         );
+        self.ingest_controller.update_failure_domain(&message.0);
         // TODO: Update shard table.
         if let Err(metastore_error) = self
             .ingest_controller
@@ -994,6 +1005,8 @@ impl Handler<IndexerLeft> for ControlPlane {
             "indexer `{}` left the cluster: rebalancing shards and rebuilding indexing plan",
             message.0.node_id()
         );
+        self.ingest_controller
+            .remove_failure_domain(message.0.node_id());
         // TODO: Update shard table.
         if let Err(metastore_error) = self
             .ingest_controller
@@ -1962,6 +1975,7 @@ mod tests {
                             shard_id: Some(ShardId::from(15)),
                             leader_id: "node1".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             shard_state: ShardState::Open as i32,
                             doc_mapping_uid: Some(DocMappingUid::default()),
                             publish_position_inclusive: None,
@@ -2093,6 +2107,7 @@ mod tests {
                             shard_id: Some(ShardId::from(15)),
                             leader_id: "node1".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             shard_state: ShardState::Open as i32,
                             doc_mapping_uid: Some(DocMappingUid::default()),
                             publish_position_inclusive: None,
@@ -2382,6 +2397,7 @@ mod tests {
                         shard_id: Some(ShardId::from(0u64)),
                         leader_id: "test-ingester".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         shard_state: ShardState::Open as i32,
                         doc_mapping_uid: Some(DocMappingUid::default()),
                         publish_position_inclusive: Some(Position::Beginning),
@@ -2536,6 +2552,7 @@ mod tests {
                         shard_id: Some(ShardId::from(0u64)),
                         leader_id: "test-ingester".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         shard_state: ShardState::Open as i32,
                         doc_mapping_uid: Some(DocMappingUid::default()),
                         publish_position_inclusive: Some(Position::Beginning),
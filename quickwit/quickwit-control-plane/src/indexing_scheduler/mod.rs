@@ -214,10 +214,12 @@ fn get_sources_to_schedule(model: &ControlPlaneModel) -> Vec<SourceToSchedule> {
                     params_fingerprint,
                 });
             }
-            SourceParams::Kafka(_)
+            SourceParams::Http(_)
+            | SourceParams::Kafka(_)
             | SourceParams::Kinesis(_)
             | SourceParams::PubSub(_)
             | SourceParams::Pulsar(_)
+            | SourceParams::Reindex(_)
             | SourceParams::File(FileSourceParams::Notifications(_)) => {
                 sources.push(SourceToSchedule {
                     source_uid,
@@ -853,6 +855,7 @@ mod tests {
                     source_params: SourceParams::Kafka(kafka_source_params.clone()),
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_queue_config: None,
                 },
             )
             .unwrap();
@@ -866,6 +869,7 @@ mod tests {
                     source_params: SourceParams::Kafka(kafka_source_params.clone()),
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_queue_config: None,
                 },
             )
             .unwrap();
@@ -880,6 +884,7 @@ mod tests {
                     source_params: SourceParams::IngestApi,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_queue_config: None,
                 },
             )
             .unwrap();
@@ -894,6 +899,7 @@ mod tests {
                     source_params: SourceParams::Ingest,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_queue_config: None,
                 },
             )
             .unwrap();
@@ -909,6 +915,7 @@ mod tests {
                     source_params: SourceParams::Ingest,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_queue_config: None,
                 },
             )
             .unwrap();
@@ -923,6 +930,7 @@ mod tests {
                     source_params: SourceParams::IngestCli,
                     transform_config: None,
                     input_format: Default::default(),
+                    dead_letter_queue_config: None,
                 },
             )
             .unwrap();
@@ -1078,6 +1086,7 @@ mod tests {
               source_params: kafka_source_params_for_test(),
               transform_config: None,
               input_format: SourceInputFormat::Json,
+              dead_letter_queue_config: None,
           })
       }
     }
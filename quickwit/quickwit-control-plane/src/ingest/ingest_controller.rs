@@ -28,9 +28,10 @@ use fnv::FnvHashSet;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use quickwit_actors::Mailbox;
+use quickwit_cluster::ClusterNode;
 use quickwit_common::pretty::PrettySample;
 use quickwit_common::Progress;
-use quickwit_ingest::{IngesterPool, LeaderId, LocalShardsUpdate};
+use quickwit_ingest::{replication_quorum, IngesterPool, LeaderId, LocalShardsUpdate};
 use quickwit_proto::control_plane::{
     AdviseResetShardsRequest, AdviseResetShardsResponse, GetOrCreateOpenShardsFailureReason,
     GetOrCreateOpenShardsRequest, GetOrCreateOpenShardsResponse, GetOrCreateOpenShardsSubrequest,
@@ -51,7 +52,7 @@ use quickwit_proto::metastore::{
 use quickwit_proto::types::{IndexUid, NodeId, NodeIdRef, Position, ShardId, SourceUid};
 use rand::rngs::ThreadRng;
 use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng, RngCore};
+use rand::{thread_rng, RngCore};
 use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, OwnedMutexGuard};
 use tokio::task::JoinHandle;
@@ -59,6 +60,7 @@ use tracing::{debug, enabled, error, info, warn, Level};
 use ulid::Ulid;
 
 use super::scaling_arbiter::ScalingArbiter;
+use super::scaling_policy::ScalingContext;
 use crate::control_plane::ControlPlane;
 use crate::ingest::wait_handle::WaitHandle;
 use crate::model::{ControlPlaneModel, ScalingMode, ShardEntry, ShardStats};
@@ -94,29 +96,25 @@ fn fire_and_forget(
     });
 }
 
-// Returns a random position of the els `slice`, such that the element in this array is NOT
-// `except_el`.
-fn pick_position(
-    els: &[&NodeIdRef],
-    except_el_opt: Option<&NodeIdRef>,
-    rng: &mut ThreadRng,
-) -> Option<usize> {
-    let except_pos_opt =
-        except_el_opt.and_then(|except_el| els.iter().position(|el| *el == except_el));
-    if let Some(except_pos) = except_pos_opt {
-        let pos = rng.gen_range(0..els.len() - 1);
-        if pos >= except_pos {
-            Some(pos + 1)
-        } else {
-            Some(pos)
+/// Failure domain of an ingester, as advertised by the node labels of its configuration.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub(crate) struct FailureDomain {
+    pub availability_zone: Option<String>,
+    pub rack: Option<String>,
+}
+
+impl FailureDomain {
+    pub fn from_cluster_node(node: &ClusterNode) -> Self {
+        Self {
+            availability_zone: node.availability_zone().map(str::to_string),
+            rack: node.rack().map(str::to_string),
         }
-    } else {
-        Some(rng.gen_range(0..els.len()))
     }
 }
 
-/// Pick a node from the `shard_count_to_node_ids` that is different from `except_node_opt`.
-/// We pick in priority nodes with the least number of shards, and we break any tie randomly.
+/// Pick a node from the `shard_count_to_node_ids` that satisfies `is_eligible`.
+/// We pick in priority nodes with the least number of shards. Among those, nodes satisfying
+/// `is_preferred` win, and we break any remaining tie randomly.
 ///
 /// Once a node has been found, we update the `shard_count_to_node_ids` to reflect the new state.
 /// In particular, the ingester node is moved from its previous shard_count level to its new
@@ -124,28 +122,35 @@ fn pick_position(
 /// BTreeMap.
 fn pick_one<'a>(
     shard_count_to_node_ids: &mut BTreeMap<usize, Vec<&'a NodeIdRef>>,
-    except_node_opt: Option<&'a NodeIdRef>,
+    is_eligible: impl Fn(&NodeIdRef) -> bool,
+    is_preferred: impl Fn(&NodeIdRef) -> bool,
     rng: &mut ThreadRng,
 ) -> Option<&'a NodeIdRef> {
-    let (&shard_count, _) = shard_count_to_node_ids.iter().find(|(_, node_ids)| {
-        let Some(except_node) = except_node_opt else {
-            return true;
-        };
-        if node_ids.len() >= 2 {
-            return true;
-        }
-        let Some(&single_node_id) = node_ids.first() else {
-            return false;
-        };
-        single_node_id != except_node
-    })?;
+    let (shard_count, position) =
+        shard_count_to_node_ids
+            .iter()
+            .find_map(|(&shard_count, node_ids)| {
+                let eligible_positions: Vec<usize> = (0..node_ids.len())
+                    .filter(|&position| is_eligible(node_ids[position]))
+                    .collect();
+                let preferred_positions: Vec<usize> = eligible_positions
+                    .iter()
+                    .copied()
+                    .filter(|&position| is_preferred(node_ids[position]))
+                    .collect();
+                let candidate_positions = if preferred_positions.is_empty() {
+                    eligible_positions
+                } else {
+                    preferred_positions
+                };
+                let position = *candidate_positions.choose(rng)?;
+                Some((shard_count, position))
+            })?;
     let mut shard_entry = shard_count_to_node_ids.entry(shard_count);
     let Entry::Occupied(occupied_shard_entry) = &mut shard_entry else {
         panic!();
     };
     let nodes = occupied_shard_entry.get_mut();
-    let position = pick_position(nodes, except_node_opt, rng)?;
-
     let node_id = nodes.swap_remove(position);
     let new_shard_count = shard_count + 1;
     let should_remove_entry = nodes.is_empty();
@@ -160,22 +165,20 @@ fn pick_one<'a>(
     Some(node_id)
 }
 
-/// Pick two ingester nodes from `shard_count_to_node_ids` different one from each other.
-/// Ingesters with the lower number of shards are preferred.
-fn pick_two<'a>(
-    shard_count_to_node_ids: &mut BTreeMap<usize, Vec<&'a NodeIdRef>>,
-    rng: &mut ThreadRng,
-) -> Option<(&'a NodeIdRef, &'a NodeIdRef)> {
-    let leader = pick_one(shard_count_to_node_ids, None, rng)?;
-    let follower = pick_one(shard_count_to_node_ids, Some(leader), rng)?;
-    Some((leader, follower))
-}
-
-fn allocate_shards(
-    node_id_shard_counts: &HashMap<NodeId, usize>,
+/// Allocates `num_shards` shards of `replication_factor` replicas each. Each allocation is made of
+/// a leader and its followers, which are all distinct nodes. Ingesters with the lower number of
+/// shards are preferred.
+///
+/// When at least one ingester advertises an availability zone, a shard never has a quorum of
+/// replicas in the same zone, so losing a zone never loses acknowledged documents. Ingesters
+/// without a zone are treated as members of the same zone. Within a zone, replicas are spread
+/// across racks when possible.
+fn allocate_shards<'a>(
+    node_id_shard_counts: &'a HashMap<NodeId, usize>,
     num_shards: usize,
-    replication_enabled: bool,
-) -> Option<Vec<(&NodeIdRef, Option<&NodeIdRef>)>> {
+    replication_factor: usize,
+    failure_domains: &HashMap<NodeId, FailureDomain>,
+) -> Option<Vec<(&'a NodeIdRef, Vec<&'a NodeIdRef>)>> {
     let mut shard_count_to_node_ids: BTreeMap<usize, Vec<&NodeIdRef>> = BTreeMap::default();
     for (node_id, &num_shards) in node_id_shard_counts {
         shard_count_to_node_ids
@@ -183,17 +186,61 @@ fn allocate_shards(
             .or_default()
             .push(node_id.as_ref());
     }
+    let failure_domain = |node_id: &NodeIdRef| {
+        failure_domains
+            .get(node_id)
+            .map(|failure_domain| {
+                (
+                    failure_domain.availability_zone.as_deref(),
+                    failure_domain.rack.as_deref(),
+                )
+            })
+            .unwrap_or_default()
+    };
+    let is_zone_aware = replication_factor >= 2
+        && node_id_shard_counts
+            .keys()
+            .any(|node_id| failure_domain(node_id.as_ref()).0.is_some());
+    let max_replicas_per_zone = if is_zone_aware {
+        replication_quorum(replication_factor) - 1
+    } else {
+        replication_factor
+    };
     let mut rng = thread_rng();
-    let mut shard_allocations: Vec<(&NodeIdRef, Option<&NodeIdRef>)> =
-        Vec::with_capacity(num_shards);
+    let mut shard_allocations: Vec<(&NodeIdRef, Vec<&NodeIdRef>)> = Vec::with_capacity(num_shards);
+
     for _ in 0..num_shards {
-        if replication_enabled {
-            let (leader, follower) = pick_two(&mut shard_count_to_node_ids, &mut rng)?;
-            shard_allocations.push((leader, Some(follower)));
-        } else {
-            let leader = pick_one(&mut shard_count_to_node_ids, None, &mut rng)?;
-            shard_allocations.push((leader, None));
+        let mut replicas: Vec<&NodeIdRef> = Vec::with_capacity(replication_factor);
+
+        for _ in 0..replication_factor {
+            let is_eligible = |node_id: &NodeIdRef| {
+                if replicas.iter().any(|&replica| replica == node_id) {
+                    return false;
+                }
+                let (zone_opt, _) = failure_domain(node_id);
+                let num_replicas_in_zone = replicas
+                    .iter()
+                    .filter(|&&replica| failure_domain(replica).0 == zone_opt)
+                    .count();
+                num_replicas_in_zone < max_replicas_per_zone
+            };
+            let is_preferred = |node_id: &NodeIdRef| {
+                let node_failure_domain = failure_domain(node_id);
+                node_failure_domain.1.is_none()
+                    || replicas
+                        .iter()
+                        .all(|&replica| failure_domain(replica) != node_failure_domain)
+            };
+            let replica = pick_one(
+                &mut shard_count_to_node_ids,
+                is_eligible,
+                is_preferred,
+                &mut rng,
+            )?;
+            replicas.push(replica);
         }
+        let leader = replicas.remove(0);
+        shard_allocations.push((leader, replicas));
     }
     Some(shard_allocations)
 }
@@ -207,6 +254,8 @@ pub struct IngestController {
     ingester_pool: IngesterPool,
     metastore: MetastoreServiceClient,
     replication_factor: usize,
+    // Failure domains of the ingesters that advertise node labels.
+    failure_domains: HashMap<NodeId, FailureDomain>,
     // This lock ensures that only one rebalance operation is performed at a time.
     rebalance_lock: Arc<Mutex<()>>,
     pub stats: IngestControllerStats,
@@ -276,11 +325,15 @@ fn get_open_shard_from_model(
         .into_iter()
         .map(|shard_entry| shard_entry.shard)
         .collect();
+    let ingest_quota = model
+        .index_metadata(index_uid)
+        .and_then(|index_metadata| index_metadata.index_config.ingest_settings.ingest_quota());
     Ok(Some(GetOrCreateOpenShardsSuccess {
         subrequest_id: get_open_shards_subrequest.subrequest_id,
         index_uid: Some(index_uid.clone()),
         source_id: get_open_shards_subrequest.source_id.clone(),
         open_shards,
+        ingest_quota,
     }))
 }
 
@@ -295,6 +348,7 @@ impl IngestController {
             metastore,
             ingester_pool,
             replication_factor,
+            failure_domains: HashMap::new(),
             rebalance_lock: Arc::new(Mutex::new(())),
             stats: IngestControllerStats::default(),
             scaling_arbiter: ScalingArbiter::with_max_shard_ingestion_throughput_mib_per_sec(
@@ -303,6 +357,33 @@ impl IngestController {
         }
     }
 
+    /// Records the failure domain of an ingester that joined the cluster or updated its labels.
+    pub(crate) fn update_failure_domain(&mut self, node: &ClusterNode) {
+        let failure_domain = FailureDomain::from_cluster_node(node);
+
+        if failure_domain == FailureDomain::default() {
+            self.failure_domains.remove(node.node_id());
+        } else {
+            self.failure_domains
+                .insert(node.node_id().to_owned(), failure_domain);
+        }
+    }
+
+    /// Forgets the failure domain of an ingester that left the cluster.
+    pub(crate) fn remove_failure_domain(&mut self, node_id: &NodeIdRef) {
+        self.failure_domains.remove(node_id);
+    }
+
+    /// Drops the state of the scaling policies of the index's sources.
+    pub(crate) fn remove_index(&mut self, index_uid: &IndexUid) {
+        self.scaling_arbiter.remove_index(index_uid);
+    }
+
+    /// Drops the state of the scaling policy of the source.
+    pub(crate) fn remove_source(&mut self, source_uid: &SourceUid) {
+        self.scaling_arbiter.remove_source(source_uid);
+    }
+
     /// Sends a retain shard request to the given list of ingesters.
     ///
     /// If the request fails, we just log an error.
@@ -396,15 +477,86 @@ impl IngestController {
             &local_shards_update.source_uid,
             &local_shards_update.shard_infos,
         );
-        let Some(scaling_mode) = self.scaling_arbiter.should_scale(shard_stats) else {
+        self.apply_scaling_policy(local_shards_update.source_uid, shard_stats, model, progress)
+            .await
+    }
+
+    /// Evaluates the custom scaling policies of the sources with open shards. This method is called
+    /// periodically by the control plane so that time-of-day schedules, shard bounds, and forecasts
+    /// take effect even when the ingesters do not report any local shards update. The default
+    /// policy only reacts to fresh ingestion rates, so it is evaluated on local shards updates
+    /// only.
+    pub(crate) async fn evaluate_scaling_policies(
+        &mut self,
+        model: &mut ControlPlaneModel,
+        progress: &Progress,
+    ) -> MetastoreResult<()> {
+        let source_uids: Vec<SourceUid> = model.shard_source_uids().cloned().collect();
+
+        for source_uid in source_uids {
+            let has_custom_scaling_policy = model
+                .index_metadata(&source_uid.index_uid)
+                .is_some_and(|index_metadata| {
+                    !index_metadata
+                        .index_config
+                        .ingest_settings
+                        .scaling_policy
+                        .is_default()
+                });
+            if !has_custom_scaling_policy {
+                continue;
+            }
+            let shard_stats = model.shard_stats(&source_uid);
+
+            // Shards are opened lazily by the routers, so we leave idle sources alone.
+            if shard_stats.num_open_shards == 0 {
+                continue;
+            }
+            self.apply_scaling_policy(source_uid, shard_stats, model, progress)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn apply_scaling_policy(
+        &mut self,
+        source_uid: SourceUid,
+        shard_stats: ShardStats,
+        model: &mut ControlPlaneModel,
+        progress: &Progress,
+    ) -> MetastoreResult<()> {
+        let scaling_policy_config = model
+            .index_metadata(&source_uid.index_uid)
+            .map(|index_metadata| {
+                index_metadata
+                    .index_config
+                    .ingest_settings
+                    .scaling_policy
+                    .clone()
+            })
+            .unwrap_or_default();
+        let scaling_context = ScalingContext::new(shard_stats);
+
+        let Some(scaling_decision) =
+            self.scaling_arbiter
+                .should_scale(&source_uid, &scaling_policy_config, scaling_context)
+        else {
             return Ok(());
         };
-
-        match scaling_mode {
+        debug!(
+            index_id=%source_uid.index_uid.index_id,
+            source_id=%source_uid.source_id,
+            scaling_policy=?scaling_policy_config.mode,
+            scaling_mode=?scaling_decision.scaling_mode,
+            "scaling policy decision: {}",
+            scaling_decision.reason
+        );
+        match scaling_decision.scaling_mode {
             ScalingMode::Up => {
                 self.try_scale_up_shards(
-                    local_shards_update.source_uid,
+                    source_uid,
                     shard_stats,
+                    &scaling_decision.reason,
                     model,
                     progress,
                 )
@@ -412,15 +564,15 @@ impl IngestController {
             }
             ScalingMode::Down => {
                 self.try_scale_down_shards(
-                    local_shards_update.source_uid,
+                    source_uid,
                     shard_stats,
+                    &scaling_decision.reason,
                     model,
                     progress,
                 )
                 .await?;
             }
         }
-
         Ok(())
     }
 
@@ -530,7 +682,7 @@ impl IngestController {
         num_shards_to_allocate: usize,
         unavailable_leaders: &FnvHashSet<NodeId>,
         model: &ControlPlaneModel,
-    ) -> Option<Vec<(NodeId, Option<NodeId>)>> {
+    ) -> Option<Vec<(NodeId, Vec<NodeId>)>> {
         // Count of open shards per available ingester node (including the ingester with 0 open
         // shards).
         let mut per_node_num_open_shards: HashMap<NodeId, usize> = self
@@ -575,17 +727,27 @@ impl IngestController {
             }
         }
 
-        assert!(self.replication_factor == 1 || self.replication_factor == 2);
-        let leader_follower_pairs: Vec<(&NodeIdRef, Option<&NodeIdRef>)> = allocate_shards(
+        let Some(shard_allocations) = allocate_shards(
             &per_node_num_open_shards,
             num_shards_to_allocate,
-            self.replication_factor == 2,
-        )?;
+            self.replication_factor,
+            &self.failure_domains,
+        ) else {
+            warn!(
+                "failed to allocate {num_shards_to_allocate} shards: not enough ingesters in \
+                 distinct availability zones to place {} replicas",
+                self.replication_factor
+            );
+            return None;
+        };
         Some(
-            leader_follower_pairs
+            shard_allocations
                 .into_iter()
-                .map(|(leader_id, follower_id)| {
-                    (leader_id.to_owned(), follower_id.map(NodeIdRef::to_owned))
+                .map(|(leader_id, follower_ids)| {
+                    (
+                        leader_id.to_owned(),
+                        follower_ids.into_iter().map(NodeIdRef::to_owned).collect(),
+                    )
                 })
                 .collect(),
         )
@@ -668,11 +830,12 @@ impl IngestController {
 
     /// Attempts to increase the number of shards. This operation is rate limited to avoid creating
     /// to many shards in a short period of time. As a result, this method may not create any
-    /// shard.
+    /// shard. The `reason` of the scaling decision is logged.
     async fn try_scale_up_shards(
         &mut self,
         source_uid: SourceUid,
         shard_stats: ShardStats,
+        reason: &str,
         model: &mut ControlPlaneModel,
         progress: &Progress,
     ) -> MetastoreResult<()> {
@@ -683,6 +846,12 @@ impl IngestController {
             return Ok(());
         }
         let new_num_open_shards = shard_stats.num_open_shards + 1;
+
+        info!(
+            index_id=%source_uid.index_uid.index_id,
+            source_id=%source_uid.source_id,
+            "scaling up number of shards to {new_num_open_shards}: {reason}"
+        );
         let new_shard_source_uids: HashMap<SourceUid, usize> =
             HashMap::from_iter([(source_uid.clone(), 1)]);
         let successful_source_uids_res = self
@@ -767,7 +936,7 @@ impl IngestController {
 
         let mut init_shard_subrequests: Vec<InitShardSubrequest> = Vec::new();
 
-        for (subrequest_id, (source_uid, (leader_id, follower_id_opt))) in
+        for (subrequest_id, (source_uid, (leader_id, follower_ids))) in
             source_uids_with_multiplicity
                 .zip(leader_follower_pairs)
                 .enumerate()
@@ -791,7 +960,12 @@ impl IngestController {
                 source_id: source_uid.source_id.clone(),
                 shard_id: Some(shard_id),
                 leader_id: leader_id.to_string(),
-                follower_id: follower_id_opt.as_ref().map(ToString::to_string),
+                follower_id: follower_ids.first().map(ToString::to_string),
+                additional_follower_ids: follower_ids
+                    .iter()
+                    .skip(1)
+                    .map(ToString::to_string)
+                    .collect(),
                 shard_state: ShardState::Open as i32,
                 doc_mapping_uid: Some(doc_mapping_uid),
                 publish_position_inclusive: Some(Position::Beginning),
@@ -824,6 +998,7 @@ impl IngestController {
                     shard_id: shard.shard_id.clone(),
                     leader_id: shard.leader_id.clone(),
                     follower_id: shard.follower_id.clone(),
+                    additional_follower_ids: shard.additional_follower_ids.clone(),
                     doc_mapping_uid: shard.doc_mapping_uid,
                     // Shards are acquired by the ingest sources
                     publish_token: None,
@@ -850,11 +1025,13 @@ impl IngestController {
     }
 
     /// Attempts to decrease the number of shards. This operation is rate limited to avoid closing
-    /// shards too aggressively. As a result, this method may not close any shard.
+    /// shards too aggressively. As a result, this method may not close any shard. The `reason` of
+    /// the scaling decision is logged.
     async fn try_scale_down_shards(
         &self,
         source_uid: SourceUid,
         shard_stats: ShardStats,
+        reason: &str,
         model: &mut ControlPlaneModel,
         progress: &Progress,
     ) -> MetastoreResult<()> {
@@ -873,7 +1050,7 @@ impl IngestController {
         info!(
             index_id=%source_uid.index_uid.index_id,
             source_id=%source_uid.source_id,
-            "scaling down number of shards to {new_num_open_shards}"
+            "scaling down number of shards to {new_num_open_shards}: {reason}"
         );
         let Some((leader_id, shard_id)) = find_scale_down_candidate(&source_uid, model) else {
             model.release_scaling_permits(&source_uid, ScalingMode::Down);
@@ -1233,7 +1410,7 @@ fn find_scale_down_candidate(
 #[cfg(test)]
 mod tests {
 
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeSet, HashSet};
     use std::str::FromStr;
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
@@ -1281,6 +1458,10 @@ mod tests {
 
         let doc_mapping_uid_1 = DocMappingUid::random();
         index_metadata_1.index_config.doc_mapping.doc_mapping_uid = doc_mapping_uid_1;
+        index_metadata_1
+            .index_config
+            .ingest_settings
+            .max_docs_per_sec = Some(1_000);
 
         let progress = Progress::default();
 
@@ -1447,6 +1628,7 @@ mod tests {
         assert_eq!(success.open_shards[0].shard_id(), ShardId::from(2));
         assert_eq!(success.open_shards[0].leader_id, "test-ingester-1");
         assert_eq!(success.open_shards[0].doc_mapping_uid(), doc_mapping_uid_0);
+        assert!(success.ingest_quota.is_none());
 
         let success = &response.successes[1];
         assert_eq!(success.subrequest_id, 1);
@@ -1457,6 +1639,11 @@ mod tests {
         assert_eq!(success.open_shards[0].leader_id, "test-ingester-2");
         assert_eq!(success.open_shards[0].doc_mapping_uid(), doc_mapping_uid_1);
 
+        let ingest_quota = success.ingest_quota.as_ref().unwrap();
+        assert_eq!(ingest_quota.max_docs_per_sec, Some(1_000));
+        assert_eq!(ingest_quota.max_bytes_per_sec, None);
+        assert_eq!(ingest_quota.max_wal_bytes, None);
+
         let failure = &response.failures[0];
         assert_eq!(failure.subrequest_id, 2);
         assert_eq!(failure.index_id, "index-not-found");
@@ -1668,13 +1855,13 @@ mod tests {
         if leader_follower_pairs[0].0 == "test-ingester-1" {
             assert_eq!(
                 leader_follower_pairs[0].1,
-                Some(NodeId::from("test-ingester-2"))
+                vec![NodeId::from("test-ingester-2")]
             );
         } else {
             assert_eq!(leader_follower_pairs[0].0, "test-ingester-2");
             assert_eq!(
                 leader_follower_pairs[0].1,
-                Some(NodeId::from("test-ingester-1"))
+                vec![NodeId::from("test-ingester-1")]
             );
         }
 
@@ -1687,13 +1874,13 @@ mod tests {
             if leader_follower_pair.0 == "test-ingester-1" {
                 assert_eq!(
                     leader_follower_pair.1,
-                    Some(NodeId::from("test-ingester-2"))
+                    vec![NodeId::from("test-ingester-2")]
                 );
             } else {
                 assert_eq!(leader_follower_pair.0, "test-ingester-2");
                 assert_eq!(
                     leader_follower_pair.1,
-                    Some(NodeId::from("test-ingester-1"))
+                    vec![NodeId::from("test-ingester-1")]
                 );
             }
         }
@@ -1722,19 +1909,19 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[1].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[1].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[2].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[2].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         let open_shards = vec![
@@ -1765,7 +1952,7 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-2");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         ingester_pool.insert("test-ingester-3".into(), IngesterServiceClient::mocked());
@@ -1778,28 +1965,128 @@ mod tests {
         assert_eq!(leader_follower_pairs[0].0, "test-ingester-3");
         assert_eq!(
             leader_follower_pairs[0].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[1].0, "test-ingester-3");
         assert_eq!(
             leader_follower_pairs[1].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[2].0, "test-ingester-3");
         assert_eq!(
             leader_follower_pairs[2].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
 
         assert_eq!(leader_follower_pairs[3].0, "test-ingester-3");
         assert_eq!(
             leader_follower_pairs[3].1,
-            Some(NodeId::from("test-ingester-1"))
+            vec![NodeId::from("test-ingester-1")]
         );
     }
 
+    #[test]
+    fn test_ingest_controller_allocate_shards_across_availability_zones() {
+        let metastore = MetastoreServiceClient::mocked();
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 3;
+
+        let mut controller = IngestController::new(
+            metastore,
+            ingester_pool.clone(),
+            replication_factor,
+            TEST_SHARD_THROUGHPUT_LIMIT_MIB,
+        );
+        let model = ControlPlaneModel::default();
+
+        for (node_id, availability_zone) in [
+            ("test-ingester-1", "zone-a"),
+            ("test-ingester-2", "zone-a"),
+            ("test-ingester-3", "zone-b"),
+            ("test-ingester-4", "zone-b"),
+        ] {
+            ingester_pool.insert(node_id.into(), IngesterServiceClient::mocked());
+            controller.failure_domains.insert(
+                node_id.into(),
+                FailureDomain {
+                    availability_zone: Some(availability_zone.to_string()),
+                    rack: None,
+                },
+            );
+        }
+        // With a replication factor of 3, a quorum of 2 replicas must never live in the same zone,
+        // so two zones are not enough.
+        let shard_allocations_opt = controller.allocate_shards(1, &FnvHashSet::default(), &model);
+        assert!(shard_allocations_opt.is_none());
+
+        ingester_pool.insert("test-ingester-5".into(), IngesterServiceClient::mocked());
+        controller.failure_domains.insert(
+            "test-ingester-5".into(),
+            FailureDomain {
+                availability_zone: Some("zone-c".to_string()),
+                rack: None,
+            },
+        );
+        let shard_allocations = controller
+            .allocate_shards(4, &FnvHashSet::default(), &model)
+            .unwrap();
+        assert_eq!(shard_allocations.len(), 4);
+
+        for (leader_id, follower_ids) in shard_allocations {
+            assert_eq!(follower_ids.len(), 2);
+
+            let availability_zones: HashSet<&str> = std::iter::once(&leader_id)
+                .chain(&follower_ids)
+                .map(|node_id| {
+                    controller.failure_domains[node_id]
+                        .availability_zone
+                        .as_deref()
+                        .unwrap()
+                })
+                .collect();
+            assert_eq!(availability_zones.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_allocate_shards_spreads_replicas_across_racks() {
+        let node_id_shard_counts: HashMap<NodeId, usize> = [
+            ("test-ingester-1", 0),
+            ("test-ingester-2", 0),
+            ("test-ingester-3", 0),
+        ]
+        .into_iter()
+        .map(|(node_id, shard_count)| (NodeId::from(node_id), shard_count))
+        .collect();
+        let failure_domains: HashMap<NodeId, FailureDomain> = [
+            ("test-ingester-1", "rack-1"),
+            ("test-ingester-2", "rack-1"),
+            ("test-ingester-3", "rack-2"),
+        ]
+        .into_iter()
+        .map(|(node_id, rack)| {
+            let failure_domain = FailureDomain {
+                availability_zone: None,
+                rack: Some(rack.to_string()),
+            };
+            (NodeId::from(node_id), failure_domain)
+        })
+        .collect();
+
+        for _ in 0..10 {
+            let shard_allocations =
+                super::allocate_shards(&node_id_shard_counts, 1, 2, &failure_domains).unwrap();
+            let (leader_id, follower_ids) = &shard_allocations[0];
+            let racks: HashSet<&str> = std::iter::once(leader_id)
+                .chain(follower_ids)
+                .map(|node_id| failure_domains[*node_id].rack.as_deref().unwrap())
+                .collect();
+            assert_eq!(racks.len(), 2);
+        }
+    }
+
     #[tokio::test]
     async fn test_ingest_controller_init_shards() {
         let metastore = MetastoreServiceClient::mocked();
@@ -2149,6 +2436,7 @@ mod tests {
                     shard_state: ShardState::Open as i32,
                     leader_id: subrequest.leader_id.clone(),
                     follower_id: subrequest.follower_id.clone(),
+                    additional_follower_ids: subrequest.additional_follower_ids.clone(),
                     doc_mapping_uid: subrequest.doc_mapping_uid,
                     publish_position_inclusive: Some(Position::Beginning),
                     publish_token: None,
@@ -2209,6 +2497,7 @@ mod tests {
             shard_state: ShardState::Open,
             short_term_ingestion_rate: RateMibPerSec(1),
             long_term_ingestion_rate: RateMibPerSec(1),
+            num_wal_bytes: 0,
         }]);
         let local_shards_update = LocalShardsUpdate {
             leader_id: "test-ingester".into(),
@@ -2276,12 +2565,14 @@ mod tests {
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(1),
                 long_term_ingestion_rate: RateMibPerSec(1),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(2),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(1),
                 long_term_ingestion_rate: RateMibPerSec(1),
+                num_wal_bytes: 0,
             },
         ]);
         let local_shards_update = LocalShardsUpdate {
@@ -2301,12 +2592,14 @@ mod tests {
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(4),
                 long_term_ingestion_rate: RateMibPerSec(4),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(2),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(4),
                 long_term_ingestion_rate: RateMibPerSec(4),
+                num_wal_bytes: 0,
             },
         ]);
         let local_shards_update = LocalShardsUpdate {
@@ -2346,6 +2639,7 @@ mod tests {
                     shard_state: ShardState::Open as i32,
                     leader_id: subrequest.leader_id.clone(),
                     follower_id: subrequest.follower_id.clone(),
+                    additional_follower_ids: subrequest.additional_follower_ids.clone(),
                     doc_mapping_uid: subrequest.doc_mapping_uid,
                     publish_position_inclusive: Some(Position::Beginning),
                     publish_token: None,
@@ -2427,6 +2721,7 @@ mod tests {
             shard_state: ShardState::Open,
             short_term_ingestion_rate: RateMibPerSec(4),
             long_term_ingestion_rate: RateMibPerSec(4),
+            num_wal_bytes: 0,
         }]);
         let local_shards_update = LocalShardsUpdate {
             leader_id: "test-ingester".into(),
@@ -2517,7 +2812,13 @@ mod tests {
 
         // Test could not find leader.
         controller
-            .try_scale_up_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_up_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
 
@@ -2569,21 +2870,39 @@ mod tests {
 
         // Test failed to open shards.
         controller
-            .try_scale_up_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_up_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
         assert_eq!(model.all_shards().count(), 0);
 
         // Test failed to init shards.
         controller
-            .try_scale_up_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_up_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap_err();
         assert_eq!(model.all_shards().count(), 0);
 
         // Test successfully opened shard.
         controller
-            .try_scale_up_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_up_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
         assert_eq!(
@@ -2592,6 +2911,111 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_ingest_controller_evaluate_scaling_policies() {
+        let index_uid = IndexUid::for_test("test-index", 0);
+        let source_id: SourceId = INGEST_V2_SOURCE_ID.to_string();
+
+        let mut mock_metastore = MockMetastoreService::new();
+        let index_uid_clone = index_uid.clone();
+        mock_metastore
+            .expect_open_shards()
+            .once()
+            .returning(move |request| {
+                assert_eq!(request.subrequests.len(), 1);
+                assert_eq!(request.subrequests[0].index_uid(), &index_uid_clone);
+                assert_eq!(request.subrequests[0].leader_id, "test-ingester");
+
+                let subresponses = vec![metastore::OpenShardSubresponse {
+                    subrequest_id: 0,
+                    open_shard: Some(Shard {
+                        index_uid: Some(index_uid_clone.clone()),
+                        source_id: INGEST_V2_SOURCE_ID.to_string(),
+                        shard_id: Some(ShardId::from(2)),
+                        leader_id: "test-ingester".to_string(),
+                        shard_state: ShardState::Open as i32,
+                        ..Default::default()
+                    }),
+                }];
+                let response = metastore::OpenShardsResponse { subresponses };
+                Ok(response)
+            });
+        let metastore = MetastoreServiceClient::from_mock(mock_metastore);
+
+        let mut mock_ingester = MockIngesterService::new();
+        mock_ingester
+            .expect_init_shards()
+            .once()
+            .returning(move |request| {
+                let subrequest = &request.subrequests[0];
+                let successes = vec![InitShardSuccess {
+                    subrequest_id: subrequest.subrequest_id,
+                    shard: subrequest.shard.clone(),
+                }];
+                let response = InitShardsResponse {
+                    successes,
+                    failures: Vec::new(),
+                };
+                Ok(response)
+            });
+        let ingester_pool = IngesterPool::default();
+        let ingester = IngesterServiceClient::from_mock(mock_ingester);
+        ingester_pool.insert("test-ingester".into(), ingester);
+
+        let replication_factor = 1;
+        let mut controller = IngestController::new(
+            metastore,
+            ingester_pool,
+            replication_factor,
+            TEST_SHARD_THROUGHPUT_LIMIT_MIB,
+        );
+        let mut model = ControlPlaneModel::default();
+        let mut index_metadata =
+            IndexMetadata::for_test(&index_uid.index_id, "ram://indexes/test-index:0");
+        index_metadata
+            .index_config
+            .ingest_settings
+            .scaling_policy
+            .min_shards = 2;
+        model.add_index(index_metadata);
+
+        let source_config = SourceConfig::ingest_v2();
+        model.add_source(&index_uid, source_config).unwrap();
+
+        let shards = vec![Shard {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(ShardId::from(1)),
+            leader_id: "test-ingester".to_string(),
+            shard_state: ShardState::Open as i32,
+            ..Default::default()
+        }];
+        model.insert_shards(&index_uid, &source_id, shards);
+
+        let progress = Progress::default();
+
+        // The source is below its minimum number of shards even though no ingester reported any
+        // local shards update.
+        controller
+            .evaluate_scaling_policies(&mut model, &progress)
+            .await
+            .unwrap();
+        assert_eq!(
+            model.all_shards().filter(|shard| shard.is_open()).count(),
+            2
+        );
+
+        // The policy is satisfied, so the shards are left untouched.
+        controller
+            .evaluate_scaling_policies(&mut model, &progress)
+            .await
+            .unwrap();
+        assert_eq!(
+            model.all_shards().filter(|shard| shard.is_open()).count(),
+            2
+        );
+    }
+
     #[tokio::test]
     async fn test_ingest_controller_try_scale_down_shards() {
         let metastore = MetastoreServiceClient::mocked();
@@ -2621,7 +3045,13 @@ mod tests {
 
         // Test could not find a scale down candidate.
         controller
-            .try_scale_down_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_down_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
 
@@ -2637,7 +3067,13 @@ mod tests {
 
         // Test ingester is unavailable.
         controller
-            .try_scale_down_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_down_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
 
@@ -2677,14 +3113,26 @@ mod tests {
 
         // Test failed to close shard.
         controller
-            .try_scale_down_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_down_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
         assert!(model.all_shards().all(|shard| shard.is_open()));
 
         // Test successfully closed shard.
         controller
-            .try_scale_down_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_down_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
         assert!(model.all_shards().all(|shard| shard.is_closed()));
@@ -2701,7 +3149,13 @@ mod tests {
 
         // Test rate limited.
         controller
-            .try_scale_down_shards(source_uid.clone(), shard_stats, &mut model, &progress)
+            .try_scale_down_shards(
+                source_uid.clone(),
+                shard_stats,
+                "test",
+                &mut model,
+                &progress,
+            )
             .await
             .unwrap();
         assert!(model.all_shards().any(|shard| shard.is_open()));
@@ -2779,36 +3233,42 @@ mod tests {
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: quickwit_ingest::RateMibPerSec(1),
                 long_term_ingestion_rate: quickwit_ingest::RateMibPerSec(1),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(2),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: quickwit_ingest::RateMibPerSec(2),
                 long_term_ingestion_rate: quickwit_ingest::RateMibPerSec(2),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(3),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: quickwit_ingest::RateMibPerSec(3),
                 long_term_ingestion_rate: quickwit_ingest::RateMibPerSec(3),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(4),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: quickwit_ingest::RateMibPerSec(4),
                 long_term_ingestion_rate: quickwit_ingest::RateMibPerSec(4),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(5),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: quickwit_ingest::RateMibPerSec(5),
                 long_term_ingestion_rate: quickwit_ingest::RateMibPerSec(5),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(6),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: quickwit_ingest::RateMibPerSec(6),
                 long_term_ingestion_rate: quickwit_ingest::RateMibPerSec(6),
+                num_wal_bytes: 0,
             },
         ]);
         model.update_shards(&source_uid, &shard_infos);
@@ -3297,16 +3757,19 @@ mod tests {
     fn test_allocate_shards_aux_aux(
         shard_counts_map: &HashMap<NodeId, usize>,
         num_shards: usize,
-        replication_enabled: bool,
+        replication_factor: usize,
     ) {
-        let shard_allocations_opt =
-            super::allocate_shards(shard_counts_map, num_shards, replication_enabled);
+        let shard_allocations_opt = super::allocate_shards(
+            shard_counts_map,
+            num_shards,
+            replication_factor,
+            &HashMap::new(),
+        );
         if num_shards == 0 {
             assert_eq!(shard_allocations_opt, Some(Vec::new()));
             return;
         }
-        let num_nodes_required = if replication_enabled { 2 } else { 1 };
-        if shard_counts_map.len() < num_nodes_required {
+        if shard_counts_map.len() < replication_factor {
             assert!(shard_allocations_opt.is_none());
             return;
         }
@@ -3316,12 +3779,13 @@ mod tests {
         if num_shards == 0 {
             return;
         }
-        for (leader, follower_opt) in shard_allocations {
-            assert_eq!(follower_opt.is_some(), replication_enabled);
+        for (leader, followers) in shard_allocations {
+            assert_eq!(followers.len(), replication_factor - 1);
             *total_counts.entry(leader).or_default() += 1;
-            if let Some(follower) = follower_opt {
+            for (i, &follower) in followers.iter().enumerate() {
                 *total_counts.entry(follower).or_default() += 1;
                 assert_ne!(follower, leader);
+                assert!(!followers[..i].contains(&follower));
             }
         }
         for (shard, count) in shard_counts_map {
@@ -3335,7 +3799,7 @@ mod tests {
             .minmax()
             .into_option()
             .unwrap();
-        if !replication_enabled {
+        if replication_factor == 1 {
             // If replication is enabled, we can end up being forced to not spread shards as evenly
             // as we would wish. For instance, if there are only two nodes initially
             // unbalanced.
@@ -3363,8 +3827,8 @@ mod tests {
             shard_counts_map.insert(NodeId::from(shard), shard_count);
         }
         for i in 0..10 {
-            test_allocate_shards_aux_aux(&shard_counts_map, i, false);
-            test_allocate_shards_aux_aux(&shard_counts_map, i, true);
+            test_allocate_shards_aux_aux(&shard_counts_map, i, 1);
+            test_allocate_shards_aux_aux(&shard_counts_map, i, 2);
         }
     }
 
@@ -3404,7 +3868,8 @@ mod tests {
         let mut rng = rand::thread_rng();
         let node = pick_one(
             &mut shard_counts,
-            Some(NodeIdRef::from_str("node2")),
+            |node_id| node_id != NodeIdRef::from_str("node2"),
+            |_| true,
             &mut rng,
         )
         .unwrap();
@@ -3418,7 +3883,7 @@ mod tests {
             &shard_counts.get(&2).unwrap()[..],
             &[NodeIdRef::from_str("node1")]
         );
-        let node = pick_one(&mut shard_counts, None, &mut rng).unwrap();
+        let node = pick_one(&mut shard_counts, |_| true, |_| true, &mut rng).unwrap();
         assert_eq!(node.as_str(), "node2");
         assert_eq!(shard_counts.len(), 1);
         assert_eq!(
@@ -19,6 +19,7 @@
 
 pub(crate) mod ingest_controller;
 mod scaling_arbiter;
+mod scaling_policy;
 mod wait_handle;
 
 pub use ingest_controller::IngestController;
@@ -17,23 +17,26 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use crate::model::{ScalingMode, ShardStats};
+use std::collections::HashMap;
 
+use quickwit_config::{ScalingPolicyConfig, ScalingPolicyMode};
+use quickwit_proto::types::{IndexUid, SourceUid};
+
+use super::scaling_policy::{
+    BoundedScalingPolicy, PredictiveScalingPolicy, ReactiveScalingPolicy, ScalingContext,
+    ScalingDecision, ScalingPolicy, ScalingThresholds, ScheduledScalingPolicy,
+};
+
+struct SourceScalingPolicy {
+    config: ScalingPolicyConfig,
+    policy: Box<dyn ScalingPolicy>,
+}
+
+/// Holds the scaling policy of each source, instantiated from the scaling policy configured for
+/// its index.
 pub(crate) struct ScalingArbiter {
-    // Threshold in MiB/s below which we decrease the number of shards.
-    scale_down_shards_threshold_mib_per_sec: f32,
-
-    // Threshold in MiB/s above which we increase the number of shards.
-    // In order to make scaling up reactive, the decision is mostly taken by inspecting the short
-    // term threshold.
-    //
-    // However, this threshold is based on a very short window of time: 5s.
-    //
-    // In order to avoid having back and forth scaling up and down in response to temporary
-    // punctual spikes of a few MB, we also compute what would be the long term ingestion rate
-    // after scaling up, and double check that it is above the long term threshold.
-    scale_up_shards_short_term_threshold_mib_per_sec: f32,
-    scale_up_shards_long_term_threshold_mib_per_sec: f32,
+    thresholds: ScalingThresholds,
+    policies: HashMap<SourceUid, SourceScalingPolicy>,
 }
 
 impl ScalingArbiter {
@@ -41,88 +44,138 @@ impl ScalingArbiter {
         max_shard_throughput_mib_per_sec: f32,
     ) -> ScalingArbiter {
         ScalingArbiter {
-            scale_up_shards_short_term_threshold_mib_per_sec: max_shard_throughput_mib_per_sec
-                * 0.8f32,
-            scale_up_shards_long_term_threshold_mib_per_sec: max_shard_throughput_mib_per_sec
-                * 0.3f32,
-            scale_down_shards_threshold_mib_per_sec: max_shard_throughput_mib_per_sec * 0.2f32,
+            thresholds: ScalingThresholds::with_max_shard_ingestion_throughput_mib_per_sec(
+                max_shard_throughput_mib_per_sec,
+            ),
+            policies: HashMap::new(),
         }
     }
 
-    pub(crate) fn should_scale(&self, shard_stats: ShardStats) -> Option<ScalingMode> {
-        // We scale up based on the short term threshold to scale up more aggressively.
-        if shard_stats.avg_short_term_ingestion_rate
-            >= self.scale_up_shards_short_term_threshold_mib_per_sec
-        {
-            let long_term_ingestion_rate_after_scale_up = shard_stats.avg_long_term_ingestion_rate
-                * (shard_stats.num_open_shards as f32)
-                / (shard_stats.num_open_shards as f32 + 1.0f32);
-            if long_term_ingestion_rate_after_scale_up
-                >= self.scale_up_shards_long_term_threshold_mib_per_sec
-            {
-                return Some(ScalingMode::Up);
-            }
+    pub(crate) fn should_scale(
+        &mut self,
+        source_uid: &SourceUid,
+        scaling_policy_config: &ScalingPolicyConfig,
+        context: ScalingContext,
+    ) -> Option<ScalingDecision> {
+        let thresholds = self.thresholds;
+        let source_policy =
+            self.policies
+                .entry(source_uid.clone())
+                .or_insert_with(|| SourceScalingPolicy {
+                    config: scaling_policy_config.clone(),
+                    policy: build_scaling_policy(scaling_policy_config, thresholds),
+                });
+        // The policy is rebuilt, and its state lost, when the index config is updated.
+        if source_policy.config != *scaling_policy_config {
+            *source_policy = SourceScalingPolicy {
+                config: scaling_policy_config.clone(),
+                policy: build_scaling_policy(scaling_policy_config, thresholds),
+            };
         }
+        source_policy.policy.should_scale(context)
+    }
 
-        // On the other hand, we scale down based on the long term ingestion rate, to avoid
-        // scaling down just due to a very short drop in ingestion
-        if shard_stats.avg_long_term_ingestion_rate <= self.scale_down_shards_threshold_mib_per_sec
-            && shard_stats.num_open_shards > 1
-        {
-            return Some(ScalingMode::Down);
-        }
+    pub(crate) fn remove_index(&mut self, index_uid: &IndexUid) {
+        self.policies
+            .retain(|source_uid, _| source_uid.index_uid != *index_uid);
+    }
 
-        None
+    pub(crate) fn remove_source(&mut self, source_uid: &SourceUid) {
+        self.policies.remove(source_uid);
     }
 }
 
+fn build_scaling_policy(
+    scaling_policy_config: &ScalingPolicyConfig,
+    thresholds: ScalingThresholds,
+) -> Box<dyn ScalingPolicy> {
+    let mut scaling_policy: Box<dyn ScalingPolicy> = match scaling_policy_config.mode {
+        ScalingPolicyMode::Reactive => Box::new(ReactiveScalingPolicy::new(thresholds)),
+        ScalingPolicyMode::Predictive => Box::new(PredictiveScalingPolicy::new(thresholds)),
+    };
+    if !scaling_policy_config.schedules.is_empty() {
+        scaling_policy = Box::new(ScheduledScalingPolicy::new(
+            scaling_policy,
+            scaling_policy_config.schedules.clone(),
+        ));
+    }
+    if scaling_policy_config.min_shards > 1 || scaling_policy_config.max_shards.is_some() {
+        scaling_policy = Box::new(BoundedScalingPolicy::new(
+            scaling_policy,
+            scaling_policy_config.min_shards,
+            scaling_policy_config.max_shards,
+        ));
+    }
+    scaling_policy
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ScalingArbiter;
+    use std::time::Instant;
+
+    use quickwit_config::ScalingSchedule;
+
+    use super::*;
     use crate::model::{ScalingMode, ShardStats};
 
+    fn scaling_context(num_open_shards: usize, ingestion_rate: f32) -> ScalingContext {
+        ScalingContext {
+            shard_stats: ShardStats {
+                num_open_shards,
+                avg_short_term_ingestion_rate: ingestion_rate,
+                avg_long_term_ingestion_rate: ingestion_rate,
+            },
+            now: Instant::now(),
+            minute_of_day: 9 * 60,
+        }
+    }
+
     #[test]
     fn test_scaling_arbiter() {
-        let scaling_arbiter = ScalingArbiter::with_max_shard_ingestion_throughput_mib_per_sec(10.0);
-        assert_eq!(
-            scaling_arbiter.should_scale(ShardStats {
-                num_open_shards: 1,
-                avg_short_term_ingestion_rate: 5.0,
-                avg_long_term_ingestion_rate: 6.0,
-            }),
-            None
-        );
-        assert_eq!(
-            scaling_arbiter.should_scale(ShardStats {
-                num_open_shards: 1,
-                avg_short_term_ingestion_rate: 8.1,
-                avg_long_term_ingestion_rate: 8.1,
-            }),
-            Some(ScalingMode::Up)
-        );
-        assert_eq!(
-            scaling_arbiter.should_scale(ShardStats {
-                num_open_shards: 2,
-                avg_short_term_ingestion_rate: 3.0,
-                avg_long_term_ingestion_rate: 1.5,
-            }),
-            Some(ScalingMode::Down)
-        );
+        let mut scaling_arbiter =
+            ScalingArbiter::with_max_shard_ingestion_throughput_mib_per_sec(10.0);
+        let source_uid = SourceUid {
+            index_uid: IndexUid::for_test("test-index", 0),
+            source_id: "test-source".to_string(),
+        };
+
+        let default_config = ScalingPolicyConfig::default();
         assert_eq!(
-            scaling_arbiter.should_scale(ShardStats {
-                num_open_shards: 1,
-                avg_short_term_ingestion_rate: 3.0,
-                avg_long_term_ingestion_rate: 1.5,
-            }),
-            None,
+            scaling_arbiter
+                .should_scale(&source_uid, &default_config, scaling_context(1, 8.1))
+                .unwrap()
+                .scaling_mode,
+            ScalingMode::Up
         );
+        assert!(scaling_arbiter
+            .should_scale(&source_uid, &default_config, scaling_context(1, 0.0))
+            .is_none());
+
+        let scheduled_config = ScalingPolicyConfig {
+            schedules: vec![ScalingSchedule {
+                start: "08:00".to_string(),
+                end: "10:00".to_string(),
+                min_shards: Some(3),
+                max_shards: None,
+            }],
+            ..Default::default()
+        };
         assert_eq!(
-            scaling_arbiter.should_scale(ShardStats {
-                num_open_shards: 1,
-                avg_short_term_ingestion_rate: 8.0f32,
-                avg_long_term_ingestion_rate: 3.0f32,
-            }),
-            None,
+            scaling_arbiter
+                .should_scale(&source_uid, &scheduled_config, scaling_context(1, 0.0))
+                .unwrap()
+                .scaling_mode,
+            ScalingMode::Up
         );
+        let bounded_config = ScalingPolicyConfig {
+            max_shards: Some(1),
+            ..Default::default()
+        };
+        assert!(scaling_arbiter
+            .should_scale(&source_uid, &bounded_config, scaling_context(1, 8.1))
+            .is_none());
+
+        scaling_arbiter.remove_index(&source_uid.index_uid);
+        assert!(scaling_arbiter.policies.is_empty());
     }
 }
@@ -0,0 +1,602 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use quickwit_config::ScalingSchedule;
+
+use crate::model::{ScalingMode, ShardStats};
+
+/// Time constant of the exponentially weighted moving averages maintained by the predictive
+/// policy: an observation weighs ~63% after that period.
+const EWMA_TIME_CONSTANT: Duration = Duration::from_secs(60);
+
+/// How far ahead the predictive policy forecasts the ingestion rate.
+const FORECAST_HORIZON: Duration = Duration::from_secs(120);
+
+/// Inputs of a scaling decision.
+#[derive(Clone, Copy)]
+pub(crate) struct ScalingContext {
+    pub shard_stats: ShardStats,
+    /// Instant at which the shard stats were received.
+    pub now: Instant,
+    /// Current minute of the day (UTC), used to evaluate the time-of-day schedules.
+    pub minute_of_day: u32,
+}
+
+impl ScalingContext {
+    pub fn new(shard_stats: ShardStats) -> Self {
+        let now_utc = time::OffsetDateTime::now_utc();
+        let minute_of_day = now_utc.hour() as u32 * 60 + now_utc.minute() as u32;
+
+        Self {
+            shard_stats,
+            now: Instant::now(),
+            minute_of_day,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScalingDecision {
+    pub scaling_mode: ScalingMode,
+    /// Human-readable explanation of the decision.
+    pub reason: String,
+}
+
+impl ScalingDecision {
+    fn up(reason: String) -> Self {
+        Self {
+            scaling_mode: ScalingMode::Up,
+            reason,
+        }
+    }
+
+    fn down(reason: String) -> Self {
+        Self {
+            scaling_mode: ScalingMode::Down,
+            reason,
+        }
+    }
+}
+
+/// Decides when the number of shards of a source should be scaled up or down.
+///
+/// Policies are instantiated for each source and fed with every update of the source's shard
+/// stats, so they may be stateful.
+pub(crate) trait ScalingPolicy: Send {
+    fn should_scale(&mut self, context: ScalingContext) -> Option<ScalingDecision>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ScalingThresholds {
+    // Threshold in MiB/s below which we decrease the number of shards.
+    scale_down_shards_threshold_mib_per_sec: f32,
+
+    // Threshold in MiB/s above which we increase the number of shards.
+    // In order to make scaling up reactive, the decision is mostly taken by inspecting the short
+    // term threshold.
+    //
+    // However, this threshold is based on a very short window of time: 5s.
+    //
+    // In order to avoid having back and forth scaling up and down in response to temporary
+    // punctual spikes of a few MB, we also compute what would be the long term ingestion rate
+    // after scaling up, and double check that it is above the long term threshold.
+    scale_up_shards_short_term_threshold_mib_per_sec: f32,
+    scale_up_shards_long_term_threshold_mib_per_sec: f32,
+}
+
+impl ScalingThresholds {
+    pub fn with_max_shard_ingestion_throughput_mib_per_sec(
+        max_shard_throughput_mib_per_sec: f32,
+    ) -> Self {
+        Self {
+            scale_up_shards_short_term_threshold_mib_per_sec: max_shard_throughput_mib_per_sec
+                * 0.8f32,
+            scale_up_shards_long_term_threshold_mib_per_sec: max_shard_throughput_mib_per_sec
+                * 0.3f32,
+            scale_down_shards_threshold_mib_per_sec: max_shard_throughput_mib_per_sec * 0.2f32,
+        }
+    }
+}
+
+/// Scales based on the current ingestion rate of the shards.
+pub(crate) struct ReactiveScalingPolicy {
+    thresholds: ScalingThresholds,
+}
+
+impl ReactiveScalingPolicy {
+    pub fn new(thresholds: ScalingThresholds) -> Self {
+        Self { thresholds }
+    }
+
+    fn should_scale_up(&self, shard_stats: ShardStats) -> Option<ScalingDecision> {
+        // We scale up based on the short term threshold to scale up more aggressively.
+        if shard_stats.avg_short_term_ingestion_rate
+            < self
+                .thresholds
+                .scale_up_shards_short_term_threshold_mib_per_sec
+        {
+            return None;
+        }
+        let long_term_ingestion_rate_after_scale_up = shard_stats.avg_long_term_ingestion_rate
+            * (shard_stats.num_open_shards as f32)
+            / (shard_stats.num_open_shards as f32 + 1.0f32);
+
+        if long_term_ingestion_rate_after_scale_up
+            < self
+                .thresholds
+                .scale_up_shards_long_term_threshold_mib_per_sec
+        {
+            return None;
+        }
+        let reason = format!(
+            "short term ingestion rate of {:.2} MiB/s per shard exceeds {:.2} MiB/s",
+            shard_stats.avg_short_term_ingestion_rate,
+            self.thresholds
+                .scale_up_shards_short_term_threshold_mib_per_sec
+        );
+        Some(ScalingDecision::up(reason))
+    }
+}
+
+impl ScalingPolicy for ReactiveScalingPolicy {
+    fn should_scale(&mut self, context: ScalingContext) -> Option<ScalingDecision> {
+        let shard_stats = context.shard_stats;
+
+        if let Some(scaling_decision) = self.should_scale_up(shard_stats) {
+            return Some(scaling_decision);
+        }
+        // On the other hand, we scale down based on the long term ingestion rate, to avoid
+        // scaling down just due to a very short drop in ingestion
+        if shard_stats.avg_long_term_ingestion_rate
+            <= self.thresholds.scale_down_shards_threshold_mib_per_sec
+            && shard_stats.num_open_shards > 1
+        {
+            let reason = format!(
+                "long term ingestion rate of {:.2} MiB/s per shard is below {:.2} MiB/s",
+                shard_stats.avg_long_term_ingestion_rate,
+                self.thresholds.scale_down_shards_threshold_mib_per_sec
+            );
+            return Some(ScalingDecision::down(reason));
+        }
+        None
+    }
+}
+
+/// Exponentially weighted moving average of the total ingestion rate of a source and of its
+/// trend (Holt's linear method), updated at irregular intervals.
+#[derive(Debug, Clone, Copy)]
+struct IngestionRateEwma {
+    // Smoothed total ingestion rate in MiB/s.
+    level: f32,
+    // Smoothed variation of the total ingestion rate in MiB/s per second.
+    trend: f32,
+    updated_at: Instant,
+}
+
+impl IngestionRateEwma {
+    fn new(ingestion_rate: f32, now: Instant) -> Self {
+        Self {
+            level: ingestion_rate,
+            trend: 0.0,
+            updated_at: now,
+        }
+    }
+
+    fn update(&mut self, ingestion_rate: f32, now: Instant) {
+        let elapsed_secs = now.saturating_duration_since(self.updated_at).as_secs_f32();
+
+        if elapsed_secs <= 0.0 {
+            return;
+        }
+        let alpha = 1.0 - (-elapsed_secs / EWMA_TIME_CONSTANT.as_secs_f32()).exp();
+        let previous_level = self.level;
+        self.level += alpha * (ingestion_rate - self.level);
+
+        let observed_trend = (self.level - previous_level) / elapsed_secs;
+        self.trend += alpha * (observed_trend - self.trend);
+        self.updated_at = now;
+    }
+
+    fn forecast(&self, horizon: Duration) -> f32 {
+        (self.level + self.trend * horizon.as_secs_f32()).max(0.0)
+    }
+}
+
+/// Scales based on a forecast of the ingestion rate derived from its moving average and trend.
+/// It scales up ahead of ramp-ups and only scales down once the forecast shows that the remaining
+/// shards would comfortably absorb the load, which avoids churn on recurring traffic patterns.
+/// Sudden spikes are still handled like the reactive policy does.
+pub(crate) struct PredictiveScalingPolicy {
+    reactive_policy: ReactiveScalingPolicy,
+    ewma_opt: Option<IngestionRateEwma>,
+}
+
+impl PredictiveScalingPolicy {
+    pub fn new(thresholds: ScalingThresholds) -> Self {
+        Self {
+            reactive_policy: ReactiveScalingPolicy::new(thresholds),
+            ewma_opt: None,
+        }
+    }
+}
+
+impl ScalingPolicy for PredictiveScalingPolicy {
+    fn should_scale(&mut self, context: ScalingContext) -> Option<ScalingDecision> {
+        let shard_stats = context.shard_stats;
+
+        if shard_stats.num_open_shards == 0 {
+            return None;
+        }
+        let num_open_shards = shard_stats.num_open_shards as f32;
+        let total_ingestion_rate = shard_stats.avg_short_term_ingestion_rate * num_open_shards;
+
+        let ewma = self
+            .ewma_opt
+            .get_or_insert_with(|| IngestionRateEwma::new(total_ingestion_rate, context.now));
+        ewma.update(total_ingestion_rate, context.now);
+
+        let forecast_ingestion_rate = ewma.forecast(FORECAST_HORIZON);
+        let trend = ewma.trend;
+        let thresholds = self.reactive_policy.thresholds;
+
+        if let Some(scaling_decision) = self.reactive_policy.should_scale_up(shard_stats) {
+            return Some(scaling_decision);
+        }
+        if trend > 0.0
+            && forecast_ingestion_rate / num_open_shards
+                >= thresholds.scale_up_shards_short_term_threshold_mib_per_sec
+        {
+            let reason = format!(
+                "forecast ingestion rate of {:.2} MiB/s per shard in {}s exceeds {:.2} MiB/s",
+                forecast_ingestion_rate / num_open_shards,
+                FORECAST_HORIZON.as_secs(),
+                thresholds.scale_up_shards_short_term_threshold_mib_per_sec
+            );
+            return Some(ScalingDecision::up(reason));
+        }
+        if shard_stats.num_open_shards > 1
+            && trend <= 0.0
+            && shard_stats.avg_long_term_ingestion_rate
+                <= thresholds.scale_down_shards_threshold_mib_per_sec
+        {
+            let forecast_ingestion_rate_after_scale_down =
+                forecast_ingestion_rate.max(ewma.level) / (num_open_shards - 1.0);
+
+            if forecast_ingestion_rate_after_scale_down
+                < thresholds.scale_up_shards_long_term_threshold_mib_per_sec
+            {
+                let reason = format!(
+                    "forecast ingestion rate of {:.2} MiB/s per shard after scaling down is below \
+                     {:.2} MiB/s",
+                    forecast_ingestion_rate_after_scale_down,
+                    thresholds.scale_up_shards_long_term_threshold_mib_per_sec
+                );
+                return Some(ScalingDecision::down(reason));
+            }
+        }
+        None
+    }
+}
+
+/// Keeps the number of shards within `[min_shards, max_shards]`, overriding the decisions of the
+/// underlying policy when they would cross a bound.
+pub(crate) struct BoundedScalingPolicy {
+    inner: Box<dyn ScalingPolicy>,
+    min_shards: usize,
+    max_shards_opt: Option<usize>,
+}
+
+impl BoundedScalingPolicy {
+    pub fn new(
+        inner: Box<dyn ScalingPolicy>,
+        min_shards: usize,
+        max_shards_opt: Option<usize>,
+    ) -> Self {
+        Self {
+            inner,
+            min_shards,
+            max_shards_opt,
+        }
+    }
+}
+
+impl ScalingPolicy for BoundedScalingPolicy {
+    fn should_scale(&mut self, context: ScalingContext) -> Option<ScalingDecision> {
+        let inner_decision_opt = self.inner.should_scale(context);
+        apply_shard_bounds(
+            inner_decision_opt,
+            context.shard_stats.num_open_shards,
+            self.min_shards,
+            self.max_shards_opt,
+            "",
+        )
+    }
+}
+
+/// Applies the shard bounds of the first time-of-day schedule containing the current time, or
+/// defers to the underlying policy outside of the schedules.
+pub(crate) struct ScheduledScalingPolicy {
+    inner: Box<dyn ScalingPolicy>,
+    schedules: Vec<ScalingSchedule>,
+}
+
+impl ScheduledScalingPolicy {
+    pub fn new(inner: Box<dyn ScalingPolicy>, schedules: Vec<ScalingSchedule>) -> Self {
+        Self { inner, schedules }
+    }
+}
+
+impl ScalingPolicy for ScheduledScalingPolicy {
+    fn should_scale(&mut self, context: ScalingContext) -> Option<ScalingDecision> {
+        // The underlying policy is always evaluated so that it keeps its state up to date.
+        let inner_decision_opt = self.inner.should_scale(context);
+
+        let Some(schedule) = self
+            .schedules
+            .iter()
+            .find(|schedule| schedule.contains(context.minute_of_day))
+        else {
+            return inner_decision_opt;
+        };
+        let bounds_label = format!(" for schedule `{}-{}`", schedule.start, schedule.end);
+        apply_shard_bounds(
+            inner_decision_opt,
+            context.shard_stats.num_open_shards,
+            schedule.min_shards.unwrap_or(1),
+            schedule.max_shards,
+            &bounds_label,
+        )
+    }
+}
+
+fn apply_shard_bounds(
+    decision_opt: Option<ScalingDecision>,
+    num_open_shards: usize,
+    min_shards: usize,
+    max_shards_opt: Option<usize>,
+    bounds_label: &str,
+) -> Option<ScalingDecision> {
+    if num_open_shards < min_shards {
+        let reason = format!(
+            "number of open shards ({num_open_shards}) is below the minimum{bounds_label} \
+             ({min_shards})"
+        );
+        return Some(ScalingDecision::up(reason));
+    }
+    if let Some(max_shards) = max_shards_opt {
+        if num_open_shards > max_shards {
+            let reason = format!(
+                "number of open shards ({num_open_shards}) is above the maximum{bounds_label} \
+                 ({max_shards})"
+            );
+            return Some(ScalingDecision::down(reason));
+        }
+    }
+    let decision = decision_opt?;
+
+    match decision.scaling_mode {
+        ScalingMode::Up
+            if max_shards_opt.is_some_and(|max_shards| num_open_shards >= max_shards) =>
+        {
+            None
+        }
+        ScalingMode::Down if num_open_shards <= min_shards => None,
+        _ => Some(decision),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scaling_context(
+        num_open_shards: usize,
+        avg_short_term_ingestion_rate: f32,
+        avg_long_term_ingestion_rate: f32,
+    ) -> ScalingContext {
+        ScalingContext {
+            shard_stats: ShardStats {
+                num_open_shards,
+                avg_short_term_ingestion_rate,
+                avg_long_term_ingestion_rate,
+            },
+            now: Instant::now(),
+            minute_of_day: 0,
+        }
+    }
+
+    fn scaling_mode(scaling_decision_opt: Option<ScalingDecision>) -> Option<ScalingMode> {
+        scaling_decision_opt.map(|scaling_decision| scaling_decision.scaling_mode)
+    }
+
+    fn thresholds() -> ScalingThresholds {
+        ScalingThresholds::with_max_shard_ingestion_throughput_mib_per_sec(10.0)
+    }
+
+    #[test]
+    fn test_reactive_scaling_policy() {
+        let mut scaling_policy = ReactiveScalingPolicy::new(thresholds());
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(1, 5.0, 6.0))),
+            None
+        );
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(1, 8.1, 8.1))),
+            Some(ScalingMode::Up)
+        );
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(2, 3.0, 1.5))),
+            Some(ScalingMode::Down)
+        );
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(1, 3.0, 1.5))),
+            None,
+        );
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(1, 8.0f32, 3.0f32))),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_ingestion_rate_ewma() {
+        let start = Instant::now();
+        let mut ewma = IngestionRateEwma::new(10.0, start);
+        assert_eq!(ewma.forecast(FORECAST_HORIZON), 10.0);
+
+        ewma.update(10.0, start + Duration::from_secs(5));
+        assert_eq!(ewma.level, 10.0);
+        assert_eq!(ewma.trend, 0.0);
+
+        for i in 1..=24 {
+            ewma.update(10.0 + i as f32, start + Duration::from_secs(5 + 5 * i));
+        }
+        assert!(ewma.level > 10.0 && ewma.level < 34.0);
+        assert!(ewma.trend > 0.0);
+        assert!(ewma.forecast(FORECAST_HORIZON) > ewma.level);
+    }
+
+    #[test]
+    fn test_predictive_scaling_policy_scales_up_ahead_of_ramp_up() {
+        let mut scaling_policy = PredictiveScalingPolicy::new(thresholds());
+        let start = Instant::now();
+        let mut reactive_scaled_up = false;
+
+        // The ingestion rate of the only shard ramps up by 0.2 MiB/s every 5 seconds.
+        for i in 0..40 {
+            let ingestion_rate = 2.0 + 0.2 * i as f32;
+            let mut context = scaling_context(1, ingestion_rate, ingestion_rate);
+            context.now = start + Duration::from_secs(5 * i);
+
+            let predictive_mode = scaling_mode(scaling_policy.should_scale(context));
+            let reactive_mode =
+                scaling_mode(ReactiveScalingPolicy::new(thresholds()).should_scale(context));
+
+            if predictive_mode == Some(ScalingMode::Up) {
+                assert!(!reactive_scaled_up);
+                assert!(ingestion_rate < 8.0);
+                return;
+            }
+            reactive_scaled_up |= reactive_mode == Some(ScalingMode::Up);
+        }
+        panic!("predictive scaling policy should have scaled up");
+    }
+
+    #[test]
+    fn test_predictive_scaling_policy_holds_shards() {
+        let mut scaling_policy = PredictiveScalingPolicy::new(thresholds());
+        let start = Instant::now();
+
+        // Two shards ingesting 1.6 MiB/s each: the reactive policy scales down, but the remaining
+        // shard would then exceed the long term scale up threshold.
+        let mut context = scaling_context(2, 1.6, 1.6);
+        assert_eq!(
+            scaling_mode(ReactiveScalingPolicy::new(thresholds()).should_scale(context)),
+            Some(ScalingMode::Down)
+        );
+        for i in 0..10 {
+            context.now = start + Duration::from_secs(5 * i);
+            assert_eq!(scaling_mode(scaling_policy.should_scale(context)), None);
+        }
+        // Once the ingestion rate durably decreases, the predictive policy scales down too.
+        let mut context = scaling_context(2, 0.5, 0.5);
+
+        for i in 10..60 {
+            context.now = start + Duration::from_secs(5 * i);
+            if scaling_mode(scaling_policy.should_scale(context)) == Some(ScalingMode::Down) {
+                return;
+            }
+        }
+        panic!("predictive scaling policy should have scaled down");
+    }
+
+    #[test]
+    fn test_bounded_scaling_policy() {
+        let reactive_policy = Box::new(ReactiveScalingPolicy::new(thresholds()));
+        let mut scaling_policy = BoundedScalingPolicy::new(reactive_policy, 2, Some(4));
+
+        let scaling_decision = scaling_policy
+            .should_scale(scaling_context(1, 0.0, 0.0))
+            .unwrap();
+        assert_eq!(scaling_decision.scaling_mode, ScalingMode::Up);
+        assert_eq!(
+            scaling_decision.reason,
+            "number of open shards (1) is below the minimum (2)"
+        );
+        // Idle sources are not scaled down below the floor.
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(2, 0.0, 0.0))),
+            None
+        );
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(3, 0.0, 0.0))),
+            Some(ScalingMode::Down)
+        );
+        // Busy sources are not scaled up above the ceiling.
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(3, 9.0, 9.0))),
+            Some(ScalingMode::Up)
+        );
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(4, 9.0, 9.0))),
+            None
+        );
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(scaling_context(5, 9.0, 9.0))),
+            Some(ScalingMode::Down)
+        );
+    }
+
+    #[test]
+    fn test_scheduled_scaling_policy() {
+        let reactive_policy = Box::new(ReactiveScalingPolicy::new(thresholds()));
+        let schedules = vec![ScalingSchedule {
+            start: "08:00".to_string(),
+            end: "11:00".to_string(),
+            min_shards: Some(4),
+            max_shards: None,
+        }];
+        let mut scaling_policy = ScheduledScalingPolicy::new(reactive_policy, schedules);
+
+        let mut context = scaling_context(2, 0.0, 0.0);
+        context.minute_of_day = 7 * 60;
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(context)),
+            Some(ScalingMode::Down)
+        );
+        context.minute_of_day = 8 * 60;
+        let scaling_decision = scaling_policy.should_scale(context).unwrap();
+        assert_eq!(scaling_decision.scaling_mode, ScalingMode::Up);
+        assert_eq!(
+            scaling_decision.reason,
+            "number of open shards (2) is below the minimum for schedule `08:00-11:00` (4)"
+        );
+        let mut context = scaling_context(4, 0.0, 0.0);
+        context.minute_of_day = 10 * 60;
+        assert_eq!(scaling_mode(scaling_policy.should_scale(context)), None);
+
+        context.minute_of_day = 11 * 60;
+        assert_eq!(
+            scaling_mode(scaling_policy.should_scale(context)),
+            Some(ScalingMode::Down)
+        );
+    }
+}
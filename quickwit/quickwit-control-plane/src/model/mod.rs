@@ -362,6 +362,16 @@ impl ControlPlaneModel {
         self.shard_table.update_shards(source_uid, shard_infos)
     }
 
+    /// Returns the number of open shards of the source and their average ingestion rates.
+    pub fn shard_stats(&self, source_uid: &SourceUid) -> ShardStats {
+        self.shard_table.shard_stats(source_uid)
+    }
+
+    /// Returns the UIDs of the sources that have shards, i.e. the ingest V2 sources.
+    pub(crate) fn shard_source_uids(&self) -> impl Iterator<Item = &SourceUid> + '_ {
+        self.shard_table.source_uids()
+    }
+
     /// Sets the state of the shards identified by their index UID, source ID, and shard IDs to
     /// `Closed`.
     pub fn close_shards(&mut self, source_uid: &SourceUid, shard_ids: &[ShardId]) -> Vec<ShardId> {
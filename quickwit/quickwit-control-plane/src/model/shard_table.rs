@@ -260,6 +260,10 @@ impl ShardTable {
         self.table_entries.len()
     }
 
+    pub(crate) fn source_uids(&self) -> impl Iterator<Item = &SourceUid> + '_ {
+        self.table_entries.keys()
+    }
+
     #[cfg(test)]
     pub fn num_shards(&self) -> usize {
         self.table_entries
@@ -446,10 +450,6 @@ impl ShardTable {
         source_uid: &SourceUid,
         shard_infos: &ShardInfos,
     ) -> ShardStats {
-        let mut num_open_shards = 0;
-        let mut short_term_ingestion_rate_sum = RateMibPerSec::default();
-        let mut long_term_ingestion_rate_sum = RateMibPerSec::default();
-
         if let Some(table_entry) = self.table_entries.get_mut(source_uid) {
             for shard_info in shard_infos {
                 let ShardInfo {
@@ -457,6 +457,7 @@ impl ShardTable {
                     shard_state,
                     short_term_ingestion_rate,
                     long_term_ingestion_rate,
+                    num_wal_bytes: _,
                 } = shard_info;
 
                 if let Some(shard_entry) = table_entry.shard_entries.get_mut(shard_id) {
@@ -469,6 +470,17 @@ impl ShardTable {
                     }
                 }
             }
+        }
+        self.shard_stats(source_uid)
+    }
+
+    /// Returns the number of open shards of the source and their average ingestion rates.
+    pub fn shard_stats(&self, source_uid: &SourceUid) -> ShardStats {
+        let mut num_open_shards = 0;
+        let mut short_term_ingestion_rate_sum = RateMibPerSec::default();
+        let mut long_term_ingestion_rate_sum = RateMibPerSec::default();
+
+        if let Some(table_entry) = self.table_entries.get(source_uid) {
             for shard_entry in table_entry.shard_entries.values() {
                 if shard_entry.is_open() {
                     num_open_shards += 1;
@@ -871,30 +883,35 @@ mod tests {
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(1),
                 long_term_ingestion_rate: RateMibPerSec(1),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(2),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(2),
                 long_term_ingestion_rate: RateMibPerSec(2),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(3),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(3),
                 long_term_ingestion_rate: RateMibPerSec(3),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(4),
                 shard_state: ShardState::Closed,
                 short_term_ingestion_rate: RateMibPerSec(4),
                 long_term_ingestion_rate: RateMibPerSec(4),
+                num_wal_bytes: 0,
             },
             ShardInfo {
                 shard_id: ShardId::from(5),
                 shard_state: ShardState::Open,
                 short_term_ingestion_rate: RateMibPerSec(5),
                 long_term_ingestion_rate: RateMibPerSec(5),
+                num_wal_bytes: 0,
             },
         ]);
         let shard_stats = shard_table.update_shards(&source_uid, &shard_infos);
@@ -59,6 +59,7 @@ fn index_metadata_for_test(index_id: &str, source_id: &str, num_pipelines: usize
         }),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_queue_config: None,
     };
     index_metadata
         .sources
@@ -17,15 +17,19 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::num::NonZeroU32;
 
 use anyhow::{bail, Context};
 use fnv::FnvHashSet;
+use quickwit_common::shared_consts::NESTED_PATH_FIELD_NAME;
 use quickwit_proto::types::DocMappingUid;
-use quickwit_query::create_default_quickwit_tokenizer_manager;
-use quickwit_query::query_ast::QueryAst;
+use quickwit_query::query_ast::{
+    contains_knn_query, BoolQuery, GeoBoundingBoxQuery, GeoDistanceQuery, KnnQuery, NestedQuery,
+    QueryAst, QueryAstTransformer, QueryAstVisitor, TermSetQuery, WithNestedDocsQuery,
+};
 use quickwit_query::tokenizers::TokenizerManager;
+use quickwit_query::{create_default_quickwit_tokenizer_manager, InvalidQuery};
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value as JsonValue};
 use serde_json_borrow::Map as BorrowedJsonMap;
@@ -100,6 +104,117 @@ pub struct DocMapper {
     tokenizer_entries: Vec<TokenizerEntry>,
     /// Tokenizer manager.
     tokenizer_manager: TokenizerManager,
+    /// Paths of the nested fields, whose objects are indexed as child documents.
+    nested_paths: Vec<String>,
+}
+
+/// Fills the similarity of the k-NN queries from the mapping of their dense vector field.
+struct KnnSimilarityResolver<'a> {
+    field_mappings: &'a MappingNode,
+    with_validation: bool,
+}
+
+impl QueryAstTransformer for KnnSimilarityResolver<'_> {
+    type Err = InvalidQuery;
+
+    fn transform_knn(&mut self, mut knn_query: KnnQuery) -> Result<Option<QueryAst>, InvalidQuery> {
+        if let Some(filter) = knn_query.filter.take() {
+            knn_query.filter = self.transform(*filter)?.map(Box::new);
+        }
+        let Some(FieldMappingType::DenseVector(dense_vector_options)) = self
+            .field_mappings
+            .find_field_mapping_type(&knn_query.field)
+        else {
+            // The query will be rejected when building the tantivy query.
+            return Ok(Some(knn_query.into()));
+        };
+        if dense_vector_options.dims == knn_query.query_vector.len() {
+            knn_query.similarity = Some(dense_vector_options.similarity);
+        } else if self.with_validation {
+            return Err(InvalidQuery::SchemaError(format!(
+                "k-NN query vector has {} dimensions but field `{}` has {}",
+                knn_query.query_vector.len(),
+                knn_query.field,
+                dense_vector_options.dims
+            )));
+        }
+        Ok(Some(knn_query.into()))
+    }
+}
+
+/// Rejects the nested queries targeting a field that is not a nested field.
+struct NestedPathValidator<'a> {
+    nested_paths: &'a [String],
+}
+
+impl<'a> QueryAstVisitor<'a> for NestedPathValidator<'_> {
+    type Err = InvalidQuery;
+
+    fn visit_nested(&mut self, nested_query: &'a NestedQuery) -> Result<(), InvalidQuery> {
+        if !self.nested_paths.contains(&nested_query.path) {
+            return Err(InvalidQuery::SchemaError(format!(
+                "field `{}` is not a nested field",
+                nested_query.path
+            )));
+        }
+        self.visit(&nested_query.query)
+    }
+}
+
+/// Rejects the geo queries targeting a field that is not a `geo_point` field.
+///
+/// Geo points are indexed as `u64` fast fields, so the tantivy schema alone cannot tell them
+/// apart from regular `u64` fields.
+struct GeoPointFieldValidator<'a> {
+    field_mappings: &'a MappingNode,
+}
+
+impl GeoPointFieldValidator<'_> {
+    fn check_geo_point_field(&self, field_name: &str) -> Result<(), InvalidQuery> {
+        if is_geo_point_field(self.field_mappings, field_name) {
+            return Ok(());
+        }
+        Err(InvalidQuery::SchemaError(format!(
+            "field `{field_name}` is not a geo_point field"
+        )))
+    }
+}
+
+impl<'a> QueryAstVisitor<'a> for GeoPointFieldValidator<'_> {
+    type Err = InvalidQuery;
+
+    fn visit_geo_bounding_box(
+        &mut self,
+        geo_bounding_box_query: &'a GeoBoundingBoxQuery,
+    ) -> Result<(), InvalidQuery> {
+        self.check_geo_point_field(&geo_bounding_box_query.field)
+    }
+
+    fn visit_geo_distance(
+        &mut self,
+        geo_distance_query: &'a GeoDistanceQuery,
+    ) -> Result<(), InvalidQuery> {
+        self.check_geo_point_field(&geo_distance_query.field)
+    }
+}
+
+fn is_geo_point_field(field_mappings: &MappingNode, field_name: &str) -> bool {
+    matches!(
+        field_mappings.find_field_mapping_type(field_name),
+        Some(FieldMappingType::GeoPoint(_))
+    )
+}
+
+/// Returns the nested field containing the field `field_name`, if any.
+fn find_enclosing_nested_path<'a>(nested_paths: &'a [String], field_name: &str) -> Option<&'a str> {
+    nested_paths
+        .iter()
+        .find(|nested_path| {
+            field_name
+                .strip_prefix(nested_path.as_str())
+                .is_some_and(|suffix| suffix.is_empty() || suffix.starts_with('.'))
+        })
+        .map(String::as_str)
 }
 
 fn validate_timestamp_field(
@@ -197,8 +312,19 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
         if !concatenate_dynamic_fields.is_empty() && dynamic_field.is_none() {
             bail!("concatenate field has `include_dynamic_fields` set, but index isn't dynamic");
         }
+        let nested_paths = field_mappings.nested_paths();
+        let validate_not_nested = |field_kind: &str, field_name: &str| -> anyhow::Result<()> {
+            if let Some(nested_path) = find_enclosing_nested_path(&nested_paths, field_name) {
+                bail!(
+                    "{field_kind} field `{field_name}` cannot be part of nested field \
+                     `{nested_path}`"
+                );
+            }
+            Ok(())
+        };
         let timestamp_field_path = if let Some(timestamp_field_name) = &doc_mapping.timestamp_field
         {
+            validate_not_nested("timestamp", timestamp_field_name)?;
             validate_timestamp_field(timestamp_field_name, &field_mappings)?;
             Some(build_field_path_from_str(timestamp_field_name))
         } else {
@@ -254,6 +380,7 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
                     default_search_field_name
                 )
             }
+            validate_not_nested("default search", default_search_field_name)?;
             let (default_search_field, _json_path) = schema
                 .find_field_with_default(default_search_field_name, dynamic_field)
                 .with_context(|| {
@@ -267,6 +394,7 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
 
         // Resolve tag fields
         for tag_field_name in &doc_mapping.tag_fields {
+            validate_not_nested("tag", tag_field_name)?;
             validate_tag(tag_field_name, &schema)?;
         }
 
@@ -279,6 +407,7 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
         let mut tag_field_names = doc_mapping.tag_fields;
 
         for partition_key in partition_key.field_names() {
+            validate_not_nested("partition key", &partition_key)?;
             if validate_tag(&partition_key, &schema).is_ok() {
                 tag_field_names.insert(partition_key);
             }
@@ -301,6 +430,7 @@ impl TryFrom<DocMapperBuilder> for DocMapper {
             mode: doc_mapping.mode,
             tokenizer_entries: doc_mapping.tokenizers,
             tokenizer_manager,
+            nested_paths,
         })
     }
 }
@@ -497,16 +627,35 @@ impl DocMapper {
 
     /// Transforms a JSON object into a tantivy [`Document`] according to the rules
     /// defined for the `DocMapper`.
+    ///
+    /// The child documents extracted from the nested fields are dropped: use
+    /// [`DocMapper::docs_from_json_obj`] to index documents.
     pub fn doc_from_json_obj(
         &self,
         json_obj: JsonObject,
         document_len: u64,
     ) -> Result<(Partition, Document), DocParsingError> {
+        let (partition, document, _nested_docs) =
+            self.docs_from_json_obj(json_obj, document_len)?;
+        Ok((partition, document))
+    }
+
+    /// Transforms a JSON object into a tantivy [`Document`] and the child documents extracted
+    /// from its nested fields.
+    ///
+    /// The child documents must be indexed right before their parent document, in the same
+    /// segment.
+    pub fn docs_from_json_obj(
+        &self,
+        json_obj: JsonObject,
+        document_len: u64,
+    ) -> Result<(Partition, Document, Vec<Document>), DocParsingError> {
         let partition: Partition = self.partition_key.eval_hash(&json_obj);
 
         let mut dynamic_json_obj = serde_json::Map::default();
         let mut field_path = Vec::new();
         let mut document = Document::default();
+        let mut nested_docs = Vec::new();
 
         if let Some(source_field) = self.source_field {
             document.add_object(
@@ -524,6 +673,7 @@ impl DocMapper {
             json_obj,
             mode,
             &mut document,
+            &mut nested_docs,
             &mut field_path,
             &mut dynamic_json_obj,
         )?;
@@ -556,13 +706,15 @@ impl DocMapper {
         }
 
         if self.index_field_presence {
-            let field_presence_hashes: FnvHashSet<u64> =
-                populate_field_presence(&document, &self.schema);
-            for field_presence_hash in field_presence_hashes {
-                document.add_field_value(FIELD_PRESENCE_FIELD, &field_presence_hash);
+            for document in nested_docs.iter_mut().chain([&mut document]) {
+                let field_presence_hashes: FnvHashSet<u64> =
+                    populate_field_presence(document, &self.schema);
+                for field_presence_hash in field_presence_hashes {
+                    document.add_field_value(FIELD_PRESENCE_FIELD, &field_presence_hash);
+                }
             }
         }
-        Ok((partition, document))
+        Ok((partition, document, nested_docs))
     }
 
     /// Converts a tantivy named Document to the json format.
@@ -644,8 +796,47 @@ impl DocMapper {
         query_ast: &QueryAst,
         with_validation: bool,
     ) -> Result<(Box<dyn Query>, WarmupInfo), QueryParserError> {
+        let mut resolved_query_ast_opt = if contains_knn_query(query_ast) {
+            let mut knn_similarity_resolver = KnnSimilarityResolver {
+                field_mappings: &self.field_mappings,
+                with_validation,
+            };
+            knn_similarity_resolver.transform(query_ast.clone())?
+        } else {
+            None
+        };
+        if with_validation {
+            let mut geo_point_field_validator = GeoPointFieldValidator {
+                field_mappings: &self.field_mappings,
+            };
+            geo_point_field_validator.visit(query_ast)?;
+        }
+        if !self.nested_paths.is_empty() {
+            if with_validation {
+                let mut nested_path_validator = NestedPathValidator {
+                    nested_paths: &self.nested_paths,
+                };
+                nested_path_validator.visit(query_ast)?;
+            }
+            // The child documents extracted from the nested fields must never be returned.
+            let root_query_ast = resolved_query_ast_opt.unwrap_or_else(|| query_ast.clone());
+            let nested_path_term_set = TermSetQuery {
+                terms_per_field: HashMap::from([(
+                    NESTED_PATH_FIELD_NAME.to_string(),
+                    self.nested_paths.iter().cloned().collect(),
+                )]),
+            };
+            resolved_query_ast_opt = Some(
+                BoolQuery {
+                    must: vec![root_query_ast],
+                    must_not: vec![nested_path_term_set.into()],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
         build_query(
-            query_ast,
+            resolved_query_ast_opt.as_ref().unwrap_or(query_ast),
             split_schema,
             self.tokenizer_manager(),
             &self.default_search_field_names[..],
@@ -653,6 +844,22 @@ impl DocMapper {
         )
     }
 
+    /// Builds the query deleting the documents matching `query_ast`.
+    ///
+    /// Unlike [`DocMapper::query`], the query also matches the child documents extracted from
+    /// the nested fields of the matching documents.
+    pub fn delete_query(
+        &self,
+        split_schema: Schema,
+        query_ast: &QueryAst,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let (query, _) = self.query(split_schema, query_ast, false)?;
+        if self.nested_paths.is_empty() {
+            return Ok(query);
+        }
+        Ok(Box::new(WithNestedDocsQuery::new(query)))
+    }
+
     /// Returns the list of search fields to search into, when no field is specified.
     /// (See `UserInputQuery`).
     pub fn default_search_fields(&self) -> &[String] {
@@ -672,6 +879,11 @@ impl DocMapper {
         self.timestamp_field_name.as_deref()
     }
 
+    /// Returns true if the field `field_name` is mapped as a `geo_point` field.
+    pub fn is_geo_point_field(&self, field_name: &str) -> bool {
+        is_geo_point_field(&self.field_mappings, field_name)
+    }
+
     /// Returns the tag `NameField`s on the current schema.
     /// Returns an error if a tag field is not found in this schema.
     pub fn tag_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
@@ -714,8 +926,9 @@ mod tests {
     use std::iter::zip;
 
     use itertools::Itertools;
+    use quickwit_common::shared_consts::NESTED_PATH_FIELD_NAME;
     use quickwit_common::PathHasher;
-    use quickwit_query::query_ast::query_ast_from_user_text;
+    use quickwit_query::query_ast::{query_ast_from_user_text, KnnQuery, NestedQuery, QueryAst};
     use serde_json::{self, json, Value as JsonValue};
     use tantivy::schema::{
         FieldType, IndexRecordOption, OwnedValue as TantivyValue, OwnedValue, Type, Value,
@@ -2070,6 +2283,315 @@ mod tests {
         Ok(format!("{query:?}"))
     }
 
+    #[test]
+    fn test_doc_mapper_knn_query() {
+        let doc_mapper: DocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {"name": "body", "type": "text"},
+                {"name": "embedding", "type": "dense_vector", "dims": 2, "similarity": "l2_norm"}
+            ]
+        }"#,
+        )
+        .unwrap();
+        let knn_query_ast = |field: &str, query_vector: Vec<f32>| -> QueryAst {
+            KnnQuery {
+                field: field.to_string(),
+                query_vector,
+                k: 10,
+                similarity: None,
+                filter: None,
+            }
+            .into()
+        };
+        let (query, _) = doc_mapper
+            .query(
+                doc_mapper.schema(),
+                &knn_query_ast("embedding", vec![0.0, 0.0]),
+                true,
+            )
+            .unwrap();
+        assert!(format!("{query:?}").contains("similarity: L2Norm"));
+
+        let error = doc_mapper
+            .query(
+                doc_mapper.schema(),
+                &knn_query_ast("embedding", vec![1.0]),
+                true,
+            )
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("k-NN query vector has 1 dimensions but field `embedding` has 2"),
+            "{error}"
+        );
+        let error = doc_mapper
+            .query(
+                doc_mapper.schema(),
+                &knn_query_ast("body", vec![1.0, 0.0]),
+                true,
+            )
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("field `body` is not a dense_vector field"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_doc_mapper_multi_fields() {
+        let doc_mapper: DocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "message",
+                    "type": "text",
+                    "fields": [{"name": "raw", "type": "text", "tokenizer": "raw", "fast": true}]
+                },
+                {
+                    "name": "tags",
+                    "type": "array<text>",
+                    "fields": [{"name": "raw", "type": "text", "tokenizer": "raw"}]
+                }
+            ],
+            "mode": "strict"
+        }"#,
+        )
+        .unwrap();
+        let schema = doc_mapper.schema();
+        let message_raw_field = schema.get_field("message.raw").unwrap();
+        let message_raw_field_entry = schema.get_field_entry(message_raw_field);
+        assert!(message_raw_field_entry.is_fast());
+        assert!(!message_raw_field_entry.is_stored());
+
+        let json_doc = json!({
+            "message": "Hello World",
+            "tags": ["red", "blue"]
+        });
+        let (_, document) = doc_mapper
+            .doc_from_json_obj(json_doc.as_object().unwrap().clone(), 0)
+            .unwrap();
+        assert_eq!(
+            document
+                .get_first(message_raw_field)
+                .and_then(|value| value.as_str()),
+            Some("Hello World")
+        );
+        let tags_raw_field = schema.get_field("tags.raw").unwrap();
+        assert_eq!(document.get_all(tags_raw_field).count(), 2);
+
+        // Sub-fields are not returned in the documents.
+        let named_doc = document.to_named_doc(&schema);
+        assert_eq!(
+            doc_mapper.doc_to_json(named_doc.0).unwrap(),
+            *json_doc.as_object().unwrap()
+        );
+
+        let query_ast: QueryAst = query_ast_from_user_text("message.raw:\"Hello World\"", None)
+            .parse_user_query(&[])
+            .unwrap();
+        doc_mapper
+            .query(doc_mapper.schema(), &query_ast, true)
+            .unwrap();
+
+        let doc_mapper_json = serde_json::to_value(&doc_mapper).unwrap();
+        assert_eq!(
+            doc_mapper_json["field_mappings"][0]["fields"][0]["name"],
+            "raw"
+        );
+        let doc_mapper_round_trip: DocMapper = serde_json::from_value(doc_mapper_json).unwrap();
+        assert!(doc_mapper_round_trip
+            .schema()
+            .get_field("message.raw")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_doc_mapper_nested_field() {
+        let doc_mapper: DocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {"name": "customer", "type": "text", "tokenizer": "raw"},
+                {
+                    "name": "items",
+                    "type": "nested",
+                    "field_mappings": [
+                        {"name": "sku", "type": "text", "tokenizer": "raw"},
+                        {"name": "qty", "type": "u64"}
+                    ]
+                }
+            ],
+            "mode": "strict"
+        }"#,
+        )
+        .unwrap();
+        let json_doc = json!({
+            "customer": "acme",
+            "items": [{"sku": "A", "qty": 1}, {"sku": "B", "qty": 5}]
+        });
+        let (_, document, nested_docs) = doc_mapper
+            .docs_from_json_obj(json_doc.as_object().unwrap().clone(), 0)
+            .unwrap();
+        assert_eq!(nested_docs.len(), 2);
+        let schema = doc_mapper.schema();
+        let nested_path_field = schema.get_field(NESTED_PATH_FIELD_NAME).unwrap();
+        let sku_field = schema.get_field("items.sku").unwrap();
+        for (nested_doc, sku) in zip(&nested_docs, ["A", "B"]) {
+            assert_eq!(
+                nested_doc
+                    .get_first(nested_path_field)
+                    .and_then(|value| value.as_str()),
+                Some("items")
+            );
+            assert_eq!(
+                nested_doc
+                    .get_first(sku_field)
+                    .and_then(|value| value.as_str()),
+                Some(sku)
+            );
+        }
+        assert!(document.get_first(sku_field).is_none());
+
+        let named_doc = document.to_named_doc(&schema);
+        assert_eq!(
+            doc_mapper.doc_to_json(named_doc.0).unwrap(),
+            *json_doc.as_object().unwrap()
+        );
+
+        let error = doc_mapper
+            .doc_from_json_str(r#"{"items": [{"sku": "A", "color": "red"}]}"#)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            DocParsingError::NoSuchFieldInSchema("items.color".to_string())
+        );
+        let error = doc_mapper
+            .doc_from_json_str(r#"{"items": ["A"]}"#)
+            .unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(..)));
+    }
+
+    #[test]
+    fn test_doc_mapper_nested_field_query() {
+        let doc_mapper: DocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "items",
+                    "type": "nested",
+                    "field_mappings": [{"name": "sku", "type": "text", "tokenizer": "raw"}]
+                },
+                {"name": "tags", "type": "array<text>", "tokenizer": "raw"}
+            ]
+        }"#,
+        )
+        .unwrap();
+        let query_ast: QueryAst = query_ast_from_user_text("tags:red", None)
+            .parse_user_query(&[])
+            .unwrap();
+        let (query, _) = doc_mapper
+            .query(doc_mapper.schema(), &query_ast, true)
+            .unwrap();
+        // Child documents are excluded.
+        assert!(format!("{query:?}").contains("MustNot"));
+
+        let nested_query_ast = |path: &str| -> QueryAst {
+            NestedQuery {
+                path: path.to_string(),
+                query: Box::new(QueryAst::MatchAll),
+                score_mode: Default::default(),
+            }
+            .into()
+        };
+        doc_mapper
+            .query(doc_mapper.schema(), &nested_query_ast("items"), true)
+            .unwrap();
+        let error = doc_mapper
+            .query(doc_mapper.schema(), &nested_query_ast("tags"), true)
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("field `tags` is not a nested field"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_doc_mapper_geo_query_requires_geo_point_field() {
+        let doc_mapper: DocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {"name": "location", "type": "geo_point"},
+                {"name": "count", "type": "u64", "fast": true}
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert!(doc_mapper.is_geo_point_field("location"));
+        assert!(!doc_mapper.is_geo_point_field("count"));
+
+        let geo_distance_query_ast = |field: &str| -> QueryAst {
+            GeoDistanceQuery {
+                field: field.to_string(),
+                center: quickwit_query::geo::GeoPoint::new(48.8566, 2.3522).unwrap(),
+                distance_meters: 1_000,
+            }
+            .into()
+        };
+        doc_mapper
+            .query(
+                doc_mapper.schema(),
+                &geo_distance_query_ast("location"),
+                true,
+            )
+            .unwrap();
+        let error = doc_mapper
+            .query(doc_mapper.schema(), &geo_distance_query_ast("count"), true)
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("field `count` is not a geo_point field"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_doc_mapper_reject_fields_inside_nested_field() {
+        for (param, field_name) in [
+            ("timestamp_field", "\"items.ts\""),
+            ("tag_fields", "[\"items.sku\"]"),
+            ("partition_key", "\"items.sku\""),
+        ] {
+            let doc_mapper_json = format!(
+                r#"{{
+                "field_mappings": [
+                    {{
+                        "name": "items",
+                        "type": "nested",
+                        "field_mappings": [
+                            {{"name": "sku", "type": "text", "tokenizer": "raw"}},
+                            {{"name": "ts", "type": "datetime", "fast": true}}
+                        ]
+                    }}
+                ],
+                "{param}": {field_name}
+            }}"#
+            );
+            let error = serde_json::from_str::<DocMapper>(&doc_mapper_json).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("cannot be part of nested field `items`"),
+                "{error}"
+            );
+        }
+    }
+
     #[test]
     fn test_doc_mapper_sub_field_query_on_non_json_field_should_error() {
         let doc_mapper: DocMapper = serde_json::from_str(
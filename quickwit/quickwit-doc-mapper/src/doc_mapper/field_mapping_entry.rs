@@ -18,11 +18,13 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 
 use anyhow::bail;
 use base64::prelude::{Engine, BASE64_STANDARD};
 use once_cell::sync::Lazy;
+use quickwit_query::vector::{VectorSimilarity, MAX_DENSE_VECTOR_DIMS};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -41,6 +43,20 @@ pub struct QuickwitObjectOptions {
     pub field_mappings: Vec<FieldMappingEntry>,
 }
 
+/// Options associated to a nested field.
+///
+/// Each object of a nested field is indexed as a separate hidden document, so that the clauses
+/// of a nested query must match the same object.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitNestedOptions {
+    pub field_mappings: Vec<FieldMappingEntry>,
+    /// Whether the objects are stored in the document returned by searches. The sub-fields of a
+    /// nested field cannot be stored individually.
+    #[serde(default = "default_as_true")]
+    pub stored: bool,
+}
+
 /// A `FieldMappingEntry` defines how a field is indexed, stored,
 /// and mapped from a JSON document to the related index fields.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -53,6 +69,9 @@ pub struct FieldMappingEntry {
     pub name: String,
     /// Property parameters which define the type and the way the value must be indexed.
     pub mapping_type: FieldMappingType,
+    /// Sub-fields indexing the value of the field in different ways, e.g. a text field also
+    /// indexed as a raw keyword. A sub-field is addressed as `<field>.<sub-field>`.
+    pub fields: Vec<FieldMappingEntry>,
 }
 
 // Struct used for serialization and deserialization
@@ -269,6 +288,74 @@ impl Default for QuickwitIpAddrOptions {
     }
 }
 
+/// Options associated to a geo point field.
+///
+/// Geo points are always fast fields: they are encoded into a single `u64` value.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitGeoPointOptions {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "default_as_true")]
+    pub stored: bool,
+}
+
+impl Default for QuickwitGeoPointOptions {
+    fn default() -> Self {
+        Self {
+            description: None,
+            stored: true,
+        }
+    }
+}
+
+/// Options associated to a dense vector field.
+///
+/// Dense vectors are always fast fields: they are encoded into a single bytes value.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitDenseVectorOptions {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Number of dimensions of the vectors.
+    pub dims: usize,
+    /// Similarity metric used by k-NN queries.
+    #[schema(value_type = String)]
+    #[serde(default)]
+    pub similarity: VectorSimilarity,
+    #[serde(default = "default_as_true")]
+    pub stored: bool,
+}
+
+impl QuickwitDenseVectorOptions {
+    /// Parses a vector expressed as an array of numbers.
+    pub(crate) fn parse_json(&self, json_val: &JsonValue) -> Result<Vec<f32>, String> {
+        let JsonValue::Array(components) = json_val else {
+            return Err(format!("expected array of numbers, got `{json_val}`"));
+        };
+        if components.len() != self.dims {
+            return Err(format!(
+                "expected vector with {} dimensions, got {}",
+                self.dims,
+                components.len()
+            ));
+        }
+        let vector = components
+            .iter()
+            .map(|component| {
+                component
+                    .as_f64()
+                    .map(|component| component as f32)
+                    .ok_or_else(|| format!("expected number, got `{component}`"))
+            })
+            .collect::<Result<Vec<f32>, String>>()?;
+        self.similarity.validate_vector(&vector)?;
+        Ok(vector)
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Eq, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct QuickwitTextTokenizer(Cow<'static, str>);
 
@@ -736,6 +823,16 @@ fn deserialize_mapping_type(
             }
             return Ok(FieldMappingType::Object(object_options));
         }
+        QuickwitFieldType::Nested => {
+            let nested_options: QuickwitNestedOptions = serde_json::from_value(json)?;
+            if nested_options.field_mappings.is_empty() {
+                anyhow::bail!("nested type must have at least one field mapping");
+            }
+            if contains_nested_field(&nested_options.field_mappings) {
+                anyhow::bail!("nested fields cannot contain nested fields");
+            }
+            return Ok(FieldMappingType::Nested(nested_options));
+        }
         QuickwitFieldType::Concatenate => {
             let concatenate_options: QuickwitConcatenateOptions = serde_json::from_value(json)?;
             if concatenate_options.concatenate_fields.is_empty()
@@ -745,6 +842,20 @@ fn deserialize_mapping_type(
             }
             return Ok(FieldMappingType::Concatenate(concatenate_options));
         }
+        QuickwitFieldType::GeoPoint => {
+            let geo_point_options: QuickwitGeoPointOptions = serde_json::from_value(json)?;
+            return Ok(FieldMappingType::GeoPoint(geo_point_options));
+        }
+        QuickwitFieldType::DenseVector => {
+            let dense_vector_options: QuickwitDenseVectorOptions = serde_json::from_value(json)?;
+            if dense_vector_options.dims == 0 || dense_vector_options.dims > MAX_DENSE_VECTOR_DIMS {
+                anyhow::bail!(
+                    "dense_vector `dims` must be between 1 and {MAX_DENSE_VECTOR_DIMS}, got `{}`",
+                    dense_vector_options.dims
+                );
+            }
+            return Ok(FieldMappingType::DenseVector(dense_vector_options));
+        }
     };
     match typ {
         Type::Str => {
@@ -802,14 +913,27 @@ impl TryFrom<FieldMappingEntryForSerialization> for FieldMappingEntry {
                     &value.name, &value.type_id
                 )
             })?;
-        let mapping_type = deserialize_mapping_type(
-            quickwit_field_type,
-            JsonValue::Object(value.field_mapping_json),
-        )
-        .map_err(|err| format!("error while parsing field `{}`: {}", value.name, err))?;
+        let mut field_mapping_json = value.field_mapping_json;
+        let fields: Vec<FieldMappingEntry> =
+            if let Some(fields_json) = field_mapping_json.remove("fields") {
+                serde_json::from_value(fields_json).map_err(|err| {
+                    format!(
+                        "error while parsing sub-fields of `{}`: {}",
+                        value.name, err
+                    )
+                })?
+            } else {
+                Vec::new()
+            };
+        let mapping_type =
+            deserialize_mapping_type(quickwit_field_type, JsonValue::Object(field_mapping_json))
+                .map_err(|err| format!("error while parsing field `{}`: {}", value.name, err))?;
+        validate_sub_fields(&mapping_type, &fields)
+            .map_err(|err| format!("error while parsing field `{}`: {}", value.name, err))?;
         Ok(FieldMappingEntry {
             name: value.name,
             mapping_type,
+            fields,
         })
     }
 }
@@ -824,6 +948,71 @@ fn serialize_to_map<S: Serialize>(val: &S) -> Option<serde_json::Map<String, Jso
     }
 }
 
+/// Sub-fields can only be attached to a field holding primitive values, and are indexed with the
+/// cardinality of their parent field.
+fn validate_sub_fields(
+    mapping_type: &FieldMappingType,
+    sub_fields: &[FieldMappingEntry],
+) -> anyhow::Result<()> {
+    if sub_fields.is_empty() {
+        return Ok(());
+    }
+    if !supports_sub_fields(mapping_type) {
+        bail!(
+            "type `{}` does not support sub-fields",
+            mapping_type.quickwit_field_type().to_type_id()
+        );
+    }
+    let mut sub_field_names = HashSet::new();
+    for sub_field in sub_fields {
+        if !sub_field_names.insert(&sub_field.name) {
+            bail!("duplicated sub-field definition `{}`", sub_field.name);
+        }
+        if !supports_sub_fields(&sub_field.mapping_type) {
+            bail!(
+                "sub-field `{}` cannot be of type `{}`",
+                sub_field.name,
+                sub_field.mapping_type.quickwit_field_type().to_type_id()
+            );
+        }
+        if let QuickwitFieldType::Array(_) = sub_field.mapping_type.quickwit_field_type() {
+            bail!(
+                "sub-field `{}` cannot be an array: sub-fields have the cardinality of their \
+                 parent field",
+                sub_field.name
+            );
+        }
+        if !sub_field.fields.is_empty() {
+            bail!("sub-field `{}` cannot have sub-fields", sub_field.name);
+        }
+    }
+    Ok(())
+}
+
+fn supports_sub_fields(mapping_type: &FieldMappingType) -> bool {
+    matches!(
+        mapping_type,
+        FieldMappingType::Text(..)
+            | FieldMappingType::I64(..)
+            | FieldMappingType::U64(..)
+            | FieldMappingType::F64(..)
+            | FieldMappingType::Bool(..)
+            | FieldMappingType::IpAddr(..)
+            | FieldMappingType::DateTime(..)
+            | FieldMappingType::Bytes(..)
+    )
+}
+
+fn contains_nested_field(entries: &[FieldMappingEntry]) -> bool {
+    entries.iter().any(|entry| match &entry.mapping_type {
+        FieldMappingType::Nested(_) => true,
+        FieldMappingType::Object(object_options) => {
+            contains_nested_field(&object_options.field_mappings)
+        }
+        _ => false,
+    })
+}
+
 fn typed_mapping_to_json_params(
     field_mapping_type: FieldMappingType,
 ) -> serde_json::Map<String, JsonValue> {
@@ -835,9 +1024,12 @@ fn typed_mapping_to_json_params(
         FieldMappingType::Bool(options, _) => serialize_to_map(&options),
         FieldMappingType::Bytes(options, _) => serialize_to_map(&options),
         FieldMappingType::IpAddr(options, _) => serialize_to_map(&options),
+        FieldMappingType::GeoPoint(options) => serialize_to_map(&options),
+        FieldMappingType::DenseVector(options) => serialize_to_map(&options),
         FieldMappingType::DateTime(date_time_options, _) => serialize_to_map(&date_time_options),
         FieldMappingType::Json(json_options, _) => serialize_to_map(&json_options),
         FieldMappingType::Object(object_options) => serialize_to_map(&object_options),
+        FieldMappingType::Nested(nested_options) => serialize_to_map(&nested_options),
         FieldMappingType::Concatenate(concatenate_options) => {
             serialize_to_map(&concatenate_options)
         }
@@ -851,7 +1043,12 @@ impl From<FieldMappingEntry> for FieldMappingEntryForSerialization {
             .mapping_type
             .quickwit_field_type()
             .to_type_id();
-        let field_mapping_json = typed_mapping_to_json_params(field_mapping_entry.mapping_type);
+        let mut field_mapping_json = typed_mapping_to_json_params(field_mapping_entry.mapping_type);
+        if !field_mapping_entry.fields.is_empty() {
+            let fields_json = serde_json::to_value(field_mapping_entry.fields)
+                .expect("sub-fields should be serializable");
+            field_mapping_json.insert("fields".to_string(), fields_json);
+        }
         FieldMappingEntryForSerialization {
             name: field_mapping_entry.name,
             type_id,
@@ -871,7 +1068,7 @@ pub const FIELD_MAPPING_NAME_PATTERN: &str = r"^[@$_\-a-zA-Z][@$_/\.\-a-zA-Z0-9]
 ///   hyphens `-`, underscores `_`, at `@` and dollar `$` signs;
 /// - must not start with a dot or a digit;
 /// - must be different from Quickwit's reserved field mapping names `_source`, `_dynamic`,
-///   `_field_presence`;
+///   `_field_presence`, `_nested_path`;
 /// - must not be longer than 255 characters.
 pub fn validate_field_mapping_name(field_mapping_name: &str) -> anyhow::Result<()> {
     static FIELD_MAPPING_NAME_PTN: Lazy<Regex> =
@@ -1270,6 +1467,132 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deserialize_nested_mapping_entry() {
+        let mapping_entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "items",
+                "type": "nested",
+                "field_mappings": [
+                    {
+                        "name": "sku",
+                        "type": "text",
+                        "tokenizer": "raw"
+                    }
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+        let FieldMappingType::Nested(nested_options) = &mapping_entry.mapping_type else {
+            panic!("wrong property type");
+        };
+        assert_eq!(nested_options.field_mappings.len(), 1);
+        assert!(nested_options.stored);
+
+        let mapping_entry_json = serde_json::to_value(&mapping_entry).unwrap();
+        assert_eq!(mapping_entry_json["type"], "nested");
+        assert_eq!(mapping_entry_json["stored"], true);
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "items",
+                "type": "nested",
+                "field_mappings": [
+                    {
+                        "name": "tags",
+                        "type": "nested",
+                        "field_mappings": [{ "name": "name", "type": "text" }]
+                    }
+                ]
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "error while parsing field `items`: nested fields cannot contain nested fields"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_mapping_entry_with_sub_fields() {
+        let mapping_entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "message",
+                "type": "text",
+                "fields": [
+                    {
+                        "name": "raw",
+                        "type": "text",
+                        "tokenizer": "raw",
+                        "fast": true
+                    }
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            mapping_entry.mapping_type,
+            FieldMappingType::Text(..)
+        ));
+        assert_eq!(mapping_entry.fields.len(), 1);
+        assert_eq!(mapping_entry.fields[0].name, "raw");
+
+        let mapping_entry_json = serde_json::to_value(&mapping_entry).unwrap();
+        assert_eq!(mapping_entry_json["fields"][0]["name"], "raw");
+        assert_eq!(mapping_entry_json["fields"][0]["tokenizer"], "raw");
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "message",
+                "type": "text",
+                "fields": [{ "name": "raw", "type": "array<text>" }]
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("sub-field `raw` cannot be an array"));
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "attributes",
+                "type": "json",
+                "fields": [{ "name": "raw", "type": "text" }]
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("type `json` does not support sub-fields"));
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "message",
+                "type": "text",
+                "fields": [
+                    { "name": "raw", "type": "text" },
+                    { "name": "raw", "type": "u64" }
+                ]
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("duplicated sub-field definition `raw`"));
+    }
+
     #[test]
     fn test_deserialize_mapping_with_unknown_type() {
         let result = serde_json::from_str::<FieldMappingEntry>(
@@ -1560,6 +1883,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_geo_point_mapping() {
+        let entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "location",
+                "type": "geo_point"
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            entry.mapping_type,
+            FieldMappingType::GeoPoint(QuickwitGeoPointOptions::default())
+        );
+        let entry_json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            entry_json,
+            serde_json::json!({
+                "name": "location",
+                "type": "geo_point",
+                "stored": true
+            })
+        );
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "location",
+                "type": "geo_point",
+                "fast": false
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("unknown field `fast`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_parse_dense_vector_mapping() {
+        let entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "embedding",
+                "type": "dense_vector",
+                "dims": 3,
+                "similarity": "l2_norm"
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            entry.mapping_type,
+            FieldMappingType::DenseVector(QuickwitDenseVectorOptions {
+                description: None,
+                dims: 3,
+                similarity: VectorSimilarity::L2Norm,
+                stored: true,
+            })
+        );
+        let entry_json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            entry_json,
+            serde_json::json!({
+                "name": "embedding",
+                "type": "dense_vector",
+                "dims": 3,
+                "similarity": "l2_norm",
+                "stored": true
+            })
+        );
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "embedding",
+                "type": "dense_vector",
+                "dims": 0
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("dense_vector `dims` must be between 1 and 4096"),
+            "{error}"
+        );
+    }
+
     #[test]
     fn test_parse_text_mapping() {
         let entry = serde_json::from_str::<FieldMappingEntry>(
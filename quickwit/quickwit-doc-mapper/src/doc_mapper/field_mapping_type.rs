@@ -22,7 +22,8 @@ use tantivy::schema::Type;
 use super::date_time_type::QuickwitDateTimeOptions;
 use super::field_mapping_entry::QuickwitBoolOptions;
 use crate::doc_mapper::field_mapping_entry::{
-    QuickwitBytesOptions, QuickwitConcatenateOptions, QuickwitIpAddrOptions, QuickwitJsonOptions,
+    QuickwitBytesOptions, QuickwitConcatenateOptions, QuickwitDenseVectorOptions,
+    QuickwitGeoPointOptions, QuickwitIpAddrOptions, QuickwitJsonOptions, QuickwitNestedOptions,
     QuickwitNumericOptions, QuickwitObjectOptions, QuickwitTextOptions,
 };
 use crate::Cardinality;
@@ -45,6 +46,10 @@ pub enum FieldMappingType {
     Bool(QuickwitBoolOptions, Cardinality),
     /// IP Address mapping type configuration.
     IpAddr(QuickwitIpAddrOptions, Cardinality),
+    /// Geo point mapping type configuration.
+    GeoPoint(QuickwitGeoPointOptions),
+    /// Dense vector mapping type configuration.
+    DenseVector(QuickwitDenseVectorOptions),
     /// Bytes mapping type configuration.
     Bytes(QuickwitBytesOptions, Cardinality),
     /// Json mapping type configuration.
@@ -53,6 +58,8 @@ pub enum FieldMappingType {
     Object(QuickwitObjectOptions),
     /// Concatenate field mapping type configuration.
     Concatenate(QuickwitConcatenateOptions),
+    /// Nested mapping type configuration.
+    Nested(QuickwitNestedOptions),
 }
 
 impl FieldMappingType {
@@ -72,6 +79,9 @@ impl FieldMappingType {
                 return QuickwitFieldType::Object;
             }
             FieldMappingType::Concatenate(_) => return QuickwitFieldType::Concatenate,
+            FieldMappingType::GeoPoint(_) => return QuickwitFieldType::GeoPoint,
+            FieldMappingType::DenseVector(_) => return QuickwitFieldType::DenseVector,
+            FieldMappingType::Nested(_) => return QuickwitFieldType::Nested,
         };
         match cardinality {
             Cardinality::SingleValued => QuickwitFieldType::Simple(primitive_type),
@@ -85,6 +95,9 @@ pub enum QuickwitFieldType {
     Simple(Type),
     Object,
     Concatenate,
+    GeoPoint,
+    DenseVector,
+    Nested,
     Array(Type),
 }
 
@@ -95,6 +108,9 @@ impl QuickwitFieldType {
             QuickwitFieldType::Object => "object".to_string(),
             QuickwitFieldType::Array(typ) => format!("array<{}>", primitive_type_to_str(typ)),
             QuickwitFieldType::Concatenate => "concatenate".to_string(),
+            QuickwitFieldType::GeoPoint => "geo_point".to_string(),
+            QuickwitFieldType::DenseVector => "dense_vector".to_string(),
+            QuickwitFieldType::Nested => "nested".to_string(),
         }
     }
 
@@ -105,6 +121,15 @@ impl QuickwitFieldType {
         if type_str == "concatenate" {
             return Some(QuickwitFieldType::Concatenate);
         }
+        if type_str == "geo_point" {
+            return Some(QuickwitFieldType::GeoPoint);
+        }
+        if type_str == "dense_vector" {
+            return Some(QuickwitFieldType::DenseVector);
+        }
+        if type_str == "nested" {
+            return Some(QuickwitFieldType::Nested);
+        }
         if type_str.starts_with("array<") && type_str.ends_with('>') {
             let parsed_type_str = parse_primitive_type(&type_str[6..type_str.len() - 1])?;
             return Some(QuickwitFieldType::Array(parsed_type_str));
@@ -168,5 +193,9 @@ mod tests {
         test_parse_type_aux("object2", None);
         test_parse_type_aux("bool", Some(QuickwitFieldType::Simple(Type::Bool)));
         test_parse_type_aux("ip", Some(QuickwitFieldType::Simple(Type::IpAddr)));
+        test_parse_type_aux("geo_point", Some(QuickwitFieldType::GeoPoint));
+        test_parse_type_aux("array<geo_point>", None);
+        test_parse_type_aux("dense_vector", Some(QuickwitFieldType::DenseVector));
+        test_parse_type_aux("nested", Some(QuickwitFieldType::Nested));
     }
 }
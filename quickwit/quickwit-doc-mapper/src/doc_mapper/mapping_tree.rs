@@ -24,19 +24,23 @@ use std::str::FromStr;
 
 use anyhow::bail;
 use itertools::Itertools;
+use quickwit_common::shared_consts::NESTED_PATH_FIELD_NAME;
+use quickwit_query::geo::GeoPoint;
+use quickwit_query::vector::encode_vector;
 use serde_json::Value as JsonValue;
 use serde_json_borrow::{Map as BorrowedJsonMap, Value as BorrowedJsonValue};
 use tantivy::schema::{
     BytesOptions, DateOptions, Field, IntoIpv6Addr, IpAddrOptions, JsonObjectOptions,
-    NumericOptions, OwnedValue as TantivyValue, SchemaBuilder, TextOptions,
+    NumericOptions, OwnedValue as TantivyValue, SchemaBuilder, TextOptions, FAST, STORED, STRING,
 };
 use tantivy::TantivyDocument as Document;
 
 use super::date_time_type::QuickwitDateTimeOptions;
 use super::field_mapping_entry::QuickwitBoolOptions;
-use super::tantivy_val_to_json::formatted_tantivy_value_to_json;
+use super::tantivy_val_to_json::{formatted_tantivy_value_to_json, tantivy_value_to_json};
 use crate::doc_mapper::field_mapping_entry::{
-    QuickwitBytesOptions, QuickwitIpAddrOptions, QuickwitNumericOptions, QuickwitObjectOptions,
+    QuickwitBytesOptions, QuickwitDenseVectorOptions, QuickwitGeoPointOptions,
+    QuickwitIpAddrOptions, QuickwitNestedOptions, QuickwitNumericOptions, QuickwitObjectOptions,
     QuickwitTextOptions,
 };
 use crate::doc_mapper::{FieldMappingType, QuickwitJsonOptions};
@@ -51,6 +55,8 @@ pub enum LeafType {
     I64(QuickwitNumericOptions),
     U64(QuickwitNumericOptions),
     IpAddr(QuickwitIpAddrOptions),
+    GeoPoint(QuickwitGeoPointOptions),
+    DenseVector(QuickwitDenseVectorOptions),
     Json(QuickwitJsonOptions),
     Text(QuickwitTextOptions),
 }
@@ -196,6 +202,14 @@ impl LeafType {
             LeafType::DateTime(date_time_options) => {
                 date_time_options.validate_json(json_val).map(|_| ())
             }
+            LeafType::GeoPoint(_) => {
+                let json_val = serde_json::to_value(json_val).map_err(|err| err.to_string())?;
+                GeoPoint::from_json(&json_val).map(|_| ())
+            }
+            LeafType::DenseVector(dense_vector_options) => {
+                let json_val = serde_json::to_value(json_val).map_err(|err| err.to_string())?;
+                dense_vector_options.parse_json(&json_val).map(|_| ())
+            }
             LeafType::Bytes(binary_options) => {
                 if let Some(byte_str) = json_val.as_str() {
                     binary_options.input_format.parse_str(byte_str)?;
@@ -247,6 +261,14 @@ impl LeafType {
                 }
             }
             LeafType::DateTime(date_time_options) => date_time_options.parse_json(&json_val),
+            LeafType::GeoPoint(_) => {
+                let geo_point = GeoPoint::from_json(&json_val)?;
+                Ok(TantivyValue::U64(geo_point.encode()))
+            }
+            LeafType::DenseVector(dense_vector_options) => {
+                let vector = dense_vector_options.parse_json(&json_val)?;
+                Ok(TantivyValue::Bytes(encode_vector(&vector)))
+            }
             LeafType::Bytes(binary_options) => binary_options.input_format.parse_json(&json_val),
             LeafType::Json(_) => {
                 if let JsonValue::Object(json_obj) = json_val {
@@ -299,6 +321,8 @@ impl LeafType {
                 Err("unsupported concat type: DateTime".to_string())
             }
             LeafType::Bytes(_binary_options) => Err("unsupported concat type: Bytes".to_string()),
+            LeafType::GeoPoint(_) => Err("unsupported concat type: GeoPoint".to_string()),
+            LeafType::DenseVector(_) => Err("unsupported concat type: DenseVector".to_string()),
             LeafType::Json(_) => {
                 if let JsonValue::Object(json_obj) = json_val {
                     Ok(OneOrIter::Iter(
@@ -323,6 +347,8 @@ impl LeafType {
             IpAddr(_),
             // won't be supported
             Bytes(_),
+            GeoPoint(_),
+            DenseVector(_),
         */
     }
 }
@@ -334,6 +360,27 @@ pub(crate) struct MappingLeaf {
     cardinality: Cardinality,
     // concatenate fields this field is part of
     concatenate: Vec<Field>,
+    // sub-fields indexing the value of this field in different ways
+    multi_fields: Vec<MappingMultiField>,
+}
+
+/// A sub-field indexing the value of its parent leaf in a different way.
+#[derive(Clone)]
+pub(crate) struct MappingMultiField {
+    name: String,
+    /// Mapping type of the sub-field, as declared in the doc mapping.
+    mapping_type: FieldMappingType,
+    leaf: MappingLeaf,
+}
+
+impl MappingMultiField {
+    fn to_field_mapping_entry(&self) -> FieldMappingEntry {
+        FieldMappingEntry {
+            name: self.name.clone(),
+            mapping_type: self.mapping_type.clone(),
+            fields: Vec::new(),
+        }
+    }
 }
 
 impl MappingLeaf {
@@ -346,8 +393,26 @@ impl MappingLeaf {
             // We just ignore `null`.
             return Ok(());
         }
+        if !self.multi_fields.is_empty() {
+            let mut multi_field_path = path.to_vec();
+            for multi_field in &self.multi_fields {
+                multi_field_path.push(&multi_field.name);
+                multi_field
+                    .leaf
+                    .validate_from_json(json_value, &multi_field_path)?;
+                multi_field_path.pop();
+            }
+        }
         if let BorrowedJsonValue::Array(els) = json_value {
             if self.cardinality == Cardinality::SingleValued {
+                // A geo point can be expressed as a `[lon, lat]` array and a dense vector is
+                // always an array.
+                if let LeafType::GeoPoint(_) | LeafType::DenseVector(_) = self.typ {
+                    return self
+                        .typ
+                        .validate_from_json(json_value)
+                        .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg));
+                }
                 return Err(DocParsingError::MultiValuesNotSupported(path.join(".")));
             }
             for el_json_val in els {
@@ -379,8 +444,30 @@ impl MappingLeaf {
             // We just ignore `null`.
             return Ok(());
         }
+        if !self.multi_fields.is_empty() {
+            let mut multi_field_path = path.to_vec();
+            for multi_field in &self.multi_fields {
+                multi_field_path.push(multi_field.name.clone());
+                multi_field.leaf.doc_from_json(
+                    json_val.clone(),
+                    document,
+                    &mut multi_field_path,
+                )?;
+                multi_field_path.pop();
+            }
+        }
         if let JsonValue::Array(els) = json_val {
             if self.cardinality == Cardinality::SingleValued {
+                // A geo point can be expressed as a `[lon, lat]` array and a dense vector is
+                // always an array.
+                if let LeafType::GeoPoint(_) | LeafType::DenseVector(_) = self.typ {
+                    let value = self
+                        .typ
+                        .value_from_json(JsonValue::Array(els))
+                        .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
+                    document.add_field_value(self.field, &value);
+                    return Ok(());
+                }
                 return Err(DocParsingError::MultiValuesNotSupported(path.join(".")));
             }
             for el_json_val in els {
@@ -443,6 +530,27 @@ impl MappingLeaf {
     pub fn get_type(&self) -> &LeafType {
         &self.typ
     }
+
+    fn find_multi_field(&self, sub_field_path: &[String]) -> Option<&MappingMultiField> {
+        let [sub_field_name] = sub_field_path else {
+            return None;
+        };
+        self.multi_fields
+            .iter()
+            .find(|multi_field| &multi_field.name == sub_field_name)
+    }
+
+    fn find_multi_field_mut(
+        &mut self,
+        sub_field_path: &[String],
+    ) -> Option<&mut MappingMultiField> {
+        let [sub_field_name] = sub_field_path else {
+            return None;
+        };
+        self.multi_fields
+            .iter_mut()
+            .find(|multi_field| &multi_field.name == sub_field_name)
+    }
 }
 
 fn extract_json_val(
@@ -767,10 +875,15 @@ impl MappingNode {
         let child_tree = self.branches.get(first_path_fragment)?;
         match (child_tree, sub_field_path.is_empty()) {
             (_, true) => Some(child_tree.clone().into()),
-            (MappingTree::Leaf(_), false) => None,
+            (MappingTree::Leaf(child_leaf), false) => child_leaf
+                .find_multi_field(sub_field_path)
+                .map(|multi_field| multi_field.mapping_type.clone()),
             (MappingTree::Node(child_node), false) => {
                 child_node.internal_find_field_mapping_type(sub_field_path)
             }
+            (MappingTree::Nested(child_nested), false) => child_nested
+                .field_mappings
+                .internal_find_field_mapping_type(sub_field_path),
         }
     }
 
@@ -792,12 +905,18 @@ impl MappingNode {
         let (first_path_fragment, sub_field_path) = field_path.split_first()?;
         let child_tree = self.branches.get_mut(first_path_fragment)?;
         match (child_tree, sub_field_path.is_empty()) {
-            (MappingTree::Leaf(_), false) => None,
+            (MappingTree::Leaf(leaf), false) => {
+                let multi_field = leaf.find_multi_field_mut(sub_field_path)?;
+                Some([&mut multi_field.leaf].into_iter())
+            }
             (MappingTree::Node(child_node), false) => {
                 child_node.internal_find_field_mapping_leaf(sub_field_path)
             }
             (MappingTree::Leaf(leaf), true) => Some([leaf].into_iter()),
             (MappingTree::Node(_), true) => None,
+            // Concatenate fields cannot include the sub-fields of a nested field, which are
+            // indexed in separate documents.
+            (MappingTree::Nested(_), _) => None,
         }
     }
 
@@ -816,9 +935,19 @@ impl MappingNode {
         let mut field_mapping_entries = Vec::new();
         for field_name in &self.branches_order {
             let child_tree = self.branches.get(field_name).expect("Missing field");
+            let fields = if let MappingTree::Leaf(mapping_leaf) = child_tree {
+                mapping_leaf
+                    .multi_fields
+                    .iter()
+                    .map(MappingMultiField::to_field_mapping_entry)
+                    .collect()
+            } else {
+                Vec::new()
+            };
             let field_mapping_entry = FieldMappingEntry {
                 name: field_name.clone(),
                 mapping_type: child_tree.clone().into(),
+                fields,
             };
             field_mapping_entries.push(field_mapping_entry);
         }
@@ -850,13 +979,21 @@ impl MappingNode {
         json_obj: serde_json::Map<String, JsonValue>,
         mode: ModeType,
         document: &mut Document,
+        nested_docs: &mut Vec<Document>,
         path: &mut Vec<String>,
         dynamic_json_obj: &mut serde_json::Map<String, JsonValue>,
     ) -> Result<(), DocParsingError> {
         for (field_name, val) in json_obj {
             if let Some(child_tree) = self.branches.get(&field_name) {
                 path.push(field_name);
-                child_tree.doc_from_json(val, mode, document, path, dynamic_json_obj)?;
+                child_tree.doc_from_json(
+                    val,
+                    mode,
+                    document,
+                    nested_docs,
+                    path,
+                    dynamic_json_obj,
+                )?;
                 path.pop();
             } else {
                 match mode {
@@ -891,6 +1028,126 @@ impl MappingNode {
             field_path.pop();
         }
     }
+
+    /// Returns the paths of the nested fields of the mapping tree.
+    pub fn nested_paths(&self) -> Vec<String> {
+        let mut nested_paths = Vec::new();
+        for mapping_tree in self.branches.values() {
+            match mapping_tree {
+                MappingTree::Leaf(_) => {}
+                MappingTree::Node(mapping_node) => {
+                    nested_paths.extend(mapping_node.nested_paths());
+                }
+                MappingTree::Nested(mapping_nested) => {
+                    nested_paths.push(mapping_nested.nested_path.clone());
+                }
+            }
+        }
+        nested_paths.sort();
+        nested_paths
+    }
+}
+
+/// Maps the objects of a nested field to child documents.
+#[derive(Clone)]
+pub(crate) struct MappingNested {
+    /// Path of the nested field, also used as the name of the field storing its objects.
+    nested_path: String,
+    nested_path_field: Field,
+    stored_field_opt: Option<Field>,
+    field_mappings: MappingNode,
+    options: QuickwitNestedOptions,
+}
+
+impl MappingNested {
+    fn validate_from_json<'a>(
+        &self,
+        json_value: &'a BorrowedJsonValue<'a>,
+        field_path: &mut Vec<&'a str>,
+        strict_mode: bool,
+    ) -> Result<(), DocParsingError> {
+        let json_objs: Vec<&BorrowedJsonValue> = match json_value {
+            BorrowedJsonValue::Null => Vec::new(),
+            BorrowedJsonValue::Array(json_values) => json_values.iter().collect(),
+            json_value => vec![json_value],
+        };
+        for json_obj in json_objs {
+            let Some(json_obj) = json_obj.as_object() else {
+                return Err(DocParsingError::ValueError(
+                    field_path.join("."),
+                    format!("expected an JSON object, got {json_obj}"),
+                ));
+            };
+            self.field_mappings
+                .validate_from_json(json_obj, strict_mode, field_path)?;
+        }
+        Ok(())
+    }
+
+    fn doc_from_json(
+        &self,
+        json_value: JsonValue,
+        mode: ModeType,
+        document: &mut Document,
+        nested_docs: &mut Vec<Document>,
+        path: &mut Vec<String>,
+    ) -> Result<(), DocParsingError> {
+        let json_values = match json_value {
+            JsonValue::Null => Vec::new(),
+            JsonValue::Array(json_values) => json_values,
+            json_value => vec![json_value],
+        };
+        // Unmapped fields of nested objects are not captured by the dynamic field, which only
+        // belongs to the root document.
+        let nested_mode = if mode == ModeType::Strict {
+            ModeType::Strict
+        } else {
+            ModeType::Lenient
+        };
+        for json_value in json_values {
+            let JsonValue::Object(json_obj) = json_value else {
+                return Err(DocParsingError::ValueError(
+                    path.join("."),
+                    format!("expected an JSON object, got {json_value}"),
+                ));
+            };
+            if let Some(stored_field) = self.stored_field_opt {
+                document.add_object(
+                    stored_field,
+                    json_obj
+                        .clone()
+                        .into_iter()
+                        .map(|(key, val)| (key, TantivyValue::from(val)))
+                        .collect(),
+                );
+            }
+            let mut nested_doc = Document::default();
+            nested_doc.add_text(self.nested_path_field, &self.nested_path);
+            self.field_mappings.doc_from_json(
+                json_obj,
+                nested_mode,
+                &mut nested_doc,
+                nested_docs,
+                path,
+                &mut serde_json::Map::default(),
+            )?;
+            nested_docs.push(nested_doc);
+        }
+        Ok(())
+    }
+
+    fn populate_json<'a>(
+        &'a self,
+        named_doc: &mut BTreeMap<String, Vec<TantivyValue>>,
+        field_path: &[&'a str],
+        doc_json: &mut serde_json::Map<String, JsonValue>,
+    ) {
+        let Some(values) = named_doc.remove(&self.nested_path) else {
+            return;
+        };
+        let json_values: Vec<JsonValue> = values.into_iter().map(tantivy_value_to_json).collect();
+        insert_json_val(field_path, JsonValue::Array(json_values), doc_json);
+    }
 }
 
 impl From<MappingTree> for FieldMappingType {
@@ -900,6 +1157,7 @@ impl From<MappingTree> for FieldMappingType {
             MappingTree::Node(node) => FieldMappingType::Object(QuickwitObjectOptions {
                 field_mappings: node.into(),
             }),
+            MappingTree::Nested(nested) => FieldMappingType::Nested(nested.options),
         }
     }
 }
@@ -913,6 +1171,8 @@ impl From<MappingLeaf> for FieldMappingType {
             LeafType::F64(opt) => FieldMappingType::F64(opt, leaf.cardinality),
             LeafType::Bool(opt) => FieldMappingType::Bool(opt, leaf.cardinality),
             LeafType::IpAddr(opt) => FieldMappingType::IpAddr(opt, leaf.cardinality),
+            LeafType::GeoPoint(opt) => FieldMappingType::GeoPoint(opt),
+            LeafType::DenseVector(opt) => FieldMappingType::DenseVector(opt),
             LeafType::DateTime(opt) => FieldMappingType::DateTime(opt, leaf.cardinality),
             LeafType::Bytes(opt) => FieldMappingType::Bytes(opt, leaf.cardinality),
             LeafType::Json(opt) => FieldMappingType::Json(opt, leaf.cardinality),
@@ -930,6 +1190,7 @@ impl From<MappingNode> for Vec<FieldMappingEntry> {
 pub(crate) enum MappingTree {
     Leaf(MappingLeaf),
     Node(MappingNode),
+    Nested(MappingNested),
 }
 
 impl MappingTree {
@@ -953,6 +1214,9 @@ impl MappingTree {
                     ))
                 }
             }
+            MappingTree::Nested(mapping_nested) => {
+                mapping_nested.validate_from_json(json_value, field_path, strict_mode)
+            }
         }
     }
 
@@ -961,6 +1225,7 @@ impl MappingTree {
         json_value: JsonValue,
         mode: ModeType,
         document: &mut Document,
+        nested_docs: &mut Vec<Document>,
         path: &mut Vec<String>,
         dynamic_json_obj: &mut serde_json::Map<String, JsonValue>,
     ) -> Result<(), DocParsingError> {
@@ -970,7 +1235,14 @@ impl MappingTree {
             }
             MappingTree::Node(mapping_node) => {
                 if let JsonValue::Object(json_obj) = json_value {
-                    mapping_node.doc_from_json(json_obj, mode, document, path, dynamic_json_obj)
+                    mapping_node.doc_from_json(
+                        json_obj,
+                        mode,
+                        document,
+                        nested_docs,
+                        path,
+                        dynamic_json_obj,
+                    )
                 } else {
                     Err(DocParsingError::ValueError(
                         path.join("."),
@@ -978,6 +1250,9 @@ impl MappingTree {
                     ))
                 }
             }
+            MappingTree::Nested(mapping_nested) => {
+                mapping_nested.doc_from_json(json_value, mode, document, nested_docs, path)
+            }
         }
     }
 
@@ -994,6 +1269,9 @@ impl MappingTree {
             MappingTree::Node(mapping_node) => {
                 mapping_node.populate_json(named_doc, field_path, doc_json);
             }
+            MappingTree::Nested(mapping_nested) => {
+                mapping_nested.populate_json(named_doc, field_path, doc_json);
+            }
         }
     }
 }
@@ -1010,13 +1288,15 @@ pub(crate) fn build_mapping_tree(
     schema: &mut SchemaBuilder,
 ) -> anyhow::Result<MappingNodeRoot> {
     let mut field_path = Vec::new();
-    build_mapping_tree_from_entries(entries, &mut field_path, schema)
+    let mut nested_path_field_opt = None;
+    build_mapping_tree_from_entries(entries, &mut field_path, schema, &mut nested_path_field_opt)
 }
 
 fn build_mapping_tree_from_entries<'a>(
     entries: &'a [FieldMappingEntry],
     field_path: &mut Vec<&'a str>,
     schema: &mut SchemaBuilder,
+    nested_path_field_opt: &mut Option<Field>,
 ) -> anyhow::Result<MappingNodeRoot> {
     let mut mapping_node = MappingNode::default();
     let mut concatenate_fields = Vec::new();
@@ -1029,8 +1309,24 @@ fn build_mapping_tree_from_entries<'a>(
             if mapping_node.branches.contains_key(&entry.name) {
                 bail!("duplicated field definition `{}`", entry.name);
             }
-            let (child_tree, mut dynamic_fields) =
-                build_mapping_from_field_type(&entry.mapping_type, field_path, schema)?;
+            let (mut child_tree, mut dynamic_fields) = build_mapping_from_field_type(
+                &entry.mapping_type,
+                field_path,
+                schema,
+                nested_path_field_opt,
+            )?;
+            if !entry.fields.is_empty() {
+                let MappingTree::Leaf(mapping_leaf) = &mut child_tree else {
+                    bail!("field `{}` does not support sub-fields", entry.name);
+                };
+                mapping_leaf.multi_fields = build_multi_fields(
+                    &entry.fields,
+                    field_path,
+                    mapping_leaf.cardinality,
+                    schema,
+                    nested_path_field_opt,
+                )?;
+            }
             field_path.pop();
             mapping_node.insert(&entry.name, child_tree);
             concatenate_dynamic_fields.append(&mut dynamic_fields);
@@ -1074,6 +1370,41 @@ fn build_mapping_tree_from_entries<'a>(
     })
 }
 
+/// Builds the sub-fields of a leaf field.
+///
+/// Sub-fields are derived from the value of their parent field: they are never stored, and they
+/// have the cardinality of their parent field.
+fn build_multi_fields(
+    sub_field_entries: &[FieldMappingEntry],
+    field_path: &[&str],
+    cardinality: Cardinality,
+    schema_builder: &mut SchemaBuilder,
+    nested_path_field_opt: &mut Option<Field>,
+) -> anyhow::Result<Vec<MappingMultiField>> {
+    let mut multi_fields = Vec::with_capacity(sub_field_entries.len());
+    for sub_field_entry in sub_field_entries {
+        let unstored_entry = unstored_field_mapping_entry(sub_field_entry.clone());
+        let mut sub_field_path: Vec<&str> = field_path.to_vec();
+        sub_field_path.push(&sub_field_entry.name);
+        let (MappingTree::Leaf(mut leaf), _) = build_mapping_from_field_type(
+            &unstored_entry.mapping_type,
+            &mut sub_field_path,
+            schema_builder,
+            nested_path_field_opt,
+        )?
+        else {
+            bail!("sub-field `{}` must be a leaf field", sub_field_entry.name);
+        };
+        leaf.cardinality = cardinality;
+        multi_fields.push(MappingMultiField {
+            name: sub_field_entry.name.clone(),
+            mapping_type: sub_field_entry.mapping_type.clone(),
+            leaf,
+        });
+    }
+    Ok(multi_fields)
+}
+
 fn get_numeric_options_for_bool_field(
     quickwit_bool_options: &QuickwitBoolOptions,
 ) -> NumericOptions {
@@ -1134,6 +1465,24 @@ fn get_bytes_options(quickwit_numeric_options: &QuickwitBytesOptions) -> BytesOp
     bytes_options
 }
 
+fn get_geo_point_options(quickwit_geo_point_options: &QuickwitGeoPointOptions) -> NumericOptions {
+    let mut numeric_options = NumericOptions::default().set_fast();
+    if quickwit_geo_point_options.stored {
+        numeric_options = numeric_options.set_stored();
+    }
+    numeric_options
+}
+
+fn get_dense_vector_options(
+    quickwit_dense_vector_options: &QuickwitDenseVectorOptions,
+) -> BytesOptions {
+    let mut bytes_options = BytesOptions::default().set_fast();
+    if quickwit_dense_vector_options.stored {
+        bytes_options = bytes_options.set_stored();
+    }
+    bytes_options
+}
+
 fn get_ip_address_options(quickwit_ip_address_options: &QuickwitIpAddrOptions) -> IpAddrOptions {
     let mut ip_address_options = IpAddrOptions::default();
     if quickwit_ip_address_options.stored {
@@ -1206,6 +1555,7 @@ fn build_mapping_from_field_type<'a>(
     field_mapping_type: &'a FieldMappingType,
     field_path: &mut Vec<&'a str>,
     schema_builder: &mut SchemaBuilder,
+    nested_path_field_opt: &mut Option<Field>,
 ) -> anyhow::Result<(MappingTree, Vec<Field>)> {
     let field_name = field_name_for_field_path(field_path);
     match field_mapping_type {
@@ -1217,6 +1567,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::Text(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1228,6 +1579,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::I64(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1239,6 +1591,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::U64(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1250,6 +1603,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::F64(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1261,6 +1615,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::Bool(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1272,6 +1627,31 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::IpAddr(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
+            };
+            Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
+        }
+        FieldMappingType::GeoPoint(options) => {
+            let geo_point_options = get_geo_point_options(options);
+            let field = schema_builder.add_u64_field(&field_name, geo_point_options);
+            let mapping_leaf = MappingLeaf {
+                field,
+                typ: LeafType::GeoPoint(options.clone()),
+                cardinality: Cardinality::SingleValued,
+                concatenate: Vec::new(),
+                multi_fields: Vec::new(),
+            };
+            Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
+        }
+        FieldMappingType::DenseVector(options) => {
+            let dense_vector_options = get_dense_vector_options(options);
+            let field = schema_builder.add_bytes_field(&field_name, dense_vector_options);
+            let mapping_leaf = MappingLeaf {
+                field,
+                typ: LeafType::DenseVector(options.clone()),
+                cardinality: Cardinality::SingleValued,
+                concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1283,6 +1663,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::DateTime(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1294,6 +1675,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::Bytes(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1305,6 +1687,7 @@ fn build_mapping_from_field_type<'a>(
                 typ: LeafType::Json(options.clone()),
                 cardinality: *cardinality,
                 concatenate: Vec::new(),
+                multi_fields: Vec::new(),
             };
             Ok((MappingTree::Leaf(mapping_leaf), Vec::new()))
         }
@@ -1316,22 +1699,88 @@ fn build_mapping_from_field_type<'a>(
                 &entries.field_mappings,
                 field_path,
                 schema_builder,
+                nested_path_field_opt,
             )?;
             Ok((
                 MappingTree::Node(field_mappings),
                 concatenate_dynamic_fields,
             ))
         }
+        FieldMappingType::Nested(options) => {
+            // The path of the nested field a child document was extracted from is shared by all
+            // the nested fields.
+            let nested_path_field = *nested_path_field_opt.get_or_insert_with(|| {
+                schema_builder.add_text_field(NESTED_PATH_FIELD_NAME, STRING | FAST)
+            });
+            let stored_field_opt = if options.stored {
+                Some(schema_builder.add_json_field(&field_name, STORED))
+            } else {
+                None
+            };
+            // The objects are stored as a whole in the root document.
+            let unstored_entries: Vec<FieldMappingEntry> = options
+                .field_mappings
+                .iter()
+                .cloned()
+                .map(unstored_field_mapping_entry)
+                .collect();
+            let MappingNodeRoot {
+                field_mappings,
+                concatenate_dynamic_fields,
+            } = build_mapping_tree_from_entries(
+                &unstored_entries,
+                field_path,
+                schema_builder,
+                nested_path_field_opt,
+            )?;
+            if !concatenate_dynamic_fields.is_empty() {
+                bail!("nested field `{field_name}` cannot include dynamic fields");
+            }
+            let mapping_nested = MappingNested {
+                nested_path: field_name,
+                nested_path_field,
+                stored_field_opt,
+                field_mappings,
+                options: options.clone(),
+            };
+            Ok((MappingTree::Nested(mapping_nested), Vec::new()))
+        }
         FieldMappingType::Concatenate(_) => {
             bail!("Concatenate shouldn't reach build_mapping_from_field_type: this is a bug")
         }
     }
 }
 
+fn unstored_field_mapping_entry(mut entry: FieldMappingEntry) -> FieldMappingEntry {
+    match &mut entry.mapping_type {
+        FieldMappingType::Text(options, _) => options.stored = false,
+        FieldMappingType::I64(options, _)
+        | FieldMappingType::U64(options, _)
+        | FieldMappingType::F64(options, _) => options.stored = false,
+        FieldMappingType::Bool(options, _) => options.stored = false,
+        FieldMappingType::IpAddr(options, _) => options.stored = false,
+        FieldMappingType::GeoPoint(options) => options.stored = false,
+        FieldMappingType::DenseVector(options) => options.stored = false,
+        FieldMappingType::DateTime(options, _) => options.stored = false,
+        FieldMappingType::Bytes(options, _) => options.stored = false,
+        FieldMappingType::Json(options, _) => options.stored = false,
+        FieldMappingType::Object(options) => {
+            options.field_mappings = std::mem::take(&mut options.field_mappings)
+                .into_iter()
+                .map(unstored_field_mapping_entry)
+                .collect();
+        }
+        FieldMappingType::Nested(_) | FieldMappingType::Concatenate(_) => {}
+    }
+    entry
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::IpAddr;
 
+    use quickwit_query::geo::GeoPoint;
+    use quickwit_query::vector::{encode_vector, VectorSimilarity};
     use serde_json::{json, Value as JsonValue};
     use tantivy::schema::{Field, IntoIpv6Addr, OwnedValue as TantivyValue, Value};
     use tantivy::{DateTime, TantivyDocument as Document};
@@ -1344,8 +1793,9 @@ mod tests {
     };
     use crate::doc_mapper::date_time_type::QuickwitDateTimeOptions;
     use crate::doc_mapper::field_mapping_entry::{
-        BinaryFormat, QuickwitBoolOptions, QuickwitBytesOptions, QuickwitIpAddrOptions,
-        QuickwitNumericOptions, QuickwitTextOptions,
+        BinaryFormat, QuickwitBoolOptions, QuickwitBytesOptions, QuickwitDenseVectorOptions,
+        QuickwitGeoPointOptions, QuickwitIpAddrOptions, QuickwitNumericOptions,
+        QuickwitTextOptions,
     };
     use crate::Cardinality;
 
@@ -1524,6 +1974,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            multi_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1567,6 +2018,94 @@ mod tests {
         assert!(err.contains("expected string, got `1200`"));
     }
 
+    #[test]
+    fn test_parse_geo_point() {
+        let typ = LeafType::GeoPoint(QuickwitGeoPointOptions::default());
+        let field = Field::from_field_id(10);
+        let leaf_entry = MappingLeaf {
+            field,
+            typ,
+            cardinality: Cardinality::SingleValued,
+            concatenate: Vec::new(),
+            multi_fields: Vec::new(),
+        };
+        let expected_geo_point = GeoPoint::new(48.85, 2.35).unwrap();
+        for geo_point_json in [
+            json!({"lat": 48.85, "lon": 2.35}),
+            json!([2.35, 48.85]),
+            json!("48.85,2.35"),
+        ] {
+            let mut document = Document::default();
+            let mut path = Vec::new();
+            leaf_entry
+                .doc_from_json(geo_point_json, &mut document, &mut path)
+                .unwrap();
+            let values: Vec<u64> = document
+                .get_all(field)
+                .flat_map(|val| val.as_u64())
+                .collect();
+            assert_eq!(&values, &[expected_geo_point.encode()]);
+        }
+        let mut document = Document::default();
+        let mut path = vec!["location".to_string()];
+        let error = leaf_entry
+            .doc_from_json(json!({"lat": 100.0, "lon": 2.35}), &mut document, &mut path)
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("latitude must be between -90 and 90"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_parse_dense_vector() {
+        let typ = LeafType::DenseVector(QuickwitDenseVectorOptions {
+            description: None,
+            dims: 3,
+            similarity: VectorSimilarity::Cosine,
+            stored: true,
+        });
+        let field = Field::from_field_id(10);
+        let leaf_entry = MappingLeaf {
+            field,
+            typ,
+            cardinality: Cardinality::SingleValued,
+            concatenate: Vec::new(),
+            multi_fields: Vec::new(),
+        };
+        let mut document = Document::default();
+        let mut path = Vec::new();
+        leaf_entry
+            .doc_from_json(json!([0.5, 1, -2.0]), &mut document, &mut path)
+            .unwrap();
+        let values: Vec<&[u8]> = document
+            .get_all(field)
+            .flat_map(|val| val.as_bytes())
+            .collect();
+        assert_eq!(&values, &[&encode_vector(&[0.5, 1.0, -2.0])[..]]);
+
+        let mut path = vec!["embedding".to_string()];
+        for (vector_json, expected_error) in [
+            (
+                json!([0.5, 1.0]),
+                "expected vector with 3 dimensions, got 2",
+            ),
+            (json!([0.5, "1.0", 2.0]), "expected number, got `\"1.0\"`"),
+            (
+                json!([0.0, 0.0, 0.0]),
+                "does not support zero-magnitude vectors",
+            ),
+            (json!(0.5), "expected array of numbers, got `0.5`"),
+        ] {
+            let error = leaf_entry
+                .doc_from_json(vector_json, &mut document, &mut path)
+                .unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{error}");
+        }
+    }
+
     #[test]
     fn test_parse_i64_mutivalued() {
         let typ = LeafType::I64(QuickwitNumericOptions::default());
@@ -1576,6 +2115,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            multi_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1599,6 +2139,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            multi_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1617,6 +2158,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            multi_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = Vec::new();
@@ -1636,6 +2178,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            multi_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = vec!["root".to_string(), "my_field".to_string()];
@@ -1782,6 +2325,7 @@ mod tests {
             typ,
             cardinality: Cardinality::MultiValued,
             concatenate: Vec::new(),
+            multi_fields: Vec::new(),
         };
         let mut document = Document::default();
         let mut path = vec!["root".to_string(), "my_field".to_string()];
@@ -255,6 +255,7 @@ mod tests {
                     QuickwitJsonOptions::default(),
                     Cardinality::SingleValued,
                 ),
+                fields: Vec::new(),
             });
         let doc_mapper = doc_mapper_builder.try_build().unwrap();
         let schema = doc_mapper.schema();
@@ -753,6 +754,7 @@ mod tests {
                     },
                     Cardinality::SingleValued,
                 ),
+                fields: Vec::new(),
             });
         doc_mapper_builder
             .doc_mapping
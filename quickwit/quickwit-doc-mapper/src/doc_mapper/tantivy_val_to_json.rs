@@ -17,6 +17,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use quickwit_query::geo::GeoPoint;
+use quickwit_query::vector::decode_vector;
 use serde_json::Value as JsonValue;
 use tantivy::schema::OwnedValue as TantivyValue;
 
@@ -116,6 +118,26 @@ fn value_to_ip(value: TantivyValue) -> Result<JsonValue, TantivyValue> {
     .ok_or(value)
 }
 
+fn value_to_geo_point(value: TantivyValue) -> Result<JsonValue, TantivyValue> {
+    match value {
+        TantivyValue::U64(encoded_geo_point) => {
+            Ok(serde_json::to_value(GeoPoint::decode(encoded_geo_point))
+                .expect("Json serialization should never fail."))
+        }
+        _ => Err(value),
+    }
+}
+
+fn value_to_dense_vector(value: TantivyValue) -> Result<JsonValue, TantivyValue> {
+    let TantivyValue::Bytes(bytes) = &value else {
+        return Err(value);
+    };
+    let Some(vector) = decode_vector(bytes) else {
+        return Err(value);
+    };
+    Ok(serde_json::to_value(vector).expect("Json serialization should never fail."))
+}
+
 fn value_to_float(
     value: TantivyValue,
     numeric_options: &QuickwitNumericOptions,
@@ -235,6 +257,8 @@ pub fn formatted_tantivy_value_to_json(
         LeafType::Text(_) => value_to_string(value),
         LeafType::Bool(_) => value_to_bool(value),
         LeafType::IpAddr(_) => value_to_ip(value),
+        LeafType::GeoPoint(_) => value_to_geo_point(value),
+        LeafType::DenseVector(_) => value_to_dense_vector(value),
         LeafType::F64(numeric_options) => value_to_float(value, numeric_options),
         LeafType::U64(numeric_options) => value_to_u64(value, numeric_options),
         LeafType::I64(numeric_options) => value_to_i64(value, numeric_options),
@@ -208,6 +208,7 @@ mod tests {
                         QuickwitNumericOptions::default(),
                         Cardinality::SingleValued,
                     ),
+                    fields: Vec::new(),
                 },
                 FieldMappingEntry {
                     name: "message".to_string(),
@@ -215,6 +216,7 @@ mod tests {
                         QuickwitTextOptions::default(),
                         Cardinality::SingleValued,
                     ),
+                    fields: Vec::new(),
                 },
             ],
             timestamp_field: Some("timestamp".to_string()),
@@ -21,9 +21,11 @@ use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::ops::Bound;
 
+use quickwit_common::shared_consts::NESTED_PATH_FIELD_NAME;
 use quickwit_query::query_ast::{
-    FieldPresenceQuery, FullTextQuery, PhrasePrefixQuery, QueryAst, QueryAstVisitor, RangeQuery,
-    TermSetQuery, WildcardQuery,
+    FieldPresenceQuery, FullTextQuery, FuzzyQuery, GeoBoundingBoxQuery, GeoDistanceQuery, KnnQuery,
+    NestedQuery, PhrasePrefixQuery, QueryAst, QueryAstVisitor, RangeQuery, RegexQuery,
+    RuntimeFieldQuery, TermSetQuery, WildcardQuery,
 };
 use quickwit_query::tokenizers::TokenizerManager;
 use quickwit_query::{find_field_or_hit_dynamic, InvalidQuery};
@@ -48,6 +50,87 @@ impl<'a> QueryAstVisitor<'a> for RangeQueryFields {
     }
 }
 
+#[derive(Default)]
+struct GeoQueryFields {
+    geo_query_field_names: HashSet<String>,
+}
+
+impl<'a> QueryAstVisitor<'a> for GeoQueryFields {
+    type Err = Infallible;
+
+    fn visit_geo_bounding_box(
+        &mut self,
+        geo_bounding_box_query: &'a GeoBoundingBoxQuery,
+    ) -> Result<(), Infallible> {
+        self.geo_query_field_names
+            .insert(geo_bounding_box_query.field.to_string());
+        Ok(())
+    }
+
+    fn visit_geo_distance(
+        &mut self,
+        geo_distance_query: &'a GeoDistanceQuery,
+    ) -> Result<(), Infallible> {
+        self.geo_query_field_names
+            .insert(geo_distance_query.field.to_string());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct KnnQueryFields {
+    knn_query_field_names: HashSet<String>,
+}
+
+impl<'a> QueryAstVisitor<'a> for KnnQueryFields {
+    type Err = Infallible;
+
+    fn visit_knn(&mut self, knn_query: &'a KnnQuery) -> Result<(), Infallible> {
+        self.knn_query_field_names
+            .insert(knn_query.field.to_string());
+        if let Some(filter) = &knn_query.filter {
+            self.visit(filter)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct NestedQueryFields {
+    nested_query_field_names: HashSet<String>,
+}
+
+impl<'a> QueryAstVisitor<'a> for NestedQueryFields {
+    type Err = Infallible;
+
+    fn visit_nested(&mut self, nested_query: &'a NestedQuery) -> Result<(), Infallible> {
+        self.nested_query_field_names
+            .insert(NESTED_PATH_FIELD_NAME.to_string());
+        self.visit(&nested_query.query)
+    }
+}
+
+#[derive(Default)]
+struct RuntimeFieldQueryFields {
+    runtime_field_input_field_names: HashSet<String>,
+}
+
+impl<'a> QueryAstVisitor<'a> for RuntimeFieldQueryFields {
+    type Err = Infallible;
+
+    fn visit_runtime_field(
+        &mut self,
+        runtime_field_query: &'a RuntimeFieldQuery,
+    ) -> Result<(), Infallible> {
+        // Invalid scripts are reported when the query is built.
+        if let Ok(compiled_runtime_field) = runtime_field_query.runtime_field.compile() {
+            self.runtime_field_input_field_names
+                .extend(compiled_runtime_field.input_fields().iter().cloned());
+        }
+        Ok(())
+    }
+}
+
 #[derive(Default)]
 struct ExistsQueryFields {
     exists_query_field_names: HashSet<String>,
@@ -84,8 +167,32 @@ pub(crate) fn build_query(
     // This cannot fail. The error type is Infallible.
     let _: Result<(), Infallible> = exists_query_fields.visit(query_ast);
 
+    // Geo queries scan the fast field column of the geo point field.
+    let mut geo_query_fields = GeoQueryFields::default();
+    // This cannot fail. The error type is Infallible.
+    let _: Result<(), Infallible> = geo_query_fields.visit(query_ast);
+
+    // k-NN queries scan the fast field column of the dense vector field.
+    let mut knn_query_fields = KnnQueryFields::default();
+    // This cannot fail. The error type is Infallible.
+    let _: Result<(), Infallible> = knn_query_fields.visit(query_ast);
+
+    // Nested queries map child documents to their parent with the nested path fast field.
+    let mut nested_query_fields = NestedQueryFields::default();
+    // This cannot fail. The error type is Infallible.
+    let _: Result<(), Infallible> = nested_query_fields.visit(query_ast);
+
+    // Runtime field queries run their script on the fast fields it reads.
+    let mut runtime_field_query_fields = RuntimeFieldQueryFields::default();
+    // This cannot fail. The error type is Infallible.
+    let _: Result<(), Infallible> = runtime_field_query_fields.visit(query_ast);
+
     let mut fast_field_names = HashSet::new();
     fast_field_names.extend(range_query_fields.range_query_field_names);
+    fast_field_names.extend(geo_query_fields.geo_query_field_names);
+    fast_field_names.extend(knn_query_fields.knn_query_field_names);
+    fast_field_names.extend(nested_query_fields.nested_query_field_names);
+    fast_field_names.extend(runtime_field_query_fields.runtime_field_input_field_names);
     fast_field_names.extend(
         exists_query_fields
             .exists_query_field_names
@@ -160,6 +267,24 @@ impl<'a> QueryAstVisitor<'a> for ExtractTermSetFields<'_> {
         }
         Ok(())
     }
+
+    // Regex and fuzzy queries run an automaton over the entire term dictionary.
+    fn visit_regex(&mut self, regex_query: &'a RegexQuery) -> anyhow::Result<()> {
+        // Missing fields are rejected, or ignored if lenient, when building the query.
+        if let Ok(field) = regex_query.find_field(self.schema) {
+            self.term_dict_fields_to_warm_up.insert(field);
+        }
+        Ok(())
+    }
+
+    fn visit_fuzzy(&mut self, fuzzy_query: &'a FuzzyQuery) -> anyhow::Result<()> {
+        if let Ok((field, _field_entry, _path)) =
+            find_field_or_hit_dynamic(&fuzzy_query.field, self.schema)
+        {
+            self.term_dict_fields_to_warm_up.insert(field);
+        }
+        Ok(())
+    }
 }
 
 fn extract_term_set_query_fields(
@@ -283,6 +408,7 @@ fn extract_prefix_term_ranges(
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashSet;
     use std::ops::Bound;
 
     use quickwit_query::query_ast::{
@@ -559,6 +685,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_regex_and_fuzzy_query() {
+        check_build_query_static_mode(
+            "title:/hel+o/",
+            Vec::new(),
+            TestExpectation::Ok("RegexQuery"),
+        );
+        check_build_query_static_mode(
+            "title:helo~1",
+            Vec::new(),
+            TestExpectation::Ok("FuzzyTermQuery"),
+        );
+        check_build_query_static_mode(
+            "foo:/hel+o/",
+            Vec::new(),
+            TestExpectation::Err("invalid query: field does not exist: `foo`"),
+        );
+        check_build_query_static_lenient_mode(
+            "foo:helo~1",
+            Vec::new(),
+            TestExpectation::Ok("EmptyQuery"),
+        );
+        check_build_query_static_mode(
+            "title:helo~3",
+            Vec::new(),
+            TestExpectation::Err("fuzzy query distance must be at most 2, got 3"),
+        );
+    }
+
     #[test]
     fn test_datetime_range_query() {
         {
@@ -734,6 +889,25 @@ mod test {
         )
         .unwrap();
         assert!(warmup_info.term_dict_fields.is_empty());
+
+        // Regex and fuzzy queries walk the entire term dictionary.
+        for user_text in ["title:/hel+o/", "title:helo~1"] {
+            let query_ast = query_ast_from_user_text(user_text, None)
+                .parse_user_query(&[])
+                .unwrap();
+            let (_, warmup_info) = build_query(
+                &query_ast,
+                make_schema(true),
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap();
+            assert_eq!(
+                warmup_info.term_dict_fields,
+                HashSet::from([tantivy::schema::Field::from_field_id(0)])
+            );
+        }
     }
 
     #[test]
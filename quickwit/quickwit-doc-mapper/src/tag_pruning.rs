@@ -61,6 +61,13 @@ fn extract_unsimplified_tags_filter_ast(query_ast: QueryAst) -> UnsimplifiedTagF
             }
             collect_tag_filters_for_clause(clause_with_resolved_occur)
         }
+        QueryAst::DisMax(dis_max_query) => UnsimplifiedTagFilterAst::Or(
+            dis_max_query
+                .disjuncts
+                .into_iter()
+                .map(extract_unsimplified_tags_filter_ast)
+                .collect(),
+        ),
         QueryAst::Term(term_query) => UnsimplifiedTagFilterAst::Tag {
             is_present: true,
             field: term_query.field,
@@ -113,6 +120,24 @@ fn extract_unsimplified_tags_filter_ast(query_ast: QueryAst) -> UnsimplifiedTagF
                 value: wildcard_query.value,
             }
         }
+        QueryAst::Regex(_)
+        | QueryAst::Fuzzy(_)
+        | QueryAst::GeoBoundingBox(_)
+        | QueryAst::GeoDistance(_)
+        | QueryAst::Ids(_)
+        | QueryAst::RuntimeField(_) => UnsimplifiedTagFilterAst::Uninformative,
+        QueryAst::Knn(knn_query) => {
+            // Nearest neighbors are only searched among the documents matching the filter.
+            if let Some(filter) = knn_query.filter {
+                extract_unsimplified_tags_filter_ast(*filter)
+            } else {
+                UnsimplifiedTagFilterAst::Uninformative
+            }
+        }
+        QueryAst::Nested(_) => {
+            // Tags cannot be defined inside nested fields.
+            UnsimplifiedTagFilterAst::Uninformative
+        }
         QueryAst::Boost { underlying, .. } => extract_unsimplified_tags_filter_ast(*underlying),
         QueryAst::UserInput(_user_text_query) => {
             panic!("Extract unsimplified should only be called on AST without UserInputQuery.");
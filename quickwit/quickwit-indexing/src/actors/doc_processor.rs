@@ -45,6 +45,7 @@ use tokio::runtime::Handle;
 #[cfg(feature = "vrl")]
 use super::vrl_processing::*;
 use crate::actors::Indexer;
+use crate::dead_letter_queue::{DeadLetter, DeadLetterPosition, DeadLetterQueue};
 use crate::models::{
     NewPublishLock, NewPublishToken, ProcessedDoc, ProcessedDocBatch, PublishLock, RawDocBatch,
 };
@@ -99,6 +100,19 @@ pub enum DocProcessorError {
     Transform(VrlTerminate),
 }
 
+impl DocProcessorError {
+    /// Returns the kind of the error, as reported in the dead letter queue of the source.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::DocMapperParsing(_) => "doc_mapper_error",
+            Self::JsonParsing(_) => "json_parse_error",
+            Self::OltpLogsParsing(_) | Self::OltpTracesParsing(_) => "otlp_parse_error",
+            #[cfg(feature = "vrl")]
+            Self::Transform(_) => "transform_error",
+        }
+    }
+}
+
 impl From<OtlpLogsError> for DocProcessorError {
     fn from(error: OtlpLogsError) -> Self {
         Self::OltpLogsParsing(error)
@@ -136,7 +150,10 @@ fn try_into_vrl_doc(
     num_bytes: usize,
 ) -> Result<VrlDoc, DocProcessorError> {
     let vrl_value = match input_format {
-        SourceInputFormat::Json => serde_json::from_slice::<VrlValue>(&raw_doc)?,
+        // CSV and Parquet documents are converted to JSON by the file source.
+        SourceInputFormat::Csv | SourceInputFormat::Json | SourceInputFormat::Parquet => {
+            serde_json::from_slice::<VrlValue>(&raw_doc)?
+        }
         SourceInputFormat::PlainText => {
             let mut map = std::collections::BTreeMap::new();
             let key = vrl::value::KeyString::from(PLAIN_TEXT);
@@ -161,7 +178,8 @@ fn try_into_json_docs(
     num_bytes: usize,
 ) -> JsonDocIterator {
     match input_format {
-        SourceInputFormat::Json => {
+        // CSV and Parquet documents are converted to JSON by the file source.
+        SourceInputFormat::Csv | SourceInputFormat::Json | SourceInputFormat::Parquet => {
             let json_doc_result = serde_json::from_slice::<JsonObject>(&raw_doc)
                 .map(|json_obj| JsonDoc::new(json_obj, num_bytes));
             JsonDocIterator::from(json_doc_result)
@@ -417,6 +435,7 @@ pub struct DocProcessor {
     #[cfg(feature = "vrl")]
     transform_opt: Option<VrlProgram>,
     input_format: SourceInputFormat,
+    dead_letter_queue_opt: Option<DeadLetterQueue>,
 }
 
 impl DocProcessor {
@@ -443,9 +462,16 @@ impl DocProcessor {
                 .map(VrlProgram::try_from_transform_config)
                 .transpose()?,
             input_format,
+            dead_letter_queue_opt: None,
         })
     }
 
+    /// Sends the documents rejected by the doc processor to the given dead letter queue.
+    pub fn with_dead_letter_queue(mut self, dead_letter_queue: DeadLetterQueue) -> Self {
+        self.dead_letter_queue_opt = Some(dead_letter_queue);
+        self
+    }
+
     // Extract a timestamp from a tantivy document.
     //
     // If the timestamp is set up in the docmapper and the timestamp is missing,
@@ -466,8 +492,17 @@ impl DocProcessor {
         Ok(Some(timestamp))
     }
 
-    fn process_raw_doc(&mut self, raw_doc: Bytes, processed_docs: &mut Vec<ProcessedDoc>) {
+    fn process_raw_doc(
+        &mut self,
+        raw_doc: Bytes,
+        processed_docs: &mut Vec<ProcessedDoc>,
+        mut dead_letters_opt: Option<&mut Vec<DeadLetter>>,
+        dead_letter_positions: impl Fn() -> Vec<DeadLetterPosition>,
+    ) {
         let num_bytes = raw_doc.len();
+        // A raw doc (an OTLP payload, for instance) may yield several docs: only the first error
+        // is recorded in the dead letter queue.
+        let mut dead_letter_raw_doc_opt = dead_letters_opt.as_ref().map(|_| raw_doc.clone());
 
         #[cfg(feature = "vrl")]
         let transform_opt = self.transform_opt.as_mut();
@@ -490,6 +525,18 @@ impl DocProcessor {
                         source_id = self.counters.source_id,
                         "{error}",
                     );
+                    if let (Some(raw_doc), Some(dead_letters)) =
+                        (dead_letter_raw_doc_opt.take(), dead_letters_opt.as_mut())
+                    {
+                        let dead_letter = DeadLetter::new(
+                            self.counters.index_id.clone(),
+                            self.counters.source_id.clone(),
+                            &error,
+                            &raw_doc,
+                            dead_letter_positions(),
+                        );
+                        dead_letters.push(dead_letter);
+                    }
                     self.counters.record_error(error, num_bytes as u64);
                 }
             }
@@ -499,12 +546,13 @@ impl DocProcessor {
     fn process_json_doc(&self, json_doc: JsonDoc) -> Result<ProcessedDoc, DocProcessorError> {
         let num_bytes = json_doc.num_bytes;
 
-        let (partition, doc) = self
+        let (partition, doc, nested_docs) = self
             .doc_mapper
-            .doc_from_json_obj(json_doc.json_obj, json_doc.num_bytes as u64)?;
+            .docs_from_json_obj(json_doc.json_obj, json_doc.num_bytes as u64)?;
         let timestamp_opt = self.extract_timestamp(&doc)?;
         Ok(ProcessedDoc {
             doc,
+            nested_docs,
             timestamp_opt,
             partition,
             num_bytes,
@@ -578,12 +626,34 @@ impl Handler<RawDocBatch> for DocProcessor {
             return Ok(());
         }
         let mut processed_docs: Vec<ProcessedDoc> = Vec::with_capacity(raw_doc_batch.docs.len());
+        let has_dead_letter_queue = self.dead_letter_queue_opt.is_some();
+        let mut dead_letters: Vec<DeadLetter> = Vec::new();
+        let num_docs = raw_doc_batch.docs.len();
+        let checkpoint_delta = &raw_doc_batch.checkpoint_delta;
 
-        for raw_doc in raw_doc_batch.docs {
+        for (doc_idx, raw_doc) in raw_doc_batch.docs.into_iter().enumerate() {
             let _protected_zone_guard = ctx.protect_zone();
-            self.process_raw_doc(raw_doc, &mut processed_docs);
+            self.process_raw_doc(
+                raw_doc,
+                &mut processed_docs,
+                has_dead_letter_queue.then_some(&mut dead_letters),
+                || DeadLetterPosition::for_doc(checkpoint_delta, doc_idx, num_docs),
+            );
             ctx.record_progress();
         }
+        if let Some(dead_letter_queue) = &self.dead_letter_queue_opt {
+            // The batch is only forwarded, and its checkpoint only advanced, once the rejected
+            // documents are safely written: otherwise, the pipeline fails and restarts from the
+            // last published checkpoint.
+            ctx.protect_future(dead_letter_queue.push(&dead_letters))
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to write {} rejected document(s) to dead letter queue",
+                        dead_letters.len()
+                    )
+                })?;
+        }
         let processed_doc_batch = ProcessedDocBatch::new(
             processed_docs,
             raw_doc_batch.checkpoint_delta,
@@ -634,6 +704,9 @@ mod tests {
     use quickwit_common::uri::Uri;
     use quickwit_config::{build_doc_mapper, SearchSettings};
     use quickwit_doc_mapper::{default_doc_mapper_for_test, DocMapper};
+    use quickwit_ingest::{
+        DocCommand, IngestResponse, IngestServiceClient, IngestServiceError, MockIngestService,
+    };
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
     use quickwit_opentelemetry::otlp::{OtlpGrpcLogsService, OtlpGrpcTracesService};
     use quickwit_proto::opentelemetry::proto::collector::logs::v1::ExportLogsServiceRequest;
@@ -642,6 +715,7 @@ mod tests {
     use quickwit_proto::opentelemetry::proto::common::v1::AnyValue as OtlpAnyValue;
     use quickwit_proto::opentelemetry::proto::logs::v1::{LogRecord, ResourceLogs, ScopeLogs};
     use quickwit_proto::opentelemetry::proto::trace::v1::{ResourceSpans, ScopeSpans, Span};
+    use quickwit_proto::types::Position;
     use serde_json::Value as JsonValue;
     use tantivy::schema::NamedFieldDocument;
     use tantivy::Document;
@@ -727,6 +801,132 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_doc_processor_dead_letter_queue() {
+        let universe = Universe::with_accelerated_time();
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = universe.create_test_mailbox();
+        let (dead_letters_tx, dead_letters_rx) = std::sync::mpsc::channel();
+
+        let mut mock_ingest_service = MockIngestService::new();
+        mock_ingest_service
+            .expect_ingest()
+            .once()
+            .returning(move |ingest_request| {
+                for doc_batch in ingest_request.doc_batches {
+                    for doc_command in doc_batch.into_iter() {
+                        if let DocCommand::Ingest { payload } = doc_command {
+                            let dead_letter: DeadLetter = serde_json::from_slice(&payload).unwrap();
+                            dead_letters_tx.send(dead_letter).unwrap();
+                        }
+                    }
+                }
+                Ok(IngestResponse {
+                    num_docs_for_processing: 2,
+                })
+            });
+        let dead_letter_queue = DeadLetterQueue::IngestV1 {
+            index_id: "my-dead-letters".to_string(),
+            ingest_service: IngestServiceClient::from_mock(mock_ingest_service),
+        };
+        let doc_processor = DocProcessor::try_new(
+            "my-index".to_string(),
+            "my-source".to_string(),
+            doc_mapper,
+            indexer_mailbox,
+            None,
+            SourceInputFormat::Json,
+        )
+        .unwrap()
+        .with_dead_letter_queue(dead_letter_queue);
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch::for_test(
+                &[
+                    br#"{"body": "happy", "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#, // missing timestamp
+                    br#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#, // ok
+                    b"{", // invalid json
+                ],
+                0..3,
+            ))
+            .await
+            .unwrap();
+        let counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters.valid.get_num_docs(), 1);
+        assert_eq!(counters.num_invalid_docs(), 2);
+
+        let batches = indexer_inbox.drain_for_test_typed::<ProcessedDocBatch>();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].docs.len(), 1);
+
+        let dead_letters: Vec<DeadLetter> = dead_letters_rx.try_iter().collect();
+        assert_eq!(dead_letters.len(), 2);
+
+        assert_eq!(dead_letters[0].index_id, "my-index");
+        assert_eq!(dead_letters[0].source_id, "my-source");
+        assert_eq!(dead_letters[0].error_kind, "doc_mapper_error");
+        assert!(dead_letters[0]
+            .payload
+            .contains("2021-12-19T16:39:57+00:00"));
+        assert_eq!(dead_letters[0].positions.len(), 1);
+        assert_eq!(
+            dead_letters[0].positions[0].to_position_inclusive,
+            Position::offset(0u64).to_string()
+        );
+        assert_eq!(dead_letters[1].error_kind, "json_parse_error");
+        assert_eq!(dead_letters[1].payload, "{");
+        assert_eq!(
+            dead_letters[1].positions[0].to_position_inclusive,
+            Position::offset(2u64).to_string()
+        );
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_doc_processor_dead_letter_queue_failure() {
+        let universe = Universe::with_accelerated_time();
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = universe.create_test_mailbox();
+
+        let mut mock_ingest_service = MockIngestService::new();
+        mock_ingest_service
+            .expect_ingest()
+            .once()
+            .returning(|_| Err(IngestServiceError::Unavailable("test".to_string())));
+        let dead_letter_queue = DeadLetterQueue::IngestV1 {
+            index_id: "my-dead-letters".to_string(),
+            ingest_service: IngestServiceClient::from_mock(mock_ingest_service),
+        };
+        let doc_processor = DocProcessor::try_new(
+            "my-index".to_string(),
+            "my-source".to_string(),
+            doc_mapper,
+            indexer_mailbox,
+            None,
+            SourceInputFormat::Json,
+        )
+        .unwrap()
+        .with_dead_letter_queue(dead_letter_queue);
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch::for_test(&[b"{"], 0..1))
+            .await
+            .unwrap();
+        let (exit_status, _) = doc_processor_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Failure(_)));
+
+        // The batch is not forwarded to the indexer, so its checkpoint is not advanced.
+        let batches = indexer_inbox.drain_for_test_typed::<ProcessedDocBatch>();
+        assert!(batches.is_empty());
+
+        universe.assert_quit().await;
+    }
+
     const DOCMAPPER_WITH_PARTITION_JSON: &str = r#"
         {
             "tag_fields": ["tenant"],
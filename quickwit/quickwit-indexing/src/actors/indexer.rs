@@ -307,6 +307,7 @@ impl IndexerState {
         for doc in batch.docs {
             let ProcessedDoc {
                 doc,
+                nested_docs,
                 timestamp_opt,
                 partition,
                 num_bytes,
@@ -332,6 +333,13 @@ impl IndexerState {
                 record_timestamp(timestamp, &mut indexed_split.split_attrs.time_range);
             }
             let _protect_guard = ctx.protect_zone();
+            // Child documents must precede their parent document in the segment.
+            for nested_doc in nested_docs {
+                indexed_split
+                    .index_writer
+                    .add_document(nested_doc)
+                    .context("failed to add nested document")?;
+            }
             indexed_split
                 .index_writer
                 .add_document(doc)
@@ -802,6 +810,7 @@ mod tests {
                         timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
                         partition: 1,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     },
                     ProcessedDoc {
                         doc: doc!(
@@ -811,6 +820,7 @@ mod tests {
                         timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
                         partition: 1,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     },
                 ],
                 SourceCheckpointDelta::from_range(4..6),
@@ -828,6 +838,7 @@ mod tests {
                         timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435i64)),
                         partition: 1,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     },
                     ProcessedDoc {
                         doc: doc!(
@@ -837,6 +848,7 @@ mod tests {
                         timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
                         partition: 1,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     },
                 ],
                 SourceCheckpointDelta::from_range(6..8),
@@ -853,6 +865,7 @@ mod tests {
                     timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
                     partition: 1,
                     num_bytes: 30,
+                    nested_docs: Vec::new(),
                 }],
                 SourceCheckpointDelta::from_range(8..9),
                 false,
@@ -938,6 +951,7 @@ mod tests {
                 timestamp_opt: None,
                 partition: 0,
                 num_bytes,
+                nested_docs: Vec::new(),
             }
         };
         for i in 0..10_000 {
@@ -1016,6 +1030,7 @@ mod tests {
                             timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
                             partition: 1,
                             num_bytes: 30,
+                            nested_docs: Vec::new(),
                         }],
                         SourceCheckpointDelta::from_range(position..position + 1),
                         false,
@@ -1094,6 +1109,7 @@ mod tests {
                     timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
                     partition: 1,
                     num_bytes: 30,
+                    nested_docs: Vec::new(),
                 }],
                 SourceCheckpointDelta::from_range(8..9),
                 false,
@@ -1181,6 +1197,7 @@ mod tests {
                     timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
                     partition: 1,
                     num_bytes: 30,
+                    nested_docs: Vec::new(),
                 }],
                 SourceCheckpointDelta::from_range(8..9),
                 false,
@@ -1265,6 +1282,7 @@ mod tests {
                         timestamp_opt: None,
                         partition: 1,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     },
                     ProcessedDoc {
                         doc: doc!(
@@ -1274,6 +1292,7 @@ mod tests {
                         timestamp_opt: None,
                         partition: 3,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     },
                 ],
                 SourceCheckpointDelta::from_range(8..9),
@@ -1359,6 +1378,7 @@ mod tests {
                         timestamp_opt: None,
                         partition,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     }],
                     SourceCheckpointDelta::from_range(partition..partition + 1),
                     false,
@@ -1437,6 +1457,7 @@ mod tests {
                         timestamp_opt: None,
                         partition: 0,
                         num_bytes: 30,
+                        nested_docs: Vec::new(),
                     }],
                     SourceCheckpointDelta::from_range(0..1),
                     false,
@@ -1508,6 +1529,7 @@ mod tests {
                     timestamp_opt: None,
                     partition: 0,
                     num_bytes: 30,
+                    nested_docs: Vec::new(),
                 }],
                 SourceCheckpointDelta::from_range(0..1),
                 false,
@@ -1564,6 +1586,7 @@ mod tests {
                     timestamp_opt: None,
                     partition: 0,
                     num_bytes: 30,
+                    nested_docs: Vec::new(),
                 }],
                 SourceCheckpointDelta::from_range(0..1),
                 true,
@@ -34,6 +34,7 @@ use quickwit_config::{IndexingSettings, RetentionPolicy, SourceConfig};
 use quickwit_doc_mapper::DocMapper;
 use quickwit_ingest::IngesterPool;
 use quickwit_proto::indexing::IndexingPipelineId;
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::{MetastoreError, MetastoreServiceClient};
 use quickwit_proto::types::ShardId;
 use quickwit_storage::{Storage, StorageResolver};
@@ -47,10 +48,12 @@ use crate::actors::publisher::PublisherType;
 use crate::actors::sequencer::Sequencer;
 use crate::actors::uploader::UploaderType;
 use crate::actors::{Indexer, Packager, Publisher, Uploader};
+use crate::dead_letter_queue::DeadLetterQueue;
 use crate::merge_policy::MergePolicy;
 use crate::models::IndexingStatistics;
 use crate::source::{
-    quickwit_supported_sources, AssignShards, Assignment, SourceActor, SourceRuntime,
+    quickwit_supported_sources, AssignShards, Assignment, ReindexSearcher, SourceActor,
+    SourceRuntime,
 };
 use crate::split_store::IndexingSplitStore;
 use crate::SplitsUpdateMailbox;
@@ -418,7 +421,7 @@ impl IndexingPipeline {
             .set_kill_switch(self.kill_switch.clone())
             .spawn(indexer);
 
-        let doc_processor = DocProcessor::try_new(
+        let mut doc_processor = DocProcessor::try_new(
             index_id.to_string(),
             source_id.to_string(),
             self.params.doc_mapper.clone(),
@@ -426,6 +429,17 @@ impl IndexingPipeline {
             self.params.source_config.transform_config.clone(),
             self.params.source_config.input_format,
         )?;
+        if let Some(dead_letter_queue_config) = &self.params.source_config.dead_letter_queue_config
+        {
+            let dead_letter_queue = DeadLetterQueue::try_new(
+                dead_letter_queue_config,
+                &self.params.source_storage_resolver,
+                self.params.ingest_router_opt.as_ref(),
+                &self.params.queues_dir_path,
+            )
+            .await?;
+            doc_processor = doc_processor.with_dead_letter_queue(dead_letter_queue);
+        }
         let (doc_processor_mailbox, doc_processor_handle) = ctx
             .spawn_actor()
             .set_backpressure_micros_counter(
@@ -444,6 +458,7 @@ impl IndexingPipeline {
             storage_resolver: self.params.source_storage_resolver.clone(),
             event_broker: self.params.event_broker.clone(),
             indexing_setting: self.params.indexing_settings.clone(),
+            reindex_searcher_opt: self.params.reindex_searcher_opt.clone(),
         };
         let source = ctx
             .protect_future(quickwit_supported_sources().load_source(source_runtime))
@@ -595,6 +610,8 @@ pub struct IndexingPipelineParams {
     pub source_storage_resolver: StorageResolver,
     pub ingester_pool: IngesterPool,
     pub queues_dir_path: PathBuf,
+    pub reindex_searcher_opt: Option<Arc<dyn ReindexSearcher>>,
+    pub ingest_router_opt: Option<IngestRouterServiceClient>,
     pub params_fingerprint: u64,
 
     pub event_broker: EventBroker,
@@ -652,6 +669,7 @@ mod tests {
             source_params: SourceParams::file_from_str(test_file).unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -721,6 +739,8 @@ mod tests {
             merge_policy: default_merge_policy(),
             retention_policy: None,
             queues_dir_path: PathBuf::from("./queues"),
+            reindex_searcher_opt: None,
+            ingest_router_opt: None,
             max_concurrent_split_uploads_index: 4,
             max_concurrent_split_uploads_merge: 5,
             cooperative_indexing_permits: None,
@@ -773,6 +793,7 @@ mod tests {
             source_params: SourceParams::file_from_str(test_file).unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -831,6 +852,8 @@ mod tests {
             ingester_pool: IngesterPool::default(),
             metastore: MetastoreServiceClient::from_mock(mock_metastore),
             queues_dir_path: PathBuf::from("./queues"),
+            reindex_searcher_opt: None,
+            ingest_router_opt: None,
             storage,
             split_store,
             merge_policy: default_merge_policy(),
@@ -879,6 +902,7 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -932,6 +956,8 @@ mod tests {
             ingester_pool: IngesterPool::default(),
             metastore,
             queues_dir_path: PathBuf::from("./queues"),
+            reindex_searcher_opt: None,
+            ingest_router_opt: None,
             storage,
             split_store,
             merge_policy: default_merge_policy(),
@@ -985,6 +1011,7 @@ mod tests {
             source_params: SourceParams::file_from_str(test_file).unwrap(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let source_config_clone = source_config.clone();
 
@@ -1060,6 +1087,8 @@ mod tests {
             ingester_pool: IngesterPool::default(),
             metastore: MetastoreServiceClient::from_mock(mock_metastore),
             queues_dir_path: PathBuf::from("./queues"),
+            reindex_searcher_opt: None,
+            ingest_router_opt: None,
             storage,
             split_store,
             merge_policy: default_merge_policy(),
@@ -51,6 +51,7 @@ use quickwit_proto::indexing::{
     ApplyIndexingPlanRequest, ApplyIndexingPlanResponse, IndexingError, IndexingPipelineId,
     IndexingTask, MergePipelineId, PipelineMetrics,
 };
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::{
     IndexMetadataRequest, IndexMetadataSubrequest, IndexesMetadataRequest,
     ListIndexesMetadataRequest, ListSplitsRequest, MetastoreResult, MetastoreService,
@@ -67,7 +68,7 @@ use super::merge_pipeline::{MergePipeline, MergePipelineParams};
 use super::{MergePlanner, MergeSchedulerService};
 use crate::actors::merge_pipeline::FinishPendingMergesAndShutdownPipeline;
 use crate::models::{DetachIndexingPipeline, DetachMergePipeline, ObservePipeline, SpawnPipeline};
-use crate::source::{AssignShards, Assignment};
+use crate::source::{AssignShards, Assignment, ReindexSearcher};
 use crate::split_store::{LocalSplitStore, SplitStoreQuota};
 use crate::{IndexingPipeline, IndexingPipelineParams, IndexingSplitStore, IndexingStatistics};
 
@@ -119,6 +120,8 @@ pub struct IndexingService {
     cooperative_indexing_permits: Option<Arc<Semaphore>>,
     merge_io_throughput_limiter_opt: Option<Limiter>,
     event_broker: EventBroker,
+    reindex_searcher_opt: Option<Arc<dyn ReindexSearcher>>,
+    ingest_router_opt: Option<IngestRouterServiceClient>,
 }
 
 impl Debug for IndexingService {
@@ -182,9 +185,25 @@ impl IndexingService {
             merge_io_throughput_limiter_opt,
             cooperative_indexing_permits,
             event_broker,
+            reindex_searcher_opt: None,
+            ingest_router_opt: None,
         })
     }
 
+    /// Sets the searcher used by the reindex sources to read the documents of their source
+    /// index. Reindex sources fail to start without it.
+    pub fn with_reindex_searcher(mut self, reindex_searcher: Arc<dyn ReindexSearcher>) -> Self {
+        self.reindex_searcher_opt = Some(reindex_searcher);
+        self
+    }
+
+    /// Sets the ingest router used by the dead letter queues of the sources when ingest v2 is
+    /// enabled. Sources with a dead letter queue fail to start without it.
+    pub fn with_ingest_router(mut self, ingest_router: IngestRouterServiceClient) -> Self {
+        self.ingest_router_opt = Some(ingest_router);
+        self
+    }
+
     async fn detach_indexing_pipeline(
         &mut self,
         pipeline_uid: &PipelineUid,
@@ -339,6 +358,8 @@ impl IndexingService {
             source_config,
             ingester_pool: self.ingester_pool.clone(),
             queues_dir_path: self.queue_dir_path.clone(),
+            reindex_searcher_opt: self.reindex_searcher_opt.clone(),
+            ingest_router_opt: self.ingest_router_opt.clone(),
             source_storage_resolver: self.storage_resolver.clone(),
             params_fingerprint,
 
@@ -1084,6 +1105,7 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let spawn_pipeline_msg = SpawnPipeline {
             index_id: index_id.clone(),
@@ -1165,6 +1187,7 @@ mod tests {
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let create_index_request = CreateIndexRequest::try_from_index_and_source_configs(
             &index_config,
@@ -1245,6 +1268,7 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let add_source_request =
             AddSourceRequest::try_from_source_config(index_uid.clone(), &source_config_1).unwrap();
@@ -1296,6 +1320,7 @@ mod tests {
             source_params: SourceParams::Kafka(kafka_params),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let add_source_request_2 =
             AddSourceRequest::try_from_source_config(index_uid.clone(), &source_config_2).unwrap();
@@ -1459,6 +1484,7 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let create_index_request =
             CreateIndexRequest::try_from_index_config(&index_config).unwrap();
@@ -1588,6 +1614,7 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         index_metadata
             .sources
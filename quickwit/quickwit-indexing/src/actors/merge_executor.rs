@@ -41,7 +41,7 @@ use quickwit_proto::metastore::{
 };
 use quickwit_proto::types::{NodeId, SplitId};
 use quickwit_query::get_quickwit_fastfield_normalizer_manager;
-use quickwit_query::query_ast::QueryAst;
+use quickwit_query::query_ast::{num_root_docs, QueryAst};
 use tantivy::directory::{Advice, DirectoryClone, MmapDirectory, RamDirectory};
 use tantivy::index::SegmentId;
 use tantivy::tokenizer::TokenizerManager;
@@ -429,7 +429,8 @@ impl MergeExecutor {
             };
 
         let merged_segment_reader = SegmentReader::open(&merged_segment)?;
-        let num_docs = merged_segment_reader.num_docs() as u64;
+        // Child documents extracted from nested fields are not accounted for.
+        let num_docs = num_root_docs(&merged_segment_reader)? as u64;
         let uncompressed_docs_size_in_bytes = (num_docs as f32
             * split.uncompressed_docs_size_in_bytes as f32
             / split.num_docs as f32) as u64;
@@ -519,8 +520,7 @@ impl MergeExecutor {
                     "Delete all documents matched by query `{:?}`",
                     parsed_query_ast
                 );
-                let (query, _) =
-                    doc_mapper.query(union_index.schema(), &parsed_query_ast, false)?;
+                let query = doc_mapper.delete_query(union_index.schema(), &parsed_query_ast)?;
                 index_writer.delete_query(query)?;
             }
             debug!("commit-delete-operations");
@@ -0,0 +1,511 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use bytes::Bytes;
+use quickwit_common::is_false;
+use quickwit_config::{enable_ingest_v2, DeadLetterQueueConfig, INGEST_V2_SOURCE_ID};
+use quickwit_ingest::{
+    get_ingest_api_service, CommitType, DocBatchBuilder, IngestRequest, IngestService,
+    IngestServiceClient, IngestServiceError, JsonDocBatchV2Builder,
+};
+use quickwit_metastore::checkpoint::SourceCheckpointDelta;
+use quickwit_proto::ingest::router::{
+    IngestRequestV2, IngestRouterService, IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::ingest::CommitTypeV2;
+use quickwit_proto::types::{DocUidGenerator, IndexId, Position, SourceId};
+use quickwit_storage::{Storage, StorageResolver};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::actors::DocProcessorError;
+
+/// A document rejected by the doc processor of a source, as recorded in the dead letter queue of
+/// the source.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeadLetter {
+    /// Unique ID of the dead letter (ULID).
+    pub dead_letter_id: String,
+    /// Time at which the document was rejected, in seconds since the Unix epoch.
+    pub timestamp: i64,
+    #[schema(value_type = String)]
+    pub index_id: IndexId,
+    #[schema(value_type = String)]
+    pub source_id: SourceId,
+    /// Kind of the rejection: `json_parse_error`, `transform_error`, `doc_mapper_error`, or
+    /// `otlp_parse_error`.
+    pub error_kind: String,
+    /// Rejection reason.
+    pub error: String,
+    /// Position range of the document in its partition, or position ranges of the batch
+    /// containing the document when the source does not assign one offset per document.
+    pub positions: Vec<DeadLetterPosition>,
+    /// Raw payload of the document, base64-encoded if it is not valid UTF-8.
+    pub payload: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub payload_base64: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeadLetterPosition {
+    pub partition_id: String,
+    pub from_position_exclusive: String,
+    pub to_position_inclusive: String,
+}
+
+impl DeadLetterPosition {
+    /// Returns the position of the `doc_idx`-th document of a batch of `num_docs` documents. The
+    /// position of a document can only be derived from the checkpoint delta of its batch when the
+    /// batch spans a single partition in which each document occupies one offset (ingest API,
+    /// Kafka, etc.). Otherwise, the position ranges of the whole batch are returned.
+    pub fn for_doc(
+        checkpoint_delta: &SourceCheckpointDelta,
+        doc_idx: usize,
+        num_docs: usize,
+    ) -> Vec<Self> {
+        if let Some(doc_position) = Self::doc_position(checkpoint_delta, doc_idx, num_docs) {
+            return vec![doc_position];
+        }
+        Self::from_checkpoint_delta(checkpoint_delta)
+    }
+
+    fn doc_position(
+        checkpoint_delta: &SourceCheckpointDelta,
+        doc_idx: usize,
+        num_docs: usize,
+    ) -> Option<Self> {
+        if checkpoint_delta.num_partitions() != 1 || doc_idx >= num_docs {
+            return None;
+        }
+        let (partition_id, partition_delta) = checkpoint_delta.iter().next()?;
+        let first_offset = match &partition_delta.from {
+            Position::Beginning => 0,
+            from_position => from_position.as_u64()? + 1,
+        };
+        let last_offset = partition_delta.to.as_u64()?;
+
+        if last_offset + 1 != first_offset + num_docs as u64 {
+            return None;
+        }
+        let offset = first_offset + doc_idx as u64;
+        let from_position_exclusive = if offset == 0 {
+            Position::Beginning
+        } else {
+            Position::offset(offset - 1)
+        };
+        let doc_position = DeadLetterPosition {
+            partition_id: partition_id.to_string(),
+            from_position_exclusive: from_position_exclusive.to_string(),
+            to_position_inclusive: Position::offset(offset).to_string(),
+        };
+        Some(doc_position)
+    }
+
+    pub fn from_checkpoint_delta(checkpoint_delta: &SourceCheckpointDelta) -> Vec<Self> {
+        checkpoint_delta
+            .iter()
+            .map(|(partition_id, partition_delta)| DeadLetterPosition {
+                partition_id: partition_id.to_string(),
+                from_position_exclusive: partition_delta.from.to_string(),
+                to_position_inclusive: partition_delta.to.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl DeadLetter {
+    pub fn new(
+        index_id: IndexId,
+        source_id: SourceId,
+        error: &DocProcessorError,
+        raw_doc: &Bytes,
+        positions: Vec<DeadLetterPosition>,
+    ) -> Self {
+        let (payload, payload_base64) = match std::str::from_utf8(raw_doc) {
+            Ok(payload) => (payload.to_string(), false),
+            Err(_) => (BASE64_STANDARD.encode(raw_doc), true),
+        };
+        Self {
+            dead_letter_id: Ulid::new().to_string(),
+            timestamp: time::OffsetDateTime::now_utc().unix_timestamp(),
+            index_id,
+            source_id,
+            error_kind: error.kind().to_string(),
+            error: error.to_string(),
+            positions,
+            payload,
+            payload_base64,
+        }
+    }
+
+    /// Returns the raw payload of the document.
+    pub fn decode_payload(&self) -> anyhow::Result<Bytes> {
+        if self.payload_base64 {
+            let payload = BASE64_STANDARD
+                .decode(&self.payload)
+                .context("failed to decode base64 payload")?;
+            Ok(Bytes::from(payload))
+        } else {
+            Ok(Bytes::from(self.payload.clone()))
+        }
+    }
+}
+
+/// Writes the documents rejected by the doc processor to the destination configured for the
+/// source: an index, with the same ingest API (v1 or v2) as the replay endpoint, or a storage.
+#[derive(Clone)]
+pub enum DeadLetterQueue {
+    IngestV1 {
+        index_id: IndexId,
+        ingest_service: IngestServiceClient,
+    },
+    IngestV2 {
+        index_id: IndexId,
+        ingest_router: IngestRouterServiceClient,
+    },
+    Storage {
+        storage: Arc<dyn Storage>,
+    },
+}
+
+impl fmt::Debug for DeadLetterQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IngestV1 { index_id, .. } => f
+                .debug_struct("DeadLetterQueue::IngestV1")
+                .field("index_id", index_id)
+                .finish(),
+            Self::IngestV2 { index_id, .. } => f
+                .debug_struct("DeadLetterQueue::IngestV2")
+                .field("index_id", index_id)
+                .finish(),
+            Self::Storage { storage } => f
+                .debug_struct("DeadLetterQueue::Storage")
+                .field("uri", storage.uri())
+                .finish(),
+        }
+    }
+}
+
+impl DeadLetterQueue {
+    pub async fn try_new(
+        dead_letter_queue_config: &DeadLetterQueueConfig,
+        storage_resolver: &StorageResolver,
+        ingest_router_opt: Option<&IngestRouterServiceClient>,
+        queues_dir_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let index_id = match dead_letter_queue_config {
+            DeadLetterQueueConfig::Index { index_id } => index_id,
+            DeadLetterQueueConfig::Storage { uri } => {
+                let storage = storage_resolver.resolve(uri).await?;
+                return Ok(Self::Storage { storage });
+            }
+        };
+        if enable_ingest_v2() {
+            let ingest_router = ingest_router_opt
+                .context("failed to create dead letter queue: ingest router is unavailable")?;
+            let dead_letter_queue = Self::IngestV2 {
+                index_id: index_id.clone(),
+                ingest_router: ingest_router.clone(),
+            };
+            return Ok(dead_letter_queue);
+        }
+        let ingest_api_service = get_ingest_api_service(queues_dir_path).await?;
+        let dead_letter_queue = Self::IngestV1 {
+            index_id: index_id.clone(),
+            ingest_service: IngestServiceClient::from_mailbox(ingest_api_service),
+        };
+        Ok(dead_letter_queue)
+    }
+
+    /// Writes the dead letters to the queue.
+    pub async fn push(&self, dead_letters: &[DeadLetter]) -> anyhow::Result<()> {
+        let Some(first_dead_letter) = dead_letters.first() else {
+            return Ok(());
+        };
+        match self {
+            Self::IngestV1 {
+                index_id,
+                ingest_service,
+            } => {
+                let mut doc_batch_builder = DocBatchBuilder::new(index_id.clone()).json_writer();
+
+                for dead_letter in dead_letters {
+                    doc_batch_builder.ingest_doc(dead_letter)?;
+                }
+                let ingest_request = IngestRequest {
+                    doc_batches: vec![doc_batch_builder.build()],
+                    commit: CommitType::Auto.into(),
+                };
+                ingest_service.clone().ingest(ingest_request).await?;
+            }
+            Self::IngestV2 {
+                index_id,
+                ingest_router,
+            } => {
+                let mut doc_batch_builder = JsonDocBatchV2Builder::default();
+                let mut doc_uid_generator = DocUidGenerator::default();
+
+                for dead_letter in dead_letters {
+                    doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), dead_letter)?;
+                }
+                let subrequest = IngestSubrequest {
+                    subrequest_id: 0,
+                    index_id: index_id.clone(),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                    doc_batch: Some(doc_batch_builder.build()),
+                };
+                let ingest_request = IngestRequestV2 {
+                    commit_type: CommitTypeV2::Auto as i32,
+                    subrequests: vec![subrequest],
+                };
+                let mut ingest_response = ingest_router.ingest(ingest_request).await?;
+
+                if let Some(ingest_failure) = ingest_response.failures.pop() {
+                    return Err(IngestServiceError::from(ingest_failure).into());
+                }
+            }
+            Self::Storage { storage } => {
+                let mut payload = Vec::new();
+
+                for dead_letter in dead_letters {
+                    serde_json::to_writer(&mut payload, dead_letter)?;
+                    payload.push(b'\n');
+                }
+                let file_path = dead_letter_file_path(
+                    &first_dead_letter.index_id,
+                    &first_dead_letter.source_id,
+                    &first_dead_letter.dead_letter_id,
+                );
+                storage.put(&file_path, Box::new(payload)).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path, relative to the storage prefix of the queue, of the file holding a batch of
+/// dead letters: `<index ID>/<source ID>/<ID of the first dead letter>.ndjson`.
+fn dead_letter_file_path(index_id: &str, source_id: &str, dead_letter_id: &str) -> PathBuf {
+    PathBuf::from(index_id)
+        .join(source_id)
+        .join(format!("{dead_letter_id}.ndjson"))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::DocParsingError;
+    use quickwit_ingest::{DocCommand, IngestResponse, MockIngestService};
+    use quickwit_proto::ingest::router::{
+        IngestFailure, IngestFailureReason, IngestResponseV2, MockIngestRouterService,
+    };
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+
+    fn dead_letter_for_test(raw_doc: &'static [u8]) -> DeadLetter {
+        let error =
+            DocProcessorError::from(DocParsingError::RequiredField("timestamp".to_string()));
+        let checkpoint_delta = SourceCheckpointDelta::from_range(0..4);
+        DeadLetter::new(
+            "test-index".to_string(),
+            "test-source".to_string(),
+            &error,
+            &Bytes::from_static(raw_doc),
+            DeadLetterPosition::for_doc(&checkpoint_delta, 1, 4),
+        )
+    }
+
+    #[test]
+    fn test_dead_letter() {
+        let dead_letter = dead_letter_for_test(br#"{"body": "foo"}"#);
+        assert_eq!(dead_letter.error_kind, "doc_mapper_error");
+        assert_eq!(
+            dead_letter.error,
+            "doc mapper parse error: the document must contain field \"timestamp\""
+        );
+        assert_eq!(dead_letter.positions.len(), 1);
+        assert_eq!(dead_letter.payload, r#"{"body": "foo"}"#);
+        assert!(!dead_letter.payload_base64);
+        assert_eq!(
+            dead_letter.decode_payload().unwrap(),
+            Bytes::from_static(br#"{"body": "foo"}"#)
+        );
+        let dead_letter = dead_letter_for_test(&[0xff, 0xfe]);
+        assert!(dead_letter.payload_base64);
+        assert_eq!(dead_letter.payload, "//4=");
+        assert_eq!(
+            dead_letter.decode_payload().unwrap(),
+            Bytes::from_static(&[0xff, 0xfe])
+        );
+    }
+
+    #[test]
+    fn test_dead_letter_position_for_doc() {
+        let checkpoint_delta = SourceCheckpointDelta::from_range(0..4);
+
+        let positions = DeadLetterPosition::for_doc(&checkpoint_delta, 0, 4);
+        assert_eq!(positions.len(), 1);
+        assert_eq!(
+            positions[0].from_position_exclusive,
+            Position::Beginning.to_string()
+        );
+        assert_eq!(
+            positions[0].to_position_inclusive,
+            Position::offset(0u64).to_string()
+        );
+
+        let checkpoint_delta = SourceCheckpointDelta::from_range(10..14);
+
+        let positions = DeadLetterPosition::for_doc(&checkpoint_delta, 2, 4);
+        assert_eq!(positions.len(), 1);
+        assert_eq!(
+            positions[0].from_position_exclusive,
+            Position::offset(11u64).to_string()
+        );
+        assert_eq!(
+            positions[0].to_position_inclusive,
+            Position::offset(12u64).to_string()
+        );
+
+        // The offsets do not match the documents (file source, for instance): the position range
+        // of the whole batch is returned.
+        let positions = DeadLetterPosition::for_doc(&checkpoint_delta, 2, 3);
+        assert_eq!(positions.len(), 1);
+        assert_eq!(
+            positions[0].from_position_exclusive,
+            Position::offset(9u64).to_string()
+        );
+        assert_eq!(
+            positions[0].to_position_inclusive,
+            Position::offset(13u64).to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingest_v1_dead_letter_queue() {
+        let dead_letters = vec![
+            dead_letter_for_test(br#"{"body": "foo"}"#),
+            dead_letter_for_test(b"{"),
+        ];
+        let expected_dead_letters = dead_letters.clone();
+
+        let mut mock_ingest_service = MockIngestService::new();
+        mock_ingest_service
+            .expect_ingest()
+            .once()
+            .returning(move |ingest_request| {
+                assert_eq!(ingest_request.doc_batches.len(), 1);
+
+                let doc_batch = ingest_request.doc_batches[0].clone();
+                assert_eq!(doc_batch.index_id, "test-dlq");
+
+                let ingested_dead_letters: Vec<DeadLetter> = doc_batch
+                    .into_iter()
+                    .map(|doc_command| match doc_command {
+                        DocCommand::Ingest { payload } => serde_json::from_slice(&payload).unwrap(),
+                        DocCommand::Commit => panic!("unexpected commit command"),
+                    })
+                    .collect();
+                assert_eq!(ingested_dead_letters, expected_dead_letters);
+
+                Ok(IngestResponse {
+                    num_docs_for_processing: 2,
+                })
+            });
+        let dead_letter_queue = DeadLetterQueue::IngestV1 {
+            index_id: "test-dlq".to_string(),
+            ingest_service: IngestServiceClient::from_mock(mock_ingest_service),
+        };
+        dead_letter_queue.push(&dead_letters).await.unwrap();
+        dead_letter_queue.push(&[]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ingest_v2_dead_letter_queue() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.subrequests.len(), 1);
+
+                let subrequest = &ingest_request.subrequests[0];
+                assert_eq!(subrequest.index_id, "test-dlq");
+                assert_eq!(subrequest.source_id, INGEST_V2_SOURCE_ID);
+                assert_eq!(subrequest.doc_batch.as_ref().unwrap().num_docs(), 1);
+
+                Ok(IngestResponseV2::default())
+            });
+        mock_ingest_router.expect_ingest().once().returning(|_| {
+            let ingest_failure = IngestFailure {
+                subrequest_id: 0,
+                index_id: "test-dlq".to_string(),
+                source_id: INGEST_V2_SOURCE_ID.to_string(),
+                reason: IngestFailureReason::IndexNotFound as i32,
+                ..Default::default()
+            };
+            Ok(IngestResponseV2 {
+                successes: Vec::new(),
+                failures: vec![ingest_failure],
+            })
+        });
+        let dead_letter_queue = DeadLetterQueue::IngestV2 {
+            index_id: "test-dlq".to_string(),
+            ingest_router: IngestRouterServiceClient::from_mock(mock_ingest_router),
+        };
+        let dead_letters = vec![dead_letter_for_test(br#"{"body": "foo"}"#)];
+        dead_letter_queue.push(&dead_letters).await.unwrap();
+        dead_letter_queue.push(&dead_letters).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_storage_dead_letter_queue() {
+        let storage = Arc::new(RamStorage::default());
+        let dead_letter_queue = DeadLetterQueue::Storage {
+            storage: storage.clone(),
+        };
+        let dead_letters = vec![
+            dead_letter_for_test(br#"{"body": "foo"}"#),
+            dead_letter_for_test(b"{"),
+        ];
+        dead_letter_queue.push(&dead_letters).await.unwrap();
+        dead_letter_queue.push(&[]).await.unwrap();
+
+        let file_path =
+            dead_letter_file_path("test-index", "test-source", &dead_letters[0].dead_letter_id);
+        assert_eq!(storage.list_files().await, vec![file_path.clone()]);
+
+        let payload = storage.get_all(&file_path).await.unwrap();
+        let written_dead_letters: Vec<DeadLetter> = payload
+            .as_slice()
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(written_dead_letters, dead_letters);
+    }
+}
@@ -19,12 +19,15 @@
 
 #![deny(clippy::disallowed_methods)]
 
+use std::sync::Arc;
+
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_cluster::Cluster;
 use quickwit_common::pubsub::EventBroker;
 use quickwit_config::NodeConfig;
 use quickwit_ingest::{IngestApiService, IngesterPool};
 use quickwit_proto::indexing::PipelineMetrics;
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_storage::StorageResolver;
 use tracing::info;
@@ -36,10 +39,12 @@ pub use crate::actors::{
 };
 pub use crate::controlled_directory::ControlledDirectory;
 use crate::models::IndexingStatistics;
+use crate::source::ReindexSearcher;
 pub use crate::split_store::{get_tantivy_directory_from_split_bundle, IndexingSplitStore};
 
 pub mod actors;
 mod controlled_directory;
+pub mod dead_letter_queue;
 pub mod merge_policy;
 mod metrics;
 pub mod models;
@@ -72,8 +77,10 @@ pub async fn start_indexing_service(
     cluster: Cluster,
     metastore: MetastoreServiceClient,
     ingester_pool: IngesterPool,
+    ingest_router: IngestRouterServiceClient,
     storage_resolver: StorageResolver,
     event_broker: EventBroker,
+    reindex_searcher: Arc<dyn ReindexSearcher>,
 ) -> anyhow::Result<Mailbox<IndexingService>> {
     info!("starting indexer service");
     let ingest_api_service_mailbox = universe.get_one::<IngestApiService>();
@@ -94,7 +101,9 @@ pub async fn start_indexing_service(
         storage_resolver,
         event_broker,
     )
-    .await?;
+    .await?
+    .with_reindex_searcher(reindex_searcher)
+    .with_ingest_router(ingest_router);
     let (indexing_service, _) = universe.spawn_builder().spawn(indexing_service);
     Ok(indexing_service)
 }
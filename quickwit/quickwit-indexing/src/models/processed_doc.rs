@@ -25,6 +25,8 @@ use tantivy::{DateTime, TantivyDocument};
 
 pub struct ProcessedDoc {
     pub doc: TantivyDocument,
+    /// Child documents extracted from the nested fields of `doc`, indexed right before it.
+    pub nested_docs: Vec<TantivyDocument>,
     pub timestamp_opt: Option<DateTime>,
     pub partition: u64,
     pub num_bytes: usize,
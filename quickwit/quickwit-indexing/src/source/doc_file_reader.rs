@@ -17,28 +17,106 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::VecDeque;
 use std::io;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use anyhow::Context;
-use async_compression::tokio::bufread::GzipDecoder;
-use bytes::Bytes;
+use anyhow::{bail, Context};
+use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
+use bytes::{Buf, Bytes};
+use csv::StringRecord;
+use parquet::errors::{ParquetError, Result as ParquetResult};
+use parquet::file::reader::{
+    ChunkReader, FileReader, Length, RowGroupReader, SerializedFileReader,
+};
 use quickwit_common::uri::Uri;
 use quickwit_common::Progress;
+use quickwit_config::SourceInputFormat;
 use quickwit_metastore::checkpoint::PartitionId;
 use quickwit_proto::metastore::SourceType;
 use quickwit_proto::types::Position;
-use quickwit_storage::StorageResolver;
+use quickwit_storage::{Storage, StorageResolver};
+use serde_json::Value as JsonValue;
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
 
 use super::{BatchBuilder, BATCH_NUM_BYTES_LIMIT};
 
+/// Number of bytes read at the beginning of a file to detect its compression.
+const MAGIC_BYTES_LEN: usize = 6;
+
 pub struct FileRecord {
     pub next_offset: u64,
     pub doc: Bytes,
     pub is_last: bool,
 }
 
+/// Compression codecs supported by the file reader.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Compression {
+    Bzip2,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "bz2" => Some(Self::Bzip2),
+            "gz" => Some(Self::Gzip),
+            "xz" => Some(Self::Xz),
+            "zst" | "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    fn from_magic_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"BZh") {
+            Some(Self::Bzip2)
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Self::Xz)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+
+    fn decoder(
+        self,
+        stream: Box<dyn AsyncRead + Send + Unpin>,
+    ) -> Box<dyn AsyncRead + Send + Unpin> {
+        let buf_reader = BufReader::new(stream);
+        match self {
+            Self::Bzip2 => Box::new(BzDecoder::new(buf_reader)),
+            Self::Gzip => Box::new(GzipDecoder::new(buf_reader)),
+            Self::Xz => Box::new(XzDecoder::new(buf_reader)),
+            Self::Zstd => Box::new(ZstdDecoder::new(buf_reader)),
+        }
+    }
+}
+
+/// Detects the compression of a file from its extension or, for files without
+/// a known compression extension, from its first bytes.
+async fn detect_compression(
+    storage: &dyn Storage,
+    uri: &Uri,
+    file_name: &Path,
+    file_size: usize,
+) -> anyhow::Result<Option<Compression>> {
+    if let Some(compression) = uri.extension().and_then(Compression::from_extension) {
+        return Ok(Some(compression));
+    }
+    let magic_bytes = storage
+        .get_slice(file_name, 0..file_size.min(MAGIC_BYTES_LEN))
+        .await?;
+    Ok(Compression::from_magic_bytes(&magic_bytes))
+}
+
 /// A helper wrapper that lets you skip bytes in compressed files where you
 /// cannot seek (e.g. gzip files).
 struct SkipReader {
@@ -54,6 +132,30 @@ impl SkipReader {
         }
     }
 
+    /// Opens a reader on the decompressed content of a file, starting at
+    /// `offset`.
+    async fn open(
+        storage: &dyn Storage,
+        file_name: &Path,
+        file_size: usize,
+        compression_opt: Option<Compression>,
+        offset: usize,
+    ) -> anyhow::Result<Self> {
+        // If the file is compressed, we can't seek to a specific offset. The
+        // reader starts from the beginning of the file, decompresses and skips
+        // the first `offset` bytes.
+        let reader = if let Some(compression) = compression_opt {
+            let stream = storage.get_slice_stream(file_name, 0..file_size).await?;
+            SkipReader::new(compression.decoder(stream), offset)
+        } else {
+            let stream = storage
+                .get_slice_stream(file_name, offset..file_size)
+                .await?;
+            SkipReader::new(stream, 0)
+        };
+        Ok(reader)
+    }
+
     async fn skip(&mut self) -> io::Result<()> {
         // allocate on the heap to avoid stack overflows
         let mut buf = vec![0u8; 64_000];
@@ -83,15 +185,298 @@ impl SkipReader {
     }
 }
 
+struct CsvRecord {
+    record_opt: Option<StringRecord>,
+    num_bytes: usize,
+    is_last: bool,
+}
+
+/// Reads the next CSV record, which spans several lines when a quoted field
+/// contains line breaks. Blank lines are skipped.
+async fn read_csv_record(reader: &mut SkipReader) -> anyhow::Result<CsvRecord> {
+    let mut buf = String::new();
+    let mut num_bytes = 0;
+    let is_last = loop {
+        let (line_size, is_last) = reader.read_line_and_peek(&mut buf).await?;
+        num_bytes += line_size;
+
+        if is_last {
+            break true;
+        }
+        if buf.trim().is_empty() {
+            buf.clear();
+            continue;
+        }
+        // Quotes are escaped by doubling them, so an odd number of quotes
+        // means that a quoted field is still open.
+        if buf.bytes().filter(|byte| *byte == b'"').count() % 2 == 0 {
+            break false;
+        }
+    };
+    let record_opt = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(buf.as_bytes())
+        .records()
+        .next()
+        .transpose()?;
+    Ok(CsvRecord {
+        record_opt,
+        num_bytes,
+        is_last,
+    })
+}
+
+/// Converts a CSV record into a JSON object keyed by the fields of the header
+/// row. Empty values are omitted.
+fn csv_record_to_json(header: &StringRecord, record: &StringRecord) -> Bytes {
+    let json_obj: serde_json::Map<String, JsonValue> = header
+        .iter()
+        .zip(record.iter())
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(field_name, value)| (field_name.to_string(), JsonValue::String(value.to_string())))
+        .collect();
+    serde_json::to_vec(&json_obj)
+        .expect("JSON object should be serializable")
+        .into()
+}
+
+/// Length of the end of a Parquet file: the length of its metadata, encoded on 4
+/// bytes, followed by the `PAR1` magic bytes.
+const PARQUET_FOOTER_TAIL_LEN: usize = 8;
+
+/// Byte chunks of a Parquet file fetched from the storage, exposed as a
+/// [`ChunkReader`] to the Parquet decoder. Reading bytes outside of the chunks
+/// fails.
+struct ParquetChunks {
+    file_size: usize,
+    // Pairs of chunk offsets and chunks.
+    chunks: Vec<(usize, Bytes)>,
+}
+
+impl ParquetChunks {
+    fn chunk(&self, start: u64, length: usize) -> ParquetResult<Bytes> {
+        let start = start as usize;
+
+        for (chunk_offset, chunk) in &self.chunks {
+            if start >= *chunk_offset && start + length <= chunk_offset + chunk.len() {
+                let chunk_start = start - chunk_offset;
+                return Ok(chunk.slice(chunk_start..chunk_start + length));
+            }
+        }
+        Err(ParquetError::General(format!(
+            "bytes {start}..{} of the Parquet file were not fetched",
+            start + length
+        )))
+    }
+}
+
+impl Length for ParquetChunks {
+    fn len(&self) -> u64 {
+        self.file_size as u64
+    }
+}
+
+impl ChunkReader for ParquetChunks {
+    type T = bytes::buf::Reader<Bytes>;
+
+    fn get_read(&self, start: u64) -> ParquetResult<Self::T> {
+        let start_usize = start as usize;
+        let chunk_len = self
+            .chunks
+            .iter()
+            .find(|(chunk_offset, chunk)| {
+                start_usize >= *chunk_offset && start_usize < chunk_offset + chunk.len()
+            })
+            .map(|(chunk_offset, chunk)| chunk_offset + chunk.len() - start_usize)
+            .unwrap_or_default();
+        Ok(self.chunk(start, chunk_len)?.reader())
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> ParquetResult<Bytes> {
+        self.chunk(start, length)
+    }
+}
+
+struct ParquetRowGroup {
+    num_rows: u64,
+    byte_range: Range<usize>,
+}
+
+/// Streams the rows of a Parquet file as JSON documents, one row group at a
+/// time. The offsets of Parquet files are row numbers rather than byte
+/// offsets.
+///
+/// The footer of the file is fetched first, then the row groups are fetched
+/// one at a time with range requests, so that the whole file is never held in
+/// memory.
+struct ParquetRows {
+    storage: Arc<dyn Storage>,
+    file_name: PathBuf,
+    file_size: usize,
+    footer_offset: usize,
+    footer: Bytes,
+    row_groups: Vec<ParquetRowGroup>,
+    next_row_group_idx: usize,
+    row_group_docs: VecDeque<Bytes>,
+    num_rows: u64,
+}
+
+impl ParquetRows {
+    async fn open(
+        storage: Arc<dyn Storage>,
+        file_name: &Path,
+        file_size: usize,
+        offset: usize,
+    ) -> anyhow::Result<Self> {
+        if file_size < PARQUET_FOOTER_TAIL_LEN {
+            bail!("file `{}` is not a Parquet file", file_name.display());
+        }
+        let footer_tail = storage
+            .get_slice(file_name, file_size - PARQUET_FOOTER_TAIL_LEN..file_size)
+            .await?;
+        let metadata_len =
+            u32::from_le_bytes(footer_tail.as_slice()[..4].try_into().unwrap()) as usize;
+        let footer_offset = file_size
+            .checked_sub(PARQUET_FOOTER_TAIL_LEN + metadata_len)
+            .with_context(|| format!("invalid footer in Parquet file `{}`", file_name.display()))?;
+        let footer = storage
+            .get_slice(file_name, footer_offset..file_size)
+            .await?;
+        let footer = Bytes::copy_from_slice(footer.as_slice());
+
+        let footer_chunks = ParquetChunks {
+            file_size,
+            chunks: vec![(footer_offset, footer.clone())],
+        };
+        let file_reader = SerializedFileReader::new(footer_chunks)?;
+        let metadata = file_reader.metadata();
+        let num_rows = metadata.file_metadata().num_rows() as u64;
+        let row_groups: Vec<ParquetRowGroup> = metadata
+            .row_groups()
+            .iter()
+            .map(|row_group_metadata| {
+                let (start, end) = row_group_metadata
+                    .columns()
+                    .iter()
+                    .map(|column_metadata| {
+                        let (start, length) = column_metadata.byte_range();
+                        (start as usize, (start + length) as usize)
+                    })
+                    .reduce(|(left_start, left_end), (right_start, right_end)| {
+                        (left_start.min(right_start), left_end.max(right_end))
+                    })
+                    .unwrap_or_default();
+                ParquetRowGroup {
+                    num_rows: row_group_metadata.num_rows() as u64,
+                    byte_range: start..end,
+                }
+            })
+            .collect();
+
+        let mut num_rows_to_skip = offset as u64;
+        let mut next_row_group_idx = 0;
+
+        while next_row_group_idx < row_groups.len() {
+            let row_group_num_rows = row_groups[next_row_group_idx].num_rows;
+
+            if num_rows_to_skip < row_group_num_rows {
+                break;
+            }
+            num_rows_to_skip -= row_group_num_rows;
+            next_row_group_idx += 1;
+        }
+        let mut parquet_rows = ParquetRows {
+            storage,
+            file_name: file_name.to_path_buf(),
+            file_size,
+            footer_offset,
+            footer,
+            row_groups,
+            next_row_group_idx,
+            row_group_docs: VecDeque::new(),
+            num_rows,
+        };
+        // If the file was entirely read before the checkpoint, all the row
+        // groups were skipped.
+        if num_rows_to_skip > 0 && parquet_rows.next_row_group_idx < parquet_rows.row_groups.len() {
+            parquet_rows.read_next_row_group().await?;
+            parquet_rows
+                .row_group_docs
+                .drain(..num_rows_to_skip as usize);
+        }
+        Ok(parquet_rows)
+    }
+
+    async fn read_next_row_group(&mut self) -> anyhow::Result<()> {
+        let row_group_idx = self.next_row_group_idx;
+        let byte_range = self.row_groups[row_group_idx].byte_range.clone();
+        let row_group_bytes = self
+            .storage
+            .get_slice(&self.file_name, byte_range.clone())
+            .await?;
+        let chunks = ParquetChunks {
+            file_size: self.file_size,
+            chunks: vec![
+                (self.footer_offset, self.footer.clone()),
+                (
+                    byte_range.start,
+                    Bytes::copy_from_slice(row_group_bytes.as_slice()),
+                ),
+            ],
+        };
+        // Decoding a row group is CPU-intensive.
+        let docs = tokio::task::spawn_blocking(move || decode_row_group(chunks, row_group_idx))
+            .await
+            .context("failed to decode Parquet row group")??;
+        self.row_group_docs.extend(docs);
+        self.next_row_group_idx += 1;
+        Ok(())
+    }
+
+    async fn next_doc(&mut self) -> anyhow::Result<Option<Bytes>> {
+        while self.row_group_docs.is_empty() {
+            if self.next_row_group_idx >= self.row_groups.len() {
+                return Ok(None);
+            }
+            self.read_next_row_group().await?;
+        }
+        Ok(self.row_group_docs.pop_front())
+    }
+}
+
+/// Decodes the rows of a row group into JSON documents.
+fn decode_row_group(chunks: ParquetChunks, row_group_idx: usize) -> anyhow::Result<Vec<Bytes>> {
+    let file_reader = SerializedFileReader::new(chunks)?;
+    let row_group_reader = file_reader.get_row_group(row_group_idx)?;
+    let mut docs = Vec::with_capacity(row_group_reader.metadata().num_rows() as usize);
+
+    for row_res in row_group_reader.get_row_iter(None)? {
+        let json_value = row_res?.to_json_value();
+        let doc = serde_json::to_vec(&json_value)?;
+        docs.push(doc.into());
+    }
+    Ok(docs)
+}
+
+enum DocFileRecords {
+    Lines(SkipReader),
+    Csv {
+        reader: SkipReader,
+        header: StringRecord,
+    },
+    Parquet(ParquetRows),
+}
+
 pub struct DocFileReader {
-    reader: SkipReader,
+    records: DocFileRecords,
     next_offset: u64,
 }
 
 impl DocFileReader {
     pub fn empty() -> Self {
         DocFileReader {
-            reader: SkipReader::new(Box::new(tokio::io::empty()), 0),
+            records: DocFileRecords::Lines(SkipReader::new(Box::new(tokio::io::empty()), 0)),
             next_offset: 0,
         }
     }
@@ -100,6 +485,7 @@ impl DocFileReader {
         storage_resolver: &StorageResolver,
         uri: &Uri,
         offset: usize,
+        input_format: SourceInputFormat,
     ) -> anyhow::Result<Self> {
         let (dir_uri, file_name) = dir_and_filename(uri)?;
         let storage = storage_resolver.resolve(&dir_uri).await?;
@@ -107,43 +493,87 @@ impl DocFileReader {
         if file_size == 0 {
             return Ok(DocFileReader::empty());
         }
-        // If it's a gzip file, we can't seek to a specific offset. `SkipReader`
-        // starts from the beginning of the file, decompresses and skips the
-        // first `offset` bytes.
-        let reader = if uri.extension() == Some("gz") {
-            let stream = storage.get_slice_stream(file_name, 0..file_size).await?;
-            let decompressed_stream = Box::new(GzipDecoder::new(BufReader::new(stream)));
-            DocFileReader {
-                reader: SkipReader::new(decompressed_stream, offset),
+        if input_format == SourceInputFormat::Parquet {
+            let parquet_rows =
+                ParquetRows::open(storage.clone(), file_name, file_size, offset).await?;
+            return Ok(DocFileReader {
+                records: DocFileRecords::Parquet(parquet_rows),
                 next_offset: offset as u64,
+            });
+        }
+        let compression_opt = detect_compression(&*storage, uri, file_name, file_size).await?;
+        let mut reader =
+            SkipReader::open(&*storage, file_name, file_size, compression_opt, offset).await?;
+        let mut next_offset = offset as u64;
+
+        let records = if input_format == SourceInputFormat::Csv {
+            // The header row is required to map the fields of the records, so
+            // it is read again when resuming from a checkpoint.
+            let header_record = if offset == 0 {
+                let header_record = read_csv_record(&mut reader).await?;
+                next_offset += header_record.num_bytes as u64;
+                header_record
+            } else {
+                let mut header_reader =
+                    SkipReader::open(&*storage, file_name, file_size, compression_opt, 0).await?;
+                read_csv_record(&mut header_reader).await?
+            };
+            DocFileRecords::Csv {
+                reader,
+                header: header_record.record_opt.unwrap_or_default(),
             }
         } else {
-            let stream = storage
-                .get_slice_stream(file_name, offset..file_size)
-                .await?;
-            DocFileReader {
-                reader: SkipReader::new(stream, 0),
-                next_offset: offset as u64,
-            }
+            DocFileRecords::Lines(reader)
         };
-        Ok(reader)
+        Ok(DocFileReader {
+            records,
+            next_offset,
+        })
     }
 
     /// Reads the next record from the underlying file. Returns `None` when EOF
     /// is reached.
     pub async fn next_record(&mut self) -> anyhow::Result<Option<FileRecord>> {
-        let mut buf = String::new();
-        // TODO retry if stream is broken (#5243)
-        let (bytes_read, is_last) = self.reader.read_line_and_peek(&mut buf).await?;
-        if bytes_read == 0 {
-            Ok(None)
-        } else {
-            self.next_offset += bytes_read as u64;
-            Ok(Some(FileRecord {
-                next_offset: self.next_offset,
-                doc: Bytes::from(buf),
-                is_last,
-            }))
+        match &mut self.records {
+            DocFileRecords::Lines(reader) => {
+                let mut buf = String::new();
+                // TODO retry if stream is broken (#5243)
+                let (bytes_read, is_last) = reader.read_line_and_peek(&mut buf).await?;
+                if bytes_read == 0 {
+                    Ok(None)
+                } else {
+                    self.next_offset += bytes_read as u64;
+                    Ok(Some(FileRecord {
+                        next_offset: self.next_offset,
+                        doc: Bytes::from(buf),
+                        is_last,
+                    }))
+                }
+            }
+            DocFileRecords::Csv { reader, header } => {
+                let csv_record = read_csv_record(reader).await?;
+                self.next_offset += csv_record.num_bytes as u64;
+
+                let Some(record) = csv_record.record_opt else {
+                    return Ok(None);
+                };
+                Ok(Some(FileRecord {
+                    next_offset: self.next_offset,
+                    doc: csv_record_to_json(header, &record),
+                    is_last: csv_record.is_last,
+                }))
+            }
+            DocFileRecords::Parquet(parquet_rows) => {
+                let Some(doc) = parquet_rows.next_doc().await? else {
+                    return Ok(None);
+                };
+                self.next_offset += 1;
+                Ok(Some(FileRecord {
+                    next_offset: self.next_offset,
+                    doc,
+                    is_last: self.next_offset >= parquet_rows.num_rows,
+                }))
+            }
         }
     }
 }
@@ -161,6 +591,7 @@ impl ObjectUriBatchReader {
         partition_id: PartitionId,
         uri: &Uri,
         position: Position,
+        input_format: SourceInputFormat,
     ) -> anyhow::Result<Self> {
         let current_offset = match position {
             Position::Beginning => 0,
@@ -176,7 +607,8 @@ impl ObjectUriBatchReader {
                 })
             }
         };
-        let reader = DocFileReader::from_uri(storage_resolver, uri, current_offset).await?;
+        let reader =
+            DocFileReader::from_uri(storage_resolver, uri, current_offset, input_format).await?;
         Ok(ObjectUriBatchReader {
             partition_id,
             reader,
@@ -194,9 +626,8 @@ impl ObjectUriBatchReader {
         if self.is_eof {
             return Ok(batch_builder);
         }
-        let limit_num_bytes = self.current_offset + BATCH_NUM_BYTES_LIMIT as usize;
         let mut new_offset = self.current_offset;
-        while new_offset < limit_num_bytes {
+        while batch_builder.num_bytes < BATCH_NUM_BYTES_LIMIT {
             if let Some(record) = source_progress
                 .protect_future(self.reader.next_record())
                 .await?
@@ -309,11 +740,20 @@ pub mod file_test_helpers {
 
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
+    use std::io::{Cursor, Read, Write};
     use std::str::FromStr;
+    use std::sync::Arc;
 
+    use async_compression::tokio::write::{BzEncoder, GzipEncoder, XzEncoder, ZstdEncoder};
     use file_test_helpers::generate_index_doc_file;
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
+    use serde_json::json;
+    use tempfile::NamedTempFile;
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
 
     use super::*;
 
@@ -374,9 +814,10 @@ mod tests {
     async fn aux_test_full_read_record(file: impl AsRef<str>, expected_lines: usize) {
         let storage_resolver = StorageResolver::for_test();
         let uri = Uri::from_str(file.as_ref()).unwrap();
-        let mut doc_reader = DocFileReader::from_uri(&storage_resolver, &uri, 0)
-            .await
-            .unwrap();
+        let mut doc_reader =
+            DocFileReader::from_uri(&storage_resolver, &uri, 0, SourceInputFormat::Json)
+                .await
+                .unwrap();
         let mut parsed_lines = 0;
         while doc_reader.next_record().await.unwrap().is_some() {
             parsed_lines += 1;
@@ -409,9 +850,10 @@ mod tests {
         let storage_resolver = StorageResolver::for_test();
         let uri = Uri::from_str(file.as_ref()).unwrap();
         // read the first part of the file
-        let mut first_part_reader = DocFileReader::from_uri(&storage_resolver, &uri, 0)
-            .await
-            .unwrap();
+        let mut first_part_reader =
+            DocFileReader::from_uri(&storage_resolver, &uri, 0, SourceInputFormat::Json)
+                .await
+                .unwrap();
         let mut resume_offset = 0;
         let mut parsed_lines = 0;
         for _ in 0..stop_at_line {
@@ -425,10 +867,14 @@ mod tests {
             parsed_lines += 1;
         }
         // read the second part of the file
-        let mut second_part_reader =
-            DocFileReader::from_uri(&storage_resolver, &uri, resume_offset)
-                .await
-                .unwrap();
+        let mut second_part_reader = DocFileReader::from_uri(
+            &storage_resolver,
+            &uri,
+            resume_offset,
+            SourceInputFormat::Json,
+        )
+        .await
+        .unwrap();
         while let Some(rec) = second_part_reader.next_record().await.unwrap() {
             assert_eq!(Bytes::from(format!("{:0>7}\n", parsed_lines)), rec.doc);
             parsed_lines += 1;
@@ -456,6 +902,241 @@ mod tests {
         aux_test_resumed_read_record(dummy_doc_file_uri, 1000, 1000).await;
     }
 
+    async fn compress_bytes(bytes: &[u8], compression: Compression) -> Vec<u8> {
+        async fn encode(mut encoder: impl AsyncWrite + Unpin, bytes: &[u8]) {
+            encoder.write_all(bytes).await.unwrap();
+            encoder.shutdown().await.unwrap();
+        }
+        let mut compressed_bytes = Vec::new();
+        match compression {
+            Compression::Bzip2 => encode(BzEncoder::new(&mut compressed_bytes), bytes).await,
+            Compression::Gzip => encode(GzipEncoder::new(&mut compressed_bytes), bytes).await,
+            Compression::Xz => encode(XzEncoder::new(&mut compressed_bytes), bytes).await,
+            Compression::Zstd => encode(ZstdEncoder::new(&mut compressed_bytes), bytes).await,
+        }
+        compressed_bytes
+    }
+
+    async fn generate_compressed_index_doc_file(
+        compression: Compression,
+        suffix: &str,
+        lines: usize,
+    ) -> NamedTempFile {
+        let mut documents_bytes = Vec::new();
+        for i in 0..lines {
+            documents_bytes.extend_from_slice(format!("{:0>7}\n", i).as_bytes());
+        }
+        let compressed_bytes = compress_bytes(&documents_bytes, compression).await;
+        let mut temp_file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        temp_file.write_all(&compressed_bytes).unwrap();
+        temp_file.flush().unwrap();
+        temp_file
+    }
+
+    #[tokio::test]
+    async fn test_compression_from_magic_bytes() {
+        for compression in [
+            Compression::Bzip2,
+            Compression::Gzip,
+            Compression::Xz,
+            Compression::Zstd,
+        ] {
+            let compressed_bytes = compress_bytes(b"hello", compression).await;
+            assert_eq!(
+                Compression::from_magic_bytes(&compressed_bytes),
+                Some(compression)
+            );
+        }
+        assert_eq!(Compression::from_magic_bytes(b"{\"body\""), None);
+        assert_eq!(Compression::from_magic_bytes(b""), None);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_read_record_compressed() {
+        for (compression, suffix) in [
+            (Compression::Bzip2, ".bz2"),
+            (Compression::Xz, ".xz"),
+            (Compression::Zstd, ".zst"),
+            // The compression is detected from the magic bytes.
+            (Compression::Gzip, ""),
+            (Compression::Zstd, ".json"),
+        ] {
+            let dummy_doc_file = generate_compressed_index_doc_file(compression, suffix, 100).await;
+            let dummy_doc_file_uri = dummy_doc_file.path().to_str().unwrap();
+            aux_test_resumed_read_record(dummy_doc_file_uri, 100, 1).await;
+            aux_test_resumed_read_record(dummy_doc_file_uri, 100, 40).await;
+            aux_test_resumed_read_record(dummy_doc_file_uri, 100, 100).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_csv_records() {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        temp_file
+            .write_all(b"name,age,city\nalice,30,Paris\n\n\"bob, jr\",,\"New\nYork\"\n")
+            .unwrap();
+        temp_file.flush().unwrap();
+
+        let storage_resolver = StorageResolver::for_test();
+        let uri = Uri::from_str(temp_file.path().to_str().unwrap()).unwrap();
+        let mut doc_reader =
+            DocFileReader::from_uri(&storage_resolver, &uri, 0, SourceInputFormat::Csv)
+                .await
+                .unwrap();
+
+        let record = doc_reader.next_record().await.unwrap().unwrap();
+        let doc: JsonValue = serde_json::from_slice(&record.doc).unwrap();
+        assert_eq!(doc, json!({"name": "alice", "age": "30", "city": "Paris"}));
+        assert_eq!(record.next_offset, 29);
+        assert!(!record.is_last);
+
+        let resume_offset = record.next_offset as usize;
+
+        let record = doc_reader.next_record().await.unwrap().unwrap();
+        let doc: JsonValue = serde_json::from_slice(&record.doc).unwrap();
+        assert_eq!(doc, json!({"name": "bob, jr", "city": "New\nYork"}));
+        assert_eq!(record.next_offset, 52);
+        assert!(record.is_last);
+
+        assert!(doc_reader.next_record().await.unwrap().is_none());
+
+        // The header row is read again when resuming.
+        let mut doc_reader = DocFileReader::from_uri(
+            &storage_resolver,
+            &uri,
+            resume_offset,
+            SourceInputFormat::Csv,
+        )
+        .await
+        .unwrap();
+        let record = doc_reader.next_record().await.unwrap().unwrap();
+        let doc: JsonValue = serde_json::from_slice(&record.doc).unwrap();
+        assert_eq!(doc, json!({"name": "bob, jr", "city": "New\nYork"}));
+        assert_eq!(record.next_offset, 52);
+    }
+
+    fn generate_parquet_file(row_groups: &[&[(i64, &str)]]) -> NamedTempFile {
+        let schema = parse_message_type(
+            "message schema { REQUIRED INT64 id; REQUIRED BINARY name (UTF8); }",
+        )
+        .unwrap();
+        let temp_file = tempfile::Builder::new()
+            .suffix(".parquet")
+            .tempfile()
+            .unwrap();
+        let mut file_writer = SerializedFileWriter::new(
+            temp_file.reopen().unwrap(),
+            Arc::new(schema),
+            Arc::new(WriterProperties::builder().build()),
+        )
+        .unwrap();
+        for rows in row_groups {
+            let ids: Vec<i64> = rows.iter().map(|(id, _)| *id).collect();
+            let names: Vec<ByteArray> = rows
+                .iter()
+                .map(|(_, name)| ByteArray::from(*name))
+                .collect();
+
+            let mut row_group_writer = file_writer.next_row_group().unwrap();
+            let mut column_writer = row_group_writer.next_column().unwrap().unwrap();
+            column_writer
+                .typed::<Int64Type>()
+                .write_batch(&ids, None, None)
+                .unwrap();
+            column_writer.close().unwrap();
+
+            let mut column_writer = row_group_writer.next_column().unwrap().unwrap();
+            column_writer
+                .typed::<ByteArrayType>()
+                .write_batch(&names, None, None)
+                .unwrap();
+            column_writer.close().unwrap();
+            row_group_writer.close().unwrap();
+        }
+        file_writer.close().unwrap();
+        temp_file
+    }
+
+    #[test]
+    fn test_parquet_chunks() {
+        let parquet_chunks = ParquetChunks {
+            file_size: 100,
+            chunks: vec![
+                (10, Bytes::from_static(b"0123456789")),
+                (90, Bytes::from_static(b"abcdefghij")),
+            ],
+        };
+        assert_eq!(parquet_chunks.len(), 100);
+        assert_eq!(parquet_chunks.get_bytes(12, 3).unwrap(), "234");
+        assert_eq!(parquet_chunks.get_bytes(92, 8).unwrap(), "cdefghij");
+        parquet_chunks.get_bytes(0, 1).unwrap_err();
+        parquet_chunks.get_bytes(18, 4).unwrap_err();
+
+        let mut buffer = String::new();
+        parquet_chunks
+            .get_read(95)
+            .unwrap()
+            .read_to_string(&mut buffer)
+            .unwrap();
+        assert_eq!(buffer, "fghij");
+    }
+
+    #[tokio::test]
+    async fn test_read_parquet_batches() {
+        let parquet_file = generate_parquet_file(&[
+            &[(0, "foo"), (1, "bar"), (2, "baz")],
+            &[(3, "qux"), (4, "quux")],
+        ]);
+        let progress = Progress::default();
+        let storage_resolver = StorageResolver::for_test();
+        let uri = Uri::from_str(parquet_file.path().to_str().unwrap()).unwrap();
+        let partition = PartitionId::from("test");
+
+        for (from, expected_ids) in [
+            (Position::Beginning, vec![0, 1, 2, 3, 4]),
+            (Position::offset(2u64), vec![2, 3, 4]),
+            (Position::offset(3u64), vec![3, 4]),
+            (Position::offset(4u64), vec![4]),
+            (Position::offset(5u64), Vec::new()),
+            (Position::offset(7u64), Vec::new()),
+        ] {
+            let mut batch_reader = ObjectUriBatchReader::try_new(
+                &storage_resolver,
+                partition.clone(),
+                &uri,
+                from.clone(),
+                SourceInputFormat::Parquet,
+            )
+            .await
+            .unwrap();
+            let batch = batch_reader
+                .read_batch(&progress, SourceType::Unspecified)
+                .await
+                .unwrap();
+            assert!(batch_reader.is_eof());
+
+            let ids: Vec<i64> = batch
+                .docs
+                .iter()
+                .map(|doc| {
+                    let doc: JsonValue = serde_json::from_slice(doc).unwrap();
+                    doc["id"].as_i64().unwrap()
+                })
+                .collect();
+            assert_eq!(ids, expected_ids);
+
+            let mut expected_checkpoint_delta = SourceCheckpointDelta::default();
+            expected_checkpoint_delta
+                .record_partition_delta(
+                    partition.clone(),
+                    Position::offset(from.as_u64().unwrap_or(0)),
+                    Position::eof(from.as_u64().unwrap_or(0).max(5)),
+                )
+                .unwrap();
+            assert_eq!(batch.checkpoint_delta, expected_checkpoint_delta);
+        }
+    }
+
     async fn aux_test_full_read_batch(
         file: impl AsRef<str>,
         expected_lines: usize,
@@ -467,10 +1148,15 @@ mod tests {
         let storage_resolver = StorageResolver::for_test();
         let uri = Uri::from_str(file.as_ref()).unwrap();
         let partition = PartitionId::from("test");
-        let mut batch_reader =
-            ObjectUriBatchReader::try_new(&storage_resolver, partition.clone(), &uri, from)
-                .await
-                .unwrap();
+        let mut batch_reader = ObjectUriBatchReader::try_new(
+            &storage_resolver,
+            partition.clone(),
+            &uri,
+            from,
+            SourceInputFormat::Json,
+        )
+        .await
+        .unwrap();
 
         let mut parsed_lines = 0;
         let mut parsed_batches = 0;
@@ -171,6 +171,7 @@ impl TypedSourceFactory for FileSourceFactory {
                     partition_id,
                     &file_uri,
                     position,
+                    source_runtime.source_config.input_format,
                 )
                 .await?;
                 FileSourceState::Filepath {
@@ -242,6 +243,7 @@ mod tests {
             source_params: SourceParams::File(params.clone()),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let index_uid = IndexUid::new_with_random_ulid("test-index");
         let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config).build();
@@ -294,6 +296,7 @@ mod tests {
             source_params: SourceParams::File(params.clone()),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let index_uid = IndexUid::new_with_random_ulid("test-index");
         let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config).build();
@@ -358,6 +361,7 @@ mod tests {
             source_params: SourceParams::File(params.clone()),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let partition_id = PartitionId::from(uri.as_str());
         let source_checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
@@ -0,0 +1,637 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::{HttpSourceParams, SourceInputFormat};
+use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint, SourceCheckpointDelta};
+use quickwit_proto::metastore::SourceType;
+use quickwit_proto::types::{IndexId, NodeId, PipelineUid, Position, SourceId};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot, OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tokio::time;
+use tracing::{debug, info};
+
+use super::{BatchBuilder, BATCH_NUM_BYTES_LIMIT, EMIT_BATCHES_TIMEOUT};
+use crate::actors::DocProcessor;
+use crate::source::{Source, SourceContext, SourceRuntime, TypedSourceFactory};
+
+/// Handles of the HTTP sources running on this node, keyed by index ID and source ID.
+static HTTP_SOURCE_HANDLES: Lazy<Mutex<HashMap<(IndexId, SourceId), HttpSourceHandle>>> =
+    Lazy::new(Default::default);
+
+#[derive(Debug, Error, Serialize)]
+pub enum HttpSourceError {
+    #[error("HTTP source `{source_id}` of index `{index_id}` is not running on this node")]
+    NotRunning {
+        index_id: IndexId,
+        source_id: SourceId,
+    },
+    #[error(
+        "HTTP source `{source_id}` of index `{index_id}` is not running on this node but on node \
+         `{node_id}` ({host}): push the documents to this node instead"
+    )]
+    RunningOnOtherNode {
+        index_id: IndexId,
+        source_id: SourceId,
+        node_id: NodeId,
+        host: IpAddr,
+    },
+    #[error(
+        "push request of {num_bytes} bytes exceeds the maximum buffer size of HTTP source \
+         `{source_id}` ({max_buffer_size} bytes)"
+    )]
+    PayloadTooLarge {
+        source_id: SourceId,
+        num_bytes: usize,
+        max_buffer_size: usize,
+    },
+    #[error("buffer of HTTP source `{source_id}` is full, retry later")]
+    BufferFull { source_id: SourceId },
+    #[error(
+        "indexing pipeline of HTTP source `{source_id}` terminated before the documents were \
+         checkpointed"
+    )]
+    Terminated { source_id: SourceId },
+    #[error("input format `{input_format:?}` is not supported by HTTP source `{source_id}`")]
+    UnsupportedInputFormat {
+        source_id: SourceId,
+        input_format: SourceInputFormat,
+    },
+}
+
+impl ServiceError for HttpSourceError {
+    fn error_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::NotRunning { .. } => ServiceErrorCode::NotFound,
+            Self::RunningOnOtherNode { .. } => ServiceErrorCode::NotFound,
+            Self::PayloadTooLarge { .. } => ServiceErrorCode::BadRequest,
+            Self::BufferFull { .. } => ServiceErrorCode::TooManyRequests,
+            Self::Terminated { .. } => ServiceErrorCode::Unavailable,
+            Self::UnsupportedInputFormat { .. } => ServiceErrorCode::BadRequest,
+        }
+    }
+}
+
+/// Response returned to the producer once the pushed documents have been processed and their
+/// checkpoint has been published along with the splits containing them.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct HttpSourcePushResponse {
+    pub num_docs: u64,
+}
+
+/// Pushes the documents contained in `body` to the HTTP source `source_id` of index `index_id`
+/// running on this node, and waits for them to be checkpointed.
+///
+/// The body is split into one document per line for the `json` and `plain_text` input formats.
+/// For the OTLP input formats, the whole body is a single document.
+pub async fn push_to_http_source(
+    index_id: &str,
+    source_id: &str,
+    body: Bytes,
+    force_commit: bool,
+) -> Result<HttpSourcePushResponse, HttpSourceError> {
+    let handle_opt = HTTP_SOURCE_HANDLES
+        .lock()
+        .unwrap()
+        .get(&(index_id.to_string(), source_id.to_string()))
+        .cloned();
+    let Some(handle) = handle_opt else {
+        return Err(HttpSourceError::NotRunning {
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+        });
+    };
+    handle.push(body, force_commit).await
+}
+
+/// Splits a push request body into documents according to the input format of the source.
+/// Returns `None` if the input format cannot be pushed, i.e. CSV and Parquet.
+fn split_docs(body: Bytes, input_format: SourceInputFormat) -> Option<Vec<Bytes>> {
+    let docs = match input_format {
+        SourceInputFormat::Json | SourceInputFormat::PlainText => {
+            let mut docs = Vec::new();
+            let mut start = 0;
+
+            for line in body.split(|byte| *byte == b'\n') {
+                let end = start + line.len();
+
+                if !line.iter().all(u8::is_ascii_whitespace) {
+                    docs.push(body.slice(start..end));
+                }
+                start = end + 1;
+            }
+            docs
+        }
+        SourceInputFormat::OtlpLogsJson
+        | SourceInputFormat::OtlpLogsProtobuf
+        | SourceInputFormat::OtlpTracesJson
+        | SourceInputFormat::OtlpTracesProtobuf => {
+            if body.is_empty() {
+                Vec::new()
+            } else {
+                vec![body]
+            }
+        }
+        SourceInputFormat::Csv | SourceInputFormat::Parquet => return None,
+    };
+    Some(docs)
+}
+
+struct PushRequest {
+    docs: Vec<Bytes>,
+    force_commit: bool,
+    ack_tx: oneshot::Sender<HttpSourcePushResponse>,
+    // Releases the space reserved in the buffer of the source once dropped, i.e. when the
+    // documents are checkpointed or the source terminates.
+    permit: OwnedSemaphorePermit,
+}
+
+#[derive(Clone)]
+struct HttpSourceHandle {
+    source_id: SourceId,
+    pipeline_uid: PipelineUid,
+    input_format: SourceInputFormat,
+    max_buffer_size: usize,
+    buffer_semaphore: Arc<Semaphore>,
+    push_tx: mpsc::UnboundedSender<PushRequest>,
+}
+
+impl HttpSourceHandle {
+    async fn push(
+        &self,
+        body: Bytes,
+        force_commit: bool,
+    ) -> Result<HttpSourcePushResponse, HttpSourceError> {
+        let Some(docs) = split_docs(body, self.input_format) else {
+            return Err(HttpSourceError::UnsupportedInputFormat {
+                source_id: self.source_id.clone(),
+                input_format: self.input_format,
+            });
+        };
+
+        if docs.is_empty() {
+            return Ok(HttpSourcePushResponse { num_docs: 0 });
+        }
+        let num_bytes: usize = docs.iter().map(|doc| doc.len()).sum();
+
+        if num_bytes > self.max_buffer_size {
+            return Err(HttpSourceError::PayloadTooLarge {
+                source_id: self.source_id.clone(),
+                num_bytes,
+                max_buffer_size: self.max_buffer_size,
+            });
+        }
+        let permit = match self
+            .buffer_semaphore
+            .clone()
+            .try_acquire_many_owned(num_bytes as u32)
+        {
+            Ok(permit) => permit,
+            Err(TryAcquireError::NoPermits) => {
+                return Err(HttpSourceError::BufferFull {
+                    source_id: self.source_id.clone(),
+                });
+            }
+            Err(TryAcquireError::Closed) => {
+                return Err(HttpSourceError::Terminated {
+                    source_id: self.source_id.clone(),
+                });
+            }
+        };
+        let (ack_tx, ack_rx) = oneshot::channel();
+        let push_request = PushRequest {
+            docs,
+            force_commit,
+            ack_tx,
+            permit,
+        };
+        if self.push_tx.send(push_request).is_err() {
+            return Err(HttpSourceError::Terminated {
+                source_id: self.source_id.clone(),
+            });
+        }
+        ack_rx.await.map_err(|_| HttpSourceError::Terminated {
+            source_id: self.source_id.clone(),
+        })
+    }
+}
+
+/// A push request whose documents were emitted but are not checkpointed yet.
+struct PendingAck {
+    last_offset: u64,
+    num_docs: u64,
+    ack_tx: oneshot::Sender<HttpSourcePushResponse>,
+    _permit: OwnedSemaphorePermit,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct HttpSourceCounters {
+    num_docs_received: u64,
+    num_bytes_received: u64,
+    num_docs_acked: u64,
+}
+
+/// A source that receives documents pushed over HTTP by the `/{index_id}/sources/{source_id}/push`
+/// REST endpoint. Producers receive a response once their documents are checkpointed.
+pub struct HttpSource {
+    index_id: IndexId,
+    handle: HttpSourceHandle,
+    push_rx: mpsc::UnboundedReceiver<PushRequest>,
+    partition_id: PartitionId,
+    // Offset of the next document pushed to the source.
+    next_offset: u64,
+    pending_acks: VecDeque<PendingAck>,
+    counters: HttpSourceCounters,
+}
+
+impl fmt::Debug for HttpSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpSource")
+            .field("index_id", &self.index_id)
+            .field("source_id", &self.handle.source_id)
+            .finish()
+    }
+}
+
+impl HttpSource {
+    fn registry_key(&self) -> (IndexId, SourceId) {
+        (self.index_id.clone(), self.handle.source_id.clone())
+    }
+
+    fn process_push_request(
+        &mut self,
+        push_request: PushRequest,
+        batch_builder: &mut BatchBuilder,
+    ) {
+        let PushRequest {
+            docs,
+            force_commit,
+            ack_tx,
+            permit,
+        } = push_request;
+
+        let num_docs = docs.len() as u64;
+        let from_position = if self.next_offset == 0 {
+            Position::Beginning
+        } else {
+            Position::offset(self.next_offset - 1)
+        };
+        let last_offset = self.next_offset + num_docs - 1;
+        let to_position = Position::offset(last_offset);
+
+        for doc in docs {
+            self.counters.num_bytes_received += doc.len() as u64;
+            batch_builder.add_doc(doc);
+        }
+        batch_builder
+            .checkpoint_delta
+            .record_partition_delta(self.partition_id.clone(), from_position, to_position)
+            .expect("HTTP source positions should be increasing");
+
+        if force_commit {
+            batch_builder.force_commit();
+        }
+        self.next_offset = last_offset + 1;
+        self.counters.num_docs_received += num_docs;
+        self.pending_acks.push_back(PendingAck {
+            last_offset,
+            num_docs,
+            ack_tx,
+            _permit: permit,
+        });
+    }
+}
+
+#[async_trait]
+impl Source for HttpSource {
+    async fn initialize(
+        &mut self,
+        _doc_processor_mailbox: &Mailbox<DocProcessor>,
+        _ctx: &SourceContext,
+    ) -> Result<(), ActorExitStatus> {
+        HTTP_SOURCE_HANDLES
+            .lock()
+            .unwrap()
+            .insert(self.registry_key(), self.handle.clone());
+        info!(
+            index_id=%self.index_id,
+            source_id=%self.handle.source_id,
+            "HTTP source ready to receive documents"
+        );
+        Ok(())
+    }
+
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut batch_builder = BatchBuilder::new(SourceType::Http);
+        let deadline = time::sleep(*EMIT_BATCHES_TIMEOUT);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                push_request_opt = self.push_rx.recv() => {
+                    // The source holds a sender in its handle, so the channel cannot be closed.
+                    let Some(push_request) = push_request_opt else {
+                        break;
+                    };
+                    self.process_push_request(push_request, &mut batch_builder);
+
+                    if batch_builder.num_bytes >= BATCH_NUM_BYTES_LIMIT {
+                        break;
+                    }
+                }
+                _ = &mut deadline => {
+                    break;
+                }
+            }
+            ctx.record_progress();
+        }
+        if !batch_builder.checkpoint_delta.is_empty() {
+            debug!(
+                num_docs=%batch_builder.docs.len(),
+                num_bytes=%batch_builder.num_bytes,
+                "sending doc batch to indexer"
+            );
+            let message = batch_builder.build();
+            ctx.send_message(doc_processor_mailbox, message).await?;
+        }
+        Ok(Duration::default())
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        _ctx: &SourceContext,
+    ) -> anyhow::Result<()> {
+        let Some(position) = checkpoint.position_for_partition(&self.partition_id) else {
+            return Ok(());
+        };
+        let checkpointed_offset = position
+            .as_u64()
+            .context("HTTP source position should be stored as u64")?;
+
+        while let Some(pending_ack) = self.pending_acks.front() {
+            if pending_ack.last_offset > checkpointed_offset {
+                break;
+            }
+            let pending_ack = self
+                .pending_acks
+                .pop_front()
+                .expect("pending ack should exist");
+            self.counters.num_docs_acked += pending_ack.num_docs;
+            // The producer may have gone away in the meantime.
+            let _ = pending_ack.ack_tx.send(HttpSourcePushResponse {
+                num_docs: pending_ack.num_docs,
+            });
+        }
+        Ok(())
+    }
+
+    async fn finalize(
+        &mut self,
+        _exit_status: &ActorExitStatus,
+        _ctx: &SourceContext,
+    ) -> anyhow::Result<()> {
+        let mut handles_guard = HTTP_SOURCE_HANDLES.lock().unwrap();
+        let registry_key = self.registry_key();
+
+        // A new pipeline for the same source may have already replaced our handle.
+        if handles_guard
+            .get(&registry_key)
+            .is_some_and(|handle| handle.pipeline_uid == self.handle.pipeline_uid)
+        {
+            handles_guard.remove(&registry_key);
+        }
+        drop(handles_guard);
+
+        // Fail the pending and in-flight push requests right away instead of letting them wait
+        // for the source to be dropped.
+        self.handle.buffer_semaphore.close();
+        self.push_rx.close();
+        self.pending_acks.clear();
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn observable_state(&self) -> JsonValue {
+        serde_json::json!({
+            "index_id": self.index_id,
+            "source_id": self.handle.source_id,
+            "next_offset": self.next_offset,
+            "num_pending_acks": self.pending_acks.len(),
+            "counters": self.counters,
+        })
+    }
+}
+
+pub struct HttpSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for HttpSourceFactory {
+    type Source = HttpSource;
+    type Params = HttpSourceParams;
+
+    async fn typed_create_source(
+        source_runtime: SourceRuntime,
+        source_params: HttpSourceParams,
+    ) -> anyhow::Result<Self::Source> {
+        let checkpoint = source_runtime.fetch_checkpoint().await?;
+        let partition_id = PartitionId::from(source_runtime.source_id());
+        let next_offset = match checkpoint.position_for_partition(&partition_id) {
+            Some(position) => {
+                position
+                    .as_u64()
+                    .context("HTTP source position should be stored as u64")?
+                    + 1
+            }
+            None => 0,
+        };
+        let max_buffer_size = source_params.max_buffer_size.as_u64().min(u32::MAX as u64) as usize;
+        let (push_tx, push_rx) = mpsc::unbounded_channel();
+        let handle = HttpSourceHandle {
+            source_id: source_runtime.source_id().to_string(),
+            pipeline_uid: source_runtime.pipeline_uid(),
+            input_format: source_runtime.source_config.input_format,
+            max_buffer_size,
+            buffer_semaphore: Arc::new(Semaphore::new(max_buffer_size)),
+            push_tx,
+        };
+        Ok(HttpSource {
+            index_id: source_runtime.index_id().to_string(),
+            handle,
+            push_rx,
+            partition_id,
+            next_offset,
+            pending_acks: VecDeque::new(),
+            counters: HttpSourceCounters::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_proto::types::IndexUid;
+
+    use super::*;
+    use crate::models::RawDocBatch;
+    use crate::source::tests::SourceRuntimeBuilder;
+    use crate::source::{SourceActor, SuggestTruncate};
+
+    #[test]
+    fn test_split_docs() {
+        let body = Bytes::from_static(b"{\"a\": 1}\n\n  \n{\"b\": 2}\r\n{\"c\": 3}");
+        let docs = split_docs(body.clone(), SourceInputFormat::Json).unwrap();
+        assert_eq!(docs.len(), 3);
+        assert_eq!(&docs[0][..], b"{\"a\": 1}");
+        assert_eq!(&docs[1][..], b"{\"b\": 2}\r");
+        assert_eq!(&docs[2][..], b"{\"c\": 3}");
+
+        let docs = split_docs(body.clone(), SourceInputFormat::OtlpLogsJson).unwrap();
+        assert_eq!(docs, [body.clone()]);
+
+        let docs = split_docs(Bytes::new(), SourceInputFormat::OtlpTracesProtobuf).unwrap();
+        assert!(docs.is_empty());
+
+        assert!(split_docs(body.clone(), SourceInputFormat::Csv).is_none());
+        assert!(split_docs(body, SourceInputFormat::Parquet).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_source() {
+        let universe = Universe::with_accelerated_time();
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+        let index_uid = IndexUid::for_test("test-http-index", 0);
+        let source_params = HttpSourceParams {
+            max_buffer_size: bytesize::ByteSize::b(64),
+        };
+        let source_config = SourceConfig::for_test(
+            "test-http-source",
+            SourceParams::Http(source_params.clone()),
+        );
+        let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config)
+            .with_mock_metastore(None)
+            .build();
+        let http_source = HttpSourceFactory::typed_create_source(source_runtime, source_params)
+            .await
+            .unwrap();
+        let source_actor = SourceActor {
+            source: Box::new(http_source),
+            doc_processor_mailbox,
+        };
+        let (source_mailbox, source_handle) = universe.spawn_builder().spawn(source_actor);
+
+        let error = push_to_http_source(
+            "test-http-index",
+            "unknown-source",
+            Bytes::from_static(b"{}"),
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, HttpSourceError::NotRunning { .. }));
+
+        let error = push_to_http_source(
+            "test-http-index",
+            "test-http-source",
+            Bytes::from(vec![b'a'; 65]),
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, HttpSourceError::PayloadTooLarge { .. }));
+
+        let push_task = tokio::spawn(push_to_http_source(
+            "test-http-index",
+            "test-http-source",
+            Bytes::from_static(b"{\"a\": 1}\n{\"b\": 2}\n"),
+            true,
+        ));
+        let mut batches = Vec::new();
+
+        while batches.is_empty() {
+            source_handle.process_pending_and_observe().await;
+            batches = doc_processor_inbox.drain_for_test_typed::<RawDocBatch>();
+        }
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].docs.len(), 2);
+        assert!(batches[0].force_commit);
+        assert_eq!(
+            format!("{:?}", batches[0].checkpoint_delta),
+            "∆(test-http-source:(..00000000000000000001])"
+        );
+        // The buffer is still holding the pending documents.
+        let error = push_to_http_source(
+            "test-http-index",
+            "test-http-source",
+            Bytes::from(vec![b'a'; 60]),
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, HttpSourceError::BufferFull { .. }));
+        assert!(!push_task.is_finished());
+
+        let mut checkpoint = SourceCheckpoint::default();
+        checkpoint
+            .try_apply_delta(batches[0].checkpoint_delta.clone())
+            .unwrap();
+        source_mailbox
+            .send_message(SuggestTruncate(checkpoint))
+            .await
+            .unwrap();
+        let push_response = push_task.await.unwrap().unwrap();
+        assert_eq!(push_response, HttpSourcePushResponse { num_docs: 2 });
+
+        let (exit_status, observation) = source_handle.quit().await;
+        assert!(matches!(exit_status, ActorExitStatus::Quit));
+        assert_eq!(observation["counters"]["num_docs_acked"], 2);
+
+        let error = push_to_http_source(
+            "test-http-index",
+            "test-http-source",
+            Bytes::from_static(b"{}"),
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, HttpSourceError::NotRunning { .. }));
+        universe.assert_quit().await;
+    }
+}
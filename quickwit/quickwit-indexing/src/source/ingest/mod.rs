@@ -42,7 +42,7 @@ use quickwit_proto::metastore::{
     SourceType,
 };
 use quickwit_proto::types::{
-    NodeId, PipelineUid, Position, PublishToken, ShardId, SourceId, SourceUid,
+    NodeId, NodeIdRef, PipelineUid, Position, PublishToken, ShardId, SourceId, SourceUid,
 };
 use serde::Serialize;
 use serde_json::json;
@@ -133,7 +133,7 @@ enum IndexingStatus {
 #[derive(Debug, Eq, PartialEq)]
 struct AssignedShard {
     leader_id: NodeId,
-    follower_id_opt: Option<NodeId>,
+    follower_ids: Vec<NodeId>,
     // This is just the shard id converted to a partition id object.
     partition_id: PartitionId,
     current_position_inclusive: Position,
@@ -338,7 +338,7 @@ impl IngestSource {
                 shard_id: Some(shard_id),
                 truncate_up_to_position_inclusive: Some(truncate_up_to_position_inclusive),
             };
-            if let Some(follower_id) = &shard.follower_id_opt {
+            for follower_id in &shard.follower_ids {
                 per_ingester_truncate_subrequests
                     .entry(follower_id)
                     .or_default()
@@ -575,8 +575,11 @@ impl Source for IngestSource {
             let index_uid = acquired_shard.index_uid().clone();
             let shard_id = acquired_shard.shard_id().clone();
             let mut current_position_inclusive = acquired_shard.publish_position_inclusive();
-            let leader_id: NodeId = acquired_shard.leader_id.into();
-            let follower_id_opt: Option<NodeId> = acquired_shard.follower_id.map(Into::into);
+            let leader_id: NodeId = acquired_shard.leader_id.clone().into();
+            let follower_ids: Vec<NodeId> = acquired_shard
+                .followers()
+                .map(NodeIdRef::to_owned)
+                .collect();
             let source_id: SourceId = acquired_shard.source_id;
             let partition_id = PartitionId::from(shard_id.as_str());
             let from_position_exclusive = current_position_inclusive.clone();
@@ -586,7 +589,7 @@ impl Source for IngestSource {
             } else if let Err(error) = ctx
                 .protect_future(self.fetch_stream.subscribe(
                     leader_id.clone(),
-                    follower_id_opt.clone(),
+                    follower_ids.clone(),
                     index_uid,
                     source_id,
                     shard_id.clone(),
@@ -609,7 +612,7 @@ impl Source for IngestSource {
 
             let assigned_shard = AssignedShard {
                 leader_id,
-                follower_id_opt,
+                follower_ids,
                 partition_id,
                 current_position_inclusive,
                 status,
@@ -723,6 +726,7 @@ mod tests {
                         source_id: "test-source".to_string(),
                         leader_id: "test-ingester-0".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         shard_id: Some(ShardId::from(0)),
                         shard_state: ShardState::Open as i32,
                         doc_mapping_uid: Some(DocMappingUid::default()),
@@ -745,6 +749,7 @@ mod tests {
                     acquired_shards: vec![Shard {
                         leader_id: "test-ingester-0".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         index_uid: Some(IndexUid::for_test("test-index", 0)),
                         source_id: "test-source".to_string(),
                         shard_id: Some(ShardId::from(1)),
@@ -770,6 +775,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(1)),
@@ -782,6 +788,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(2)),
@@ -948,6 +955,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             event_broker,
             indexing_setting: IndexingSettings::default(),
+            reindex_searcher_opt: None,
         };
         let retry_params = RetryParams::no_retries();
         let mut source = IngestSource::try_new(source_runtime, retry_params)
@@ -1023,7 +1031,7 @@ mod tests {
         let assigned_shard = source.assigned_shards.get(&ShardId::from(1)).unwrap();
         let expected_assigned_shard = AssignedShard {
             leader_id: "test-ingester-0".into(),
-            follower_id_opt: None,
+            follower_ids: Vec::new(),
             partition_id: 1u64.into(),
             current_position_inclusive: Position::offset(11u64),
             status: IndexingStatus::Active,
@@ -1033,7 +1041,7 @@ mod tests {
         let assigned_shard = source.assigned_shards.get(&ShardId::from(2)).unwrap();
         let expected_assigned_shard = AssignedShard {
             leader_id: "test-ingester-0".into(),
-            follower_id_opt: None,
+            follower_ids: Vec::new(),
             partition_id: 2u64.into(),
             current_position_inclusive: Position::offset(12u64),
             status: IndexingStatus::Active,
@@ -1074,6 +1082,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(1)),
@@ -1086,6 +1095,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(2)),
@@ -1152,6 +1162,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             event_broker,
             indexing_setting: IndexingSettings::default(),
+            reindex_searcher_opt: None,
         };
         let retry_params = RetryParams::for_test();
         let mut source = IngestSource::try_new(source_runtime, retry_params)
@@ -1219,6 +1230,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(1)),
@@ -1231,6 +1243,7 @@ mod tests {
                         Shard {
                             leader_id: "test-ingester-0".to_string(),
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             index_uid: Some(IndexUid::for_test("test-index", 0)),
                             source_id: "test-source".to_string(),
                             shard_id: Some(ShardId::from(2)),
@@ -1317,6 +1330,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             event_broker,
             indexing_setting: IndexingSettings::default(),
+            reindex_searcher_opt: None,
         };
         let retry_params = RetryParams::for_test();
         let mut source = IngestSource::try_new(source_runtime, retry_params)
@@ -1383,6 +1397,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             event_broker,
             indexing_setting: IndexingSettings::default(),
+            reindex_searcher_opt: None,
         };
         let retry_params = RetryParams::for_test();
         let mut source = IngestSource::try_new(source_runtime, retry_params)
@@ -1402,7 +1417,7 @@ mod tests {
             ShardId::from(1),
             AssignedShard {
                 leader_id: "test-ingester-0".into(),
-                follower_id_opt: None,
+                follower_ids: Vec::new(),
                 partition_id: 1u64.into(),
                 current_position_inclusive: Position::offset(11u64),
                 status: IndexingStatus::Active,
@@ -1412,7 +1427,7 @@ mod tests {
             ShardId::from(2),
             AssignedShard {
                 leader_id: "test-ingester-1".into(),
-                follower_id_opt: None,
+                follower_ids: Vec::new(),
                 partition_id: 2u64.into(),
                 current_position_inclusive: Position::offset(22u64),
                 status: IndexingStatus::Active,
@@ -1575,6 +1590,7 @@ mod tests {
                     acquired_shards: vec![Shard {
                         leader_id: "test-ingester-0".to_string(),
                         follower_id: None,
+                        additional_follower_ids: Vec::new(),
                         index_uid: Some(IndexUid::for_test("test-index", 0)),
                         source_id: "test-source".to_string(),
                         shard_id: Some(ShardId::from(1)),
@@ -1617,6 +1633,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             event_broker,
             indexing_setting: IndexingSettings::default(),
+            reindex_searcher_opt: None,
         };
         let retry_params = RetryParams::for_test();
         let mut source = IngestSource::try_new(source_runtime, retry_params)
@@ -1772,6 +1789,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             event_broker,
             indexing_setting: IndexingSettings::default(),
+            reindex_searcher_opt: None,
         };
         let retry_params = RetryParams::for_test();
         let mut source = IngestSource::try_new(source_runtime, retry_params)
@@ -1789,7 +1807,7 @@ mod tests {
             ShardId::from(1),
             AssignedShard {
                 leader_id: "test-ingester-0".into(),
-                follower_id_opt: None,
+                follower_ids: Vec::new(),
                 partition_id: 1u64.into(),
                 current_position_inclusive: Position::offset(11u64),
                 status: IndexingStatus::Active,
@@ -1799,7 +1817,7 @@ mod tests {
             ShardId::from(2),
             AssignedShard {
                 leader_id: "test-ingester-0".into(),
-                follower_id_opt: Some("test-ingester-1".into()),
+                follower_ids: vec!["test-ingester-1".into()],
                 partition_id: 2u64.into(),
                 current_position_inclusive: Position::offset(22u64),
                 status: IndexingStatus::Active,
@@ -1809,7 +1827,7 @@ mod tests {
             ShardId::from(3),
             AssignedShard {
                 leader_id: "test-ingester-1".into(),
-                follower_id_opt: Some("test-ingester-0".into()),
+                follower_ids: vec!["test-ingester-0".into()],
                 partition_id: 3u64.into(),
                 current_position_inclusive: Position::offset(33u64),
                 status: IndexingStatus::Active,
@@ -1819,7 +1837,7 @@ mod tests {
             ShardId::from(4),
             AssignedShard {
                 leader_id: "test-ingester-2".into(),
-                follower_id_opt: Some("test-ingester-3".into()),
+                follower_ids: vec!["test-ingester-3".into()],
                 partition_id: 4u64.into(),
                 current_position_inclusive: Position::offset(44u64),
                 status: IndexingStatus::Active,
@@ -1829,7 +1847,7 @@ mod tests {
             ShardId::from(5),
             AssignedShard {
                 leader_id: "test-ingester-2".into(),
-                follower_id_opt: Some("test-ingester-3".into()),
+                follower_ids: vec!["test-ingester-3".into()],
                 partition_id: 5u64.into(),
                 current_position_inclusive: Position::Beginning,
                 status: IndexingStatus::Active,
@@ -1904,6 +1922,7 @@ mod tests {
             storage_resolver: StorageResolver::for_test(),
             event_broker: event_broker.clone(),
             indexing_setting: IndexingSettings::default(),
+            reindex_searcher_opt: None,
         };
         let retry_params = RetryParams::for_test();
         let mut source = IngestSource::try_new(source_runtime, retry_params)
@@ -893,6 +893,7 @@ mod kafka_broker_tests {
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         (source_id, source_config)
     }
@@ -61,6 +61,7 @@ mod doc_file_reader;
 mod file_source;
 #[cfg(feature = "gcp-pubsub")]
 mod gcp_pubsub_source;
+mod http_source;
 mod ingest;
 mod ingest_api_source;
 #[cfg(feature = "kafka")]
@@ -71,6 +72,7 @@ mod kinesis;
 mod pulsar_source;
 #[cfg(feature = "queue-sources")]
 mod queue_sources;
+mod reindex_source;
 mod source_factory;
 mod stdin_source;
 mod vec_source;
@@ -78,6 +80,7 @@ mod void_source;
 
 use std::collections::BTreeSet;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
@@ -86,6 +89,9 @@ use bytesize::ByteSize;
 pub use file_source::{FileSource, FileSourceFactory};
 #[cfg(feature = "gcp-pubsub")]
 pub use gcp_pubsub_source::{GcpPubSubSource, GcpPubSubSourceFactory};
+pub use http_source::{
+    push_to_http_source, HttpSource, HttpSourceError, HttpSourceFactory, HttpSourcePushResponse,
+};
 #[cfg(feature = "kafka")]
 pub use kafka_source::{KafkaSource, KafkaSourceFactory};
 #[cfg(feature = "kinesis")]
@@ -112,6 +118,10 @@ use quickwit_proto::metastore::{
 };
 use quickwit_proto::types::{IndexUid, NodeIdRef, PipelineUid, ShardId};
 use quickwit_storage::StorageResolver;
+pub use reindex_source::{
+    build_reindex_query_ast, validate_reindex_source_index, ReindexProgress, ReindexSearcher,
+    ReindexSource, ReindexSourceFactory,
+};
 use serde_json::Value as JsonValue;
 pub use source_factory::{SourceFactory, SourceLoader, TypedSourceFactory};
 use tokio::runtime::Handle;
@@ -164,6 +174,8 @@ pub struct SourceRuntime {
     pub storage_resolver: StorageResolver,
     pub event_broker: EventBroker,
     pub indexing_setting: IndexingSettings,
+    // Searcher reading the documents of the source index of the reindex sources.
+    pub reindex_searcher_opt: Option<Arc<dyn ReindexSearcher>>,
 }
 
 impl SourceRuntime {
@@ -412,6 +424,7 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
         source_factory.add_source(SourceType::File, FileSourceFactory);
         #[cfg(feature = "gcp-pubsub")]
         source_factory.add_source(SourceType::PubSub, GcpPubSubSourceFactory);
+        source_factory.add_source(SourceType::Http, HttpSourceFactory);
         source_factory.add_source(SourceType::IngestV1, IngestApiSourceFactory);
         source_factory.add_source(SourceType::IngestV2, IngestSourceFactory);
         #[cfg(feature = "kafka")]
@@ -420,6 +433,7 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
         source_factory.add_source(SourceType::Kinesis, KinesisSourceFactory);
         #[cfg(feature = "pulsar")]
         source_factory.add_source(SourceType::Pulsar, PulsarSourceFactory);
+        source_factory.add_source(SourceType::Reindex, ReindexSourceFactory);
         source_factory.add_source(SourceType::Vec, VecSourceFactory);
         source_factory.add_source(SourceType::Void, VoidSourceFactory);
         source_factory
@@ -588,6 +602,7 @@ mod tests {
         source_config: SourceConfig,
         metastore_opt: Option<MetastoreServiceClient>,
         queues_dir_path_opt: Option<PathBuf>,
+        reindex_searcher_opt: Option<Arc<dyn ReindexSearcher>>,
     }
 
     impl SourceRuntimeBuilder {
@@ -597,6 +612,7 @@ mod tests {
                 source_config,
                 metastore_opt: None,
                 queues_dir_path_opt: None,
+                reindex_searcher_opt: None,
             }
         }
 
@@ -624,13 +640,10 @@ mod tests {
                 storage_resolver: StorageResolver::for_test(),
                 event_broker: EventBroker::default(),
                 indexing_setting: IndexingSettings::default(),
+                reindex_searcher_opt: self.reindex_searcher_opt,
             }
         }
 
-        #[cfg(all(
-            test,
-            any(feature = "kafka-broker-tests", feature = "sqs-localstack-tests")
-        ))]
         pub fn with_metastore(mut self, metastore: MetastoreServiceClient) -> Self {
             self.metastore_opt = Some(metastore);
             self
@@ -649,6 +662,11 @@ mod tests {
             self
         }
 
+        pub fn with_reindex_searcher(mut self, reindex_searcher: Arc<dyn ReindexSearcher>) -> Self {
+            self.reindex_searcher_opt = Some(reindex_searcher);
+            self
+        }
+
         fn setup_mock_metastore(
             &self,
             source_checkpoint_delta_opt: Option<SourceCheckpointDelta>,
@@ -693,6 +711,7 @@ mod tests {
                 source_params: SourceParams::void(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_queue_config: None,
             };
             check_source_connectivity(&StorageResolver::for_test(), &source_config).await?;
         }
@@ -704,6 +723,7 @@ mod tests {
                 source_params: SourceParams::Vec(VecSourceParams::default()),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_queue_config: None,
             };
             check_source_connectivity(&StorageResolver::for_test(), &source_config).await?;
         }
@@ -715,6 +735,7 @@ mod tests {
                 source_params: SourceParams::file_from_str("file-does-not-exist.json").unwrap(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_queue_config: None,
             };
             assert!(
                 check_source_connectivity(&StorageResolver::for_test(), &source_config)
@@ -730,6 +751,7 @@ mod tests {
                 source_params: SourceParams::file_from_str("data/test_corpus.json").unwrap(),
                 transform_config: None,
                 input_format: SourceInputFormat::Json,
+                dead_letter_queue_config: None,
             };
             assert!(
                 check_source_connectivity(&StorageResolver::for_test(), &source_config)
@@ -24,7 +24,7 @@ use std::time::Duration;
 use itertools::Itertools;
 use quickwit_actors::{ActorExitStatus, Mailbox};
 use quickwit_common::rate_limited_error;
-use quickwit_config::{FileSourceMessageType, FileSourceSqs};
+use quickwit_config::{FileSourceMessageType, FileSourceSqs, SourceInputFormat};
 use quickwit_metastore::checkpoint::SourceCheckpoint;
 use quickwit_proto::indexing::IndexingPipelineId;
 use quickwit_proto::metastore::SourceType;
@@ -71,6 +71,7 @@ pub struct QueueCoordinator {
     storage_resolver: StorageResolver,
     pipeline_id: IndexingPipelineId,
     source_type: SourceType,
+    input_format: SourceInputFormat,
     queue: Arc<dyn Queue>,
     queue_receiver: QueueReceiver,
     observable_state: QueueCoordinatorObservableState,
@@ -116,6 +117,7 @@ impl QueueCoordinator {
             local_state: QueueLocalState::default(),
             pipeline_id: source_runtime.pipeline_id,
             source_type: source_runtime.source_config.source_type(),
+            input_format: source_runtime.source_config.input_format,
             storage_resolver: source_runtime.storage_resolver,
             queue_receiver: QueueReceiver::new(queue.clone(), RECEIVE_POLL_TIMEOUT),
             queue,
@@ -275,7 +277,10 @@ impl QueueCoordinator {
                 self.observable_state.num_messages_processed += 1;
             }
         } else if let Some(ready_message) = self.local_state.get_ready_for_read() {
-            match ready_message.start_processing(&self.storage_resolver).await {
+            match ready_message
+                .start_processing(&self.storage_resolver, self.input_format)
+                .await
+            {
                 Ok(new_in_progress) => {
                     self.local_state.set_currently_read(new_in_progress)?;
                 }
@@ -361,6 +366,7 @@ mod tests {
             queue,
             message_type: MessageType::RawUri,
             source_type: SourceType::Unspecified,
+            input_format: SourceInputFormat::Json,
             storage_resolver: StorageResolver::for_test(),
             publish_token: Ulid::new().to_string(),
             visibility_settings: VisibilitySettings::from_commit_timeout(5),
@@ -25,6 +25,7 @@ use std::time::Instant;
 use anyhow::Context;
 use quickwit_common::rate_limited_warn;
 use quickwit_common::uri::Uri;
+use quickwit_config::SourceInputFormat;
 use quickwit_metastore::checkpoint::PartitionId;
 use quickwit_proto::types::Position;
 use quickwit_storage::{OwnedBytes, StorageResolver};
@@ -177,6 +178,7 @@ impl ReadyMessage {
     pub async fn start_processing(
         self,
         storage_resolver: &StorageResolver,
+        input_format: SourceInputFormat,
     ) -> anyhow::Result<Option<InProgressMessage>> {
         let partition_id = self.partition_id();
         match self.content.payload {
@@ -186,6 +188,7 @@ impl ReadyMessage {
                     partition_id.clone(),
                     &uri,
                     self.position,
+                    input_format,
                 )
                 .await?;
                 if batch_reader.is_eof() {
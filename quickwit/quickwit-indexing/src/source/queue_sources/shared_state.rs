@@ -129,6 +129,7 @@ impl QueueSharedState {
                 source_id: self.source_uid.source_id.clone(),
                 leader_id: String::new(),
                 follower_id: None,
+                additional_follower_ids: Vec::new(),
                 shard_id: Some(ShardId::from(partition_id.as_str())),
                 doc_mapping_uid: Some(DocMappingUid::default()),
                 publish_token: Some(publish_token.to_string()),
@@ -275,6 +276,7 @@ pub mod shared_state_for_tests {
                                 publish_token: Some(token),
                                 index_uid: sub_req.index_uid,
                                 follower_id: sub_req.follower_id,
+                                additional_follower_ids: Vec::new(),
                                 leader_id: sub_req.leader_id,
                                 doc_mapping_uid: sub_req.doc_mapping_uid,
                                 publish_position_inclusive: Some(position),
@@ -316,6 +318,7 @@ pub mod shared_state_for_tests {
                             publish_token: Some(request.publish_token.clone()),
                             index_uid: None,
                             follower_id: None,
+                            additional_follower_ids: Vec::new(),
                             leader_id: "dummy".to_string(),
                             doc_mapping_uid: None,
                             publish_position_inclusive: Some(position),
@@ -0,0 +1,974 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hasher;
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use fnv::FnvHasher;
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::{build_doc_mapper, IndexConfig, ReindexSourceParams};
+use quickwit_doc_mapper::{DocMapper, SOURCE_FIELD_NAME};
+use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpoint};
+use quickwit_metastore::IndexMetadataResponseExt;
+use quickwit_proto::metastore::{
+    DeleteSourceRequest, IndexMetadataRequest, MetastoreService, MetastoreServiceClient, SourceType,
+};
+use quickwit_proto::search::{
+    CountHits, Hit, SearchRequest, SearchResponse, SortDatetimeFormat, SortField, SortOrder,
+    SortValue,
+};
+use quickwit_proto::types::{IndexUid, Position, SourceId};
+use quickwit_query::query_ast::{query_ast_from_user_text, BoolQuery, QueryAst, RangeQuery};
+use quickwit_query::JsonLiteral;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::source::{BatchBuilder, Source, SourceContext, SourceRuntime, TypedSourceFactory};
+
+/// Number of documents fetched from the source index per search request.
+const PAGE_SIZE: u64 = 1_000;
+
+/// Interval at which the source checks whether its last checkpoint is published once all the
+/// documents are sent to the indexing pipeline.
+const COMPLETION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The reindex source checkpoints its progress in a single partition.
+const REINDEX_PARTITION_ID: &str = "reindex";
+
+/// Searches the source index of a reindex source.
+///
+/// The reindex source reads the documents through the root search of the cluster rather than
+/// from a list of splits fixed when the job starts, which would not survive merges and garbage
+/// collection. The searcher is provided by `quickwit-serve`, on top of the node's search service.
+#[async_trait]
+pub trait ReindexSearcher: Send + Sync + 'static {
+    async fn root_search(&self, search_request: SearchRequest) -> anyhow::Result<SearchResponse>;
+}
+
+/// Position of the reindex source: the number of documents sent to the indexing pipeline and the
+/// point from which the next search resumes. The number of documents is zero-padded so that the
+/// lexicographical order of the positions matches the progress of the job.
+#[derive(Debug, Default)]
+struct ReindexCursor {
+    num_docs: u64,
+    resume_point_opt: Option<ReindexResumePoint>,
+}
+
+/// The documents are read in the order of their timestamp, and the addresses of the documents
+/// change when their splits are merged, so each search resumes at the timestamp of the last
+/// document sent, inclusive. The documents with this timestamp that were already sent are
+/// identified by the hashes of their source, and skipped.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct ReindexResumePoint {
+    timestamp_nanos: i64,
+    // Hashes of the documents sent with the timestamp, repeated for identical documents.
+    doc_hashes: Vec<u64>,
+}
+
+impl ReindexCursor {
+    fn from_position(position: &Position) -> anyhow::Result<Self> {
+        let offset = match position {
+            Position::Beginning | Position::Eof(None) => return Ok(Self::default()),
+            Position::Offset(offset) | Position::Eof(Some(offset)) => offset,
+        };
+        let (num_docs_str, resume_point_json) = offset
+            .as_str()
+            .split_once(':')
+            .with_context(|| format!("invalid reindex position `{position}`"))?;
+        let num_docs = num_docs_str
+            .parse()
+            .with_context(|| format!("invalid reindex position `{position}`"))?;
+        let resume_point = serde_json::from_str(resume_point_json)
+            .with_context(|| format!("invalid reindex position `{position}`"))?;
+        Ok(Self {
+            num_docs,
+            resume_point_opt: Some(resume_point),
+        })
+    }
+
+    fn to_position(&self, is_eof: bool) -> Position {
+        let Some(resume_point) = &self.resume_point_opt else {
+            return if is_eof {
+                Position::Eof(None)
+            } else {
+                Position::Beginning
+            };
+        };
+        let resume_point_json =
+            serde_json::to_string(resume_point).expect("resume point should be JSON serializable");
+        let offset = format!("{:020}:{resume_point_json}", self.num_docs);
+
+        if is_eof {
+            Position::eof(offset.as_str())
+        } else {
+            Position::offset(offset.as_str())
+        }
+    }
+}
+
+/// Progress of a reindex job, derived from the checkpoint of its source.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ReindexProgress {
+    /// Number of documents indexed and published into the target index.
+    pub num_docs_reindexed: u64,
+    /// Whether all the documents to reindex are published.
+    pub completed: bool,
+}
+
+impl ReindexProgress {
+    pub fn from_checkpoint(source_checkpoint: &SourceCheckpoint) -> Self {
+        let Some(position) =
+            source_checkpoint.position_for_partition(&PartitionId::from(REINDEX_PARTITION_ID))
+        else {
+            return Self::default();
+        };
+        let num_docs_reindexed = ReindexCursor::from_position(position)
+            .map(|cursor| cursor.num_docs)
+            .unwrap_or_default();
+        Self {
+            num_docs_reindexed,
+            completed: position.is_eof(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ReindexSourceCounters {
+    num_docs_processed: u64,
+    num_bytes_processed: u64,
+}
+
+/// A source that reads the documents of another index, in the order of its timestamp field, and
+/// sends their original JSON (`_source`) to the indexing pipeline.
+///
+/// Once the last checkpoint of the job is published, the source deletes itself from the target
+/// index.
+pub struct ReindexSource {
+    source_id: SourceId,
+    source_index_id: String,
+    source_type: SourceType,
+    index_uid: IndexUid,
+    metastore: MetastoreServiceClient,
+    searcher: Arc<dyn ReindexSearcher>,
+    search_request: SearchRequest,
+    query_ast: QueryAst,
+    timestamp_field_name: String,
+    cursor: ReindexCursor,
+    is_eof: bool,
+    counters: ReindexSourceCounters,
+}
+
+impl fmt::Debug for ReindexSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ReindexSource {{ source_id: {}, source_index_id: {} }}",
+            self.source_id, self.source_index_id
+        )
+    }
+}
+
+impl ReindexSource {
+    /// Deletes the source once the checkpoint recording the end of the job is published, which
+    /// makes the control plane shut down its pipeline.
+    async fn delete_source_if_completed(&self, ctx: &SourceContext) -> anyhow::Result<()> {
+        let index_metadata_request = IndexMetadataRequest::for_index_uid(self.index_uid.clone());
+        let index_metadata = ctx
+            .protect_future(
+                self.metastore
+                    .clone()
+                    .index_metadata(index_metadata_request),
+            )
+            .await?
+            .deserialize_index_metadata()?;
+        let completed = index_metadata
+            .checkpoint
+            .source_checkpoint(&self.source_id)
+            .map(|source_checkpoint| ReindexProgress::from_checkpoint(source_checkpoint).completed)
+            .unwrap_or(false);
+        if !completed {
+            return Ok(());
+        }
+        info!(
+            index_uid=%self.index_uid,
+            source_id=%self.source_id,
+            num_docs=self.cursor.num_docs,
+            "reindex completed, deleting source"
+        );
+        let delete_source_request = DeleteSourceRequest {
+            index_uid: Some(self.index_uid.clone()),
+            source_id: self.source_id.clone(),
+        };
+        ctx.protect_future(self.metastore.clone().delete_source(delete_source_request))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for ReindexSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        if self.is_eof {
+            if let Err(error) = self.delete_source_if_completed(ctx).await {
+                warn!(%error, source_id=%self.source_id, "failed to delete reindex source");
+            }
+            return Ok(COMPLETION_CHECK_INTERVAL);
+        }
+        let mut search_request = self.search_request.clone();
+
+        // Pages are made large enough to hold, on top of the documents already sent with the
+        // timestamp the search resumes at, a full page of new documents.
+        let mut num_docs_to_skip: HashMap<u64, usize> = HashMap::new();
+
+        if let Some(resume_point) = &self.cursor.resume_point_opt {
+            let resume_query_ast = build_resume_query_ast(
+                &self.query_ast,
+                &self.timestamp_field_name,
+                resume_point.timestamp_nanos,
+            )?;
+            search_request.query_ast = serde_json::to_string(&resume_query_ast)
+                .context("failed to serialize reindex query")?;
+            search_request.max_hits += resume_point.doc_hashes.len() as u64;
+
+            for doc_hash in &resume_point.doc_hashes {
+                *num_docs_to_skip.entry(*doc_hash).or_default() += 1;
+            }
+        }
+        let max_hits = search_request.max_hits;
+
+        let search_response = ctx
+            .protect_future(self.searcher.root_search(search_request))
+            .await
+            .with_context(|| format!("failed to search index `{}`", self.source_index_id))?;
+
+        // Skipping the documents of the splits that could not be searched would silently lose
+        // them.
+        if !search_response.errors.is_empty() {
+            return Err(anyhow::anyhow!(
+                "failed to search index `{}`: {}",
+                self.source_index_id,
+                search_response.errors.join(", ")
+            )
+            .into());
+        }
+        let from_position = self.cursor.to_position(false);
+        let num_hits = search_response.hits.len() as u64;
+        let mut num_docs_sent = 0;
+        let mut batch_builder = BatchBuilder::new(self.source_type);
+
+        for hit in search_response.hits {
+            let timestamp_nanos = hit_timestamp_nanos(&hit).with_context(|| {
+                format!(
+                    "search hit is missing its timestamp field `{}`",
+                    self.timestamp_field_name
+                )
+            })?;
+            let mut hit_json: JsonValue =
+                serde_json::from_str(&hit.json).context("failed to parse search hit")?;
+            let source_json = hit_json
+                .get_mut(SOURCE_FIELD_NAME)
+                .map(JsonValue::take)
+                .filter(JsonValue::is_object)
+                .context("search hit is missing its `_source` field")?;
+            let doc = Bytes::from(
+                serde_json::to_vec(&source_json).context("failed to serialize document")?,
+            );
+            let doc_hash = hash_doc(&doc);
+
+            match &mut self.cursor.resume_point_opt {
+                Some(resume_point) if resume_point.timestamp_nanos == timestamp_nanos => {
+                    if let Some(num_docs) = num_docs_to_skip.get_mut(&doc_hash) {
+                        if *num_docs > 0 {
+                            *num_docs -= 1;
+                            continue;
+                        }
+                    }
+                    resume_point.doc_hashes.push(doc_hash);
+                }
+                _ => {
+                    // The hits are sorted by timestamp, so the documents with the timestamp of
+                    // the previous resume point are all behind.
+                    self.cursor.resume_point_opt = Some(ReindexResumePoint {
+                        timestamp_nanos,
+                        doc_hashes: vec![doc_hash],
+                    });
+                    num_docs_to_skip.clear();
+                }
+            }
+            self.counters.num_bytes_processed += doc.len() as u64;
+            batch_builder.add_doc(doc);
+
+            self.cursor.num_docs += 1;
+            num_docs_sent += 1;
+        }
+        self.counters.num_docs_processed += num_docs_sent;
+        self.is_eof = num_hits < max_hits;
+
+        let to_position = self.cursor.to_position(self.is_eof);
+        batch_builder
+            .checkpoint_delta
+            .record_partition_delta(
+                PartitionId::from(REINDEX_PARTITION_ID),
+                from_position,
+                to_position,
+            )
+            .context("failed to record partition delta")?;
+        doc_processor_mailbox
+            .send_message(batch_builder.build())
+            .await?;
+
+        if self.is_eof {
+            info!(
+                source_index_id=%self.source_index_id,
+                num_docs=self.cursor.num_docs,
+                "reindexed all documents"
+            );
+        }
+        Ok(Duration::ZERO)
+    }
+
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn observable_state(&self) -> JsonValue {
+        serde_json::json!({
+            "source_index_id": self.source_index_id,
+            "num_docs_processed": self.counters.num_docs_processed,
+            "num_bytes_processed": self.counters.num_bytes_processed,
+            "completed": self.is_eof,
+        })
+    }
+}
+
+/// Checks that the documents of an index can be reindexed: the original JSON of the documents is
+/// read from their `_source` field, which is only stored when `store_source` is enabled, in the
+/// order of their timestamp, which does not change when splits are merged.
+pub fn validate_reindex_source_index(source_index_config: &IndexConfig) -> anyhow::Result<()> {
+    if !source_index_config.doc_mapping.store_source {
+        bail!(
+            "index `{}` does not store the source of its documents (`store_source: false`) and \
+             cannot be reindexed",
+            source_index_config.index_id
+        );
+    }
+    if source_index_config.doc_mapping.timestamp_field.is_none() {
+        bail!(
+            "index `{}` has no timestamp field and cannot be reindexed",
+            source_index_config.index_id
+        );
+    }
+    Ok(())
+}
+
+/// Builds the query selecting the documents of the source index to reindex.
+pub fn build_reindex_query_ast(
+    params: &ReindexSourceParams,
+    doc_mapper: &DocMapper,
+) -> anyhow::Result<QueryAst> {
+    let mut query_ast = match &params.query {
+        Some(query) => query_ast_from_user_text(query, None)
+            .parse_user_query(doc_mapper.default_search_fields())
+            .context("invalid reindex query")?,
+        None => QueryAst::MatchAll,
+    };
+    if params.start_timestamp.is_none() && params.end_timestamp.is_none() {
+        return Ok(query_ast);
+    }
+    let timestamp_field_name = doc_mapper.timestamp_field_name().with_context(|| {
+        format!(
+            "index `{}` has no timestamp field, reindexing a time range is not supported",
+            params.source_index_id
+        )
+    })?;
+    let time_range_query = RangeQuery {
+        field: timestamp_field_name.to_string(),
+        lower_bound: params
+            .start_timestamp
+            .map(|start_timestamp| Bound::Included(JsonLiteral::Number(start_timestamp.into())))
+            .unwrap_or(Bound::Unbounded),
+        upper_bound: params
+            .end_timestamp
+            .map(|end_timestamp| Bound::Excluded(JsonLiteral::Number(end_timestamp.into())))
+            .unwrap_or(Bound::Unbounded),
+    };
+    query_ast = BoolQuery {
+        must: vec![query_ast, time_range_query.into()],
+        ..Default::default()
+    }
+    .into();
+    Ok(query_ast)
+}
+
+/// Builds the search request reading the first page of the documents to reindex.
+///
+/// The documents are sorted by timestamp, which, unlike their addresses, does not change when
+/// splits are merged. The following pages are read by resuming at the timestamp of the last
+/// document sent, see [`ReindexResumePoint`].
+fn build_reindex_search_request(
+    params: &ReindexSourceParams,
+    timestamp_field_name: &str,
+    query_ast: &QueryAst,
+) -> anyhow::Result<SearchRequest> {
+    let search_request = SearchRequest {
+        index_id_patterns: vec![params.source_index_id.clone()],
+        query_ast: serde_json::to_string(query_ast)?,
+        start_timestamp: params.start_timestamp,
+        end_timestamp: params.end_timestamp,
+        max_hits: PAGE_SIZE,
+        sort_fields: vec![SortField {
+            field_name: timestamp_field_name.to_string(),
+            sort_order: SortOrder::Asc as i32,
+            sort_datetime_format: Some(SortDatetimeFormat::UnixTimestampNanos as i32),
+        }],
+        count_hits: CountHits::Underestimate as i32,
+        allow_partial_search_results: Some(false),
+        ..Default::default()
+    };
+    Ok(search_request)
+}
+
+/// Builds the query selecting the documents to reindex with a timestamp greater than or equal to
+/// `timestamp_nanos`.
+fn build_resume_query_ast(
+    query_ast: &QueryAst,
+    timestamp_field_name: &str,
+    timestamp_nanos: i64,
+) -> anyhow::Result<QueryAst> {
+    let timestamp = OffsetDateTime::from_unix_timestamp_nanos(timestamp_nanos as i128)
+        .context("invalid reindex timestamp")?
+        .format(&Rfc3339)
+        .context("failed to format reindex timestamp")?;
+    let resume_range_query = RangeQuery {
+        field: timestamp_field_name.to_string(),
+        lower_bound: Bound::Included(JsonLiteral::String(timestamp)),
+        upper_bound: Bound::Unbounded,
+    };
+    let resume_query_ast = BoolQuery {
+        must: vec![query_ast.clone(), resume_range_query.into()],
+        ..Default::default()
+    }
+    .into();
+    Ok(resume_query_ast)
+}
+
+/// Returns the timestamp of a hit, in nanoseconds, from its sort value.
+fn hit_timestamp_nanos(hit: &Hit) -> Option<i64> {
+    let sort_value = hit
+        .partial_hit
+        .as_ref()?
+        .sort_value
+        .as_ref()?
+        .sort_value
+        .as_ref()?;
+
+    match sort_value {
+        SortValue::I64(timestamp_nanos) => Some(*timestamp_nanos),
+        SortValue::U64(timestamp_nanos) => i64::try_from(*timestamp_nanos).ok(),
+        _ => None,
+    }
+}
+
+/// Hashes the source of a document. The hash must be stable across versions because it is
+/// recorded in the checkpoint of the source.
+fn hash_doc(doc: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(doc);
+    hasher.finish()
+}
+
+pub struct ReindexSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for ReindexSourceFactory {
+    type Source = ReindexSource;
+    type Params = ReindexSourceParams;
+
+    async fn typed_create_source(
+        source_runtime: SourceRuntime,
+        params: ReindexSourceParams,
+    ) -> anyhow::Result<ReindexSource> {
+        let searcher = source_runtime
+            .reindex_searcher_opt
+            .clone()
+            .context("reindex sources require a searcher to read the source index")?;
+        let index_metadata_request =
+            IndexMetadataRequest::for_index_id(params.source_index_id.clone());
+        let source_index_config = source_runtime
+            .metastore
+            .clone()
+            .index_metadata(index_metadata_request)
+            .await?
+            .deserialize_index_metadata()?
+            .index_config;
+        validate_reindex_source_index(&source_index_config)?;
+
+        let doc_mapper = build_doc_mapper(
+            &source_index_config.doc_mapping,
+            &source_index_config.search_settings,
+        )?;
+        let timestamp_field_name = doc_mapper
+            .timestamp_field_name()
+            .context("reindex source index should have a timestamp field")?
+            .to_string();
+        let query_ast = build_reindex_query_ast(&params, &doc_mapper)?;
+        let search_request =
+            build_reindex_search_request(&params, &timestamp_field_name, &query_ast)?;
+
+        let checkpoint: SourceCheckpoint = source_runtime.fetch_checkpoint().await?;
+        let position = checkpoint
+            .position_for_partition(&PartitionId::from(REINDEX_PARTITION_ID))
+            .cloned()
+            .unwrap_or_default();
+        let cursor = ReindexCursor::from_position(&position)?;
+
+        info!(
+            source_index_id=%params.source_index_id,
+            num_docs=cursor.num_docs,
+            "starting reindex source"
+        );
+        Ok(ReindexSource {
+            source_id: source_runtime.source_id().to_string(),
+            source_index_id: params.source_index_id,
+            source_type: source_runtime.source_config.source_type(),
+            index_uid: source_runtime.index_uid().clone(),
+            metastore: source_runtime.metastore.clone(),
+            searcher,
+            search_request,
+            query_ast,
+            timestamp_field_name,
+            cursor,
+            is_eof: position.is_eof(),
+            counters: ReindexSourceCounters::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::sync::Mutex;
+
+    use quickwit_actors::Universe;
+    use quickwit_config::{SourceConfig, SourceInputFormat, SourceParams};
+    use quickwit_metastore::checkpoint::IndexCheckpointDelta;
+    use quickwit_metastore::{metastore_for_test, CreateIndexRequestExt};
+    use quickwit_proto::metastore::{CreateIndexRequest, PublishSplitsRequest};
+    use quickwit_proto::search::{PartialHit, SortByValue};
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::RawDocBatch;
+    use crate::source::tests::SourceRuntimeBuilder;
+    use crate::source::SourceActor;
+
+    /// Serves the documents sorted by timestamp, starting at the lower bound of the resume query.
+    /// Every other request reverses the order of the documents with the same timestamp, as a
+    /// merge of their splits would.
+    #[derive(Default)]
+    struct MockReindexSearcher {
+        docs: Vec<(i64, JsonValue)>,
+        search_requests: Mutex<Vec<SearchRequest>>,
+    }
+
+    impl MockReindexSearcher {
+        fn resume_timestamp_nanos(&self, query_ast: &QueryAst) -> Option<i64> {
+            let QueryAst::Bool(bool_query) = query_ast else {
+                return None;
+            };
+            let Some(QueryAst::Range(range_query)) = bool_query.must.get(1) else {
+                return None;
+            };
+            let Bound::Included(JsonLiteral::String(timestamp)) = &range_query.lower_bound else {
+                return None;
+            };
+            let timestamp_nanos = self
+                .docs
+                .iter()
+                .map(|(timestamp_nanos, _)| *timestamp_nanos)
+                .find(|timestamp_nanos| {
+                    OffsetDateTime::from_unix_timestamp_nanos(*timestamp_nanos as i128)
+                        .unwrap()
+                        .format(&Rfc3339)
+                        .unwrap()
+                        == *timestamp
+                })
+                .unwrap();
+            Some(timestamp_nanos)
+        }
+    }
+
+    #[async_trait]
+    impl ReindexSearcher for MockReindexSearcher {
+        async fn root_search(
+            &self,
+            search_request: SearchRequest,
+        ) -> anyhow::Result<SearchResponse> {
+            let query_ast: QueryAst = serde_json::from_str(&search_request.query_ast).unwrap();
+            let start_timestamp_nanos = self.resume_timestamp_nanos(&query_ast).unwrap_or(i64::MIN);
+            let mut docs: Vec<&(i64, JsonValue)> = self
+                .docs
+                .iter()
+                .filter(|(timestamp_nanos, _)| *timestamp_nanos >= start_timestamp_nanos)
+                .collect();
+            let mut search_requests = self.search_requests.lock().unwrap();
+
+            if search_requests.len() % 2 == 1 {
+                docs.reverse();
+                docs.sort_by_key(|(timestamp_nanos, _)| *timestamp_nanos);
+            }
+            let hits: Vec<Hit> = docs
+                .into_iter()
+                .take(search_request.max_hits as usize)
+                .map(|(timestamp_nanos, doc)| Hit {
+                    json: json!({"timestamp": timestamp_nanos, "_source": doc}).to_string(),
+                    partial_hit: Some(PartialHit {
+                        sort_value: Some(SortByValue {
+                            sort_value: Some(SortValue::I64(*timestamp_nanos)),
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect();
+            search_requests.push(search_request);
+
+            Ok(SearchResponse {
+                num_hits: hits.len() as u64,
+                hits,
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn test_build_reindex_query_ast() {
+        let doc_mapping_json = r#"{
+            "field_mappings": [{"name": "body", "type": "text"}]
+        }"#;
+        let doc_mapping = serde_json::from_str(doc_mapping_json).unwrap();
+        let doc_mapper = build_doc_mapper(&doc_mapping, &Default::default()).unwrap();
+        let mut params = ReindexSourceParams {
+            source_index_id: "my-index".to_string(),
+            query: None,
+            start_timestamp: None,
+            end_timestamp: None,
+        };
+        let query_ast = build_reindex_query_ast(&params, &doc_mapper).unwrap();
+        assert_eq!(query_ast, QueryAst::MatchAll);
+
+        params.query = Some("body:error".to_string());
+        let query_ast = build_reindex_query_ast(&params, &doc_mapper).unwrap();
+        assert!(matches!(query_ast, QueryAst::FullText(_)));
+
+        params.start_timestamp = Some(1_700_000_000);
+        let error = build_reindex_query_ast(&params, &doc_mapper).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "index `my-index` has no timestamp field, reindexing a time range is not supported"
+        );
+    }
+
+    #[test]
+    fn test_validate_reindex_source_index() {
+        let mut index_config = IndexConfig::for_test("my-index", "ram:///indexes/my-index");
+        validate_reindex_source_index(&index_config).unwrap();
+
+        index_config.doc_mapping.timestamp_field = None;
+        let error = validate_reindex_source_index(&index_config).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "index `my-index` has no timestamp field and cannot be reindexed"
+        );
+    }
+
+    #[test]
+    fn test_reindex_cursor_position() {
+        let cursor = ReindexCursor::default();
+        assert_eq!(cursor.to_position(false), Position::Beginning);
+        assert_eq!(cursor.to_position(true), Position::Eof(None));
+
+        let cursor = ReindexCursor {
+            num_docs: 42,
+            resume_point_opt: Some(ReindexResumePoint {
+                timestamp_nanos: 1_700_000_000_000_000_000,
+                doc_hashes: vec![7, 7, 8],
+            }),
+        };
+        let position = cursor.to_position(false);
+        assert!(position > Position::Beginning);
+        let next_position = ReindexCursor {
+            num_docs: 43,
+            resume_point_opt: Some(ReindexResumePoint {
+                timestamp_nanos: 0,
+                doc_hashes: Vec::new(),
+            }),
+        }
+        .to_position(false);
+        assert!(position < next_position);
+
+        let cursor = ReindexCursor::from_position(&position).unwrap();
+        assert_eq!(cursor.num_docs, 42);
+        assert_eq!(
+            cursor.resume_point_opt.unwrap(),
+            ReindexResumePoint {
+                timestamp_nanos: 1_700_000_000_000_000_000,
+                doc_hashes: vec![7, 7, 8],
+            }
+        );
+
+        let mut source_checkpoint = SourceCheckpoint::default();
+        source_checkpoint.add_partition(PartitionId::from(REINDEX_PARTITION_ID), position.as_eof());
+        assert_eq!(
+            ReindexProgress::from_checkpoint(&source_checkpoint),
+            ReindexProgress {
+                num_docs_reindexed: 42,
+                completed: true,
+            }
+        );
+        assert_eq!(
+            ReindexProgress::from_checkpoint(&SourceCheckpoint::default()),
+            ReindexProgress::default()
+        );
+    }
+
+    async fn create_source_and_target_indexes(
+        metastore: &MetastoreServiceClient,
+        source_config: &SourceConfig,
+        store_source: bool,
+    ) -> IndexUid {
+        let mut source_index_config =
+            IndexConfig::for_test("source-index", "ram:///indexes/source-index");
+        source_index_config.doc_mapping.store_source = store_source;
+        let create_index_request =
+            CreateIndexRequest::try_from_index_config(&source_index_config).unwrap();
+        metastore
+            .clone()
+            .create_index(create_index_request)
+            .await
+            .unwrap();
+
+        let target_index_config =
+            IndexConfig::for_test("target-index", "ram:///indexes/target-index");
+        let create_index_request = CreateIndexRequest::try_from_index_and_source_configs(
+            &target_index_config,
+            &[source_config.clone()],
+        )
+        .unwrap();
+        metastore
+            .clone()
+            .create_index(create_index_request)
+            .await
+            .unwrap()
+            .index_uid()
+            .clone()
+    }
+
+    fn reindex_source_config() -> SourceConfig {
+        SourceConfig {
+            source_id: "test-reindex-source".to_string(),
+            num_pipelines: NonZeroUsize::MIN,
+            enabled: true,
+            source_params: SourceParams::Reindex(ReindexSourceParams {
+                source_index_id: "source-index".to_string(),
+                query: None,
+                start_timestamp: Some(1_700_000_000),
+                end_timestamp: None,
+            }),
+            transform_config: None,
+            input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reindex_source_requires_store_source() {
+        let metastore = metastore_for_test();
+        let source_config = reindex_source_config();
+        let index_uid = create_source_and_target_indexes(&metastore, &source_config, false).await;
+
+        let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config)
+            .with_metastore(metastore)
+            .with_reindex_searcher(Arc::new(MockReindexSearcher::default()))
+            .build();
+        let SourceParams::Reindex(params) = source_runtime.source_config.source_params.clone()
+        else {
+            unreachable!()
+        };
+        let error = ReindexSourceFactory::typed_create_source(source_runtime, params)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "index `source-index` does not store the source of its documents (`store_source: \
+             false`) and cannot be reindexed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reindex_source() {
+        let metastore = metastore_for_test();
+        let source_config = reindex_source_config();
+        let index_uid = create_source_and_target_indexes(&metastore, &source_config, true).await;
+
+        // Three documents share a timestamp, and the second page ends in the middle of them.
+        let searcher = Arc::new(MockReindexSearcher {
+            docs: vec![
+                (1_700_000_001_000_000_000, json!({"body": "doc-1"})),
+                (1_700_000_002_000_000_000, json!({"body": "doc-2"})),
+                (1_700_000_002_000_000_000, json!({"body": "doc-3"})),
+                (1_700_000_002_000_000_000, json!({"body": "doc-4"})),
+                (1_700_000_003_000_000_000, json!({"body": "doc-5"})),
+            ],
+            ..Default::default()
+        });
+        let source_runtime = SourceRuntimeBuilder::new(index_uid.clone(), source_config)
+            .with_metastore(metastore.clone())
+            .with_reindex_searcher(searcher.clone())
+            .build();
+        let SourceParams::Reindex(params) = source_runtime.source_config.source_params.clone()
+        else {
+            unreachable!()
+        };
+        let mut reindex_source = ReindexSourceFactory::typed_create_source(source_runtime, params)
+            .await
+            .unwrap();
+        reindex_source.search_request.max_hits = 2;
+
+        let universe = Universe::with_accelerated_time();
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+        let reindex_source_actor = SourceActor {
+            source: Box::new(reindex_source),
+            doc_processor_mailbox,
+        };
+        let (_reindex_source_mailbox, reindex_source_handle) =
+            universe.spawn_builder().spawn(reindex_source_actor);
+        universe.sleep(Duration::from_secs(2)).await;
+
+        let counters = reindex_source_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters["source_index_id"], json!("source-index"));
+        assert_eq!(counters["num_docs_processed"], json!(5));
+        assert_eq!(counters["completed"], json!(true));
+
+        let doc_batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(doc_batches.len(), 3);
+
+        let bodies: Vec<Vec<String>> = doc_batches
+            .iter()
+            .map(|doc_batch| {
+                doc_batch
+                    .docs
+                    .iter()
+                    .map(|doc| {
+                        let doc_json: JsonValue = serde_json::from_slice(doc).unwrap();
+                        doc_json["body"].as_str().unwrap().to_string()
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            bodies,
+            [
+                vec!["doc-1", "doc-2"],
+                vec!["doc-4", "doc-3"],
+                vec!["doc-5"]
+            ]
+        );
+        let (_, partition_delta) = doc_batches[0].checkpoint_delta.iter().next().unwrap();
+        assert_eq!(partition_delta.from, Position::Beginning);
+        assert!(!partition_delta.to.is_eof());
+
+        let (_, partition_delta) = doc_batches[1].checkpoint_delta.iter().next().unwrap();
+        let cursor = ReindexCursor::from_position(&partition_delta.to).unwrap();
+        assert_eq!(cursor.num_docs, 4);
+        let resume_point = cursor.resume_point_opt.unwrap();
+        assert_eq!(resume_point.timestamp_nanos, 1_700_000_002_000_000_000);
+        assert_eq!(resume_point.doc_hashes.len(), 3);
+
+        let (_, partition_delta) = doc_batches[2].checkpoint_delta.iter().next().unwrap();
+        assert!(partition_delta.to.is_eof());
+
+        {
+            let search_requests = searcher.search_requests.lock().unwrap();
+            assert_eq!(search_requests.len(), 3);
+            assert_eq!(search_requests[0].index_id_patterns, ["source-index"]);
+            assert_eq!(search_requests[0].sort_fields[0].field_name, "timestamp");
+            assert_eq!(search_requests[0].start_timestamp, Some(1_700_000_000));
+            assert_eq!(search_requests[0].max_hits, 2);
+            assert_eq!(search_requests[1].max_hits, 3);
+            assert_eq!(search_requests[2].max_hits, 5);
+            assert!(search_requests
+                .iter()
+                .all(|search_request| search_request.search_after.is_none()));
+        }
+        // The source deletes itself once its last checkpoint is published.
+        let mut checkpoint_delta = doc_batches[0].checkpoint_delta.clone();
+        for doc_batch in &doc_batches[1..] {
+            checkpoint_delta
+                .extend(doc_batch.checkpoint_delta.clone())
+                .unwrap();
+        }
+        let index_checkpoint_delta = IndexCheckpointDelta {
+            source_id: "test-reindex-source".to_string(),
+            source_delta: checkpoint_delta,
+        };
+        let publish_splits_request = PublishSplitsRequest {
+            index_uid: Some(index_uid.clone()),
+            index_checkpoint_delta_json_opt: Some(
+                serde_json::to_string(&index_checkpoint_delta).unwrap(),
+            ),
+            ..Default::default()
+        };
+        metastore
+            .clone()
+            .publish_splits(publish_splits_request)
+            .await
+            .unwrap();
+        universe.sleep(COMPLETION_CHECK_INTERVAL * 2).await;
+
+        let index_metadata = metastore
+            .index_metadata(IndexMetadataRequest::for_index_uid(index_uid))
+            .await
+            .unwrap()
+            .deserialize_index_metadata()
+            .unwrap();
+        assert!(!index_metadata.sources.contains_key("test-reindex-source"));
+
+        reindex_source_handle.quit().await;
+        universe.assert_quit().await;
+    }
+}
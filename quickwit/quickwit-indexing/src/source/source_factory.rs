@@ -133,6 +133,7 @@ mod tests {
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let source_runtime = SourceRuntimeBuilder::new(index_uid, source_config).build();
         source_loader.load_source(source_runtime).await?;
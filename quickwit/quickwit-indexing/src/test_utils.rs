@@ -175,6 +175,7 @@ impl TestSandbox {
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         let pipeline_id = self
             .indexing_service
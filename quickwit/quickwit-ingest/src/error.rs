@@ -134,6 +134,15 @@ impl From<IngestFailure> for IngestServiceError {
             IngestFailureReason::CircuitBreaker => {
                 IngestServiceError::RateLimited(RateLimitingCause::CircuitBreaker)
             }
+            IngestFailureReason::IndexRateLimited => {
+                IngestServiceError::RateLimited(RateLimitingCause::IndexRateLimiting)
+            }
+            IngestFailureReason::IndexWalQuotaExceeded => {
+                IngestServiceError::RateLimited(RateLimitingCause::IndexWalQuotaExceeded)
+            }
+            IngestFailureReason::ReplicationFailed => {
+                IngestServiceError::Unavailable("replication failed".to_string())
+            }
         }
     }
 }
@@ -54,16 +54,19 @@ pub struct ShardInfo {
     pub short_term_ingestion_rate: RateMibPerSec,
     /// Long term ingestion rate. It is measured over a larger period of time.
     pub long_term_ingestion_rate: RateMibPerSec,
+    /// Approximate number of bytes held by the shard in the WAL.
+    pub num_wal_bytes: u64,
 }
 
 impl Serialize for ShardInfo {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         serializer.serialize_str(&format!(
-            "{}:{}:{}:{}",
+            "{}:{}:{}:{}:{}",
             self.shard_id,
             self.shard_state.as_json_str_name(),
             self.short_term_ingestion_rate.0,
             self.long_term_ingestion_rate.0,
+            self.num_wal_bytes,
         ))
     }
 }
@@ -99,11 +102,20 @@ impl<'de> Deserialize<'de> for ShardInfo {
             .map(RateMibPerSec)
             .map_err(|_| serde::de::Error::custom("invalid shard ingestion rate"))?;
 
+        // Ingesters running an older version do not broadcast their WAL usage.
+        let num_wal_bytes = parts
+            .next()
+            .map(|part| part.parse::<u64>())
+            .transpose()
+            .map_err(|_| serde::de::Error::custom("invalid shard WAL usage"))?
+            .unwrap_or_default();
+
         Ok(Self {
             shard_id,
             shard_state,
             short_term_ingestion_rate,
             long_term_ingestion_rate,
+            num_wal_bytes,
         })
     }
 }
@@ -179,17 +191,19 @@ impl ShardThroughputTimeSeriesMap {
     #[allow(clippy::mutable_key_type)]
     pub fn record_shard_throughputs(
         &mut self,
-        shard_throughputs: HashMap<(SourceUid, ShardId), (ShardState, ConstantRate)>,
+        shard_throughputs: HashMap<(SourceUid, ShardId), (ShardState, ConstantRate, u64)>,
     ) {
         self.shard_time_series
             .retain(|key, _| shard_throughputs.contains_key(key));
-        for ((source_uid, shard_id), (shard_state, throughput)) in shard_throughputs {
+        for ((source_uid, shard_id), (shard_state, throughput, num_wal_bytes)) in shard_throughputs
+        {
             let throughput_measurement = throughput.rescale(Duration::from_secs(1)).work_bytes();
             let shard_time_series = self
                 .shard_time_series
                 .entry((source_uid.clone(), shard_id.clone()))
                 .or_default();
             shard_time_series.shard_state = shard_state;
+            shard_time_series.num_wal_bytes = num_wal_bytes;
             shard_time_series.record(throughput_measurement);
         }
     }
@@ -220,6 +234,7 @@ impl ShardThroughputTimeSeriesMap {
                 shard_state,
                 short_term_ingestion_rate,
                 long_term_ingestion_rate,
+                num_wal_bytes: shard_time_series.num_wal_bytes,
             };
 
             per_source_shard_infos
@@ -234,6 +249,7 @@ impl ShardThroughputTimeSeriesMap {
 #[derive(Default)]
 struct ShardThroughputTimeSeries {
     shard_state: ShardState,
+    num_wal_bytes: u64,
     measurements: [ByteSize; SHARD_THROUGHPUT_LONG_TERM_WINDOW_LEN],
     len: usize,
 }
@@ -284,12 +300,12 @@ impl BroadcastLocalShardsTask {
             return Some(LocalShardsSnapshot::default());
         };
 
-        let queue_ids: Vec<(QueueId, ShardState)> = state_guard
+        let queue_ids: Vec<(QueueId, ShardState, u64)> = state_guard
             .shards
             .iter()
             .filter_map(|(queue_id, shard)| {
                 if shard.is_advertisable && !shard.is_replica() {
-                    Some((queue_id.clone(), shard.shard_state))
+                    Some((queue_id.clone(), shard.shard_state, shard.num_wal_bytes))
                 } else {
                     None
                 }
@@ -300,9 +316,12 @@ impl BroadcastLocalShardsTask {
         let mut num_closed_shards = 0;
 
         #[allow(clippy::mutable_key_type)]
-        let ingestion_rates: HashMap<(SourceUid, ShardId), (ShardState, ConstantRate)> = queue_ids
+        let ingestion_rates: HashMap<
+            (SourceUid, ShardId),
+            (ShardState, ConstantRate, u64),
+        > = queue_ids
             .iter()
-            .flat_map(|(queue_id, shard_state)| {
+            .flat_map(|(queue_id, shard_state, num_wal_bytes)| {
                 let Some((_rate_limiter, rate_meter)) = state_guard.rate_trackers.get_mut(queue_id)
                 else {
                     warn!(
@@ -317,7 +336,10 @@ impl BroadcastLocalShardsTask {
                     source_id,
                 };
                 // Shard ingestion rate in MiB/s.
-                Some(((source_uid, shard_id), (*shard_state, rate_meter.harvest())))
+                Some((
+                    (source_uid, shard_id),
+                    (*shard_state, rate_meter.harvest(), *num_wal_bytes),
+                ))
             })
             .collect();
 
@@ -467,12 +489,17 @@ mod tests {
             shard_state: ShardState::Open,
             short_term_ingestion_rate: RateMibPerSec(42),
             long_term_ingestion_rate: RateMibPerSec(40),
+            num_wal_bytes: 1024,
         };
         let serialized = serde_json::to_string(&shard_info).unwrap();
-        assert_eq!(serialized, r#""00000000000000000001:open:42:40""#);
+        assert_eq!(serialized, r#""00000000000000000001:open:42:40:1024""#);
 
         let deserialized = serde_json::from_str::<ShardInfo>(&serialized).unwrap();
         assert_eq!(deserialized, shard_info);
+
+        let deserialized =
+            serde_json::from_str::<ShardInfo>(r#""00000000000000000001:open:42:40""#).unwrap();
+        assert_eq!(deserialized.num_wal_bytes, 0);
     }
 
     #[test]
@@ -495,6 +522,7 @@ mod tests {
                     shard_state: ShardState::Open,
                     short_term_ingestion_rate: RateMibPerSec(42),
                     long_term_ingestion_rate: RateMibPerSec(42),
+                    num_wal_bytes: 0,
                 }]
                 .into_iter()
                 .collect(),
@@ -536,6 +564,7 @@ mod tests {
                     shard_state: ShardState::Closed,
                     short_term_ingestion_rate: RateMibPerSec(42),
                     long_term_ingestion_rate: RateMibPerSec(42),
+                    num_wal_bytes: 0,
                 }]
                 .into_iter()
                 .collect(),
@@ -732,6 +761,7 @@ mod tests {
             shard_state: ShardState::Open,
             short_term_ingestion_rate: RateMibPerSec(42),
             long_term_ingestion_rate: RateMibPerSec(42),
+            num_wal_bytes: 0,
         }])
         .unwrap();
 
@@ -294,12 +294,12 @@ impl MultiFetchStream {
         self.fetch_message_tx.clone()
     }
 
-    /// Subscribes to a shard and fails over to the replica if an error occurs.
+    /// Subscribes to a shard and fails over to the replicas if an error occurs.
     #[allow(clippy::too_many_arguments)]
     pub async fn subscribe(
         &mut self,
         leader_id: NodeId,
-        follower_id_opt: Option<NodeId>,
+        follower_ids: Vec<NodeId>,
         index_uid: IndexUid,
         source_id: SourceId,
         shard_id: ShardId,
@@ -313,15 +313,12 @@ impl MultiFetchStream {
                 "stream has already subscribed to shard `{queue_id}`"
             )));
         }
-        let (preferred_ingester_id, failover_ingester_id_opt) =
-            select_preferred_and_failover_ingesters(&self.self_node_id, leader_id, follower_id_opt);
+        let (preferred_ingester_id, failover_ingester_ids) =
+            select_preferred_and_failover_ingesters(&self.self_node_id, leader_id, follower_ids);
 
-        let mut ingester_ids = Vec::with_capacity(1 + failover_ingester_id_opt.is_some() as usize);
+        let mut ingester_ids = Vec::with_capacity(1 + failover_ingester_ids.len());
         ingester_ids.push(preferred_ingester_id);
-
-        if let Some(failover_ingester_id) = failover_ingester_id_opt {
-            ingester_ids.push(failover_ingester_id);
-        }
+        ingester_ids.extend(failover_ingester_ids);
         let fetch_stream_future = retrying_fetch_stream(
             self.client_id.clone(),
             index_uid,
@@ -387,25 +384,27 @@ impl Drop for MultiFetchStream {
     }
 }
 
-/// Chooses the ingester to stream records from, preferring "local" ingesters.
+/// Chooses the ingester to stream records from, preferring "local" ingesters. The other ingesters
+/// are returned as failovers, leader first.
 fn select_preferred_and_failover_ingesters(
     self_node_id: &NodeId,
     leader_id: NodeId,
-    follower_id_opt: Option<NodeId>,
-) -> (NodeId, Option<NodeId>) {
+    follower_ids: Vec<NodeId>,
+) -> (NodeId, Vec<NodeId>) {
     // The replication factor is 1 and there is no follower.
-    let Some(follower_id) = follower_id_opt else {
-        return (leader_id, None);
-    };
-    if &leader_id == self_node_id {
-        (leader_id, Some(follower_id))
-    } else if &follower_id == self_node_id {
-        (follower_id, Some(leader_id))
-    } else if rand::random::<bool>() {
-        (leader_id, Some(follower_id))
-    } else {
-        (follower_id, Some(leader_id))
+    if follower_ids.is_empty() {
+        return (leader_id, Vec::new());
     }
+    let mut ingester_ids = Vec::with_capacity(1 + follower_ids.len());
+    ingester_ids.push(leader_id);
+    ingester_ids.extend(follower_ids);
+
+    let preferred_idx = ingester_ids
+        .iter()
+        .position(|ingester_id| ingester_id == self_node_id)
+        .unwrap_or_else(|| rand::random::<usize>() % ingester_ids.len());
+    let preferred_ingester_id = ingester_ids.remove(preferred_idx);
+    (preferred_ingester_id, ingester_ids)
 }
 
 /// Performs multiple fault-tolerant fetch stream attempts until the stream reaches
@@ -502,6 +501,20 @@ async fn fault_tolerant_fetch_stream(
         };
         let mut fetch_stream = match ingester.open_fetch_stream(open_fetch_stream_request).await {
             Ok(fetch_stream) => fetch_stream,
+            Err(IngestV2Error::ShardNotFound { .. }) if failover_ingester_id_opt.is_some() => {
+                // Lagging replica shards are deleted by the leader, so the other replicas may
+                // still hold the shard.
+                let failover_ingester_id =
+                    failover_ingester_id_opt.expect("failover ingester ID should be set");
+                warn!(
+                    client_id=%client_id,
+                    index_uid=%index_uid,
+                    source_id=%source_id,
+                    shard_id=%shard_id,
+                    "failed to open fetch stream from ingester `{ingester_id}`: shard not found, failing over to ingester `{failover_ingester_id}`"
+                );
+                continue;
+            }
             Err(not_found_error @ IngestV2Error::ShardNotFound { .. }) => {
                 error!(
                     client_id=%client_id,
@@ -1281,26 +1294,49 @@ pub(super) mod tests {
     fn test_select_preferred_and_failover_ingesters() {
         let self_node_id: NodeId = "test-ingester-0".into();
 
-        let (preferred, failover) =
-            select_preferred_and_failover_ingesters(&self_node_id, "test-ingester-0".into(), None);
+        let (preferred, failovers) = select_preferred_and_failover_ingesters(
+            &self_node_id,
+            "test-ingester-0".into(),
+            Vec::new(),
+        );
         assert_eq!(preferred, "test-ingester-0");
-        assert!(failover.is_none());
+        assert!(failovers.is_empty());
 
-        let (preferred, failover) = select_preferred_and_failover_ingesters(
+        let (preferred, failovers) = select_preferred_and_failover_ingesters(
             &self_node_id,
             "test-ingester-0".into(),
-            Some("test-ingester-1".into()),
+            vec!["test-ingester-1".into()],
         );
         assert_eq!(preferred, "test-ingester-0");
-        assert_eq!(failover.unwrap(), "test-ingester-1");
+        assert_eq!(failovers, ["test-ingester-1"]);
 
-        let (preferred, failover) = select_preferred_and_failover_ingesters(
+        let (preferred, failovers) = select_preferred_and_failover_ingesters(
             &self_node_id,
             "test-ingester-1".into(),
-            Some("test-ingester-0".into()),
+            vec!["test-ingester-0".into()],
         );
         assert_eq!(preferred, "test-ingester-0");
-        assert_eq!(failover.unwrap(), "test-ingester-1");
+        assert_eq!(failovers, ["test-ingester-1"]);
+
+        let (preferred, failovers) = select_preferred_and_failover_ingesters(
+            &self_node_id,
+            "test-ingester-1".into(),
+            vec!["test-ingester-2".into(), "test-ingester-0".into()],
+        );
+        assert_eq!(preferred, "test-ingester-0");
+        assert_eq!(failovers, ["test-ingester-1", "test-ingester-2"]);
+
+        let (preferred, mut failovers) = select_preferred_and_failover_ingesters(
+            &self_node_id,
+            "test-ingester-1".into(),
+            vec!["test-ingester-2".into(), "test-ingester-3".into()],
+        );
+        failovers.push(preferred);
+        failovers.sort();
+        assert_eq!(
+            failovers,
+            ["test-ingester-1", "test-ingester-2", "test-ingester-3"]
+        );
     }
 
     #[tokio::test]
@@ -1660,6 +1696,17 @@ pub(super) mod tests {
         let ingester_0 = IngesterServiceClient::from_mock(mock_ingester_0);
         ingester_pool.insert("test-ingester-0".into(), ingester_0);
 
+        let mut mock_ingester_1 = MockIngesterService::new();
+        mock_ingester_1
+            .expect_open_fetch_stream()
+            .return_once(move |_request| {
+                Err(IngestV2Error::ShardNotFound {
+                    shard_id: ShardId::from(1),
+                })
+            });
+        let ingester_1 = IngesterServiceClient::from_mock(mock_ingester_1);
+        ingester_pool.insert("test-ingester-1".into(), ingester_1);
+
         fault_tolerant_fetch_stream(
             client_id,
             index_uid,
@@ -1685,6 +1732,97 @@ pub(super) mod tests {
         assert!(from_position_exclusive.is_eof());
     }
 
+    #[tokio::test]
+    async fn test_fault_tolerant_fetch_stream_shard_not_found_failover() {
+        let client_id = "test-client".to_string();
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+        let source_id: SourceId = "test-source".into();
+        let shard_id = ShardId::from(1);
+        let mut from_position_exclusive = Position::offset(0u64);
+
+        let ingester_ids: Vec<NodeId> = vec!["test-ingester-0".into(), "test-ingester-1".into()];
+        let ingester_pool = IngesterPool::default();
+
+        let (fetch_message_tx, mut fetch_stream) = ServiceStream::new_bounded(5);
+        let (service_stream_tx_1, service_stream_1) = ServiceStream::new_unbounded();
+
+        let mut mock_ingester_0 = MockIngesterService::new();
+        mock_ingester_0
+            .expect_open_fetch_stream()
+            .return_once(move |_request| {
+                Err(IngestV2Error::ShardNotFound {
+                    shard_id: ShardId::from(1),
+                })
+            });
+        let ingester_0 = IngesterServiceClient::from_mock(mock_ingester_0);
+        ingester_pool.insert("test-ingester-0".into(), ingester_0);
+
+        let mut mock_ingester_1 = MockIngesterService::new();
+        mock_ingester_1
+            .expect_open_fetch_stream()
+            .return_once(move |request| {
+                assert_eq!(request.from_position_exclusive(), Position::offset(0u64));
+                Ok(service_stream_1)
+            });
+        let ingester_1 = IngesterServiceClient::from_mock(mock_ingester_1);
+        ingester_pool.insert("test-ingester-1".into(), ingester_1);
+
+        let fetch_payload = FetchPayload {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(shard_id.clone()),
+            mrecord_batch: MRecordBatch::for_test(["\0\0test-doc-foo"]),
+            from_position_exclusive: Some(Position::offset(0u64)),
+            to_position_inclusive: Some(Position::offset(1u64)),
+        };
+        let fetch_message = FetchMessage::new_payload(fetch_payload);
+        service_stream_tx_1.send(Ok(fetch_message)).unwrap();
+
+        let fetch_eof = FetchEof {
+            index_uid: Some(index_uid.clone()),
+            source_id: source_id.clone(),
+            shard_id: Some(shard_id.clone()),
+            eof_position: Some(Position::eof(1u64)),
+        };
+        let fetch_message = FetchMessage::new_eof(fetch_eof);
+        service_stream_tx_1.send(Ok(fetch_message)).unwrap();
+
+        fault_tolerant_fetch_stream(
+            client_id,
+            index_uid,
+            source_id,
+            shard_id,
+            &mut from_position_exclusive,
+            &ingester_ids,
+            ingester_pool,
+            fetch_message_tx,
+        )
+        .await;
+
+        let fetch_message = timeout(Duration::from_millis(100), fetch_stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+            .into_inner();
+        let fetch_payload = into_fetch_payload(fetch_message);
+        assert_eq!(
+            fetch_payload.to_position_inclusive(),
+            Position::offset(1u64)
+        );
+
+        let fetch_message = timeout(Duration::from_millis(100), fetch_stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+            .into_inner();
+        let fetch_eof = into_fetch_eof(fetch_message);
+        assert_eq!(fetch_eof.eof_position(), Position::eof(1u64));
+
+        assert_eq!(from_position_exclusive, Position::eof(1u64));
+    }
+
     #[tokio::test]
     async fn test_retrying_fetch_stream() {
         let client_id = "test-client".to_string();
@@ -28,7 +28,7 @@ use anyhow::Context;
 use async_trait::async_trait;
 use bytesize::ByteSize;
 use futures::stream::FuturesUnordered;
-use futures::StreamExt;
+use futures::{FutureExt, StreamExt};
 use mrecordlog::error::CreateQueueError;
 use once_cell::sync::OnceCell;
 use quickwit_cluster::Cluster;
@@ -50,14 +50,15 @@ use quickwit_proto::ingest::ingester::{
     OpenReplicationStreamRequest, OpenReplicationStreamResponse, PersistFailure,
     PersistFailureReason, PersistRequest, PersistResponse, PersistSuccess, ReplicateFailureReason,
     ReplicateSubrequest, RetainShardsForSource, RetainShardsRequest, RetainShardsResponse,
-    SynReplicationMessage, TruncateShardsRequest, TruncateShardsResponse,
+    SynReplicationMessage, TruncateShardsRequest, TruncateShardsResponse, TruncateShardsSubrequest,
 };
 use quickwit_proto::ingest::{
     CommitTypeV2, DocBatchV2, IngestV2Error, IngestV2Result, ParseFailure, Shard, ShardIds,
     ShardState,
 };
 use quickwit_proto::types::{
-    queue_id, split_queue_id, IndexUid, NodeId, Position, QueueId, ShardId, SourceId, SubrequestId,
+    queue_id, split_queue_id, IndexUid, NodeId, NodeIdRef, Position, QueueId, ShardId, SourceId,
+    SubrequestId,
 };
 use serde_json::{json, Value as JsonValue};
 use tokio::sync::Semaphore;
@@ -79,7 +80,7 @@ use super::replication::{
     SYN_REPLICATION_STREAM_CAPACITY,
 };
 use super::state::{IngesterState, InnerIngesterState, WeakIngesterState};
-use super::IngesterPool;
+use super::{replication_quorum, IngesterPool};
 use crate::ingest_v2::doc_mapper::get_or_try_build_doc_mapper;
 use crate::ingest_v2::metrics::report_wal_usage;
 use crate::ingest_v2::models::IngesterShardType;
@@ -228,26 +229,30 @@ impl Ingester {
             .rate_trackers
             .insert(queue_id, (rate_limiter, rate_meter));
 
-        let primary_shard = if let Some(follower_id) = &shard.follower_id {
-            let leader_id: NodeId = shard.leader_id.clone().into();
-            let follower_id: NodeId = follower_id.clone().into();
+        let follower_ids: Vec<NodeId> = shard.followers().map(NodeIdRef::to_owned).collect();
 
-            let replication_client = self
-                .init_replication_stream(
-                    &mut state.replication_streams,
-                    leader_id,
-                    follower_id.clone(),
-                )
-                .await?;
+        let primary_shard = if !follower_ids.is_empty() {
+            let leader_id: NodeId = shard.leader_id.clone().into();
 
-            if let Err(error) = replication_client.init_replica(shard).await {
-                // TODO: Remove dangling queue from the WAL.
-                error!("failed to initialize replica shard: {error}");
-                let message = format!("failed to initialize replica shard: {error}");
-                return Err(IngestV2Error::Internal(message));
+            for follower_id in &follower_ids {
+                let replication_client = self
+                    .init_replication_stream(
+                        &mut state.replication_streams,
+                        leader_id.clone(),
+                        follower_id.clone(),
+                    )
+                    .await?;
+
+                if let Err(error) = replication_client.init_replica(shard.clone()).await {
+                    // TODO: Remove dangling queue from the WAL.
+                    error!("failed to initialize replica shard on `{follower_id}`: {error}");
+                    let message =
+                        format!("failed to initialize replica shard on `{follower_id}`: {error}");
+                    return Err(IngestV2Error::Internal(message));
+                }
             }
             IngesterShard::new_primary(
-                follower_id,
+                follower_ids,
                 ShardState::Open,
                 Position::Beginning,
                 Position::Beginning,
@@ -279,6 +284,36 @@ impl Ingester {
         tokio::spawn(future);
     }
 
+    /// Deletes the replica shards that missed an acknowledged batch in a separate background task.
+    /// Fetch streams opened against these replicas then fail over to the other ingesters.
+    fn background_delete_lagging_replica_shards(
+        &self,
+        lagging_replica_shards: HashMap<NodeId, Vec<TruncateShardsSubrequest>>,
+    ) {
+        for (follower_id, subrequests) in lagging_replica_shards {
+            let Some(ingester) = self.ingester_pool.get(&follower_id) else {
+                warn!(
+                    "failed to delete lagging replica shards: ingester `{follower_id}` is \
+                     unavailable"
+                );
+                continue;
+            };
+            let truncate_shards_request = TruncateShardsRequest {
+                ingester_id: follower_id.to_string(),
+                subrequests,
+            };
+            let future = async move {
+                if let Err(error) = ingester.truncate_shards(truncate_shards_request).await {
+                    warn!(
+                        "failed to delete lagging replica shards on ingester `{follower_id}`: \
+                         {error}"
+                    );
+                }
+            };
+            tokio::spawn(future);
+        }
+    }
+
     /// Resets the local shards at most once by minute by querying the control plane for the shards
     /// that should be deleted or truncated and then performing the requested operations.
     ///
@@ -453,6 +488,15 @@ impl Ingester {
         // Keep track of the shards that need to be closed following an IO error.
         let mut shards_to_close: HashSet<QueueId> = HashSet::new();
 
+        // Keep track of the shards that need to be closed because some of their replicas failed to
+        // persist a batch that was nonetheless acknowledged by a quorum of replicas.
+        let mut shards_to_close_after_partial_replication: HashSet<QueueId> = HashSet::new();
+
+        // Keep track of the replica shards that missed an acknowledged batch. They are deleted so
+        // that fetch streams fail over to the replicas that hold every acknowledged record.
+        let mut lagging_replica_shards: HashMap<NodeId, Vec<TruncateShardsSubrequest>> =
+            HashMap::new();
+
         // Keep track of dangling shards, i.e., shards for which there is no longer a corresponding
         // queue in the WAL and should be deleted.
         let mut shards_to_delete: HashSet<QueueId> = HashSet::new();
@@ -520,7 +564,7 @@ impl Ingester {
                 }
                 let doc_mapper = shard.doc_mapper_opt.clone().expect("shard should be open");
                 let validate_shard = shard.validate;
-                let follower_id_opt = shard.follower_id_opt().cloned();
+                let follower_ids = shard.follower_ids().to_vec();
                 let from_position_exclusive = shard.replication_position_inclusive.clone();
 
                 let doc_batch = match subrequest.doc_batch {
@@ -619,17 +663,17 @@ impl Ingester {
                 rate_meter.update(valid_batch_num_bytes);
                 total_requested_capacity += requested_capacity;
 
-                let mut successfully_replicated = true;
-
-                if let Some(follower_id) = follower_id_opt {
-                    successfully_replicated = false;
+                // The leader counts towards the quorum.
+                let num_required_replica_acks = replication_quorum(follower_ids.len() + 1) - 1;
+                let num_followers = follower_ids.len();
 
+                for follower_id in follower_ids {
                     let replicate_subrequest = ReplicateSubrequest {
                         subrequest_id: subrequest.subrequest_id,
                         index_uid: subrequest.index_uid.clone(),
                         source_id: subrequest.source_id.clone(),
                         shard_id: subrequest.shard_id.clone(),
-                        from_position_exclusive: Some(from_position_exclusive),
+                        from_position_exclusive: Some(from_position_exclusive.clone()),
                         doc_batch: Some(valid_doc_batch.clone()),
                     };
                     per_follower_replicate_subrequests
@@ -646,7 +690,11 @@ impl Ingester {
                     doc_batch: valid_doc_batch,
                     parse_failures,
                     expected_position_inclusive: None,
-                    successfully_replicated,
+                    num_followers,
+                    num_required_replica_acks,
+                    num_replica_acks: 0,
+                    replicate_failure_reason_opt: None,
+                    lagging_follower_ids: Vec::new(),
                 };
                 pending_persist_subrequests.insert(
                     pending_persist_subrequest.subrequest_id,
@@ -654,7 +702,7 @@ impl Ingester {
                 );
             }
         }
-        // replicate to the follower
+        // replicate to the followers
         {
             let mut replicate_futures = FuturesUnordered::new();
 
@@ -665,23 +713,48 @@ impl Ingester {
                     .expect("replication stream should be initialized")
                     .replication_client();
                 let leader_id = self.self_node_id.clone();
-
-                let replicate_future = replication_client.replicate(
-                    leader_id,
-                    follower_id,
-                    replicate_subrequests,
-                    commit_type,
-                );
+                let subrequest_ids: Vec<SubrequestId> = replicate_subrequests
+                    .iter()
+                    .map(|replicate_subrequest| replicate_subrequest.subrequest_id)
+                    .collect();
+                let replicate_future = replication_client
+                    .replicate(
+                        leader_id,
+                        follower_id.clone(),
+                        replicate_subrequests,
+                        commit_type,
+                    )
+                    .map(move |replication_result| {
+                        (follower_id, subrequest_ids, replication_result)
+                    });
                 replicate_futures.push(replicate_future);
             }
-            while let Some(replication_result) = replicate_futures.next().await {
+            while let Some((follower_id, subrequest_ids, replication_result)) =
+                replicate_futures.next().await
+            {
                 let replicate_response = match replication_result {
                     Ok(replicate_response) => replicate_response,
-                    Err(_) => {
-                        // TODO: Handle replication error:
-                        // 1. Close and evict all the shards hosted by the follower.
-                        // 2. Close and evict the replication client.
-                        // 3. Return `PersistFailureReason::ShardClosed` to router.
+                    Err(replication_error) => {
+                        warn!(
+                            "failed to replicate records to follower `{follower_id}`: \
+                             {replication_error}"
+                        );
+                        // The state of the replica shards is unknown, so the affected subrequests
+                        // are rejected unless they reach a quorum without this follower, and the
+                        // shards are closed either way.
+                        for subrequest_id in subrequest_ids {
+                            let pending_persist_subrequest = pending_persist_subrequests
+                                .get_mut(&subrequest_id)
+                                .expect("persist subrequest should exist");
+                            shards_to_close_after_partial_replication
+                                .insert(pending_persist_subrequest.queue_id.clone());
+                            pending_persist_subrequest
+                                .lagging_follower_ids
+                                .push(follower_id.clone());
+                            pending_persist_subrequest
+                                .replicate_failure_reason_opt
+                                .get_or_insert(PersistFailureReason::ReplicationFailed);
+                        }
                         continue;
                     }
                 };
@@ -690,7 +763,20 @@ impl Ingester {
                         .get_mut(&replicate_success.subrequest_id)
                         .expect("persist subrequest should exist");
 
-                    pending_persist_subrequest.successfully_replicated = true;
+                    if let Some(expected_position_inclusive) =
+                        &pending_persist_subrequest.expected_position_inclusive
+                    {
+                        if replicate_success.replication_position_inclusive.as_ref()
+                            != Some(expected_position_inclusive)
+                        {
+                            return Err(IngestV2Error::Internal(format!(
+                                "inconsistent replica positions: expected \
+                                 {expected_position_inclusive:?}, got {:?}",
+                                replicate_success.replication_position_inclusive
+                            )));
+                        }
+                    }
+                    pending_persist_subrequest.num_replica_acks += 1;
                     pending_persist_subrequest.expected_position_inclusive =
                         replicate_success.replication_position_inclusive;
                 }
@@ -705,27 +791,72 @@ impl Ingester {
                         ReplicateFailureReason::ShardClosed => PersistFailureReason::ShardClosed,
                         ReplicateFailureReason::WalFull => PersistFailureReason::WalFull,
                     };
-                    let persist_failure = PersistFailure {
-                        subrequest_id: replicate_failure.subrequest_id,
-                        index_uid: replicate_failure.index_uid,
-                        source_id: replicate_failure.source_id,
-                        shard_id: replicate_failure.shard_id,
-                        reason: persist_failure_reason as i32,
-                    };
-                    persist_failures.push(persist_failure);
+                    let pending_persist_subrequest = pending_persist_subrequests
+                        .get_mut(&replicate_failure.subrequest_id)
+                        .expect("persist subrequest should exist");
+
+                    pending_persist_subrequest
+                        .lagging_follower_ids
+                        .push(follower_id.clone());
+                    pending_persist_subrequest
+                        .replicate_failure_reason_opt
+                        .get_or_insert(persist_failure_reason);
                 }
             }
+            // Subrequests that did not reach a quorum of replicas are rejected.
+            for pending_persist_subrequest in pending_persist_subrequests.values() {
+                if pending_persist_subrequest.reached_quorum() {
+                    continue;
+                }
+                let persist_failure_reason = pending_persist_subrequest
+                    .replicate_failure_reason_opt
+                    .unwrap_or(PersistFailureReason::ReplicationFailed);
+
+                if pending_persist_subrequest.num_replica_acks > 0 {
+                    // Some replicas persisted a batch that the leader rejects, so the replicas
+                    // diverged.
+                    shards_to_close_after_partial_replication
+                        .insert(pending_persist_subrequest.queue_id.clone());
+                }
+                let persist_failure = PersistFailure {
+                    subrequest_id: pending_persist_subrequest.subrequest_id,
+                    index_uid: pending_persist_subrequest.index_uid.clone(),
+                    source_id: pending_persist_subrequest.source_id.clone(),
+                    shard_id: pending_persist_subrequest.shard_id.clone(),
+                    reason: persist_failure_reason as i32,
+                };
+                persist_failures.push(persist_failure);
+            }
         }
         // finally write locally
         {
             let now = Instant::now();
             for subrequest in pending_persist_subrequests.into_values() {
-                if !subrequest.successfully_replicated {
+                if !subrequest.reached_quorum() {
                     continue;
                 }
+                if subrequest.num_replica_acks < subrequest.num_followers {
+                    // The batch is acknowledged because a quorum of replicas persisted it, but the
+                    // lagging replicas must not receive subsequent writes, so we close the shard.
+                    shards_to_close_after_partial_replication.insert(subrequest.queue_id.clone());
+
+                    for follower_id in &subrequest.lagging_follower_ids {
+                        let truncate_shards_subrequest = TruncateShardsSubrequest {
+                            index_uid: subrequest.index_uid.clone(),
+                            source_id: subrequest.source_id.clone(),
+                            shard_id: subrequest.shard_id.clone(),
+                            truncate_up_to_position_inclusive: Some(Position::Eof(None)),
+                        };
+                        lagging_replica_shards
+                            .entry(follower_id.clone())
+                            .or_default()
+                            .push(truncate_shards_subrequest);
+                    }
+                }
                 let queue_id = subrequest.queue_id;
 
                 let batch_num_docs = subrequest.doc_batch.num_docs() as u64;
+                let batch_num_wal_bytes = estimate_size(&subrequest.doc_batch).as_u64();
 
                 let append_result = append_non_empty_doc_batch(
                     &mut state_guard.mrecordlog,
@@ -775,11 +906,12 @@ impl Ingester {
                         )));
                     }
                 }
-                state_guard
+                let shard = state_guard
                     .shards
                     .get_mut(&queue_id)
-                    .expect("primary shard should exist")
-                    .set_replication_position_inclusive(current_position_inclusive.clone(), now);
+                    .expect("primary shard should exist");
+                shard.set_replication_position_inclusive(current_position_inclusive.clone(), now);
+                shard.num_wal_bytes += batch_num_wal_bytes;
 
                 let persist_success = PersistSuccess {
                     subrequest_id: subrequest.subrequest_id,
@@ -804,6 +936,15 @@ impl Ingester {
                 warn!("closed shard `{queue_id}` following IO error");
             }
         }
+        if !shards_to_close_after_partial_replication.is_empty() {
+            for queue_id in &shards_to_close_after_partial_replication {
+                let Some(shard) = state_guard.shards.get_mut(queue_id) else {
+                    continue;
+                };
+                shard.close();
+                warn!("closed shard `{queue_id}` following partial replication");
+            }
+        }
         if !shards_to_delete.is_empty() {
             for queue_id in &shards_to_delete {
                 state_guard.shards.remove(queue_id);
@@ -814,6 +955,9 @@ impl Ingester {
         let wal_usage = state_guard.mrecordlog.resource_usage();
         drop(state_guard);
 
+        if !lagging_replica_shards.is_empty() {
+            self.background_delete_lagging_replica_shards(lagging_replica_shards);
+        }
         let disk_used = wal_usage.disk_used_bytes as u64;
 
         if disk_used >= self.disk_capacity.as_u64() * 90 / 100 {
@@ -1079,10 +1223,11 @@ impl Ingester {
                 "truncation_position_inclusive": shard.truncation_position_inclusive,
             });
             match &shard.shard_type {
-                IngesterShardType::Primary { follower_id, .. } => {
+                IngesterShardType::Primary { follower_ids, .. } => {
                     shard_json["type"] = json!("primary");
                     shard_json["leader_id"] = json!(self.self_node_id.to_string());
-                    shard_json["follower_id"] = json!(follower_id.to_string());
+                    shard_json["follower_id"] = json!(follower_ids[0].to_string());
+                    shard_json["follower_ids"] = json!(follower_ids);
                 }
                 IngesterShardType::Replica { leader_id } => {
                     shard_json["type"] = json!("replica");
@@ -1285,7 +1430,17 @@ struct PendingPersistSubrequest {
     doc_batch: DocBatchV2,
     parse_failures: Vec<ParseFailure>,
     expected_position_inclusive: Option<Position>,
-    successfully_replicated: bool,
+    num_followers: usize,
+    num_required_replica_acks: usize,
+    num_replica_acks: usize,
+    replicate_failure_reason_opt: Option<PersistFailureReason>,
+    lagging_follower_ids: Vec<NodeId>,
+}
+
+impl PendingPersistSubrequest {
+    fn reached_quorum(&self) -> bool {
+        self.num_replica_acks >= self.num_required_replica_acks
+    }
 }
 
 #[cfg(test)]
@@ -1299,12 +1454,13 @@ mod tests {
     use bytes::Bytes;
     use quickwit_cluster::{create_cluster_for_test_with_id, ChannelTransport};
     use quickwit_common::shared_consts::INGESTER_PRIMARY_SHARDS_PREFIX;
+    use quickwit_common::test_utils::wait_until_predicate;
     use quickwit_common::tower::ConstantRate;
     use quickwit_config::service::QuickwitService;
     use quickwit_proto::control_plane::{AdviseResetShardsResponse, MockControlPlaneService};
     use quickwit_proto::ingest::ingester::{
         IngesterServiceGrpcServer, IngesterServiceGrpcServerAdapter, InitShardSubrequest,
-        PersistSubrequest, TruncateShardsSubrequest,
+        PersistSubrequest,
     };
     use quickwit_proto::ingest::{
         DocBatchV2, ParseFailureReason, ShardIdPosition, ShardIdPositions, ShardIds, ShardPKey,
@@ -1696,6 +1852,7 @@ mod tests {
             shard_state: ShardState::Open as i32,
             leader_id: ingester_ctx.node_id.to_string(),
             follower_id: None,
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(doc_mapping_uid),
             publish_position_inclusive: None,
             publish_token: None,
@@ -2487,6 +2644,146 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_ingester_persist_replicate_quorum() {
+        let (leader_ctx, leader) = IngesterForTest::default()
+            .with_node_id("test-leader")
+            .with_replication()
+            .build()
+            .await;
+
+        let (follower_1_ctx, follower_1) = IngesterForTest::default()
+            .with_node_id("test-follower-1")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        let (follower_2_ctx, follower_2) = IngesterForTest::default()
+            .with_node_id("test-follower-2")
+            .with_ingester_pool(&leader_ctx.ingester_pool)
+            .with_replication()
+            .build()
+            .await;
+
+        leader_ctx.ingester_pool.insert(
+            follower_1_ctx.node_id.clone(),
+            IngesterServiceClient::new(follower_1.clone()),
+        );
+        leader_ctx.ingester_pool.insert(
+            follower_2_ctx.node_id.clone(),
+            IngesterServiceClient::new(follower_2.clone()),
+        );
+
+        let index_uid: IndexUid = IndexUid::for_test("test-index", 0);
+
+        let doc_mapping_uid = DocMappingUid::random();
+        let doc_mapping_json = format!(
+            r#"{{
+                "doc_mapping_uid": "{doc_mapping_uid}"
+            }}"#
+        );
+        let init_shards_request = InitShardsRequest {
+            subrequests: vec![InitShardSubrequest {
+                subrequest_id: 0,
+                shard: Some(Shard {
+                    index_uid: Some(index_uid.clone()),
+                    source_id: "test-source".to_string(),
+                    shard_id: Some(ShardId::from(1)),
+                    shard_state: ShardState::Open as i32,
+                    leader_id: leader_ctx.node_id.to_string(),
+                    follower_id: Some(follower_1_ctx.node_id.to_string()),
+                    additional_follower_ids: vec![follower_2_ctx.node_id.to_string()],
+                    doc_mapping_uid: Some(doc_mapping_uid),
+                    ..Default::default()
+                }),
+                doc_mapping_json,
+                validate_docs: true,
+            }],
+        };
+        leader.init_shards(init_shards_request).await.unwrap();
+
+        let queue_id_01 = queue_id(&index_uid, "test-source", &ShardId::from(1));
+
+        let persist_request = PersistRequest {
+            leader_id: "test-leader".to_string(),
+            commit_type: CommitTypeV2::Force as i32,
+            subrequests: vec![PersistSubrequest {
+                subrequest_id: 0,
+                index_uid: Some(index_uid.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                doc_batch: Some(DocBatchV2::for_test([r#"{"doc": "test-doc-010"}"#])),
+            }],
+        };
+        let persist_response = leader.persist(persist_request.clone()).await.unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        for follower in [&follower_1, &follower_2] {
+            let follower_state_guard = follower.state.lock_fully().await.unwrap();
+            let replica_shard_01 = follower_state_guard.shards.get(&queue_id_01).unwrap();
+            replica_shard_01.assert_is_replica();
+            replica_shard_01.assert_replication_position(Position::offset(1u64));
+        }
+
+        // The second follower can no longer persist documents: the leader and the first follower
+        // still form a quorum, so the batch is acknowledged, but the primary shard gets closed.
+        follower_2
+            .state
+            .lock_fully()
+            .await
+            .unwrap()
+            .shards
+            .get_mut(&queue_id_01)
+            .unwrap()
+            .close();
+
+        let persist_response = leader.persist(persist_request).await.unwrap();
+        assert_eq!(persist_response.successes.len(), 1);
+        assert_eq!(persist_response.failures.len(), 0);
+
+        let persist_success = &persist_response.successes[0];
+        assert_eq!(
+            persist_success.replication_position_inclusive,
+            Some(Position::offset(3u64))
+        );
+
+        let leader_state_guard = leader.state.lock_fully().await.unwrap();
+        let primary_shard_01 = leader_state_guard.shards.get(&queue_id_01).unwrap();
+        primary_shard_01.assert_is_primary();
+        primary_shard_01.assert_is_closed();
+        primary_shard_01.assert_replication_position(Position::offset(3u64));
+
+        drop(leader_state_guard);
+
+        let follower_1_state_guard = follower_1.state.lock_fully().await.unwrap();
+        let replica_shard_01 = follower_1_state_guard.shards.get(&queue_id_01).unwrap();
+        replica_shard_01.assert_replication_position(Position::offset(3u64));
+        drop(follower_1_state_guard);
+
+        // The lagging replica shard is deleted so that fetch streams no longer read from it.
+        wait_until_predicate(
+            || {
+                let follower_2 = follower_2.clone();
+                let queue_id_01 = queue_id_01.clone();
+                async move {
+                    !follower_2
+                        .state
+                        .lock_fully()
+                        .await
+                        .unwrap()
+                        .shards
+                        .contains_key(&queue_id_01)
+                }
+            },
+            Duration::from_secs(5),
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+    }
+
     #[tokio::test]
     async fn test_ingester_persist_replicate_grpc() {
         let (leader_ctx, leader) = IngesterForTest::default()
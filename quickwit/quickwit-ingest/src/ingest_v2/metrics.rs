@@ -37,8 +37,11 @@ pub(crate) struct IngestResultMetrics {
     pub internal: IntCounter,
     pub no_shards_available: IntCounter,
     pub shard_rate_limited: IntCounter,
+    pub index_rate_limited: IntCounter,
+    pub index_wal_quota_exceeded: IntCounter,
     pub wal_full: IntCounter,
     pub timeout: IntCounter,
+    pub replication_failed: IntCounter,
     pub router_timeout: IntCounter,
     pub router_load_shedding: IntCounter,
     pub load_shedding: IntCounter,
@@ -64,8 +67,12 @@ impl Default for IngestResultMetrics {
             internal: ingest_result_total_vec.with_label_values(["internal"]),
             no_shards_available: ingest_result_total_vec.with_label_values(["no_shards_available"]),
             shard_rate_limited: ingest_result_total_vec.with_label_values(["shard_rate_limited"]),
+            index_rate_limited: ingest_result_total_vec.with_label_values(["index_rate_limited"]),
+            index_wal_quota_exceeded: ingest_result_total_vec
+                .with_label_values(["index_wal_quota_exceeded"]),
             wal_full: ingest_result_total_vec.with_label_values(["wal_full"]),
             timeout: ingest_result_total_vec.with_label_values(["timeout"]),
+            replication_failed: ingest_result_total_vec.with_label_values(["replication_failed"]),
             router_timeout: ingest_result_total_vec.with_label_values(["router_timeout"]),
             router_load_shedding: ingest_result_total_vec
                 .with_label_values(["router_load_shedding"]),
@@ -87,6 +94,11 @@ pub(super) struct IngestV2Metrics {
     pub wal_disk_used_bytes: IntGauge,
     pub wal_memory_used_bytes: IntGauge,
     pub ingest_results: IngestResultMetrics,
+    // Per-index metrics, only reported for indexes with ingest quotas.
+    pub quota_ingested_bytes_total: IntCounterVec<1>,
+    pub quota_ingested_docs_total: IntCounterVec<1>,
+    pub quota_wal_used_bytes: IntGaugeVec<1>,
+    pub quota_rejected_requests_total: IntCounterVec<2>,
 }
 
 impl Default for IngestV2Metrics {
@@ -151,6 +163,35 @@ impl Default for IngestV2Metrics {
                 "ingest",
                 &[],
             ),
+            quota_ingested_bytes_total: new_counter_vec(
+                "quota_ingested_bytes_total",
+                "Total number of bytes admitted by the router for indexes with ingest quotas.",
+                "ingest",
+                &[],
+                ["index"],
+            ),
+            quota_ingested_docs_total: new_counter_vec(
+                "quota_ingested_docs_total",
+                "Total number of docs admitted by the router for indexes with ingest quotas.",
+                "ingest",
+                &[],
+                ["index"],
+            ),
+            quota_wal_used_bytes: new_gauge_vec(
+                "quota_wal_used_bytes",
+                "WAL space used in bytes by indexes with ingest quotas, as seen by the router.",
+                "ingest",
+                &[],
+                ["index"],
+            ),
+            quota_rejected_requests_total: new_counter_vec(
+                "quota_rejected_requests_total",
+                "Total number of subrequests rejected because an index exceeded one of its ingest \
+                 quotas.",
+                "ingest",
+                &[],
+                ["index", "quota"],
+            ),
         }
     }
 }
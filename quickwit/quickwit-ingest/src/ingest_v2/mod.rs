@@ -28,6 +28,7 @@ mod models;
 mod mrecord;
 mod mrecordlog_utils;
 mod publish_tracker;
+mod quota;
 mod rate_meter;
 mod replication;
 mod router;
@@ -69,6 +70,12 @@ pub type LeaderId = NodeId;
 
 pub type FollowerId = NodeId;
 
+/// Returns the number of copies of a shard (leader included) that must have persisted a batch of
+/// documents before the batch is acknowledged, i.e. a strict majority of the replicas.
+pub fn replication_quorum(replication_factor: usize) -> usize {
+    replication_factor / 2 + 1
+}
+
 const IDLE_SHARD_TIMEOUT_ENV_KEY: &str = "QW_IDLE_SHARD_TIMEOUT_SECS";
 
 const DEFAULT_IDLE_SHARD_TIMEOUT: Duration = Duration::from_secs(15 * 60); // 15 minutes
@@ -282,6 +289,15 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_replication_quorum() {
+        assert_eq!(replication_quorum(1), 1);
+        assert_eq!(replication_quorum(2), 2);
+        assert_eq!(replication_quorum(3), 2);
+        assert_eq!(replication_quorum(4), 3);
+        assert_eq!(replication_quorum(5), 3);
+    }
+
     #[test]
     fn test_doc_batch_builder() {
         let doc_batch_builder = DocBatchV2Builder::default();
@@ -27,8 +27,8 @@ use tokio::sync::watch;
 
 #[derive(Debug, Clone)]
 pub(super) enum IngesterShardType {
-    /// A primary shard hosted on a leader and replicated on a follower.
-    Primary { follower_id: NodeId },
+    /// A primary shard hosted on a leader and replicated on one or several followers.
+    Primary { follower_ids: Vec<NodeId> },
     /// A replica shard hosted on a follower.
     Replica { leader_id: NodeId },
     /// A shard hosted on a single node when the replication factor is set to 1.
@@ -62,11 +62,14 @@ pub(super) struct IngesterShard {
     pub shard_status_rx: watch::Receiver<ShardStatus>,
     /// Instant at which the shard was last written to.
     pub last_write_instant: Instant,
+    /// Approximate number of bytes held by the shard's mrecordlog queue, i.e. the records
+    /// appended and not yet truncated.
+    pub num_wal_bytes: u64,
 }
 
 impl IngesterShard {
     pub fn new_primary(
-        follower_id: NodeId,
+        follower_ids: Vec<NodeId>,
         shard_state: ShardState,
         replication_position_inclusive: Position,
         truncation_position_inclusive: Position,
@@ -77,7 +80,7 @@ impl IngesterShard {
         let shard_status = (shard_state, replication_position_inclusive.clone());
         let (shard_status_tx, shard_status_rx) = watch::channel(shard_status);
         Self {
-            shard_type: IngesterShardType::Primary { follower_id },
+            shard_type: IngesterShardType::Primary { follower_ids },
             shard_state,
             replication_position_inclusive,
             truncation_position_inclusive,
@@ -87,6 +90,7 @@ impl IngesterShard {
             shard_status_tx,
             shard_status_rx,
             last_write_instant: now,
+            num_wal_bytes: 0,
         }
     }
 
@@ -112,6 +116,7 @@ impl IngesterShard {
             shard_status_tx,
             shard_status_rx,
             last_write_instant: now,
+            num_wal_bytes: 0,
         }
     }
 
@@ -136,14 +141,15 @@ impl IngesterShard {
             shard_status_tx,
             shard_status_rx,
             last_write_instant: now,
+            num_wal_bytes: 0,
         }
     }
 
-    pub fn follower_id_opt(&self) -> Option<&NodeId> {
+    pub fn follower_ids(&self) -> &[NodeId] {
         match &self.shard_type {
-            IngesterShardType::Primary { follower_id, .. } => Some(follower_id),
-            IngesterShardType::Replica { .. } => None,
-            IngesterShardType::Solo => None,
+            IngesterShardType::Primary { follower_ids, .. } => follower_ids,
+            IngesterShardType::Replica { .. } => &[],
+            IngesterShardType::Solo => &[],
         }
     }
 
@@ -255,7 +261,7 @@ mod tests {
         let doc_mapper = build_doc_mapper(&doc_mapping, &search_settings).unwrap();
 
         let primary_shard = IngesterShard::new_primary(
-            "test-follower".into(),
+            vec!["test-follower".into()],
             ShardState::Closed,
             Position::offset(42u64),
             Position::Beginning,
@@ -265,7 +271,7 @@ mod tests {
         );
         assert!(matches!(
             &primary_shard.shard_type,
-            IngesterShardType::Primary { follower_id, .. } if *follower_id == "test-follower"
+            IngesterShardType::Primary { follower_ids, .. } if *follower_ids == ["test-follower"]
         ));
         assert!(!primary_shard.is_replica());
         assert_eq!(primary_shard.shard_state, ShardState::Closed);
@@ -19,7 +19,7 @@
 
 use std::io;
 use std::iter::once;
-use std::ops::RangeInclusive;
+use std::ops::{RangeBounds, RangeInclusive};
 
 use bytesize::ByteSize;
 #[cfg(feature = "failpoints")]
@@ -175,6 +175,22 @@ pub(super) fn queue_position_range(
     Some(first_position..=last_position)
 }
 
+/// Returns the total size of the records stored in the queue within `range`. Returns 0 if the queue
+/// does not exist.
+pub(super) fn queue_num_bytes<R>(
+    mrecordlog: &MultiRecordLogAsync,
+    queue_id: &QueueId,
+    range: R,
+) -> u64
+where
+    R: RangeBounds<u64> + 'static,
+{
+    let Ok(records) = mrecordlog.range(queue_id, range) else {
+        return 0;
+    };
+    records.map(|record| record.payload.len() as u64).sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -290,4 +306,30 @@ mod tests {
         let position_range = queue_position_range(&mrecordlog, &"test-queue".to_string()).unwrap();
         assert_eq!(position_range, 1..=1);
     }
+
+    #[tokio::test]
+    async fn test_queue_num_bytes() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut mrecordlog = MultiRecordLogAsync::open(tempdir.path()).await.unwrap();
+
+        let queue_id = "test-queue".to_string();
+        assert_eq!(queue_num_bytes(&mrecordlog, &queue_id, ..), 0);
+
+        mrecordlog.create_queue(&queue_id).await.unwrap();
+        assert_eq!(queue_num_bytes(&mrecordlog, &queue_id, ..), 0);
+
+        mrecordlog
+            .append_records(&queue_id, None, std::iter::once(&b"test-doc-foo"[..]))
+            .await
+            .unwrap();
+        mrecordlog
+            .append_records(&queue_id, None, std::iter::once(&b"test-doc-quux"[..]))
+            .await
+            .unwrap();
+        assert_eq!(queue_num_bytes(&mrecordlog, &queue_id, ..), 25);
+        assert_eq!(queue_num_bytes(&mrecordlog, &queue_id, ..=0), 12);
+
+        mrecordlog.truncate(&queue_id, 0).await.unwrap();
+        assert_eq!(queue_num_bytes(&mrecordlog, &queue_id, ..), 13);
+    }
 }
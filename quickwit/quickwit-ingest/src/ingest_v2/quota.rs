@@ -0,0 +1,474 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use quickwit_common::rate_limiter::{RateLimiter, RateLimiterSettings};
+use quickwit_common::tower::ConstantRate;
+use quickwit_proto::ingest::{IngestQuota, RateLimitingCause};
+use quickwit_proto::types::{IndexId, NodeId, ShardId, SourceUid};
+
+use super::broadcast::ShardInfos;
+use super::metrics::INGEST_V2_METRICS;
+
+/// Duration clients are asked to wait before retrying when an index exceeds its WAL quota. The WAL
+/// usage of an index only goes down as indexers publish splits and shards get truncated, so
+/// retrying sooner is pointless.
+const WAL_QUOTA_EXCEEDED_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(super) struct QuotaExceeded {
+    pub rate_limiting_cause: RateLimitingCause,
+    pub retry_after: Duration,
+}
+
+/// Holds the ingest quotas of the indexes, as reported by the control plane, and the WAL usage of
+/// the indexes, as broadcast by the ingesters.
+///
+/// Rate quotas are enforced by each router without coordination, so each router only enforces an
+/// even share of them, based on the number of routers in the cluster. The WAL quota, on the other
+/// hand, is computed from the cluster-wide WAL usage of the index.
+#[derive(Debug)]
+pub(super) struct IngestQuotaTable {
+    quotas: HashMap<IndexId, IndexQuotaState>,
+    wal_usages: HashMap<IndexId, IndexWalUsage>,
+    num_routers: usize,
+}
+
+impl Default for IngestQuotaTable {
+    fn default() -> Self {
+        Self {
+            quotas: HashMap::default(),
+            wal_usages: HashMap::default(),
+            num_routers: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct IndexQuotaState {
+    quota: IngestQuota,
+    // Shares of the rate quotas enforced by this router.
+    max_bytes_per_sec_share_opt: Option<u64>,
+    max_docs_per_sec_share_opt: Option<u64>,
+    bytes_rate_limiter_opt: Option<RateLimiter>,
+    docs_rate_limiter_opt: Option<RateLimiter>,
+}
+
+impl IndexQuotaState {
+    fn new(quota: IngestQuota, num_routers: usize) -> Self {
+        let max_bytes_per_sec_share_opt = quota
+            .max_bytes_per_sec
+            .map(|max_bytes_per_sec| router_share(max_bytes_per_sec, num_routers));
+        let max_docs_per_sec_share_opt = quota
+            .max_docs_per_sec
+            .map(|max_docs_per_sec| router_share(max_docs_per_sec, num_routers));
+        let bytes_rate_limiter_opt = max_bytes_per_sec_share_opt.map(per_sec_rate_limiter);
+        let docs_rate_limiter_opt = max_docs_per_sec_share_opt.map(per_sec_rate_limiter);
+        Self {
+            quota,
+            max_bytes_per_sec_share_opt,
+            max_docs_per_sec_share_opt,
+            bytes_rate_limiter_opt,
+            docs_rate_limiter_opt,
+        }
+    }
+}
+
+/// Returns the share of a cluster-wide rate quota enforced by each of the `num_routers` routers,
+/// rounded up.
+fn router_share(max_per_sec: u64, num_routers: usize) -> u64 {
+    max_per_sec.div_ceil(num_routers.max(1) as u64)
+}
+
+/// Builds a rate limiter allowing `max_per_sec` permits per second, with bursts of at most one
+/// second worth of permits.
+fn per_sec_rate_limiter(max_per_sec: u64) -> RateLimiter {
+    let settings = RateLimiterSettings {
+        burst_limit: max_per_sec,
+        rate_limit: ConstantRate::new(max_per_sec, Duration::from_secs(1)),
+        refill_period: Duration::from_secs(1),
+    };
+    RateLimiter::from_settings(settings)
+}
+
+/// Acquires `num_permits` permits from the rate limiter. Requests larger than the burst limit
+/// would never succeed, so they drain the whole bucket instead.
+fn acquire_clamped(
+    rate_limiter: &mut RateLimiter,
+    max_per_sec: u64,
+    num_permits: u64,
+) -> Result<u64, Duration> {
+    let num_permits = num_permits.min(max_per_sec);
+    rate_limiter.acquire_with_duration(num_permits)?;
+    Ok(num_permits)
+}
+
+#[derive(Debug, Default)]
+struct IndexWalUsage {
+    // Number of bytes held in the WAL by each shard of the index, along with the shard's leader.
+    per_shard_num_bytes: HashMap<(SourceUid, ShardId), (NodeId, u64)>,
+    total_num_bytes: u64,
+}
+
+impl IndexWalUsage {
+    fn recompute_total(&mut self) {
+        self.total_num_bytes = self
+            .per_shard_num_bytes
+            .values()
+            .map(|(_, num_bytes)| *num_bytes)
+            .sum();
+    }
+}
+
+impl IngestQuotaTable {
+    /// Sets or removes the quota of an index. Rate limiters are preserved if the quota did not
+    /// change.
+    pub fn update_quota(&mut self, index_id: &str, quota_opt: Option<IngestQuota>) {
+        let Some(quota) = quota_opt else {
+            self.quotas.remove(index_id);
+            return;
+        };
+        if let Some(quota_state) = self.quotas.get(index_id) {
+            if quota_state.quota == quota {
+                return;
+            }
+        }
+        self.quotas.insert(
+            index_id.to_string(),
+            IndexQuotaState::new(quota, self.num_routers),
+        );
+    }
+
+    /// Sets the number of routers in the cluster, among which the rate quotas are split. Rate
+    /// limiters are reset if the number changed.
+    pub fn set_num_routers(&mut self, num_routers: usize) {
+        let num_routers = num_routers.max(1);
+
+        if self.num_routers == num_routers {
+            return;
+        }
+        self.num_routers = num_routers;
+
+        for quota_state in self.quotas.values_mut() {
+            *quota_state = IndexQuotaState::new(quota_state.quota.clone(), num_routers);
+        }
+    }
+
+    /// Replaces the WAL usage of the shards of a source hosted by `leader_id`.
+    pub fn update_wal_usage(
+        &mut self,
+        leader_id: &NodeId,
+        source_uid: &SourceUid,
+        shard_infos: &ShardInfos,
+    ) {
+        let index_id = &source_uid.index_uid.index_id;
+        let wal_usage = self.wal_usages.entry(index_id.clone()).or_default();
+
+        wal_usage
+            .per_shard_num_bytes
+            .retain(|(shard_source_uid, _), (shard_leader_id, _)| {
+                shard_source_uid != source_uid || shard_leader_id != leader_id
+            });
+        for shard_info in shard_infos {
+            wal_usage.per_shard_num_bytes.insert(
+                (source_uid.clone(), shard_info.shard_id.clone()),
+                (leader_id.clone(), shard_info.num_wal_bytes),
+            );
+        }
+        wal_usage.recompute_total();
+        self.report_wal_usage(index_id);
+    }
+
+    /// Forgets the WAL usage of shards that have been deleted.
+    pub fn remove_shards(&mut self, source_uid: &SourceUid, shard_ids: &[ShardId]) {
+        if shard_ids.is_empty() {
+            return;
+        }
+        let index_id = &source_uid.index_uid.index_id;
+        let Some(wal_usage) = self.wal_usages.get_mut(index_id) else {
+            return;
+        };
+        for shard_id in shard_ids {
+            wal_usage
+                .per_shard_num_bytes
+                .remove(&(source_uid.clone(), shard_id.clone()));
+        }
+        wal_usage.recompute_total();
+
+        if wal_usage.per_shard_num_bytes.is_empty() {
+            self.wal_usages.remove(index_id);
+        }
+        self.report_wal_usage(index_id);
+    }
+
+    /// Checks whether a subrequest of `num_bytes` bytes and `num_docs` docs for the index
+    /// `index_id` fits within the quotas of the index, consuming the corresponding permits if
+    /// it does.
+    pub fn check(
+        &mut self,
+        index_id: &str,
+        num_bytes: u64,
+        num_docs: u64,
+    ) -> Result<(), QuotaExceeded> {
+        let Some(quota_state) = self.quotas.get_mut(index_id) else {
+            return Ok(());
+        };
+        if let Some(max_wal_bytes) = quota_state.quota.max_wal_bytes {
+            let num_wal_bytes = self
+                .wal_usages
+                .get(index_id)
+                .map(|wal_usage| wal_usage.total_num_bytes)
+                .unwrap_or(0);
+
+            if num_wal_bytes >= max_wal_bytes {
+                return Err(reject(
+                    index_id,
+                    "wal_bytes",
+                    RateLimitingCause::IndexWalQuotaExceeded,
+                    WAL_QUOTA_EXCEEDED_RETRY_AFTER,
+                ));
+            }
+        }
+        let mut num_acquired_docs = 0;
+
+        if let (Some(docs_rate_limiter), Some(max_docs_per_sec)) = (
+            &mut quota_state.docs_rate_limiter_opt,
+            quota_state.max_docs_per_sec_share_opt,
+        ) {
+            match acquire_clamped(docs_rate_limiter, max_docs_per_sec, num_docs) {
+                Ok(num_permits) => num_acquired_docs = num_permits,
+                Err(retry_after) => {
+                    return Err(reject(
+                        index_id,
+                        "docs_per_sec",
+                        RateLimitingCause::IndexRateLimiting,
+                        retry_after,
+                    ));
+                }
+            }
+        }
+        if let (Some(bytes_rate_limiter), Some(max_bytes_per_sec)) = (
+            &mut quota_state.bytes_rate_limiter_opt,
+            quota_state.max_bytes_per_sec_share_opt,
+        ) {
+            if let Err(retry_after) =
+                acquire_clamped(bytes_rate_limiter, max_bytes_per_sec, num_bytes)
+            {
+                if let Some(docs_rate_limiter) = &mut quota_state.docs_rate_limiter_opt {
+                    docs_rate_limiter.release(num_acquired_docs);
+                }
+                return Err(reject(
+                    index_id,
+                    "bytes_per_sec",
+                    RateLimitingCause::IndexRateLimiting,
+                    retry_after,
+                ));
+            }
+        }
+        INGEST_V2_METRICS
+            .quota_ingested_bytes_total
+            .with_label_values([index_id])
+            .inc_by(num_bytes);
+        INGEST_V2_METRICS
+            .quota_ingested_docs_total
+            .with_label_values([index_id])
+            .inc_by(num_docs);
+        Ok(())
+    }
+
+    fn report_wal_usage(&self, index_id: &str) {
+        if !self.quotas.contains_key(index_id) {
+            return;
+        }
+        let num_wal_bytes = self
+            .wal_usages
+            .get(index_id)
+            .map(|wal_usage| wal_usage.total_num_bytes)
+            .unwrap_or(0);
+        INGEST_V2_METRICS
+            .quota_wal_used_bytes
+            .with_label_values([index_id])
+            .set(num_wal_bytes as i64);
+    }
+}
+
+fn reject(
+    index_id: &str,
+    quota: &str,
+    rate_limiting_cause: RateLimitingCause,
+    retry_after: Duration,
+) -> QuotaExceeded {
+    INGEST_V2_METRICS
+        .quota_rejected_requests_total
+        .with_label_values([index_id, quota])
+        .inc();
+    QuotaExceeded {
+        rate_limiting_cause,
+        retry_after,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::ingest::ShardState;
+    use quickwit_proto::types::IndexUid;
+
+    use super::*;
+    use crate::ingest_v2::broadcast::ShardInfo;
+    use crate::RateMibPerSec;
+
+    fn shard_info(shard_id: u64, num_wal_bytes: u64) -> ShardInfo {
+        ShardInfo {
+            shard_id: ShardId::from(shard_id),
+            shard_state: ShardState::Open,
+            short_term_ingestion_rate: RateMibPerSec(0),
+            long_term_ingestion_rate: RateMibPerSec(0),
+            num_wal_bytes,
+        }
+    }
+
+    #[test]
+    fn test_ingest_quota_table_no_quota() {
+        let mut quota_table = IngestQuotaTable::default();
+        quota_table.check("test-index", u64::MAX, u64::MAX).unwrap();
+    }
+
+    #[test]
+    fn test_ingest_quota_table_rate_quotas() {
+        let mut quota_table = IngestQuotaTable::default();
+        let quota = IngestQuota {
+            max_bytes_per_sec: Some(1_000),
+            max_docs_per_sec: Some(10),
+            max_wal_bytes: None,
+        };
+        quota_table.update_quota("test-index", Some(quota.clone()));
+
+        quota_table.check("test-index", 500, 5).unwrap();
+        quota_table.check("test-other-index", 10_000, 100).unwrap();
+
+        let quota_exceeded = quota_table.check("test-index", 100, 6).unwrap_err();
+        assert_eq!(
+            quota_exceeded.rate_limiting_cause,
+            RateLimitingCause::IndexRateLimiting
+        );
+        assert!(quota_exceeded.retry_after > Duration::ZERO);
+        assert!(quota_exceeded.retry_after <= Duration::from_secs(1));
+
+        let quota_exceeded = quota_table.check("test-index", 600, 1).unwrap_err();
+        assert_eq!(
+            quota_exceeded.rate_limiting_cause,
+            RateLimitingCause::IndexRateLimiting
+        );
+        // The docs permits are released when the bytes quota is exceeded.
+        quota_table.check("test-index", 500, 5).unwrap();
+
+        // Updating the table with the same quota preserves the state of the rate limiters.
+        quota_table.update_quota("test-index", Some(quota));
+        quota_table.check("test-index", 1, 1).unwrap_err();
+
+        quota_table.update_quota("test-index", None);
+        quota_table.check("test-index", 10_000, 100).unwrap();
+    }
+
+    #[test]
+    fn test_ingest_quota_table_num_routers() {
+        let mut quota_table = IngestQuotaTable::default();
+        let quota = IngestQuota {
+            max_bytes_per_sec: Some(1_000),
+            max_docs_per_sec: Some(10),
+            max_wal_bytes: None,
+        };
+        quota_table.update_quota("test-index", Some(quota));
+        quota_table.set_num_routers(4);
+
+        // Each of the 4 routers enforces a quarter of the quotas, rounded up.
+        quota_table.check("test-index", 250, 3).unwrap();
+        quota_table.check("test-index", 1, 0).unwrap_err();
+
+        quota_table.set_num_routers(0);
+        quota_table.check("test-index", 1_000, 10).unwrap();
+        quota_table.check("test-index", 1, 0).unwrap_err();
+
+        assert_eq!(router_share(1_000, 3), 334);
+        assert_eq!(router_share(10, 1), 10);
+    }
+
+    #[test]
+    fn test_ingest_quota_table_oversized_request() {
+        let mut quota_table = IngestQuotaTable::default();
+        let quota = IngestQuota {
+            max_bytes_per_sec: Some(1_000),
+            max_docs_per_sec: None,
+            max_wal_bytes: None,
+        };
+        quota_table.update_quota("test-index", Some(quota));
+
+        // A request larger than the per-second quota drains the bucket instead of being rejected
+        // forever.
+        quota_table.check("test-index", 5_000, 1).unwrap();
+        quota_table.check("test-index", 1, 1).unwrap_err();
+    }
+
+    #[test]
+    fn test_ingest_quota_table_wal_quota() {
+        let mut quota_table = IngestQuotaTable::default();
+        let quota = IngestQuota {
+            max_bytes_per_sec: None,
+            max_docs_per_sec: None,
+            max_wal_bytes: Some(1_000),
+        };
+        quota_table.update_quota("test-index", Some(quota));
+
+        let source_uid = SourceUid {
+            index_uid: IndexUid::for_test("test-index", 0),
+            source_id: "test-source".to_string(),
+        };
+        let leader_id_0 = NodeId::from("test-ingester-0");
+        let leader_id_1 = NodeId::from("test-ingester-1");
+
+        let shard_infos = ShardInfos::from_iter([shard_info(1, 400), shard_info(2, 300)]);
+        quota_table.update_wal_usage(&leader_id_0, &source_uid, &shard_infos);
+        quota_table.check("test-index", 100, 1).unwrap();
+
+        let shard_infos = ShardInfos::from_iter([shard_info(3, 300)]);
+        quota_table.update_wal_usage(&leader_id_1, &source_uid, &shard_infos);
+
+        let quota_exceeded = quota_table.check("test-index", 100, 1).unwrap_err();
+        assert_eq!(
+            quota_exceeded,
+            QuotaExceeded {
+                rate_limiting_cause: RateLimitingCause::IndexWalQuotaExceeded,
+                retry_after: WAL_QUOTA_EXCEEDED_RETRY_AFTER,
+            }
+        );
+        // Shard 2 got truncated.
+        let shard_infos = ShardInfos::from_iter([shard_info(1, 400), shard_info(2, 0)]);
+        quota_table.update_wal_usage(&leader_id_0, &source_uid, &shard_infos);
+        quota_table.check("test-index", 100, 1).unwrap();
+
+        let shard_infos = ShardInfos::from_iter([shard_info(1, 400), shard_info(2, 400)]);
+        quota_table.update_wal_usage(&leader_id_0, &source_uid, &shard_infos);
+        quota_table.check("test-index", 100, 1).unwrap_err();
+
+        quota_table.remove_shards(&source_uid, &[ShardId::from(3)]);
+        quota_table.check("test-index", 100, 1).unwrap();
+    }
+}
@@ -666,11 +666,12 @@ impl ReplicationTask {
                     continue;
                 }
             };
-            state_guard
+            let shard = state_guard
                 .shards
                 .get_mut(&queue_id)
-                .expect("replica shard should be initialized")
-                .set_replication_position_inclusive(current_position_inclusive.clone(), now);
+                .expect("replica shard should be initialized");
+            shard.set_replication_position_inclusive(current_position_inclusive.clone(), now);
+            shard.num_wal_bytes += requested_capacity.as_u64();
 
             INGEST_METRICS
                 .replicated_num_bytes_total
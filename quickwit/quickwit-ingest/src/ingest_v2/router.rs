@@ -37,7 +37,7 @@ use quickwit_proto::ingest::ingester::{
     IngesterService, PersistFailureReason, PersistRequest, PersistResponse, PersistSubrequest,
 };
 use quickwit_proto::ingest::router::{
-    IngestFailureReason, IngestRequestV2, IngestResponseV2, IngestRouterService,
+    IngestFailure, IngestFailureReason, IngestRequestV2, IngestResponseV2, IngestRouterService,
 };
 use quickwit_proto::ingest::{
     CommitTypeV2, IngestV2Error, IngestV2Result, RateLimitingCause, ShardState,
@@ -54,6 +54,7 @@ use super::debouncing::{
 };
 use super::ingester::PERSIST_REQUEST_TIMEOUT;
 use super::metrics::IngestResultMetrics;
+use super::quota::IngestQuotaTable;
 use super::routing_table::RoutingTable;
 use super::workbench::IngestWorkbench;
 use super::{pending_subrequests, IngesterPool};
@@ -90,6 +91,10 @@ fn ingest_request_timeout() -> Duration {
 
 const MAX_PERSIST_ATTEMPTS: usize = 5;
 
+/// Interval at which the router refreshes the routing table entries from the control plane, even
+/// if they have open shards, in order to pick up changes to the ingest quotas of the indexes.
+const ROUTING_TABLE_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
 type PersistResult = (PersistRequestSummary, IngestV2Result<PersistResponse>);
 
 #[derive(Clone)]
@@ -109,6 +114,8 @@ struct RouterState {
     debouncer: GetOrCreateOpenShardsRequestDebouncer,
     // Holds the routing table mapping index and source IDs to shards.
     routing_table: RoutingTable,
+    // Holds the ingest quotas and the WAL usage of the indexes.
+    quota_table: IngestQuotaTable,
 }
 
 impl fmt::Debug for IngestRouter {
@@ -134,6 +141,7 @@ impl IngestRouter {
                 self_node_id: self_node_id.clone(),
                 table: HashMap::default(),
             },
+            quota_table: IngestQuotaTable::default(),
         }));
         let ingest_semaphore_permits = get_ingest_router_buffer_size().as_u64() as usize;
         let ingest_semaphore = Arc::new(Semaphore::new(ingest_semaphore_permits));
@@ -149,6 +157,16 @@ impl IngestRouter {
         }
     }
 
+    /// Sets the number of ingest routers in the cluster, among which the rate quotas of the indexes
+    /// are split.
+    pub async fn set_num_routers(&self, num_routers: usize) {
+        self.state
+            .lock()
+            .await
+            .quota_table
+            .set_num_routers(num_routers);
+    }
+
     pub fn subscribe(&self) {
         let weak_router_state = WeakRouterState(Arc::downgrade(&self.state));
         self.event_broker
@@ -175,14 +193,21 @@ impl IngestRouter {
         let mut state_guard = self.state.lock().await;
 
         for subrequest in pending_subrequests(&workbench.subworkbenches) {
+            let refresh_due = state_guard.routing_table.poll_refresh(
+                &subrequest.index_id,
+                &subrequest.source_id,
+                ROUTING_TABLE_REFRESH_INTERVAL,
+            );
             if !state_guard.routing_table.has_open_shards(
                 &subrequest.index_id,
                 &subrequest.source_id,
                 ingester_pool,
                 &mut debounced_request.closed_shards,
                 unavailable_leaders,
-            ) {
-                // No shard available! Let's attempt to create one.
+            ) || refresh_due
+            {
+                // No shard available or the entry is stale! Let's attempt to (re)fetch or create
+                // some.
                 let acquire_result = state_guard
                     .debouncer
                     .acquire(&subrequest.index_id, &subrequest.source_id);
@@ -263,6 +288,10 @@ impl IngestRouter {
         let mut state_guard = self.state.lock().await;
 
         for success in response.successes {
+            state_guard
+                .quota_table
+                .update_quota(&success.index_uid().index_id, success.ingest_quota);
+
             state_guard.routing_table.replace_shards(
                 success.index_uid().clone(),
                 success.source_id,
@@ -293,7 +322,9 @@ impl IngestRouter {
                     for persist_failure in persist_response.failures {
                         workbench.record_persist_failure(&persist_failure);
                         match persist_failure.reason() {
-                            PersistFailureReason::ShardClosed => {
+                            // The leader closes the shards it failed to replicate.
+                            PersistFailureReason::ShardClosed
+                            | PersistFailureReason::ReplicationFailed => {
                                 let shard_id = persist_failure.shard_id().clone();
                                 let index_uid: IndexUid = persist_failure.index_uid().clone();
                                 let source_id: SourceId = persist_failure.source_id;
@@ -498,6 +529,49 @@ impl IngestRouter {
         })
     }
 
+    /// Rejects the subrequests targeting indexes that exceeded one of their ingest quotas and
+    /// returns the corresponding failures.
+    async fn enforce_ingest_quotas(
+        &self,
+        ingest_request: &mut IngestRequestV2,
+    ) -> Vec<IngestFailure> {
+        let mut quota_failures = Vec::new();
+        let mut state_guard = self.state.lock().await;
+
+        ingest_request.subrequests.retain(|subrequest| {
+            let num_bytes = subrequest.num_bytes() as u64;
+            let num_docs = subrequest
+                .doc_batch
+                .as_ref()
+                .map(|doc_batch| doc_batch.num_docs())
+                .unwrap_or(0) as u64;
+
+            let Err(quota_exceeded) =
+                state_guard
+                    .quota_table
+                    .check(&subrequest.index_id, num_bytes, num_docs)
+            else {
+                return true;
+            };
+            let reason = match quota_exceeded.rate_limiting_cause {
+                RateLimitingCause::IndexWalQuotaExceeded => {
+                    IngestFailureReason::IndexWalQuotaExceeded
+                }
+                _ => IngestFailureReason::IndexRateLimited,
+            };
+            let quota_failure = IngestFailure {
+                subrequest_id: subrequest.subrequest_id,
+                index_id: subrequest.index_id.clone(),
+                source_id: subrequest.source_id.clone(),
+                reason: reason as i32,
+                retry_after_ms: Some(quota_exceeded.retry_after.as_millis() as u64),
+            };
+            quota_failures.push(quota_failure);
+            false
+        });
+        quota_failures
+    }
+
     pub async fn debug_info(&self) -> JsonValue {
         let state_guard = self.state.lock().await;
         let routing_table_json = state_guard.routing_table.debug_info();
@@ -542,6 +616,15 @@ fn update_ingest_metrics(ingest_result: &IngestV2Result<IngestResponseV2>, num_s
                         ingest_results_metrics.router_load_shedding.inc()
                     }
                     IngestFailureReason::LoadShedding => ingest_results_metrics.load_shedding.inc(),
+                    IngestFailureReason::IndexRateLimited => {
+                        ingest_results_metrics.index_rate_limited.inc()
+                    }
+                    IngestFailureReason::IndexWalQuotaExceeded => {
+                        ingest_results_metrics.index_wal_quota_exceeded.inc()
+                    }
+                    IngestFailureReason::ReplicationFailed => {
+                        ingest_results_metrics.replication_failed.inc()
+                    }
                 }
             }
         }
@@ -568,6 +651,16 @@ fn update_ingest_metrics(ingest_result: &IngestV2Result<IngestResponseV2>, num_s
                         .shard_rate_limited
                         .inc_by(num_subrequests);
                 }
+                RateLimitingCause::IndexRateLimiting => {
+                    ingest_results_metrics
+                        .index_rate_limited
+                        .inc_by(num_subrequests);
+                }
+                RateLimitingCause::IndexWalQuotaExceeded => {
+                    ingest_results_metrics
+                        .index_wal_quota_exceeded
+                        .inc_by(num_subrequests);
+                }
                 RateLimitingCause::Unknown => {
                     ingest_results_metrics.unspecified.inc_by(num_subrequests);
                 }
@@ -594,7 +687,10 @@ fn update_ingest_metrics(ingest_result: &IngestV2Result<IngestResponseV2>, num_s
 
 #[async_trait]
 impl IngestRouterService for IngestRouter {
-    async fn ingest(&self, ingest_request: IngestRequestV2) -> IngestV2Result<IngestResponseV2> {
+    async fn ingest(
+        &self,
+        mut ingest_request: IngestRequestV2,
+    ) -> IngestV2Result<IngestResponseV2> {
         let request_size_bytes = ingest_request.num_bytes();
 
         let mut gauge_guard = GaugeGuard::from_gauge(&MEMORY_METRICS.in_flight.ingest_router);
@@ -607,6 +703,16 @@ impl IngestRouterService for IngestRouter {
             .try_acquire_many_owned(request_size_bytes as u32)
             .map_err(|_| IngestV2Error::TooManyRequests(RateLimitingCause::RouterLoadShedding))?;
 
+        let quota_failures = self.enforce_ingest_quotas(&mut ingest_request).await;
+
+        if !quota_failures.is_empty() && ingest_request.subrequests.is_empty() {
+            let ingest_res = Ok(IngestResponseV2 {
+                successes: Vec::new(),
+                failures: quota_failures,
+            });
+            update_ingest_metrics(&ingest_res, num_subrequests);
+            return ingest_res;
+        }
         let ingest_res = if ingest_request.commit_type() == CommitTypeV2::Auto {
             self.ingest_timeout(ingest_request, ingest_request_timeout())
                 .await
@@ -615,7 +721,10 @@ impl IngestRouterService for IngestRouter {
                 .retry_batch_persist(ingest_request, MAX_PERSIST_ATTEMPTS)
                 .await)
         };
-
+        let ingest_res = ingest_res.map(|mut ingest_response| {
+            ingest_response.failures.extend(quota_failures);
+            ingest_response
+        });
         update_ingest_metrics(&ingest_res, num_subrequests);
 
         ingest_res
@@ -632,16 +741,15 @@ impl EventSubscriber<LocalShardsUpdate> for WeakRouterState {
             return;
         };
         let leader_id = local_shards_update.leader_id;
-        let index_uid = local_shards_update.source_uid.index_uid;
-        let source_id = local_shards_update.source_uid.source_id;
+        let source_uid = local_shards_update.source_uid;
 
         let mut open_shard_ids: Vec<ShardId> = Vec::new();
         let mut closed_shard_ids: Vec<ShardId> = Vec::new();
 
-        for shard_info in local_shards_update.shard_infos {
+        for shard_info in &local_shards_update.shard_infos {
             match shard_info.shard_state {
-                ShardState::Open => open_shard_ids.push(shard_info.shard_id),
-                ShardState::Closed => closed_shard_ids.push(shard_info.shard_id),
+                ShardState::Open => open_shard_ids.push(shard_info.shard_id.clone()),
+                ShardState::Closed => closed_shard_ids.push(shard_info.shard_id.clone()),
                 ShardState::Unavailable | ShardState::Unspecified => {
                     // Ingesters never broadcast the `Unavailable`` state because, from their point
                     // of view, they are never unavailable.
@@ -650,6 +758,14 @@ impl EventSubscriber<LocalShardsUpdate> for WeakRouterState {
         }
         let mut state_guard = state.lock().await;
 
+        state_guard.quota_table.update_wal_usage(
+            &leader_id,
+            &source_uid,
+            &local_shards_update.shard_infos,
+        );
+        let index_uid = source_uid.index_uid;
+        let source_id = source_uid.source_id;
+
         state_guard
             .routing_table
             .close_shards(&index_uid, &source_id, &closed_shard_ids);
@@ -678,6 +794,10 @@ impl EventSubscriber<ShardPositionsUpdate> for WeakRouterState {
         }
         let mut state_guard = state.lock().await;
 
+        state_guard
+            .quota_table
+            .remove_shards(&shard_positions_update.source_uid, &deleted_shard_ids);
+
         let index_uid = shard_positions_update.source_uid.index_uid;
         let source_id = shard_positions_update.source_uid.source_id;
 
@@ -706,7 +826,8 @@ mod tests {
     };
     use quickwit_proto::ingest::router::IngestSubrequest;
     use quickwit_proto::ingest::{
-        CommitTypeV2, DocBatchV2, ParseFailure, ParseFailureReason, Shard, ShardIds, ShardState,
+        CommitTypeV2, DocBatchV2, IngestQuota, ParseFailure, ParseFailureReason, Shard, ShardIds,
+        ShardState,
     };
     use quickwit_proto::types::{DocUid, Position, SourceUid};
     use tokio::task::yield_now;
@@ -918,6 +1039,7 @@ mod tests {
                                 shard_state: ShardState::Open as i32,
                                 ..Default::default()
                             }],
+                            ingest_quota: None,
                         },
                         GetOrCreateOpenShardsSuccess {
                             subrequest_id: 1,
@@ -939,6 +1061,7 @@ mod tests {
                                     ..Default::default()
                                 },
                             ],
+                            ingest_quota: None,
                         },
                     ],
                     failures: vec![
@@ -1127,6 +1250,7 @@ mod tests {
                             leader_id: "test-ingester".into(),
                             ..Default::default()
                         }],
+                        ingest_quota: None,
                     }],
                     ..Default::default()
                 };
@@ -1296,6 +1420,13 @@ mod tests {
                     leader_id: "test-ingester-0".to_string(),
                     ..Default::default()
                 },
+                Shard {
+                    index_uid: Some(index_uid.clone()),
+                    shard_id: Some(ShardId::from(3)),
+                    shard_state: ShardState::Open as i32,
+                    leader_id: "test-ingester-0".to_string(),
+                    ..Default::default()
+                },
             ],
         );
         drop(state_guard);
@@ -1326,6 +1457,13 @@ mod tests {
                         shard_id: Some(ShardId::from(2)),
                         reason: PersistFailureReason::ShardClosed as i32,
                     },
+                    PersistFailure {
+                        subrequest_id: 2,
+                        index_uid: Some(index_uid.clone()),
+                        source_id: "test-source".to_string(),
+                        shard_id: Some(ShardId::from(3)),
+                        reason: PersistFailureReason::ReplicationFailed as i32,
+                    },
                 ],
             });
             (persist_summary, persist_result)
@@ -1339,11 +1477,13 @@ mod tests {
             .routing_table
             .find_entry("test-index-0", "test-source")
             .unwrap();
-        assert_eq!(routing_table_entry.len(), 1);
+        assert_eq!(routing_table_entry.len(), 2);
 
-        let shard = routing_table_entry.all_shards()[0];
-        assert_eq!(shard.shard_id, ShardId::from(2));
-        assert_eq!(shard.shard_state, ShardState::Closed);
+        let shards = routing_table_entry.all_shards();
+        assert_eq!(shards[0].shard_id, ShardId::from(2));
+        assert_eq!(shards[0].shard_state, ShardState::Closed);
+        assert_eq!(shards[1].shard_id, ShardId::from(3));
+        assert_eq!(shards[1].shard_state, ShardState::Closed);
     }
 
     #[tokio::test]
@@ -1668,6 +1808,138 @@ mod tests {
         assert_eq!(response.failures.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_router_ingest_enforces_quotas() {
+        let self_node_id = "test-router".into();
+        let control_plane = ControlPlaneServiceClient::from_mock(MockControlPlaneService::new());
+        let ingester_pool = IngesterPool::default();
+        let replication_factor = 1;
+        let router = IngestRouter::new(
+            self_node_id,
+            control_plane,
+            ingester_pool.clone(),
+            replication_factor,
+            EventBroker::default(),
+        );
+        let index_uid_0: IndexUid = IndexUid::for_test("test-index-0", 0);
+        let index_uid_1: IndexUid = IndexUid::for_test("test-index-1", 0);
+
+        let mut state_guard = router.state.lock().await;
+        state_guard.routing_table.replace_shards(
+            index_uid_1.clone(),
+            "test-source",
+            vec![Shard {
+                index_uid: Some(index_uid_1.clone()),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(1)),
+                shard_state: ShardState::Open as i32,
+                leader_id: "test-ingester-0".to_string(),
+                ..Default::default()
+            }],
+        );
+        let ingest_quota = IngestQuota {
+            max_wal_bytes: Some(1_000),
+            ..Default::default()
+        };
+        state_guard
+            .quota_table
+            .update_quota("test-index-0", Some(ingest_quota));
+
+        let source_uid = SourceUid {
+            index_uid: index_uid_0.clone(),
+            source_id: "test-source".to_string(),
+        };
+        let shard_infos = BTreeSet::from_iter([ShardInfo {
+            shard_id: ShardId::from(1),
+            shard_state: ShardState::Open,
+            short_term_ingestion_rate: RateMibPerSec(0),
+            long_term_ingestion_rate: RateMibPerSec(0),
+            num_wal_bytes: 2_000,
+        }]);
+        state_guard.quota_table.update_wal_usage(
+            &"test-ingester-0".into(),
+            &source_uid,
+            &shard_infos,
+        );
+        drop(state_guard);
+
+        let mut mock_ingester_0 = MockIngesterService::new();
+        let index_uid_1_clone = index_uid_1.clone();
+        mock_ingester_0
+            .expect_persist()
+            .once()
+            .returning(move |request| {
+                assert_eq!(request.subrequests.len(), 1);
+
+                let subrequest = &request.subrequests[0];
+                assert_eq!(subrequest.subrequest_id, 1);
+                assert_eq!(subrequest.index_uid(), &index_uid_1_clone);
+
+                let response = PersistResponse {
+                    leader_id: request.leader_id,
+                    successes: vec![PersistSuccess {
+                        subrequest_id: 1,
+                        index_uid: Some(index_uid_1_clone.clone()),
+                        source_id: "test-source".to_string(),
+                        shard_id: Some(ShardId::from(1)),
+                        replication_position_inclusive: Some(Position::offset(0u64)),
+                        num_persisted_docs: 1,
+                        parse_failures: Vec::new(),
+                    }],
+                    failures: Vec::new(),
+                };
+                Ok(response)
+            });
+        let ingester_0 = IngesterServiceClient::from_mock(mock_ingester_0);
+        ingester_pool.insert("test-ingester-0".into(), ingester_0);
+
+        let ingest_request = IngestRequestV2 {
+            subrequests: vec![
+                IngestSubrequest {
+                    subrequest_id: 0,
+                    index_id: "test-index-0".to_string(),
+                    source_id: "test-source".to_string(),
+                    doc_batch: Some(DocBatchV2::for_test(["test-doc-foo"])),
+                },
+                IngestSubrequest {
+                    subrequest_id: 1,
+                    index_id: "test-index-1".to_string(),
+                    source_id: "test-source".to_string(),
+                    doc_batch: Some(DocBatchV2::for_test(["test-doc-bar"])),
+                },
+            ],
+            commit_type: CommitTypeV2::Auto as i32,
+        };
+        let response = router.ingest(ingest_request).await.unwrap();
+        assert_eq!(response.successes.len(), 1);
+        assert_eq!(response.successes[0].subrequest_id, 1);
+
+        assert_eq!(response.failures.len(), 1);
+        let failure = &response.failures[0];
+        assert_eq!(failure.subrequest_id, 0);
+        assert_eq!(failure.index_id, "test-index-0");
+        assert_eq!(failure.reason(), IngestFailureReason::IndexWalQuotaExceeded);
+        assert_eq!(failure.retry_after_ms, Some(10_000));
+
+        // The ingester is not called when all the subrequests are rejected.
+        let ingest_request = IngestRequestV2 {
+            subrequests: vec![IngestSubrequest {
+                subrequest_id: 0,
+                index_id: "test-index-0".to_string(),
+                source_id: "test-source".to_string(),
+                doc_batch: Some(DocBatchV2::for_test(["test-doc-baz"])),
+            }],
+            commit_type: CommitTypeV2::Auto as i32,
+        };
+        let response = router.ingest(ingest_request).await.unwrap();
+        assert!(response.successes.is_empty());
+        assert_eq!(response.failures.len(), 1);
+        assert_eq!(
+            response.failures[0].reason(),
+            IngestFailureReason::IndexWalQuotaExceeded
+        );
+    }
+
     #[tokio::test]
     async fn test_router_ingest_retry() {
         let self_node_id = "test-router".into();
@@ -1821,12 +2093,14 @@ mod tests {
                     shard_state: ShardState::Closed,
                     short_term_ingestion_rate: RateMibPerSec(0),
                     long_term_ingestion_rate: RateMibPerSec(0),
+                    num_wal_bytes: 0,
                 },
                 ShardInfo {
                     shard_id: ShardId::from(2),
                     shard_state: ShardState::Open,
                     short_term_ingestion_rate: RateMibPerSec(0),
                     long_term_ingestion_rate: RateMibPerSec(0),
+                    num_wal_bytes: 0,
                 },
             ]),
         };
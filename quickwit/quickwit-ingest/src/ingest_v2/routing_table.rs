@@ -20,6 +20,7 @@
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 use quickwit_proto::ingest::{Shard, ShardIds, ShardState};
 use quickwit_proto::types::{IndexId, IndexUid, NodeId, ShardId, SourceId};
@@ -64,6 +65,9 @@ pub(super) struct RoutingTableEntry {
     /// Shards located on remote nodes.
     pub remote_shards: Vec<RoutingEntry>,
     pub remote_round_robin_idx: AtomicUsize,
+    /// Last time the entry was refreshed from the control plane, which also reports the ingest
+    /// quota of the index. `None` if the entry was only populated from chitchat events.
+    pub refreshed_at_opt: Option<Instant>,
 }
 
 impl RoutingTableEntry {
@@ -94,6 +98,7 @@ impl RoutingTableEntry {
             source_id,
             local_shards,
             remote_shards,
+            refreshed_at_opt: Some(Instant::now()),
             ..Default::default()
         }
     }
@@ -378,6 +383,30 @@ impl RoutingTable {
         result
     }
 
+    /// Returns `true` if the entry for the source exists and was not refreshed from the control
+    /// plane within the last `refresh_interval`, in which case the entry is considered refreshed
+    /// from now on.
+    pub fn poll_refresh(
+        &mut self,
+        index_id: impl Into<IndexId>,
+        source_id: impl Into<SourceId>,
+        refresh_interval: Duration,
+    ) -> bool {
+        let key = (index_id.into(), source_id.into());
+        let Some(entry) = self.table.get_mut(&key) else {
+            return false;
+        };
+        let now = Instant::now();
+
+        if let Some(refreshed_at) = entry.refreshed_at_opt {
+            if now.duration_since(refreshed_at) < refresh_interval {
+                return false;
+            }
+        }
+        entry.refreshed_at_opt = Some(now);
+        true
+    }
+
     /// Replaces the routing table entry for the source with the provided shards.
     pub fn replace_shards(
         &mut self,
@@ -603,6 +632,7 @@ mod tests {
             local_round_robin_idx: AtomicUsize::default(),
             remote_shards: Vec::new(),
             remote_round_robin_idx: AtomicUsize::default(),
+            refreshed_at_opt: None,
         };
         assert!(table_entry.has_open_shards(
             &ingester_pool,
@@ -644,6 +674,7 @@ mod tests {
                 },
             ],
             remote_round_robin_idx: AtomicUsize::default(),
+            refreshed_at_opt: None,
         };
         assert!(table_entry.has_open_shards(
             &ingester_pool,
@@ -701,6 +732,7 @@ mod tests {
             local_round_robin_idx: AtomicUsize::default(),
             remote_shards: Vec::new(),
             remote_round_robin_idx: AtomicUsize::default(),
+            refreshed_at_opt: None,
         };
         let shard = table_entry
             .next_open_shard_round_robin(&ingester_pool, &rate_limited_shards)
@@ -759,6 +791,7 @@ mod tests {
                 },
             ],
             remote_round_robin_idx: AtomicUsize::default(),
+            refreshed_at_opt: None,
         };
         let shard = table_entry
             .next_open_shard_round_robin(&ingester_pool, &rate_limited_shards)
@@ -954,6 +987,7 @@ mod tests {
                 },
             ],
             remote_round_robin_idx: AtomicUsize::default(),
+            refreshed_at_opt: None,
         };
         table_entry.close_shards(
             &index_uid,
@@ -1035,6 +1069,7 @@ mod tests {
                 },
             ],
             remote_round_robin_idx: AtomicUsize::default(),
+            refreshed_at_opt: None,
         };
         table_entry.delete_shards(
             &index_uid,
@@ -1052,4 +1087,29 @@ mod tests {
         assert_eq!(table_entry.remote_shards[0].shard_id, ShardId::from(5));
         assert_eq!(table_entry.remote_shards[1].shard_id, ShardId::from(7));
     }
+
+    #[test]
+    fn test_routing_table_poll_refresh() {
+        let mut routing_table = RoutingTable {
+            self_node_id: "test-node-0".into(),
+            table: HashMap::default(),
+        };
+        let refresh_interval = Duration::from_secs(30);
+        assert!(!routing_table.poll_refresh("test-index", "test-source", refresh_interval));
+
+        let index_uid = IndexUid::for_test("test-index", 0);
+        routing_table.insert_open_shards(
+            &"test-node-1".into(),
+            index_uid.clone(),
+            "test-source",
+            &[ShardId::from(1)],
+        );
+        // Entries populated from chitchat events only have never been refreshed.
+        assert!(routing_table.poll_refresh("test-index", "test-source", refresh_interval));
+        assert!(!routing_table.poll_refresh("test-index", "test-source", refresh_interval));
+        assert!(routing_table.poll_refresh("test-index", "test-source", Duration::ZERO));
+
+        routing_table.replace_shards(index_uid, "test-source", Vec::new());
+        assert!(!routing_table.poll_refresh("test-index", "test-source", refresh_interval));
+    }
 }
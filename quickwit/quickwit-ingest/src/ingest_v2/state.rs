@@ -38,7 +38,9 @@ use tracing::{error, info};
 use super::models::IngesterShard;
 use super::rate_meter::RateMeter;
 use super::replication::{ReplicationStreamTaskHandle, ReplicationTaskHandle};
-use crate::ingest_v2::mrecordlog_utils::{force_delete_queue, queue_position_range};
+use crate::ingest_v2::mrecordlog_utils::{
+    force_delete_queue, queue_num_bytes, queue_position_range,
+};
 use crate::mrecordlog_async::MultiRecordLogAsync;
 use crate::{FollowerId, LeaderId};
 
@@ -194,6 +196,7 @@ impl IngesterState {
                 );
                 // We want to advertise the shard as read-only right away.
                 solo_shard.is_advertisable = true;
+                solo_shard.num_wal_bytes = queue_num_bytes(&mrecordlog, &queue_id, ..);
                 inner_guard.shards.insert(queue_id.clone(), solo_shard);
 
                 let rate_limiter = RateLimiter::from_settings(rate_limiter_settings);
@@ -388,6 +391,12 @@ impl FullyLockedIngesterState<'_> {
         if shard.truncation_position_inclusive >= truncate_up_to_position_inclusive {
             return;
         }
+        let num_truncated_bytes = queue_num_bytes(
+            &self.mrecordlog,
+            queue_id,
+            ..=truncate_up_to_offset_inclusive,
+        );
+
         match self
             .mrecordlog
             .truncate(queue_id, truncate_up_to_offset_inclusive)
@@ -396,6 +405,7 @@ impl FullyLockedIngesterState<'_> {
             Ok(_) => {
                 info!("truncated shard `{queue_id}` at {truncate_up_to_position_inclusive}");
                 shard.truncation_position_inclusive = truncate_up_to_position_inclusive;
+                shard.num_wal_bytes = shard.num_wal_bytes.saturating_sub(num_truncated_bytes);
             }
             Err(TruncateError::MissingQueue(_)) => {
                 error!("failed to truncate shard `{queue_id}`: WAL queue not found");
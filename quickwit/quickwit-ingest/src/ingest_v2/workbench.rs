@@ -291,6 +291,7 @@ impl IngestWorkbench {
                     index_id: subworkbench.subrequest.index_id,
                     source_id: subworkbench.subrequest.source_id,
                     reason: failure.reason() as i32,
+                    retry_after_ms: None,
                 };
                 failures.push(failure);
             }
@@ -358,6 +359,10 @@ impl SubworkbenchFailure {
                 RateLimitingCause::WalFull => IngestFailureReason::WalFull,
                 RateLimitingCause::CircuitBreaker => IngestFailureReason::CircuitBreaker,
                 RateLimitingCause::ShardRateLimiting => IngestFailureReason::ShardRateLimited,
+                RateLimitingCause::IndexRateLimiting => IngestFailureReason::IndexRateLimited,
+                RateLimitingCause::IndexWalQuotaExceeded => {
+                    IngestFailureReason::IndexWalQuotaExceeded
+                }
                 RateLimitingCause::Unknown => IngestFailureReason::Unspecified,
             },
             Self::Persist(persist_failure_reason) => (*persist_failure_reason).into(),
@@ -78,6 +78,8 @@ pub(super) async fn create_empty_cluster(
         grpc_advertise_addr: config.grpc_advertise_addr,
         indexing_tasks: Vec::new(),
         indexing_cpu_capacity: CpuCapacity::zero(),
+        availability_zone: None,
+        rack: None,
     };
     let cluster = Cluster::join(
         config.cluster_id.clone(),
@@ -150,6 +152,7 @@ pub(super) async fn configure_source(
         source_params,
         transform_config,
         input_format,
+        dead_letter_queue_config: None,
     })
 }
 
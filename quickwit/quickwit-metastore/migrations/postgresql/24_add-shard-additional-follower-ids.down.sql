@@ -0,0 +1,2 @@
+ALTER TABLE shards
+    DROP IF EXISTS additional_follower_ids;
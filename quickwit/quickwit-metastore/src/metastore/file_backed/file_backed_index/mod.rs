@@ -31,7 +31,7 @@ use std::ops::Bound;
 use itertools::Itertools;
 use quickwit_common::pretty::PrettySample;
 use quickwit_config::{
-    DocMapping, IndexingSettings, RetentionPolicy, SearchSettings, SourceConfig,
+    DocMapping, IndexingSettings, IngestSettings, RetentionPolicy, SearchSettings, SourceConfig,
 };
 use quickwit_proto::metastore::{
     AcquireShardsRequest, AcquireShardsResponse, DeleteQuery, DeleteShardsRequest,
@@ -234,6 +234,11 @@ impl FileBackedIndex {
         self.metadata.set_indexing_settings(search_settings)
     }
 
+    /// Replaces the ingest settings in the index config, returning whether a mutation occurred.
+    pub fn set_ingest_settings(&mut self, ingest_settings: IngestSettings) -> bool {
+        self.metadata.set_ingest_settings(ingest_settings)
+    }
+
     /// Replaces the doc mapping in the index config, returning whether a mutation occurred.
     pub fn set_doc_mapping(&mut self, doc_mapping: DocMapping) -> bool {
         self.metadata.set_doc_mapping(doc_mapping)
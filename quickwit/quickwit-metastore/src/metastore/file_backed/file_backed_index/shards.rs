@@ -130,6 +130,7 @@ impl Shards {
                     shard_state: ShardState::Open as i32,
                     leader_id: subrequest.leader_id,
                     follower_id: subrequest.follower_id,
+                    additional_follower_ids: subrequest.additional_follower_ids,
                     doc_mapping_uid: subrequest.doc_mapping_uid,
                     publish_position_inclusive: Some(Position::Beginning),
                     publish_token: subrequest.publish_token.clone(),
@@ -144,6 +145,7 @@ impl Shards {
                     %shard_id,
                     leader_id=%shard.leader_id,
                     follower_id=?shard.follower_id,
+                    additional_follower_ids=?shard.additional_follower_ids,
                     "opened shard"
                 );
                 shard
@@ -373,6 +375,7 @@ mod tests {
             shard_id: Some(ShardId::from(1)),
             leader_id: "leader_id".to_string(),
             follower_id: None,
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_token: None,
         };
@@ -407,6 +410,7 @@ mod tests {
             shard_id: Some(ShardId::from(2)),
             leader_id: "leader_id".to_string(),
             follower_id: Some("follower_id".to_string()),
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_token: Some("publish_token".to_string()),
         };
@@ -562,6 +562,7 @@ impl MetastoreService for FileBackedMetastore {
         let search_settings = request.deserialize_search_settings()?;
         let indexing_settings = request.deserialize_indexing_settings()?;
         let doc_mapping = request.deserialize_doc_mapping()?;
+        let ingest_settings_opt = request.deserialize_ingest_settings()?;
         let index_uid = request.index_uid();
 
         let index_metadata = self
@@ -571,6 +572,10 @@ impl MetastoreService for FileBackedMetastore {
                 mutation_occurred |= index.set_indexing_settings(indexing_settings);
                 mutation_occurred |= index.set_doc_mapping(doc_mapping);
 
+                if let Some(ingest_settings) = ingest_settings_opt {
+                    mutation_occurred |= index.set_ingest_settings(ingest_settings);
+                }
+
                 let index_metadata = index.metadata().clone();
 
                 if mutation_occurred {
@@ -24,7 +24,8 @@ use std::collections::HashMap;
 
 use quickwit_common::uri::Uri;
 use quickwit_config::{
-    DocMapping, IndexConfig, IndexingSettings, RetentionPolicy, SearchSettings, SourceConfig,
+    DocMapping, IndexConfig, IndexingSettings, IngestSettings, RetentionPolicy, SearchSettings,
+    SourceConfig,
 };
 use quickwit_proto::metastore::{EntityKind, MetastoreError, MetastoreResult};
 use quickwit_proto::types::{IndexUid, SourceId};
@@ -130,6 +131,16 @@ impl IndexMetadata {
         }
     }
 
+    /// Replaces the current ingest settings, returning whether a mutation occurred.
+    pub fn set_ingest_settings(&mut self, ingest_settings: IngestSettings) -> bool {
+        if self.index_config.ingest_settings != ingest_settings {
+            self.index_config.ingest_settings = ingest_settings;
+            true
+        } else {
+            false
+        }
+    }
+
     /// Replaces the current doc mapping, returning whether a mutation occurred.
     pub fn set_doc_mapping(&mut self, doc_mapping: DocMapping) -> bool {
         if self.index_config.doc_mapping != doc_mapping {
@@ -34,8 +34,8 @@ pub use index_metadata::IndexMetadata;
 use itertools::Itertools;
 use quickwit_common::thread_pool::run_cpu_intensive;
 use quickwit_config::{
-    DocMapping, FileSourceParams, IndexConfig, IndexingSettings, RetentionPolicy, SearchSettings,
-    SourceConfig, SourceParams,
+    DocMapping, FileSourceParams, IndexConfig, IndexingSettings, IngestSettings, RetentionPolicy,
+    SearchSettings, SourceConfig, SourceParams,
 };
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore::{
@@ -194,6 +194,7 @@ pub trait UpdateIndexRequestExt {
         retention_policy_opt: &Option<RetentionPolicy>,
         indexing_settings: &IndexingSettings,
         doc_mapping: &DocMapping,
+        ingest_settings: &IngestSettings,
     ) -> MetastoreResult<UpdateIndexRequest>;
 
     /// Deserializes the `search_settings_json` field of an [`UpdateIndexRequest`] into a
@@ -211,6 +212,10 @@ pub trait UpdateIndexRequestExt {
     /// Deserilalize the `doc_mapping_json` field of an `[UpdateIndexRequest]` into a
     /// [`DocMapping`] object.
     fn deserialize_doc_mapping(&self) -> MetastoreResult<DocMapping>;
+
+    /// Deserializes the `ingest_settings_json` field of an [`UpdateIndexRequest`] into an
+    /// [`IngestSettings`] object. Returns `None` if the field is not set.
+    fn deserialize_ingest_settings(&self) -> MetastoreResult<Option<IngestSettings>>;
 }
 
 impl UpdateIndexRequestExt for UpdateIndexRequest {
@@ -220,6 +225,7 @@ impl UpdateIndexRequestExt for UpdateIndexRequest {
         retention_policy_opt: &Option<RetentionPolicy>,
         indexing_settings: &IndexingSettings,
         doc_mapping: &DocMapping,
+        ingest_settings: &IngestSettings,
     ) -> MetastoreResult<UpdateIndexRequest> {
         let search_settings_json = serde_utils::to_json_str(search_settings)?;
         let retention_policy_json = retention_policy_opt
@@ -228,6 +234,7 @@ impl UpdateIndexRequestExt for UpdateIndexRequest {
             .transpose()?;
         let indexing_settings_json = serde_utils::to_json_str(indexing_settings)?;
         let doc_mapping_json = serde_utils::to_json_str(doc_mapping)?;
+        let ingest_settings_json = serde_utils::to_json_str(ingest_settings)?;
 
         let update_request = UpdateIndexRequest {
             index_uid: Some(index_uid.into()),
@@ -235,6 +242,7 @@ impl UpdateIndexRequestExt for UpdateIndexRequest {
             retention_policy_json,
             indexing_settings_json,
             doc_mapping_json,
+            ingest_settings_json: Some(ingest_settings_json),
         };
         Ok(update_request)
     }
@@ -257,6 +265,13 @@ impl UpdateIndexRequestExt for UpdateIndexRequest {
     fn deserialize_doc_mapping(&self) -> MetastoreResult<DocMapping> {
         serde_utils::from_json_str(&self.doc_mapping_json)
     }
+
+    fn deserialize_ingest_settings(&self) -> MetastoreResult<Option<IngestSettings>> {
+        self.ingest_settings_json
+            .as_ref()
+            .map(|ingest_settings| serde_utils::from_json_str(ingest_settings))
+            .transpose()
+    }
 }
 
 /// Helper trait to build a [`IndexMetadataResponse`] and deserialize its payload.
@@ -993,6 +1008,8 @@ fn use_shard_api(params: &SourceParams) -> bool {
     match params {
         SourceParams::File(FileSourceParams::Filepath(_)) => false,
         SourceParams::File(FileSourceParams::Notifications(_)) => true,
+        SourceParams::Http(_) => false,
+        SourceParams::Reindex(_) => false,
         SourceParams::Ingest => true,
         SourceParams::IngestApi => false,
         SourceParams::IngestCli => false,
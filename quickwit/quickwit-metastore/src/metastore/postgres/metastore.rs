@@ -422,6 +422,7 @@ impl MetastoreService for PostgresqlMetastore {
         let search_settings = request.deserialize_search_settings()?;
         let indexing_settings = request.deserialize_indexing_settings()?;
         let doc_mapping = request.deserialize_doc_mapping()?;
+        let ingest_settings_opt = request.deserialize_ingest_settings()?;
 
         let index_uid: IndexUid = request.index_uid().clone();
         let updated_index_metadata = run_with_tx!(self.connection_pool, tx, "update index", {
@@ -431,6 +432,10 @@ impl MetastoreService for PostgresqlMetastore {
                 mutation_occurred |= index_metadata.set_search_settings(search_settings);
                 mutation_occurred |= index_metadata.set_indexing_settings(indexing_settings);
                 mutation_occurred |= index_metadata.set_doc_mapping(doc_mapping);
+
+                if let Some(ingest_settings) = ingest_settings_opt {
+                    mutation_occurred |= index_metadata.set_ingest_settings(ingest_settings);
+                }
                 Ok(MutationOccurred::from(mutation_occurred))
             })
             .await
@@ -1663,6 +1668,7 @@ async fn open_or_fetch_shard<'e>(
         .bind(subrequest.shard_id().as_str())
         .bind(&subrequest.leader_id)
         .bind(&subrequest.follower_id)
+        .bind(&subrequest.additional_follower_ids)
         .bind(subrequest.doc_mapping_uid)
         .bind(&subrequest.publish_token)
         // Use a timestamp generated by the metastore node to avoid clock drift issues
@@ -1678,6 +1684,7 @@ async fn open_or_fetch_shard<'e>(
             shard_id=%shard.shard_id(),
             leader_id=%shard.leader_id,
             follower_id=?shard.follower_id,
+            additional_follower_ids=?shard.additional_follower_ids,
             "opened shard"
         );
         return Ok(shard);
@@ -1829,6 +1836,7 @@ mod tests {
                 let Shard {
                     doc_mapping_uid,
                     follower_id,
+                    additional_follower_ids,
                     index_uid,
                     leader_id,
                     publish_position_inclusive,
@@ -1850,6 +1858,7 @@ mod tests {
                     .bind(shard_state_name)
                     .bind(leader_id)
                     .bind(follower_id)
+                    .bind(additional_follower_ids)
                     .bind(doc_mapping_uid)
                     .bind(publish_position_inclusive.unwrap().to_string())
                     .bind(publish_token)
@@ -268,6 +268,7 @@ pub(super) struct PgShard {
     pub shard_id: ShardId,
     pub leader_id: String,
     pub follower_id: Option<String>,
+    pub additional_follower_ids: Vec<String>,
     pub shard_state: PgShardState,
     #[sqlx(try_from = "String")]
     pub doc_mapping_uid: DocMappingUid,
@@ -285,6 +286,7 @@ impl From<PgShard> for Shard {
             shard_state: ShardState::from(pg_shard.shard_state) as i32,
             leader_id: pg_shard.leader_id,
             follower_id: pg_shard.follower_id,
+            additional_follower_ids: pg_shard.additional_follower_ids,
             doc_mapping_uid: Some(pg_shard.doc_mapping_uid),
             publish_position_inclusive: Some(pg_shard.publish_position_inclusive.into()),
             publish_token: pg_shard.publish_token,
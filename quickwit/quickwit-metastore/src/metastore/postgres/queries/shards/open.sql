@@ -1,5 +1,5 @@
-INSERT INTO shards(index_uid, source_id, shard_id, leader_id, follower_id, doc_mapping_uid, publish_token, update_timestamp)
-    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+INSERT INTO shards(index_uid, source_id, shard_id, leader_id, follower_id, additional_follower_ids, doc_mapping_uid, publish_token, update_timestamp)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
 ON CONFLICT
     DO NOTHING
 RETURNING
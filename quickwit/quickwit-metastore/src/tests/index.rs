@@ -25,11 +25,12 @@
 //  - list_indexes
 //  - delete_index
 
+use bytesize::ByteSize;
 use quickwit_common::rand::append_random_suffix;
 use quickwit_config::merge_policy_config::{MergePolicyConfig, StableLogMergePolicyConfig};
 use quickwit_config::{
-    IndexConfig, IndexingSettings, RetentionPolicy, SearchSettings, SourceConfig, CLI_SOURCE_ID,
-    INGEST_V2_SOURCE_ID,
+    IndexConfig, IndexingSettings, IngestSettings, RetentionPolicy, SearchSettings, SourceConfig,
+    CLI_SOURCE_ID, INGEST_V2_SOURCE_ID,
 };
 use quickwit_doc_mapper::{Cardinality, FieldMappingEntry, FieldMappingType, QuickwitJsonOptions};
 use quickwit_proto::metastore::{
@@ -128,6 +129,7 @@ pub async fn test_metastore_update_retention_policy<
             &loop_retention_policy_opt,
             &index_config.indexing_settings,
             &index_config.doc_mapping,
+            &index_config.ingest_settings,
         )
         .unwrap();
         let response_metadata = metastore
@@ -175,6 +177,7 @@ pub async fn test_metastore_update_search_settings<
             &index_config.retention_policy_opt,
             &index_config.indexing_settings,
             &index_config.doc_mapping,
+            &index_config.ingest_settings,
         )
         .unwrap();
         let response_metadata = metastore
@@ -232,6 +235,7 @@ pub async fn test_metastore_update_indexing_settings<
                 ..Default::default()
             },
             &index_config.doc_mapping,
+            &index_config.ingest_settings,
         )
         .unwrap();
         let resp_metadata = metastore
@@ -260,6 +264,93 @@ pub async fn test_metastore_update_indexing_settings<
     cleanup_index(&mut metastore, index_uid).await;
 }
 
+pub async fn test_metastore_update_ingest_settings<
+    MetastoreToTest: MetastoreService + MetastoreServiceExt + DefaultForTest,
+>() {
+    let (mut metastore, index_uid, index_config) =
+        setup_metastore_for_update::<MetastoreToTest>().await;
+
+    let quota_ingest_settings = IngestSettings {
+        max_bytes_per_sec: Some(ByteSize::mib(5)),
+        max_docs_per_sec: Some(1_000),
+        max_wal_bytes: None,
+    };
+    for loop_ingest_settings in [
+        IngestSettings::default(),
+        quota_ingest_settings.clone(),
+        quota_ingest_settings,
+        IngestSettings::default(),
+    ] {
+        let index_update = UpdateIndexRequest::try_from_updates(
+            index_uid.clone(),
+            &index_config.search_settings,
+            &index_config.retention_policy_opt,
+            &index_config.indexing_settings,
+            &index_config.doc_mapping,
+            &loop_ingest_settings,
+        )
+        .unwrap();
+        let resp_metadata = metastore
+            .update_index(index_update)
+            .await
+            .unwrap()
+            .deserialize_index_metadata()
+            .unwrap();
+        assert_eq!(
+            resp_metadata.index_config.ingest_settings,
+            loop_ingest_settings
+        );
+        let updated_metadata = metastore
+            .index_metadata(IndexMetadataRequest::for_index_id(
+                index_uid.index_id.to_string(),
+            ))
+            .await
+            .unwrap()
+            .deserialize_index_metadata()
+            .unwrap();
+        assert_eq!(
+            updated_metadata.index_config.ingest_settings,
+            loop_ingest_settings
+        );
+    }
+    // Requests sent by older clients do not carry the ingest settings and leave them untouched.
+    let ingest_settings = IngestSettings {
+        max_wal_bytes: Some(ByteSize::gib(1)),
+        ..Default::default()
+    };
+    let index_update = UpdateIndexRequest::try_from_updates(
+        index_uid.clone(),
+        &index_config.search_settings,
+        &index_config.retention_policy_opt,
+        &index_config.indexing_settings,
+        &index_config.doc_mapping,
+        &ingest_settings,
+    )
+    .unwrap();
+    metastore.update_index(index_update).await.unwrap();
+
+    let mut index_update = UpdateIndexRequest::try_from_updates(
+        index_uid.clone(),
+        &index_config.search_settings,
+        &index_config.retention_policy_opt,
+        &index_config.indexing_settings,
+        &index_config.doc_mapping,
+        &IngestSettings::default(),
+    )
+    .unwrap();
+    index_update.ingest_settings_json = None;
+
+    let resp_metadata = metastore
+        .update_index(index_update)
+        .await
+        .unwrap()
+        .deserialize_index_metadata()
+        .unwrap();
+    assert_eq!(resp_metadata.index_config.ingest_settings, ingest_settings);
+
+    cleanup_index(&mut metastore, index_uid).await;
+}
+
 pub async fn test_metastore_update_doc_mapping<
     MetastoreToTest: MetastoreService + MetastoreServiceExt + DefaultForTest,
 >() {
@@ -279,6 +370,7 @@ pub async fn test_metastore_update_doc_mapping<
     new_field.field_mappings.push(FieldMappingEntry {
         name: "new_field".to_string(),
         mapping_type: FieldMappingType::Json(json_options.clone(), Cardinality::SingleValued),
+        fields: Vec::new(),
     });
     new_field.doc_mapping_uid = DocMappingUid::random();
     let mut new_field_stored = initial.clone();
@@ -291,6 +383,7 @@ pub async fn test_metastore_update_doc_mapping<
             },
             Cardinality::SingleValued,
         ),
+        fields: Vec::new(),
     });
     new_field_stored.doc_mapping_uid = DocMappingUid::random();
 
@@ -301,6 +394,7 @@ pub async fn test_metastore_update_doc_mapping<
             &index_config.retention_policy_opt,
             &index_config.indexing_settings,
             &loop_doc_mapping,
+            &index_config.ingest_settings,
         )
         .unwrap();
         let resp_metadata = metastore
@@ -136,6 +136,7 @@ pub async fn test_metastore_open_shards<
             shard_id: Some(ShardId::from(1)),
             leader_id: "test-ingester-foo".to_string(),
             follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_token: None,
         }],
@@ -169,6 +170,7 @@ pub async fn test_metastore_open_shards<
             shard_id: Some(ShardId::from(1)),
             leader_id: "test-ingester-foo".to_string(),
             follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_token: Some("publish-token-baz".to_string()),
         }],
@@ -199,6 +201,7 @@ pub async fn test_metastore_open_shards<
             shard_id: Some(ShardId::from(2)),
             leader_id: "test-ingester-foo".to_string(),
             follower_id: None,
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_token: Some("publish-token-open".to_string()),
         }],
@@ -219,6 +222,29 @@ pub async fn test_metastore_open_shards<
     assert_eq!(shard.publish_position_inclusive(), Position::Beginning);
     assert_eq!(shard.publish_token(), "publish-token-open");
 
+    // Test open shard #3 with additional followers.
+    let open_shards_request = OpenShardsRequest {
+        subrequests: vec![OpenShardSubrequest {
+            subrequest_id: 0,
+            index_uid: Some(test_index.index_uid.clone()),
+            source_id: test_index.source_id.clone(),
+            shard_id: Some(ShardId::from(3)),
+            leader_id: "test-ingester-foo".to_string(),
+            follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: vec!["test-ingester-qux".to_string()],
+            doc_mapping_uid: Some(DocMappingUid::default()),
+            publish_token: None,
+        }],
+    };
+    let open_shards_response = metastore.open_shards(open_shards_request).await.unwrap();
+    assert_eq!(open_shards_response.subresponses.len(), 1);
+
+    let shard = open_shards_response.subresponses[0].open_shard();
+    assert_eq!(shard.shard_id(), ShardId::from(3));
+    assert_eq!(shard.follower_id(), "test-ingester-bar");
+    assert_eq!(shard.additional_follower_ids, ["test-ingester-qux"]);
+    assert_eq!(shard.replication_factor(), 3);
+
     cleanup_index(&mut metastore, test_index.index_uid).await;
 }
 
@@ -242,6 +268,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Closed as i32,
             leader_id: "test-ingester-foo".to_string(),
             follower_id: Some("test-ingester-bar".to_string()),
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: Some("test-publish-token-foo".to_string()),
@@ -254,6 +281,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Open as i32,
             leader_id: "test-ingester-bar".to_string(),
             follower_id: Some("test-ingester-qux".to_string()),
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: Some("test-publish-token-bar".to_string()),
@@ -266,6 +294,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Open as i32,
             leader_id: "test-ingester-qux".to_string(),
             follower_id: Some("test-ingester-baz".to_string()),
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: None,
@@ -278,6 +307,7 @@ pub async fn test_metastore_acquire_shards<
             shard_state: ShardState::Open as i32,
             leader_id: "test-ingester-baz".to_string(),
             follower_id: Some("test-ingester-tux".to_string()),
+            additional_follower_ids: Vec::new(),
             doc_mapping_uid: Some(DocMappingUid::default()),
             publish_position_inclusive: Some(Position::Beginning),
             publish_token: None,
@@ -370,6 +400,7 @@ pub async fn test_metastore_list_shards<
                 shard_state: ShardState::Open as i32,
                 leader_id: "test-ingester-foo".to_string(),
                 follower_id: Some("test-ingester-bar".to_string()),
+                additional_follower_ids: Vec::new(),
                 doc_mapping_uid: Some(DocMappingUid::default()),
                 publish_position_inclusive: Some(Position::Beginning),
                 publish_token: Some("test-publish-token-foo".to_string()),
@@ -382,6 +413,7 @@ pub async fn test_metastore_list_shards<
                 shard_state: ShardState::Closed as i32,
                 leader_id: "test-ingester-bar".to_string(),
                 follower_id: Some("test-ingester-qux".to_string()),
+                additional_follower_ids: Vec::new(),
                 doc_mapping_uid: Some(DocMappingUid::default()),
                 publish_position_inclusive: Some(Position::Beginning),
                 publish_token: Some("test-publish-token-bar".to_string()),
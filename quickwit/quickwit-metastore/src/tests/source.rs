@@ -60,6 +60,7 @@ pub async fn test_metastore_add_source<MetastoreToTest: MetastoreServiceExt + De
         source_params: SourceParams::void(),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_queue_config: None,
     };
 
     assert_eq!(
@@ -159,6 +160,7 @@ pub async fn test_metastore_toggle_source<MetastoreToTest: MetastoreServiceExt +
         source_params: SourceParams::void(),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_queue_config: None,
     };
     let add_source_request =
         AddSourceRequest::try_from_source_config(index_uid.clone(), &source).unwrap();
@@ -225,6 +227,7 @@ pub async fn test_metastore_delete_source<MetastoreToTest: MetastoreServiceExt +
         source_params: SourceParams::void(),
         transform_config: None,
         input_format: SourceInputFormat::Json,
+        dead_letter_queue_config: None,
     };
 
     let index_config = IndexConfig::for_test(&index_id, index_uri.as_str());
@@ -346,6 +349,7 @@ pub async fn test_metastore_reset_checkpoint<
             source_params: SourceParams::void(),
             transform_config: None,
             input_format: SourceInputFormat::Json,
+            dead_letter_queue_config: None,
         };
         metastore
             .add_source(
@@ -28,6 +28,7 @@ pub struct OtlpServiceMetrics {
     pub request_duration_seconds: HistogramVec<5>,
     pub ingested_log_records_total: IntCounterVec<4>,
     pub ingested_spans_total: IntCounterVec<4>,
+    pub ingested_data_points_total: IntCounterVec<4>,
     pub ingested_bytes_total: IntCounterVec<4>,
 }
 
@@ -70,6 +71,13 @@ impl Default for OtlpServiceMetrics {
                 &[],
                 ["service", "index", "transport", "format"],
             ),
+            ingested_data_points_total: new_counter_vec(
+                "ingested_data_points_total",
+                "Number of metric data points ingested",
+                "otlp",
+                &[],
+                ["service", "index", "transport", "format"],
+            ),
             ingested_bytes_total: new_counter_vec(
                 "ingested_bytes_total",
                 "Number of bytes ingested",
@@ -34,6 +34,7 @@ use serde_json::{Number as JsonNumber, Value as JsonValue};
 
 mod logs;
 mod metrics;
+mod otel_metrics;
 mod span_id;
 #[cfg(any(test, feature = "testsuite"))]
 mod test_utils;
@@ -44,6 +45,10 @@ pub use logs::{
     parse_otlp_logs_json, parse_otlp_logs_protobuf, JsonLogIterator, OtlpGrpcLogsService,
     OtlpLogsError, OTEL_LOGS_INDEX_ID,
 };
+pub use otel_metrics::{
+    AggregationTemporality, MetricDataPoint, MetricValues, OtlpGrpcMetricsService,
+    OTEL_METRICS_INDEX_ID,
+};
 pub use span_id::{SpanId, TryFromSpanIdError};
 #[cfg(any(test, feature = "testsuite"))]
 pub use test_utils::make_resource_spans_for_test;
@@ -58,6 +63,7 @@ pub use traces::{
 #[derive(Debug, Clone, Copy)]
 pub enum OtelSignal {
     Logs,
+    Metrics,
     Traces,
 }
 
@@ -65,6 +71,7 @@ impl OtelSignal {
     pub fn header_name(&self) -> &'static str {
         match self {
             OtelSignal::Logs => "qw-otel-logs-index",
+            OtelSignal::Metrics => "qw-otel-metrics-index",
             OtelSignal::Traces => "qw-otel-traces-index",
         }
     }
@@ -72,6 +79,7 @@ impl OtelSignal {
     pub fn default_index_id(&self) -> &'static str {
         match self {
             OtelSignal::Logs => OTEL_LOGS_INDEX_ID,
+            OtelSignal::Metrics => OTEL_METRICS_INDEX_ID,
             OtelSignal::Traces => OTEL_TRACES_INDEX_ID,
         }
     }
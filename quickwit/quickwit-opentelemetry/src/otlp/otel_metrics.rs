@@ -0,0 +1,929 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use quickwit_common::thread_pool::run_cpu_intensive;
+use quickwit_common::uri::Uri;
+use quickwit_config::{load_index_config_from_user_config, ConfigFormat, IndexConfig};
+use quickwit_ingest::{CommitType, JsonDocBatchV2Builder};
+use quickwit_proto::ingest::router::IngestRouterServiceClient;
+use quickwit_proto::ingest::DocBatchV2;
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::metrics_service_server::MetricsService;
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::{
+    ExportMetricsPartialSuccess, ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
+use quickwit_proto::opentelemetry::proto::metrics::v1::exponential_histogram_data_point::Buckets as OtlpBuckets;
+use quickwit_proto::opentelemetry::proto::metrics::v1::metric::Data as OtlpMetricData;
+use quickwit_proto::opentelemetry::proto::metrics::v1::number_data_point::Value as OtlpNumberValue;
+use quickwit_proto::opentelemetry::proto::metrics::v1::{
+    AggregationTemporality as OtlpAggregationTemporality, NumberDataPoint as OtlpNumberDataPoint,
+};
+use quickwit_proto::types::{DocUidGenerator, IndexId};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use time::OffsetDateTime;
+use tonic::{Request, Response, Status};
+use tracing::field::Empty;
+use tracing::{error, instrument, Span as RuntimeSpan};
+
+use super::{extract_otel_index_id_from_metadata, ingest_doc_batch_v2, is_zero, OtelSignal};
+use crate::otlp::extract_attributes;
+use crate::otlp::metrics::OTLP_SERVICE_METRICS;
+
+pub const OTEL_METRICS_INDEX_ID: &str = "otel-metrics-v0_9";
+
+const OTEL_METRICS_INDEX_CONFIG: &str = r#"
+version: 0.8
+
+index_id: ${INDEX_ID}
+
+doc_mapping:
+  mode: strict
+  field_mappings:
+    - name: timestamp_nanos
+      type: datetime
+      input_formats: [unix_timestamp]
+      output_format: unix_timestamp_nanos
+      indexed: false
+      fast: true
+      fast_precision: milliseconds
+    - name: start_timestamp_nanos
+      type: datetime
+      input_formats: [unix_timestamp]
+      output_format: unix_timestamp_nanos
+      indexed: false
+    - name: service_name
+      type: text
+      tokenizer: raw
+      fast: true
+    - name: metric_name
+      type: text
+      tokenizer: raw
+      fast: true
+    - name: metric_description
+      type: text
+      indexed: false
+    - name: metric_unit
+      type: text
+      tokenizer: raw
+      fast: true
+    - name: metric_type
+      type: text
+      tokenizer: raw
+      fast: true
+    - name: aggregation_temporality
+      type: text
+      tokenizer: raw
+    - name: is_monotonic
+      type: bool
+      indexed: false
+    - name: value
+      type: f64
+      fast: true
+    - name: count
+      type: u64
+      fast: true
+    - name: sum
+      type: f64
+      fast: true
+    - name: min
+      type: f64
+      fast: true
+    - name: max
+      type: f64
+      fast: true
+    - name: bucket_counts
+      type: array<u64>
+      indexed: false
+    - name: explicit_bounds
+      type: array<f64>
+      indexed: false
+    - name: scale
+      type: i64
+      indexed: false
+    - name: zero_count
+      type: u64
+      indexed: false
+    - name: positive_offset
+      type: i64
+      indexed: false
+    - name: positive_bucket_counts
+      type: array<u64>
+      indexed: false
+    - name: negative_offset
+      type: i64
+      indexed: false
+    - name: negative_bucket_counts
+      type: array<u64>
+      indexed: false
+    - name: attributes
+      type: json
+      tokenizer: raw
+      fast: true
+    - name: flags
+      type: u64
+      indexed: false
+    - name: resource_attributes
+      type: json
+      tokenizer: raw
+      fast: true
+    - name: resource_dropped_attributes_count
+      type: u64
+      indexed: false
+    - name: scope_name
+      type: text
+      indexed: false
+    - name: scope_version
+      type: text
+      indexed: false
+    - name: scope_attributes
+      type: json
+      indexed: false
+    - name: scope_dropped_attributes_count
+      type: u64
+      indexed: false
+
+  timestamp_field: timestamp_nanos
+
+indexing_settings:
+  commit_timeout_secs: 5
+
+search_settings:
+  default_search_fields: [metric_name]
+"#;
+
+/// A data point of a gauge, sum, histogram, or exponential histogram metric, flattened along with
+/// the metadata of its metric, resource, and instrumentation scope.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricDataPoint {
+    pub timestamp_nanos: u64,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_timestamp_nanos: Option<u64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub service_name: String,
+    pub metric_name: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric_description: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric_unit: Option<String>,
+    #[serde(flatten)]
+    pub values: MetricValues,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, JsonValue>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub flags: u32,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub resource_attributes: HashMap<String, JsonValue>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub resource_dropped_attributes_count: u32,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope_name: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope_version: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub scope_attributes: HashMap<String, JsonValue>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub scope_dropped_attributes_count: u32,
+}
+
+/// The values of a data point, which depend on the type of the metric.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "metric_type", rename_all = "snake_case")]
+pub enum MetricValues {
+    Gauge {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<f64>,
+    },
+    Sum {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<f64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        aggregation_temporality: Option<AggregationTemporality>,
+        is_monotonic: bool,
+    },
+    Histogram {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        aggregation_temporality: Option<AggregationTemporality>,
+        count: u64,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sum: Option<f64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        bucket_counts: Vec<u64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        explicit_bounds: Vec<f64>,
+    },
+    ExponentialHistogram {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        aggregation_temporality: Option<AggregationTemporality>,
+        count: u64,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sum: Option<f64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+        scale: i32,
+        zero_count: u64,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        positive_offset: Option<i32>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        positive_bucket_counts: Vec<u64>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        negative_offset: Option<i32>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        negative_bucket_counts: Vec<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationTemporality {
+    Delta,
+    Cumulative,
+}
+
+impl AggregationTemporality {
+    fn from_otlp(aggregation_temporality: i32) -> Option<Self> {
+        match OtlpAggregationTemporality::from_i32(aggregation_temporality)? {
+            OtlpAggregationTemporality::Delta => Some(AggregationTemporality::Delta),
+            OtlpAggregationTemporality::Cumulative => Some(AggregationTemporality::Cumulative),
+            OtlpAggregationTemporality::Unspecified => None,
+        }
+    }
+}
+
+struct ParsedMetrics {
+    doc_batch: DocBatchV2,
+    num_data_points: u64,
+    num_rejected_data_points: u64,
+    error_message: String,
+}
+
+#[derive(Clone)]
+pub struct OtlpGrpcMetricsService {
+    ingest_router: IngestRouterServiceClient,
+}
+
+impl OtlpGrpcMetricsService {
+    pub fn new(ingest_router: IngestRouterServiceClient) -> Self {
+        Self { ingest_router }
+    }
+
+    pub fn index_config(default_index_root_uri: &Uri) -> anyhow::Result<IndexConfig> {
+        let index_config_str =
+            OTEL_METRICS_INDEX_CONFIG.replace("${INDEX_ID}", OTEL_METRICS_INDEX_ID);
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            index_config_str.as_bytes(),
+            default_index_root_uri,
+        )?;
+        Ok(index_config)
+    }
+
+    async fn export_inner(
+        &mut self,
+        request: ExportMetricsServiceRequest,
+        index_id: IndexId,
+        labels: [&str; 4],
+    ) -> Result<ExportMetricsServiceResponse, Status> {
+        let ParsedMetrics {
+            doc_batch,
+            num_data_points,
+            num_rejected_data_points,
+            error_message,
+        } = run_cpu_intensive({
+            let parent_span = RuntimeSpan::current();
+            || Self::parse_metrics(request, parent_span)
+        })
+        .await
+        .map_err(|join_error| {
+            error!(error=?join_error, "failed to parse metrics");
+            Status::internal("failed to parse metrics")
+        })?;
+        if num_data_points == 0 && num_rejected_data_points == 0 {
+            return Err(tonic::Status::invalid_argument("request is empty"));
+        }
+        if num_data_points == 0 {
+            return Err(tonic::Status::invalid_argument(error_message));
+        }
+        let num_bytes = doc_batch.num_bytes() as u64;
+        self.store_metrics(index_id, doc_batch).await?;
+
+        OTLP_SERVICE_METRICS
+            .ingested_data_points_total
+            .with_label_values(labels)
+            .inc_by(num_data_points);
+        OTLP_SERVICE_METRICS
+            .ingested_bytes_total
+            .with_label_values(labels)
+            .inc_by(num_bytes);
+
+        let response = ExportMetricsServiceResponse {
+            // `rejected_data_points=0` and `error_message=""` is considered a "full" success.
+            partial_success: Some(ExportMetricsPartialSuccess {
+                rejected_data_points: num_rejected_data_points as i64,
+                error_message,
+            }),
+        };
+        Ok(response)
+    }
+
+    #[instrument(skip_all, parent = parent_span, fields(num_data_points = Empty, num_bytes = Empty, num_rejected_data_points = Empty))]
+    fn parse_metrics(
+        request: ExportMetricsServiceRequest,
+        parent_span: RuntimeSpan,
+    ) -> ParsedMetrics {
+        let (data_points, mut num_rejected_data_points, mut error_message) =
+            parse_otlp_metrics(request);
+        let mut num_data_points = 0;
+
+        let mut doc_batch_builder = JsonDocBatchV2Builder::default();
+        let mut doc_uid_generator = DocUidGenerator::default();
+        for data_point in data_points {
+            let doc_uid = doc_uid_generator.next_doc_uid();
+            if let Err(error) = doc_batch_builder.add_doc(doc_uid, data_point) {
+                error!(error=?error, "failed to JSON serialize data point");
+                error_message = format!("failed to JSON serialize data point: {error:?}");
+                num_rejected_data_points += 1;
+            } else {
+                num_data_points += 1;
+            }
+        }
+        let doc_batch = doc_batch_builder.build();
+        let current_span = RuntimeSpan::current();
+        current_span.record("num_data_points", num_data_points);
+        current_span.record("num_bytes", doc_batch.num_bytes());
+        current_span.record("num_rejected_data_points", num_rejected_data_points);
+
+        ParsedMetrics {
+            doc_batch,
+            num_data_points,
+            num_rejected_data_points,
+            error_message,
+        }
+    }
+
+    #[instrument(skip_all, fields(num_bytes = doc_batch.num_bytes()))]
+    async fn store_metrics(
+        &mut self,
+        index_id: String,
+        doc_batch: DocBatchV2,
+    ) -> Result<(), tonic::Status> {
+        ingest_doc_batch_v2(
+            self.ingest_router.clone(),
+            index_id,
+            doc_batch,
+            CommitType::Auto,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn export_instrumented(
+        &mut self,
+        request: ExportMetricsServiceRequest,
+        index_id: IndexId,
+    ) -> Result<ExportMetricsServiceResponse, Status> {
+        let start = std::time::Instant::now();
+
+        let labels = ["metrics", &index_id, "grpc", "protobuf"];
+
+        OTLP_SERVICE_METRICS
+            .requests_total
+            .with_label_values(labels)
+            .inc();
+        let (export_res, is_error) =
+            match self.export_inner(request, index_id.clone(), labels).await {
+                ok @ Ok(_) => (ok, "false"),
+                err @ Err(_) => {
+                    OTLP_SERVICE_METRICS
+                        .request_errors_total
+                        .with_label_values(labels)
+                        .inc();
+                    (err, "true")
+                }
+            };
+        let elapsed = start.elapsed().as_secs_f64();
+        let labels = ["metrics", &index_id, "grpc", "protobuf", is_error];
+        OTLP_SERVICE_METRICS
+            .request_duration_seconds
+            .with_label_values(labels)
+            .observe(elapsed);
+
+        export_res
+    }
+}
+
+#[async_trait]
+impl MetricsService for OtlpGrpcMetricsService {
+    #[instrument(name = "ingest_metrics", skip_all)]
+    async fn export(
+        &self,
+        request: Request<ExportMetricsServiceRequest>,
+    ) -> Result<Response<ExportMetricsServiceResponse>, Status> {
+        let index_id =
+            extract_otel_index_id_from_metadata(request.metadata(), OtelSignal::Metrics)?;
+        let request = request.into_inner();
+        self.clone()
+            .export_instrumented(request, index_id)
+            .await
+            .map(Response::new)
+    }
+}
+
+/// Metadata shared by all the data points of a metric.
+struct MetricMetadata<'a> {
+    service_name: &'a str,
+    metric_name: &'a str,
+    metric_description: Option<&'a str>,
+    metric_unit: Option<&'a str>,
+    resource_attributes: &'a HashMap<String, JsonValue>,
+    resource_dropped_attributes_count: u32,
+    scope_name: Option<&'a str>,
+    scope_version: Option<&'a str>,
+    scope_attributes: &'a HashMap<String, JsonValue>,
+    scope_dropped_attributes_count: u32,
+}
+
+impl MetricMetadata<'_> {
+    fn make_data_point(
+        &self,
+        time_unix_nano: u64,
+        start_time_unix_nano: u64,
+        attributes: HashMap<String, JsonValue>,
+        flags: u32,
+        values: MetricValues,
+    ) -> MetricDataPoint {
+        let timestamp_nanos = if time_unix_nano == 0 {
+            OffsetDateTime::now_utc().unix_timestamp_nanos() as u64
+        } else {
+            time_unix_nano
+        };
+        let start_timestamp_nanos = if start_time_unix_nano == 0 {
+            None
+        } else {
+            Some(start_time_unix_nano)
+        };
+        MetricDataPoint {
+            timestamp_nanos,
+            start_timestamp_nanos,
+            service_name: self.service_name.to_string(),
+            metric_name: self.metric_name.to_string(),
+            metric_description: self.metric_description.map(str::to_string),
+            metric_unit: self.metric_unit.map(str::to_string),
+            values,
+            attributes,
+            flags,
+            resource_attributes: self.resource_attributes.clone(),
+            resource_dropped_attributes_count: self.resource_dropped_attributes_count,
+            scope_name: self.scope_name.map(str::to_string),
+            scope_version: self.scope_version.map(str::to_string),
+            scope_attributes: self.scope_attributes.clone(),
+            scope_dropped_attributes_count: self.scope_dropped_attributes_count,
+        }
+    }
+}
+
+fn number_data_point_value(data_point: &OtlpNumberDataPoint) -> Option<f64> {
+    match data_point.value? {
+        OtlpNumberValue::AsDouble(value) => Some(value),
+        OtlpNumberValue::AsInt(value) => Some(value as f64),
+    }
+}
+
+fn split_buckets(buckets_opt: Option<OtlpBuckets>) -> (Option<i32>, Vec<u64>) {
+    match buckets_opt {
+        Some(buckets) => (Some(buckets.offset), buckets.bucket_counts),
+        None => (None, Vec::new()),
+    }
+}
+
+/// Flattens the data points of the metrics of an export request. Returns the data points, the
+/// number of data points rejected because their metric type is not supported, and an error
+/// message describing the rejection.
+fn parse_otlp_metrics(request: ExportMetricsServiceRequest) -> (Vec<MetricDataPoint>, u64, String) {
+    let mut data_points = Vec::new();
+    let mut num_rejected_data_points = 0;
+    let mut error_message = String::new();
+
+    for resource_metrics in request.resource_metrics {
+        let resource = resource_metrics.resource.unwrap_or_default();
+        let mut resource_attributes = extract_attributes(resource.attributes);
+        let resource_dropped_attributes_count = resource.dropped_attributes_count;
+
+        let service_name = match resource_attributes.remove("service.name") {
+            Some(JsonValue::String(value)) => value.to_string(),
+            _ => "unknown_service".to_string(),
+        };
+        for scope_metrics in resource_metrics.scope_metrics {
+            let scope = scope_metrics.scope.unwrap_or_default();
+            let scope_attributes = extract_attributes(scope.attributes);
+
+            for metric in scope_metrics.metrics {
+                let metadata = MetricMetadata {
+                    service_name: &service_name,
+                    metric_name: &metric.name,
+                    metric_description: Some(metric.description.as_str())
+                        .filter(|description| !description.is_empty()),
+                    metric_unit: Some(metric.unit.as_str()).filter(|unit| !unit.is_empty()),
+                    resource_attributes: &resource_attributes,
+                    resource_dropped_attributes_count,
+                    scope_name: Some(scope.name.as_str()).filter(|name| !name.is_empty()),
+                    scope_version: Some(scope.version.as_str())
+                        .filter(|version| !version.is_empty()),
+                    scope_attributes: &scope_attributes,
+                    scope_dropped_attributes_count: scope.dropped_attributes_count,
+                };
+                match metric.data {
+                    Some(OtlpMetricData::Gauge(gauge)) => {
+                        for data_point in gauge.data_points {
+                            let values = MetricValues::Gauge {
+                                value: number_data_point_value(&data_point),
+                            };
+                            data_points.push(metadata.make_data_point(
+                                data_point.time_unix_nano,
+                                data_point.start_time_unix_nano,
+                                extract_attributes(data_point.attributes),
+                                data_point.flags,
+                                values,
+                            ));
+                        }
+                    }
+                    Some(OtlpMetricData::Sum(sum)) => {
+                        let aggregation_temporality =
+                            AggregationTemporality::from_otlp(sum.aggregation_temporality);
+
+                        for data_point in sum.data_points {
+                            let values = MetricValues::Sum {
+                                value: number_data_point_value(&data_point),
+                                aggregation_temporality,
+                                is_monotonic: sum.is_monotonic,
+                            };
+                            data_points.push(metadata.make_data_point(
+                                data_point.time_unix_nano,
+                                data_point.start_time_unix_nano,
+                                extract_attributes(data_point.attributes),
+                                data_point.flags,
+                                values,
+                            ));
+                        }
+                    }
+                    Some(OtlpMetricData::Histogram(histogram)) => {
+                        let aggregation_temporality =
+                            AggregationTemporality::from_otlp(histogram.aggregation_temporality);
+
+                        for data_point in histogram.data_points {
+                            let values = MetricValues::Histogram {
+                                aggregation_temporality,
+                                count: data_point.count,
+                                sum: data_point.sum,
+                                min: data_point.min,
+                                max: data_point.max,
+                                bucket_counts: data_point.bucket_counts,
+                                explicit_bounds: data_point.explicit_bounds,
+                            };
+                            data_points.push(metadata.make_data_point(
+                                data_point.time_unix_nano,
+                                data_point.start_time_unix_nano,
+                                extract_attributes(data_point.attributes),
+                                data_point.flags,
+                                values,
+                            ));
+                        }
+                    }
+                    Some(OtlpMetricData::ExponentialHistogram(exponential_histogram)) => {
+                        let aggregation_temporality = AggregationTemporality::from_otlp(
+                            exponential_histogram.aggregation_temporality,
+                        );
+                        for data_point in exponential_histogram.data_points {
+                            let (positive_offset, positive_bucket_counts) =
+                                split_buckets(data_point.positive);
+                            let (negative_offset, negative_bucket_counts) =
+                                split_buckets(data_point.negative);
+                            let values = MetricValues::ExponentialHistogram {
+                                aggregation_temporality,
+                                count: data_point.count,
+                                sum: data_point.sum,
+                                min: data_point.min,
+                                max: data_point.max,
+                                scale: data_point.scale,
+                                zero_count: data_point.zero_count,
+                                positive_offset,
+                                positive_bucket_counts,
+                                negative_offset,
+                                negative_bucket_counts,
+                            };
+                            data_points.push(metadata.make_data_point(
+                                data_point.time_unix_nano,
+                                data_point.start_time_unix_nano,
+                                extract_attributes(data_point.attributes),
+                                data_point.flags,
+                                values,
+                            ));
+                        }
+                    }
+                    Some(OtlpMetricData::Summary(summary)) => {
+                        num_rejected_data_points += summary.data_points.len() as u64;
+                        error_message = format!(
+                            "summary metrics are not supported: rejected data points of metric \
+                             `{}`",
+                            metric.name
+                        );
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+    (data_points, num_rejected_data_points, error_message)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{metastore_for_test, CreateIndexRequestExt};
+    use quickwit_proto::ingest::router::{
+        IngestResponseV2, IngestSuccess, MockIngestRouterService,
+    };
+    use quickwit_proto::metastore::{CreateIndexRequest, MetastoreService};
+    use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpAnyValueValue;
+    use quickwit_proto::opentelemetry::proto::common::v1::{
+        AnyValue as OtlpAnyValue, InstrumentationScope, KeyValue as OtlpKeyValue,
+    };
+    use quickwit_proto::opentelemetry::proto::metrics::v1::{
+        ExponentialHistogram as OtlpExponentialHistogram,
+        ExponentialHistogramDataPoint as OtlpExponentialHistogramDataPoint, Gauge as OtlpGauge,
+        Histogram as OtlpHistogram, HistogramDataPoint as OtlpHistogramDataPoint,
+        Metric as OtlpMetric, ResourceMetrics, ScopeMetrics, Sum as OtlpSum,
+        Summary as OtlpSummary, SummaryDataPoint as OtlpSummaryDataPoint,
+    };
+    use quickwit_proto::opentelemetry::proto::resource::v1::Resource;
+    use serde_json::json;
+
+    use super::*;
+
+    fn make_key_value(key: &str, value: &str) -> OtlpKeyValue {
+        OtlpKeyValue {
+            key: key.to_string(),
+            value: Some(OtlpAnyValue {
+                value: Some(OtlpAnyValueValue::StringValue(value.to_string())),
+            }),
+        }
+    }
+
+    fn make_metric(name: &str, data: OtlpMetricData) -> OtlpMetric {
+        OtlpMetric {
+            name: name.to_string(),
+            description: String::new(),
+            unit: "ms".to_string(),
+            data: Some(data),
+        }
+    }
+
+    fn make_export_metrics_request_for_test() -> ExportMetricsServiceRequest {
+        let gauge = OtlpMetricData::Gauge(OtlpGauge {
+            data_points: vec![OtlpNumberDataPoint {
+                attributes: vec![make_key_value("host", "host-1")],
+                time_unix_nano: 1_000,
+                value: Some(OtlpNumberValue::AsDouble(0.5)),
+                ..Default::default()
+            }],
+        });
+        let sum = OtlpMetricData::Sum(OtlpSum {
+            data_points: vec![
+                OtlpNumberDataPoint {
+                    start_time_unix_nano: 1_000,
+                    time_unix_nano: 2_000,
+                    value: Some(OtlpNumberValue::AsInt(42)),
+                    ..Default::default()
+                },
+                OtlpNumberDataPoint {
+                    start_time_unix_nano: 1_000,
+                    time_unix_nano: 3_000,
+                    value: Some(OtlpNumberValue::AsInt(43)),
+                    ..Default::default()
+                },
+            ],
+            aggregation_temporality: OtlpAggregationTemporality::Cumulative as i32,
+            is_monotonic: true,
+        });
+        let histogram = OtlpMetricData::Histogram(OtlpHistogram {
+            data_points: vec![OtlpHistogramDataPoint {
+                time_unix_nano: 4_000,
+                count: 3,
+                sum: Some(12.0),
+                min: Some(1.0),
+                max: Some(8.0),
+                bucket_counts: vec![1, 1, 1],
+                explicit_bounds: vec![2.0, 5.0],
+                ..Default::default()
+            }],
+            aggregation_temporality: OtlpAggregationTemporality::Delta as i32,
+        });
+        let exponential_histogram =
+            OtlpMetricData::ExponentialHistogram(OtlpExponentialHistogram {
+                data_points: vec![OtlpExponentialHistogramDataPoint {
+                    time_unix_nano: 5_000,
+                    count: 4,
+                    sum: Some(10.0),
+                    scale: 1,
+                    zero_count: 1,
+                    positive: Some(OtlpBuckets {
+                        offset: -1,
+                        bucket_counts: vec![2, 1],
+                    }),
+                    ..Default::default()
+                }],
+                aggregation_temporality: OtlpAggregationTemporality::Delta as i32,
+            });
+        let summary = OtlpMetricData::Summary(OtlpSummary {
+            data_points: vec![OtlpSummaryDataPoint::default()],
+        });
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(Resource {
+                    attributes: vec![
+                        make_key_value("service.name", "checkout"),
+                        make_key_value("region", "us-east-1"),
+                    ],
+                    dropped_attributes_count: 0,
+                }),
+                scope_metrics: vec![ScopeMetrics {
+                    scope: Some(InstrumentationScope {
+                        name: "my-meter".to_string(),
+                        version: "1.0.0".to_string(),
+                        attributes: Vec::new(),
+                        dropped_attributes_count: 0,
+                    }),
+                    metrics: vec![
+                        make_metric("cpu.utilization", gauge),
+                        make_metric("http.requests", sum),
+                        make_metric("http.duration", histogram),
+                        make_metric("db.duration", exponential_histogram),
+                        make_metric("rpc.duration", summary),
+                    ],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_index_config_is_valid() {
+        let index_config =
+            OtlpGrpcMetricsService::index_config(&Uri::for_test("ram:///indexes")).unwrap();
+        assert_eq!(index_config.index_id, OTEL_METRICS_INDEX_ID);
+    }
+
+    #[tokio::test]
+    async fn test_create_index() {
+        let metastore = metastore_for_test();
+        let index_config =
+            OtlpGrpcMetricsService::index_config(&Uri::for_test("ram:///indexes")).unwrap();
+        let create_index_request =
+            CreateIndexRequest::try_from_index_config(&index_config).unwrap();
+        metastore.create_index(create_index_request).await.unwrap();
+    }
+
+    #[test]
+    fn test_parse_otlp_metrics() {
+        let request = make_export_metrics_request_for_test();
+        let (data_points, num_rejected_data_points, error_message) = parse_otlp_metrics(request);
+        assert_eq!(data_points.len(), 5);
+        assert_eq!(num_rejected_data_points, 1);
+        assert_eq!(
+            error_message,
+            "summary metrics are not supported: rejected data points of metric `rpc.duration`"
+        );
+        let data_points_json: Vec<JsonValue> = data_points
+            .into_iter()
+            .map(|data_point| serde_json::to_value(data_point).unwrap())
+            .collect();
+        assert_eq!(
+            data_points_json[0],
+            json!({
+                "timestamp_nanos": 1_000,
+                "service_name": "checkout",
+                "metric_name": "cpu.utilization",
+                "metric_unit": "ms",
+                "metric_type": "gauge",
+                "value": 0.5,
+                "attributes": { "host": "host-1" },
+                "resource_attributes": { "region": "us-east-1" },
+                "scope_name": "my-meter",
+                "scope_version": "1.0.0",
+            })
+        );
+        assert_eq!(data_points_json[1]["metric_type"], "sum");
+        assert_eq!(data_points_json[1]["start_timestamp_nanos"], 1_000);
+        assert_eq!(data_points_json[1]["value"], 42.0);
+        assert_eq!(data_points_json[1]["aggregation_temporality"], "cumulative");
+        assert_eq!(data_points_json[1]["is_monotonic"], true);
+        assert_eq!(data_points_json[2]["value"], 43.0);
+
+        assert_eq!(data_points_json[3]["metric_type"], "histogram");
+        assert_eq!(data_points_json[3]["aggregation_temporality"], "delta");
+        assert_eq!(data_points_json[3]["count"], 3);
+        assert_eq!(data_points_json[3]["sum"], 12.0);
+        assert_eq!(data_points_json[3]["bucket_counts"], json!([1, 1, 1]));
+        assert_eq!(data_points_json[3]["explicit_bounds"], json!([2.0, 5.0]));
+
+        assert_eq!(data_points_json[4]["metric_type"], "exponential_histogram");
+        assert_eq!(data_points_json[4]["scale"], 1);
+        assert_eq!(data_points_json[4]["zero_count"], 1);
+        assert_eq!(data_points_json[4]["positive_offset"], -1);
+        assert_eq!(data_points_json[4]["positive_bucket_counts"], json!([2, 1]));
+        assert!(data_points_json[4].get("negative_offset").is_none());
+
+        let data_point: MetricDataPoint =
+            serde_json::from_value(data_points_json[4].clone()).unwrap();
+        assert!(matches!(
+            data_point.values,
+            MetricValues::ExponentialHistogram { count: 4, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_export_metrics() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .withf(|request| {
+                let subrequest = &request.subrequests[0];
+                subrequest.index_id == OTEL_METRICS_INDEX_ID
+                    && subrequest.doc_batch.as_ref().unwrap().num_docs() == 5
+            })
+            .returning(|_| {
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        num_ingested_docs: 5,
+                        ..Default::default()
+                    }],
+                    failures: Vec::new(),
+                })
+            });
+        let ingest_router = IngestRouterServiceClient::from_mock(mock_ingest_router);
+        let metrics_service = OtlpGrpcMetricsService::new(ingest_router);
+
+        let request = Request::new(make_export_metrics_request_for_test());
+        let response = metrics_service.export(request).await.unwrap().into_inner();
+        let partial_success = response.partial_success.unwrap();
+        assert_eq!(partial_success.rejected_data_points, 1);
+
+        let request = Request::new(ExportMetricsServiceRequest::default());
+        let status = metrics_service.export(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
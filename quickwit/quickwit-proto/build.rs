@@ -147,6 +147,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "Shard.follower_id",
             "#[serde(default, skip_serializing_if = \"Option::is_none\")]",
         )
+        .field_attribute(
+            "Shard.additional_follower_ids",
+            "#[serde(default, skip_serializing_if = \"Vec::is_empty\")]",
+        )
         .field_attribute(
             "Shard.publish_position_inclusive",
             "#[serde(default, skip_serializing_if = \"Option::is_none\")]",
@@ -189,8 +193,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]",
         )
         .type_attribute("PartialHit", "#[derive(Eq, Hash)]")
-        .type_attribute("PartialHit.sort_value", "#[derive(Copy)]")
+        .field_attribute(
+            "PartialHit.extra_sort_values",
+            "#[serde(default, skip_serializing_if = \"Vec::is_empty\")]",
+        )
+        .field_attribute(
+            "PartialHit.collapse_value",
+            "#[serde(default, skip_serializing_if = \"Option::is_none\")]",
+        )
         .type_attribute("SearchRequest", "#[derive(Eq, Hash)]")
+        .type_attribute("SnippetOptions", "#[derive(Eq, Hash)]")
         .type_attribute("ListFieldSerialized", "#[derive(Eq)]")
         .type_attribute("SortByValue", "#[derive(Ord, PartialOrd)]")
         .type_attribute("SortField", "#[derive(Eq, Hash)]")
@@ -223,6 +235,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "ExportLogsServiceResponse",
             r#"#[derive(utoipa::ToSchema)]"#,
         )
+        .type_attribute(
+            "ExportMetricsServiceResponse",
+            r#"#[derive(utoipa::ToSchema)]"#,
+        )
         .out_dir("src/codegen/opentelemetry")
         .compile_with_config(prost_config, &protos, &["protos/third-party"])?;
     Ok(())
@@ -13,6 +13,7 @@ pub struct ExportMetricsServiceRequest {
     >,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
+#[derive(utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExportMetricsServiceResponse {
@@ -45,6 +45,9 @@ pub struct GetOrCreateOpenShardsSuccess {
     pub source_id: ::prost::alloc::string::String,
     #[prost(message, repeated, tag = "4")]
     pub open_shards: ::prost::alloc::vec::Vec<super::ingest::Shard>,
+    /// Ingest quotas of the index, if any.
+    #[prost(message, optional, tag = "5")]
+    pub ingest_quota: ::core::option::Option<super::ingest::IngestQuota>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
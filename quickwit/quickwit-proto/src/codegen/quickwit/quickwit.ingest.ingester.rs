@@ -441,6 +441,7 @@ pub enum PersistFailureReason {
     ShardRateLimited = 3,
     WalFull = 4,
     Timeout = 5,
+    ReplicationFailed = 6,
 }
 impl PersistFailureReason {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -459,6 +460,9 @@ impl PersistFailureReason {
             }
             PersistFailureReason::WalFull => "PERSIST_FAILURE_REASON_WAL_FULL",
             PersistFailureReason::Timeout => "PERSIST_FAILURE_REASON_TIMEOUT",
+            PersistFailureReason::ReplicationFailed => {
+                "PERSIST_FAILURE_REASON_REPLICATION_FAILED"
+            }
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -470,6 +474,7 @@ impl PersistFailureReason {
             "PERSIST_FAILURE_REASON_SHARD_RATE_LIMITED" => Some(Self::ShardRateLimited),
             "PERSIST_FAILURE_REASON_WAL_FULL" => Some(Self::WalFull),
             "PERSIST_FAILURE_REASON_TIMEOUT" => Some(Self::Timeout),
+            "PERSIST_FAILURE_REASON_REPLICATION_FAILED" => Some(Self::ReplicationFailed),
             _ => None,
         }
     }
@@ -64,6 +64,9 @@ pub struct IngestFailure {
     pub source_id: ::prost::alloc::string::String,
     #[prost(enumeration = "IngestFailureReason", tag = "5")]
     pub reason: i32,
+    /// Set when the subrequest was rejected because the index exceeded one of its ingest quotas.
+    #[prost(uint64, optional, tag = "6")]
+    pub retry_after_ms: ::core::option::Option<u64>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[serde(rename_all = "snake_case")]
@@ -81,6 +84,9 @@ pub enum IngestFailureReason {
     RouterLoadShedding = 8,
     LoadShedding = 9,
     CircuitBreaker = 10,
+    IndexRateLimited = 11,
+    IndexWalQuotaExceeded = 12,
+    ReplicationFailed = 13,
 }
 impl IngestFailureReason {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -110,6 +116,15 @@ impl IngestFailureReason {
             IngestFailureReason::CircuitBreaker => {
                 "INGEST_FAILURE_REASON_CIRCUIT_BREAKER"
             }
+            IngestFailureReason::IndexRateLimited => {
+                "INGEST_FAILURE_REASON_INDEX_RATE_LIMITED"
+            }
+            IngestFailureReason::IndexWalQuotaExceeded => {
+                "INGEST_FAILURE_REASON_INDEX_WAL_QUOTA_EXCEEDED"
+            }
+            IngestFailureReason::ReplicationFailed => {
+                "INGEST_FAILURE_REASON_REPLICATION_FAILED"
+            }
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -128,6 +143,11 @@ impl IngestFailureReason {
             }
             "INGEST_FAILURE_REASON_LOAD_SHEDDING" => Some(Self::LoadShedding),
             "INGEST_FAILURE_REASON_CIRCUIT_BREAKER" => Some(Self::CircuitBreaker),
+            "INGEST_FAILURE_REASON_INDEX_RATE_LIMITED" => Some(Self::IndexRateLimited),
+            "INGEST_FAILURE_REASON_INDEX_WAL_QUOTA_EXCEEDED" => {
+                Some(Self::IndexWalQuotaExceeded)
+            }
+            "INGEST_FAILURE_REASON_REPLICATION_FAILED" => Some(Self::ReplicationFailed),
             _ => None,
         }
     }
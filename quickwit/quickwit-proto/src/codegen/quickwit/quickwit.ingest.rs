@@ -51,6 +51,11 @@ pub struct Shard {
     #[prost(string, optional, tag = "5")]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub follower_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// The node IDs of the ingesters holding additional copies of the data when the replication
+    /// factor is greater than 2.
+    #[prost(string, repeated, tag = "13")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_follower_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     /// Mutable fields
     #[prost(enumeration = "ShardState", tag = "8")]
     pub shard_state: i32,
@@ -115,6 +120,18 @@ pub struct ParseFailure {
     #[prost(string, tag = "3")]
     pub message: ::prost::alloc::string::String,
 }
+/// Ingest quotas of an index, enforced by the ingest routers.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IngestQuota {
+    #[prost(uint64, optional, tag = "1")]
+    pub max_bytes_per_sec: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub max_docs_per_sec: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "3")]
+    pub max_wal_bytes: ::core::option::Option<u64>,
+}
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[serde(rename_all = "snake_case")]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
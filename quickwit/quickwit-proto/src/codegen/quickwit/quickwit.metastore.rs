@@ -34,6 +34,9 @@ pub struct UpdateIndexRequest {
     pub indexing_settings_json: ::prost::alloc::string::String,
     #[prost(string, tag = "5")]
     pub doc_mapping_json: ::prost::alloc::string::String,
+    /// Left unset by older clients, in which case the ingest settings are not updated.
+    #[prost(string, optional, tag = "6")]
+    pub ingest_settings_json: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -336,6 +339,8 @@ pub struct OpenShardSubrequest {
     pub leader_id: ::prost::alloc::string::String,
     #[prost(string, optional, tag = "6")]
     pub follower_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "9")]
+    pub additional_follower_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     #[prost(message, optional, tag = "7")]
     pub doc_mapping_uid: ::core::option::Option<crate::types::DocMappingUid>,
     #[prost(string, optional, tag = "8")]
@@ -550,6 +555,10 @@ pub enum SourceType {
     Vec = 10,
     Void = 11,
     Stdin = 13,
+    /// HTTP push endpoint
+    Http = 14,
+    /// Documents of another index
+    Reindex = 15,
 }
 impl SourceType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -571,6 +580,8 @@ impl SourceType {
             SourceType::Vec => "SOURCE_TYPE_VEC",
             SourceType::Void => "SOURCE_TYPE_VOID",
             SourceType::Stdin => "SOURCE_TYPE_STDIN",
+            SourceType::Http => "SOURCE_TYPE_HTTP",
+            SourceType::Reindex => "SOURCE_TYPE_REINDEX",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -589,6 +600,8 @@ impl SourceType {
             "SOURCE_TYPE_VEC" => Some(Self::Vec),
             "SOURCE_TYPE_VOID" => Some(Self::Void),
             "SOURCE_TYPE_STDIN" => Some(Self::Stdin),
+            "SOURCE_TYPE_HTTP" => Some(Self::Http),
+            "SOURCE_TYPE_REINDEX" => Some(Self::Reindex),
             _ => None,
         }
     }
@@ -176,7 +176,7 @@ pub struct SearchRequest {
     /// Fields to extract snippet on
     #[prost(string, repeated, tag = "12")]
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
-    /// Optional sort by one or more fields (limited to 2 at the moment).
+    /// Optional sort by one or more fields.
     #[prost(message, repeated, tag = "14")]
     pub sort_fields: ::prost::alloc::vec::Vec<SortField>,
     /// If set, the search response will include a search id
@@ -191,6 +191,48 @@ pub struct SearchRequest {
     pub search_after: ::core::option::Option<PartialHit>,
     #[prost(enumeration = "CountHits", tag = "17")]
     pub count_hits: i32,
+    /// Options controlling how snippets are generated.
+    /// Ignored if `snippet_fields` is empty.
+    #[prost(message, optional, tag = "18")]
+    pub snippet_options: ::core::option::Option<SnippetOptions>,
+    /// If set, only the best hit for each distinct value of this text fast field
+    /// is returned.
+    #[prost(string, optional, tag = "19")]
+    pub collapse_field: ::core::option::Option<::prost::alloc::string::String>,
+    /// JSON object defining fields computed at query time by a script, indexed by
+    /// field name.
+    #[prost(string, optional, tag = "20")]
+    pub runtime_mappings: ::core::option::Option<::prost::alloc::string::String>,
+    /// Controls how split failures are handled by the root searcher.
+    /// - If unset, failed splits are reported in the response, but a leaf request
+    ///    failing as a whole fails the search.
+    /// - If true, the search returns a degraded answer: any failure, including a
+    ///    leaf request failing as a whole, is reported as failed splits.
+    /// - If false, the search fails as soon as one split fails.
+    #[prost(bool, optional, tag = "21")]
+    pub allow_partial_search_results: ::core::option::Option<bool>,
+    /// Fields of the documents returned in the hits, nested fields being designated
+    /// by their dot-separated path. If empty, the whole documents are returned.
+    #[prost(string, repeated, tag = "22")]
+    pub source_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[derive(Eq, Hash)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SnippetOptions {
+    /// Tag inserted before each highlighted term. Defaults to `<b>`.
+    #[prost(string, optional, tag = "1")]
+    pub pre_tag: ::core::option::Option<::prost::alloc::string::String>,
+    /// Tag inserted after each highlighted term. Defaults to `</b>`.
+    #[prost(string, optional, tag = "2")]
+    pub post_tag: ::core::option::Option<::prost::alloc::string::String>,
+    /// Maximum number of characters of a snippet. Defaults to 150.
+    #[prost(uint32, optional, tag = "3")]
+    pub max_num_chars: ::core::option::Option<u32>,
+    /// Maximum number of snippets returned per field. Unlimited by default.
+    #[prost(uint32, optional, tag = "4")]
+    pub max_num_snippets: ::core::option::Option<u32>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Eq, Hash)]
@@ -367,6 +409,10 @@ pub struct LeafHit {
     /// A snippet of the matching content
     #[prost(string, optional, tag = "3")]
     pub leaf_snippet_json: ::core::option::Option<::prost::alloc::string::String>,
+    /// Values of the runtime fields of the request, as a JSON object mapping each field to its
+    /// array of values.
+    #[prost(string, optional, tag = "4")]
+    pub leaf_fields_json: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -384,6 +430,10 @@ pub struct Hit {
     /// The index id of the hit
     #[prost(string, tag = "4")]
     pub index_id: ::prost::alloc::string::String,
+    /// Values of the runtime fields of the request, as a JSON object mapping each field to its
+    /// array of values.
+    #[prost(string, optional, tag = "5")]
+    pub fields_json: ::core::option::Option<::prost::alloc::string::String>,
 }
 /// A partial hit, is a hit for which we have not fetch the content yet.
 /// Instead, it holds a document_uri which is enough information to
@@ -409,6 +459,10 @@ pub struct PartialHit {
     pub sort_value: ::core::option::Option<SortByValue>,
     #[prost(message, optional, tag = "11")]
     pub sort_value2: ::core::option::Option<SortByValue>,
+    /// Values of the sorting keys following the first two, if any.
+    #[prost(message, repeated, tag = "12")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_sort_values: ::prost::alloc::vec::Vec<SortByValue>,
     #[prost(string, tag = "2")]
     pub split_id: ::prost::alloc::string::String,
     /// (segment_ord, doc) form a tantivy DocAddress, which is sufficient to identify a document
@@ -418,6 +472,11 @@ pub struct PartialHit {
     /// The DocId identifies a unique document at the scale of a tantivy segment.
     #[prost(uint32, tag = "4")]
     pub doc_id: u32,
+    /// Value of the collapse field for the given document, if the search request
+    /// collapses hits and the document has a value for this field.
+    #[prost(string, optional, tag = "5")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collapse_value: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[derive(Ord, PartialOrd)]
@@ -442,6 +501,9 @@ pub mod sort_by_value {
         F64(f64),
         #[prost(bool, tag = "4")]
         Boolean(bool),
+        /// Value of a text (keyword) fast field.
+        #[prost(string, tag = "5")]
+        Str(::prost::alloc::string::String),
     }
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
@@ -483,6 +545,8 @@ pub struct SnippetRequest {
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
     #[prost(string, tag = "2")]
     pub query_ast_resolved: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "3")]
+    pub snippet_options: ::core::option::Option<SnippetOptions>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -505,6 +569,13 @@ pub struct FetchDocsRequest {
     /// `DocMapper` as json serialized trait.
     #[prost(string, tag = "6")]
     pub doc_mapper: ::prost::alloc::string::String,
+    /// Fields of the documents to return. If empty, the whole documents are returned.
+    #[prost(string, repeated, tag = "8")]
+    pub source_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Runtime fields of the search request, as a JSON object. Their values are computed from the
+    /// fast fields of the documents and returned in `leaf_fields_json`.
+    #[prost(string, optional, tag = "9")]
+    pub runtime_mappings: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
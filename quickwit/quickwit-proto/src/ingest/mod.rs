@@ -49,6 +49,10 @@ pub enum RateLimitingCause {
     CircuitBreaker,
     #[error("shard rate limiting")]
     ShardRateLimiting,
+    #[error("index rate limiting")]
+    IndexRateLimiting,
+    #[error("index WAL quota exceeded")]
+    IndexWalQuotaExceeded,
     #[error("unknown")]
     Unknown,
 }
@@ -120,14 +124,24 @@ impl MakeLoadShedError for IngestV2Error {
 }
 
 impl Shard {
-    /// List of nodes that are storing the shard (the leader, and optionally the follower).
+    /// List of nodes that are storing the shard (the leader, and optionally the followers).
     pub fn ingesters(&self) -> impl Iterator<Item = &NodeIdRef> + '_ {
-        [Some(&self.leader_id), self.follower_id.as_ref()]
-            .into_iter()
-            .flatten()
+        std::iter::once(NodeIdRef::from_str(&self.leader_id)).chain(self.followers())
+    }
+
+    /// List of nodes that are storing a replica of the shard.
+    pub fn followers(&self) -> impl Iterator<Item = &NodeIdRef> + '_ {
+        self.follower_id
+            .iter()
+            .chain(&self.additional_follower_ids)
             .map(|node_id| NodeIdRef::from_str(node_id))
     }
 
+    /// Number of copies of the shard, i.e. the leader and the followers.
+    pub fn replication_factor(&self) -> usize {
+        1 + self.follower_id.is_some() as usize + self.additional_follower_ids.len()
+    }
+
     pub fn source_uid(&self) -> SourceUid {
         SourceUid {
             index_uid: self.index_uid().clone(),
@@ -319,6 +333,7 @@ impl From<PersistFailureReason> for IngestFailureReason {
             PersistFailureReason::WalFull => IngestFailureReason::WalFull,
             PersistFailureReason::ShardRateLimited => IngestFailureReason::ShardRateLimited,
             PersistFailureReason::Timeout => IngestFailureReason::Timeout,
+            PersistFailureReason::ReplicationFailed => IngestFailureReason::ReplicationFailed,
         }
     }
 }
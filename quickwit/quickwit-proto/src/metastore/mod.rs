@@ -299,6 +299,7 @@ impl SourceType {
         match self {
             SourceType::Cli => "ingest-cli",
             SourceType::File => "file",
+            SourceType::Http => "http",
             SourceType::IngestV1 => "ingest-api",
             SourceType::IngestV2 => "ingest",
             SourceType::Kafka => "kafka",
@@ -306,6 +307,7 @@ impl SourceType {
             SourceType::Nats => "nats",
             SourceType::PubSub => "pubsub",
             SourceType::Pulsar => "pulsar",
+            SourceType::Reindex => "reindex",
             SourceType::Stdin => "stdin",
             SourceType::Unspecified => "unspecified",
             SourceType::Vec => "vec",
@@ -319,6 +321,7 @@ impl fmt::Display for SourceType {
         let source_type_str = match self {
             SourceType::Cli => "CLI ingest",
             SourceType::File => "file",
+            SourceType::Http => "HTTP",
             SourceType::IngestV1 => "ingest API v1",
             SourceType::IngestV2 => "ingest API v2",
             SourceType::Kafka => "Apache Kafka",
@@ -326,6 +329,7 @@ impl fmt::Display for SourceType {
             SourceType::Nats => "NATS",
             SourceType::PubSub => "Google Cloud Pub/Sub",
             SourceType::Pulsar => "Apache Pulsar",
+            SourceType::Reindex => "reindex",
             SourceType::Stdin => "Stdin",
             SourceType::Unspecified => "unspecified",
             SourceType::Vec => "vec",
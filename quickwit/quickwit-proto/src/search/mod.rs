@@ -55,7 +55,6 @@ impl fmt::Display for SplitSearchError {
 }
 
 impl Eq for SortByValue {}
-impl Copy for SortByValue {}
 impl From<SortValue> for SortByValue {
     fn from(sort_value: SortValue) -> Self {
         SortByValue {
@@ -85,6 +84,7 @@ impl SortByValue {
                 }
             }
             Some(SortValue::Boolean(b)) => Bool(b),
+            Some(SortValue::Str(s)) => String(s),
             None => Null,
         }
     }
@@ -108,14 +108,15 @@ impl SortByValue {
             }
             // Strings that can be converted to a number are accepted.
             // Some clients (like JS clients) can't easily handle large integers
-            // without losing precision, so we accept them as strings.
+            // without losing precision, so we accept them as strings. Other strings are values
+            // of text fast fields.
             String(value) => {
                 if let Ok(number) = value.parse::<i64>() {
                     Some(SortValue::I64(number))
                 } else if let Ok(number) = value.parse::<u64>() {
                     Some(SortValue::U64(number))
                 } else {
-                    return None;
+                    Some(SortValue::Str(value))
                 }
             }
             Array(_) | Object(_) => return None,
@@ -130,30 +131,32 @@ impl SortByValue {
 // This is terrible because this means Eq, PartialEq are not really in line with Ord's
 // implementation. if in presence of NaN.
 impl Eq for SortValue {}
-impl Copy for SortValue {}
 
 impl Ord for SortValue {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
         // We make sure to end up with a total order.
-        match (*self, *other) {
+        match (self, other) {
             // Same types.
-            (SortValue::U64(left), SortValue::U64(right)) => left.cmp(&right),
-            (SortValue::I64(left), SortValue::I64(right)) => left.cmp(&right),
-            (SortValue::Boolean(left), SortValue::Boolean(right)) => left.cmp(&right),
+            (SortValue::U64(left), SortValue::U64(right)) => left.cmp(right),
+            (SortValue::I64(left), SortValue::I64(right)) => left.cmp(right),
+            (SortValue::Boolean(left), SortValue::Boolean(right)) => left.cmp(right),
+            (SortValue::Str(left), SortValue::Str(right)) => left.cmp(right),
+            // Strings sort after all the other types.
+            (SortValue::Str(_), _) => Ordering::Greater,
             // We half the logic by making sure we keep
             // the "stronger" type on the left.
             (SortValue::U64(left), SortValue::I64(right)) => {
-                if left > i64::MAX as u64 {
+                if *left > i64::MAX as u64 {
                     return Ordering::Greater;
                 }
-                (left as i64).cmp(&right)
+                (*left as i64).cmp(right)
             }
-            (SortValue::F64(left), SortValue::F64(right)) => left.total_cmp(&right),
-            (SortValue::F64(left), SortValue::U64(right)) => left.total_cmp(&(right as f64)),
-            (SortValue::F64(left), SortValue::I64(right)) => left.total_cmp(&(right as f64)),
-            (SortValue::Boolean(left), right) => SortValue::U64(left as u64).cmp(&right),
-            (left, right) => right.cmp(&left).reverse(),
+            (SortValue::F64(left), SortValue::F64(right)) => left.total_cmp(right),
+            (SortValue::F64(left), SortValue::U64(right)) => left.total_cmp(&(*right as f64)),
+            (SortValue::F64(left), SortValue::I64(right)) => left.total_cmp(&(*right as f64)),
+            (SortValue::Boolean(left), right) => SortValue::U64(*left as u64).cmp(right),
+            (left, right) => right.cmp(left).reverse(),
         }
     }
 }
@@ -181,6 +184,9 @@ impl std::hash::Hash for SortValue {
             SortValue::Boolean(b) => {
                 b.hash(state);
             }
+            SortValue::Str(s) => {
+                s.hash(state);
+            }
         }
     }
 }
@@ -191,13 +197,12 @@ impl SortValue {
     /// For number, we prefer to represent them, in order, as i64, then as u64 and finally as f64.
     pub fn normalize(&self) -> Self {
         match self {
-            SortValue::I64(_) => *self,
-            SortValue::Boolean(_) => *self,
+            SortValue::I64(_) | SortValue::Boolean(_) | SortValue::Str(_) => self.clone(),
             SortValue::U64(number) => {
                 if let Ok(number) = (*number).try_into() {
                     SortValue::I64(number)
                 } else {
-                    *self
+                    self.clone()
                 }
             }
             SortValue::F64(number) => {
@@ -210,7 +215,7 @@ impl SortValue {
                         return SortValue::U64(number as u64);
                     }
                 }
-                *self
+                self.clone()
             }
         }
     }
@@ -219,10 +224,33 @@ impl SortValue {
 impl PartialHit {
     /// Helper to get access to the 1st sort value
     pub fn sort_value(&self) -> Option<SortValue> {
-        if let Some(sort_value) = self.sort_value {
-            sort_value.sort_value
+        self.sort_value
+            .as_ref()
+            .and_then(|sort_value| sort_value.sort_value.clone())
+    }
+
+    /// Iterates over the values of all the sorting keys, in order.
+    pub fn sort_by_values(&self) -> impl Iterator<Item = Option<&SortByValue>> {
+        [self.sort_value.as_ref(), self.sort_value2.as_ref()]
+            .into_iter()
+            .chain(self.extra_sort_values.iter().map(Some))
+    }
+
+    /// Iterates mutably over the values of all the sorting keys, in order.
+    pub fn sort_by_values_mut(&mut self) -> impl Iterator<Item = Option<&mut SortByValue>> {
+        [self.sort_value.as_mut(), self.sort_value2.as_mut()]
+            .into_iter()
+            .chain(self.extra_sort_values.iter_mut().map(Some))
+    }
+
+    /// Appends the value of the next sorting key.
+    pub fn push_sort_value(&mut self, sort_value: SortByValue) {
+        if self.sort_value.is_none() {
+            self.sort_value = Some(sort_value);
+        } else if self.sort_value2.is_none() {
+            self.sort_value2 = Some(sort_value);
         } else {
-            None
+            self.extra_sort_values.push(sort_value);
         }
     }
 }
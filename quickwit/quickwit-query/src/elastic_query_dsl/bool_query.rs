@@ -34,16 +34,16 @@ use crate::query_ast::{self, QueryAst};
 pub struct BoolQuery {
     #[serde_as(deserialize_as = "DefaultOnNull<OneOrMany<_, PreferMany>>")]
     #[serde(default)]
-    must: Vec<ElasticQueryDslInner>,
+    pub(crate) must: Vec<ElasticQueryDslInner>,
     #[serde_as(deserialize_as = "DefaultOnNull<OneOrMany<_, PreferMany>>")]
     #[serde(default)]
-    must_not: Vec<ElasticQueryDslInner>,
+    pub(crate) must_not: Vec<ElasticQueryDslInner>,
     #[serde_as(deserialize_as = "DefaultOnNull<OneOrMany<_, PreferMany>>")]
     #[serde(default)]
-    should: Vec<ElasticQueryDslInner>,
+    pub(crate) should: Vec<ElasticQueryDslInner>,
     #[serde_as(deserialize_as = "DefaultOnNull<OneOrMany<_, PreferMany>>")]
     #[serde(default)]
-    filter: Vec<ElasticQueryDslInner>,
+    pub(crate) filter: Vec<ElasticQueryDslInner>,
     #[serde(default)]
     pub boost: Option<NotNaNf32>,
     #[serde(default)]
@@ -0,0 +1,128 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::bail;
+use serde::Deserialize;
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Matches the documents matching the positive query, and multiplies the score of the ones also
+/// matching the negative query by `negative_boost`.
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BoostingQuery {
+    pub(crate) positive: Box<ElasticQueryDslInner>,
+    pub(crate) negative: Box<ElasticQueryDslInner>,
+    pub negative_boost: NotNaNf32,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+impl From<BoostingQuery> for ElasticQueryDslInner {
+    fn from(boosting_query: BoostingQuery) -> Self {
+        Self::Boosting(boosting_query)
+    }
+}
+
+impl ConvertibleToQueryAst for BoostingQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let negative_boost_f32: f32 = self.negative_boost.into();
+        if !(0.0..=1.0).contains(&negative_boost_f32) {
+            bail!("`negative_boost` must be between 0 and 1, got {negative_boost_f32}");
+        }
+        let positive_ast = self.positive.convert_to_query_ast()?;
+        let negative_ast = self.negative.convert_to_query_ast()?;
+        // Each matching document matches exactly one of the two clauses below. Filters do not
+        // contribute to the score, so the score of the second one is the score of the positive
+        // query, multiplied by `negative_boost`.
+        let not_negative_ast: QueryAst = query_ast::BoolQuery {
+            must: vec![positive_ast.clone()],
+            must_not: vec![negative_ast.clone()],
+            ..Default::default()
+        }
+        .into();
+        let negative_ast: QueryAst = query_ast::BoolQuery {
+            must: vec![positive_ast],
+            filter: vec![negative_ast],
+            ..Default::default()
+        }
+        .into();
+        let boosting_ast: QueryAst = query_ast::BoolQuery {
+            should: vec![
+                not_negative_ast,
+                negative_ast.boost(Some(self.negative_boost)),
+            ],
+            ..Default::default()
+        }
+        .into();
+        Ok(boosting_ast.boost(self.boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boosting_query() {
+        let boosting_query: BoostingQuery = serde_json::from_str(
+            r#"{
+                "positive": { "term": { "text": "apple" } },
+                "negative": { "term": { "text": "pie" } },
+                "negative_boost": 0.5
+            }"#,
+        )
+        .unwrap();
+        let QueryAst::Bool(bool_query) = boosting_query.convert_to_query_ast().unwrap() else {
+            panic!();
+        };
+        let [QueryAst::Bool(not_negative_query), QueryAst::Boost { underlying, boost }] =
+            &bool_query.should[..]
+        else {
+            panic!();
+        };
+        assert_eq!(not_negative_query.must.len(), 1);
+        assert_eq!(not_negative_query.must_not.len(), 1);
+        assert_eq!(*boost, NotNaNf32::try_from(0.5).unwrap());
+        let QueryAst::Bool(negative_query) = &**underlying else {
+            panic!();
+        };
+        assert_eq!(negative_query.must, not_negative_query.must);
+        assert_eq!(negative_query.filter, not_negative_query.must_not);
+    }
+
+    #[test]
+    fn test_boosting_query_invalid_negative_boost() {
+        let boosting_query: BoostingQuery = serde_json::from_str(
+            r#"{
+                "positive": { "match_all": {} },
+                "negative": { "term": { "text": "pie" } },
+                "negative_boost": 2.0
+            }"#,
+        )
+        .unwrap();
+        let error = boosting_query.convert_to_query_ast().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`negative_boost` must be between 0 and 1, got 2"
+        );
+    }
+}
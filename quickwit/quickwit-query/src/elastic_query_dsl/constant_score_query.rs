@@ -0,0 +1,84 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Matches the documents matching the filter, and gives them all a score equal to `boost`.
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ConstantScoreQuery {
+    pub(crate) filter: Box<ElasticQueryDslInner>,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+impl From<ConstantScoreQuery> for ElasticQueryDslInner {
+    fn from(constant_score_query: ConstantScoreQuery) -> Self {
+        Self::ConstantScore(constant_score_query)
+    }
+}
+
+impl ConvertibleToQueryAst for ConstantScoreQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let filter_ast = self.filter.convert_to_query_ast()?;
+        // Filters do not contribute to the score, which is then given by the match all query
+        // alone, i.e. 1.0 before boosting.
+        let constant_score_ast: QueryAst = query_ast::BoolQuery {
+            should: vec![QueryAst::MatchAll],
+            filter: vec![filter_ast],
+            ..Default::default()
+        }
+        .into();
+        Ok(constant_score_ast.boost(self.boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elastic_query_dsl::term_query::term_query_from_field_value;
+
+    #[test]
+    fn test_constant_score_query() {
+        let constant_score_query: ConstantScoreQuery = serde_json::from_str(
+            r#"{ "filter": { "term": { "user.id": "kimchy" } }, "boost": 1.2 }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            *constant_score_query.filter,
+            ElasticQueryDslInner::from(term_query_from_field_value("user.id", "kimchy"))
+        );
+        let QueryAst::Boost { underlying, boost } =
+            constant_score_query.convert_to_query_ast().unwrap()
+        else {
+            panic!();
+        };
+        assert_eq!(boost, NotNaNf32::try_from(1.2).unwrap());
+        let QueryAst::Bool(bool_query) = *underlying else {
+            panic!();
+        };
+        assert_eq!(bool_query.should, vec![QueryAst::MatchAll]);
+        assert_eq!(bool_query.filter.len(), 1);
+        assert!(bool_query.must.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Matches the documents matching any of the queries.
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DisMaxQuery {
+    pub(crate) queries: Vec<ElasticQueryDslInner>,
+    #[serde(default)]
+    pub tie_breaker: Option<NotNaNf32>,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+impl From<DisMaxQuery> for ElasticQueryDslInner {
+    fn from(dis_max_query: DisMaxQuery) -> Self {
+        Self::DisMax(dis_max_query)
+    }
+}
+
+impl ConvertibleToQueryAst for DisMaxQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let DisMaxQuery {
+            queries,
+            tie_breaker,
+            boost,
+        } = self;
+        if queries.is_empty() {
+            return Ok(QueryAst::MatchNone);
+        }
+        let disjuncts = queries
+            .into_iter()
+            .map(|query| query.convert_to_query_ast())
+            .collect::<anyhow::Result<Vec<QueryAst>>>()?;
+        let dis_max_ast: QueryAst = query_ast::DisMaxQuery {
+            disjuncts,
+            tie_breaker: tie_breaker.unwrap_or(NotNaNf32::ZERO),
+        }
+        .into();
+        Ok(dis_max_ast.boost(boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elastic_query_dsl::term_query::term_query_from_field_value;
+
+    #[test]
+    fn test_dis_max_query() {
+        let dis_max_query: DisMaxQuery = serde_json::from_str(
+            r#"{
+                "queries": [
+                    { "term": { "title": "quick" } },
+                    { "term": { "body": "quick" } }
+                ],
+                "tie_breaker": 0.7
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            dis_max_query.queries,
+            vec![
+                term_query_from_field_value("title", "quick").into(),
+                term_query_from_field_value("body", "quick").into(),
+            ]
+        );
+        let QueryAst::DisMax(dis_max_query) = dis_max_query.convert_to_query_ast().unwrap() else {
+            panic!();
+        };
+        assert_eq!(dis_max_query.disjuncts.len(), 2);
+        assert_eq!(dis_max_query.tie_breaker, NotNaNf32::try_from(0.7).unwrap());
+
+        let dis_max_query: DisMaxQuery = serde_json::from_str(r#"{ "queries": [] }"#).unwrap();
+        assert_eq!(
+            dis_max_query.convert_to_query_ast().unwrap(),
+            QueryAst::MatchNone
+        );
+    }
+}
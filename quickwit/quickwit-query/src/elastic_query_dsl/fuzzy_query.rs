@@ -0,0 +1,271 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use anyhow::bail;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use super::StringOrStructForSerialization;
+use crate::elastic_query_dsl::one_field_map::OneFieldMap;
+use crate::elastic_query_dsl::{
+    default_max_expansions, ConvertibleToQueryAst, ElasticQueryDslInner,
+};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst, MAX_FUZZY_DISTANCE};
+
+/// Matches the terms within a Levenshtein distance of a value. The value is not analyzed.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "OneFieldMap<StringOrStructForSerialization<FuzzyQueryParams>>")]
+pub(crate) struct FuzzyQuery {
+    pub field: String,
+    pub value: FuzzyQueryParams,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FuzzyQueryParams {
+    pub value: String,
+    #[serde(default)]
+    pub fuzziness: Fuzziness,
+    // Accepted for compatibility, all the matching terms are used.
+    #[serde(default = "default_max_expansions")]
+    pub max_expansions: u32,
+    #[serde(default)]
+    pub prefix_length: u32,
+    #[serde(default = "default_transpositions")]
+    pub transpositions: bool,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+fn default_transpositions() -> bool {
+    true
+}
+
+impl From<OneFieldMap<StringOrStructForSerialization<FuzzyQueryParams>>> for FuzzyQuery {
+    fn from(one_field_map: OneFieldMap<StringOrStructForSerialization<FuzzyQueryParams>>) -> Self {
+        FuzzyQuery {
+            field: one_field_map.field,
+            value: one_field_map.value.inner,
+        }
+    }
+}
+
+impl From<String> for FuzzyQueryParams {
+    fn from(value: String) -> FuzzyQueryParams {
+        FuzzyQueryParams {
+            value,
+            fuzziness: Fuzziness::default(),
+            max_expansions: default_max_expansions(),
+            prefix_length: 0,
+            transpositions: default_transpositions(),
+            boost: None,
+        }
+    }
+}
+
+impl From<FuzzyQuery> for ElasticQueryDslInner {
+    fn from(fuzzy_query: FuzzyQuery) -> Self {
+        Self::Fuzzy(fuzzy_query)
+    }
+}
+
+/// Maximum edit distance allowed, either explicit or depending on the length of the value.
+///
+/// See <https://www.elastic.co/guide/en/elasticsearch/reference/current/common-options.html#fuzziness>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fuzziness {
+    Distance(u8),
+    /// Values shorter than `low` must match exactly, values shorter than `high` are allowed one
+    /// edit, and longer values two edits.
+    Auto {
+        low: usize,
+        high: usize,
+    },
+}
+
+impl Default for Fuzziness {
+    fn default() -> Self {
+        Fuzziness::Auto { low: 3, high: 6 }
+    }
+}
+
+impl Fuzziness {
+    fn distance(&self, value: &str) -> u8 {
+        match *self {
+            Fuzziness::Distance(distance) => distance,
+            Fuzziness::Auto { low, high } => {
+                let num_chars = value.chars().count();
+                if num_chars < low {
+                    0
+                } else if num_chars < high {
+                    1
+                } else {
+                    2
+                }
+            }
+        }
+    }
+
+    fn parse(fuzziness_str: &str) -> Option<Fuzziness> {
+        if fuzziness_str == "AUTO" {
+            return Some(Fuzziness::default());
+        }
+        if let Some(bounds_str) = fuzziness_str.strip_prefix("AUTO:") {
+            let (low_str, high_str) = bounds_str.split_once(',')?;
+            let low = low_str.trim().parse().ok()?;
+            let high = high_str.trim().parse().ok()?;
+            return Some(Fuzziness::Auto { low, high });
+        }
+        fuzziness_str.parse().ok().map(Fuzziness::Distance)
+    }
+}
+
+struct FuzzinessVisitor;
+
+impl<'de> Visitor<'de> for FuzzinessVisitor {
+    type Value = Fuzziness;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an edit distance, `AUTO`, or `AUTO:<low>,<high>`")
+    }
+
+    fn visit_u64<E>(self, distance: u64) -> Result<Fuzziness, E>
+    where E: de::Error {
+        u8::try_from(distance)
+            .map(Fuzziness::Distance)
+            .map_err(|_| E::custom(format!("invalid fuzziness `{distance}`")))
+    }
+
+    fn visit_str<E>(self, fuzziness_str: &str) -> Result<Fuzziness, E>
+    where E: de::Error {
+        Fuzziness::parse(fuzziness_str)
+            .ok_or_else(|| E::custom(format!("invalid fuzziness `{fuzziness_str}`")))
+    }
+}
+
+impl<'de> Deserialize<'de> for Fuzziness {
+    fn deserialize<D>(deserializer: D) -> Result<Fuzziness, D::Error>
+    where D: Deserializer<'de> {
+        deserializer.deserialize_any(FuzzinessVisitor)
+    }
+}
+
+impl ConvertibleToQueryAst for FuzzyQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let FuzzyQueryParams {
+            value,
+            fuzziness,
+            max_expansions: _,
+            prefix_length,
+            transpositions,
+            boost,
+        } = self.value;
+        if prefix_length != 0 {
+            bail!("fuzzy queries with a non-zero `prefix_length` are not supported");
+        }
+        let distance = fuzziness.distance(&value);
+        if distance > MAX_FUZZY_DISTANCE {
+            bail!("fuzziness must be at most {MAX_FUZZY_DISTANCE}, got {distance}");
+        }
+        let fuzzy_ast: QueryAst = query_ast::FuzzyQuery {
+            field: self.field,
+            value,
+            distance,
+            transpositions,
+            lenient: false,
+        }
+        .into();
+        Ok(fuzzy_ast.boost(boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_fuzzy_query(fuzzy_query_json: &str) -> anyhow::Result<QueryAst> {
+        let fuzzy_query: FuzzyQuery = serde_json::from_str(fuzzy_query_json)?;
+        fuzzy_query.convert_to_query_ast()
+    }
+
+    fn fuzzy_query_ast(value: &str, distance: u8, transpositions: bool) -> QueryAst {
+        query_ast::FuzzyQuery {
+            field: "service".to_string(),
+            value: value.to_string(),
+            distance,
+            transpositions,
+            lenient: false,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_fuzzy_query() {
+        assert_eq!(
+            convert_fuzzy_query(r#"{ "service": "paymnets" }"#).unwrap(),
+            fuzzy_query_ast("paymnets", 2, true)
+        );
+        assert_eq!(
+            convert_fuzzy_query(r#"{ "service": { "value": "pay", "fuzziness": "AUTO" } }"#)
+                .unwrap(),
+            fuzzy_query_ast("pay", 1, true)
+        );
+        assert_eq!(
+            convert_fuzzy_query(r#"{ "service": { "value": "pay", "fuzziness": "AUTO:4,8" } }"#)
+                .unwrap(),
+            fuzzy_query_ast("pay", 0, true)
+        );
+        assert_eq!(
+            convert_fuzzy_query(
+                r#"{ "service": { "value": "paymnets", "fuzziness": 1, "transpositions": false } }"#
+            )
+            .unwrap(),
+            fuzzy_query_ast("paymnets", 1, false)
+        );
+        assert_eq!(
+            convert_fuzzy_query(r#"{ "service": { "value": "paymnets", "fuzziness": "1" } }"#)
+                .unwrap(),
+            fuzzy_query_ast("paymnets", 1, true)
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_query_errors() {
+        let error =
+            convert_fuzzy_query(r#"{ "service": { "value": "payments", "fuzziness": 3 } }"#)
+                .unwrap_err();
+        assert_eq!(error.to_string(), "fuzziness must be at most 2, got 3");
+
+        let error =
+            convert_fuzzy_query(r#"{ "service": { "value": "payments", "prefix_length": 2 } }"#)
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "fuzzy queries with a non-zero `prefix_length` are not supported"
+        );
+
+        let error =
+            convert_fuzzy_query(r#"{ "service": { "value": "payments", "fuzziness": "SOME" } }"#)
+                .unwrap_err();
+        assert!(error.to_string().contains("invalid fuzziness `SOME`"));
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::geo::GeoPoint;
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Matches the geo points located inside a bounding box.
+///
+/// Only the `top_left` / `bottom_right` form of the bounding box is supported.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "JsonMap<String, JsonValue>")]
+pub(crate) struct GeoBoundingBoxQuery {
+    pub field: String,
+    pub bounding_box: BoundingBox,
+    pub boost: Option<NotNaNf32>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BoundingBox {
+    pub top_left: GeoPoint,
+    pub bottom_right: GeoPoint,
+}
+
+/// Geo queries take the target field as a key of the query object, next to the query
+/// parameters. This removes the parameters listed in `param_keys` from the object and returns
+/// them along with the single remaining `(field, value)` entry.
+pub(crate) fn split_field_and_params(
+    query_name: &str,
+    mut object: JsonMap<String, JsonValue>,
+    param_keys: &[&str],
+) -> Result<(String, JsonValue, JsonMap<String, JsonValue>), String> {
+    let mut params = JsonMap::new();
+    for param_key in param_keys {
+        if let Some(param_value) = object.remove(*param_key) {
+            params.insert(param_key.to_string(), param_value);
+        }
+    }
+    let mut field_entries = object.into_iter();
+    let Some((field, value)) = field_entries.next() else {
+        return Err(format!("`{query_name}` query requires a field"));
+    };
+    if let Some((second_field, _)) = field_entries.next() {
+        return Err(format!(
+            "`{query_name}` query expects a single field, got several ({field}, {second_field}, \
+             ...)"
+        ));
+    }
+    Ok((field, value, params))
+}
+
+impl TryFrom<JsonMap<String, JsonValue>> for GeoBoundingBoxQuery {
+    type Error = String;
+
+    fn try_from(object: JsonMap<String, JsonValue>) -> Result<Self, String> {
+        let (field, value, params) =
+            split_field_and_params("geo_bounding_box", object, &["boost"])?;
+        let bounding_box: BoundingBox =
+            serde_json::from_value(value).map_err(|error| error.to_string())?;
+        let boost: Option<NotNaNf32> =
+            serde_json::from_value(params.get("boost").cloned().unwrap_or_default())
+                .map_err(|error| error.to_string())?;
+        Ok(GeoBoundingBoxQuery {
+            field,
+            bounding_box,
+            boost,
+        })
+    }
+}
+
+impl From<GeoBoundingBoxQuery> for ElasticQueryDslInner {
+    fn from(geo_bounding_box_query: GeoBoundingBoxQuery) -> Self {
+        Self::GeoBoundingBox(geo_bounding_box_query)
+    }
+}
+
+impl ConvertibleToQueryAst for GeoBoundingBoxQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let geo_bounding_box_ast: QueryAst = query_ast::GeoBoundingBoxQuery {
+            field: self.field,
+            top_left: self.bounding_box.top_left,
+            bottom_right: self.bounding_box.bottom_right,
+        }
+        .into();
+        Ok(geo_bounding_box_ast.boost(self.boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elastic_query_dsl::ElasticQueryDsl;
+
+    #[test]
+    fn test_geo_bounding_box_query() {
+        let query_dsl: ElasticQueryDsl = serde_json::from_str(
+            r#"{
+                "geo_bounding_box": {
+                    "pin.location": {
+                        "top_left": {"lat": 40.73, "lon": -74.1},
+                        "bottom_right": "40.01,-71.12"
+                    },
+                    "boost": 2.0
+                }
+            }"#,
+        )
+        .unwrap();
+        let query_ast = QueryAst::try_from(query_dsl).unwrap();
+        let expected_ast: QueryAst = query_ast::GeoBoundingBoxQuery {
+            field: "pin.location".to_string(),
+            top_left: GeoPoint::new(40.73, -74.1).unwrap(),
+            bottom_right: GeoPoint::new(40.01, -71.12).unwrap(),
+        }
+        .into();
+        assert_eq!(
+            query_ast,
+            expected_ast.boost(Some(NotNaNf32::try_from(2.0).unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_geo_bounding_box_query_errors() {
+        let error =
+            serde_json::from_str::<ElasticQueryDsl>(r#"{"geo_bounding_box": {"boost": 2.0}}"#)
+                .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("`geo_bounding_box` query requires a field"),
+            "{error}"
+        );
+        let error = serde_json::from_str::<ElasticQueryDsl>(
+            r#"{"geo_bounding_box": {"location": {"top": 40.73, "left": -74.1, "bottom": 40.01, "right": -71.12}}}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown field `top`"), "{error}");
+    }
+}
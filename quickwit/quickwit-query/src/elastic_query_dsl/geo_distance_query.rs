@@ -0,0 +1,148 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::elastic_query_dsl::geo_bounding_box_query::split_field_and_params;
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::geo::{parse_distance_meters, GeoPoint};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Matches the geo points located within a given distance of a point.
+///
+/// Distances are always computed on the sphere (`distance_type: arc`).
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(try_from = "JsonMap<String, JsonValue>")]
+pub(crate) struct GeoDistanceQuery {
+    pub field: String,
+    pub center: GeoPoint,
+    pub distance_meters: u64,
+    pub boost: Option<NotNaNf32>,
+}
+
+impl TryFrom<JsonMap<String, JsonValue>> for GeoDistanceQuery {
+    type Error = String;
+
+    fn try_from(object: JsonMap<String, JsonValue>) -> Result<Self, String> {
+        let (field, value, params) = split_field_and_params(
+            "geo_distance",
+            object,
+            &["distance", "distance_type", "boost"],
+        )?;
+        let center = GeoPoint::from_json(&value)?;
+        let distance_meters = match params.get("distance") {
+            Some(JsonValue::String(distance)) => parse_distance_meters(distance)?,
+            Some(JsonValue::Number(distance)) => distance
+                .as_f64()
+                .filter(|distance| *distance >= 0.0)
+                .ok_or_else(|| format!("invalid distance `{distance}`"))?,
+            Some(distance) => return Err(format!("invalid distance `{distance}`")),
+            None => return Err("`geo_distance` query requires a `distance`".to_string()),
+        };
+        match params.get("distance_type").and_then(JsonValue::as_str) {
+            None | Some("arc") => {}
+            Some(distance_type) => {
+                return Err(format!(
+                    "`distance_type` other than `arc` are not supported, got `{distance_type}`"
+                ))
+            }
+        }
+        let boost: Option<NotNaNf32> =
+            serde_json::from_value(params.get("boost").cloned().unwrap_or_default())
+                .map_err(|error| error.to_string())?;
+        Ok(GeoDistanceQuery {
+            field,
+            center,
+            distance_meters: distance_meters.round() as u64,
+            boost,
+        })
+    }
+}
+
+impl From<GeoDistanceQuery> for ElasticQueryDslInner {
+    fn from(geo_distance_query: GeoDistanceQuery) -> Self {
+        Self::GeoDistance(geo_distance_query)
+    }
+}
+
+impl ConvertibleToQueryAst for GeoDistanceQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let geo_distance_ast: QueryAst = query_ast::GeoDistanceQuery {
+            field: self.field,
+            center: self.center,
+            distance_meters: self.distance_meters,
+        }
+        .into();
+        Ok(geo_distance_ast.boost(self.boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elastic_query_dsl::ElasticQueryDsl;
+
+    #[test]
+    fn test_geo_distance_query() {
+        let query_dsl: ElasticQueryDsl = serde_json::from_str(
+            r#"{
+                "geo_distance": {
+                    "distance": "12km",
+                    "pin.location": [-70.0, 40.0]
+                }
+            }"#,
+        )
+        .unwrap();
+        let query_ast = QueryAst::try_from(query_dsl).unwrap();
+        let expected_ast: QueryAst = query_ast::GeoDistanceQuery {
+            field: "pin.location".to_string(),
+            center: GeoPoint::new(40.0, -70.0).unwrap(),
+            distance_meters: 12_000,
+        }
+        .into();
+        assert_eq!(query_ast, expected_ast);
+    }
+
+    #[test]
+    fn test_geo_distance_query_errors() {
+        for (query_json, expected_error) in [
+            (
+                r#"{"geo_distance": {"location": "40,-70"}}"#,
+                "`geo_distance` query requires a `distance`",
+            ),
+            (
+                r#"{"geo_distance": {"distance": "12 lightyears", "location": "40,-70"}}"#,
+                "unknown distance unit `lightyears`",
+            ),
+            (
+                r#"{"geo_distance": {"distance": 200, "distance_type": "plane", "location": "40,-70"}}"#,
+                "`distance_type` other than `arc` are not supported",
+            ),
+            (
+                r#"{"geo_distance": {"distance": 200, "location": "40,-70", "other": "40,-70"}}"#,
+                "expects a single field",
+            ),
+        ] {
+            let error = serde_json::from_str::<ElasticQueryDsl>(query_json).unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{error}");
+        }
+    }
+}
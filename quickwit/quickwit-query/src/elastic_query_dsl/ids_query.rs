@@ -0,0 +1,87 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+
+use serde::Deserialize;
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Quickwit documents do not have a built-in identifier: the `ids` query matches the documents
+/// by address, as returned in the `_id` of the hits.
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct IdsQuery {
+    values: Vec<String>,
+    #[serde(default)]
+    boost: Option<NotNaNf32>,
+}
+
+impl From<IdsQuery> for ElasticQueryDslInner {
+    fn from(ids_query: IdsQuery) -> Self {
+        Self::Ids(ids_query)
+    }
+}
+
+impl ConvertibleToQueryAst for IdsQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        if self.values.is_empty() {
+            return Ok(QueryAst::MatchNone);
+        }
+        let ids: BTreeSet<String> = self.values.into_iter().collect();
+        let ids_ast: QueryAst = query_ast::IdsQuery {
+            ids,
+            split_id: None,
+        }
+        .into();
+        Ok(ids_ast.boost(self.boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_query() {
+        let ids_query: IdsQuery =
+            serde_json::from_str(r#"{ "values": ["2", "1", "2"], "boost": 2.0 }"#).unwrap();
+        let QueryAst::Boost { underlying, boost } = ids_query.convert_to_query_ast().unwrap()
+        else {
+            panic!();
+        };
+        assert_eq!(boost, NotNaNf32::try_from(2.0).unwrap());
+        let QueryAst::Ids(ids_query) = *underlying else {
+            panic!();
+        };
+        assert_eq!(
+            ids_query.ids,
+            BTreeSet::from(["1".to_string(), "2".to_string()])
+        );
+        assert!(ids_query.split_id.is_none());
+
+        let ids_query: IdsQuery = serde_json::from_str(r#"{ "values": [] }"#).unwrap();
+        assert_eq!(
+            ids_query.convert_to_query_ast().unwrap(),
+            QueryAst::MatchNone
+        );
+    }
+}
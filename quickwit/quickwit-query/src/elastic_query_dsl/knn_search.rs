@@ -0,0 +1,143 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+use serde_with::formats::PreferMany;
+use serde_with::{serde_as, DefaultOnNull, OneOrMany};
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Top level `knn` section of an Elasticsearch search request.
+///
+/// # Unsupported features
+/// - `similarity` (minimum similarity threshold)
+/// - `inner_hits`
+/// - `query_vector_builder`
+#[serde_as]
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ElasticKnnSearch {
+    pub field: String,
+    pub query_vector: Vec<f32>,
+    pub k: u32,
+    /// The search is exact, so this parameter is ignored.
+    #[serde(default)]
+    pub num_candidates: Option<u32>,
+    #[serde_as(deserialize_as = "DefaultOnNull<OneOrMany<_, PreferMany>>")]
+    #[serde(default)]
+    pub(crate) filter: Vec<ElasticQueryDslInner>,
+    #[serde(default)]
+    pub(crate) boost: Option<NotNaNf32>,
+}
+
+impl TryFrom<ElasticKnnSearch> for QueryAst {
+    type Error = anyhow::Error;
+
+    fn try_from(knn_search: ElasticKnnSearch) -> anyhow::Result<Self> {
+        knn_search.convert_to_query_ast()
+    }
+}
+
+impl ConvertibleToQueryAst for ElasticKnnSearch {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let mut filter_asts = self
+            .filter
+            .into_iter()
+            .map(|filter| filter.convert_to_query_ast())
+            .collect::<anyhow::Result<Vec<QueryAst>>>()?;
+        let filter_ast_opt = match filter_asts.len() {
+            0 => None,
+            1 => filter_asts.pop(),
+            _ => Some(
+                query_ast::BoolQuery {
+                    filter: filter_asts,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        };
+        let knn_query_ast: QueryAst = query_ast::KnnQuery {
+            field: self.field,
+            query_vector: self.query_vector,
+            k: self.k,
+            similarity: None,
+            filter: filter_ast_opt.map(Box::new),
+        }
+        .into();
+        Ok(knn_query_ast.boost(self.boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_knn_search() {
+        let knn_search: ElasticKnnSearch = serde_json::from_str(
+            r#"{
+                "field": "embedding",
+                "query_vector": [0.5, 1.0],
+                "k": 5,
+                "num_candidates": 50,
+                "filter": { "term": { "color": "red" } }
+            }"#,
+        )
+        .unwrap();
+        let QueryAst::Knn(knn_query) = knn_search.convert_to_query_ast().unwrap() else {
+            panic!();
+        };
+        assert_eq!(knn_query.field, "embedding");
+        assert_eq!(knn_query.query_vector, [0.5, 1.0]);
+        assert_eq!(knn_query.k, 5);
+        assert!(knn_query.similarity.is_none());
+        assert!(matches!(*knn_query.filter.unwrap(), QueryAst::Term(_)));
+    }
+
+    #[test]
+    fn test_knn_search_with_boost_and_filters() {
+        let knn_search: ElasticKnnSearch = serde_json::from_str(
+            r#"{
+                "field": "embedding",
+                "query_vector": [0.5, 1.0],
+                "k": 5,
+                "boost": 0.5,
+                "filter": [
+                    { "term": { "color": "red" } },
+                    { "term": { "size": "xl" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let QueryAst::Boost { underlying, boost } = knn_search.convert_to_query_ast().unwrap()
+        else {
+            panic!();
+        };
+        assert_eq!(boost, NotNaNf32::try_from(0.5).unwrap());
+        let QueryAst::Knn(knn_query) = *underlying else {
+            panic!();
+        };
+        let QueryAst::Bool(filter_bool_query) = *knn_query.filter.unwrap() else {
+            panic!();
+        };
+        assert_eq!(filter_bool_query.filter.len(), 2);
+    }
+}
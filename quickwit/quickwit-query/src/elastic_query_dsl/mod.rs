@@ -20,20 +20,35 @@
 use serde::{Deserialize, Serialize};
 
 mod bool_query;
+mod boosting_query;
+mod constant_score_query;
+mod dis_max_query;
 mod exists_query;
+mod fuzzy_query;
+mod geo_bounding_box_query;
+mod geo_distance_query;
+mod ids_query;
+mod knn_search;
 mod match_bool_prefix;
 mod match_phrase_query;
 mod match_query;
 mod multi_match;
+mod nested_query;
 mod one_field_map;
 mod phrase_prefix_query;
+mod prefix_query;
 mod query_string_query;
 mod range_query;
+mod regexp_query;
+mod simple_query_string_query;
 mod string_or_struct;
 mod term_query;
 mod terms_query;
+mod visitor;
+mod wildcard_query;
 
 use bool_query::BoolQuery;
+pub use knn_search::ElasticKnnSearch;
 pub use one_field_map::OneFieldMap;
 use phrase_prefix_query::MatchPhrasePrefixQuery;
 pub(crate) use query_string_query::QueryStringQuery;
@@ -41,12 +56,24 @@ use range_query::RangeQuery;
 pub(crate) use string_or_struct::StringOrStructForSerialization;
 use term_query::TermQuery;
 
+use crate::elastic_query_dsl::boosting_query::BoostingQuery;
+use crate::elastic_query_dsl::constant_score_query::ConstantScoreQuery;
+use crate::elastic_query_dsl::dis_max_query::DisMaxQuery;
 use crate::elastic_query_dsl::exists_query::ExistsQuery;
+use crate::elastic_query_dsl::fuzzy_query::FuzzyQuery;
+use crate::elastic_query_dsl::geo_bounding_box_query::GeoBoundingBoxQuery;
+use crate::elastic_query_dsl::geo_distance_query::GeoDistanceQuery;
+use crate::elastic_query_dsl::ids_query::IdsQuery;
 use crate::elastic_query_dsl::match_bool_prefix::MatchBoolPrefixQuery;
 use crate::elastic_query_dsl::match_phrase_query::MatchPhraseQuery;
 use crate::elastic_query_dsl::match_query::MatchQuery;
 use crate::elastic_query_dsl::multi_match::MultiMatchQuery;
+use crate::elastic_query_dsl::nested_query::NestedQuery;
+use crate::elastic_query_dsl::prefix_query::PrefixQuery;
+use crate::elastic_query_dsl::regexp_query::RegexpQuery;
+use crate::elastic_query_dsl::simple_query_string_query::SimpleQueryStringQuery;
 use crate::elastic_query_dsl::terms_query::TermsQuery;
+use crate::elastic_query_dsl::wildcard_query::WildcardQuery;
 use crate::not_nan_f32::NotNaNf32;
 use crate::query_ast::QueryAst;
 
@@ -87,6 +114,18 @@ pub(crate) enum ElasticQueryDslInner {
     MultiMatch(MultiMatchQuery),
     Range(RangeQuery),
     Exists(ExistsQuery),
+    Prefix(PrefixQuery),
+    Wildcard(WildcardQuery),
+    Regexp(RegexpQuery),
+    Fuzzy(FuzzyQuery),
+    SimpleQueryString(SimpleQueryStringQuery),
+    Ids(IdsQuery),
+    ConstantScore(ConstantScoreQuery),
+    DisMax(DisMaxQuery),
+    Boosting(BoostingQuery),
+    GeoBoundingBox(GeoBoundingBoxQuery),
+    GeoDistance(GeoDistanceQuery),
+    Nested(NestedQuery),
 }
 
 #[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
@@ -134,6 +173,24 @@ impl ConvertibleToQueryAst for ElasticQueryDslInner {
             Self::Match(match_query) => match_query.convert_to_query_ast(),
             Self::Exists(exists_query) => exists_query.convert_to_query_ast(),
             Self::MultiMatch(multi_match_query) => multi_match_query.convert_to_query_ast(),
+            Self::Prefix(prefix_query) => prefix_query.convert_to_query_ast(),
+            Self::Wildcard(wildcard_query) => wildcard_query.convert_to_query_ast(),
+            Self::Regexp(regexp_query) => regexp_query.convert_to_query_ast(),
+            Self::Fuzzy(fuzzy_query) => fuzzy_query.convert_to_query_ast(),
+            Self::SimpleQueryString(simple_query_string_query) => {
+                simple_query_string_query.convert_to_query_ast()
+            }
+            Self::Ids(ids_query) => ids_query.convert_to_query_ast(),
+            Self::ConstantScore(constant_score_query) => {
+                constant_score_query.convert_to_query_ast()
+            }
+            Self::DisMax(dis_max_query) => dis_max_query.convert_to_query_ast(),
+            Self::Boosting(boosting_query) => boosting_query.convert_to_query_ast(),
+            Self::GeoBoundingBox(geo_bounding_box_query) => {
+                geo_bounding_box_query.convert_to_query_ast()
+            }
+            Self::GeoDistance(geo_distance_query) => geo_distance_query.convert_to_query_ast(),
+            Self::Nested(nested_query) => nested_query.convert_to_query_ast(),
         }
     }
 }
@@ -35,7 +35,7 @@ use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
 /// In Quickwit, we operate this expansion in generic way at the time of deserialization.
 #[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
 #[serde(try_from = "MultiMatchQueryForDeserialization")]
-pub struct MultiMatchQuery(Box<ElasticQueryDslInner>);
+pub struct MultiMatchQuery(pub(crate) Box<ElasticQueryDslInner>);
 
 #[serde_as]
 #[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
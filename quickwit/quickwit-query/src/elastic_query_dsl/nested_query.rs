@@ -0,0 +1,101 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, NestedScoreMode, QueryAst};
+
+/// Matches the documents having at least one object of the nested field `path` matching
+/// `query`.
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NestedQuery {
+    pub path: String,
+    pub(crate) query: Box<ElasticQueryDslInner>,
+    #[serde(default)]
+    pub score_mode: NestedScoreMode,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+impl From<NestedQuery> for ElasticQueryDslInner {
+    fn from(nested_query: NestedQuery) -> Self {
+        Self::Nested(nested_query)
+    }
+}
+
+impl ConvertibleToQueryAst for NestedQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let query_ast = self.query.convert_to_query_ast()?;
+        let nested_query_ast: QueryAst = query_ast::NestedQuery {
+            path: self.path,
+            query: Box::new(query_ast),
+            score_mode: self.score_mode,
+        }
+        .into();
+        Ok(nested_query_ast.boost(self.boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_query() {
+        let nested_query: NestedQuery = serde_json::from_str(
+            r#"{
+                "path": "items",
+                "query": {
+                    "bool": {
+                        "must": [
+                            { "term": { "items.sku": "A" } },
+                            { "range": { "items.qty": { "gte": 5 } } }
+                        ]
+                    }
+                },
+                "score_mode": "max"
+            }"#,
+        )
+        .unwrap();
+        let QueryAst::Nested(nested_query_ast) = nested_query.convert_to_query_ast().unwrap()
+        else {
+            panic!();
+        };
+        assert_eq!(nested_query_ast.path, "items");
+        assert_eq!(nested_query_ast.score_mode, NestedScoreMode::Max);
+        let QueryAst::Bool(bool_query) = *nested_query_ast.query else {
+            panic!();
+        };
+        assert_eq!(bool_query.must.len(), 2);
+    }
+
+    #[test]
+    fn test_nested_query_default_score_mode() {
+        let nested_query: NestedQuery =
+            serde_json::from_str(r#"{ "path": "items", "query": { "match_all": {} } }"#).unwrap();
+        assert_eq!(nested_query.score_mode, NestedScoreMode::Avg);
+        assert!(serde_json::from_str::<NestedQuery>(
+            r#"{ "path": "items", "query": { "match_all": {} }, "score_mode": "median" }"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+
+use super::StringOrStructForSerialization;
+use crate::elastic_query_dsl::one_field_map::OneFieldMap;
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Matches the terms starting with a given prefix. The prefix is not analyzed.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "OneFieldMap<StringOrStructForSerialization<PrefixQueryParams>>")]
+pub(crate) struct PrefixQuery {
+    pub field: String,
+    pub value: PrefixQueryParams,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PrefixQueryParams {
+    pub value: String,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+impl From<OneFieldMap<StringOrStructForSerialization<PrefixQueryParams>>> for PrefixQuery {
+    fn from(one_field_map: OneFieldMap<StringOrStructForSerialization<PrefixQueryParams>>) -> Self {
+        PrefixQuery {
+            field: one_field_map.field,
+            value: one_field_map.value.inner,
+        }
+    }
+}
+
+impl From<String> for PrefixQueryParams {
+    fn from(value: String) -> PrefixQueryParams {
+        PrefixQueryParams { value, boost: None }
+    }
+}
+
+impl From<PrefixQuery> for ElasticQueryDslInner {
+    fn from(prefix_query: PrefixQuery) -> Self {
+        Self::Prefix(prefix_query)
+    }
+}
+
+/// Escapes the characters interpreted by wildcard queries.
+fn escape_wildcard(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '*' | '?' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl ConvertibleToQueryAst for PrefixQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let PrefixQueryParams { value, boost } = self.value;
+        let wildcard_ast: QueryAst = query_ast::WildcardQuery {
+            field: self.field,
+            value: format!("{}*", escape_wildcard(&value)),
+            lenient: false,
+        }
+        .into();
+        Ok(wildcard_ast.boost(boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_query() {
+        let prefix_query: PrefixQuery =
+            serde_json::from_str(r#"{ "service": { "value": "api*", "boost": 2.0 } }"#).unwrap();
+        let query_ast = prefix_query.convert_to_query_ast().unwrap();
+        let QueryAst::Boost { underlying, .. } = query_ast else {
+            panic!("expected a boost query, got {query_ast:?}");
+        };
+        assert_eq!(
+            *underlying,
+            QueryAst::Wildcard(query_ast::WildcardQuery {
+                field: "service".to_string(),
+                value: r"api\**".to_string(),
+                lenient: false,
+            })
+        );
+
+        let prefix_query: PrefixQuery = serde_json::from_str(r#"{ "service": "api" }"#).unwrap();
+        assert_eq!(
+            prefix_query.convert_to_query_ast().unwrap(),
+            QueryAst::Wildcard(query_ast::WildcardQuery {
+                field: "service".to_string(),
+                value: "api*".to_string(),
+                lenient: false,
+            })
+        );
+    }
+}
@@ -0,0 +1,427 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::bail;
+use serde::Deserialize;
+
+use super::LeniencyBool;
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{QueryAst, UserInputQuery};
+use crate::BooleanOperand;
+
+/// The `simple_query_string` query uses a more limited syntax than `query_string`, and never
+/// fails on a malformed query: dangling operators and unbalanced parentheses are ignored.
+///
+/// It is translated into Quickwit's query language, and then handled like a `query_string`
+/// query.
+///
+/// # Unsupported features
+/// - flags other than `ALL`
+/// - per field boosts (`title^2`)
+#[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SimpleQueryStringQuery {
+    query: String,
+    #[serde(default)]
+    fields: Option<Vec<String>>,
+    #[serde(default)]
+    default_operator: BooleanOperand,
+    #[serde(default)]
+    flags: Option<String>,
+    #[serde(default)]
+    boost: Option<NotNaNf32>,
+    #[serde(default)]
+    lenient: LeniencyBool,
+}
+
+impl From<SimpleQueryStringQuery> for ElasticQueryDslInner {
+    fn from(simple_query_string_query: SimpleQueryStringQuery) -> Self {
+        Self::SimpleQueryString(simple_query_string_query)
+    }
+}
+
+impl ConvertibleToQueryAst for SimpleQueryStringQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        if let Some(flags) = &self.flags {
+            if !flags.eq_ignore_ascii_case("ALL") {
+                bail!("`simple_query_string` flags other than `ALL` are not supported");
+            }
+        }
+        if let Some(field) = self
+            .fields
+            .iter()
+            .flatten()
+            .find(|field| field.contains('^'))
+        {
+            bail!("per field boosts are not supported, got field `{field}`");
+        }
+        let user_text = translate_simple_query_string(&self.query);
+        if user_text.is_empty() {
+            return Ok(QueryAst::MatchNone);
+        }
+        let user_input_query: QueryAst = UserInputQuery {
+            user_text,
+            default_fields: self.fields,
+            default_operator: self.default_operator,
+            lenient: self.lenient,
+        }
+        .into();
+        Ok(user_input_query.boost(self.boost))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum SimpleQueryToken {
+    /// Characters of the term, each flagged as escaped or not.
+    Term(Vec<(char, bool)>),
+    /// A quoted phrase, followed by its optional slop or prefix suffix.
+    Phrase {
+        text: String,
+        suffix: String,
+    },
+    And,
+    Or,
+    Not,
+    OpenParen,
+    CloseParen,
+}
+
+impl SimpleQueryToken {
+    fn is_clause_start(&self) -> bool {
+        matches!(
+            self,
+            SimpleQueryToken::Term(_)
+                | SimpleQueryToken::Phrase { .. }
+                | SimpleQueryToken::Not
+                | SimpleQueryToken::OpenParen
+        )
+    }
+}
+
+fn tokenize_simple_query_string(query: &str) -> Vec<SimpleQueryToken> {
+    fn flush_term(term: &mut Vec<(char, bool)>, tokens: &mut Vec<SimpleQueryToken>) {
+        if !term.is_empty() {
+            tokens.push(SimpleQueryToken::Term(std::mem::take(term)));
+        }
+    }
+    let mut tokens = Vec::new();
+    let mut term: Vec<(char, bool)> = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped_char) = chars.next() {
+                    term.push((escaped_char, true));
+                }
+            }
+            '-' if term.is_empty() => tokens.push(SimpleQueryToken::Not),
+            '"' => {
+                flush_term(&mut term, &mut tokens);
+                let mut text = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            if let Some(escaped_char) = chars.next() {
+                                text.push(escaped_char);
+                            }
+                        }
+                        '"' => break,
+                        _ => text.push(c),
+                    }
+                }
+                let mut suffix = String::new();
+                if chars.next_if_eq(&'*').is_some() {
+                    suffix.push('*');
+                } else if chars.next_if_eq(&'~').is_some() {
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        suffix.push(digit);
+                    }
+                    if !suffix.is_empty() {
+                        suffix.insert(0, '~');
+                    }
+                }
+                tokens.push(SimpleQueryToken::Phrase { text, suffix });
+            }
+            '|' | '+' | '(' | ')' => {
+                flush_term(&mut term, &mut tokens);
+                let token = match c {
+                    '|' => SimpleQueryToken::Or,
+                    '+' => SimpleQueryToken::And,
+                    '(' => SimpleQueryToken::OpenParen,
+                    _ => SimpleQueryToken::CloseParen,
+                };
+                tokens.push(token);
+            }
+            _ if c.is_whitespace() => flush_term(&mut term, &mut tokens),
+            _ => term.push((c, false)),
+        }
+    }
+    flush_term(&mut term, &mut tokens);
+    tokens
+}
+
+/// Characters with a special meaning in Quickwit's query language.
+fn is_special_char(c: char) -> bool {
+    matches!(
+        c,
+        '+' | '^' | '`' | ':' | '{' | '}' | '"' | '[' | ']' | '(' | ')' | '~' | '!' | '\\' | '*'
+    ) || c.is_whitespace()
+}
+
+fn translate_term(term_chars: &[(char, bool)]) -> String {
+    let mut term_chars = term_chars;
+    let mut suffix = String::new();
+    if let [prefix_chars @ .., ('*', false)] = term_chars {
+        term_chars = prefix_chars;
+        suffix.push('*');
+    } else if let Some(tilde_pos) = term_chars
+        .iter()
+        .rposition(|&term_char| term_char == ('~', false))
+    {
+        let distance_chars = &term_chars[tilde_pos + 1..];
+        if distance_chars
+            .iter()
+            .all(|&(c, escaped)| c.is_ascii_digit() && !escaped)
+        {
+            suffix.push('~');
+            suffix.extend(distance_chars.iter().map(|(c, _)| c));
+            term_chars = &term_chars[..tilde_pos];
+        }
+    }
+    let text: String = term_chars.iter().map(|(c, _)| c).collect();
+    // Those would otherwise be interpreted as operators or as a regex.
+    let needs_quoting = matches!(text.as_str(), "AND" | "OR" | "NOT" | "IN")
+        || (text.len() > 1 && text.starts_with('/') && text.ends_with('/'));
+    if needs_quoting {
+        // A suffix on a phrase is a prefix or a slop, not a fuzzy distance.
+        if suffix.starts_with('~') {
+            suffix.clear();
+        }
+        return format!("\"{text}\"{suffix}");
+    }
+    let mut translated = String::with_capacity(text.len() + suffix.len());
+    for c in text.chars() {
+        if is_special_char(c) {
+            translated.push('\\');
+        }
+        translated.push(c);
+    }
+    translated.push_str(&suffix);
+    translated
+}
+
+fn translate_phrase(text: &str, suffix: &str) -> String {
+    let mut translated = String::with_capacity(text.len() + suffix.len() + 2);
+    translated.push('"');
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            translated.push('\\');
+        }
+        translated.push(c);
+    }
+    translated.push('"');
+    translated.push_str(suffix);
+    translated
+}
+
+fn ends_with_clause(parts: &[String]) -> bool {
+    !matches!(
+        parts.last().map(String::as_str),
+        None | Some("(" | "-(" | "AND" | "OR")
+    )
+}
+
+fn join_parts(parts: &[String]) -> String {
+    let mut user_text = String::new();
+    for (part_ord, part) in parts.iter().enumerate() {
+        let previous_opens_group = part_ord > 0 && parts[part_ord - 1].ends_with('(');
+        if part_ord > 0 && !previous_opens_group && part != ")" {
+            user_text.push(' ');
+        }
+        user_text.push_str(part);
+    }
+    user_text
+}
+
+/// Translates a `simple_query_string` query into Quickwit's query language.
+///
+/// Returns an empty string if the query does not contain any clause.
+fn translate_simple_query_string(query: &str) -> String {
+    let tokens = tokenize_simple_query_string(query);
+    let mut parts: Vec<String> = Vec::with_capacity(tokens.len());
+    let mut negate_next = false;
+    let mut depth = 0;
+
+    for (token_ord, token) in tokens.iter().enumerate() {
+        let next_is_clause_start = tokens
+            .get(token_ord + 1)
+            .map(SimpleQueryToken::is_clause_start)
+            .unwrap_or(false);
+        let clause = match token {
+            SimpleQueryToken::Term(term_chars) => translate_term(term_chars),
+            SimpleQueryToken::Phrase { text, suffix } => translate_phrase(text, suffix),
+            SimpleQueryToken::And | SimpleQueryToken::Or => {
+                if ends_with_clause(&parts) && next_is_clause_start {
+                    let operator = if *token == SimpleQueryToken::And {
+                        "AND"
+                    } else {
+                        "OR"
+                    };
+                    parts.push(operator.to_string());
+                }
+                continue;
+            }
+            SimpleQueryToken::Not => {
+                negate_next = next_is_clause_start;
+                continue;
+            }
+            SimpleQueryToken::OpenParen => {
+                let open_paren = if std::mem::take(&mut negate_next) {
+                    "-("
+                } else {
+                    "("
+                };
+                parts.push(open_paren.to_string());
+                depth += 1;
+                continue;
+            }
+            SimpleQueryToken::CloseParen => {
+                if depth > 0 {
+                    close_group(&mut parts);
+                    depth -= 1;
+                }
+                continue;
+            }
+        };
+        if std::mem::take(&mut negate_next) {
+            parts.push(format!("-{clause}"));
+        } else {
+            parts.push(clause);
+        }
+    }
+    for _ in 0..depth {
+        close_group(&mut parts);
+    }
+    join_parts(&parts)
+}
+
+/// Closes the innermost group, dropping it if it is empty.
+fn close_group(parts: &mut Vec<String>) {
+    if matches!(parts.last().map(String::as_str), Some("AND" | "OR")) {
+        parts.pop();
+    }
+    if matches!(parts.last().map(String::as_str), Some("(" | "-(")) {
+        parts.pop();
+        // The group may have been preceded by an operator, which is now dangling.
+        if matches!(parts.last().map(String::as_str), Some("AND" | "OR")) {
+            parts.pop();
+        }
+    } else {
+        parts.push(")".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_simple_query_string() {
+        for (simple_query, expected_user_text) in [
+            ("", ""),
+            ("hello world", "hello world"),
+            ("hello | world", "hello OR world"),
+            ("hello + world", "hello AND world"),
+            ("hello +world -bye", "hello AND world -bye"),
+            ("\"hello world\"~2 | wor*", "\"hello world\"~2 OR wor*"),
+            ("\"hello wor\"*", "\"hello wor\"*"),
+            ("helo~1 helo~", "helo~1 helo~"),
+            ("-(hello | world) bye", "-(hello OR world) bye"),
+            ("(hello | world", "(hello OR world)"),
+            ("hello) | world", "hello OR world"),
+            ("| hello + + world |", "hello AND world"),
+            ("() hello", "hello"),
+            ("hello | ()", "hello"),
+            ("(hello | -)", "(hello)"),
+            ("e-mail foo:bar [1]", "e-mail foo\\:bar \\[1\\]"),
+            ("\\+1 \\*", "\\+1 \\*"),
+            ("cats AND dogs", "cats \"AND\" dogs"),
+            ("/path/", "\"/path/\""),
+            ("\"say \\\"hi\\\"\"", "\"say \\\"hi\\\"\""),
+            ("*", "*"),
+        ] {
+            assert_eq!(
+                translate_simple_query_string(simple_query),
+                expected_user_text,
+                "{simple_query}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_simple_query_string_query() {
+        let simple_query_string_query: SimpleQueryStringQuery = serde_json::from_str(
+            r#"{ "query": "hello | world", "fields": ["title"], "default_operator": "AND" }"#,
+        )
+        .unwrap();
+        let QueryAst::UserInput(user_input_query) =
+            simple_query_string_query.convert_to_query_ast().unwrap()
+        else {
+            panic!();
+        };
+        assert_eq!(user_input_query.user_text, "hello OR world");
+        assert_eq!(
+            user_input_query.default_fields,
+            Some(vec!["title".to_string()])
+        );
+        assert_eq!(user_input_query.default_operator, BooleanOperand::And);
+
+        let simple_query_string_query: SimpleQueryStringQuery =
+            serde_json::from_str(r#"{ "query": " | " }"#).unwrap();
+        assert_eq!(
+            simple_query_string_query.convert_to_query_ast().unwrap(),
+            QueryAst::MatchNone
+        );
+    }
+
+    #[test]
+    fn test_simple_query_string_query_errors() {
+        let simple_query_string_query: SimpleQueryStringQuery =
+            serde_json::from_str(r#"{ "query": "hello", "flags": "OR|AND" }"#).unwrap();
+        let error = simple_query_string_query
+            .convert_to_query_ast()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`simple_query_string` flags other than `ALL` are not supported"
+        );
+
+        let simple_query_string_query: SimpleQueryStringQuery =
+            serde_json::from_str(r#"{ "query": "hello", "fields": ["title^2"] }"#).unwrap();
+        let error = simple_query_string_query
+            .convert_to_query_ast()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "per field boosts are not supported, got field `title^2`"
+        );
+    }
+}
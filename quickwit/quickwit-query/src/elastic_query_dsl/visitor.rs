@@ -17,26 +17,89 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use crate::match_all::MatchAllQuery;
-use crate::match_none::MatchNoneQuery;
-use crate::query_string_query::QueryStringQuery;
-use crate::range_query::RangeQuery;
-use crate::term_query::TermQuery;
-use crate::QueryDsl;
-
-pub trait QueryDslVisitor<'a> {
+use crate::elastic_query_dsl::bool_query::BoolQuery;
+use crate::elastic_query_dsl::boosting_query::BoostingQuery;
+use crate::elastic_query_dsl::constant_score_query::ConstantScoreQuery;
+use crate::elastic_query_dsl::dis_max_query::DisMaxQuery;
+use crate::elastic_query_dsl::exists_query::ExistsQuery;
+use crate::elastic_query_dsl::fuzzy_query::FuzzyQuery;
+use crate::elastic_query_dsl::geo_bounding_box_query::GeoBoundingBoxQuery;
+use crate::elastic_query_dsl::geo_distance_query::GeoDistanceQuery;
+use crate::elastic_query_dsl::ids_query::IdsQuery;
+use crate::elastic_query_dsl::match_bool_prefix::MatchBoolPrefixQuery;
+use crate::elastic_query_dsl::match_phrase_query::MatchPhraseQuery;
+use crate::elastic_query_dsl::match_query::MatchQuery;
+use crate::elastic_query_dsl::multi_match::MultiMatchQuery;
+use crate::elastic_query_dsl::nested_query::NestedQuery;
+use crate::elastic_query_dsl::phrase_prefix_query::MatchPhrasePrefixQuery;
+use crate::elastic_query_dsl::prefix_query::PrefixQuery;
+use crate::elastic_query_dsl::range_query::RangeQuery;
+use crate::elastic_query_dsl::regexp_query::RegexpQuery;
+use crate::elastic_query_dsl::simple_query_string_query::SimpleQueryStringQuery;
+use crate::elastic_query_dsl::term_query::TermQuery;
+use crate::elastic_query_dsl::terms_query::TermsQuery;
+use crate::elastic_query_dsl::wildcard_query::WildcardQuery;
+use crate::elastic_query_dsl::{
+    ElasticQueryDslInner, MatchAllQuery, MatchNoneQuery, QueryStringQuery,
+};
+
+/// Walks an Elasticsearch query DSL tree.
+///
+/// The default implementations of compound queries visit their children, and the ones of leaf
+/// queries do nothing.
+#[allow(dead_code)]
+pub(crate) trait ElasticQueryDslVisitor<'a> {
     type Err;
 
-    fn visit(&mut self, query_dsl: &'a QueryDsl) -> Result<(), Self::Err> {
+    fn visit(&mut self, query_dsl: &'a ElasticQueryDslInner) -> Result<(), Self::Err> {
         match query_dsl {
-            QueryDsl::QueryString(query_string_query) => {
+            ElasticQueryDslInner::QueryString(query_string_query) => {
                 self.visit_query_string(query_string_query)
             }
-            QueryDsl::Bool(bool_query) => self.visit_bool_query(bool_query),
-            QueryDsl::Term(term_query) => self.visit_term(term_query),
-            QueryDsl::MatchAll(just_boost) => self.visit_match_all(just_boost),
-            QueryDsl::MatchNone(match_none) => self.visit_match_none(match_none),
-            QueryDsl::Range(range_query) => self.visit_range(range_query),
+            ElasticQueryDslInner::Bool(bool_query) => self.visit_bool(bool_query),
+            ElasticQueryDslInner::Term(term_query) => self.visit_term(term_query),
+            ElasticQueryDslInner::Terms(terms_query) => self.visit_terms(terms_query),
+            ElasticQueryDslInner::MatchAll(match_all_query) => {
+                self.visit_match_all(match_all_query)
+            }
+            ElasticQueryDslInner::MatchNone(match_none_query) => {
+                self.visit_match_none(match_none_query)
+            }
+            ElasticQueryDslInner::Match(match_query) => self.visit_match(match_query),
+            ElasticQueryDslInner::MatchBoolPrefix(match_bool_prefix_query) => {
+                self.visit_match_bool_prefix(match_bool_prefix_query)
+            }
+            ElasticQueryDslInner::MatchPhrase(match_phrase_query) => {
+                self.visit_match_phrase(match_phrase_query)
+            }
+            ElasticQueryDslInner::MatchPhrasePrefix(match_phrase_prefix_query) => {
+                self.visit_match_phrase_prefix(match_phrase_prefix_query)
+            }
+            ElasticQueryDslInner::MultiMatch(multi_match_query) => {
+                self.visit_multi_match(multi_match_query)
+            }
+            ElasticQueryDslInner::Range(range_query) => self.visit_range(range_query),
+            ElasticQueryDslInner::Exists(exists_query) => self.visit_exists(exists_query),
+            ElasticQueryDslInner::Prefix(prefix_query) => self.visit_prefix(prefix_query),
+            ElasticQueryDslInner::Wildcard(wildcard_query) => self.visit_wildcard(wildcard_query),
+            ElasticQueryDslInner::Regexp(regexp_query) => self.visit_regexp(regexp_query),
+            ElasticQueryDslInner::Fuzzy(fuzzy_query) => self.visit_fuzzy(fuzzy_query),
+            ElasticQueryDslInner::SimpleQueryString(simple_query_string_query) => {
+                self.visit_simple_query_string(simple_query_string_query)
+            }
+            ElasticQueryDslInner::Ids(ids_query) => self.visit_ids(ids_query),
+            ElasticQueryDslInner::ConstantScore(constant_score_query) => {
+                self.visit_constant_score(constant_score_query)
+            }
+            ElasticQueryDslInner::DisMax(dis_max_query) => self.visit_dis_max(dis_max_query),
+            ElasticQueryDslInner::Boosting(boosting_query) => self.visit_boosting(boosting_query),
+            ElasticQueryDslInner::GeoBoundingBox(geo_bounding_box_query) => {
+                self.visit_geo_bounding_box(geo_bounding_box_query)
+            }
+            ElasticQueryDslInner::GeoDistance(geo_distance_query) => {
+                self.visit_geo_distance(geo_distance_query)
+            }
+            ElasticQueryDslInner::Nested(nested_query) => self.visit_nested(nested_query),
         }
     }
 
@@ -47,15 +110,15 @@ pub trait QueryDslVisitor<'a> {
         Ok(())
     }
 
-    fn visit_bool_query(&mut self, bool_query: &'a BoolQuery) -> Result<(), Self::Err> {
-        for ast in bool_query
+    fn visit_bool(&mut self, bool_query: &'a BoolQuery) -> Result<(), Self::Err> {
+        for query_dsl in bool_query
             .must
             .iter()
             .chain(bool_query.should.iter())
             .chain(bool_query.must_not.iter())
             .chain(bool_query.filter.iter())
         {
-            self.visit(ast)?;
+            self.visit(query_dsl)?;
         }
         Ok(())
     }
@@ -64,15 +127,175 @@ pub trait QueryDslVisitor<'a> {
         Ok(())
     }
 
-    fn visit_match_all(&mut self, _match_all: &'a MatchAllQuery) -> Result<(), Self::Err> {
+    fn visit_terms(&mut self, _terms_query: &'a TermsQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_match_all(&mut self, _match_all_query: &'a MatchAllQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_match_none(&mut self, _match_none_query: &'a MatchNoneQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_match(&mut self, _match_query: &'a MatchQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_match_bool_prefix(
+        &mut self,
+        _match_bool_prefix_query: &'a MatchBoolPrefixQuery,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_match_phrase(
+        &mut self,
+        _match_phrase_query: &'a MatchPhraseQuery,
+    ) -> Result<(), Self::Err> {
         Ok(())
     }
 
-    fn visit_match_none(&mut self, _match_none: &'a MatchNoneQuery) -> Result<(), Self::Err> {
+    fn visit_match_phrase_prefix(
+        &mut self,
+        _match_phrase_prefix_query: &'a MatchPhrasePrefixQuery,
+    ) -> Result<(), Self::Err> {
         Ok(())
     }
 
+    /// Multi match queries are expanded at deserialization time: the expanded query is
+    /// visited.
+    fn visit_multi_match(
+        &mut self,
+        multi_match_query: &'a MultiMatchQuery,
+    ) -> Result<(), Self::Err> {
+        self.visit(&multi_match_query.0)
+    }
+
     fn visit_range(&mut self, _range_query: &'a RangeQuery) -> Result<(), Self::Err> {
         Ok(())
     }
+
+    fn visit_exists(&mut self, _exists_query: &'a ExistsQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_prefix(&mut self, _prefix_query: &'a PrefixQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_wildcard(&mut self, _wildcard_query: &'a WildcardQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_regexp(&mut self, _regexp_query: &'a RegexpQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_fuzzy(&mut self, _fuzzy_query: &'a FuzzyQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_simple_query_string(
+        &mut self,
+        _simple_query_string_query: &'a SimpleQueryStringQuery,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_ids(&mut self, _ids_query: &'a IdsQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_constant_score(
+        &mut self,
+        constant_score_query: &'a ConstantScoreQuery,
+    ) -> Result<(), Self::Err> {
+        self.visit(&constant_score_query.filter)
+    }
+
+    fn visit_dis_max(&mut self, dis_max_query: &'a DisMaxQuery) -> Result<(), Self::Err> {
+        for query_dsl in &dis_max_query.queries {
+            self.visit(query_dsl)?;
+        }
+        Ok(())
+    }
+
+    fn visit_boosting(&mut self, boosting_query: &'a BoostingQuery) -> Result<(), Self::Err> {
+        self.visit(&boosting_query.positive)?;
+        self.visit(&boosting_query.negative)
+    }
+
+    fn visit_geo_bounding_box(
+        &mut self,
+        _geo_bounding_box_query: &'a GeoBoundingBoxQuery,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_geo_distance(
+        &mut self,
+        _geo_distance_query: &'a GeoDistanceQuery,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_nested(&mut self, nested_query: &'a NestedQuery) -> Result<(), Self::Err> {
+        self.visit(&nested_query.query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct TermFieldCollector<'a> {
+        fields: Vec<&'a str>,
+    }
+
+    impl<'a> ElasticQueryDslVisitor<'a> for TermFieldCollector<'a> {
+        type Err = ();
+
+        fn visit_term(&mut self, term_query: &'a TermQuery) -> Result<(), ()> {
+            self.fields.push(&term_query.field);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_elastic_query_dsl_visitor() {
+        let query_dsl: ElasticQueryDslInner = serde_json::from_str(
+            r#"{
+                "bool": {
+                    "must": [
+                        { "constant_score": { "filter": { "term": { "service": "api" } } } },
+                        {
+                            "dis_max": {
+                                "queries": [
+                                    { "term": { "title": "quick" } },
+                                    { "match": { "body": "quick" } }
+                                ]
+                            }
+                        }
+                    ],
+                    "should": {
+                        "boosting": {
+                            "positive": { "term": { "level": "error" } },
+                            "negative": { "term": { "host": "test" } },
+                            "negative_boost": 0.5
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let mut term_field_collector = TermFieldCollector::default();
+        term_field_collector.visit(&query_dsl).unwrap();
+        assert_eq!(
+            term_field_collector.fields,
+            ["service", "title", "level", "host"]
+        );
+    }
 }
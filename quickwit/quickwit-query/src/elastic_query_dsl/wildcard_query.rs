@@ -0,0 +1,104 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+
+use super::StringOrStructForSerialization;
+use crate::elastic_query_dsl::one_field_map::OneFieldMap;
+use crate::elastic_query_dsl::{ConvertibleToQueryAst, ElasticQueryDslInner};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::{self, QueryAst};
+
+/// Matches the terms against a wildcard pattern. At the moment, only a single `*` wildcard at the
+/// end of the pattern is supported.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "OneFieldMap<StringOrStructForSerialization<WildcardQueryParams>>")]
+pub(crate) struct WildcardQuery {
+    pub field: String,
+    pub value: WildcardQueryParams,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WildcardQueryParams {
+    #[serde(alias = "wildcard")]
+    pub value: String,
+    #[serde(default)]
+    pub boost: Option<NotNaNf32>,
+}
+
+impl From<OneFieldMap<StringOrStructForSerialization<WildcardQueryParams>>> for WildcardQuery {
+    fn from(
+        one_field_map: OneFieldMap<StringOrStructForSerialization<WildcardQueryParams>>,
+    ) -> Self {
+        WildcardQuery {
+            field: one_field_map.field,
+            value: one_field_map.value.inner,
+        }
+    }
+}
+
+impl From<String> for WildcardQueryParams {
+    fn from(value: String) -> WildcardQueryParams {
+        WildcardQueryParams { value, boost: None }
+    }
+}
+
+impl From<WildcardQuery> for ElasticQueryDslInner {
+    fn from(wildcard_query: WildcardQuery) -> Self {
+        Self::Wildcard(wildcard_query)
+    }
+}
+
+impl ConvertibleToQueryAst for WildcardQuery {
+    fn convert_to_query_ast(self) -> anyhow::Result<QueryAst> {
+        let WildcardQueryParams { value, boost } = self.value;
+        let wildcard_ast: QueryAst = query_ast::WildcardQuery {
+            field: self.field,
+            value,
+            lenient: false,
+        }
+        .into();
+        Ok(wildcard_ast.boost(boost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_query() {
+        for wildcard_query_json in [
+            r#"{ "service": { "value": "api-*" } }"#,
+            r#"{ "service": { "wildcard": "api-*" } }"#,
+            r#"{ "service": "api-*" }"#,
+        ] {
+            let wildcard_query: WildcardQuery = serde_json::from_str(wildcard_query_json).unwrap();
+            assert_eq!(
+                wildcard_query.convert_to_query_ast().unwrap(),
+                QueryAst::Wildcard(query_ast::WildcardQuery {
+                    field: "service".to_string(),
+                    value: "api-*".to_string(),
+                    lenient: false,
+                })
+            );
+        }
+    }
+}
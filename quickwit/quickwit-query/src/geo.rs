@@ -0,0 +1,388 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Geo point helpers shared by the `geo_point` field type, the geo queries and the geo grid
+//! aggregations.
+//!
+//! A geo point is indexed as a single `u64` fast field value: the latitude is quantized in the
+//! upper 32 bits and the longitude in the lower 32 bits, which gives a precision of about a
+//! centimeter.
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Mean radius of the earth in meters, as used by Elasticsearch.
+const EARTH_MEAN_RADIUS_METERS: f64 = 6_371_008.7714;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Maximum precision (length) of a geohash cell key.
+pub const MAX_GEOHASH_PRECISION: u8 = 12;
+
+/// Maximum zoom level of a geotile cell key.
+pub const MAX_GEOTILE_PRECISION: u8 = 29;
+
+const QUANTIZATION_SCALE: f64 = u32::MAX as f64;
+
+/// A point on earth expressed in degrees.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+// Latitude and longitude are validated on construction and can never be NaN.
+impl Eq for GeoPoint {}
+
+/// The different representations of a geo point accepted in documents and queries. They match
+/// the ones accepted by Elasticsearch, except for geohashes and WKT.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GeoPointRepr {
+    Object { lat: f64, lon: f64 },
+    // GeoJSON order: `[lon, lat]`.
+    Array([f64; 2]),
+    // `"lat,lon"`
+    String(String),
+}
+
+impl<'de> Deserialize<'de> for GeoPoint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        match GeoPointRepr::deserialize(deserializer)? {
+            GeoPointRepr::Object { lat, lon } => GeoPoint::new(lat, lon),
+            GeoPointRepr::Array([lon, lat]) => GeoPoint::new(lat, lon),
+            GeoPointRepr::String(lat_lon) => lat_lon.parse(),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::str::FromStr for GeoPoint {
+    type Err = String;
+
+    fn from_str(lat_lon: &str) -> Result<GeoPoint, String> {
+        let invalid_geo_point = || format!("expected a geo point `lat,lon`, got `{lat_lon}`");
+        let (lat_str, lon_str) = lat_lon.split_once(',').ok_or_else(invalid_geo_point)?;
+        let lat: f64 = lat_str.trim().parse().map_err(|_| invalid_geo_point())?;
+        let lon: f64 = lon_str.trim().parse().map_err(|_| invalid_geo_point())?;
+        GeoPoint::new(lat, lon)
+    }
+}
+
+impl fmt::Display for GeoPoint {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{},{}", self.lat, self.lon)
+    }
+}
+
+impl GeoPoint {
+    /// Creates a new geo point, checking that the latitude and longitude are in range.
+    pub fn new(lat: f64, lon: f64) -> Result<GeoPoint, String> {
+        if !(-90.0..=90.0).contains(&lat) {
+            return Err(format!("latitude must be between -90 and 90, got `{lat}`"));
+        }
+        if !(-180.0..=180.0).contains(&lon) {
+            return Err(format!(
+                "longitude must be between -180 and 180, got `{lon}`"
+            ));
+        }
+        Ok(GeoPoint { lat, lon })
+    }
+
+    /// Parses a geo point from any of the JSON representations accepted in documents.
+    pub fn from_json(json_value: &serde_json::Value) -> Result<GeoPoint, String> {
+        GeoPoint::deserialize(json_value).map_err(|error| error.to_string())
+    }
+
+    /// Encodes the geo point into the `u64` stored in the fast field.
+    pub fn encode(&self) -> u64 {
+        let lat_bits = quantize(self.lat, 90.0, 180.0);
+        let lon_bits = quantize(self.lon, 180.0, 360.0);
+        ((lat_bits as u64) << 32) | lon_bits as u64
+    }
+
+    /// Decodes a geo point encoded with [`GeoPoint::encode`].
+    pub fn decode(encoded: u64) -> GeoPoint {
+        let lat = dequantize((encoded >> 32) as u32, 90.0, 180.0);
+        let lon = dequantize(encoded as u32, 180.0, 360.0);
+        GeoPoint { lat, lon }
+    }
+
+    /// Returns the great-circle distance in meters between two points (haversine formula).
+    pub fn distance_meters(&self, other: &GeoPoint) -> f64 {
+        let lat1 = self.lat.to_radians();
+        let lat2 = other.lat.to_radians();
+        let delta_lat = lat2 - lat1;
+        let delta_lon = (other.lon - self.lon).to_radians();
+        let h = (delta_lat / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * (delta_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_MEAN_RADIUS_METERS * h.sqrt().min(1.0).asin()
+    }
+
+    /// Returns the geohash of the cell of length `precision` containing the point.
+    ///
+    /// `precision` is clamped to `[1, MAX_GEOHASH_PRECISION]`.
+    pub fn geohash(&self, precision: u8) -> String {
+        geohash_cell_to_string(self.geohash_cell(precision), precision)
+    }
+
+    /// Returns the geohash cell of length `precision` containing the point, as the integer made
+    /// of the `5 * precision` interleaved longitude and latitude bits.
+    ///
+    /// `precision` is clamped to `[1, MAX_GEOHASH_PRECISION]`.
+    pub fn geohash_cell(&self, precision: u8) -> u64 {
+        let precision = precision.clamp(1, MAX_GEOHASH_PRECISION);
+        let mut lat_range = (-90.0f64, 90.0f64);
+        let mut lon_range = (-180.0f64, 180.0f64);
+        let mut cell = 0u64;
+        for bit_idx in 0..precision as usize * 5 {
+            let (range, value) = if bit_idx % 2 == 0 {
+                (&mut lon_range, self.lon)
+            } else {
+                (&mut lat_range, self.lat)
+            };
+            let mid = (range.0 + range.1) / 2.0;
+            cell <<= 1;
+            if value >= mid {
+                cell |= 1;
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+        }
+        cell
+    }
+
+    /// Returns the key `{zoom}/{x}/{y}` of the web map tile containing the point.
+    ///
+    /// `zoom` is clamped to `[0, MAX_GEOTILE_PRECISION]`.
+    pub fn geotile(&self, zoom: u8) -> String {
+        geotile_cell_to_string(self.geotile_cell(zoom), zoom)
+    }
+
+    /// Returns the web map tile containing the point, as `x << 32 | y`.
+    ///
+    /// `zoom` is clamped to `[0, MAX_GEOTILE_PRECISION]`.
+    pub fn geotile_cell(&self, zoom: u8) -> u64 {
+        let zoom = zoom.min(MAX_GEOTILE_PRECISION);
+        let num_tiles = 1u64 << zoom;
+        let max_tile = (num_tiles - 1) as f64;
+        let x = ((self.lon + 180.0) / 360.0 * num_tiles as f64).floor();
+        let lat_rad = self.lat.to_radians();
+        let y = ((1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / std::f64::consts::PI) / 2.0
+            * num_tiles as f64)
+            .floor();
+        // The poles are projected to infinity: we clamp them to the first and last tiles.
+        let y = if y.is_nan() {
+            if self.lat > 0.0 {
+                0.0
+            } else {
+                max_tile
+            }
+        } else {
+            y
+        };
+        ((x.clamp(0.0, max_tile) as u64) << 32) | y.clamp(0.0, max_tile) as u64
+    }
+}
+
+/// Formats a cell returned by [`GeoPoint::geohash_cell`] as a geohash.
+pub fn geohash_cell_to_string(cell: u64, precision: u8) -> String {
+    let precision = precision.clamp(1, MAX_GEOHASH_PRECISION);
+    (0..precision)
+        .rev()
+        .map(|char_idx| GEOHASH_ALPHABET[(cell >> (char_idx * 5)) as usize & 31] as char)
+        .collect()
+}
+
+/// Formats a cell returned by [`GeoPoint::geotile_cell`] as a `{zoom}/{x}/{y}` key.
+pub fn geotile_cell_to_string(cell: u64, zoom: u8) -> String {
+    let zoom = zoom.min(MAX_GEOTILE_PRECISION);
+    format!("{zoom}/{}/{}", cell >> 32, cell & u32::MAX as u64)
+}
+
+fn quantize(value: f64, offset: f64, span: f64) -> u32 {
+    ((value + offset) / span * QUANTIZATION_SCALE).round() as u32
+}
+
+fn dequantize(quantized: u32, offset: f64, span: f64) -> f64 {
+    quantized as f64 / QUANTIZATION_SCALE * span - offset
+}
+
+/// A rectangle defined by its top left and bottom right corners.
+///
+/// If the left longitude is greater than the right longitude, the box crosses the
+/// antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeoBoundingBox {
+    pub top_left: GeoPoint,
+    pub bottom_right: GeoPoint,
+}
+
+impl GeoBoundingBox {
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        if point.lat > self.top_left.lat || point.lat < self.bottom_right.lat {
+            return false;
+        }
+        if self.top_left.lon <= self.bottom_right.lon {
+            self.top_left.lon <= point.lon && point.lon <= self.bottom_right.lon
+        } else {
+            point.lon >= self.top_left.lon || point.lon <= self.bottom_right.lon
+        }
+    }
+}
+
+/// Parses an Elasticsearch distance such as `12km` or `200m` and returns it in meters.
+///
+/// A number without unit is interpreted as meters.
+pub fn parse_distance_meters(distance: &str) -> Result<f64, String> {
+    let distance = distance.trim();
+    let unit_start = distance
+        .find(|character: char| character.is_ascii_alphabetic())
+        .unwrap_or(distance.len());
+    let (value_str, unit) = distance.split_at(unit_start);
+    let value: f64 = value_str
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid distance `{distance}`"))?;
+    let meters_per_unit = match unit {
+        "" | "m" | "meters" => 1.0,
+        "km" | "kilometers" => 1_000.0,
+        "cm" | "centimeters" => 0.01,
+        "mm" | "millimeters" => 0.001,
+        "mi" | "miles" => 1_609.344,
+        "yd" | "yards" => 0.9144,
+        "ft" | "feet" => 0.3048,
+        "in" | "inch" => 0.0254,
+        "nmi" | "NM" | "nauticalmiles" => 1_852.0,
+        _ => return Err(format!("unknown distance unit `{unit}` in `{distance}`")),
+    };
+    if value < 0.0 || !value.is_finite() {
+        return Err(format!(
+            "distance must be a positive number, got `{distance}`"
+        ));
+    }
+    Ok(value * meters_per_unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geo_point_deserialize() {
+        let expected = GeoPoint {
+            lat: 48.85,
+            lon: 2.35,
+        };
+        for json in [
+            r#"{"lat": 48.85, "lon": 2.35}"#,
+            r#"[2.35, 48.85]"#,
+            r#""48.85, 2.35""#,
+        ] {
+            let geo_point: GeoPoint = serde_json::from_str(json).unwrap();
+            assert_eq!(geo_point, expected);
+        }
+        let error = serde_json::from_str::<GeoPoint>(r#"{"lat": 91.0, "lon": 2.35}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("latitude must be between -90 and 90"),
+            "{error}"
+        );
+        let error = GeoPoint::from_json(&serde_json::json!("48.85")).unwrap_err();
+        assert!(error.contains("expected a geo point `lat,lon`"), "{error}");
+    }
+
+    #[test]
+    fn test_geo_point_encode_decode() {
+        for (lat, lon) in [
+            (0.0, 0.0),
+            (48.8566, 2.3522),
+            (-33.8688, 151.2093),
+            (90.0, 180.0),
+            (-90.0, -180.0),
+        ] {
+            let geo_point = GeoPoint::new(lat, lon).unwrap();
+            let decoded = GeoPoint::decode(geo_point.encode());
+            assert!((decoded.lat - lat).abs() < 1e-7, "{decoded:?}");
+            assert!((decoded.lon - lon).abs() < 1e-7, "{decoded:?}");
+        }
+    }
+
+    #[test]
+    fn test_geo_point_distance() {
+        let paris = GeoPoint::new(48.8566, 2.3522).unwrap();
+        let london = GeoPoint::new(51.5074, -0.1278).unwrap();
+        let distance = paris.distance_meters(&london);
+        assert!((distance - 343_560.0).abs() < 1_000.0, "{distance}");
+        assert_eq!(paris.distance_meters(&paris), 0.0);
+    }
+
+    #[test]
+    fn test_geo_point_geohash() {
+        let geo_point = GeoPoint::new(57.64911, 10.40744).unwrap();
+        assert_eq!(geo_point.geohash(11), "u4pruydqqvj");
+        assert_eq!(geo_point.geohash(3), "u4p");
+        assert_eq!(geo_point.geohash(0), "u");
+    }
+
+    #[test]
+    fn test_geo_point_geotile() {
+        let geo_point = GeoPoint::new(48.8566, 2.3522).unwrap();
+        assert_eq!(geo_point.geotile(0), "0/0/0");
+        assert_eq!(geo_point.geotile(8), "8/129/88");
+        assert_eq!(GeoPoint::new(90.0, 0.0).unwrap().geotile(2), "2/2/0");
+        assert_eq!(GeoPoint::new(-90.0, 180.0).unwrap().geotile(2), "2/3/3");
+    }
+
+    #[test]
+    fn test_geo_bounding_box_contains() {
+        let bounding_box = GeoBoundingBox {
+            top_left: GeoPoint::new(50.0, 0.0).unwrap(),
+            bottom_right: GeoPoint::new(40.0, 10.0).unwrap(),
+        };
+        assert!(bounding_box.contains(&GeoPoint::new(48.8566, 2.3522).unwrap()));
+        assert!(!bounding_box.contains(&GeoPoint::new(51.5074, -0.1278).unwrap()));
+
+        let antimeridian_box = GeoBoundingBox {
+            top_left: GeoPoint::new(10.0, 170.0).unwrap(),
+            bottom_right: GeoPoint::new(-10.0, -170.0).unwrap(),
+        };
+        assert!(antimeridian_box.contains(&GeoPoint::new(0.0, 175.0).unwrap()));
+        assert!(antimeridian_box.contains(&GeoPoint::new(0.0, -175.0).unwrap()));
+        assert!(!antimeridian_box.contains(&GeoPoint::new(0.0, 0.0).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_distance_meters() {
+        assert_eq!(parse_distance_meters("12km").unwrap(), 12_000.0);
+        assert_eq!(parse_distance_meters("200").unwrap(), 200.0);
+        assert_eq!(parse_distance_meters("1.5 mi").unwrap(), 2_414.016);
+        assert_eq!(parse_distance_meters("1nmi").unwrap(), 1_852.0);
+        assert!(parse_distance_meters("12parsecs")
+            .unwrap_err()
+            .contains("unknown distance unit `parsecs`"));
+        assert!(parse_distance_meters("km")
+            .unwrap_err()
+            .contains("invalid distance"));
+    }
+}
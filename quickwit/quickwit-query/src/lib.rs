@@ -30,12 +30,15 @@
 
 mod elastic_query_dsl;
 mod error;
+pub mod geo;
 mod json_literal;
 mod not_nan_f32;
 pub mod query_ast;
+pub mod runtime_field;
 pub mod tokenizers;
+pub mod vector;
 
-pub use elastic_query_dsl::{ElasticQueryDsl, OneFieldMap};
+pub use elastic_query_dsl::{ElasticKnnSearch, ElasticQueryDsl, OneFieldMap};
 pub use error::InvalidQuery;
 pub use json_literal::{InterpretUserInput, JsonLiteral};
 pub(crate) use not_nan_f32::NotNaNf32;
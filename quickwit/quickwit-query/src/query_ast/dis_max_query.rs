@@ -0,0 +1,144 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use tantivy::query::DisjunctionMaxQuery as TantivyDisjunctionMaxQuery;
+use tantivy::schema::Schema as TantivySchema;
+
+use super::{BuildTantivyAst, QueryAst};
+use crate::not_nan_f32::NotNaNf32;
+use crate::query_ast::TantivyQueryAst;
+use crate::tokenizers::TokenizerManager;
+use crate::{InvalidQuery, TantivyQuery};
+
+/// Matches the documents matching any of the disjuncts.
+///
+/// The score of a document is the best score of the disjuncts it matches, plus the scores of the
+/// other matching disjuncts multiplied by the tie breaker.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct DisMaxQuery {
+    pub disjuncts: Vec<QueryAst>,
+    #[serde(default = "default_tie_breaker")]
+    pub tie_breaker: NotNaNf32,
+}
+
+fn default_tie_breaker() -> NotNaNf32 {
+    NotNaNf32::ZERO
+}
+
+impl From<DisMaxQuery> for QueryAst {
+    fn from(dis_max_query: DisMaxQuery) -> Self {
+        QueryAst::DisMax(dis_max_query)
+    }
+}
+
+impl BuildTantivyAst for DisMaxQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+        search_fields: &[String],
+        with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let tie_breaker: f32 = self.tie_breaker.into();
+        if !(0.0..=1.0).contains(&tie_breaker) {
+            return Err(InvalidQuery::SchemaError(format!(
+                "dis_max query `tie_breaker` must be between 0 and 1, got `{tie_breaker}`"
+            )));
+        }
+        if self.disjuncts.is_empty() {
+            return Ok(TantivyQueryAst::match_none());
+        }
+        let disjuncts = self
+            .disjuncts
+            .iter()
+            .map(|disjunct| {
+                let disjunct_ast = disjunct.build_tantivy_ast_call(
+                    schema,
+                    tokenizer_manager,
+                    search_fields,
+                    with_validation,
+                )?;
+                Ok(disjunct_ast.simplify().into())
+            })
+            .collect::<Result<Vec<Box<dyn TantivyQuery>>, InvalidQuery>>()?;
+        Ok(TantivyDisjunctionMaxQuery::with_tie_breaker(disjuncts, tie_breaker).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{Schema, FAST};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+
+    fn search_score(dis_max_query: DisMaxQuery) -> Result<Option<f32>, InvalidQuery> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        index_writer.add_document(doc!(id_field => 1u64)).unwrap();
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let query = QueryAst::from(dis_max_query).build_tantivy_query(
+            &schema,
+            &create_default_quickwit_tokenizer_manager(),
+            &[],
+            true,
+        )?;
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(1).order_by_score())
+            .unwrap();
+        Ok(top_docs.first().map(|(score, _doc_address)| *score))
+    }
+
+    #[test]
+    fn test_dis_max_query() {
+        let boosted_match_all = QueryAst::MatchAll.boost(Some(NotNaNf32::try_from(2.0).unwrap()));
+        let disjuncts = vec![boosted_match_all, QueryAst::MatchAll];
+
+        let dis_max_query = DisMaxQuery {
+            disjuncts: disjuncts.clone(),
+            tie_breaker: NotNaNf32::ZERO,
+        };
+        assert_eq!(search_score(dis_max_query).unwrap(), Some(2.0));
+
+        let dis_max_query = DisMaxQuery {
+            disjuncts,
+            tie_breaker: NotNaNf32::try_from(0.5).unwrap(),
+        };
+        assert_eq!(search_score(dis_max_query).unwrap(), Some(2.5));
+
+        let dis_max_query = DisMaxQuery {
+            disjuncts: Vec::new(),
+            tie_breaker: NotNaNf32::ZERO,
+        };
+        assert_eq!(search_score(dis_max_query).unwrap(), None);
+
+        let dis_max_query = DisMaxQuery {
+            disjuncts: vec![QueryAst::MatchAll],
+            tie_breaker: NotNaNf32::try_from(1.5).unwrap(),
+        };
+        search_score(dis_max_query).unwrap_err();
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tantivy::query::FuzzyTermQuery as TantivyFuzzyTermQuery;
+use tantivy::schema::{FieldType, Schema as TantivySchema};
+use tantivy::Term;
+
+use super::{BuildTantivyAst, QueryAst};
+use crate::query_ast::TantivyQueryAst;
+use crate::tokenizers::TokenizerManager;
+use crate::{find_field_or_hit_dynamic, InvalidQuery};
+
+/// Maximum Levenshtein distance supported by fuzzy queries.
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// A Fuzzy query matches the terms within a given Levenshtein distance of a value, allowing
+/// for instance to match 'bond' with a query like 'bnod'.
+///
+/// The value is normalized by the tokenizer of the field, and must produce a single term.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct FuzzyQuery {
+    pub field: String,
+    pub value: String,
+    /// Maximum number of single-character edits, at most [`MAX_FUZZY_DISTANCE`].
+    pub distance: u8,
+    /// Whether the transposition of two adjacent characters counts as a single edit.
+    pub transpositions: bool,
+    /// Support missing fields
+    pub lenient: bool,
+}
+
+impl From<FuzzyQuery> for QueryAst {
+    fn from(fuzzy_query: FuzzyQuery) -> Self {
+        Self::Fuzzy(fuzzy_query)
+    }
+}
+
+impl FuzzyQuery {
+    /// Returns the normalized term the Levenshtein automaton is built from.
+    pub fn extract_term(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+    ) -> Result<Term, InvalidQuery> {
+        let (field, field_entry, json_path) = find_field_or_hit_dynamic(&self.field, schema)?;
+        let (tokenizer_name, term_prefix) = match field_entry.field_type() {
+            FieldType::Str(text_options) => {
+                let text_field_indexing = text_options.get_indexing_options().ok_or_else(|| {
+                    InvalidQuery::SchemaError(format!(
+                        "field {} is not full-text searchable",
+                        field_entry.name()
+                    ))
+                })?;
+                (
+                    text_field_indexing.tokenizer(),
+                    Term::from_field_text(field, ""),
+                )
+            }
+            FieldType::JsonObject(json_options) => {
+                let text_field_indexing =
+                    json_options.get_text_indexing_options().ok_or_else(|| {
+                        InvalidQuery::SchemaError(format!(
+                            "field {} is not full-text searchable",
+                            field_entry.name()
+                        ))
+                    })?;
+                let mut term = Term::from_field_json_path(
+                    field,
+                    json_path,
+                    json_options.is_expand_dots_enabled(),
+                );
+                term.append_type_and_str("");
+                (text_field_indexing.tokenizer(), term)
+            }
+            _ => {
+                return Err(InvalidQuery::SchemaError(
+                    "trying to run a Fuzzy query on a non-text field".to_string(),
+                ))
+            }
+        };
+        let mut normalizer = tokenizer_manager
+            .get_normalizer(tokenizer_name)
+            .with_context(|| format!("no tokenizer named `{}` is registered", tokenizer_name))?;
+        let mut token_stream = normalizer.token_stream(&self.value);
+        let mut tokens = Vec::new();
+        token_stream.process(&mut |token| {
+            tokens.push(token.text.clone());
+        });
+        let [token] = &tokens[..] else {
+            return Err(anyhow::anyhow!(
+                "fuzzy query value `{}` must produce exactly one term",
+                self.value
+            )
+            .into());
+        };
+        let mut term = term_prefix;
+        term.append_bytes(token.as_bytes());
+        Ok(term)
+    }
+}
+
+impl BuildTantivyAst for FuzzyQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+        _search_fields: &[String],
+        _with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        if self.distance > MAX_FUZZY_DISTANCE {
+            return Err(anyhow::anyhow!(
+                "fuzzy query distance must be at most {MAX_FUZZY_DISTANCE}, got {}",
+                self.distance
+            )
+            .into());
+        }
+        let term = match self.extract_term(schema, tokenizer_manager) {
+            Ok(term) => term,
+            Err(InvalidQuery::FieldDoesNotExist { .. }) if self.lenient => {
+                return Ok(TantivyQueryAst::match_none())
+            }
+            Err(error) => return Err(error),
+        };
+        let fuzzy_query = TantivyFuzzyTermQuery::new(term, self.distance, self.transpositions);
+        Ok(fuzzy_query.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{Schema, Type, INDEXED, STRING, TEXT};
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+
+    fn test_schema() -> TantivySchema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("service", STRING);
+        schema_builder.add_text_field("body", TEXT);
+        schema_builder.add_json_field("attributes", TEXT);
+        schema_builder.add_u64_field("status", INDEXED);
+        schema_builder.build()
+    }
+
+    fn fuzzy_query(field: &str, value: &str) -> FuzzyQuery {
+        FuzzyQuery {
+            field: field.to_string(),
+            value: value.to_string(),
+            distance: 1,
+            transpositions: true,
+            lenient: false,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_query_extract_term() {
+        let schema = test_schema();
+        let tokenizer_manager = create_default_quickwit_tokenizer_manager();
+
+        let term = fuzzy_query("service", "Payments")
+            .extract_term(&schema, &tokenizer_manager)
+            .unwrap();
+        assert_eq!(term.value().as_str(), Some("Payments"));
+
+        let term = fuzzy_query("body", "Payments")
+            .extract_term(&schema, &tokenizer_manager)
+            .unwrap();
+        assert_eq!(term.value().as_str(), Some("payments"));
+
+        let term = fuzzy_query("attributes.service", "Payments")
+            .extract_term(&schema, &tokenizer_manager)
+            .unwrap();
+        assert_eq!(term.value().json_path_type(), Some(Type::Str));
+        assert!(term.serialized_value_bytes().ends_with(b"payments"));
+    }
+
+    #[test]
+    fn test_fuzzy_query_errors() {
+        let schema = test_schema();
+        let tokenizer_manager = create_default_quickwit_tokenizer_manager();
+        for (query, expected_error) in [
+            (
+                fuzzy_query("missing", "foo"),
+                "field does not exist: `missing`",
+            ),
+            (
+                fuzzy_query("status", "200"),
+                "trying to run a Fuzzy query on a non-text field",
+            ),
+            (
+                FuzzyQuery {
+                    distance: 3,
+                    ..fuzzy_query("body", "foo")
+                },
+                "fuzzy query distance must be at most 2, got 3",
+            ),
+        ] {
+            let error = query
+                .build_tantivy_ast_call(&schema, &tokenizer_manager, &[], true)
+                .unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{error}");
+        }
+        let lenient_fuzzy_query = FuzzyQuery {
+            lenient: true,
+            ..fuzzy_query("missing", "foo")
+        };
+        let tantivy_ast = lenient_fuzzy_query
+            .build_tantivy_ast_call(&schema, &tokenizer_manager, &[], true)
+            .unwrap();
+        assert_eq!(tantivy_ast, TantivyQueryAst::match_none());
+    }
+}
@@ -0,0 +1,309 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use tantivy::query::{
+    ConstScorer, EmptyScorer, EnableScoring, Explanation, Scorer, VecDocSet, Weight,
+};
+use tantivy::schema::{FieldType, Schema as TantivySchema};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError};
+
+use super::{BuildTantivyAst, QueryAst};
+use crate::geo::{GeoBoundingBox, GeoPoint};
+use crate::query_ast::TantivyQueryAst;
+use crate::tokenizers::TokenizerManager;
+use crate::{find_field_or_hit_dynamic, InvalidQuery};
+
+/// Matches the documents with a `geo_point` field located inside a bounding box.
+///
+/// If the left longitude of the box is greater than its right longitude, the box is considered
+/// to cross the antimeridian.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct GeoBoundingBoxQuery {
+    pub field: String,
+    pub top_left: GeoPoint,
+    pub bottom_right: GeoPoint,
+}
+
+impl From<GeoBoundingBoxQuery> for QueryAst {
+    fn from(geo_bounding_box_query: GeoBoundingBoxQuery) -> Self {
+        Self::GeoBoundingBox(geo_bounding_box_query)
+    }
+}
+
+impl BuildTantivyAst for GeoBoundingBoxQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        _tokenizer_manager: &TokenizerManager,
+        _search_fields: &[String],
+        _with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        if self.top_left.lat < self.bottom_right.lat {
+            return Err(InvalidQuery::SchemaError(format!(
+                "top left latitude `{}` must be greater than bottom right latitude `{}`",
+                self.top_left.lat, self.bottom_right.lat
+            )));
+        }
+        let field_name = check_geo_point_field(&self.field, schema)?;
+        let bounding_box = GeoBoundingBox {
+            top_left: self.top_left,
+            bottom_right: self.bottom_right,
+        };
+        Ok(GeoFilterQuery {
+            field_name,
+            filter: GeoFilter::BoundingBox(bounding_box),
+        }
+        .into())
+    }
+}
+
+/// Matches the documents with a `geo_point` field located within a given distance of a point.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct GeoDistanceQuery {
+    pub field: String,
+    pub center: GeoPoint,
+    pub distance_meters: u64,
+}
+
+impl From<GeoDistanceQuery> for QueryAst {
+    fn from(geo_distance_query: GeoDistanceQuery) -> Self {
+        Self::GeoDistance(geo_distance_query)
+    }
+}
+
+impl BuildTantivyAst for GeoDistanceQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        _tokenizer_manager: &TokenizerManager,
+        _search_fields: &[String],
+        _with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let field_name = check_geo_point_field(&self.field, schema)?;
+        Ok(GeoFilterQuery {
+            field_name,
+            filter: GeoFilter::Distance {
+                center: self.center,
+                distance_meters: self.distance_meters as f64,
+            },
+        }
+        .into())
+    }
+}
+
+/// Geo points are indexed as `u64` fast fields. The tantivy schema does not let us
+/// tell them apart from regular `u64` fields: the doc mapper checks the field is mapped as a
+/// `geo_point` field when validating the query.
+fn check_geo_point_field(field: &str, schema: &TantivySchema) -> Result<String, InvalidQuery> {
+    let (_field, field_entry, json_path) = find_field_or_hit_dynamic(field, schema)?;
+    match field_entry.field_type() {
+        FieldType::U64(options) if options.is_fast() && json_path.is_empty() => {
+            Ok(field_entry.name().to_string())
+        }
+        _ => Err(InvalidQuery::SchemaError(format!(
+            "field `{field}` is not a geo_point field"
+        ))),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum GeoFilter {
+    BoundingBox(GeoBoundingBox),
+    Distance {
+        center: GeoPoint,
+        distance_meters: f64,
+    },
+}
+
+impl GeoFilter {
+    fn matches(&self, encoded_geo_point: u64) -> bool {
+        let geo_point = GeoPoint::decode(encoded_geo_point);
+        match self {
+            GeoFilter::BoundingBox(bounding_box) => bounding_box.contains(&geo_point),
+            GeoFilter::Distance {
+                center,
+                distance_meters,
+            } => center.distance_meters(&geo_point) <= *distance_meters,
+        }
+    }
+}
+
+/// Tantivy query scanning the fast field column of a geo point field.
+///
+/// Every document matching the filter gets a constant score.
+#[derive(Clone, Debug)]
+struct GeoFilterQuery {
+    field_name: String,
+    filter: GeoFilter,
+}
+
+impl tantivy::query::Query for GeoFilterQuery {
+    fn weight(&self, _enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+impl Weight for GeoFilterQuery {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let Some(column) = reader.fast_fields().column_opt::<u64>(&self.field_name)? else {
+            return Ok(Box::new(EmptyScorer));
+        };
+        let matching_doc_ids: Vec<DocId> = (0..reader.max_doc())
+            .filter(|doc_id| {
+                column
+                    .values_for_doc(*doc_id)
+                    .any(|encoded_geo_point| self.filter.matches(encoded_geo_point))
+            })
+            .collect();
+        if matching_doc_ids.is_empty() {
+            return Ok(Box::new(EmptyScorer));
+        }
+        let doc_set = VecDocSet::from(matching_doc_ids);
+        Ok(Box::new(ConstScorer::new(doc_set, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) does not match"
+            )));
+        }
+        Ok(Explanation::new("GeoFilterQuery", 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::Count;
+    use tantivy::schema::{Schema, FAST, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+
+    fn count_matching_docs(query_ast: QueryAst) -> usize {
+        let mut schema_builder = Schema::builder();
+        let location = schema_builder.add_u64_field("location", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for (lat, lon) in [
+            (48.8566, 2.3522),   // Paris
+            (51.5074, -0.1278),  // London
+            (52.5200, 13.4050),  // Berlin
+            (-17.7134, 178.065), // Fiji
+        ] {
+            let geo_point = GeoPoint::new(lat, lon).unwrap();
+            index_writer
+                .add_document(doc!(location => geo_point.encode()))
+                .unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let query = query_ast
+            .build_tantivy_query(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap();
+        searcher.search(&query, &Count).unwrap()
+    }
+
+    #[test]
+    fn test_geo_bounding_box_query() {
+        let query_ast: QueryAst = GeoBoundingBoxQuery {
+            field: "location".to_string(),
+            top_left: GeoPoint::new(53.0, -1.0).unwrap(),
+            bottom_right: GeoPoint::new(48.0, 3.0).unwrap(),
+        }
+        .into();
+        assert_eq!(count_matching_docs(query_ast), 2);
+
+        let antimeridian_query_ast: QueryAst = GeoBoundingBoxQuery {
+            field: "location".to_string(),
+            top_left: GeoPoint::new(0.0, 170.0).unwrap(),
+            bottom_right: GeoPoint::new(-20.0, -170.0).unwrap(),
+        }
+        .into();
+        assert_eq!(count_matching_docs(antimeridian_query_ast), 1);
+    }
+
+    #[test]
+    fn test_geo_distance_query() {
+        let query_ast: QueryAst = GeoDistanceQuery {
+            field: "location".to_string(),
+            center: GeoPoint::new(48.8566, 2.3522).unwrap(),
+            distance_meters: 400_000,
+        }
+        .into();
+        assert_eq!(count_matching_docs(query_ast), 2);
+    }
+
+    #[test]
+    fn test_geo_query_errors() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("service", STRING);
+        let schema = schema_builder.build();
+        let query_ast: QueryAst = GeoDistanceQuery {
+            field: "service".to_string(),
+            center: GeoPoint::new(0.0, 0.0).unwrap(),
+            distance_meters: 1_000,
+        }
+        .into();
+        let error = query_ast
+            .build_tantivy_query(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("field `service` is not a geo_point field"),
+            "{error}"
+        );
+
+        let query_ast: QueryAst = GeoBoundingBoxQuery {
+            field: "service".to_string(),
+            top_left: GeoPoint::new(0.0, 0.0).unwrap(),
+            bottom_right: GeoPoint::new(10.0, 10.0).unwrap(),
+        }
+        .into();
+        let error = query_ast
+            .build_tantivy_query(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("must be greater than bottom right latitude"),
+            "{error}"
+        );
+    }
+}
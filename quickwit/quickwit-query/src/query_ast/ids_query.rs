@@ -0,0 +1,264 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tantivy::query::{
+    ConstScorer, EmptyScorer, EnableScoring, Explanation, Scorer, VecDocSet, Weight,
+};
+use tantivy::schema::Schema as TantivySchema;
+use tantivy::{DocId, DocSet, Score, SegmentId, SegmentReader, TantivyError};
+
+use super::{BuildTantivyAst, QueryAst, QueryAstTransformer};
+use crate::query_ast::TantivyQueryAst;
+use crate::tokenizers::TokenizerManager;
+use crate::InvalidQuery;
+
+/// Matches the documents with the given addresses.
+///
+/// Quickwit documents do not have a built-in identifier: they are identified by their address
+/// `{split_id}:{segment_ord}:{doc_id}`, the ordinals being formatted in hexadecimal, as in the
+/// `_shard_doc` sort values. Since addresses are local to a split, the query matches no
+/// document until the leaf search binds it to the split it searches.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct IdsQuery {
+    pub ids: BTreeSet<String>,
+    /// Split the query is run against, see [`bind_ids_queries_to_split`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_id: Option<String>,
+}
+
+impl From<IdsQuery> for QueryAst {
+    fn from(ids_query: IdsQuery) -> Self {
+        QueryAst::Ids(ids_query)
+    }
+}
+
+/// Parses a document address, returning `None` if the ID is not a Quickwit document address.
+fn parse_doc_address(id: &str) -> Option<(&str, u32, DocId)> {
+    let mut id_parts = id.splitn(3, ':');
+    let split_id = id_parts.next()?;
+    let segment_ord = u32::from_str_radix(id_parts.next()?, 16).ok()?;
+    let doc_id = u32::from_str_radix(id_parts.next()?, 16).ok()?;
+    Some((split_id, segment_ord, doc_id))
+}
+
+impl BuildTantivyAst for IdsQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        _schema: &TantivySchema,
+        _tokenizer_manager: &TokenizerManager,
+        _search_fields: &[String],
+        _with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let Some(split_id) = &self.split_id else {
+            return Ok(TantivyQueryAst::match_none());
+        };
+        let mut doc_ids_per_segment_ord: HashMap<u32, Vec<DocId>> = HashMap::new();
+
+        for id in &self.ids {
+            // IDs that are not document addresses cannot match any document.
+            let Some((id_split_id, segment_ord, doc_id)) = parse_doc_address(id) else {
+                continue;
+            };
+            if id_split_id == split_id {
+                doc_ids_per_segment_ord
+                    .entry(segment_ord)
+                    .or_default()
+                    .push(doc_id);
+            }
+        }
+        if doc_ids_per_segment_ord.is_empty() {
+            return Ok(TantivyQueryAst::match_none());
+        }
+        Ok(DocAddressesQuery {
+            doc_ids_per_segment_ord,
+        }
+        .into())
+    }
+}
+
+/// Binds the `ids` queries of a query AST to the split the leaf search is running against.
+pub fn bind_ids_queries_to_split(query_ast: QueryAst, split_id: &str) -> QueryAst {
+    let mut binder = IdsQueryBinder { split_id };
+    let Ok(Some(query_ast)) = binder.transform(query_ast) else {
+        return QueryAst::MatchNone;
+    };
+    query_ast
+}
+
+struct IdsQueryBinder<'a> {
+    split_id: &'a str,
+}
+
+impl QueryAstTransformer for IdsQueryBinder<'_> {
+    type Err = std::convert::Infallible;
+
+    fn transform_ids(&mut self, mut ids_query: IdsQuery) -> Result<Option<QueryAst>, Self::Err> {
+        ids_query.split_id = Some(self.split_id.to_string());
+        Ok(Some(ids_query.into()))
+    }
+}
+
+/// Tantivy query matching documents by segment ordinal and doc ID.
+///
+/// Every matching document gets a constant score.
+#[derive(Clone, Debug)]
+struct DocAddressesQuery {
+    doc_ids_per_segment_ord: HashMap<u32, Vec<DocId>>,
+}
+
+impl tantivy::query::Query for DocAddressesQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        let searcher = enable_scoring.searcher().ok_or_else(|| {
+            TantivyError::InvalidArgument(
+                "`ids` query requires a searcher to resolve segment ordinals".to_string(),
+            )
+        })?;
+        let doc_ids_per_segment_id = searcher
+            .segment_readers()
+            .iter()
+            .enumerate()
+            .filter_map(|(segment_ord, segment_reader)| {
+                let mut doc_ids = self
+                    .doc_ids_per_segment_ord
+                    .get(&(segment_ord as u32))?
+                    .clone();
+                doc_ids.retain(|doc_id| *doc_id < segment_reader.max_doc());
+                doc_ids.sort_unstable();
+                doc_ids.dedup();
+                Some((segment_reader.segment_id(), doc_ids))
+            })
+            .collect();
+        Ok(Box::new(DocAddressesWeight {
+            doc_ids_per_segment_id,
+        }))
+    }
+}
+
+struct DocAddressesWeight {
+    doc_ids_per_segment_id: HashMap<SegmentId, Vec<DocId>>,
+}
+
+impl Weight for DocAddressesWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        match self.doc_ids_per_segment_id.get(&reader.segment_id()) {
+            Some(doc_ids) if !doc_ids.is_empty() => {
+                let doc_set = VecDocSet::from(doc_ids.clone());
+                Ok(Box::new(ConstScorer::new(doc_set, boost)))
+            }
+            _ => Ok(Box::new(EmptyScorer)),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) does not match"
+            )));
+        }
+        Ok(Explanation::new("DocAddressesQuery", 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tantivy::collector::{Count, DocSetCollector};
+    use tantivy::schema::{Schema, FAST};
+    use tantivy::{doc, DocAddress, Index};
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+    use crate::query_ast::BoolQuery;
+
+    #[test]
+    fn test_parse_doc_address() {
+        assert_eq!(
+            parse_doc_address("split-1:00000001:0000000a"),
+            Some(("split-1", 1, 10))
+        );
+        assert_eq!(parse_doc_address("split-1:1"), None);
+        assert_eq!(parse_doc_address("split-1:1:foo"), None);
+        assert_eq!(parse_doc_address("42"), None);
+    }
+
+    #[test]
+    fn test_ids_query() {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for id in 0..4u64 {
+            index_writer.add_document(doc!(id_field => id)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        let ids_query: QueryAst = IdsQuery {
+            ids: BTreeSet::from([
+                "split-1:00000000:00000001".to_string(),
+                "split-1:00000000:00000003".to_string(),
+                "split-1:00000000:000000ff".to_string(),
+                "split-2:00000000:00000002".to_string(),
+                "foo".to_string(),
+            ]),
+            split_id: None,
+        }
+        .into();
+        let query_ast: QueryAst = BoolQuery {
+            must: vec![ids_query],
+            ..Default::default()
+        }
+        .into();
+        let search = |query_ast: &QueryAst| {
+            let query = query_ast
+                .build_tantivy_query(
+                    &schema,
+                    &create_default_quickwit_tokenizer_manager(),
+                    &[],
+                    true,
+                )
+                .unwrap();
+            searcher.search(&query, &DocSetCollector).unwrap()
+        };
+        // Unbound queries do not match any document.
+        assert!(search(&query_ast).is_empty());
+
+        let bound_query_ast = bind_ids_queries_to_split(query_ast.clone(), "split-1");
+        assert_eq!(
+            search(&bound_query_ast),
+            HashSet::from([DocAddress::new(0, 1), DocAddress::new(0, 3)])
+        );
+        let bound_query_ast = bind_ids_queries_to_split(query_ast, "split-3");
+        let query = bound_query_ast
+            .build_tantivy_query(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap();
+        assert_eq!(searcher.search(&query, &Count).unwrap(), 0);
+    }
+}
@@ -0,0 +1,523 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tantivy::columnar::BytesColumn;
+use tantivy::query::{EmptyScorer, EnableScoring, Explanation, Scorer, Weight};
+use tantivy::schema::{FieldType, Schema as TantivySchema};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term, TERMINATED};
+
+use super::scored_docs_scorer::ScoredDocsScorer;
+use super::{BuildTantivyAst, QueryAst, QueryAstVisitor};
+use crate::query_ast::TantivyQueryAst;
+use crate::tokenizers::TokenizerManager;
+use crate::vector::{decode_vector, VectorSimilarity, MAX_DENSE_VECTOR_DIMS};
+use crate::{find_field_or_hit_dynamic, InvalidQuery, TantivyQuery};
+
+/// Maximum number of nearest neighbors a k-NN query can retrieve.
+pub const MAX_KNN_K: u32 = 10_000;
+
+/// Retrieves the `k` documents whose `dense_vector` field is the most similar to a query vector.
+///
+/// The search is exact: every vector of the split is compared to the query vector. The `k`
+/// nearest neighbors are retrieved from each segment and scored with the similarity metric of
+/// the field, so that they can be merged with other query clauses and across splits. See
+/// [`top_level_knn_k`] for how the search keeps the `k` best neighbors of all splits.
+#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+pub struct KnnQuery {
+    pub field: String,
+    pub query_vector: Vec<f32>,
+    pub k: u32,
+    /// Similarity metric of the field. It is resolved from the doc mapping before the query is
+    /// built.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<VectorSimilarity>,
+    /// Only the documents matching this query are considered as neighbors.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Box<QueryAst>>,
+}
+
+// NaN components are rejected when the query is built.
+impl Eq for KnnQuery {}
+
+impl From<KnnQuery> for QueryAst {
+    fn from(knn_query: KnnQuery) -> Self {
+        Self::Knn(knn_query)
+    }
+}
+
+impl BuildTantivyAst for KnnQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+        search_fields: &[String],
+        with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        if self.k == 0 || self.k > MAX_KNN_K {
+            return Err(InvalidQuery::SchemaError(format!(
+                "k-NN query `k` must be between 1 and {MAX_KNN_K}, got `{}`",
+                self.k
+            )));
+        }
+        if self.query_vector.is_empty() || self.query_vector.len() > MAX_DENSE_VECTOR_DIMS {
+            return Err(InvalidQuery::SchemaError(format!(
+                "k-NN query vector must have between 1 and {MAX_DENSE_VECTOR_DIMS} dimensions"
+            )));
+        }
+        let field_name = check_dense_vector_field(&self.field, schema)?;
+        let Some(similarity) = self.similarity else {
+            return Err(InvalidQuery::SchemaError(format!(
+                "field `{}` is not a dense_vector field",
+                self.field
+            )));
+        };
+        similarity
+            .validate_vector(&self.query_vector)
+            .map_err(|err_msg| {
+                InvalidQuery::SchemaError(format!("invalid query vector: {err_msg}"))
+            })?;
+        let filter_opt: Option<Box<dyn TantivyQuery>> = match &self.filter {
+            Some(filter) => {
+                let filter_ast = filter.build_tantivy_ast_call(
+                    schema,
+                    tokenizer_manager,
+                    search_fields,
+                    with_validation,
+                )?;
+                Some(filter_ast.simplify().into())
+            }
+            None => None,
+        };
+        Ok(KnnTantivyQuery {
+            field_name,
+            query_vector: self.query_vector.clone(),
+            k: self.k as usize,
+            similarity,
+            filter_opt,
+        }
+        .into())
+    }
+}
+
+/// Returns true if the query AST contains a k-NN query.
+pub fn contains_knn_query(query_ast: &QueryAst) -> bool {
+    struct ContainsKnnQuery;
+
+    impl<'a> QueryAstVisitor<'a> for ContainsKnnQuery {
+        // Visiting stops at the first k-NN query.
+        type Err = ();
+
+        fn visit_knn(&mut self, _knn_query: &'a KnnQuery) -> Result<(), ()> {
+            Err(())
+        }
+    }
+    ContainsKnnQuery.visit(query_ast).is_err()
+}
+
+/// Returns the `k` of the k-NN query if the query AST is a single k-NN query, possibly boosted or
+/// wrapped in a boolean query without any other clause.
+///
+/// The hits of such a query are the `k` nearest neighbors of all splits: the search must only
+/// keep the `k` best hits when merging the results of segments and splits. When the k-NN query
+/// is combined with other clauses, the `k` nearest neighbors are retrieved from each split.
+pub fn top_level_knn_k(query_ast: &QueryAst) -> Option<u32> {
+    match query_ast {
+        QueryAst::Knn(knn_query) => Some(knn_query.k),
+        QueryAst::Boost { underlying, .. } => top_level_knn_k(underlying),
+        QueryAst::Bool(bool_query)
+            if bool_query.must.is_empty()
+                && bool_query.must_not.is_empty()
+                && bool_query.filter.is_empty()
+                && bool_query.should.len() == 1 =>
+        {
+            top_level_knn_k(&bool_query.should[0])
+        }
+        _ => None,
+    }
+}
+
+/// Dense vectors are indexed as bytes fast fields. The tantivy schema does not let us
+/// tell them apart from regular bytes fields, so this is the best we can check.
+fn check_dense_vector_field(field: &str, schema: &TantivySchema) -> Result<String, InvalidQuery> {
+    let (_field, field_entry, json_path) = find_field_or_hit_dynamic(field, schema)?;
+    match field_entry.field_type() {
+        FieldType::Bytes(options) if options.is_fast() && json_path.is_empty() => {
+            Ok(field_entry.name().to_string())
+        }
+        _ => Err(InvalidQuery::SchemaError(format!(
+            "field `{field}` is not a dense_vector field"
+        ))),
+    }
+}
+
+/// Tantivy query scanning the fast field column of a dense vector field.
+struct KnnTantivyQuery {
+    field_name: String,
+    query_vector: Vec<f32>,
+    k: usize,
+    similarity: VectorSimilarity,
+    filter_opt: Option<Box<dyn TantivyQuery>>,
+}
+
+impl Clone for KnnTantivyQuery {
+    fn clone(&self) -> Self {
+        KnnTantivyQuery {
+            field_name: self.field_name.clone(),
+            query_vector: self.query_vector.clone(),
+            k: self.k,
+            similarity: self.similarity,
+            filter_opt: self.filter_opt.as_ref().map(|filter| filter.box_clone()),
+        }
+    }
+}
+
+impl fmt::Debug for KnnTantivyQuery {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("KnnTantivyQuery")
+            .field("field_name", &self.field_name)
+            .field("dims", &self.query_vector.len())
+            .field("k", &self.k)
+            .field("similarity", &self.similarity)
+            .field("filter", &self.filter_opt)
+            .finish()
+    }
+}
+
+impl TantivyQuery for KnnTantivyQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        // The filter only restricts the candidates, it does not contribute to the score.
+        let filter_weight_opt = self
+            .filter_opt
+            .as_ref()
+            .map(|filter| {
+                filter.weight(EnableScoring::disabled_from_schema(enable_scoring.schema()))
+            })
+            .transpose()?;
+        Ok(Box::new(KnnWeight {
+            field_name: self.field_name.clone(),
+            query_vector: self.query_vector.clone(),
+            k: self.k,
+            similarity: self.similarity,
+            filter_weight_opt,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        if let Some(filter) = &self.filter_opt {
+            filter.query_terms(visitor);
+        }
+    }
+}
+
+struct KnnWeight {
+    field_name: String,
+    query_vector: Vec<f32>,
+    k: usize,
+    similarity: VectorSimilarity,
+    filter_weight_opt: Option<Box<dyn Weight>>,
+}
+
+impl KnnWeight {
+    /// Returns the score of the vector of a document, or `None` if the document has no vector.
+    ///
+    /// Documents usually have distinct vectors, so scores are cached by term ordinal only to
+    /// avoid decoding the same vector twice.
+    fn score_doc(
+        &self,
+        column: &BytesColumn,
+        doc: DocId,
+        score_cache: &mut HashMap<u64, Option<Score>>,
+        buffer: &mut Vec<u8>,
+    ) -> tantivy::Result<Option<Score>> {
+        let mut best_score_opt: Option<Score> = None;
+        for term_ord in column.term_ords(doc) {
+            let score_opt = if let Some(score_opt) = score_cache.get(&term_ord) {
+                *score_opt
+            } else {
+                buffer.clear();
+                column.ord_to_bytes(term_ord, buffer)?;
+                let score_opt = decode_vector(buffer)
+                    .filter(|vector| vector.len() == self.query_vector.len())
+                    .map(|vector| self.similarity.score(&self.query_vector, &vector));
+                score_cache.insert(term_ord, score_opt);
+                score_opt
+            };
+            if let Some(score) = score_opt {
+                best_score_opt = Some(best_score_opt.map_or(score, |best| best.max(score)));
+            }
+        }
+        Ok(best_score_opt)
+    }
+
+    /// Returns the `k` nearest neighbors of the segment as `(doc, score)` pairs sorted by doc id.
+    fn nearest_neighbors(&self, reader: &SegmentReader) -> tantivy::Result<Vec<(DocId, Score)>> {
+        let Some(column) = reader.fast_fields().bytes(&self.field_name)? else {
+            return Ok(Vec::new());
+        };
+        let mut candidates: Vec<(DocId, Score)> = Vec::new();
+        let mut score_cache: HashMap<u64, Option<Score>> = HashMap::new();
+        let mut buffer = Vec::new();
+        let alive_bitset_opt = reader.alive_bitset();
+
+        let mut score_candidate = |doc: DocId| -> tantivy::Result<()> {
+            if alive_bitset_opt.is_some_and(|alive_bitset| alive_bitset.is_deleted(doc)) {
+                return Ok(());
+            }
+            if let Some(score) = self.score_doc(&column, doc, &mut score_cache, &mut buffer)? {
+                candidates.push((doc, score));
+            }
+            Ok(())
+        };
+        if let Some(filter_weight) = &self.filter_weight_opt {
+            let mut filter_scorer = filter_weight.scorer(reader, 1.0)?;
+            let mut doc = filter_scorer.doc();
+            while doc != TERMINATED {
+                score_candidate(doc)?;
+                doc = filter_scorer.advance();
+            }
+        } else {
+            for doc in 0..reader.max_doc() {
+                score_candidate(doc)?;
+            }
+        }
+        if candidates.len() > self.k {
+            // Ties are broken by doc id to keep the results deterministic.
+            candidates.select_nth_unstable_by(
+                self.k - 1,
+                |(left_doc, left_score), (right_doc, right_score)| {
+                    right_score
+                        .total_cmp(left_score)
+                        .then_with(|| left_doc.cmp(right_doc))
+                },
+            );
+            candidates.truncate(self.k);
+        }
+        candidates.sort_unstable_by_key(|(doc, _score)| *doc);
+        Ok(candidates)
+    }
+}
+
+impl Weight for KnnWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let nearest_neighbors = self.nearest_neighbors(reader)?;
+        if nearest_neighbors.is_empty() {
+            return Ok(Box::new(EmptyScorer));
+        }
+        Ok(Box::new(ScoredDocsScorer::new(nearest_neighbors, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) is not one of the k nearest neighbors"
+            )));
+        }
+        Ok(Explanation::new(
+            format!("KnnQuery similarity={:?}", self.similarity),
+            scorer.score(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{Schema, FAST, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::query_ast::{BoolQuery, TermQuery};
+    use crate::vector::encode_vector;
+    use crate::{create_default_quickwit_tokenizer_manager, NotNaNf32};
+
+    #[track_caller]
+    fn assert_hits(hits: Vec<(u64, Score)>, expected_hits: &[(u64, Score)]) {
+        assert_eq!(hits.len(), expected_hits.len(), "{hits:?}");
+        for ((id, score), (expected_id, expected_score)) in hits.iter().zip(expected_hits) {
+            assert_eq!(id, expected_id, "{hits:?}");
+            assert!((score - expected_score).abs() < 1e-6, "{hits:?}");
+        }
+    }
+
+    fn knn_search(knn_query: KnnQuery) -> Vec<(u64, Score)> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", FAST);
+        let color_field = schema_builder.add_text_field("color", STRING);
+        let embedding_field = schema_builder.add_bytes_field("embedding", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for (id, color, vector) in [
+            (0u64, "red", [1.0f32, 0.0]),
+            (1, "blue", [0.0, 1.0]),
+            (2, "red", [0.8, 0.6]),
+            (3, "blue", [0.6, 0.8]),
+            (4, "red", [-1.0, 0.0]),
+        ] {
+            index_writer
+                .add_document(doc!(
+                    id_field => id,
+                    color_field => color,
+                    embedding_field => encode_vector(&vector),
+                ))
+                .unwrap();
+        }
+        // A document without vector is never a neighbor.
+        index_writer
+            .add_document(doc!(id_field => 5u64, color_field => "red"))
+            .unwrap();
+        index_writer.delete_term(Term::from_field_u64(id_field, 3));
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let query = QueryAst::from(knn_query)
+            .build_tantivy_query(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap();
+        let id_column = searcher.segment_reader(0).fast_fields().u64("id").unwrap();
+        searcher
+            .search(&query, &TopDocs::with_limit(10))
+            .unwrap()
+            .into_iter()
+            .map(|(score, doc_address)| (id_column.first(doc_address.doc_id).unwrap(), score))
+            .collect()
+    }
+
+    #[test]
+    fn test_knn_query() {
+        let knn_query = KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![1.0, 0.0],
+            k: 3,
+            similarity: Some(VectorSimilarity::L2Norm),
+            filter: None,
+        };
+        assert_hits(
+            knn_search(knn_query),
+            &[(0, 1.0), (2, 1.0 / 1.4), (1, 1.0 / 3.0)],
+        );
+    }
+
+    #[test]
+    fn test_knn_query_with_filter() {
+        let knn_query = KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![0.0, 1.0],
+            k: 2,
+            similarity: Some(VectorSimilarity::DotProduct),
+            filter: Some(Box::new(
+                TermQuery {
+                    field: "color".to_string(),
+                    value: "red".to_string(),
+                }
+                .into(),
+            )),
+        };
+        // Documents #0 and #4 are tied: the lowest doc id wins.
+        assert_hits(knn_search(knn_query), &[(2, 0.8), (0, 0.5)]);
+    }
+
+    #[test]
+    fn test_knn_query_errors() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("color", STRING);
+        schema_builder.add_bytes_field("embedding", FAST);
+        let schema = schema_builder.build();
+        let build_error = |knn_query: KnnQuery| {
+            QueryAst::from(knn_query)
+                .build_tantivy_query(
+                    &schema,
+                    &create_default_quickwit_tokenizer_manager(),
+                    &[],
+                    true,
+                )
+                .unwrap_err()
+                .to_string()
+        };
+        let knn_query = KnnQuery {
+            field: "color".to_string(),
+            query_vector: vec![1.0, 0.0],
+            k: 3,
+            similarity: Some(VectorSimilarity::Cosine),
+            filter: None,
+        };
+        let error = build_error(knn_query.clone());
+        assert!(
+            error.contains("field `color` is not a dense_vector field"),
+            "{error}"
+        );
+
+        let error = build_error(KnnQuery {
+            field: "embedding".to_string(),
+            k: 0,
+            ..knn_query.clone()
+        });
+        assert!(error.contains("`k` must be between 1 and 10000"), "{error}");
+
+        let error = build_error(KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![0.0, 0.0],
+            ..knn_query
+        });
+        assert!(error.contains("zero-magnitude vectors"), "{error}");
+    }
+
+    #[test]
+    fn test_top_level_knn_k() {
+        let knn_query_ast: QueryAst = KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![1.0, 0.0],
+            k: 3,
+            similarity: None,
+            filter: None,
+        }
+        .into();
+        assert_eq!(top_level_knn_k(&knn_query_ast), Some(3));
+
+        let boosted_knn_query_ast = knn_query_ast.clone().boost(Some(NotNaNf32::ONE));
+        assert_eq!(top_level_knn_k(&boosted_knn_query_ast), Some(3));
+
+        let bool_query_ast: QueryAst = BoolQuery {
+            should: vec![knn_query_ast.clone()],
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(top_level_knn_k(&bool_query_ast), Some(3));
+
+        // Combined with other clauses, the nearest neighbors are retrieved from each split.
+        let bool_query_ast: QueryAst = BoolQuery {
+            should: vec![knn_query_ast, QueryAst::MatchAll],
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(top_level_knn_k(&bool_query_ast), None);
+        assert_eq!(top_level_knn_k(&QueryAst::MatchAll), None);
+    }
+}
@@ -24,10 +24,19 @@ use tantivy::schema::Schema as TantivySchema;
 use crate::tokenizers::TokenizerManager;
 
 mod bool_query;
+mod dis_max_query;
 mod field_presence;
 mod full_text_query;
+mod fuzzy_query;
+mod geo_query;
+mod ids_query;
+mod knn_query;
+mod nested_query;
 mod phrase_prefix_query;
 mod range_query;
+mod regex_query;
+mod runtime_field_query;
+mod scored_docs_scorer;
 mod tantivy_query_ast;
 mod term_query;
 mod term_set_query;
@@ -37,10 +46,20 @@ mod visitor;
 mod wildcard_query;
 
 pub use bool_query::BoolQuery;
+pub use dis_max_query::DisMaxQuery;
 pub use field_presence::FieldPresenceQuery;
 pub use full_text_query::{FullTextMode, FullTextParams, FullTextQuery};
+pub use fuzzy_query::{FuzzyQuery, MAX_FUZZY_DISTANCE};
+pub use geo_query::{GeoBoundingBoxQuery, GeoDistanceQuery};
+pub use ids_query::{bind_ids_queries_to_split, IdsQuery};
+pub use knn_query::{contains_knn_query, top_level_knn_k, KnnQuery, MAX_KNN_K};
+pub use nested_query::{
+    num_root_docs, NestedDocsReader, NestedQuery, NestedScoreMode, WithNestedDocsQuery,
+};
 pub use phrase_prefix_query::PhrasePrefixQuery;
 pub use range_query::RangeQuery;
+pub use regex_query::RegexQuery;
+pub use runtime_field_query::{resolve_runtime_fields, RuntimeFieldPredicate, RuntimeFieldQuery};
 use tantivy_query_ast::TantivyQueryAst;
 pub use term_query::TermQuery;
 pub use term_set_query::TermSetQuery;
@@ -55,6 +74,7 @@ use crate::{BooleanOperand, InvalidQuery, NotNaNf32};
 #[serde(rename_all = "snake_case")]
 pub enum QueryAst {
     Bool(BoolQuery),
+    DisMax(DisMaxQuery),
     Term(TermQuery),
     TermSet(TermSetQuery),
     FieldPresence(FieldPresenceQuery),
@@ -63,6 +83,14 @@ pub enum QueryAst {
     Range(RangeQuery),
     UserInput(UserInputQuery),
     Wildcard(WildcardQuery),
+    Regex(RegexQuery),
+    Fuzzy(FuzzyQuery),
+    GeoBoundingBox(GeoBoundingBoxQuery),
+    GeoDistance(GeoDistanceQuery),
+    Ids(IdsQuery),
+    Knn(KnnQuery),
+    Nested(NestedQuery),
+    RuntimeField(RuntimeFieldQuery),
     MatchAll,
     MatchNone,
     Boost {
@@ -105,10 +133,33 @@ impl QueryAst {
             | ast @ QueryAst::MatchNone
             | ast @ QueryAst::FieldPresence(_)
             | ast @ QueryAst::Range(_)
-            | ast @ QueryAst::Wildcard(_) => Ok(ast),
+            | ast @ QueryAst::Wildcard(_)
+            | ast @ QueryAst::Regex(_)
+            | ast @ QueryAst::Fuzzy(_)
+            | ast @ QueryAst::GeoBoundingBox(_)
+            | ast @ QueryAst::GeoDistance(_)
+            | ast @ QueryAst::Ids(_)
+            | ast @ QueryAst::RuntimeField(_) => Ok(ast),
+            QueryAst::DisMax(mut dis_max_query) => {
+                dis_max_query.disjuncts =
+                    parse_user_query_in_asts(dis_max_query.disjuncts, default_search_fields)?;
+                Ok(dis_max_query.into())
+            }
             QueryAst::UserInput(user_text_query) => {
                 user_text_query.parse_user_query(default_search_fields)
             }
+            QueryAst::Knn(mut knn_query) => {
+                if let Some(filter) = knn_query.filter.take() {
+                    let filter = filter.parse_user_query(default_search_fields)?;
+                    knn_query.filter = Some(Box::new(filter));
+                }
+                Ok(knn_query.into())
+            }
+            QueryAst::Nested(mut nested_query) => {
+                nested_query.query =
+                    Box::new(nested_query.query.parse_user_query(default_search_fields)?);
+                Ok(nested_query.into())
+            }
             QueryAst::Boost { underlying, boost } => {
                 let underlying = underlying.parse_user_query(default_search_fields)?;
                 Ok(QueryAst::Boost {
@@ -249,6 +300,60 @@ impl BuildTantivyAst for QueryAst {
                 search_fields,
                 with_validation,
             ),
+            QueryAst::Regex(regex) => regex.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::Fuzzy(fuzzy) => fuzzy.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::GeoBoundingBox(geo_bounding_box) => geo_bounding_box.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::GeoDistance(geo_distance) => geo_distance.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::DisMax(dis_max) => dis_max.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::Ids(ids) => ids.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::Knn(knn) => knn.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::Nested(nested) => nested.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
+            QueryAst::RuntimeField(runtime_field) => runtime_field.build_tantivy_ast_call(
+                schema,
+                tokenizer_manager,
+                search_fields,
+                with_validation,
+            ),
         }
     }
 }
@@ -0,0 +1,549 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::ops::Range;
+
+use quickwit_common::shared_consts::NESTED_PATH_FIELD_NAME;
+use serde::{Deserialize, Serialize};
+use tantivy::columnar::StrColumn;
+use tantivy::query::{EmptyScorer, EnableScoring, Explanation, Scorer, Weight};
+use tantivy::schema::{IndexRecordOption, Schema as TantivySchema};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term, TERMINATED};
+
+use super::scored_docs_scorer::ScoredDocsScorer;
+use super::tantivy_query_ast::TantivyBoolQuery;
+use super::{BuildTantivyAst, QueryAst};
+use crate::query_ast::TantivyQueryAst;
+use crate::tokenizers::TokenizerManager;
+use crate::{InvalidQuery, TantivyQuery};
+
+/// Matches the documents having at least one object of a nested field matching a query.
+///
+/// The objects of a nested field are indexed as separate child documents, right before the
+/// document they belong to. Unlike with regular objects, all the clauses of the query must
+/// therefore match the same object.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct NestedQuery {
+    /// Path of the nested field.
+    pub path: String,
+    /// Query matched against the objects of the nested field.
+    pub query: Box<QueryAst>,
+    /// How the scores of the matching objects are combined into the score of the document.
+    #[serde(default)]
+    pub score_mode: NestedScoreMode,
+}
+
+/// How the scores of the matching objects of a nested field are combined.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NestedScoreMode {
+    #[default]
+    Avg,
+    Max,
+    Min,
+    Sum,
+    /// The score of the matching objects is ignored and the document gets a score of 0.
+    None,
+}
+
+impl From<NestedQuery> for QueryAst {
+    fn from(nested_query: NestedQuery) -> Self {
+        QueryAst::Nested(nested_query)
+    }
+}
+
+impl BuildTantivyAst for NestedQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        tokenizer_manager: &TokenizerManager,
+        search_fields: &[String],
+        with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let nested_path_field = schema.get_field(NESTED_PATH_FIELD_NAME).map_err(|_| {
+            InvalidQuery::SchemaError(format!("field `{}` is not a nested field", self.path))
+        })?;
+        let child_query_ast = self.query.build_tantivy_ast_call(
+            schema,
+            tokenizer_manager,
+            search_fields,
+            with_validation,
+        )?;
+        let nested_path_term = Term::from_field_text(nested_path_field, &self.path);
+        let nested_path_query =
+            tantivy::query::TermQuery::new(nested_path_term, IndexRecordOption::Basic);
+        let child_query: Box<dyn TantivyQuery> = TantivyBoolQuery {
+            must: vec![child_query_ast],
+            filter: vec![nested_path_query.into()],
+            ..Default::default()
+        }
+        .simplify()
+        .into();
+        Ok(BlockJoinQuery {
+            child_query,
+            score_mode: self.score_mode,
+        }
+        .into())
+    }
+}
+
+/// Gives access to the layout of the nested documents of a segment.
+///
+/// The child documents extracted from the nested fields of a document are indexed right before
+/// it, in the same segment, and are the only ones to have a value for the `_nested_path` field.
+pub struct NestedDocsReader {
+    nested_path_column: StrColumn,
+    max_doc: DocId,
+}
+
+impl NestedDocsReader {
+    /// Returns `None` if the segment does not contain any child document.
+    pub fn open(segment_reader: &SegmentReader) -> tantivy::Result<Option<Self>> {
+        let nested_path_column_opt = segment_reader.fast_fields().str(NESTED_PATH_FIELD_NAME)?;
+        Ok(
+            nested_path_column_opt.map(|nested_path_column| NestedDocsReader {
+                nested_path_column,
+                max_doc: segment_reader.max_doc(),
+            }),
+        )
+    }
+
+    /// Returns the term ordinal of a nested path in the segment, if any child document was
+    /// extracted from it.
+    pub fn nested_path_ord(&self, nested_path: &str) -> tantivy::Result<Option<u64>> {
+        let term_ord_opt = self.nested_path_column.dictionary().term_ord(nested_path)?;
+        Ok(term_ord_opt)
+    }
+
+    /// Returns the term ordinal of the nested path of a child document, or `None` if the
+    /// document is a root document.
+    pub fn doc_nested_path_ord(&self, doc: DocId) -> Option<u64> {
+        self.nested_path_column.term_ords(doc).next()
+    }
+
+    pub fn is_root_doc(&self, doc: DocId) -> bool {
+        self.doc_nested_path_ord(doc).is_none()
+    }
+
+    /// Returns the root document a child document belongs to.
+    pub fn parent(&self, child_doc: DocId) -> Option<DocId> {
+        (child_doc + 1..self.max_doc).find(|doc| self.is_root_doc(*doc))
+    }
+
+    /// Returns the child documents of a root document.
+    pub fn children(&self, root_doc: DocId) -> Range<DocId> {
+        let mut first_child_doc = root_doc;
+        while first_child_doc > 0 && !self.is_root_doc(first_child_doc - 1) {
+            first_child_doc -= 1;
+        }
+        first_child_doc..root_doc
+    }
+}
+
+/// Returns the number of alive documents of a segment, child documents excluded.
+pub fn num_root_docs(segment_reader: &SegmentReader) -> tantivy::Result<u32> {
+    let Some(nested_docs) = NestedDocsReader::open(segment_reader)? else {
+        return Ok(segment_reader.num_docs());
+    };
+    let num_root_docs = segment_reader
+        .doc_ids_alive()
+        .filter(|doc| nested_docs.is_root_doc(*doc))
+        .count();
+    Ok(num_root_docs as u32)
+}
+
+#[derive(Default)]
+struct ChildScores {
+    num_children: u32,
+    sum: Score,
+    min: Score,
+    max: Score,
+}
+
+impl ChildScores {
+    fn add(&mut self, score: Score) {
+        if self.num_children == 0 {
+            self.min = score;
+            self.max = score;
+        } else {
+            self.min = self.min.min(score);
+            self.max = self.max.max(score);
+        }
+        self.sum += score;
+        self.num_children += 1;
+    }
+
+    fn score(&self, score_mode: NestedScoreMode) -> Score {
+        match score_mode {
+            NestedScoreMode::Avg => self.sum / self.num_children as Score,
+            NestedScoreMode::Max => self.max,
+            NestedScoreMode::Min => self.min,
+            NestedScoreMode::Sum => self.sum,
+            NestedScoreMode::None => 0.0,
+        }
+    }
+}
+
+/// Tantivy query matching the root documents of the child documents matching a query.
+#[derive(Debug)]
+struct BlockJoinQuery {
+    child_query: Box<dyn TantivyQuery>,
+    score_mode: NestedScoreMode,
+}
+
+impl Clone for BlockJoinQuery {
+    fn clone(&self) -> Self {
+        BlockJoinQuery {
+            child_query: self.child_query.box_clone(),
+            score_mode: self.score_mode,
+        }
+    }
+}
+
+impl TantivyQuery for BlockJoinQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        let child_weight = if self.score_mode == NestedScoreMode::None {
+            self.child_query
+                .weight(EnableScoring::disabled_from_schema(enable_scoring.schema()))?
+        } else {
+            self.child_query.weight(enable_scoring)?
+        };
+        Ok(Box::new(BlockJoinWeight {
+            child_weight,
+            score_mode: self.score_mode,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.child_query.query_terms(visitor);
+    }
+}
+
+struct BlockJoinWeight {
+    child_weight: Box<dyn Weight>,
+    score_mode: NestedScoreMode,
+}
+
+impl Weight for BlockJoinWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let Some(nested_docs) = NestedDocsReader::open(reader)? else {
+            return Ok(Box::new(EmptyScorer));
+        };
+        let mut child_scorer = self.child_weight.scorer(reader, boost)?;
+        let mut scored_root_docs: Vec<(DocId, Score)> = Vec::new();
+        let mut current_root_doc_opt: Option<(DocId, ChildScores)> = None;
+        let mut child_doc = child_scorer.doc();
+
+        while child_doc != TERMINATED {
+            // Child documents are sorted, so all the children of a root document are visited
+            // before moving on to the next root document.
+            let is_same_root_doc = matches!(
+                current_root_doc_opt,
+                Some((root_doc, _)) if child_doc < root_doc
+            );
+            if !is_same_root_doc {
+                if let Some((root_doc, child_scores)) = current_root_doc_opt.take() {
+                    scored_root_docs.push((root_doc, child_scores.score(self.score_mode)));
+                }
+                current_root_doc_opt = nested_docs
+                    .parent(child_doc)
+                    .map(|root_doc| (root_doc, ChildScores::default()));
+            }
+            if let Some((_, child_scores)) = &mut current_root_doc_opt {
+                child_scores.add(child_scorer.score());
+            }
+            child_doc = child_scorer.advance();
+        }
+        if let Some((root_doc, child_scores)) = current_root_doc_opt {
+            scored_root_docs.push((root_doc, child_scores.score(self.score_mode)));
+        }
+        if scored_root_docs.is_empty() {
+            return Ok(Box::new(EmptyScorer));
+        }
+        // The boost has already been applied to the scores of the child documents.
+        Ok(Box::new(ScoredDocsScorer::new(scored_root_docs, 1.0)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) does not have any matching nested object"
+            )));
+        }
+        Ok(Explanation::new(
+            format!("NestedQuery score_mode={:?}", self.score_mode),
+            scorer.score(),
+        ))
+    }
+}
+
+/// Extends the root documents matched by a query with their child documents.
+///
+/// Deleting the documents matched by this query also deletes the objects of their nested
+/// fields, which would otherwise be attached to the next root document of the segment.
+#[derive(Debug)]
+pub struct WithNestedDocsQuery {
+    root_query: Box<dyn TantivyQuery>,
+}
+
+impl WithNestedDocsQuery {
+    pub fn new(root_query: Box<dyn TantivyQuery>) -> Self {
+        WithNestedDocsQuery { root_query }
+    }
+}
+
+impl Clone for WithNestedDocsQuery {
+    fn clone(&self) -> Self {
+        WithNestedDocsQuery {
+            root_query: self.root_query.box_clone(),
+        }
+    }
+}
+
+impl TantivyQuery for WithNestedDocsQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        let root_weight = self
+            .root_query
+            .weight(EnableScoring::disabled_from_schema(enable_scoring.schema()))?;
+        Ok(Box::new(WithNestedDocsWeight { root_weight }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.root_query.query_terms(visitor);
+    }
+}
+
+struct WithNestedDocsWeight {
+    root_weight: Box<dyn Weight>,
+}
+
+impl Weight for WithNestedDocsWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let mut root_scorer = self.root_weight.scorer(reader, boost)?;
+        let Some(nested_docs) = NestedDocsReader::open(reader)? else {
+            return Ok(root_scorer);
+        };
+        let mut scored_docs: Vec<(DocId, Score)> = Vec::new();
+        let mut doc = root_scorer.doc();
+
+        while doc != TERMINATED {
+            // Child documents matched on their own are ignored: they go away with their root
+            // document.
+            if nested_docs.is_root_doc(doc) {
+                scored_docs.extend(nested_docs.children(doc).map(|child_doc| (child_doc, 1.0)));
+                scored_docs.push((doc, 1.0));
+            }
+            doc = root_scorer.advance();
+        }
+        if scored_docs.is_empty() {
+            return Ok(Box::new(EmptyScorer));
+        }
+        Ok(Box::new(ScoredDocsScorer::new(scored_docs, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) does not match the query"
+            )));
+        }
+        Ok(Explanation::new("WithNestedDocsQuery", scorer.score()))
+    }
+}
+
+impl fmt::Debug for NestedDocsReader {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("NestedDocsReader")
+            .field("max_doc", &self.max_doc)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::{Count, TopDocs};
+    use tantivy::schema::{Schema, FAST, INDEXED, STRING};
+    use tantivy::{doc, Index, IndexWriter, TantivyDocument};
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+    use crate::query_ast::{BoolQuery, TermQuery};
+
+    fn create_orders_index() -> Index {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let nested_path_field =
+            schema_builder.add_text_field(NESTED_PATH_FIELD_NAME, STRING | FAST);
+        let sku_field = schema_builder.add_text_field("items.sku", STRING);
+        let qty_field = schema_builder.add_u64_field("items.qty", INDEXED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer: IndexWriter<TantivyDocument> =
+            index.writer_with_num_threads(1, 15_000_000).unwrap();
+        let orders: [(u64, &[(&str, u64)]); 3] = [
+            (1, &[("A", 1), ("B", 5)]),
+            (2, &[("C", 2), ("A", 5)]),
+            (3, &[]),
+        ];
+        for (id, items) in orders {
+            for (sku, qty) in items {
+                index_writer
+                    .add_document(doc!(
+                        nested_path_field => "items",
+                        sku_field => *sku,
+                        qty_field => *qty,
+                    ))
+                    .unwrap();
+            }
+            index_writer.add_document(doc!(id_field => id)).unwrap();
+        }
+        index_writer.commit().unwrap();
+        index
+    }
+
+    fn search_ids(index: &Index, query_ast: QueryAst) -> Vec<(u64, Score)> {
+        let query: Box<dyn TantivyQuery> = query_ast
+            .build_tantivy_ast_call(
+                &index.schema(),
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap()
+            .simplify()
+            .into();
+        let searcher = index.reader().unwrap().searcher();
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(10).order_by_score())
+            .unwrap();
+        let id_column = searcher.segment_reader(0).fast_fields().u64("id").unwrap();
+        let mut ids: Vec<(u64, Score)> = top_docs
+            .into_iter()
+            .map(|(score, doc_address)| (id_column.first(doc_address.doc_id).unwrap(), score))
+            .collect();
+        ids.sort_by_key(|(id, _score)| *id);
+        ids
+    }
+
+    fn term_query(field: &str, value: &str) -> QueryAst {
+        TermQuery {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_nested_query() {
+        let index = create_orders_index();
+        let sku_a_and_qty_5: QueryAst = BoolQuery {
+            must: vec![term_query("items.sku", "A"), term_query("items.qty", "5")],
+            ..Default::default()
+        }
+        .into();
+        let nested_query: QueryAst = NestedQuery {
+            path: "items".to_string(),
+            query: Box::new(sku_a_and_qty_5),
+            score_mode: NestedScoreMode::None,
+        }
+        .into();
+        // The first order has an item `A` and an item with a quantity of 5, but they are not the
+        // same item.
+        assert_eq!(search_ids(&index, nested_query), [(2, 0.0)]);
+
+        let nested_query: QueryAst = NestedQuery {
+            path: "items".to_string(),
+            query: Box::new(QueryAst::MatchAll),
+            score_mode: NestedScoreMode::Sum,
+        }
+        .into();
+        assert_eq!(search_ids(&index, nested_query), [(1, 2.0), (2, 2.0)]);
+
+        let nested_query: QueryAst = NestedQuery {
+            path: "unknown".to_string(),
+            query: Box::new(QueryAst::MatchAll),
+            score_mode: NestedScoreMode::Avg,
+        }
+        .into();
+        assert!(search_ids(&index, nested_query).is_empty());
+    }
+
+    #[test]
+    fn test_nested_query_without_nested_field() {
+        let schema = Schema::builder().build();
+        let nested_query: QueryAst = NestedQuery {
+            path: "items".to_string(),
+            query: Box::new(QueryAst::MatchAll),
+            score_mode: NestedScoreMode::Avg,
+        }
+        .into();
+        let error = nested_query
+            .build_tantivy_ast_call(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid query: field `items` is not a nested field"
+        );
+    }
+
+    #[test]
+    fn test_nested_docs_reader() {
+        let index = create_orders_index();
+        let searcher = index.reader().unwrap().searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let nested_docs = NestedDocsReader::open(segment_reader).unwrap().unwrap();
+        assert_eq!(nested_docs.parent(0), Some(2));
+        assert_eq!(nested_docs.parent(3), Some(5));
+        assert_eq!(nested_docs.children(2), 0..2);
+        assert_eq!(nested_docs.children(6), 6..6);
+        assert!(nested_docs.nested_path_ord("items").unwrap().is_some());
+        assert!(nested_docs.nested_path_ord("unknown").unwrap().is_none());
+        assert_eq!(num_root_docs(segment_reader).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_with_nested_docs_query() {
+        let index = create_orders_index();
+        let id_field = index.schema().get_field("id").unwrap();
+        let mut index_writer: IndexWriter<TantivyDocument> =
+            index.writer_with_num_threads(1, 15_000_000).unwrap();
+        let delete_query = WithNestedDocsQuery::new(Box::new(tantivy::query::TermQuery::new(
+            Term::from_field_u64(id_field, 1),
+            IndexRecordOption::Basic,
+        )));
+        index_writer.delete_query(Box::new(delete_query)).unwrap();
+        index_writer.commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        assert_eq!(
+            searcher.search(&tantivy::query::AllQuery, &Count).unwrap(),
+            4
+        );
+        assert_eq!(num_root_docs(searcher.segment_reader(0)).unwrap(), 2);
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use tantivy::query::RegexQuery as TantivyRegexQuery;
+use tantivy::schema::{Field, FieldType, Schema as TantivySchema};
+
+use super::{BuildTantivyAst, QueryAst};
+use crate::query_ast::TantivyQueryAst;
+use crate::tokenizers::TokenizerManager;
+use crate::{find_field_or_hit_dynamic, InvalidQuery};
+
+/// A Regex query matches the terms of a text field against a regular expression.
+///
+/// The regular expression must match the entire term. It is not normalized by the tokenizer of
+/// the field: on a field with a lowercasing tokenizer, `Error.*` never matches.
+///
+/// At the moment, regex queries are not supported on JSON fields.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct RegexQuery {
+    pub field: String,
+    pub regex: String,
+    /// Support missing fields
+    pub lenient: bool,
+}
+
+impl From<RegexQuery> for QueryAst {
+    fn from(regex_query: RegexQuery) -> Self {
+        Self::Regex(regex_query)
+    }
+}
+
+impl RegexQuery {
+    /// Returns the text field targeted by the query.
+    pub fn find_field(&self, schema: &TantivySchema) -> Result<Field, InvalidQuery> {
+        let (field, field_entry, _json_path) = find_field_or_hit_dynamic(&self.field, schema)?;
+        match field_entry.field_type() {
+            FieldType::Str(text_options) => {
+                if text_options.get_indexing_options().is_none() {
+                    return Err(InvalidQuery::SchemaError(format!(
+                        "field {} is not full-text searchable",
+                        field_entry.name()
+                    )));
+                }
+                Ok(field)
+            }
+            FieldType::JsonObject(_) => Err(InvalidQuery::SchemaError(format!(
+                "regex queries are not supported on JSON field {}",
+                field_entry.name()
+            ))),
+            _ => Err(InvalidQuery::SchemaError(
+                "trying to run a Regex query on a non-text field".to_string(),
+            )),
+        }
+    }
+}
+
+impl BuildTantivyAst for RegexQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        _tokenizer_manager: &TokenizerManager,
+        _search_fields: &[String],
+        _with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let field = match self.find_field(schema) {
+            Ok(field) => field,
+            Err(InvalidQuery::FieldDoesNotExist { .. }) if self.lenient => {
+                return Ok(TantivyQueryAst::match_none())
+            }
+            Err(error) => return Err(error),
+        };
+        let regex_query = TantivyRegexQuery::from_pattern(&self.regex, field)
+            .map_err(|_| InvalidQuery::SchemaError(format!("invalid regex `{}`", self.regex)))?;
+        Ok(regex_query.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{Schema, STRING, TEXT};
+
+    use super::*;
+    use crate::create_default_quickwit_tokenizer_manager;
+
+    fn build_regex_query(field: &str, regex: &str, lenient: bool) -> Result<String, String> {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("service", STRING);
+        schema_builder.add_json_field("attributes", TEXT);
+        schema_builder.add_u64_field("status", tantivy::schema::INDEXED);
+        let schema = schema_builder.build();
+        let regex_query = RegexQuery {
+            field: field.to_string(),
+            regex: regex.to_string(),
+            lenient,
+        };
+        regex_query
+            .build_tantivy_ast_call(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .map(|tantivy_ast| format!("{tantivy_ast:?}"))
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn test_regex_query() {
+        let tantivy_ast = build_regex_query("service", "api-.*", false).unwrap();
+        assert!(tantivy_ast.contains("RegexQuery"), "{tantivy_ast}");
+
+        let tantivy_ast = build_regex_query("missing", "api-.*", true).unwrap();
+        assert_eq!(tantivy_ast, format!("{:?}", TantivyQueryAst::match_none()));
+    }
+
+    #[test]
+    fn test_regex_query_errors() {
+        for (field, regex, expected_error) in [
+            ("missing", "api-.*", "field does not exist: `missing`"),
+            ("service", "api-(", "invalid regex `api-(`"),
+            (
+                "attributes.service",
+                "api-.*",
+                "regex queries are not supported on JSON field attributes",
+            ),
+            (
+                "status",
+                "2.*",
+                "trying to run a Regex query on a non-text field",
+            ),
+        ] {
+            let error = build_regex_query(field, regex, false).unwrap_err();
+            assert!(error.contains(expected_error), "{error}");
+        }
+    }
+}
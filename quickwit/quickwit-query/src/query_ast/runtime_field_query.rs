@@ -0,0 +1,546 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Bound;
+
+use serde::{Deserialize, Serialize};
+use tantivy::query::{EmptyScorer, EnableScoring, Explanation, Scorer, Weight};
+use tantivy::schema::Schema as TantivySchema;
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError};
+
+use super::scored_docs_scorer::ScoredDocsScorer;
+use super::{
+    BoolQuery, BuildTantivyAst, FieldPresenceQuery, FullTextQuery, FuzzyQuery, PhrasePrefixQuery,
+    QueryAst, QueryAstTransformer, RangeQuery, RegexQuery, TermQuery, TermSetQuery, WildcardQuery,
+};
+use crate::query_ast::TantivyQueryAst;
+use crate::runtime_field::{
+    CompiledRuntimeField, FastFieldDocReader, RuntimeField, RuntimeFieldType, RuntimeMappings,
+    RuntimeValue,
+};
+use crate::tokenizers::TokenizerManager;
+use crate::{InvalidQuery, JsonLiteral, TantivyQuery};
+
+/// Matches the documents whose runtime field values satisfy a predicate.
+///
+/// Runtime fields are not indexed: the script of the field is run on every alive document of the
+/// split, reading its input from the fast fields of the document. The cost of the query is
+/// therefore linear in the number of documents of the split, whatever the other clauses of the
+/// query. Queries targeting a runtime field are rewritten into this query by
+/// [`resolve_runtime_fields`].
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct RuntimeFieldQuery {
+    /// Name of the runtime field.
+    pub field: String,
+    /// Definition of the runtime field.
+    pub runtime_field: RuntimeField,
+    pub predicate: RuntimeFieldPredicate,
+}
+
+/// Condition on the values of a runtime field.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeFieldPredicate {
+    /// The field has at least one value.
+    Exists,
+    /// One of the values of the field is equal to one of the terms.
+    Terms(BTreeSet<String>),
+    /// One of the values of the field is in the range.
+    Range {
+        lower_bound: Bound<JsonLiteral>,
+        upper_bound: Bound<JsonLiteral>,
+    },
+}
+
+impl From<RuntimeFieldQuery> for QueryAst {
+    fn from(runtime_field_query: RuntimeFieldQuery) -> Self {
+        QueryAst::RuntimeField(runtime_field_query)
+    }
+}
+
+fn expected_value_type(field_type: RuntimeFieldType) -> &'static str {
+    match field_type {
+        RuntimeFieldType::Keyword => "keyword",
+        RuntimeFieldType::Long => "long",
+        RuntimeFieldType::Double => "double",
+        RuntimeFieldType::Boolean => "boolean",
+    }
+}
+
+impl RuntimeFieldQuery {
+    fn build_matcher(&self) -> Result<RuntimeValueMatcher, InvalidQuery> {
+        let field_type = self.runtime_field.field_type;
+        match &self.predicate {
+            RuntimeFieldPredicate::Exists => Ok(RuntimeValueMatcher::Exists),
+            RuntimeFieldPredicate::Terms(terms) => {
+                let values = terms
+                    .iter()
+                    .map(|term| {
+                        field_type
+                            .coerce(&serde_json::Value::String(term.clone()))
+                            .ok_or_else(|| InvalidQuery::InvalidSearchTerm {
+                                expected_value_type: expected_value_type(field_type),
+                                field_name: self.field.clone(),
+                                value: term.clone(),
+                            })
+                    })
+                    .collect::<Result<Vec<RuntimeValue>, InvalidQuery>>()?;
+                Ok(RuntimeValueMatcher::Values(values))
+            }
+            RuntimeFieldPredicate::Range {
+                lower_bound,
+                upper_bound,
+            } => {
+                let convert_bound = |bound: &Bound<JsonLiteral>| match bound {
+                    Bound::Included(literal) => {
+                        field_type.coerce_literal(literal).map(Bound::Included)
+                    }
+                    Bound::Excluded(literal) => {
+                        field_type.coerce_literal(literal).map(Bound::Excluded)
+                    }
+                    Bound::Unbounded => Some(Bound::Unbounded),
+                };
+                let invalid_boundary = || InvalidQuery::InvalidBoundary {
+                    expected_value_type: expected_value_type(field_type),
+                    field_name: self.field.clone(),
+                };
+                let lower_bound = convert_bound(lower_bound).ok_or_else(invalid_boundary)?;
+                let upper_bound = convert_bound(upper_bound).ok_or_else(invalid_boundary)?;
+                Ok(RuntimeValueMatcher::Range(lower_bound, upper_bound))
+            }
+        }
+    }
+}
+
+impl BuildTantivyAst for RuntimeFieldQuery {
+    fn build_tantivy_ast_impl(
+        &self,
+        schema: &TantivySchema,
+        _tokenizer_manager: &TokenizerManager,
+        _search_fields: &[String],
+        _with_validation: bool,
+    ) -> Result<TantivyQueryAst, InvalidQuery> {
+        let compiled_runtime_field = self.runtime_field.compile().map_err(|error| {
+            InvalidQuery::SchemaError(format!("invalid runtime field `{}`: {error}", self.field))
+        })?;
+        compiled_runtime_field.check_input_fields(&self.field, schema)?;
+        let matcher = self.build_matcher()?;
+        Ok(RuntimeFieldTantivyQuery {
+            field_name: self.field.clone(),
+            compiled_runtime_field,
+            matcher,
+        }
+        .into())
+    }
+}
+
+/// Predicate with its values coerced to the type of the runtime field.
+#[derive(Clone, Debug)]
+enum RuntimeValueMatcher {
+    Exists,
+    Values(Vec<RuntimeValue>),
+    Range(Bound<RuntimeValue>, Bound<RuntimeValue>),
+}
+
+impl RuntimeValueMatcher {
+    fn matches(&self, values: &[RuntimeValue]) -> bool {
+        match self {
+            RuntimeValueMatcher::Exists => !values.is_empty(),
+            RuntimeValueMatcher::Values(expected_values) => {
+                values.iter().any(|value| expected_values.contains(value))
+            }
+            RuntimeValueMatcher::Range(lower_bound, upper_bound) => values.iter().any(|value| {
+                let above_lower_bound = match lower_bound {
+                    Bound::Included(bound) => value >= bound,
+                    Bound::Excluded(bound) => value > bound,
+                    Bound::Unbounded => true,
+                };
+                let below_upper_bound = match upper_bound {
+                    Bound::Included(bound) => value <= bound,
+                    Bound::Excluded(bound) => value < bound,
+                    Bound::Unbounded => true,
+                };
+                above_lower_bound && below_upper_bound
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RuntimeFieldTantivyQuery {
+    field_name: String,
+    compiled_runtime_field: CompiledRuntimeField,
+    matcher: RuntimeValueMatcher,
+}
+
+impl TantivyQuery for RuntimeFieldTantivyQuery {
+    fn weight(&self, _enable_scoring: EnableScoring<'_>) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(RuntimeFieldWeight {
+            field_name: self.field_name.clone(),
+            compiled_runtime_field: self.compiled_runtime_field.clone(),
+            matcher: self.matcher.clone(),
+        }))
+    }
+}
+
+struct RuntimeFieldWeight {
+    field_name: String,
+    compiled_runtime_field: CompiledRuntimeField,
+    matcher: RuntimeValueMatcher,
+}
+
+impl RuntimeFieldWeight {
+    /// Returns the matching docs of the segment, sorted by doc id.
+    ///
+    /// The script is run on every alive document of the segment.
+    fn matching_docs(&self, reader: &SegmentReader) -> tantivy::Result<Vec<(DocId, Score)>> {
+        let mut evaluator = self.compiled_runtime_field.evaluator();
+        let fast_field_doc_reader = FastFieldDocReader::open(reader, evaluator.input_fields())?;
+        let alive_bitset_opt = reader.alive_bitset();
+        let mut matching_docs = Vec::new();
+
+        for doc in 0..reader.max_doc() {
+            if alive_bitset_opt.is_some_and(|alive_bitset| alive_bitset.is_deleted(doc)) {
+                continue;
+            }
+            let json_doc = fast_field_doc_reader.read_doc(doc)?;
+            let values = evaluator.evaluate(&json_doc);
+            if self.matcher.matches(&values) {
+                matching_docs.push((doc, 1.0));
+            }
+        }
+        Ok(matching_docs)
+    }
+}
+
+impl Weight for RuntimeFieldWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let matching_docs = self.matching_docs(reader)?;
+        if matching_docs.is_empty() {
+            return Ok(Box::new(EmptyScorer));
+        }
+        Ok(Box::new(ScoredDocsScorer::new(matching_docs, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "document #({doc}) does not match"
+            )));
+        }
+        Ok(Explanation::new(
+            format!("RuntimeFieldQuery field={}", self.field_name),
+            scorer.score(),
+        ))
+    }
+}
+
+impl fmt::Debug for RuntimeFieldWeight {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("RuntimeFieldWeight")
+            .field("field_name", &self.field_name)
+            .field("matcher", &self.matcher)
+            .finish()
+    }
+}
+
+/// Rewrites the queries targeting a runtime field into [`RuntimeFieldQuery`] nodes.
+///
+/// Term, terms, match, range and exists queries are supported. The query AST must not contain
+/// user input queries anymore.
+pub fn resolve_runtime_fields(
+    query_ast: QueryAst,
+    runtime_mappings: &RuntimeMappings,
+) -> anyhow::Result<QueryAst> {
+    if runtime_mappings.is_empty() {
+        return Ok(query_ast);
+    }
+    let mut resolver = RuntimeFieldResolver { runtime_mappings };
+    Ok(resolver
+        .transform(query_ast)?
+        .unwrap_or(QueryAst::MatchNone))
+}
+
+struct RuntimeFieldResolver<'a> {
+    runtime_mappings: &'a RuntimeMappings,
+}
+
+impl RuntimeFieldResolver<'_> {
+    fn runtime_field_query(
+        &self,
+        field: &str,
+        predicate: RuntimeFieldPredicate,
+    ) -> Option<QueryAst> {
+        let runtime_field = self.runtime_mappings.get(field)?;
+        let runtime_field_query = RuntimeFieldQuery {
+            field: field.to_string(),
+            runtime_field: runtime_field.clone(),
+            predicate,
+        };
+        Some(runtime_field_query.into())
+    }
+
+    fn check_not_runtime_field(&self, field: &str, query_type: &str) -> anyhow::Result<()> {
+        if self.runtime_mappings.contains_key(field) {
+            anyhow::bail!("{query_type} queries are not supported on runtime field `{field}`");
+        }
+        Ok(())
+    }
+}
+
+impl QueryAstTransformer for RuntimeFieldResolver<'_> {
+    type Err = anyhow::Error;
+
+    fn transform_term(&mut self, term_query: TermQuery) -> anyhow::Result<Option<QueryAst>> {
+        let predicate = RuntimeFieldPredicate::Terms(BTreeSet::from([term_query.value.clone()]));
+        Ok(Some(
+            self.runtime_field_query(&term_query.field, predicate)
+                .unwrap_or(QueryAst::Term(term_query)),
+        ))
+    }
+
+    fn transform_term_set(
+        &mut self,
+        mut term_set: TermSetQuery,
+    ) -> anyhow::Result<Option<QueryAst>> {
+        let runtime_field_names: Vec<String> = term_set
+            .terms_per_field
+            .keys()
+            .filter(|field| self.runtime_mappings.contains_key(*field))
+            .cloned()
+            .collect();
+        if runtime_field_names.is_empty() {
+            return Ok(Some(QueryAst::TermSet(term_set)));
+        }
+        let mut should = Vec::new();
+        for field in runtime_field_names {
+            let terms = term_set.terms_per_field.remove(&field).unwrap_or_default();
+            should.extend(self.runtime_field_query(&field, RuntimeFieldPredicate::Terms(terms)));
+        }
+        if !term_set.terms_per_field.is_empty() {
+            should.push(QueryAst::TermSet(term_set));
+        }
+        Ok(Some(
+            BoolQuery {
+                should,
+                ..Default::default()
+            }
+            .into(),
+        ))
+    }
+
+    fn transform_full_text(
+        &mut self,
+        full_text: FullTextQuery,
+    ) -> anyhow::Result<Option<QueryAst>> {
+        // Runtime fields are not tokenized: the text is matched as a whole.
+        let predicate = RuntimeFieldPredicate::Terms(BTreeSet::from([full_text.text.clone()]));
+        Ok(Some(
+            self.runtime_field_query(&full_text.field, predicate)
+                .unwrap_or(QueryAst::FullText(full_text)),
+        ))
+    }
+
+    fn transform_range(&mut self, range_query: RangeQuery) -> anyhow::Result<Option<QueryAst>> {
+        let predicate = RuntimeFieldPredicate::Range {
+            lower_bound: range_query.lower_bound.clone(),
+            upper_bound: range_query.upper_bound.clone(),
+        };
+        Ok(Some(
+            self.runtime_field_query(&range_query.field, predicate)
+                .unwrap_or(QueryAst::Range(range_query)),
+        ))
+    }
+
+    fn transform_exists(
+        &mut self,
+        exists_query: FieldPresenceQuery,
+    ) -> anyhow::Result<Option<QueryAst>> {
+        Ok(Some(
+            self.runtime_field_query(&exists_query.field, RuntimeFieldPredicate::Exists)
+                .unwrap_or(QueryAst::FieldPresence(exists_query)),
+        ))
+    }
+
+    fn transform_phrase_prefix(
+        &mut self,
+        phrase_query: PhrasePrefixQuery,
+    ) -> anyhow::Result<Option<QueryAst>> {
+        self.check_not_runtime_field(&phrase_query.field, "phrase prefix")?;
+        Ok(Some(QueryAst::PhrasePrefix(phrase_query)))
+    }
+
+    fn transform_wildcard(
+        &mut self,
+        wildcard_query: WildcardQuery,
+    ) -> anyhow::Result<Option<QueryAst>> {
+        self.check_not_runtime_field(&wildcard_query.field, "wildcard")?;
+        Ok(Some(QueryAst::Wildcard(wildcard_query)))
+    }
+
+    fn transform_regex(&mut self, regex_query: RegexQuery) -> anyhow::Result<Option<QueryAst>> {
+        self.check_not_runtime_field(&regex_query.field, "regex")?;
+        Ok(Some(QueryAst::Regex(regex_query)))
+    }
+
+    fn transform_fuzzy(&mut self, fuzzy_query: FuzzyQuery) -> anyhow::Result<Option<QueryAst>> {
+        self.check_not_runtime_field(&fuzzy_query.field, "fuzzy")?;
+        Ok(Some(QueryAst::Fuzzy(fuzzy_query)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::query_ast::qast_helper;
+
+    fn runtime_mappings() -> RuntimeMappings {
+        serde_json::from_value(json!({
+            "status_class": {"type": "long", "script": "to_int!(.status) / 100"}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_runtime_fields() {
+        let runtime_mappings = runtime_mappings();
+        let query_ast = qast_helper("status_class:5 AND service:api", &[]);
+        let resolved_query_ast = resolve_runtime_fields(query_ast, &runtime_mappings).unwrap();
+        let QueryAst::Bool(bool_query) = resolved_query_ast else {
+            panic!("expected a boolean query, got {resolved_query_ast:?}");
+        };
+        assert_eq!(bool_query.must.len(), 2);
+        assert_eq!(
+            bool_query.must[0],
+            QueryAst::RuntimeField(RuntimeFieldQuery {
+                field: "status_class".to_string(),
+                runtime_field: runtime_mappings["status_class"].clone(),
+                predicate: RuntimeFieldPredicate::Terms(BTreeSet::from(["5".to_string()])),
+            })
+        );
+        assert!(matches!(bool_query.must[1], QueryAst::FullText(_)));
+
+        let query_ast = qast_helper("status_class:[4 TO 5}", &[]);
+        let resolved_query_ast = resolve_runtime_fields(query_ast, &runtime_mappings).unwrap();
+        let QueryAst::RuntimeField(runtime_field_query) = resolved_query_ast else {
+            panic!("expected a runtime field query, got {resolved_query_ast:?}");
+        };
+        assert!(matches!(
+            runtime_field_query.predicate,
+            RuntimeFieldPredicate::Range {
+                lower_bound: Bound::Included(_),
+                upper_bound: Bound::Excluded(_),
+            }
+        ));
+
+        let query_ast = qast_helper("status_class:5*", &[]);
+        let error = resolve_runtime_fields(query_ast, &runtime_mappings).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "phrase prefix queries are not supported on runtime field `status_class`"
+        );
+    }
+
+    #[test]
+    fn test_runtime_value_matcher() {
+        let runtime_field_query = RuntimeFieldQuery {
+            field: "status_class".to_string(),
+            runtime_field: runtime_mappings()["status_class"].clone(),
+            predicate: RuntimeFieldPredicate::Range {
+                lower_bound: Bound::Included(JsonLiteral::String("4".to_string())),
+                upper_bound: Bound::Excluded(JsonLiteral::Number(5.into())),
+            },
+        };
+        let matcher = runtime_field_query.build_matcher().unwrap();
+        assert!(matcher.matches(&[RuntimeValue::Long(4)]));
+        assert!(matcher.matches(&[RuntimeValue::Long(2), RuntimeValue::Long(4)]));
+        assert!(!matcher.matches(&[RuntimeValue::Long(5)]));
+        assert!(!matcher.matches(&[]));
+
+        let runtime_field_query = RuntimeFieldQuery {
+            predicate: RuntimeFieldPredicate::Terms(BTreeSet::from(["error".to_string()])),
+            ..runtime_field_query
+        };
+        let error = runtime_field_query.build_matcher().unwrap_err();
+        assert!(matches!(error, InvalidQuery::InvalidSearchTerm { .. }));
+    }
+
+    #[cfg(feature = "vrl")]
+    #[test]
+    fn test_runtime_field_query() {
+        use tantivy::collector::DocSetCollector;
+        use tantivy::schema::{Schema, FAST, STORED};
+        use tantivy::{doc, Index};
+
+        use crate::create_default_quickwit_tokenizer_manager;
+
+        let mut schema_builder = Schema::builder();
+        let status_field = schema_builder.add_u64_field("status", FAST | STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for status in [200u64, 404, 500, 503] {
+            index_writer
+                .add_document(doc!(status_field => status))
+                .unwrap();
+        }
+        index_writer.add_document(doc!()).unwrap();
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        let query_ast =
+            resolve_runtime_fields(qast_helper("status_class:5", &[]), &runtime_mappings())
+                .unwrap();
+        let query = query_ast
+            .build_tantivy_query(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap();
+        let mut docs: Vec<DocId> = searcher
+            .search(&query, &DocSetCollector)
+            .unwrap()
+            .into_iter()
+            .map(|doc_address| doc_address.doc_id)
+            .collect();
+        docs.sort_unstable();
+        assert_eq!(docs, [2, 3]);
+
+        let query_ast =
+            resolve_runtime_fields(qast_helper("status_class:*", &[]), &runtime_mappings())
+                .unwrap();
+        let query = query_ast
+            .build_tantivy_query(
+                &schema,
+                &create_default_quickwit_tokenizer_manager(),
+                &[],
+                true,
+            )
+            .unwrap();
+        assert_eq!(searcher.search(&query, &DocSetCollector).unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use tantivy::query::Scorer;
+use tantivy::{DocId, DocSet, Score, TERMINATED};
+
+/// Iterates over a list of `(doc, score)` pairs computed ahead of time.
+///
+/// The pairs must be sorted by doc id.
+pub(crate) struct ScoredDocsScorer {
+    scored_docs: Vec<(DocId, Score)>,
+    cursor: usize,
+    boost: Score,
+}
+
+impl ScoredDocsScorer {
+    pub fn new(scored_docs: Vec<(DocId, Score)>, boost: Score) -> Self {
+        debug_assert!(scored_docs.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        ScoredDocsScorer {
+            scored_docs,
+            cursor: 0,
+            boost,
+        }
+    }
+}
+
+impl DocSet for ScoredDocsScorer {
+    fn advance(&mut self) -> DocId {
+        if self.cursor < self.scored_docs.len() {
+            self.cursor += 1;
+        }
+        self.doc()
+    }
+
+    fn doc(&self) -> DocId {
+        self.scored_docs
+            .get(self.cursor)
+            .map(|(doc, _score)| *doc)
+            .unwrap_or(TERMINATED)
+    }
+
+    fn size_hint(&self) -> u32 {
+        (self.scored_docs.len() - self.cursor) as u32
+    }
+}
+
+impl Scorer for ScoredDocsScorer {
+    fn score(&mut self) -> Score {
+        self.scored_docs
+            .get(self.cursor)
+            .map(|(_doc, score)| *score * self.boost)
+            .unwrap_or(0.0)
+    }
+}
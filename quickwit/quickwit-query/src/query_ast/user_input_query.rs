@@ -37,6 +37,9 @@ use crate::{BooleanOperand, InvalidQuery, JsonLiteral};
 
 const DEFAULT_PHRASE_QUERY_MAX_EXPANSION: u32 = 50;
 
+/// Distance of fuzzy queries written without an explicit distance, e.g. `term~`.
+const DEFAULT_FUZZY_DISTANCE: u8 = 2;
+
 /// A query expressed in the tantivy query grammar DSL.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct UserInputQuery {
@@ -227,6 +230,53 @@ fn is_wildcard(phrase: &str) -> bool {
         .is_break()
 }
 
+/// Returns the regular expression of a `/regex/` literal.
+///
+/// Slashes within the regular expression must be escaped (`\/`), so that terms such as paths
+/// (`/var/log/`) are not mistaken for regex literals.
+fn extract_regex(phrase: &str) -> Option<String> {
+    let mut chars = phrase.strip_prefix('/')?.strip_suffix('/')?.chars();
+    let mut regex = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                // A trailing backslash escapes the closing slash.
+                let escaped_char = chars.next()?;
+
+                if escaped_char != '/' {
+                    regex.push('\\');
+                }
+                regex.push(escaped_char);
+            }
+            '/' => return None,
+            _ => regex.push(c),
+        }
+    }
+    if regex.is_empty() {
+        return None;
+    }
+    Some(regex)
+}
+
+/// Returns the value and the distance of a `value~` or `value~distance` literal.
+fn extract_fuzzy(phrase: &str) -> Option<(&str, u8)> {
+    let (value, distance_str) = phrase.rsplit_once('~')?;
+    // `\~` is an escaped tilde.
+    if value.is_empty() || value.ends_with('\\') {
+        return None;
+    }
+    if distance_str.is_empty() {
+        return Some((value, DEFAULT_FUZZY_DISTANCE));
+    }
+    if !distance_str.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    // Distances that do not fit in a `u8` are rejected when building the query.
+    let distance = distance_str.parse::<u8>().unwrap_or(u8::MAX);
+    Some((value, distance))
+}
+
 /// Convert a leaf of a text query AST to a QueryAst.
 /// This may generate more than a single leaf if there are multiple default fields.
 fn convert_user_input_literal(
@@ -264,11 +314,40 @@ fn convert_user_input_literal(
         mode,
         zero_terms_query: crate::MatchAllOrNone::MatchNone,
     };
+    let regex_opt = if delimiter == Delimiter::None {
+        extract_regex(&phrase)
+    } else {
+        None
+    };
+    let fuzzy_opt = if delimiter != Delimiter::None || regex_opt.is_some() {
+        None
+    } else if slop > 0 {
+        // The grammar may have parsed the `~distance` suffix of the term as a slop.
+        Some((phrase.as_str(), slop.min(u8::MAX as u32) as u8))
+    } else {
+        extract_fuzzy(&phrase)
+    };
     let wildcard = delimiter == Delimiter::None && is_wildcard(&phrase);
     let mut phrase_queries: Vec<QueryAst> = field_names
         .into_iter()
         .map(|field_name| {
-            if prefix {
+            if let Some(regex) = &regex_opt {
+                query_ast::RegexQuery {
+                    field: field_name,
+                    regex: regex.clone(),
+                    lenient,
+                }
+                .into()
+            } else if let Some((value, distance)) = fuzzy_opt {
+                query_ast::FuzzyQuery {
+                    field: field_name,
+                    value: value.to_string(),
+                    distance,
+                    transpositions: true,
+                    lenient,
+                }
+                .into()
+            } else if prefix {
                 query_ast::PhrasePrefixQuery {
                     field: field_name,
                     phrase: phrase.clone(),
@@ -310,10 +389,14 @@ fn convert_user_input_literal(
 
 #[cfg(test)]
 mod tests {
+    use super::{extract_fuzzy, extract_regex};
     use crate::query_ast::{
-        BoolQuery, BuildTantivyAst, FullTextMode, FullTextQuery, QueryAst, UserInputQuery,
+        BoolQuery, BuildTantivyAst, FullTextMode, FullTextParams, FullTextQuery, FuzzyQuery,
+        QueryAst, RegexQuery, UserInputQuery,
+    };
+    use crate::{
+        create_default_quickwit_tokenizer_manager, BooleanOperand, InvalidQuery, MatchAllOrNone,
     };
-    use crate::{create_default_quickwit_tokenizer_manager, BooleanOperand, InvalidQuery};
 
     #[test]
     fn test_user_input_query_not_parsed_error() {
@@ -423,6 +506,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_user_input_query_regex_and_fuzzy() {
+        let parse_user_query = |user_text: &str| {
+            UserInputQuery {
+                user_text: user_text.to_string(),
+                default_fields: None,
+                default_operator: BooleanOperand::And,
+                lenient: false,
+            }
+            .parse_user_query(&["body".to_string()])
+            .unwrap()
+        };
+        assert_eq!(
+            parse_user_query("service:/api-.*-prod/"),
+            QueryAst::Regex(RegexQuery {
+                field: "service".to_string(),
+                regex: "api-.*-prod".to_string(),
+                lenient: false,
+            })
+        );
+        assert_eq!(
+            parse_user_query("path:/var/log/"),
+            QueryAst::FullText(FullTextQuery {
+                field: "path".to_string(),
+                text: "/var/log/".to_string(),
+                params: FullTextParams {
+                    tokenizer: None,
+                    mode: FullTextMode::PhraseFallbackToIntersection,
+                    zero_terms_query: MatchAllOrNone::MatchNone,
+                },
+                lenient: false,
+            })
+        );
+        assert_eq!(
+            parse_user_query("service:paymnets~1"),
+            QueryAst::Fuzzy(FuzzyQuery {
+                field: "service".to_string(),
+                value: "paymnets".to_string(),
+                distance: 1,
+                transpositions: true,
+                lenient: false,
+            })
+        );
+        assert_eq!(
+            parse_user_query("paymnets~"),
+            QueryAst::Fuzzy(FuzzyQuery {
+                field: "body".to_string(),
+                value: "paymnets".to_string(),
+                distance: 2,
+                transpositions: true,
+                lenient: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_extract_regex() {
+        assert_eq!(extract_regex("/ab.*/").as_deref(), Some("ab.*"));
+        assert_eq!(extract_regex(r"/a\d+/").as_deref(), Some(r"a\d+"));
+        assert_eq!(extract_regex(r"/var\/log/").as_deref(), Some("var/log"));
+        assert_eq!(extract_regex("/"), None);
+        assert_eq!(extract_regex("//"), None);
+        assert_eq!(extract_regex("/ab"), None);
+        assert_eq!(extract_regex("/var/log/"), None);
+        assert_eq!(extract_regex(r"/ab\/"), None);
+    }
+
+    #[test]
+    fn test_extract_fuzzy() {
+        assert_eq!(extract_fuzzy("foo~"), Some(("foo", 2)));
+        assert_eq!(extract_fuzzy("foo~0"), Some(("foo", 0)));
+        assert_eq!(extract_fuzzy("foo~300"), Some(("foo", u8::MAX)));
+        assert_eq!(extract_fuzzy("foo"), None);
+        assert_eq!(extract_fuzzy("~1"), None);
+        assert_eq!(extract_fuzzy("foo~bar"), None);
+        assert_eq!(extract_fuzzy(r"foo\~"), None);
+    }
+
     #[test]
     fn test_user_input_query_override_default_fields() {
         let ast = UserInputQuery {
@@ -21,8 +21,9 @@ use crate::not_nan_f32::NotNaNf32;
 use crate::query_ast::field_presence::FieldPresenceQuery;
 use crate::query_ast::user_input_query::UserInputQuery;
 use crate::query_ast::{
-    BoolQuery, FullTextQuery, PhrasePrefixQuery, QueryAst, RangeQuery, TermQuery, TermSetQuery,
-    WildcardQuery,
+    BoolQuery, DisMaxQuery, FullTextQuery, FuzzyQuery, GeoBoundingBoxQuery, GeoDistanceQuery,
+    IdsQuery, KnnQuery, NestedQuery, PhrasePrefixQuery, QueryAst, RangeQuery, RegexQuery,
+    RuntimeFieldQuery, TermQuery, TermSetQuery, WildcardQuery,
 };
 
 /// Simple trait to implement a Visitor over the QueryAst.
@@ -32,6 +33,7 @@ pub trait QueryAstVisitor<'a> {
     fn visit(&mut self, query_ast: &'a QueryAst) -> Result<(), Self::Err> {
         match query_ast {
             QueryAst::Bool(bool_query) => self.visit_bool(bool_query),
+            QueryAst::DisMax(dis_max_query) => self.visit_dis_max(dis_max_query),
             QueryAst::Term(term_query) => self.visit_term(term_query),
             QueryAst::TermSet(term_set_query) => self.visit_term_set(term_set_query),
             QueryAst::FullText(full_text_query) => self.visit_full_text(full_text_query),
@@ -45,6 +47,16 @@ pub trait QueryAstVisitor<'a> {
             QueryAst::UserInput(user_text_query) => self.visit_user_text(user_text_query),
             QueryAst::FieldPresence(exists) => self.visit_exists(exists),
             QueryAst::Wildcard(wildcard) => self.visit_wildcard(wildcard),
+            QueryAst::Regex(regex) => self.visit_regex(regex),
+            QueryAst::Fuzzy(fuzzy) => self.visit_fuzzy(fuzzy),
+            QueryAst::GeoBoundingBox(geo_bounding_box) => {
+                self.visit_geo_bounding_box(geo_bounding_box)
+            }
+            QueryAst::GeoDistance(geo_distance) => self.visit_geo_distance(geo_distance),
+            QueryAst::Ids(ids) => self.visit_ids(ids),
+            QueryAst::Knn(knn) => self.visit_knn(knn),
+            QueryAst::Nested(nested) => self.visit_nested(nested),
+            QueryAst::RuntimeField(runtime_field) => self.visit_runtime_field(runtime_field),
         }
     }
 
@@ -61,6 +73,13 @@ pub trait QueryAstVisitor<'a> {
         Ok(())
     }
 
+    fn visit_dis_max(&mut self, dis_max_query: &'a DisMaxQuery) -> Result<(), Self::Err> {
+        for ast in &dis_max_query.disjuncts {
+            self.visit(ast)?;
+        }
+        Ok(())
+    }
+
     fn visit_term(&mut self, _term_query: &'a TermQuery) -> Result<(), Self::Err> {
         Ok(())
     }
@@ -111,6 +130,50 @@ pub trait QueryAstVisitor<'a> {
     fn visit_wildcard(&mut self, _wildcard_query: &'a WildcardQuery) -> Result<(), Self::Err> {
         Ok(())
     }
+
+    fn visit_regex(&mut self, _regex_query: &'a RegexQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_fuzzy(&mut self, _fuzzy_query: &'a FuzzyQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_geo_bounding_box(
+        &mut self,
+        _geo_bounding_box_query: &'a GeoBoundingBoxQuery,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_geo_distance(
+        &mut self,
+        _geo_distance_query: &'a GeoDistanceQuery,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_ids(&mut self, _ids_query: &'a IdsQuery) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn visit_knn(&mut self, knn_query: &'a KnnQuery) -> Result<(), Self::Err> {
+        if let Some(filter) = &knn_query.filter {
+            self.visit(filter)?;
+        }
+        Ok(())
+    }
+
+    fn visit_nested(&mut self, nested_query: &'a NestedQuery) -> Result<(), Self::Err> {
+        self.visit(&nested_query.query)
+    }
+
+    fn visit_runtime_field(
+        &mut self,
+        _runtime_field_query: &'a RuntimeFieldQuery,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
 }
 
 /// Simple trait to implement a Visitor over the QueryAst.
@@ -120,6 +183,7 @@ pub trait QueryAstTransformer {
     fn transform(&mut self, query_ast: QueryAst) -> Result<Option<QueryAst>, Self::Err> {
         match query_ast {
             QueryAst::Bool(bool_query) => self.transform_bool(bool_query),
+            QueryAst::DisMax(dis_max_query) => self.transform_dis_max(dis_max_query),
             QueryAst::Term(term_query) => self.transform_term(term_query),
             QueryAst::TermSet(term_set_query) => self.transform_term_set(term_set_query),
             QueryAst::FullText(full_text_query) => self.transform_full_text(full_text_query),
@@ -133,6 +197,16 @@ pub trait QueryAstTransformer {
             QueryAst::UserInput(user_text_query) => self.transform_user_text(user_text_query),
             QueryAst::FieldPresence(exists) => self.transform_exists(exists),
             QueryAst::Wildcard(wildcard) => self.transform_wildcard(wildcard),
+            QueryAst::Regex(regex) => self.transform_regex(regex),
+            QueryAst::Fuzzy(fuzzy) => self.transform_fuzzy(fuzzy),
+            QueryAst::GeoBoundingBox(geo_bounding_box) => {
+                self.transform_geo_bounding_box(geo_bounding_box)
+            }
+            QueryAst::GeoDistance(geo_distance) => self.transform_geo_distance(geo_distance),
+            QueryAst::Ids(ids) => self.transform_ids(ids),
+            QueryAst::Knn(knn) => self.transform_knn(knn),
+            QueryAst::Nested(nested) => self.transform_nested(nested),
+            QueryAst::RuntimeField(runtime_field) => self.transform_runtime_field(runtime_field),
         }
     }
 
@@ -161,6 +235,18 @@ pub trait QueryAstTransformer {
         Ok(Some(QueryAst::Bool(bool_query)))
     }
 
+    fn transform_dis_max(
+        &mut self,
+        mut dis_max_query: DisMaxQuery,
+    ) -> Result<Option<QueryAst>, Self::Err> {
+        dis_max_query.disjuncts = dis_max_query
+            .disjuncts
+            .into_iter()
+            .filter_map(|query_ast| self.transform(query_ast).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(QueryAst::DisMax(dis_max_query)))
+    }
+
     fn transform_term(&mut self, term_query: TermQuery) -> Result<Option<QueryAst>, Self::Err> {
         Ok(Some(QueryAst::Term(term_query)))
     }
@@ -231,4 +317,55 @@ pub trait QueryAstTransformer {
     ) -> Result<Option<QueryAst>, Self::Err> {
         Ok(Some(QueryAst::Wildcard(wildcard_query)))
     }
+
+    fn transform_regex(&mut self, regex_query: RegexQuery) -> Result<Option<QueryAst>, Self::Err> {
+        Ok(Some(QueryAst::Regex(regex_query)))
+    }
+
+    fn transform_fuzzy(&mut self, fuzzy_query: FuzzyQuery) -> Result<Option<QueryAst>, Self::Err> {
+        Ok(Some(QueryAst::Fuzzy(fuzzy_query)))
+    }
+
+    fn transform_geo_bounding_box(
+        &mut self,
+        geo_bounding_box_query: GeoBoundingBoxQuery,
+    ) -> Result<Option<QueryAst>, Self::Err> {
+        Ok(Some(QueryAst::GeoBoundingBox(geo_bounding_box_query)))
+    }
+
+    fn transform_geo_distance(
+        &mut self,
+        geo_distance_query: GeoDistanceQuery,
+    ) -> Result<Option<QueryAst>, Self::Err> {
+        Ok(Some(QueryAst::GeoDistance(geo_distance_query)))
+    }
+
+    fn transform_ids(&mut self, ids_query: IdsQuery) -> Result<Option<QueryAst>, Self::Err> {
+        Ok(Some(QueryAst::Ids(ids_query)))
+    }
+
+    fn transform_knn(&mut self, mut knn_query: KnnQuery) -> Result<Option<QueryAst>, Self::Err> {
+        if let Some(filter) = knn_query.filter.take() {
+            knn_query.filter = self.transform(*filter)?.map(Box::new);
+        }
+        Ok(Some(QueryAst::Knn(knn_query)))
+    }
+
+    fn transform_nested(
+        &mut self,
+        mut nested_query: NestedQuery,
+    ) -> Result<Option<QueryAst>, Self::Err> {
+        let Some(query) = self.transform(*nested_query.query)? else {
+            return Ok(None);
+        };
+        nested_query.query = Box::new(query);
+        Ok(Some(QueryAst::Nested(nested_query)))
+    }
+
+    fn transform_runtime_field(
+        &mut self,
+        runtime_field_query: RuntimeFieldQuery,
+    ) -> Result<Option<QueryAst>, Self::Err> {
+        Ok(Some(QueryAst::RuntimeField(runtime_field_query)))
+    }
 }
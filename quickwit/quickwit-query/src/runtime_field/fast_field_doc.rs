@@ -0,0 +1,190 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use tantivy::columnar::DynamicColumn;
+use tantivy::{DocId, SegmentReader};
+use time::format_description::well_known::Rfc3339;
+
+/// Builds the JSON documents given to runtime field scripts from the fast fields of a segment.
+///
+/// Only the input fields of the scripts are read. Fields with a single value are represented by
+/// a scalar, fields with several values by an array, and fields without value are absent.
+pub struct FastFieldDocReader {
+    columns: Vec<(Vec<String>, Vec<DynamicColumn>)>,
+}
+
+impl FastFieldDocReader {
+    pub fn open(segment_reader: &SegmentReader, input_fields: &[String]) -> tantivy::Result<Self> {
+        let mut columns = Vec::with_capacity(input_fields.len());
+
+        for input_field in input_fields {
+            let field_columns: Vec<DynamicColumn> = segment_reader
+                .fast_fields()
+                .dynamic_column_handles(input_field)?
+                .into_iter()
+                .map(|column_handle| column_handle.open())
+                .collect::<std::io::Result<_>>()?;
+            let path: Vec<String> = input_field.split('.').map(ToString::to_string).collect();
+            columns.push((path, field_columns));
+        }
+        Ok(FastFieldDocReader { columns })
+    }
+
+    /// Returns the JSON document of `doc`.
+    pub fn read_doc(&self, doc: DocId) -> tantivy::Result<serde_json::Value> {
+        let mut json_doc = serde_json::Value::Object(serde_json::Map::new());
+        let mut values = Vec::new();
+
+        for (path, field_columns) in &self.columns {
+            values.clear();
+            for column in field_columns {
+                read_column_values(column, doc, &mut values)?;
+            }
+            let json_value = match values.len() {
+                0 => continue,
+                1 => values[0].clone(),
+                _ => serde_json::Value::Array(values.clone()),
+            };
+            insert_at_path(&mut json_doc, path, json_value);
+        }
+        Ok(json_doc)
+    }
+}
+
+fn read_column_values(
+    column: &DynamicColumn,
+    doc: DocId,
+    values: &mut Vec<serde_json::Value>,
+) -> tantivy::Result<()> {
+    match column {
+        DynamicColumn::Bool(column) => {
+            values.extend(column.values_for_doc(doc).map(serde_json::Value::from));
+        }
+        DynamicColumn::I64(column) => {
+            values.extend(column.values_for_doc(doc).map(serde_json::Value::from));
+        }
+        DynamicColumn::U64(column) => {
+            values.extend(column.values_for_doc(doc).map(serde_json::Value::from));
+        }
+        DynamicColumn::F64(column) => {
+            values.extend(column.values_for_doc(doc).map(serde_json::Value::from));
+        }
+        DynamicColumn::IpAddr(column) => {
+            values.extend(column.values_for_doc(doc).map(|ip_addr| {
+                let ip_addr_str = if let Some(ipv4_addr) = ip_addr.to_ipv4_mapped() {
+                    ipv4_addr.to_string()
+                } else {
+                    ip_addr.to_string()
+                };
+                serde_json::Value::String(ip_addr_str)
+            }));
+        }
+        DynamicColumn::DateTime(column) => {
+            for date_time in column.values_for_doc(doc) {
+                if let Ok(date_time_str) = date_time.into_utc().format(&Rfc3339) {
+                    values.push(serde_json::Value::String(date_time_str));
+                }
+            }
+        }
+        DynamicColumn::Bytes(column) => {
+            let mut buffer = Vec::new();
+            for term_ord in column.term_ords(doc) {
+                buffer.clear();
+                column.ord_to_bytes(term_ord, &mut buffer)?;
+                values.push(serde_json::Value::String(BASE64_STANDARD.encode(&buffer)));
+            }
+        }
+        DynamicColumn::Str(column) => {
+            let mut buffer = String::new();
+            for term_ord in column.term_ords(doc) {
+                buffer.clear();
+                column.ord_to_str(term_ord, &mut buffer)?;
+                values.push(serde_json::Value::String(buffer.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn insert_at_path(
+    json_doc: &mut serde_json::Value,
+    path: &[String],
+    json_value: serde_json::Value,
+) {
+    let Some((last_key, parent_keys)) = path.split_last() else {
+        return;
+    };
+    let mut current = json_doc;
+    for key in parent_keys {
+        let serde_json::Value::Object(map) = current else {
+            return;
+        };
+        current = map
+            .entry(key.clone())
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+    }
+    if let serde_json::Value::Object(map) = current {
+        map.insert(last_key.clone(), json_value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tantivy::schema::{Schema, FAST, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_fast_field_doc_reader() {
+        let mut schema_builder = Schema::builder();
+        let status_field = schema_builder.add_u64_field("status", FAST);
+        let host_field = schema_builder.add_text_field("host", STRING | FAST);
+        let attributes_field = schema_builder.add_json_field("attributes", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        index_writer
+            .add_document(doc!(
+                status_field => 200u64,
+                host_field => "a",
+                host_field => "b",
+                attributes_field => json!({"region": "eu"}),
+            ))
+            .unwrap();
+        index_writer.add_document(doc!()).unwrap();
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let input_fields = [
+            "status".to_string(),
+            "host".to_string(),
+            "attributes.region".to_string(),
+        ];
+        let fast_field_doc_reader =
+            FastFieldDocReader::open(searcher.segment_reader(0), &input_fields).unwrap();
+        assert_eq!(
+            fast_field_doc_reader.read_doc(0).unwrap(),
+            json!({"status": 200, "host": ["a", "b"], "attributes": {"region": "eu"}})
+        );
+        assert_eq!(fast_field_doc_reader.read_doc(1).unwrap(), json!({}));
+    }
+}
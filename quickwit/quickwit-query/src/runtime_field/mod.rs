@@ -0,0 +1,488 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime fields are computed at query time by running a script on each document.
+//!
+//! They are declared in the `runtime_mappings` of a search request and can be used like regular
+//! fields in queries, aggregations and in the returned documents. Scripts are written in VRL and
+//! read their input from the fast fields of the document, whether they filter, aggregate or are
+//! returned in the `fields` of the hits. Scripts can only call pure VRL functions.
+//!
+//! Running the scripts requires Quickwit to be compiled with the `vrl` feature.
+
+mod fast_field_doc;
+#[cfg(feature = "vrl")]
+mod script;
+
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(feature = "vrl")]
+use std::sync::Arc;
+
+pub use fast_field_doc::FastFieldDocReader;
+use serde::{Deserialize, Serialize};
+use tantivy::schema::Schema as TantivySchema;
+
+use crate::{find_field_or_hit_dynamic, InvalidQuery, JsonLiteral};
+
+/// Runtime fields of a search request, indexed by name.
+pub type RuntimeMappings = BTreeMap<String, RuntimeField>;
+
+/// Type of the values of a runtime field.
+///
+/// The values returned by the script are coerced to this type. Values that cannot be coerced are
+/// ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeFieldType {
+    Keyword,
+    Long,
+    Double,
+    Boolean,
+}
+
+impl RuntimeFieldType {
+    /// Coerces a JSON value to this type. Arrays are flattened and `null` has no value.
+    pub fn coerce_values(&self, json_value: &serde_json::Value) -> Vec<RuntimeValue> {
+        match json_value {
+            serde_json::Value::Null => Vec::new(),
+            serde_json::Value::Array(json_values) => json_values
+                .iter()
+                .filter_map(|json_value| self.coerce(json_value))
+                .collect(),
+            json_value => self.coerce(json_value).into_iter().collect(),
+        }
+    }
+
+    /// Coerces a scalar JSON value to this type.
+    pub fn coerce(&self, json_value: &serde_json::Value) -> Option<RuntimeValue> {
+        use serde_json::Value as JsonValue;
+        match (self, json_value) {
+            (RuntimeFieldType::Keyword, JsonValue::String(text)) => {
+                Some(RuntimeValue::Keyword(text.clone()))
+            }
+            (RuntimeFieldType::Keyword, JsonValue::Number(number)) => {
+                Some(RuntimeValue::Keyword(number.to_string()))
+            }
+            (RuntimeFieldType::Keyword, JsonValue::Bool(bool_val)) => {
+                Some(RuntimeValue::Keyword(bool_val.to_string()))
+            }
+            (RuntimeFieldType::Long, JsonValue::Number(number)) => {
+                if let Some(val) = number.as_i64() {
+                    return Some(RuntimeValue::Long(val));
+                }
+                let val = number.as_f64().filter(|val| val.is_finite())?;
+                Some(RuntimeValue::Long(val as i64))
+            }
+            (RuntimeFieldType::Long, JsonValue::String(text)) => {
+                if let Ok(val) = text.trim().parse::<i64>() {
+                    return Some(RuntimeValue::Long(val));
+                }
+                let val = text
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|val| val.is_finite())?;
+                Some(RuntimeValue::Long(val as i64))
+            }
+            (RuntimeFieldType::Double, JsonValue::Number(number)) => {
+                number.as_f64().map(RuntimeValue::Double)
+            }
+            (RuntimeFieldType::Double, JsonValue::String(text)) => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|val| val.is_finite())
+                .map(RuntimeValue::Double),
+            (RuntimeFieldType::Boolean, JsonValue::Bool(bool_val)) => {
+                Some(RuntimeValue::Boolean(*bool_val))
+            }
+            (RuntimeFieldType::Boolean, JsonValue::String(text)) => match text.as_str() {
+                "true" => Some(RuntimeValue::Boolean(true)),
+                "false" => Some(RuntimeValue::Boolean(false)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Coerces a value of a query to this type.
+    pub fn coerce_literal(&self, json_literal: &JsonLiteral) -> Option<RuntimeValue> {
+        let json_value = match json_literal {
+            JsonLiteral::Number(number) => serde_json::Value::Number(number.clone()),
+            JsonLiteral::String(text) => serde_json::Value::String(text.clone()),
+            JsonLiteral::Bool(bool_val) => serde_json::Value::Bool(*bool_val),
+        };
+        self.coerce(&json_value)
+    }
+}
+
+/// Value of a runtime field.
+///
+/// The values of a given runtime field all have the same variant, so the derived order is the
+/// natural order of the values.
+#[derive(Serialize, Debug, Clone, PartialEq, PartialOrd)]
+#[serde(untagged)]
+pub enum RuntimeValue {
+    Keyword(String),
+    Long(i64),
+    Double(f64),
+    Boolean(bool),
+}
+
+impl RuntimeValue {
+    /// Returns the value as a JSON value.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            RuntimeValue::Keyword(text) => serde_json::Value::String(text.clone()),
+            RuntimeValue::Long(val) => serde_json::Value::from(*val),
+            RuntimeValue::Double(val) => serde_json::Value::from(*val),
+            RuntimeValue::Boolean(bool_val) => serde_json::Value::Bool(*bool_val),
+        }
+    }
+}
+
+/// Definition of a runtime field.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeField {
+    #[serde(rename = "type")]
+    pub field_type: RuntimeFieldType,
+    pub script: RuntimeFieldScript,
+}
+
+/// Script computing the values of a runtime field.
+///
+/// The values of the field are the result of the script: a scalar, an array of scalars, or
+/// `null` when the document has no value. The document is the target of the script, so its
+/// fields are read with paths such as `.status` or `.user.name`.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(try_from = "RuntimeFieldScriptForDeserialization")]
+pub struct RuntimeFieldScript {
+    pub source: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuntimeFieldScriptForDeserialization {
+    Source(String),
+    Script {
+        source: String,
+        #[serde(default)]
+        lang: Option<String>,
+    },
+}
+
+impl TryFrom<RuntimeFieldScriptForDeserialization> for RuntimeFieldScript {
+    type Error = String;
+
+    fn try_from(script: RuntimeFieldScriptForDeserialization) -> Result<Self, String> {
+        match script {
+            RuntimeFieldScriptForDeserialization::Source(source) => {
+                Ok(RuntimeFieldScript { source })
+            }
+            RuntimeFieldScriptForDeserialization::Script { source, lang } => {
+                if let Some(lang) = lang.filter(|lang| lang != "vrl") {
+                    return Err(format!(
+                        "unsupported script language `{lang}`, runtime fields only support `vrl`"
+                    ));
+                }
+                Ok(RuntimeFieldScript { source })
+            }
+        }
+    }
+}
+
+impl RuntimeField {
+    /// Compiles the script of the runtime field.
+    ///
+    /// Compiled scripts are cached, so the script of a runtime field is compiled once even though
+    /// the query is built for every split of the request.
+    pub fn compile(&self) -> anyhow::Result<CompiledRuntimeField> {
+        #[cfg(feature = "vrl")]
+        {
+            let program = script::ScriptProgram::compile_cached(&self.script.source)?;
+            Ok(CompiledRuntimeField {
+                field_type: self.field_type,
+                program,
+            })
+        }
+        #[cfg(not(feature = "vrl"))]
+        {
+            anyhow::bail!("runtime fields require Quickwit to be compiled with the `vrl` feature")
+        }
+    }
+}
+
+/// Compiled script of a runtime field. Cloning it is cheap.
+#[derive(Clone)]
+pub struct CompiledRuntimeField {
+    field_type: RuntimeFieldType,
+    #[cfg(feature = "vrl")]
+    program: Arc<script::ScriptProgram>,
+}
+
+impl CompiledRuntimeField {
+    pub fn field_type(&self) -> RuntimeFieldType {
+        self.field_type
+    }
+
+    /// Returns the paths of the document fields read by the script.
+    pub fn input_fields(&self) -> &[String] {
+        #[cfg(feature = "vrl")]
+        {
+            self.program.input_fields()
+        }
+        #[cfg(not(feature = "vrl"))]
+        {
+            &[]
+        }
+    }
+
+    /// Checks that the fields read by the script are fast fields of the schema.
+    pub fn check_input_fields(
+        &self,
+        field_name: &str,
+        schema: &TantivySchema,
+    ) -> Result<(), InvalidQuery> {
+        for input_field in self.input_fields() {
+            let (_field, field_entry, _json_path) = find_field_or_hit_dynamic(input_field, schema)?;
+            if !field_entry.is_fast() {
+                return Err(InvalidQuery::SchemaError(format!(
+                    "runtime field `{field_name}` reads field `{input_field}`, which is not a \
+                     fast field"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an evaluator running the script on documents.
+    pub fn evaluator(&self) -> RuntimeFieldEvaluator {
+        RuntimeFieldEvaluator {
+            field_type: self.field_type,
+            #[cfg(feature = "vrl")]
+            program: self.program.clone(),
+            #[cfg(feature = "vrl")]
+            runtime: script::ScriptRuntime::default(),
+        }
+    }
+}
+
+impl fmt::Debug for CompiledRuntimeField {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("CompiledRuntimeField")
+            .field("field_type", &self.field_type)
+            .field("input_fields", &self.input_fields())
+            .finish()
+    }
+}
+
+/// Runs the compiled script of a runtime field on documents, one at a time.
+pub struct RuntimeFieldEvaluator {
+    field_type: RuntimeFieldType,
+    #[cfg(feature = "vrl")]
+    program: Arc<script::ScriptProgram>,
+    #[cfg(feature = "vrl")]
+    runtime: script::ScriptRuntime,
+}
+
+impl RuntimeFieldEvaluator {
+    pub fn field_type(&self) -> RuntimeFieldType {
+        self.field_type
+    }
+
+    /// Returns the paths of the document fields read by the script.
+    pub fn input_fields(&self) -> &[String] {
+        #[cfg(feature = "vrl")]
+        {
+            self.program.input_fields()
+        }
+        #[cfg(not(feature = "vrl"))]
+        {
+            &[]
+        }
+    }
+
+    /// Returns the values of the runtime field for a JSON document.
+    ///
+    /// A document on which the script fails has no value.
+    pub fn evaluate(&mut self, doc: &serde_json::Value) -> Vec<RuntimeValue> {
+        #[cfg(feature = "vrl")]
+        {
+            match self.runtime.run(&self.program, doc) {
+                Ok(json_value) => self.field_type.coerce_values(&json_value),
+                Err(_) => Vec::new(),
+            }
+        }
+        #[cfg(not(feature = "vrl"))]
+        {
+            let _ = doc;
+            Vec::new()
+        }
+    }
+}
+
+/// Parses the runtime mappings of a search request, and checks that their scripts compile.
+pub fn parse_runtime_mappings(runtime_mappings_json: &str) -> anyhow::Result<RuntimeMappings> {
+    let runtime_mappings: RuntimeMappings = serde_json::from_str(runtime_mappings_json)
+        .map_err(|error| anyhow::anyhow!("invalid runtime mappings: {error}"))?;
+    for (field_name, runtime_field) in &runtime_mappings {
+        if field_name.is_empty() {
+            anyhow::bail!("runtime field names must not be empty");
+        }
+        runtime_field
+            .compile()
+            .map_err(|error| anyhow::anyhow!("invalid runtime field `{field_name}`: {error}"))?;
+    }
+    Ok(runtime_mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_runtime_field_deserialize() {
+        let runtime_field: RuntimeField = serde_json::from_value(json!({
+            "type": "long",
+            "script": "to_int!(.status)"
+        }))
+        .unwrap();
+        assert_eq!(runtime_field.field_type, RuntimeFieldType::Long);
+        assert_eq!(runtime_field.script.source, "to_int!(.status)");
+
+        let runtime_field: RuntimeField = serde_json::from_value(json!({
+            "type": "keyword",
+            "script": {"source": "upcase!(.level)", "lang": "vrl"}
+        }))
+        .unwrap();
+        assert_eq!(runtime_field.field_type, RuntimeFieldType::Keyword);
+        assert_eq!(runtime_field.script.source, "upcase!(.level)");
+
+        let error = serde_json::from_value::<RuntimeField>(json!({
+            "type": "keyword",
+            "script": {"source": "emit(doc['level'].value)", "lang": "painless"}
+        }))
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("unsupported script language `painless`"));
+
+        serde_json::from_value::<RuntimeField>(json!({
+            "type": "date",
+            "script": ".timestamp"
+        }))
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_runtime_field_type_coerce() {
+        assert_eq!(
+            RuntimeFieldType::Keyword.coerce_values(&json!(["a", 1, true, null, {"b": 2}])),
+            [
+                RuntimeValue::Keyword("a".to_string()),
+                RuntimeValue::Keyword("1".to_string()),
+                RuntimeValue::Keyword("true".to_string()),
+            ]
+        );
+        assert_eq!(
+            RuntimeFieldType::Long.coerce_values(&json!([1, -2.7, "3", "4.5", "a", true])),
+            [
+                RuntimeValue::Long(1),
+                RuntimeValue::Long(-2),
+                RuntimeValue::Long(3),
+                RuntimeValue::Long(4),
+            ]
+        );
+        assert_eq!(
+            RuntimeFieldType::Double.coerce_values(&json!(["0.5", 2])),
+            [RuntimeValue::Double(0.5), RuntimeValue::Double(2.0)]
+        );
+        assert_eq!(
+            RuntimeFieldType::Boolean.coerce_values(&json!([true, "false", "yes", 1])),
+            [RuntimeValue::Boolean(true), RuntimeValue::Boolean(false)]
+        );
+        assert!(RuntimeFieldType::Long
+            .coerce_values(&json!(null))
+            .is_empty());
+    }
+
+    #[cfg(feature = "vrl")]
+    #[test]
+    fn test_runtime_field_evaluate() {
+        let runtime_field: RuntimeField = serde_json::from_value(json!({
+            "type": "keyword",
+            "script": "parts = split(string!(.request.path), \"/\")\nparts[1]"
+        }))
+        .unwrap();
+        let compiled_runtime_field = runtime_field.compile().unwrap();
+        assert_eq!(compiled_runtime_field.input_fields(), ["request.path"]);
+        let mut evaluator = compiled_runtime_field.evaluator();
+        assert_eq!(
+            evaluator.evaluate(&json!({"request": {"path": "/api/v1"}})),
+            [RuntimeValue::Keyword("api".to_string())]
+        );
+        // The script fails on documents without path.
+        assert!(evaluator.evaluate(&json!({"request": {}})).is_empty());
+    }
+
+    #[cfg(feature = "vrl")]
+    #[test]
+    fn test_runtime_field_rejects_impure_functions() {
+        for script in [
+            "get_env_var!(\"HOME\")",
+            "to_string(now())",
+            "uuid_v4()",
+            "log(.status)\n.status",
+        ] {
+            let runtime_field = RuntimeField {
+                field_type: RuntimeFieldType::Keyword,
+                script: RuntimeFieldScript {
+                    source: script.to_string(),
+                },
+            };
+            let error = runtime_field.compile().unwrap_err();
+            assert!(
+                error.to_string().contains("failed to compile VRL script"),
+                "{script}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_runtime_mappings() {
+        let error = parse_runtime_mappings(r#"{"status": {"type": "long"}}"#).unwrap_err();
+        assert!(error.to_string().contains("invalid runtime mappings"));
+
+        let result = parse_runtime_mappings(
+            r#"{"status": {"type": "long", "script": "to_int!(.status_code)"}}"#,
+        );
+        if cfg!(feature = "vrl") {
+            let runtime_mappings = result.unwrap();
+            assert_eq!(runtime_mappings.len(), 1);
+        } else {
+            let error = result.unwrap_err();
+            assert!(error.to_string().contains("`vrl` feature"));
+        }
+    }
+}
@@ -0,0 +1,271 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use vrl::compiler::runtime::Runtime;
+use vrl::compiler::state::RuntimeState;
+use vrl::compiler::{Program, TargetValueRef, TimeZone};
+use vrl::path::{OwnedSegment, PathPrefix};
+use vrl::value::{Secrets as VrlSecrets, Value as VrlValue};
+
+/// VRL functions available to runtime field scripts.
+///
+/// Scripts run on the searchers for every document they read, so they are restricted to pure
+/// functions: functions reading the environment, the clock, random sources or the network
+/// (`get_env_var`, `now`, `uuid_v4`, `dns_lookup`, ...) or having side effects (`log`, ...) are
+/// rejected at compile time.
+const ALLOWED_FUNCTIONS: &[&str] = &[
+    "abs",
+    "append",
+    "array",
+    "assert",
+    "assert_eq",
+    "boolean",
+    "ceil",
+    "chunks",
+    "compact",
+    "contains",
+    "contains_all",
+    "decode_base16",
+    "decode_base64",
+    "decode_percent",
+    "decode_punycode",
+    "del",
+    "downcase",
+    "encode_base16",
+    "encode_base64",
+    "encode_json",
+    "encode_key_value",
+    "encode_logfmt",
+    "encode_percent",
+    "encode_punycode",
+    "ends_with",
+    "exists",
+    "filter",
+    "find",
+    "flatten",
+    "float",
+    "floor",
+    "for_each",
+    "format_int",
+    "format_number",
+    "format_timestamp",
+    "from_unix_timestamp",
+    "get",
+    "includes",
+    "int",
+    "ip_aton",
+    "ip_cidr_contains",
+    "ip_ntoa",
+    "ip_subnet",
+    "ip_to_ipv6",
+    "ipv6_to_ipv4",
+    "is_array",
+    "is_boolean",
+    "is_empty",
+    "is_float",
+    "is_integer",
+    "is_ipv4",
+    "is_ipv6",
+    "is_json",
+    "is_null",
+    "is_nullish",
+    "is_object",
+    "is_regex",
+    "is_string",
+    "is_timestamp",
+    "join",
+    "keys",
+    "length",
+    "map_keys",
+    "map_values",
+    "match",
+    "match_any",
+    "match_array",
+    "md5",
+    "merge",
+    "mod",
+    "object",
+    "parse_csv",
+    "parse_duration",
+    "parse_float",
+    "parse_int",
+    "parse_json",
+    "parse_key_value",
+    "parse_logfmt",
+    "parse_query_string",
+    "parse_regex",
+    "parse_regex_all",
+    "parse_timestamp",
+    "parse_url",
+    "parse_user_agent",
+    "push",
+    "redact",
+    "replace",
+    "round",
+    "seahash",
+    "set",
+    "sha1",
+    "sha2",
+    "sha3",
+    "slice",
+    "split",
+    "starts_with",
+    "string",
+    "strip_ansi_escape_codes",
+    "strip_whitespace",
+    "strlen",
+    "tally",
+    "tally_value",
+    "timestamp",
+    "to_bool",
+    "to_float",
+    "to_int",
+    "to_regex",
+    "to_string",
+    "to_unix_timestamp",
+    "truncate",
+    "unique",
+    "unnest",
+    "upcase",
+    "values",
+    "zip",
+];
+
+/// Maximum number of compiled scripts kept in cache.
+const SCRIPT_CACHE_CAPACITY: usize = 256;
+
+static SCRIPT_CACHE: Lazy<Mutex<HashMap<String, Arc<ScriptProgram>>>> = Lazy::new(Default::default);
+
+/// Compiled VRL script of a runtime field.
+pub(super) struct ScriptProgram {
+    program: Program,
+    input_fields: Vec<String>,
+}
+
+impl ScriptProgram {
+    pub fn compile(source: &str) -> anyhow::Result<ScriptProgram> {
+        let functions: Vec<Box<dyn vrl::compiler::Function>> = vrl::stdlib::all()
+            .into_iter()
+            .filter(|function| ALLOWED_FUNCTIONS.contains(&function.identifier()))
+            .collect();
+        let compilation_res =
+            vrl::compiler::compile(source, &functions).map_err(|diagnostics| {
+                let formatter = vrl::diagnostic::Formatter::new(source, diagnostics);
+                anyhow::anyhow!("failed to compile VRL script:\n {formatter}")
+            })?;
+        let program = compilation_res.program;
+        let mut input_fields: Vec<String> = Vec::new();
+
+        for target_path in &program.info().target_queries {
+            if target_path.prefix != PathPrefix::Event {
+                continue;
+            }
+            // Array indexes and coalesced segments are resolved by the script itself: the input
+            // field is the longest path of plain field segments.
+            let field_segments: Vec<String> = target_path
+                .path
+                .segments
+                .iter()
+                .map_while(|segment| match segment {
+                    OwnedSegment::Field(field_name) => Some(field_name.to_string()),
+                    _ => None,
+                })
+                .collect();
+            if field_segments.is_empty() {
+                continue;
+            }
+            let input_field = field_segments.join(".");
+            if !input_fields.contains(&input_field) {
+                input_fields.push(input_field);
+            }
+        }
+        Ok(ScriptProgram {
+            program,
+            input_fields,
+        })
+    }
+
+    /// Compiles a script, or returns it from the cache of compiled scripts.
+    pub fn compile_cached(source: &str) -> anyhow::Result<Arc<ScriptProgram>> {
+        if let Some(program) = SCRIPT_CACHE.lock().unwrap().get(source) {
+            return Ok(program.clone());
+        }
+        let program = Arc::new(ScriptProgram::compile(source)?);
+        let mut script_cache = SCRIPT_CACHE.lock().unwrap();
+        if script_cache.len() >= SCRIPT_CACHE_CAPACITY {
+            script_cache.clear();
+        }
+        script_cache.insert(source.to_string(), program.clone());
+        Ok(program)
+    }
+
+    pub fn input_fields(&self) -> &[String] {
+        &self.input_fields
+    }
+}
+
+/// State used to run the scripts.
+pub(super) struct ScriptRuntime {
+    runtime: Runtime,
+    timezone: TimeZone,
+    metadata: VrlValue,
+    secrets: VrlSecrets,
+}
+
+impl Default for ScriptRuntime {
+    fn default() -> Self {
+        ScriptRuntime {
+            runtime: Runtime::new(RuntimeState::default()),
+            timezone: TimeZone::default(),
+            metadata: VrlValue::Object(BTreeMap::new()),
+            secrets: VrlSecrets::default(),
+        }
+    }
+}
+
+impl ScriptRuntime {
+    /// Runs the script on a document and returns its result.
+    pub fn run(
+        &mut self,
+        program: &ScriptProgram,
+        doc: &serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let mut vrl_value: VrlValue = serde_json::from_value(doc.clone())?;
+        let mut target = TargetValueRef {
+            value: &mut vrl_value,
+            metadata: &mut self.metadata,
+            secrets: &mut self.secrets,
+        };
+        let runtime_res = self
+            .runtime
+            .resolve(&mut target, &program.program, &self.timezone);
+
+        if let VrlValue::Object(metadata) = target.metadata {
+            metadata.clear();
+        }
+        self.runtime.clear();
+
+        let result = runtime_res.map_err(|terminate| anyhow::anyhow!("{terminate:?}"))?;
+        Ok(serde_json::to_value(result)?)
+    }
+}
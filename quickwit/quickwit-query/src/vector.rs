@@ -0,0 +1,215 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Dense vector helpers shared by the `dense_vector` field type and the k-NN query.
+//!
+//! A dense vector is indexed as a bytes fast field value: the concatenation of the little endian
+//! encoding of its `f32` components.
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of dimensions of a dense vector, same as Elasticsearch.
+pub const MAX_DENSE_VECTOR_DIMS: usize = 4096;
+
+/// Similarity metric used to score documents against a query vector.
+///
+/// Scores are always positive and a higher score means a more similar vector. They are
+/// computed with the same formulas as Elasticsearch, so that they can be combined with other
+/// query scores.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorSimilarity {
+    /// `(1 + cosine(query, vector)) / 2`
+    #[default]
+    Cosine,
+    /// `(1 + dot_product(query, vector)) / 2`, for unit-length vectors.
+    DotProduct,
+    /// `1 / (1 + l2_norm(query, vector)^2)`
+    L2Norm,
+}
+
+impl VectorSimilarity {
+    /// Returns the score of `vector` with respect to `query_vector`.
+    ///
+    /// Both vectors must have the same number of dimensions.
+    pub fn score(&self, query_vector: &[f32], vector: &[f32]) -> f32 {
+        debug_assert_eq!(query_vector.len(), vector.len());
+        match self {
+            VectorSimilarity::Cosine => {
+                let norms_product = norm(query_vector) * norm(vector);
+                if norms_product == 0.0 {
+                    return 0.0;
+                }
+                let cosine = dot_product(query_vector, vector) / norms_product;
+                (1.0 + cosine.clamp(-1.0, 1.0)) / 2.0
+            }
+            VectorSimilarity::DotProduct => {
+                (1.0 + dot_product(query_vector, vector)).max(0.0) / 2.0
+            }
+            VectorSimilarity::L2Norm => {
+                let squared_distance: f32 = query_vector
+                    .iter()
+                    .zip(vector)
+                    .map(|(left, right)| (left - right) * (left - right))
+                    .sum();
+                1.0 / (1.0 + squared_distance)
+            }
+        }
+    }
+
+    /// Checks that a vector can be scored with this similarity.
+    pub fn validate_vector(&self, vector: &[f32]) -> Result<(), String> {
+        if vector.iter().any(|component| !component.is_finite()) {
+            return Err("vector components must be finite numbers".to_string());
+        }
+        match self {
+            VectorSimilarity::Cosine if norm(vector) == 0.0 => {
+                Err("the `cosine` similarity does not support zero-magnitude vectors".to_string())
+            }
+            VectorSimilarity::DotProduct if (norm(vector) - 1.0).abs() > 1e-4 => {
+                Err("the `dot_product` similarity requires vectors of unit length".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn dot_product(left: &[f32], right: &[f32]) -> f32 {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| left * right)
+        .sum()
+}
+
+fn norm(vector: &[f32]) -> f32 {
+    dot_product(vector, vector).sqrt()
+}
+
+/// Encodes a vector into the bytes stored in the fast field.
+pub fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(vector.len() * 4);
+    for component in vector {
+        bytes.extend_from_slice(&component.to_le_bytes());
+    }
+    bytes
+}
+
+/// Decodes a vector encoded with [`encode_vector`].
+///
+/// Returns `None` if the length of the byte slice is not a multiple of 4.
+pub fn decode_vector(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    let vector = bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+    Some(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_vector() {
+        let vector = [0.5, -1.25, 3.0];
+        let bytes = encode_vector(&vector);
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(decode_vector(&bytes).unwrap(), vector);
+        assert!(decode_vector(&bytes[..11]).is_none());
+        assert!(decode_vector(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_vector_similarity_score() {
+        let query_vector = [1.0, 0.0];
+        assert_eq!(
+            VectorSimilarity::Cosine.score(&query_vector, &[2.0, 0.0]),
+            1.0
+        );
+        assert_eq!(
+            VectorSimilarity::Cosine.score(&query_vector, &[0.0, 3.0]),
+            0.5
+        );
+        assert_eq!(
+            VectorSimilarity::Cosine.score(&query_vector, &[-1.0, 0.0]),
+            0.0
+        );
+        assert_eq!(
+            VectorSimilarity::Cosine.score(&query_vector, &[0.0, 0.0]),
+            0.0
+        );
+
+        assert_eq!(
+            VectorSimilarity::DotProduct.score(&query_vector, &[1.0, 0.0]),
+            1.0
+        );
+        assert_eq!(
+            VectorSimilarity::DotProduct.score(&query_vector, &[0.0, 1.0]),
+            0.5
+        );
+
+        assert_eq!(
+            VectorSimilarity::L2Norm.score(&query_vector, &[1.0, 0.0]),
+            1.0
+        );
+        assert_eq!(
+            VectorSimilarity::L2Norm.score(&query_vector, &[1.0, 1.0]),
+            0.5
+        );
+        assert_eq!(
+            VectorSimilarity::L2Norm.score(&query_vector, &[3.0, 0.0]),
+            0.2
+        );
+    }
+
+    #[test]
+    fn test_vector_similarity_validate_vector() {
+        VectorSimilarity::Cosine
+            .validate_vector(&[1.0, 2.0])
+            .unwrap();
+        VectorSimilarity::Cosine
+            .validate_vector(&[0.0, 0.0])
+            .unwrap_err();
+        VectorSimilarity::Cosine
+            .validate_vector(&[f32::NAN, 0.0])
+            .unwrap_err();
+        VectorSimilarity::DotProduct
+            .validate_vector(&[0.6, 0.8])
+            .unwrap();
+        VectorSimilarity::DotProduct
+            .validate_vector(&[1.0, 1.0])
+            .unwrap_err();
+        VectorSimilarity::L2Norm
+            .validate_vector(&[0.0, 0.0])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_vector_similarity_serde() {
+        let similarity: VectorSimilarity = serde_json::from_str(r#""l2_norm""#).unwrap();
+        assert_eq!(similarity, VectorSimilarity::L2Norm);
+        assert_eq!(
+            serde_json::to_string(&VectorSimilarity::DotProduct).unwrap(),
+            r#""dot_product""#
+        );
+    }
+}
@@ -27,7 +27,10 @@ pub use quickwit_ingest::CommitType;
 use quickwit_metastore::{IndexMetadata, Split, SplitInfo};
 use quickwit_proto::ingest::Shard;
 use quickwit_search::SearchResponseRest;
-use quickwit_serve::{ListSplitsQueryParams, ListSplitsResponse, SearchRequestQueryString};
+use quickwit_serve::{
+    ListSplitsQueryParams, ListSplitsResponse, ReindexRequest, ReindexStatus,
+    SearchRequestQueryString,
+};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::{Client, ClientBuilder, Method, StatusCode, Url};
 use serde::Serialize;
@@ -422,6 +425,37 @@ impl<'a> IndexClient<'a> {
         let file_entries = response.deserialize().await?;
         Ok(file_entries)
     }
+
+    pub async fn reindex(&self, reindex_request: &ReindexRequest) -> Result<ReindexStatus, Error> {
+        let body = Bytes::from(serde_json::to_vec(reindex_request).unwrap());
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                "_reindex",
+                None,
+                None,
+                Some(body),
+                self.timeout,
+            )
+            .await?;
+        let reindex_status = response.deserialize().await?;
+        Ok(reindex_status)
+    }
+
+    pub async fn reindex_status(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> Result<ReindexStatus, Error> {
+        let path = format!("_reindex/{index_id}/{source_id}");
+        let response = self
+            .transport
+            .send::<()>(Method::GET, &path, None, None, None, self.timeout)
+            .await?;
+        let reindex_status = response.deserialize().await?;
+        Ok(reindex_status)
+    }
 }
 
 /// Client for splits APIs.
@@ -695,7 +729,10 @@ mod test {
     use quickwit_ingest::CommitType;
     use quickwit_metastore::IndexMetadata;
     use quickwit_search::SearchResponseRest;
-    use quickwit_serve::{ListSplitsQueryParams, ListSplitsResponse, SearchRequestQueryString};
+    use quickwit_serve::{
+        ListSplitsQueryParams, ListSplitsResponse, ReindexRequest, ReindexStatus,
+        SearchRequestQueryString,
+    };
     use reqwest::header::CONTENT_TYPE;
     use reqwest::{StatusCode, Url};
     use serde_json::json;
@@ -734,9 +771,12 @@ mod test {
             num_hits: 0,
             hits: Vec::new(),
             snippets: None,
+            fields: None,
             aggregations: None,
             elapsed_time_micros: 100,
             errors: Vec::new(),
+            num_failed_splits: 0,
+            failed_splits: Vec::new(),
         };
         Mock::given(method("POST"))
             .and(path("/api/v1/my-index/search"))
@@ -999,6 +1039,53 @@ mod test {
             .delete("my-index", true)
             .await
             .unwrap_err();
+        // POST reindex
+        let reindex_request = ReindexRequest {
+            source_index_id: "my-index".to_string(),
+            target_index_id: "my-index-v2".to_string(),
+            query: Some("severity:error".to_string()),
+            start_timestamp: None,
+            end_timestamp: None,
+            transform: None,
+        };
+        let reindex_status = ReindexStatus {
+            index_id: "my-index-v2".to_string(),
+            source_id: "reindex-my-index-abcdef".to_string(),
+            source_index_id: "my-index".to_string(),
+            num_docs_reindexed: 0,
+            completed: false,
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/v1/_reindex"))
+            .and(body_json(&reindex_request))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(reindex_status.clone()),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        assert_eq!(
+            qw_client.indexes().reindex(&reindex_request).await.unwrap(),
+            reindex_status
+        );
+
+        // GET reindex status
+        Mock::given(method("GET"))
+            .and(path("/api/v1/_reindex/my-index-v2/reindex-my-index-abcdef"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(reindex_status.clone()),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        assert_eq!(
+            qw_client
+                .indexes()
+                .reindex_status("my-index-v2", "reindex-my-index-abcdef")
+                .await
+                .unwrap(),
+            reindex_status
+        );
     }
 
     #[tokio::test]
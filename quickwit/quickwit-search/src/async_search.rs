@@ -0,0 +1,497 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Async searches are root searches running in the background of the node that received them.
+//! Their state is stored in the distributed KV store of the searchers, so they can be polled or
+//! deleted from any node until they expire.
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use quickwit_proto::metastore::MetastoreServiceClient;
+use quickwit_proto::search::{SearchRequest, SearchResponse};
+use serde::{Deserialize, Serialize};
+use tantivy::time::OffsetDateTime;
+use tokio::sync::watch;
+use tracing::debug;
+use ulid::Ulid;
+
+use crate::root::root_search_with_partial_results;
+use crate::service::SearcherContext;
+use crate::{ClusterClient, SearchError};
+
+/// Default duration during which an async search and its results are kept.
+pub const DEFAULT_ASYNC_SEARCH_KEEP_ALIVE: Duration = Duration::from_secs(60 * 60);
+
+/// Maximum duration during which an async search and its results are kept. The results are held
+/// in the memory of the searchers until they expire.
+pub const MAX_ASYNC_SEARCH_KEEP_ALIVE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default duration during which the submission of an async search waits for the search to
+/// complete before returning its ID.
+pub const DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Interval at which a running async search checks that it was not deleted and did not expire.
+const ASYNC_SEARCH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// State of an async search.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AsyncSearchResponse {
+    /// ID of the async search.
+    pub id: String,
+    /// Name of the principal that submitted the search, if authentication is enabled.
+    pub owner_opt: Option<String>,
+    /// Index ID patterns targeted by the search.
+    pub index_id_patterns: Vec<String>,
+    /// Whether the search is still running.
+    pub is_running: bool,
+    /// Time at which the search was submitted, in milliseconds since the Unix epoch.
+    pub start_time_in_millis: i64,
+    /// Time after which the search and its results are deleted, in milliseconds since the Unix
+    /// epoch.
+    pub expiration_time_in_millis: i64,
+    /// Time at which the search completed, in milliseconds since the Unix epoch.
+    pub completion_time_in_millis: Option<i64>,
+    /// Response of the search once it completed successfully. While the search is running, holds
+    /// the partial results of the splits searched so far, without hits.
+    pub response: Option<SearchResponse>,
+    /// Error of the search if it failed.
+    pub error: Option<SearchError>,
+}
+
+/// Record of an async search in the KV store.
+#[derive(Serialize, Deserialize)]
+enum AsyncSearchRecord {
+    Active(AsyncSearchResponse),
+    // Deleted async searches leave a tombstone until they expire, so that a search still running
+    // notices the deletion and does not store its results.
+    Deleted,
+}
+
+fn now_in_millis() -> i64 {
+    (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+fn parse_async_search_id(async_search_id: &str) -> crate::Result<Ulid> {
+    Ulid::from_str(async_search_id).map_err(|_| {
+        SearchError::InvalidArgument(format!("invalid async search ID `{async_search_id}`"))
+    })
+}
+
+fn async_search_key(async_search_ulid: Ulid) -> Vec<u8> {
+    format!("async-search:{async_search_ulid}").into_bytes()
+}
+
+async fn load_async_search_record(
+    cluster_client: &ClusterClient,
+    key: &[u8],
+) -> Option<AsyncSearchRecord> {
+    let payload = cluster_client.get_kv(key).await?;
+    serde_json::from_slice(&payload).ok()
+}
+
+async fn store_async_search_record(
+    cluster_client: &ClusterClient,
+    key: &[u8],
+    record: &AsyncSearchRecord,
+    expiration_time_in_millis: i64,
+) {
+    let now_in_millis = now_in_millis();
+    if expiration_time_in_millis <= now_in_millis {
+        return;
+    }
+    let ttl = Duration::from_millis((expiration_time_in_millis - now_in_millis) as u64);
+    let payload = serde_json::to_vec(record).expect("async search record should be serializable");
+    cluster_client.put_kv(key, &payload, ttl).await;
+}
+
+async fn is_async_search_active(cluster_client: &ClusterClient, key: &[u8]) -> bool {
+    matches!(
+        load_async_search_record(cluster_client, key).await,
+        Some(AsyncSearchRecord::Active(_))
+    )
+}
+
+/// Starts a root search in the background and returns its state once it completes or after
+/// `wait_for_completion_timeout`, whichever comes first.
+pub(crate) async fn submit_async_search(
+    search_request: SearchRequest,
+    keep_alive: Duration,
+    wait_for_completion_timeout: Duration,
+    owner_opt: Option<String>,
+    searcher_context: Arc<SearcherContext>,
+    metastore: MetastoreServiceClient,
+    cluster_client: ClusterClient,
+) -> crate::Result<AsyncSearchResponse> {
+    if search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "scroll is not supported by async searches".to_string(),
+        ));
+    }
+    if keep_alive < Duration::from_secs(1) {
+        return Err(SearchError::InvalidArgument(
+            "async search keep alive must be at least 1 second".to_string(),
+        ));
+    }
+    if keep_alive > MAX_ASYNC_SEARCH_KEEP_ALIVE {
+        return Err(SearchError::InvalidArgument(format!(
+            "async search keep alive must be at most {} secs",
+            MAX_ASYNC_SEARCH_KEEP_ALIVE.as_secs()
+        )));
+    }
+    let async_search_ulid = Ulid::new();
+    let start_time_in_millis = now_in_millis();
+    let async_search_response = AsyncSearchResponse {
+        id: async_search_ulid.to_string(),
+        owner_opt,
+        index_id_patterns: search_request.index_id_patterns.clone(),
+        is_running: true,
+        start_time_in_millis,
+        expiration_time_in_millis: start_time_in_millis + keep_alive.as_millis() as i64,
+        completion_time_in_millis: None,
+        response: None,
+        error: None,
+    };
+    let key = async_search_key(async_search_ulid);
+    store_async_search_record(
+        &cluster_client,
+        &key,
+        &AsyncSearchRecord::Active(async_search_response.clone()),
+        async_search_response.expiration_time_in_millis,
+    )
+    .await;
+    debug!(async_search_id=%async_search_response.id, "submit-async-search");
+
+    let mut join_handle = tokio::spawn(run_async_search(
+        key,
+        async_search_response.clone(),
+        search_request,
+        searcher_context,
+        metastore,
+        cluster_client,
+    ));
+    match tokio::time::timeout(wait_for_completion_timeout, &mut join_handle).await {
+        Ok(join_result) => Ok(join_result?),
+        // The search keeps running in the background.
+        Err(_elapsed) => Ok(async_search_response),
+    }
+}
+
+async fn run_async_search(
+    key: Vec<u8>,
+    mut async_search_response: AsyncSearchResponse,
+    search_request: SearchRequest,
+    searcher_context: Arc<SearcherContext>,
+    metastore: MetastoreServiceClient,
+    cluster_client: ClusterClient,
+) -> AsyncSearchResponse {
+    let (partial_results_tx, mut partial_results_rx) = watch::channel(None);
+    let search_fut = root_search_with_partial_results(
+        &searcher_context,
+        search_request,
+        metastore,
+        &cluster_client,
+        &partial_results_tx,
+    );
+    tokio::pin!(search_fut);
+
+    let mut check_interval = tokio::time::interval(ASYNC_SEARCH_CHECK_INTERVAL);
+    check_interval.tick().await;
+
+    let search_result = loop {
+        tokio::select! {
+            search_result = &mut search_fut => break search_result,
+            _ = check_interval.tick() => {
+                if !is_async_search_active(&cluster_client, &key).await {
+                    debug!(async_search_id=%async_search_response.id, "cancel-async-search");
+                    return async_search_response;
+                }
+                if partial_results_rx.has_changed().unwrap_or(false) {
+                    async_search_response.response =
+                        partial_results_rx.borrow_and_update().clone();
+                    store_async_search_record(
+                        &cluster_client,
+                        &key,
+                        &AsyncSearchRecord::Active(async_search_response.clone()),
+                        async_search_response.expiration_time_in_millis,
+                    )
+                    .await;
+                }
+            }
+        }
+    };
+    async_search_response.is_running = false;
+    async_search_response.completion_time_in_millis = Some(now_in_millis());
+
+    match search_result {
+        Ok(search_response) => async_search_response.response = Some(search_response),
+        Err(search_error) => async_search_response.error = Some(search_error),
+    }
+    // The async search may have been deleted while the search was completing.
+    if is_async_search_active(&cluster_client, &key).await {
+        store_async_search_record(
+            &cluster_client,
+            &key,
+            &AsyncSearchRecord::Active(async_search_response.clone()),
+            async_search_response.expiration_time_in_millis,
+        )
+        .await;
+    }
+    async_search_response
+}
+
+/// Returns the state of an async search.
+pub(crate) async fn get_async_search(
+    async_search_id: &str,
+    cluster_client: &ClusterClient,
+) -> crate::Result<AsyncSearchResponse> {
+    let key = async_search_key(parse_async_search_id(async_search_id)?);
+
+    match load_async_search_record(cluster_client, &key).await {
+        Some(AsyncSearchRecord::Active(async_search_response)) => Ok(async_search_response),
+        Some(AsyncSearchRecord::Deleted) | None => Err(SearchError::AsyncSearchNotFound(
+            async_search_id.to_string(),
+        )),
+    }
+}
+
+/// Deletes an async search. The search is cancelled if it is still running.
+pub(crate) async fn delete_async_search(
+    async_search_id: &str,
+    cluster_client: &ClusterClient,
+) -> crate::Result<()> {
+    let key = async_search_key(parse_async_search_id(async_search_id)?);
+
+    let Some(AsyncSearchRecord::Active(async_search_response)) =
+        load_async_search_record(cluster_client, &key).await
+    else {
+        return Err(SearchError::AsyncSearchNotFound(
+            async_search_id.to_string(),
+        ));
+    };
+    store_async_search_record(
+        cluster_client,
+        &key,
+        &AsyncSearchRecord::Deleted,
+        async_search_response.expiration_time_in_millis,
+    )
+    .await;
+    debug!(async_search_id=%async_search_id, "delete-async-search");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use quickwit_indexing::TestSandbox;
+    use quickwit_query::query_ast::qast_json_helper;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        SearchJobPlacer, SearchService, SearchServiceClient, SearchServiceImpl, SearcherPool,
+    };
+
+    #[tokio::test]
+    async fn test_async_search() {
+        let index_id = "test-async-search";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"])
+            .await
+            .unwrap();
+        let docs = vec![
+            json!({"body": "hello happy tax payer"}),
+            json!({"body": "hello"}),
+        ];
+        test_sandbox.add_documents(docs).await.unwrap();
+
+        let socket_addr: SocketAddr = ([127, 0, 0, 1], 7280).into();
+        let searcher_pool = SearcherPool::default();
+        let cluster_client = ClusterClient::new(SearchJobPlacer::new(searcher_pool.clone()));
+        let search_service = Arc::new(SearchServiceImpl::new(
+            test_sandbox.metastore(),
+            test_sandbox.storage_resolver(),
+            cluster_client,
+            Arc::new(SearcherContext::for_test()),
+        ));
+        searcher_pool.insert(
+            socket_addr,
+            SearchServiceClient::from_service(search_service.clone(), socket_addr),
+        );
+        let search_request = SearchRequest {
+            index_id_patterns: vec![index_id.to_string()],
+            query_ast: qast_json_helper("tax", &["body"]),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let async_search_response = search_service
+            .submit_async_search(
+                search_request.clone(),
+                Duration::from_secs(60),
+                Duration::from_secs(30),
+                Some("alice".to_string()),
+            )
+            .await
+            .unwrap();
+        assert!(!async_search_response.is_running);
+        assert_eq!(async_search_response.owner_opt.as_deref(), Some("alice"));
+        assert_eq!(async_search_response.index_id_patterns, [index_id]);
+        assert!(async_search_response.completion_time_in_millis.is_some());
+        assert_eq!(async_search_response.response.unwrap().num_hits, 1);
+
+        // The search does not complete before the submission returns.
+        let async_search_response = search_service
+            .submit_async_search(
+                search_request,
+                Duration::from_secs(60),
+                Duration::ZERO,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(async_search_response.is_running);
+        assert!(async_search_response.owner_opt.is_none());
+        assert!(async_search_response.response.is_none());
+        assert_eq!(
+            async_search_response.expiration_time_in_millis
+                - async_search_response.start_time_in_millis,
+            60_000
+        );
+        let async_search_id = async_search_response.id;
+
+        let async_search_response = loop {
+            let async_search_response = search_service
+                .get_async_search(async_search_id.clone())
+                .await
+                .unwrap();
+            if !async_search_response.is_running {
+                break async_search_response;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(async_search_response.id, async_search_id);
+        assert_eq!(async_search_response.response.unwrap().num_hits, 1);
+        assert!(async_search_response.error.is_none());
+
+        search_service
+            .delete_async_search(async_search_id.clone())
+            .await
+            .unwrap();
+        let search_error = search_service
+            .get_async_search(async_search_id.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::AsyncSearchNotFound(_)));
+
+        let search_error = search_service
+            .delete_async_search(async_search_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::AsyncSearchNotFound(_)));
+
+        let search_error = search_service
+            .get_async_search("not-an-id".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::InvalidArgument(_)));
+
+        test_sandbox.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_async_search_failure() {
+        let index_id = "test-async-search-failure";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"])
+            .await
+            .unwrap();
+        let socket_addr: SocketAddr = ([127, 0, 0, 1], 7280).into();
+        let searcher_pool = SearcherPool::default();
+        let cluster_client = ClusterClient::new(SearchJobPlacer::new(searcher_pool.clone()));
+        let search_service = Arc::new(SearchServiceImpl::new(
+            test_sandbox.metastore(),
+            test_sandbox.storage_resolver(),
+            cluster_client,
+            Arc::new(SearcherContext::for_test()),
+        ));
+        searcher_pool.insert(
+            socket_addr,
+            SearchServiceClient::from_service(search_service.clone(), socket_addr),
+        );
+        let search_request = SearchRequest {
+            index_id_patterns: vec!["index-does-not-exist".to_string()],
+            query_ast: qast_json_helper("tax", &["body"]),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let async_search_response = search_service
+            .submit_async_search(
+                search_request.clone(),
+                Duration::from_secs(60),
+                Duration::from_secs(30),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(!async_search_response.is_running);
+        assert!(async_search_response.response.is_none());
+        assert!(matches!(
+            async_search_response.error,
+            Some(SearchError::IndexesNotFound { .. })
+        ));
+
+        let search_error = search_service
+            .submit_async_search(
+                search_request.clone(),
+                MAX_ASYNC_SEARCH_KEEP_ALIVE + Duration::from_secs(1),
+                Duration::ZERO,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::InvalidArgument(_)));
+
+        let search_request = SearchRequest {
+            scroll_ttl_secs: Some(60),
+            ..search_request
+        };
+        let search_error = search_service
+            .submit_async_search(
+                search_request,
+                Duration::from_secs(60),
+                Duration::ZERO,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(search_error, SearchError::InvalidArgument(_)));
+
+        test_sandbox.assert_quit().await;
+    }
+}
@@ -394,6 +394,8 @@ mod tests {
         PartialHit {
             sort_value: Some(SortValue::U64(sort_value).into()),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: split_id.to_string(),
             segment_ord: 1,
             doc_id,
@@ -19,7 +19,8 @@
 
 use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
 use quickwit_common::binary_heap::{SortKeyMapper, TopK};
@@ -29,17 +30,27 @@ use quickwit_proto::search::{
     SortValue, SplitSearchError,
 };
 use quickwit_proto::types::SplitId;
+use quickwit_query::query_ast::{top_level_knn_k, QueryAst};
 use serde::Deserialize;
 use tantivy::aggregation::agg_req::{get_fast_field_names, Aggregations};
 use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::aggregation::{AggregationLimitsGuard, AggregationSegmentCollector};
 use tantivy::collector::{Collector, SegmentCollector};
-use tantivy::columnar::{ColumnType, MonotonicallyMappableToU64};
+use tantivy::columnar::{BytesColumn, ColumnType, MonotonicallyMappableToU64};
 use tantivy::fastfield::Column;
 use tantivy::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
 
 use crate::find_trace_ids_collector::{FindTraceIdsCollector, FindTraceIdsSegmentCollector, Span};
-use crate::top_k_collector::{specialized_top_k_segment_collector, QuickwitSegmentTopKCollector};
+use crate::geo_grid_collector::{GeoGridAggregation, GeoGridFruit, GeoGridSegmentCollector};
+use crate::nested_aggregation_collector::{
+    NestedAggregation, NestedAggregationFruit, NestedAggregationSegmentCollector,
+};
+use crate::runtime_terms_collector::{
+    RuntimeTermCounts, RuntimeTermsAggregation, RuntimeTermsSegmentCollector,
+};
+use crate::top_k_collector::{
+    specialized_top_k_segment_collector, QuickwitSegmentTopKCollector, SegmentCollapser,
+};
 use crate::{merge_resource_stats, merge_resource_stats_it, GlobalDocAddress};
 
 #[derive(Clone, Debug)]
@@ -60,6 +71,7 @@ impl From<SortByComponent> for SortByPair {
         Self {
             first: value,
             second: None,
+            others: Vec::new(),
         }
     }
 }
@@ -67,6 +79,8 @@ impl From<SortByComponent> for SortByPair {
 pub(crate) struct SortByPair {
     first: SortByComponent,
     second: Option<SortByComponent>,
+    /// Sorting keys following the first two, if any.
+    others: Vec<SortByComponent>,
 }
 impl SortByPair {
     pub fn sort_orders(&self) -> (SortOrder, SortOrder) {
@@ -78,6 +92,25 @@ impl SortByPair {
                 .unwrap_or(SortOrder::Desc),
         )
     }
+    pub fn other_sort_orders(&self) -> Vec<SortOrder> {
+        self.others
+            .iter()
+            .map(|sort_by| sort_by.sort_order())
+            .collect()
+    }
+    pub fn sort_key_mapper(&self) -> HitSortingMapper {
+        let (order1, order2) = self.sort_orders();
+        HitSortingMapper {
+            order1,
+            order2,
+            other_orders: self.other_sort_orders(),
+        }
+    }
+    fn components(&self) -> impl Iterator<Item = &SortByComponent> {
+        std::iter::once(&self.first)
+            .chain(self.second.as_ref())
+            .chain(self.others.iter())
+    }
 }
 impl SortByComponent {
     fn to_sorting_field_extractor_component(
@@ -96,9 +129,20 @@ impl SortByComponent {
                     )
                 });
                 let sort_field_type = SortFieldType::try_from(column_type)?;
+                // For text and bytes columns, the u64 column holds term ordinals. We also need
+                // the term dictionary to convert them back into values.
+                let dictionary_column = match sort_field_type {
+                    SortFieldType::Str => segment_reader
+                        .fast_fields()
+                        .str(field_name)?
+                        .map(BytesColumn::from),
+                    SortFieldType::Bytes => segment_reader.fast_fields().bytes(field_name)?,
+                    _ => None,
+                };
                 Ok(SortingFieldExtractorComponent::FastField {
                     sort_column,
                     sort_field_type,
+                    dictionary_column,
                 })
             }
             SortByComponent::Score { .. } => Ok(SortingFieldExtractorComponent::Score),
@@ -136,6 +180,10 @@ pub(crate) enum SortFieldType {
     F64,
     DateTime,
     Bool,
+    /// Text fast field, sorted by term ordinal.
+    Str,
+    /// Bytes fast field, sorted by term ordinal.
+    Bytes,
 }
 
 /// The `SortingFieldExtractor` is used to extract a score, which can either be a true score,
@@ -146,6 +194,8 @@ pub(crate) enum SortingFieldExtractorComponent {
     FastField {
         sort_column: Column<u64>,
         sort_field_type: SortFieldType,
+        /// Term dictionary of text and bytes fast fields, `None` for other field types.
+        dictionary_column: Option<BytesColumn>,
     },
     Score,
 }
@@ -192,18 +242,32 @@ impl SortingFieldExtractorComponent {
     /// This is used to convert `search_after` sort value to a u64 representation that will respect
     /// the same order as the `SortValue` representation.
     pub fn convert_u64_ff_val_to_sort_value(&self, sort_value: u64) -> SortValue {
-        let map_fast_field_to_value = |fast_field_value, field_type| match field_type {
-            SortFieldType::U64 => SortValue::U64(fast_field_value),
-            SortFieldType::I64 => SortValue::I64(i64::from_u64(fast_field_value)),
-            SortFieldType::F64 => SortValue::F64(f64::from_u64(fast_field_value)),
-            SortFieldType::DateTime => SortValue::I64(i64::from_u64(fast_field_value)),
-            SortFieldType::Bool => SortValue::Boolean(fast_field_value != 0u64),
-        };
         match self {
             SortingFieldExtractorComponent::DocId => SortValue::U64(sort_value),
             SortingFieldExtractorComponent::FastField {
-                sort_field_type, ..
-            } => map_fast_field_to_value(sort_value, *sort_field_type),
+                sort_field_type,
+                dictionary_column,
+                ..
+            } => match sort_field_type {
+                SortFieldType::U64 => SortValue::U64(sort_value),
+                SortFieldType::I64 => SortValue::I64(i64::from_u64(sort_value)),
+                SortFieldType::F64 => SortValue::F64(f64::from_u64(sort_value)),
+                SortFieldType::DateTime => SortValue::I64(i64::from_u64(sort_value)),
+                SortFieldType::Bool => SortValue::Boolean(sort_value != 0u64),
+                SortFieldType::Str | SortFieldType::Bytes => {
+                    let mut term_bytes = Vec::new();
+                    dictionary_column
+                        .as_ref()
+                        .expect("Internal error: Got term ordinal, but no term dictionary")
+                        .ord_to_bytes(sort_value, &mut term_bytes)
+                        .expect("failed to lookup sort value in the column term dictionary");
+                    if *sort_field_type == SortFieldType::Str {
+                        SortValue::Str(String::from_utf8_lossy(&term_bytes).into_owned())
+                    } else {
+                        SortValue::Str(encode_hex(&term_bytes))
+                    }
+                }
+            },
             SortingFieldExtractorComponent::Score => SortValue::F64(f64::from_u64(sort_value)),
         }
     }
@@ -226,7 +290,9 @@ impl SortingFieldExtractorComponent {
                 _ => panic!("Internal error: Got non-U64 sort value for DocId."),
             },
             SortingFieldExtractorComponent::FastField {
-                sort_field_type, ..
+                sort_field_type,
+                dictionary_column,
+                ..
             } => {
                 // We need to convert a (potential user provided) value in the correct u64
                 // representation of the fast field.
@@ -263,7 +329,43 @@ impl SortingFieldExtractorComponent {
                 // - [X] I64 -> Bool
                 // - [X] U64 -> Bool
                 //
+                // Text and bytes values are mapped to term ordinals, which only exist for text and
+                // bytes fields. Following the `SortValue` ordering, they sort after every other
+                // type.
+                //
+                // Integer-like text values are parsed as numbers when deserializing
+                // `search_after`, so we convert them back first.
+                let sort_value = match (sort_value, sort_field_type) {
+                    (SortValue::U64(val), SortFieldType::Str) => SortValue::Str(val.to_string()),
+                    (SortValue::I64(val), SortFieldType::Str) => SortValue::Str(val.to_string()),
+                    (sort_value, _) => sort_value,
+                };
                 let val = match (sort_value, sort_field_type) {
+                    (SortValue::Str(val), SortFieldType::Str | SortFieldType::Bytes) => {
+                        let dictionary_column = dictionary_column
+                            .as_ref()
+                            .expect("Internal error: Got text sort field, but no term dictionary");
+                        let term_bytes = if *sort_field_type == SortFieldType::Bytes {
+                            decode_hex(&val).unwrap_or_else(|| val.into_bytes())
+                        } else {
+                            val.into_bytes()
+                        };
+                        return search_after_term_ord(dictionary_column, &term_bytes, sort_order);
+                    }
+                    (_, SortFieldType::Str | SortFieldType::Bytes) => {
+                        // Non-text values sort before all the text values.
+                        if sort_order == SortOrder::Asc {
+                            return None;
+                        }
+                        u64::MIN // matches nothing as search_after is not inclusive
+                    }
+                    (SortValue::Str(_), _) => {
+                        // Text values sort after all the non-text values.
+                        if sort_order == SortOrder::Desc {
+                            return None;
+                        }
+                        u64::MAX
+                    }
                     // Same field type, no conversion needed.
                     (SortValue::U64(val), SortFieldType::U64) => val,
                     (SortValue::F64(val), SortFieldType::F64) => val.to_u64(),
@@ -381,6 +483,7 @@ impl From<SortingFieldExtractorComponent> for SortingFieldExtractorPair {
         Self {
             first: value,
             second: None,
+            others: Vec::new(),
         }
     }
 }
@@ -388,6 +491,8 @@ impl From<SortingFieldExtractorComponent> for SortingFieldExtractorPair {
 pub(crate) struct SortingFieldExtractorPair {
     pub first: SortingFieldExtractorComponent,
     pub second: Option<SortingFieldExtractorComponent>,
+    /// Extractors of the sorting keys following the first two, if any.
+    pub others: Vec<SortingFieldExtractorComponent>,
 }
 
 impl SortingFieldExtractorPair {
@@ -398,6 +503,7 @@ impl SortingFieldExtractorPair {
                 .as_ref()
                 .map(|second| second.is_score())
                 .unwrap_or(false)
+            || self.others.iter().any(|other| other.is_score())
     }
     /// Returns the list of sort values for the given element
     ///
@@ -433,6 +539,18 @@ impl SortingFieldExtractorPair {
             .and_then(|second| second.extract_typed_sort_value_opt(doc_id, score));
         (first, second)
     }
+    /// Returns the sort values of the sorting keys following the first two for the given element.
+    #[inline]
+    pub(crate) fn extract_other_typed_sort_values(
+        &self,
+        doc_id: DocId,
+        score: Score,
+    ) -> Vec<Option<u64>> {
+        self.others
+            .iter()
+            .map(|other| other.extract_typed_sort_value_opt(doc_id, score))
+            .collect()
+    }
 }
 
 impl TryFrom<ColumnType> for SortFieldType {
@@ -445,6 +563,8 @@ impl TryFrom<ColumnType> for SortFieldType {
             ColumnType::F64 => Ok(SortFieldType::F64),
             ColumnType::DateTime => Ok(SortFieldType::DateTime),
             ColumnType::Bool => Ok(SortFieldType::Bool),
+            ColumnType::Str => Ok(SortFieldType::Str),
+            ColumnType::Bytes => Ok(SortFieldType::Bytes),
             _ => Err(TantivyError::InvalidArgument(format!(
                 "Unsupported sort field type `{:?}`.",
                 column_type
@@ -453,6 +573,56 @@ impl TryFrom<ColumnType> for SortFieldType {
     }
 }
 
+/// Returns the u64 representation of a `search_after` text value for a field with the given
+/// term dictionary.
+///
+/// Term ordinals follow the lexicographic order of the terms. If the term does not exist in the
+/// segment, we pick the ordinal bound that keeps `search_after` exclusive. Returns None if
+/// everything matches.
+fn search_after_term_ord(
+    dictionary_column: &BytesColumn,
+    term_bytes: &[u8],
+    sort_order: SortOrder,
+) -> Option<u64> {
+    let dictionary = dictionary_column.dictionary();
+    if let Some(term_ord) = dictionary
+        .term_ord(term_bytes)
+        .expect("failed to lookup search_after value in the column term dictionary")
+    {
+        return Some(term_ord);
+    }
+    let mut stream = dictionary
+        .range()
+        .ge(term_bytes)
+        .into_stream()
+        .expect("failed to create stream over the column term dictionary");
+    // Ordinal of the first term greater than the search_after value.
+    let next_term_ord = if stream.advance() {
+        stream.term_ord()
+    } else {
+        dictionary_column.num_terms() as u64
+    };
+    match sort_order {
+        SortOrder::Asc => next_term_ord.checked_sub(1),
+        SortOrder::Desc => Some(next_term_ord),
+    }
+}
+
+/// Encodes bytes sort values in hexadecimal, which preserves their ordering.
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
+
 /// Takes a user-defined sorting criteria and resolves it to a
 /// segment specific `SortingFieldExtractorPair`.
 fn get_score_extractor(
@@ -468,12 +638,20 @@ fn get_score_extractor(
             .as_ref()
             .map(|first| first.to_sorting_field_extractor_component(segment_reader))
             .transpose()?,
+        others: sort_by
+            .others
+            .iter()
+            .map(|other| other.to_sorting_field_extractor_component(segment_reader))
+            .collect::<tantivy::Result<_>>()?,
     })
 }
 
 #[allow(clippy::large_enum_variant)]
 enum AggregationSegmentCollectors {
     FindTraceIdsSegmentCollector(Box<FindTraceIdsSegmentCollector>),
+    GeoGridSegmentCollector(GeoGridSegmentCollector),
+    NestedAggregationSegmentCollector(NestedAggregationSegmentCollector),
+    RuntimeTermsSegmentCollector(RuntimeTermsSegmentCollector),
     TantivyAggregationSegmentCollector(AggregationSegmentCollector),
 }
 
@@ -484,12 +662,14 @@ pub struct QuickwitSegmentCollector {
     num_hits: u64,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(crate) struct SegmentPartialHit {
     /// Normalized to u64, the typed value can be reconstructed with
     /// SortingFieldExtractorComponent.
     pub sort_value: Option<u64>,
     pub sort_value2: Option<u64>,
+    /// Values of the sorting keys following the first two, if any.
+    pub other_sort_values: Vec<Option<u64>>,
     pub doc_id: DocId,
 }
 
@@ -500,7 +680,17 @@ impl SegmentPartialHit {
         segment_ord: SegmentOrdinal,
         first: &SortingFieldExtractorComponent,
         second: &Option<SortingFieldExtractorComponent>,
+        others: &[SortingFieldExtractorComponent],
     ) -> PartialHit {
+        let extra_sort_values = self
+            .other_sort_values
+            .iter()
+            .zip(others)
+            .map(|(sort_value_opt, other)| SortByValue {
+                sort_value: sort_value_opt
+                    .map(|sort_value| other.convert_u64_ff_val_to_sort_value(sort_value)),
+            })
+            .collect();
         PartialHit {
             sort_value: self
                 .sort_value
@@ -519,6 +709,8 @@ impl SegmentPartialHit {
                 .map(|sort_value| SortByValue {
                     sort_value: Some(sort_value),
                 }),
+            extra_sort_values,
+            collapse_value: None,
             doc_id: self.doc_id,
             split_id,
             segment_ord,
@@ -542,6 +734,15 @@ impl SegmentCollector for QuickwitSegmentCollector {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => {
                 collector.collect_block(filtered_docs)
             }
+            Some(AggregationSegmentCollectors::GeoGridSegmentCollector(collector)) => {
+                collector.collect_block(filtered_docs)
+            }
+            Some(AggregationSegmentCollectors::NestedAggregationSegmentCollector(collector)) => {
+                collector.collect_block(filtered_docs)
+            }
+            Some(AggregationSegmentCollectors::RuntimeTermsSegmentCollector(collector)) => {
+                collector.collect_block(filtered_docs)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect_block(filtered_docs)
             }
@@ -560,6 +761,15 @@ impl SegmentCollector for QuickwitSegmentCollector {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => {
                 collector.collect(doc_id, score)
             }
+            Some(AggregationSegmentCollectors::GeoGridSegmentCollector(collector)) => {
+                collector.collect(doc_id, score)
+            }
+            Some(AggregationSegmentCollectors::NestedAggregationSegmentCollector(collector)) => {
+                collector.collect(doc_id)
+            }
+            Some(AggregationSegmentCollectors::RuntimeTermsSegmentCollector(collector)) => {
+                collector.collect(doc_id, score)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 collector.collect(doc_id, score)
             }
@@ -580,6 +790,24 @@ impl SegmentCollector for QuickwitSegmentCollector {
                     postcard::to_allocvec(&fruit).expect("Collector fruit should be serializable.");
                 Some(serialized)
             }
+            Some(AggregationSegmentCollectors::GeoGridSegmentCollector(collector)) => {
+                let fruit: GeoGridFruit = collector.harvest()?;
+                let serialized =
+                    postcard::to_allocvec(&fruit).expect("Collector fruit should be serializable.");
+                Some(serialized)
+            }
+            Some(AggregationSegmentCollectors::NestedAggregationSegmentCollector(collector)) => {
+                let fruit: NestedAggregationFruit = collector.harvest()?;
+                let serialized =
+                    postcard::to_allocvec(&fruit).expect("Collector fruit should be serializable.");
+                Some(serialized)
+            }
+            Some(AggregationSegmentCollectors::RuntimeTermsSegmentCollector(collector)) => {
+                let fruit: RuntimeTermCounts = collector.harvest();
+                let serialized =
+                    postcard::to_allocvec(&fruit).expect("Collector fruit should be serializable.");
+                Some(serialized)
+            }
             Some(AggregationSegmentCollectors::TantivyAggregationSegmentCollector(collector)) => {
                 let serialized = postcard::to_allocvec(&collector.harvest()?)
                     .expect("Collector fruit should be serializable.");
@@ -607,6 +835,12 @@ pub enum QuickwitAggregations {
     /// Aggregation used by the Jaeger service to find trace IDs that match a
     /// [`quickwit_proto::jaeger::storage::v1::FindTraceIDsRequest`].
     FindTraceIdsAggregation(FindTraceIdsCollector),
+    /// Geohash or geotile grid aggregation over a `geo_point` field.
+    GeoGridAggregation(GeoGridAggregation),
+    /// Aggregation over the objects of a nested field.
+    NestedAggregation(NestedAggregation),
+    /// Terms aggregation over a runtime field.
+    RuntimeTermsAggregation(RuntimeTermsAggregation),
     /// Your classic Tantivy aggregation.
     TantivyAggregations(Aggregations),
 }
@@ -618,6 +852,11 @@ impl QuickwitAggregations {
             QuickwitAggregations::FindTraceIdsAggregation(collector) => {
                 collector.fast_field_names()
             }
+            QuickwitAggregations::GeoGridAggregation(collector) => collector.fast_field_names(),
+            QuickwitAggregations::NestedAggregation(collector) => collector.fast_field_names(),
+            QuickwitAggregations::RuntimeTermsAggregation(collector) => {
+                collector.fast_field_names()
+            }
             QuickwitAggregations::TantivyAggregations(aggregations) => {
                 get_fast_field_names(aggregations)
             }
@@ -629,6 +868,24 @@ impl QuickwitAggregations {
             QuickwitAggregations::FindTraceIdsAggregation(aggreg) => {
                 QuickwitIncrementalAggregations::FindTraceIdsAggregation(aggreg.clone(), Vec::new())
             }
+            QuickwitAggregations::GeoGridAggregation(aggreg) => {
+                QuickwitIncrementalAggregations::GeoGridAggregation(
+                    aggreg.clone(),
+                    GeoGridFruit::default(),
+                )
+            }
+            QuickwitAggregations::NestedAggregation(aggreg) => {
+                QuickwitIncrementalAggregations::NestedAggregation(
+                    aggreg.clone(),
+                    NestedAggregationFruit::default(),
+                )
+            }
+            QuickwitAggregations::RuntimeTermsAggregation(aggreg) => {
+                QuickwitIncrementalAggregations::RuntimeTermsAggregation(
+                    aggreg.clone(),
+                    RuntimeTermCounts::default(),
+                )
+            }
             QuickwitAggregations::TantivyAggregations(aggreg) => {
                 QuickwitIncrementalAggregations::TantivyAggregations(aggreg.clone(), Vec::new())
             }
@@ -639,6 +896,9 @@ impl QuickwitAggregations {
 #[derive(Clone)]
 enum QuickwitIncrementalAggregations {
     FindTraceIdsAggregation(FindTraceIdsCollector, Vec<Vec<Span>>),
+    GeoGridAggregation(GeoGridAggregation, GeoGridFruit),
+    NestedAggregation(NestedAggregation, NestedAggregationFruit),
+    RuntimeTermsAggregation(RuntimeTermsAggregation, RuntimeTermCounts),
     TantivyAggregations(Aggregations, Vec<Vec<u8>>),
     NoAggregation,
 }
@@ -655,6 +915,21 @@ impl QuickwitIncrementalAggregations {
                     state.push(new_state);
                 }
             }
+            QuickwitIncrementalAggregations::GeoGridAggregation(_, ref mut state) => {
+                let fruit: GeoGridFruit =
+                    postcard::from_bytes(&intermediate_result).map_err(map_error)?;
+                GeoGridAggregation::merge_fruit(state, fruit)?;
+            }
+            QuickwitIncrementalAggregations::NestedAggregation(_, ref mut state) => {
+                let fruit: NestedAggregationFruit =
+                    postcard::from_bytes(&intermediate_result).map_err(map_error)?;
+                NestedAggregation::merge_fruit(state, fruit)?;
+            }
+            QuickwitIncrementalAggregations::RuntimeTermsAggregation(_, ref mut state) => {
+                let fruit: RuntimeTermCounts =
+                    postcard::from_bytes(&intermediate_result).map_err(map_error)?;
+                RuntimeTermsAggregation::merge_term_counts(state, fruit);
+            }
             QuickwitIncrementalAggregations::TantivyAggregations(_, state) => {
                 state.push(intermediate_result);
             }
@@ -675,6 +950,8 @@ impl QuickwitIncrementalAggregations {
                                     sort_value: Some(SortValue::I64(timestamp)),
                                 }),
                                 sort_value2: None,
+                                extra_sort_values: Vec::new(),
+                                collapse_value: None,
                                 split_id: SplitId::new(),
                                 segment_ord: 0,
                                 doc_id: 0,
@@ -684,6 +961,9 @@ impl QuickwitIncrementalAggregations {
                 }
                 None
             }
+            QuickwitIncrementalAggregations::GeoGridAggregation(_, _) => None,
+            QuickwitIncrementalAggregations::NestedAggregation(_, _) => None,
+            QuickwitIncrementalAggregations::RuntimeTermsAggregation(_, _) => None,
             QuickwitIncrementalAggregations::TantivyAggregations(_, _) => None,
             QuickwitIncrementalAggregations::NoAggregation => None,
         }
@@ -700,6 +980,18 @@ impl QuickwitIncrementalAggregations {
                 let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
                 Ok(Some(serialized))
             }
+            QuickwitIncrementalAggregations::GeoGridAggregation(_, state) => {
+                let serialized = postcard::to_allocvec(&state).map_err(map_error)?;
+                Ok(Some(serialized))
+            }
+            QuickwitIncrementalAggregations::NestedAggregation(_, state) => {
+                let serialized = postcard::to_allocvec(&state).map_err(map_error)?;
+                Ok(Some(serialized))
+            }
+            QuickwitIncrementalAggregations::RuntimeTermsAggregation(_, state) => {
+                let serialized = postcard::to_allocvec(&state).map_err(map_error)?;
+                Ok(Some(serialized))
+            }
             QuickwitIncrementalAggregations::TantivyAggregations(aggregation, state) => {
                 merge_intermediate_aggregation_result(
                     &Some(QuickwitAggregations::TantivyAggregations(aggregation)),
@@ -724,6 +1016,11 @@ pub(crate) struct QuickwitCollector {
     pub aggregation: Option<QuickwitAggregations>,
     pub aggregation_limits: AggregationLimitsGuard,
     search_after: Option<PartialHit>,
+    /// If set, only the best hit for each distinct value of this field is kept.
+    pub collapse_field: Option<String>,
+    /// `k` of the top-level k-NN query, if any: only the `k` best hits of all segments and
+    /// splits are kept.
+    pub knn_k_opt: Option<usize>,
 }
 
 impl QuickwitCollector {
@@ -738,12 +1035,16 @@ impl QuickwitCollector {
         self.max_hits = search_request.max_hits as usize;
         self.start_offset = search_request.start_offset as usize;
         self.search_after.clone_from(&search_request.search_after);
+        self.collapse_field
+            .clone_from(&search_request.collapse_field);
     }
     pub fn fast_field_names(&self) -> HashSet<String> {
         let mut fast_field_names = HashSet::default();
-        self.sort_by.first.add_fast_field(&mut fast_field_names);
-        if let Some(sort_by_second) = &self.sort_by.second {
-            sort_by_second.add_fast_field(&mut fast_field_names);
+        for sort_by in self.sort_by.components() {
+            sort_by.add_fast_field(&mut fast_field_names);
+        }
+        if let Some(collapse_field) = &self.collapse_field {
+            fast_field_names.insert(collapse_field.clone());
         }
         if let Some(aggregations) = &self.aggregation {
             fast_field_names.extend(aggregations.fast_field_names());
@@ -779,6 +1080,21 @@ impl Collector for QuickwitCollector {
                     Box::new(collector.for_segment(0, segment_reader)?),
                 ))
             }
+            Some(QuickwitAggregations::GeoGridAggregation(collector)) => {
+                Some(AggregationSegmentCollectors::GeoGridSegmentCollector(
+                    collector.for_segment(segment_ord, segment_reader, &self.aggregation_limits)?,
+                ))
+            }
+            Some(QuickwitAggregations::NestedAggregation(collector)) => Some(
+                AggregationSegmentCollectors::NestedAggregationSegmentCollector(
+                    collector.for_segment(segment_ord, segment_reader, &self.aggregation_limits)?,
+                ),
+            ),
+            Some(QuickwitAggregations::RuntimeTermsAggregation(collector)) => {
+                Some(AggregationSegmentCollectors::RuntimeTermsSegmentCollector(
+                    collector.for_segment(0, segment_reader)?,
+                ))
+            }
             Some(QuickwitAggregations::TantivyAggregations(aggs)) => Some(
                 AggregationSegmentCollectors::TantivyAggregationSegmentCollector(
                     AggregationSegmentCollector::from_agg_req_and_reader(
@@ -792,19 +1108,24 @@ impl Collector for QuickwitCollector {
             None => None,
         };
         let score_extractor = get_score_extractor(&self.sort_by, segment_reader)?;
-        let (order1, order2) = self.sort_by.sort_orders();
+        let sort_key_mapper = self.sort_by.sort_key_mapper();
 
         let segment_top_k_collector = if leaf_max_hits == 0 {
             None
         } else {
+            let collapser_opt = self
+                .collapse_field
+                .as_ref()
+                .map(|collapse_field| SegmentCollapser::for_segment(collapse_field, segment_reader))
+                .transpose()?;
             let coll: Box<dyn QuickwitSegmentTopKCollector> = specialized_top_k_segment_collector(
                 self.split_id.clone(),
                 score_extractor,
                 leaf_max_hits,
                 segment_ord,
                 self.search_after.clone(),
-                order1,
-                order2,
+                sort_key_mapper,
+                collapser_opt,
             );
             Some(coll)
         };
@@ -820,13 +1141,9 @@ impl Collector for QuickwitCollector {
         // We do not need BM25 scoring in Quickwit if it is not opted-in.
         // By returning false, we inform tantivy that it does not need to decompress
         // term frequencies.
-        self.sort_by.first.requires_scoring()
-            || self
-                .sort_by
-                .second
-                .as_ref()
-                .map(|sort_by| sort_by.requires_scoring())
-                .unwrap_or(false)
+        self.sort_by
+            .components()
+            .any(|sort_by| sort_by.requires_scoring())
     }
 
     fn merge_fruits(
@@ -838,15 +1155,20 @@ impl Collector for QuickwitCollector {
         // We want the hits in [start_offset..start_offset + max_hits).
         // All leaves will return their top [0..start_offset + max_hits) documents.
         // We compute the overall [0..start_offset + max_hits) documents ...
-        let num_hits = self.start_offset + self.max_hits;
-        let (sort_order1, sort_order2) = self.sort_by.sort_orders();
+        let mut num_hits = self.start_offset + self.max_hits;
+        if let Some(knn_k) = self.knn_k_opt {
+            num_hits = num_hits.min(knn_k);
+        }
         let mut merged_leaf_response = merge_leaf_responses(
             &self.aggregation,
             segment_fruits?,
-            sort_order1,
-            sort_order2,
+            self.sort_by.sort_key_mapper(),
+            self.collapse_field.is_some(),
             num_hits,
         )?;
+        if let Some(knn_k) = self.knn_k_opt {
+            merged_leaf_response.num_hits = merged_leaf_response.num_hits.min(knn_k as u64);
+        }
         // ... and drop the first [..start_offsets) hits.
         // note that self.start_offset is 0 when merging from leaf_search, and is only set when
         // merging from root_search, so as to remove the firsts elements only once.
@@ -882,6 +1204,36 @@ fn merge_intermediate_aggregation_result<'a>(
             let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
             Some(serialized)
         }
+        Some(QuickwitAggregations::GeoGridAggregation(collector)) => {
+            let fruits: Vec<GeoGridFruit> = intermediate_aggregation_results
+                .map(|intermediate_aggregation_result| {
+                    postcard::from_bytes(intermediate_aggregation_result).map_err(map_error)
+                })
+                .collect::<Result<_, _>>()?;
+            let merged_fruit: GeoGridFruit = collector.merge_fruits(fruits)?;
+            let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
+            Some(serialized)
+        }
+        Some(QuickwitAggregations::NestedAggregation(collector)) => {
+            let fruits: Vec<NestedAggregationFruit> = intermediate_aggregation_results
+                .map(|intermediate_aggregation_result| {
+                    postcard::from_bytes(intermediate_aggregation_result).map_err(map_error)
+                })
+                .collect::<Result<_, _>>()?;
+            let merged_fruit: NestedAggregationFruit = collector.merge_fruits(fruits)?;
+            let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
+            Some(serialized)
+        }
+        Some(QuickwitAggregations::RuntimeTermsAggregation(collector)) => {
+            let fruits: Vec<RuntimeTermCounts> = intermediate_aggregation_results
+                .map(|intermediate_aggregation_result| {
+                    postcard::from_bytes(intermediate_aggregation_result).map_err(map_error)
+                })
+                .collect::<Result<_, _>>()?;
+            let merged_fruit: RuntimeTermCounts = collector.merge_fruits(fruits)?;
+            let serialized = postcard::to_allocvec(&merged_fruit).map_err(map_error)?;
+            Some(serialized)
+        }
         Some(QuickwitAggregations::TantivyAggregations(_)) => {
             let fruits: Vec<IntermediateAggregationResults> = intermediate_aggregation_results
                 .map(|intermediate_aggregation_result| {
@@ -912,8 +1264,8 @@ fn merge_intermediate_aggregation_result<'a>(
 fn merge_leaf_responses(
     aggregations_opt: &Option<QuickwitAggregations>,
     mut leaf_responses: Vec<LeafSearchResponse>,
-    sort_order1: SortOrder,
-    sort_order2: SortOrder,
+    sort_key_mapper: HitSortingMapper,
+    collapse: bool,
     max_hits: usize,
 ) -> tantivy::Result<LeafSearchResponse> {
     // Optimization: No merging needed if there is only one result.
@@ -950,16 +1302,19 @@ fn merge_leaf_responses(
         .flat_map(|leaf_response| leaf_response.failed_splits.iter())
         .cloned()
         .collect_vec();
-    let all_partial_hits: Vec<PartialHit> = leaf_responses
+    let mut all_partial_hits: Vec<PartialHit> = leaf_responses
         .into_iter()
         .flat_map(|leaf_response| leaf_response.partial_hits)
         .collect();
-    let top_k_partial_hits: Vec<PartialHit> = top_k_partial_hits(
-        all_partial_hits.into_iter(),
-        sort_order1,
-        sort_order2,
-        max_hits,
-    );
+    if collapse {
+        let mut collapsed_hits = HashMap::new();
+        for partial_hit in all_partial_hits {
+            add_collapsed_hit(&mut collapsed_hits, partial_hit, &sort_key_mapper);
+        }
+        all_partial_hits = collapsed_hits.into_values().collect();
+    }
+    let top_k_partial_hits: Vec<PartialHit> =
+        top_k_partial_hits(all_partial_hits.into_iter(), sort_key_mapper, max_hits);
     Ok(LeafSearchResponse {
         intermediate_aggregation_result: merged_intermediate_aggregation_result,
         num_hits,
@@ -977,11 +1332,9 @@ fn merge_leaf_responses(
 /// TODO we could possibly optimize the sort away (but I doubt it matters).
 fn top_k_partial_hits(
     partial_hits: impl Iterator<Item = PartialHit>,
-    order1: SortOrder,
-    order2: SortOrder,
+    sort_key_mapper: HitSortingMapper,
     num_hits: usize,
 ) -> Vec<PartialHit> {
-    let sort_key_mapper = HitSortingMapper { order1, order2 };
     let mut top_k_hits = TopK::new(num_hits, sort_key_mapper);
 
     partial_hits.for_each(|hit| top_k_hits.add_entry(hit));
@@ -989,6 +1342,28 @@ fn top_k_partial_hits(
     top_k_hits.finalize()
 }
 
+/// Adds a hit to the best hits per collapse value, keeping only the best hit for each value.
+///
+/// Hits without a collapse value are collapsed together.
+fn add_collapsed_hit(
+    collapsed_hits: &mut HashMap<Option<String>, PartialHit>,
+    partial_hit: PartialHit,
+    sort_key_mapper: &HitSortingMapper,
+) {
+    match collapsed_hits.entry(partial_hit.collapse_value.clone()) {
+        Entry::Occupied(mut entry) => {
+            if sort_key_mapper.get_sort_key(&partial_hit)
+                > sort_key_mapper.get_sort_key(entry.get())
+            {
+                entry.insert(partial_hit);
+            }
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(partial_hit);
+        }
+    }
+}
+
 pub(crate) fn sort_by_from_request(search_request: &SearchRequest) -> SortByPair {
     let to_sort_by_component = |field_name: &str, order| {
         if field_name == "_score" {
@@ -1003,30 +1378,32 @@ pub(crate) fn sort_by_from_request(search_request: &SearchRequest) -> SortByPair
         }
     };
 
-    let num_sort_fields = search_request.sort_fields.len();
-    if num_sort_fields == 0 {
-        SortByComponent::DocId {
-            order: SortOrder::Desc,
-        }
-        .into()
-    } else if num_sort_fields == 1 {
-        let sort_field = &search_request.sort_fields[0];
+    let mut sort_by_components = search_request.sort_fields.iter().map(|sort_field| {
         let order = SortOrder::from_i32(sort_field.sort_order).unwrap_or(SortOrder::Desc);
-        to_sort_by_component(&sort_field.field_name, order).into()
-    } else if num_sort_fields == 2 {
-        let sort_field1 = &search_request.sort_fields[0];
-        let order1 = SortOrder::from_i32(sort_field1.sort_order).unwrap_or(SortOrder::Desc);
-        let sort_field2 = &search_request.sort_fields[1];
-        let order2 = SortOrder::from_i32(sort_field2.sort_order).unwrap_or(SortOrder::Desc);
-        SortByPair {
-            first: to_sort_by_component(&sort_field1.field_name, order1),
-            second: Some(to_sort_by_component(&sort_field2.field_name, order2)),
+        to_sort_by_component(&sort_field.field_name, order)
+    });
+    let Some(first) = sort_by_components.next() else {
+        return SortByComponent::DocId {
+            order: SortOrder::Desc,
         }
-    } else {
-        panic!("Sort by more than 2 fields is not supported yet.")
+        .into();
+    };
+    SortByPair {
+        first,
+        second: sort_by_components.next(),
+        others: sort_by_components.collect(),
     }
 }
 
+/// Returns the `k` of the top-level k-NN query of the request, if any.
+///
+/// The root only accepts top-level k-NN queries sorted by descending score, without
+/// `search_after`, so that the `k` best hits are the `k` nearest neighbors.
+fn knn_k_from_request(search_request: &SearchRequest) -> Option<usize> {
+    let query_ast: QueryAst = serde_json::from_str(&search_request.query_ast).ok()?;
+    top_level_knn_k(&query_ast).map(|knn_k| knn_k as usize)
+}
+
 /// Builds the QuickwitCollector, in function of the information that was requested by the user.
 pub(crate) fn make_collector_for_split(
     split_id: SplitId,
@@ -1046,6 +1423,8 @@ pub(crate) fn make_collector_for_split(
         aggregation,
         aggregation_limits,
         search_after: search_request.search_after.clone(),
+        collapse_field: search_request.collapse_field.clone(),
+        knn_k_opt: knn_k_from_request(search_request),
     })
 }
 
@@ -1067,13 +1446,16 @@ pub(crate) fn make_merge_collector(
         aggregation,
         aggregation_limits: aggregation_limits.clone(),
         search_after: search_request.search_after.clone(),
+        collapse_field: search_request.collapse_field.clone(),
+        knn_k_opt: knn_k_from_request(search_request),
     })
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SegmentPartialHitSortingKey {
     sort_value: Option<u64>,
     sort_value2: Option<u64>,
+    other_sort_values: Vec<(SortOrder, Option<u64>)>,
     doc_id: DocId,
     // TODO This should not be there.
     sort_order: SortOrder,
@@ -1097,8 +1479,10 @@ impl Ord for SegmentPartialHitSortingKey {
         let order2 = self
             .sort_order2
             .compare_opt(&self.sort_value2, &other.sort_value2);
+        let order_others =
+            compare_other_sort_values(&self.other_sort_values, &other.other_sort_values);
         let order_addr = self.sort_order.compare(&self.doc_id, &other.doc_id);
-        order.then(order2).then(order_addr)
+        order.then(order2).then(order_others).then(order_addr)
     }
 }
 
@@ -1112,6 +1496,7 @@ impl PartialOrd for SegmentPartialHitSortingKey {
 pub(crate) struct PartialHitSortingKey {
     sort_value: Option<SortValue>,
     sort_value2: Option<SortValue>,
+    other_sort_values: Vec<(SortOrder, Option<SortValue>)>,
     address: GlobalDocAddress,
     // TODO remove this
     sort_order: SortOrder,
@@ -1137,9 +1522,12 @@ impl Ord for PartialHitSortingKey {
             .sort_order2
             .compare_opt(&self.sort_value2, &other.sort_value2);
 
+        let order_others =
+            compare_other_sort_values(&self.other_sort_values, &other.other_sort_values);
+
         let order_addr = self.sort_order.compare(&self.address, &other.address);
 
-        order.then(order2).then(order_addr)
+        order.then(order2).then(order_others).then(order_addr)
     }
 }
 
@@ -1149,18 +1537,54 @@ impl PartialOrd for PartialHitSortingKey {
     }
 }
 
+/// Lexicographically compares the values of the sorting keys following the first two.
+#[inline]
+fn compare_other_sort_values<T: Ord>(
+    sort_values: &[(SortOrder, Option<T>)],
+    other_sort_values: &[(SortOrder, Option<T>)],
+) -> Ordering {
+    debug_assert_eq!(sort_values.len(), other_sort_values.len());
+    sort_values
+        .iter()
+        .zip(other_sort_values)
+        .map(|((sort_order, sort_value), (_, other_sort_value))| {
+            sort_order.compare_opt(sort_value, other_sort_value)
+        })
+        .find(|order| order.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
 #[derive(Clone)]
 pub(crate) struct HitSortingMapper {
     pub order1: SortOrder,
     pub order2: SortOrder,
+    pub other_orders: Vec<SortOrder>,
 }
 
 impl SortKeyMapper<PartialHit> for HitSortingMapper {
     type Key = PartialHitSortingKey;
     fn get_sort_key(&self, partial_hit: &PartialHit) -> PartialHitSortingKey {
         PartialHitSortingKey {
-            sort_value: partial_hit.sort_value.and_then(|v| v.sort_value),
-            sort_value2: partial_hit.sort_value2.and_then(|v| v.sort_value),
+            sort_value: partial_hit
+                .sort_value
+                .as_ref()
+                .and_then(|v| v.sort_value.clone()),
+            sort_value2: partial_hit
+                .sort_value2
+                .as_ref()
+                .and_then(|v| v.sort_value.clone()),
+            other_sort_values: self
+                .other_orders
+                .iter()
+                .enumerate()
+                .map(|(idx, sort_order)| {
+                    let sort_value = partial_hit
+                        .extra_sort_values
+                        .get(idx)
+                        .and_then(|v| v.sort_value.clone());
+                    (*sort_order, sort_value)
+                })
+                .collect(),
             address: GlobalDocAddress::from_partial_hit(partial_hit),
             sort_order: self.order1,
             sort_order2: self.order2,
@@ -1174,6 +1598,15 @@ impl SortKeyMapper<SegmentPartialHit> for HitSortingMapper {
         SegmentPartialHitSortingKey {
             sort_value: partial_hit.sort_value,
             sort_value2: partial_hit.sort_value2,
+            other_sort_values: self
+                .other_orders
+                .iter()
+                .enumerate()
+                .map(|(idx, sort_order)| {
+                    let sort_value = partial_hit.other_sort_values.get(idx).copied().flatten();
+                    (*sort_order, sort_value)
+                })
+                .collect(),
             doc_id: partial_hit.doc_id,
             sort_order: self.order1,
             sort_order2: self.order2,
@@ -1185,12 +1618,16 @@ impl SortKeyMapper<SegmentPartialHit> for HitSortingMapper {
 #[derive(Clone)]
 pub(crate) struct IncrementalCollector {
     top_k_hits: TopK<PartialHit, PartialHitSortingKey, HitSortingMapper>,
+    // Best hit per collapse value, if the hits are collapsed. In that case, `top_k_hits` is only
+    // used to compute the top-K at the end.
+    collapsed_hits: Option<HashMap<Option<String>, PartialHit>>,
     incremental_aggregation: QuickwitIncrementalAggregations,
     num_hits: u64,
     failed_splits: Vec<SplitSearchError>,
     num_attempted_splits: u64,
     num_successful_splits: u64,
     start_offset: usize,
+    knn_k_opt: Option<usize>,
     resource_stats: Option<ResourceStats>,
 }
 
@@ -1202,11 +1639,17 @@ impl IncrementalCollector {
             .as_ref()
             .map(QuickwitAggregations::maybe_incremental_aggregator)
             .unwrap_or(QuickwitIncrementalAggregations::NoAggregation);
-        let (order1, order2) = collector.sort_by.sort_orders();
-        let sort_key_mapper = HitSortingMapper { order1, order2 };
+        let sort_key_mapper = collector.sort_by.sort_key_mapper();
+        let collapsed_hits = collector.collapse_field.as_ref().map(|_| HashMap::new());
+        let mut num_top_hits = collector.max_hits + collector.start_offset;
+        if let Some(knn_k) = collector.knn_k_opt {
+            num_top_hits = num_top_hits.min(knn_k);
+        }
         IncrementalCollector {
-            top_k_hits: TopK::new(collector.max_hits + collector.start_offset, sort_key_mapper),
+            top_k_hits: TopK::new(num_top_hits, sort_key_mapper),
+            collapsed_hits,
             start_offset: collector.start_offset,
+            knn_k_opt: collector.knn_k_opt,
             incremental_aggregation,
             num_hits: 0,
             failed_splits: Vec::new(),
@@ -1231,7 +1674,17 @@ impl IncrementalCollector {
         merge_resource_stats(&resource_stats, &mut self.resource_stats);
 
         self.num_hits += num_hits;
-        self.top_k_hits.add_entries(partial_hits.into_iter());
+        if let Some(collapsed_hits) = self.collapsed_hits.as_mut() {
+            for partial_hit in partial_hits {
+                add_collapsed_hit(
+                    collapsed_hits,
+                    partial_hit,
+                    &self.top_k_hits.sort_key_mapper,
+                );
+            }
+        } else {
+            self.top_k_hits.add_entries(partial_hits.into_iter());
+        }
         self.failed_splits.extend(failed_splits);
         self.num_attempted_splits += num_attempted_splits;
         self.num_successful_splits += num_successful_splits;
@@ -1257,6 +1710,11 @@ impl IncrementalCollector {
                 .virtual_worst_hit()
                 .map(Cow::Owned);
         }
+        // A better hit for an already collected collapse value does not take a new slot, so we
+        // can't tell which hit is the worst of the top-K.
+        if self.collapsed_hits.is_some() {
+            return None;
+        }
 
         if self.top_k_hits.at_capacity() {
             self.top_k_hits.peek_worst().map(Cow::Borrowed)
@@ -1268,12 +1726,21 @@ impl IncrementalCollector {
     /// Finalize the merge, creating a LeafSearchResponse.
     pub(crate) fn finalize(self) -> tantivy::Result<LeafSearchResponse> {
         let intermediate_aggregation_result = self.incremental_aggregation.finalize()?;
-        let mut partial_hits = self.top_k_hits.finalize();
+        let mut top_k_hits = self.top_k_hits;
+        if let Some(collapsed_hits) = self.collapsed_hits {
+            top_k_hits.add_entries(collapsed_hits.into_values());
+        }
+        let mut partial_hits = top_k_hits.finalize();
         if self.start_offset != 0 {
             partial_hits.drain(0..self.start_offset.min(partial_hits.len()));
         }
+        let num_hits = if let Some(knn_k) = self.knn_k_opt {
+            self.num_hits.min(knn_k as u64)
+        } else {
+            self.num_hits
+        };
         Ok(LeafSearchResponse {
-            num_hits: self.num_hits,
+            num_hits,
             partial_hits,
             failed_splits: self.failed_splits,
             num_attempted_splits: self.num_attempted_splits,
@@ -1288,6 +1755,7 @@ impl IncrementalCollector {
 mod tests {
     use std::cmp::Ordering;
 
+    use itertools::Itertools;
     use quickwit_proto::search::{
         LeafSearchResponse, PartialHit, ResourceStats, SearchRequest, SortByValue, SortField,
         SortOrder, SortValue, SplitSearchError,
@@ -1296,13 +1764,15 @@ mod tests {
     use tantivy::TantivyDocument;
 
     use super::{make_merge_collector, IncrementalCollector};
-    use crate::collector::top_k_partial_hits;
+    use crate::collector::{top_k_partial_hits, HitSortingMapper};
 
     #[test]
     fn test_merge_partial_hits_no_tie() {
         let make_doc = |sort_value: u64| PartialHit {
             sort_value: Some(SortValue::U64(sort_value).into()),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: "split1".to_string(),
             segment_ord: 0u32,
             doc_id: 0u32,
@@ -1310,8 +1780,11 @@ mod tests {
         assert_eq!(
             top_k_partial_hits(
                 vec![make_doc(1u64), make_doc(3u64), make_doc(2u64),].into_iter(),
-                SortOrder::Asc,
-                SortOrder::Asc,
+                HitSortingMapper {
+                    order1: SortOrder::Asc,
+                    order2: SortOrder::Asc,
+                    other_orders: Vec::new(),
+                },
                 2
             ),
             vec![make_doc(1), make_doc(2)]
@@ -1323,6 +1796,8 @@ mod tests {
         let make_hit_given_split_id = |split_id: u64| PartialHit {
             sort_value: Some(SortValue::U64(0u64).into()),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: format!("split_{split_id}"),
             segment_ord: 0u32,
             doc_id: 0u32,
@@ -1335,8 +1810,11 @@ mod tests {
                     make_hit_given_split_id(2u64),
                 ]
                 .into_iter(),
-                SortOrder::Desc,
-                SortOrder::Desc,
+                HitSortingMapper {
+                    order1: SortOrder::Desc,
+                    order2: SortOrder::Desc,
+                    other_orders: Vec::new(),
+                },
                 2
             ),
             &[make_hit_given_split_id(3), make_hit_given_split_id(2)]
@@ -1349,8 +1827,11 @@ mod tests {
                     make_hit_given_split_id(2u64),
                 ]
                 .into_iter(),
-                SortOrder::Asc,
-                SortOrder::Asc,
+                HitSortingMapper {
+                    order1: SortOrder::Asc,
+                    order2: SortOrder::Asc,
+                    other_orders: Vec::new(),
+                },
                 2
             ),
             &[make_hit_given_split_id(1), make_hit_given_split_id(2)]
@@ -1591,8 +2072,10 @@ mod tests {
                                 format!(
                                     "{} {:?} {:?}",
                                     hit.doc_id,
-                                    hit.sort_value.and_then(|el| el.sort_value).clone(),
-                                    hit.sort_value2.and_then(|el| el.sort_value).clone()
+                                    hit.sort_value(),
+                                    hit.sort_value2
+                                        .as_ref()
+                                        .and_then(|el| el.sort_value.clone())
                                 )
                             })
                             .collect::<Vec<_>>();
@@ -1637,6 +2120,8 @@ mod tests {
                 sort_value2: Some(SortByValue {
                     sort_value: val2.map(SortValue::U64),
                 }),
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             })
             .collect::<Vec<_>>();
         // we eliminate based on sort value
@@ -1684,6 +2169,8 @@ mod tests {
                 doc_id: 5,
                 sort_value: None,
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             };
             let request = SearchRequest {
                 max_hits: 1000,
@@ -1739,6 +2226,253 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_single_split_sorting_by_three_fields() {
+        use tantivy::schema::{NumericOptions, Schema};
+        use tantivy::Index;
+
+        // every combination of 0..2 + None for 3 fields.
+        let values = [None, Some(0u64), Some(1u64)];
+        let dataset: Vec<[Option<u64>; 3]> = values
+            .into_iter()
+            .cartesian_product(values)
+            .cartesian_product(values)
+            .map(|((val1, val2), val3)| [val1, val2, val3])
+            .collect();
+
+        let mut schema_builder = Schema::builder();
+        let opts = NumericOptions::default().set_fast();
+        let fields = ["sort1", "sort2", "sort3"]
+            .map(|field_name| schema_builder.add_u64_field(field_name, opts.clone()));
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer(50_000_000).unwrap();
+        for sort_values in &dataset {
+            let mut doc = TantivyDocument::new();
+            for (field, sort_value_opt) in fields.iter().zip(sort_values) {
+                if let Some(sort_value) = sort_value_opt {
+                    doc.add_u64(*field, *sort_value);
+                }
+            }
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        // Missing values always come last.
+        let cmp_desc = |a: &Option<u64>, b: &Option<u64>| b.cmp(a);
+        let cmp_asc = |a: &Option<u64>, b: &Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => a.cmp(b),
+            _ => b.cmp(a),
+        };
+        let mut expected: Vec<(usize, [Option<u64>; 3])> =
+            dataset.iter().cloned().enumerate().collect();
+        expected.sort_by(|(doc_id_a, a), (doc_id_b, b)| {
+            cmp_desc(&a[0], &b[0])
+                .then(cmp_asc(&a[1], &b[1]))
+                .then(cmp_desc(&a[2], &b[2]))
+                .then(doc_id_b.cmp(doc_id_a))
+        });
+
+        let collector = super::make_collector_for_split(
+            "fake_split_id".to_string(),
+            &make_request(dataset.len() as u64, "sort1,-sort2,sort3"),
+            Default::default(),
+        )
+        .unwrap();
+        let res = searcher
+            .search(&tantivy::query::AllQuery, &collector)
+            .unwrap();
+        let got_doc_ids: Vec<u32> = res.partial_hits.iter().map(|hit| hit.doc_id).collect();
+        let expected_doc_ids: Vec<u32> =
+            expected.iter().map(|(doc_id, _)| *doc_id as u32).collect();
+        assert_eq!(got_doc_ids, expected_doc_ids);
+        for (hit, (_, sort_values)) in res.partial_hits.iter().zip(&expected) {
+            assert_eq!(hit.extra_sort_values.len(), 1);
+            assert_eq!(
+                hit.extra_sort_values[0].sort_value,
+                sort_values[2].map(SortValue::U64)
+            );
+        }
+
+        // Paginating with search_after over the three sort values yields the remaining hits.
+        for (i, search_after) in res.partial_hits.iter().enumerate() {
+            let mut request = make_request(dataset.len() as u64, "sort1,-sort2,sort3");
+            request.search_after = Some(search_after.clone());
+            let collector = super::make_collector_for_split(
+                "fake_split_id".to_string(),
+                &request,
+                Default::default(),
+            )
+            .unwrap();
+            let after_res = searcher
+                .search(&tantivy::query::AllQuery, &collector)
+                .unwrap();
+            let after_doc_ids: Vec<u32> = after_res
+                .partial_hits
+                .iter()
+                .map(|hit| hit.doc_id)
+                .collect();
+            assert_eq!(after_doc_ids, expected_doc_ids[i + 1..]);
+        }
+
+        // Merging leaf responses keeps the same order.
+        let merge_collector =
+            make_merge_collector(&make_request(5, "sort1,-sort2,sort3"), &Default::default())
+                .unwrap();
+        let (first_half, second_half) = res.partial_hits.split_at(res.partial_hits.len() / 2);
+        let leaf_responses = [second_half, first_half]
+            .into_iter()
+            .map(|partial_hits| {
+                Ok(LeafSearchResponse {
+                    partial_hits: partial_hits.to_vec(),
+                    ..LeafSearchResponse::default()
+                })
+            })
+            .collect();
+        let merged_res = merge_collector.merge_fruits(leaf_responses).unwrap();
+        assert_eq!(merged_res.partial_hits, res.partial_hits[..5]);
+    }
+
+    #[test]
+    fn test_collapse_hits_on_text_fast_field() {
+        use tantivy::schema::{Schema, FAST, STRING};
+        use tantivy::Index;
+
+        let mut schema_builder = Schema::builder();
+        let host_field = schema_builder.add_text_field("host", STRING | FAST);
+        let timestamp_field = schema_builder.add_u64_field("timestamp", FAST);
+        let schema = schema_builder.build();
+
+        let mut request = make_request(10, "timestamp");
+        request.collapse_field = Some("host".to_string());
+
+        let split_docs: [&[(Option<&str>, u64)]; 2] = [
+            &[(Some("a"), 1), (Some("b"), 2), (Some("a"), 3), (None, 6)],
+            &[(None, 4), (Some("b"), 5), (Some("c"), 0), (Some("a"), 2)],
+        ];
+        let mut leaf_responses = Vec::new();
+        for (split_ord, docs) in split_docs.iter().enumerate() {
+            let index = Index::create_in_ram(schema.clone());
+            let mut index_writer = index.writer(50_000_000).unwrap();
+            for (host_opt, timestamp) in docs.iter() {
+                let mut doc = TantivyDocument::new();
+                if let Some(host) = host_opt {
+                    doc.add_text(host_field, host);
+                }
+                doc.add_u64(timestamp_field, *timestamp);
+                index_writer.add_document(doc).unwrap();
+            }
+            index_writer.commit().unwrap();
+            let searcher = index.reader().unwrap().searcher();
+            let collector = super::make_collector_for_split(
+                format!("split{split_ord}"),
+                &request,
+                Default::default(),
+            )
+            .unwrap();
+            let leaf_response = searcher
+                .search(&tantivy::query::AllQuery, &collector)
+                .unwrap();
+            leaf_responses.push(leaf_response);
+        }
+        let collapsed_hits = |leaf_response: &LeafSearchResponse| {
+            leaf_response
+                .partial_hits
+                .iter()
+                .map(|hit| {
+                    let timestamp = hit.sort_value();
+                    (hit.collapse_value.clone(), timestamp)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            collapsed_hits(&leaf_responses[0]),
+            [
+                (None, Some(SortValue::U64(6))),
+                (Some("a".to_string()), Some(SortValue::U64(3))),
+                (Some("b".to_string()), Some(SortValue::U64(2))),
+            ]
+        );
+        assert_eq!(leaf_responses[0].num_hits, 4);
+
+        let merged_response = merge_collector_equal_results(&request, leaf_responses);
+        assert_eq!(
+            collapsed_hits(&merged_response),
+            [
+                (None, Some(SortValue::U64(6))),
+                (Some("b".to_string()), Some(SortValue::U64(5))),
+                (Some("a".to_string()), Some(SortValue::U64(3))),
+                (Some("c".to_string()), Some(SortValue::U64(0))),
+            ]
+        );
+        assert_eq!(merged_response.num_hits, 8);
+    }
+
+    #[test]
+    fn test_merge_collectors_keeps_knn_k_best_hits() {
+        let query_ast = quickwit_query::query_ast::KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![1.0, 0.0],
+            k: 3,
+            similarity: None,
+            filter: None,
+        };
+        let request = SearchRequest {
+            query_ast: serde_json::to_string(&quickwit_query::query_ast::QueryAst::from(query_ast))
+                .unwrap(),
+            max_hits: 10,
+            sort_fields: vec![SortField {
+                field_name: "_score".to_string(),
+                sort_order: SortOrder::Desc as i32,
+                sort_datetime_format: None,
+            }],
+            ..Default::default()
+        };
+        let make_leaf_response = |split_id: &str, scores: &[f64]| LeafSearchResponse {
+            num_hits: scores.len() as u64,
+            partial_hits: scores
+                .iter()
+                .enumerate()
+                .map(|(doc_id, score)| PartialHit {
+                    split_id: split_id.to_string(),
+                    segment_ord: 0,
+                    doc_id: doc_id as u32,
+                    sort_value: Some(SortValue::F64(*score).into()),
+                    sort_value2: None,
+                    extra_sort_values: Vec::new(),
+                    collapse_value: None,
+                })
+                .collect(),
+            failed_splits: Vec::new(),
+            num_attempted_splits: 1,
+            num_successful_splits: 1,
+            intermediate_aggregation_result: None,
+            resource_stats: None,
+        };
+        // Each split returns its own 3 nearest neighbors.
+        let merged_response = merge_collector_equal_results(
+            &request,
+            vec![
+                make_leaf_response("split1", &[0.9, 0.5, 0.1]),
+                make_leaf_response("split2", &[0.8, 0.7, 0.2]),
+            ],
+        );
+        assert_eq!(merged_response.num_hits, 3);
+        let hits: Vec<(&str, Option<SortValue>)> = merged_response
+            .partial_hits
+            .iter()
+            .map(|hit| (hit.split_id.as_str(), hit.sort_value()))
+            .collect();
+        assert_eq!(
+            hits,
+            [
+                ("split1", Some(SortValue::F64(0.9))),
+                ("split2", Some(SortValue::F64(0.8))),
+                ("split2", Some(SortValue::F64(0.7))),
+            ]
+        );
+    }
+
     fn merge_collector_equal_results(
         request: &SearchRequest,
         results: Vec<LeafSearchResponse>,
@@ -1781,6 +2515,8 @@ mod tests {
                     doc_id: 123,
                     sort_value: Some(SortValue::I64(1234).into()),
                     sort_value2: None,
+                    extra_sort_values: Vec::new(),
+                    collapse_value: None,
                 }],
                 failed_splits: Vec::new(),
                 num_attempted_splits: 3,
@@ -1800,6 +2536,8 @@ mod tests {
                     doc_id: 123,
                     sort_value: Some(SortValue::I64(1234).into()),
                     sort_value2: None,
+                    extra_sort_values: Vec::new(),
+                    collapse_value: None,
                 }],
                 failed_splits: Vec::new(),
                 num_attempted_splits: 3,
@@ -1831,6 +2569,8 @@ mod tests {
                             doc_id: 123,
                             sort_value: Some(SortValue::I64(1234).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                         },
                         PartialHit {
                             split_id: "1".to_string(),
@@ -1838,6 +2578,8 @@ mod tests {
                             doc_id: 125,
                             sort_value: Some(SortValue::I64(1236).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                         },
                     ],
                     failed_splits: Vec::new(),
@@ -1854,6 +2596,8 @@ mod tests {
                         doc_id: 3,
                         sort_value: Some(SortValue::I64(1235).into()),
                         sort_value2: None,
+                        extra_sort_values: Vec::new(),
+                        collapse_value: None,
                     }],
                     failed_splits: vec![SplitSearchError {
                         error: "fake error".to_string(),
@@ -1879,6 +2623,8 @@ mod tests {
                         doc_id: 125,
                         sort_value: Some(SortValue::I64(1236).into()),
                         sort_value2: None,
+                        extra_sort_values: Vec::new(),
+                        collapse_value: None,
                     },
                     PartialHit {
                         split_id: "2".to_string(),
@@ -1886,6 +2632,8 @@ mod tests {
                         doc_id: 3,
                         sort_value: Some(SortValue::I64(1235).into()),
                         sort_value2: None,
+                        extra_sort_values: Vec::new(),
+                        collapse_value: None,
                     },
                 ],
                 failed_splits: vec![SplitSearchError {
@@ -1923,6 +2671,8 @@ mod tests {
                             doc_id: 123,
                             sort_value: Some(SortValue::I64(1234).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                         },
                         PartialHit {
                             split_id: "1".to_string(),
@@ -1930,6 +2680,8 @@ mod tests {
                             doc_id: 125,
                             sort_value: Some(SortValue::I64(1236).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                         },
                     ],
                     failed_splits: Vec::new(),
@@ -1949,6 +2701,8 @@ mod tests {
                         doc_id: 3,
                         sort_value: Some(SortValue::I64(1235).into()),
                         sort_value2: None,
+                        extra_sort_values: Vec::new(),
+                        collapse_value: None,
                     }],
                     failed_splits: vec![SplitSearchError {
                         error: "fake error".to_string(),
@@ -1977,6 +2731,8 @@ mod tests {
                         doc_id: 123,
                         sort_value: Some(SortValue::I64(1234).into()),
                         sort_value2: None,
+                        extra_sort_values: Vec::new(),
+                        collapse_value: None,
                     },
                     PartialHit {
                         split_id: "2".to_string(),
@@ -1984,6 +2740,8 @@ mod tests {
                         doc_id: 3,
                         sort_value: Some(SortValue::I64(1235).into()),
                         sort_value2: None,
+                        extra_sort_values: Vec::new(),
+                        collapse_value: None,
                     },
                 ],
                 failed_splits: vec![SplitSearchError {
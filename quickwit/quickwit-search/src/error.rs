@@ -19,6 +19,7 @@
 
 use itertools::Itertools;
 use quickwit_common::rate_limited_error;
+use quickwit_common::retry::Retryable;
 use quickwit_doc_mapper::QueryParserError;
 use quickwit_proto::error::grpc_error_to_grpc_status;
 use quickwit_proto::metastore::{EntityKind, MetastoreError};
@@ -35,6 +36,8 @@ use tokio::task::JoinError;
 #[derive(Error, Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum SearchError {
+    #[error("async search `{0}` not found")]
+    AsyncSearchNotFound(String),
     #[error("could not find indexes matching the IDs `{index_ids:?}`")]
     IndexesNotFound { index_ids: Vec<String> },
     #[error("internal error: `{0}`")]
@@ -75,6 +78,7 @@ impl SearchError {
 impl ServiceError for SearchError {
     fn error_code(&self) -> ServiceErrorCode {
         match self {
+            Self::AsyncSearchNotFound(_) => ServiceErrorCode::NotFound,
             Self::IndexesNotFound { .. } => ServiceErrorCode::NotFound,
             Self::Internal(error_msg) => {
                 rate_limited_error!(limit_per_min = 6, "search internal error: {error_msg}");
@@ -97,6 +101,15 @@ impl ServiceError for SearchError {
     }
 }
 
+impl Retryable for SearchError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Internal(_) | Self::Timeout(_) | Self::TooManyRequests | Self::Unavailable(_)
+        )
+    }
+}
+
 impl GrpcServiceError for SearchError {
     fn new_internal(message: String) -> Self {
         Self::Internal(message)
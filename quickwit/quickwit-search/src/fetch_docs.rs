@@ -23,23 +23,33 @@ use std::sync::Arc;
 use anyhow::{Context, Ok};
 use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
-use quickwit_doc_mapper::DocMapper;
+use quickwit_doc_mapper::{DocMapper, WarmupInfo};
 use quickwit_proto::search::{
     FetchDocsResponse, PartialHit, SnippetRequest, SplitIdAndFooterOffsets,
 };
+use quickwit_query::runtime_field::{
+    parse_runtime_mappings, CompiledRuntimeField, FastFieldDocReader, RuntimeFieldEvaluator,
+    RuntimeValue,
+};
 use quickwit_storage::Storage;
+use serde_json::Value as JsonValue;
 use tantivy::query::Query;
 use tantivy::schema::document::CompactDocValue;
-use tantivy::schema::{Document as DocumentTrait, Field, TantivyDocument, Value};
+use tantivy::schema::{
+    Document as DocumentTrait, Field, FieldType, Schema, TantivyDocument, Value,
+};
 use tantivy::snippet::SnippetGenerator;
-use tantivy::{ReloadPolicy, Score, Searcher, Term};
+use tantivy::{DocAddress, ReloadPolicy, Score, Searcher, Term};
 use tracing::{error, Instrument};
 
-use crate::leaf::open_index_with_caches;
+use crate::leaf::{open_index_with_caches, warmup};
+use crate::list_fields::matches_pattern;
 use crate::service::SearcherContext;
 use crate::{convert_document_to_json_string, GlobalDocAddress};
 
 const SNIPPET_MAX_NUM_CHARS: usize = 150;
+const DEFAULT_SNIPPET_PRE_TAG: &str = "<b>";
+const DEFAULT_SNIPPET_POST_TAG: &str = "</b>";
 
 /// Given a list of global doc address, fetches all the documents and
 /// returns them as a hashmap.
@@ -50,6 +60,8 @@ async fn fetch_docs_to_map(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    source_fields: &[String],
+    runtime_fields: &[(String, CompiledRuntimeField)],
 ) -> anyhow::Result<HashMap<GlobalDocAddress, Document>> {
     let mut split_fetch_docs_futures = Vec::new();
 
@@ -77,6 +89,8 @@ async fn fetch_docs_to_map(
             split_and_offset,
             doc_mapper.clone(),
             snippet_request_opt,
+            source_fields,
+            runtime_fields,
         ));
     }
 
@@ -110,6 +124,11 @@ async fn fetch_docs_to_map(
 /// This function takes a list of partial hits (possibly from different splits)
 /// and the storage associated to an index, fetches the document from
 /// the split document stores, and returns the full hits.
+///
+/// If `source_fields` is not empty, the documents are restricted to those fields. The values of
+/// the runtime fields of `runtime_mappings_opt` are computed for each document and returned
+/// separately.
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_docs(
     searcher_context: Arc<SearcherContext>,
     partial_hits: Vec<PartialHit>,
@@ -117,7 +136,10 @@ pub async fn fetch_docs(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    source_fields: &[String],
+    runtime_mappings_opt: Option<&str>,
 ) -> anyhow::Result<FetchDocsResponse> {
+    let runtime_fields = compile_runtime_fields(runtime_mappings_opt)?;
     let global_doc_addrs: Vec<GlobalDocAddress> = partial_hits
         .iter()
         .map(GlobalDocAddress::from_partial_hit)
@@ -130,6 +152,8 @@ pub async fn fetch_docs(
         splits,
         doc_mapper,
         snippet_request_opt,
+        source_fields,
+        &runtime_fields,
     )
     .await?;
 
@@ -143,6 +167,7 @@ pub async fn fetch_docs(
                     leaf_json: document.content_json,
                     partial_hit: Some(partial_hit.clone()),
                     leaf_snippet_json: document.snippet_json,
+                    leaf_fields_json: document.fields_json,
                 })
             } else {
                 None
@@ -155,11 +180,77 @@ pub async fn fetch_docs(
 // number of concurrent fetch allowed for a single split.
 const NUM_CONCURRENT_REQUESTS: usize = 30;
 
-/// A struct for holding a fetched document's content and snippet.
+/// A struct for holding a fetched document's content, snippet and runtime field values.
 #[derive(Debug)]
 struct Document {
     content_json: String,
     snippet_json: Option<String>,
+    fields_json: Option<String>,
+}
+
+/// Compiles the runtime fields of a fetch docs request.
+fn compile_runtime_fields(
+    runtime_mappings_opt: Option<&str>,
+) -> anyhow::Result<Vec<(String, CompiledRuntimeField)>> {
+    let Some(runtime_mappings_json) = runtime_mappings_opt else {
+        return Ok(Vec::new());
+    };
+    parse_runtime_mappings(runtime_mappings_json)?
+        .into_iter()
+        .map(|(field_name, runtime_field)| Ok((field_name, runtime_field.compile()?)))
+        .collect()
+}
+
+/// Computes the values of the runtime fields of the documents fetched from a split.
+///
+/// Like when filtering and aggregating, the scripts read the fast fields of the documents, so
+/// the returned values are consistent with the query and the aggregations.
+fn add_runtime_field_values(
+    searcher: &Searcher,
+    runtime_fields: &[(String, CompiledRuntimeField)],
+    documents: &mut [(GlobalDocAddress, Document)],
+) -> anyhow::Result<()> {
+    let mut evaluators: Vec<(&str, RuntimeFieldEvaluator)> = runtime_fields
+        .iter()
+        .map(|(field_name, compiled_runtime_field)| {
+            (field_name.as_str(), compiled_runtime_field.evaluator())
+        })
+        .collect();
+    let mut fast_field_doc_readers_per_segment: HashMap<u32, Vec<FastFieldDocReader>> =
+        HashMap::new();
+
+    for (global_doc_addr, document) in documents {
+        let DocAddress {
+            segment_ord,
+            doc_id,
+        } = global_doc_addr.doc_addr;
+        if !fast_field_doc_readers_per_segment.contains_key(&segment_ord) {
+            let segment_reader = searcher.segment_reader(segment_ord);
+            let fast_field_doc_readers = evaluators
+                .iter()
+                .map(|(_, evaluator)| {
+                    FastFieldDocReader::open(segment_reader, evaluator.input_fields())
+                })
+                .collect::<tantivy::Result<Vec<_>>>()?;
+            fast_field_doc_readers_per_segment.insert(segment_ord, fast_field_doc_readers);
+        }
+        let fast_field_doc_readers = &fast_field_doc_readers_per_segment[&segment_ord];
+        let mut fields = serde_json::Map::new();
+
+        for ((field_name, evaluator), fast_field_doc_reader) in
+            evaluators.iter_mut().zip(fast_field_doc_readers)
+        {
+            let json_doc = fast_field_doc_reader.read_doc(doc_id)?;
+            let values = evaluator.evaluate(&json_doc);
+            if values.is_empty() {
+                continue;
+            }
+            let json_values = values.iter().map(RuntimeValue::to_json).collect();
+            fields.insert(field_name.to_string(), JsonValue::Array(json_values));
+        }
+        document.fields_json = Some(serde_json::to_string(&fields)?);
+    }
+    Ok(())
 }
 
 /// Fetching docs from a specific split.
@@ -170,6 +261,8 @@ async fn fetch_docs_in_split(
     split: &SplitIdAndFooterOffsets,
     doc_mapper: Arc<DocMapper>,
     snippet_request_opt: Option<&SnippetRequest>,
+    source_fields: &[String],
+    runtime_fields: &[(String, CompiledRuntimeField)],
 ) -> anyhow::Result<Vec<(GlobalDocAddress, Document)>> {
     global_doc_addrs.sort_by_key(|doc| doc.doc_addr);
     // Opens the index without the ephemeral unbounded cache, this cache is indeed not useful
@@ -196,6 +289,17 @@ async fn fetch_docs_in_split(
         .reload_policy(ReloadPolicy::Manual)
         .try_into()?;
     let searcher = Arc::new(index_reader.searcher());
+    if !runtime_fields.is_empty() {
+        let warmup_info = WarmupInfo {
+            fast_field_names: runtime_fields
+                .iter()
+                .flat_map(|(_, compiled_runtime_field)| compiled_runtime_field.input_fields())
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        warmup(&searcher, &warmup_info).await?;
+    }
     let fields_snippet_generator_opt = if let Some(snippet_request) = snippet_request_opt {
         Some(create_fields_snippet_generator(&searcher, doc_mapper.clone(), snippet_request).await?)
     } else {
@@ -213,13 +317,15 @@ async fn fetch_docs_in_split(
                 .context("searcher-doc-async")?;
 
             let named_field_doc = doc.to_named_doc(moved_searcher.schema());
-            let content_json = convert_document_to_json_string(named_field_doc, &moved_doc_mapper)?;
+            let content_json =
+                convert_document_to_json_string(named_field_doc, &moved_doc_mapper, source_fields)?;
             if fields_snippet_generator_opt_clone.is_none() {
                 return Ok((
                     global_doc_addr,
                     Document {
                         content_json,
                         snippet_json: None,
+                        fields_json: None,
                     },
                 ));
             }
@@ -231,6 +337,7 @@ async fn fetch_docs_in_split(
                     Document {
                         content_json,
                         snippet_json: None,
+                        fields_json: None,
                     },
                 ));
             }
@@ -250,16 +357,21 @@ async fn fetch_docs_in_split(
                 Document {
                     content_json,
                     snippet_json: Some(snippet_json),
+                    fields_json: None,
                 },
             ))
         }
         .in_current_span()
     });
 
-    futures::stream::iter(doc_futures)
+    let mut documents: Vec<(GlobalDocAddress, Document)> = futures::stream::iter(doc_futures)
         .buffer_unordered(NUM_CONCURRENT_REQUESTS)
         .try_collect::<Vec<_>>()
-        .await
+        .await?;
+    if !runtime_fields.is_empty() {
+        add_runtime_field_values(&searcher, runtime_fields, &mut documents)?;
+    }
+    Ok(documents)
 }
 
 // A struct to hold the snippet generators associated to
@@ -267,6 +379,9 @@ async fn fetch_docs_in_split(
 #[derive(Clone)]
 struct FieldsSnippetGenerator {
     field_generators: Arc<HashMap<String, SnippetGenerator>>,
+    pre_tag: Option<String>,
+    post_tag: Option<String>,
+    max_num_snippets: Option<usize>,
 }
 
 impl FieldsSnippetGenerator {
@@ -281,13 +396,22 @@ impl FieldsSnippetGenerator {
                 .into_iter()
                 .filter_map(|value| {
                     value.as_str().and_then(|text| {
-                        let snippet = snippet_generator.snippet(text);
-                        match snippet.is_empty() {
-                            false => Some(snippet.to_html()),
-                            _ => None,
+                        let mut snippet = snippet_generator.snippet(text);
+                        if snippet.is_empty() {
+                            return None;
+                        }
+                        if self.pre_tag.is_some() || self.post_tag.is_some() {
+                            snippet.set_snippet_prefix_postfix(
+                                self.pre_tag.as_deref().unwrap_or(DEFAULT_SNIPPET_PRE_TAG),
+                                self.post_tag.as_deref().unwrap_or(DEFAULT_SNIPPET_POST_TAG),
+                            );
                         }
+                        Some(snippet.to_html())
                     })
                 })
+                // The snippet generator extracts a single fragment per value, so the limit applies
+                // to the values of multivalued fields.
+                .take(self.max_num_snippets.unwrap_or(usize::MAX))
                 .collect();
             Some(values)
         } else {
@@ -300,6 +424,34 @@ impl FieldsSnippetGenerator {
     }
 }
 
+// Resolves the snippet fields, which may contain field patterns such as `body*`,
+// against the split schema. Patterns only match stored text fields.
+fn resolve_snippet_fields(schema: &Schema, snippet_fields: &[String]) -> Vec<String> {
+    let mut resolved_snippet_fields: Vec<String> = Vec::new();
+
+    for snippet_field in snippet_fields {
+        if !snippet_field.contains('*') {
+            resolved_snippet_fields.push(snippet_field.clone());
+            continue;
+        }
+        for (_field, field_entry) in schema.fields() {
+            let FieldType::Str(text_options) = field_entry.field_type() else {
+                continue;
+            };
+            if !text_options.is_stored()
+                || text_options.get_indexing_options().is_none()
+                || !matches_pattern(snippet_field, field_entry.name())
+            {
+                continue;
+            }
+            resolved_snippet_fields.push(field_entry.name().to_string());
+        }
+    }
+    resolved_snippet_fields.sort_unstable();
+    resolved_snippet_fields.dedup();
+    resolved_snippet_fields
+}
+
 // Creates FieldsSnippetGenerator.
 async fn create_fields_snippet_generator(
     searcher: &Searcher,
@@ -310,15 +462,26 @@ async fn create_fields_snippet_generator(
     let query_ast_resolved = serde_json::from_str(&snippet_request.query_ast_resolved)
         .context("failed to deserialize QueryAst")?;
     let (query, _) = doc_mapper.query(schema.clone(), &query_ast_resolved, false)?;
+    let snippet_options = snippet_request.snippet_options.clone().unwrap_or_default();
+    let max_num_chars = snippet_options
+        .max_num_chars
+        .map(|max_num_chars| max_num_chars as usize)
+        .unwrap_or(SNIPPET_MAX_NUM_CHARS);
     let mut snippet_generators = HashMap::new();
-    for field_name in &snippet_request.snippet_fields {
-        let field = schema.get_field(field_name)?;
-        let snippet_generator = create_snippet_generator(searcher, &query, field).await?;
-        snippet_generators.insert(field_name.clone(), snippet_generator);
-    }
 
+    for field_name in resolve_snippet_fields(schema, &snippet_request.snippet_fields) {
+        let field = schema.get_field(&field_name)?;
+        let snippet_generator =
+            create_snippet_generator(searcher, &query, field, max_num_chars).await?;
+        snippet_generators.insert(field_name, snippet_generator);
+    }
     Ok(FieldsSnippetGenerator {
         field_generators: Arc::new(snippet_generators),
+        pre_tag: snippet_options.pre_tag,
+        post_tag: snippet_options.post_tag,
+        max_num_snippets: snippet_options
+            .max_num_snippets
+            .map(|max_num_snippets| max_num_snippets as usize),
     })
 }
 
@@ -327,6 +490,7 @@ async fn create_snippet_generator(
     searcher: &Searcher,
     query: &dyn Query,
     field: Field,
+    max_num_chars: usize,
 ) -> anyhow::Result<SnippetGenerator> {
     let mut terms: Vec<&Term> = Vec::new();
     // TODO ok with termset?
@@ -352,6 +516,6 @@ async fn create_snippet_generator(
         terms_text,
         tokenizer,
         field,
-        SNIPPET_MAX_NUM_CHARS,
+        max_num_chars,
     ))
 }
@@ -0,0 +1,618 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+
+use fnv::FnvHashMap;
+use quickwit_query::geo::{
+    geohash_cell_to_string, geotile_cell_to_string, GeoPoint, MAX_GEOHASH_PRECISION,
+    MAX_GEOTILE_PRECISION,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+use tantivy::aggregation::agg_req::{get_fast_field_names, Aggregations};
+use tantivy::aggregation::agg_result::AggregationResults;
+use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+use tantivy::aggregation::{AggregationLimitsGuard, AggregationSegmentCollector};
+use tantivy::collector::SegmentCollector;
+use tantivy::fastfield::Column;
+use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// Number of documents per grid cell, indexed by cell.
+pub type GeoGridCellCounts = HashMap<u64, u64>;
+
+const DEFAULT_GEO_GRID_SIZE: usize = 10_000;
+
+const DEFAULT_GEOHASH_PRECISION: u8 = 5;
+
+const DEFAULT_GEOTILE_PRECISION: u8 = 7;
+
+/// Maximum number of cells a geo grid aggregation can count documents in. Like the bucket limit
+/// of the regular aggregations, it bounds the memory used by high precision grids.
+const MAX_GEO_GRID_CELLS: usize = 65_000;
+
+/// Type of grid used to bucket geo points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoGridType {
+    /// Cells are geohashes. The precision is the length of the geohash.
+    Geohash,
+    /// Cells are web map tiles. The precision is the zoom level.
+    Geotile,
+}
+
+impl GeoGridType {
+    fn cell(&self, geo_point: &GeoPoint, precision: u8) -> u64 {
+        match self {
+            GeoGridType::Geohash => geo_point.geohash_cell(precision),
+            GeoGridType::Geotile => geo_point.geotile_cell(precision),
+        }
+    }
+
+    fn cell_key(&self, cell: u64, precision: u8) -> String {
+        match self {
+            GeoGridType::Geohash => geohash_cell_to_string(cell, precision),
+            GeoGridType::Geotile => geotile_cell_to_string(cell, precision),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum GeoGridAggregationRequest {
+    GeohashGrid(GeoGridParams),
+    GeotileGrid(GeoGridParams),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GeoGridParams {
+    field: String,
+    #[serde(default)]
+    precision: Option<u8>,
+    #[serde(default)]
+    size: Option<usize>,
+}
+
+/// Buckets the documents matching a query into a `geohash_grid` or a `geotile_grid`, counting
+/// the number of documents in each cell of the grid.
+///
+/// The geo grid aggregation can be requested along with regular aggregations, which are run
+/// over the same documents. Sub-aggregations are not supported, and a request can contain at
+/// most one geo grid aggregation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoGridAggregation {
+    /// The name of the aggregation in the response.
+    pub name: String,
+    /// The grid used to bucket geo points.
+    pub grid_type: GeoGridType,
+    /// The name of the `geo_point` fast field.
+    pub field: String,
+    /// The geohash length or the tile zoom level.
+    pub precision: u8,
+    /// The maximum number of buckets returned.
+    pub size: usize,
+    /// The regular aggregations requested along with the geo grid aggregation.
+    pub sibling_aggs: Aggregations,
+}
+
+impl<'de> Deserialize<'de> for GeoGridAggregation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let aggs = serde_json::Map::<String, JsonValue>::deserialize(deserializer)?;
+        GeoGridAggregation::from_request(aggs).map_err(serde::de::Error::custom)
+    }
+}
+
+fn is_geo_grid_aggregation(agg: &JsonValue) -> bool {
+    agg.get("geohash_grid").is_some() || agg.get("geotile_grid").is_some()
+}
+
+impl GeoGridAggregation {
+    fn from_request(
+        mut aggs: serde_json::Map<String, JsonValue>,
+    ) -> Result<GeoGridAggregation, String> {
+        let geo_grid_names: Vec<String> = aggs
+            .iter()
+            .filter(|(_, agg)| is_geo_grid_aggregation(agg))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let name = match &geo_grid_names[..] {
+            [name] => name.clone(),
+            [] => return Err("request does not contain a geo grid aggregation".to_string()),
+            _ => return Err("request can contain at most one geo grid aggregation".to_string()),
+        };
+        let geo_grid_json = aggs.remove(&name).expect("aggregation should exist");
+
+        if geo_grid_json.get("aggs").is_some() || geo_grid_json.get("aggregations").is_some() {
+            return Err(format!(
+                "geo grid aggregation `{name}` does not support sub-aggregations"
+            ));
+        }
+        let request: GeoGridAggregationRequest =
+            serde_json::from_value(geo_grid_json).map_err(|error| error.to_string())?;
+        let (grid_type, params, precision_range, default_precision) = match request {
+            GeoGridAggregationRequest::GeohashGrid(params) => (
+                GeoGridType::Geohash,
+                params,
+                1..=MAX_GEOHASH_PRECISION,
+                DEFAULT_GEOHASH_PRECISION,
+            ),
+            GeoGridAggregationRequest::GeotileGrid(params) => (
+                GeoGridType::Geotile,
+                params,
+                0..=MAX_GEOTILE_PRECISION,
+                DEFAULT_GEOTILE_PRECISION,
+            ),
+        };
+        let precision = params.precision.unwrap_or(default_precision);
+        if !precision_range.contains(&precision) {
+            return Err(format!(
+                "precision must be between {} and {}, got {precision}",
+                precision_range.start(),
+                precision_range.end()
+            ));
+        }
+        let size = params.size.unwrap_or(DEFAULT_GEO_GRID_SIZE);
+        if size == 0 || size > MAX_GEO_GRID_CELLS {
+            return Err(format!(
+                "size must be between 1 and {MAX_GEO_GRID_CELLS}, got {size}"
+            ));
+        }
+        let sibling_aggs: Aggregations =
+            serde_json::from_value(JsonValue::Object(aggs)).map_err(|error| error.to_string())?;
+        Ok(GeoGridAggregation {
+            name,
+            grid_type,
+            field: params.field,
+            precision,
+            size,
+            sibling_aggs,
+        })
+    }
+}
+
+/// Intermediate result of a [`GeoGridAggregation`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoGridFruit {
+    cell_counts: GeoGridCellCounts,
+    sibling_aggs: IntermediateAggregationResults,
+}
+
+#[derive(Serialize)]
+struct GeoGridBucket {
+    key: String,
+    doc_count: u64,
+}
+
+#[derive(Serialize)]
+struct GeoGridAggregationResult {
+    buckets: Vec<GeoGridBucket>,
+}
+
+fn too_many_cells_error() -> TantivyError {
+    TantivyError::InvalidArgument(format!(
+        "geo grid aggregation has more than {MAX_GEO_GRID_CELLS} cells, use a lower precision"
+    ))
+}
+
+impl GeoGridAggregation {
+    /// The names of the fast fields accessed by this collector.
+    pub fn fast_field_names(&self) -> HashSet<String> {
+        let mut fast_field_names = get_fast_field_names(&self.sibling_aggs);
+        fast_field_names.insert(self.field.clone());
+        fast_field_names
+    }
+
+    pub(crate) fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+        aggregation_limits: &AggregationLimitsGuard,
+    ) -> tantivy::Result<GeoGridSegmentCollector> {
+        let geo_point_column_opt = segment_reader
+            .fast_fields()
+            .column_opt::<u64>(&self.field)?;
+        let sibling_aggs_collector_opt = if self.sibling_aggs.is_empty() {
+            None
+        } else {
+            Some(AggregationSegmentCollector::from_agg_req_and_reader(
+                &self.sibling_aggs,
+                segment_reader,
+                segment_ord,
+                aggregation_limits,
+            )?)
+        };
+        Ok(GeoGridSegmentCollector {
+            geo_point_column_opt,
+            grid_type: self.grid_type,
+            precision: self.precision,
+            counts: FnvHashMap::default(),
+            has_too_many_cells: false,
+            sibling_aggs_collector_opt,
+        })
+    }
+
+    /// Merges `other` into `fruit`.
+    pub(crate) fn merge_fruit(
+        fruit: &mut GeoGridFruit,
+        other: GeoGridFruit,
+    ) -> tantivy::Result<()> {
+        for (cell, count) in other.cell_counts {
+            *fruit.cell_counts.entry(cell).or_default() += count;
+        }
+        if fruit.cell_counts.len() > MAX_GEO_GRID_CELLS {
+            return Err(too_many_cells_error());
+        }
+        fruit.sibling_aggs.merge_fruits(other.sibling_aggs)?;
+        Ok(())
+    }
+
+    pub(crate) fn merge_fruits(&self, fruits: Vec<GeoGridFruit>) -> tantivy::Result<GeoGridFruit> {
+        let mut merged_fruit = GeoGridFruit::default();
+        for fruit in fruits {
+            Self::merge_fruit(&mut merged_fruit, fruit)?;
+        }
+        Ok(merged_fruit)
+    }
+
+    /// Builds the final aggregation result: the `size` most populated cells, sorted by
+    /// decreasing document count, along with the results of the sibling aggregations.
+    pub(crate) fn finalize(
+        &self,
+        fruit: GeoGridFruit,
+        aggregation_limits: &AggregationLimitsGuard,
+    ) -> tantivy::Result<JsonValue> {
+        let mut buckets: Vec<GeoGridBucket> = fruit
+            .cell_counts
+            .into_iter()
+            .map(|(cell, doc_count)| GeoGridBucket {
+                key: self.grid_type.cell_key(cell, self.precision),
+                doc_count,
+            })
+            .collect();
+        buckets.sort_unstable_by(|left, right| {
+            right
+                .doc_count
+                .cmp(&left.doc_count)
+                .then_with(|| left.key.cmp(&right.key))
+        });
+        buckets.truncate(self.size);
+
+        let mut result = serde_json::Map::new();
+
+        if !self.sibling_aggs.is_empty() {
+            let aggregation_results: AggregationResults = fruit
+                .sibling_aggs
+                .into_final_result(self.sibling_aggs.clone(), aggregation_limits.clone())?;
+            let JsonValue::Object(sibling_results) = serde_json::to_value(aggregation_results)
+                .map_err(|error| TantivyError::InternalError(error.to_string()))?
+            else {
+                return Err(TantivyError::InternalError(
+                    "aggregation results should be serialized as a JSON object".to_string(),
+                ));
+            };
+            result = sibling_results;
+        }
+        let geo_grid_result = serde_json::to_value(GeoGridAggregationResult { buckets })
+            .map_err(|error| TantivyError::InternalError(error.to_string()))?;
+        result.insert(self.name.clone(), geo_grid_result);
+        Ok(JsonValue::Object(result))
+    }
+}
+
+/// Segment collector of the [`GeoGridAggregation`].
+pub struct GeoGridSegmentCollector {
+    geo_point_column_opt: Option<Column<u64>>,
+    grid_type: GeoGridType,
+    precision: u8,
+    counts: FnvHashMap<u64, u64>,
+    /// Set when a document falls into a new cell while the maximum number of cells is reached.
+    has_too_many_cells: bool,
+    sibling_aggs_collector_opt: Option<AggregationSegmentCollector>,
+}
+
+impl GeoGridSegmentCollector {
+    fn count_geo_points(&mut self, doc: DocId) {
+        let Some(geo_point_column) = &self.geo_point_column_opt else {
+            return;
+        };
+        for encoded_geo_point in geo_point_column.values_for_doc(doc) {
+            let geo_point = GeoPoint::decode(encoded_geo_point);
+            let cell = self.grid_type.cell(&geo_point, self.precision);
+
+            if let Some(count) = self.counts.get_mut(&cell) {
+                *count += 1;
+            } else if self.counts.len() < MAX_GEO_GRID_CELLS {
+                self.counts.insert(cell, 1);
+            } else {
+                self.has_too_many_cells = true;
+            }
+        }
+    }
+
+    pub(crate) fn collect(&mut self, doc: DocId, score: Score) {
+        self.count_geo_points(doc);
+
+        if let Some(sibling_aggs_collector) = &mut self.sibling_aggs_collector_opt {
+            sibling_aggs_collector.collect(doc, score);
+        }
+    }
+
+    pub(crate) fn collect_block(&mut self, docs: &[DocId]) {
+        for doc in docs {
+            self.count_geo_points(*doc);
+        }
+        if let Some(sibling_aggs_collector) = &mut self.sibling_aggs_collector_opt {
+            sibling_aggs_collector.collect_block(docs);
+        }
+    }
+
+    pub(crate) fn harvest(self) -> tantivy::Result<GeoGridFruit> {
+        if self.has_too_many_cells {
+            return Err(too_many_cells_error());
+        }
+        let sibling_aggs = if let Some(sibling_aggs_collector) = self.sibling_aggs_collector_opt {
+            sibling_aggs_collector.harvest()?
+        } else {
+            IntermediateAggregationResults::default()
+        };
+        Ok(GeoGridFruit {
+            cell_counts: self.counts.into_iter().collect(),
+            sibling_aggs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{Schema, FAST};
+    use tantivy::{doc, Index, Searcher};
+
+    use super::*;
+    use crate::QuickwitAggregations;
+
+    #[test]
+    fn test_geo_grid_aggregation_deserialize() {
+        let aggregation: QuickwitAggregations = serde_json::from_str(
+            r#"{"cities": {"geohash_grid": {"field": "location", "precision": 3}}}"#,
+        )
+        .unwrap();
+        let QuickwitAggregations::GeoGridAggregation(geo_grid_aggregation) = aggregation else {
+            panic!("expected a geo grid aggregation, got {aggregation:?}");
+        };
+        assert_eq!(
+            geo_grid_aggregation,
+            GeoGridAggregation {
+                name: "cities".to_string(),
+                grid_type: GeoGridType::Geohash,
+                field: "location".to_string(),
+                precision: 3,
+                size: DEFAULT_GEO_GRID_SIZE,
+                sibling_aggs: Aggregations::default(),
+            }
+        );
+
+        let aggregation: QuickwitAggregations = serde_json::from_str(
+            r#"{
+                "tiles": {"geotile_grid": {"field": "location"}},
+                "services": {"terms": {"field": "service"}}
+            }"#,
+        )
+        .unwrap();
+        let QuickwitAggregations::GeoGridAggregation(geo_grid_aggregation) = aggregation else {
+            panic!("expected a geo grid aggregation, got {aggregation:?}");
+        };
+        assert_eq!(geo_grid_aggregation.name, "tiles");
+        assert_eq!(geo_grid_aggregation.grid_type, GeoGridType::Geotile);
+        assert_eq!(geo_grid_aggregation.precision, DEFAULT_GEOTILE_PRECISION);
+        assert_eq!(geo_grid_aggregation.sibling_aggs.len(), 1);
+        assert!(geo_grid_aggregation.sibling_aggs.contains_key("services"));
+
+        let error = serde_json::from_str::<GeoGridAggregation>(
+            r#"{"cities": {"geohash_grid": {"field": "location", "precision": 13}}}"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("precision must be between 1 and 12, got 13"),
+            "{error}"
+        );
+
+        let error = serde_json::from_str::<GeoGridAggregation>(
+            r#"{"cities": {"geohash_grid": {"field": "location", "size": 100000}}}"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("size must be between 1 and 65000, got 100000"),
+            "{error}"
+        );
+
+        let error = serde_json::from_str::<GeoGridAggregation>(
+            r#"{
+                "cities": {
+                    "geohash_grid": {"field": "location"},
+                    "aggs": {"services": {"terms": {"field": "service"}}}
+                }
+            }"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("geo grid aggregation `cities` does not support sub-aggregations"),
+            "{error}"
+        );
+
+        let error = serde_json::from_str::<GeoGridAggregation>(
+            r#"{
+                "cities": {"geohash_grid": {"field": "location"}},
+                "tiles": {"geotile_grid": {"field": "location"}}
+            }"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("request can contain at most one geo grid aggregation"),
+            "{error}"
+        );
+
+        // Regular aggregations are left to tantivy.
+        let aggregation: QuickwitAggregations =
+            serde_json::from_str(r#"{"services": {"terms": {"field": "service"}}}"#).unwrap();
+        assert!(matches!(
+            aggregation,
+            QuickwitAggregations::TantivyAggregations(_)
+        ));
+    }
+
+    fn collect_geo_grid(
+        searcher: &Searcher,
+        geo_grid_aggregation: &GeoGridAggregation,
+        aggregation_limits: &AggregationLimitsGuard,
+    ) -> tantivy::Result<GeoGridFruit> {
+        let segment_reader = searcher.segment_reader(0);
+        let mut segment_collector =
+            geo_grid_aggregation.for_segment(0, segment_reader, aggregation_limits)?;
+        let docs: Vec<DocId> = (0..segment_reader.max_doc()).collect();
+        segment_collector.collect_block(&docs);
+        segment_collector.harvest()
+    }
+
+    #[test]
+    fn test_geo_grid_aggregation_collect() {
+        let mut schema_builder = Schema::builder();
+        let location = schema_builder.add_u64_field("location", FAST);
+        let population = schema_builder.add_u64_field("population", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for (lat, lon, num_inhabitants) in [
+            (48.8566, 2.3522, 10u64),  // Paris
+            (48.8606, 2.3376, 20u64),  // Paris, Louvre
+            (51.5074, -0.1278, 30u64), // London
+        ] {
+            let geo_point = GeoPoint::new(lat, lon).unwrap();
+            index_writer
+                .add_document(doc!(location => geo_point.encode(), population => num_inhabitants))
+                .unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let aggregation_limits = AggregationLimitsGuard::new(None, None);
+
+        let geo_grid_aggregation: GeoGridAggregation = serde_json::from_str(
+            r#"{
+                "cities": {"geohash_grid": {"field": "location", "precision": 3, "size": 10}},
+                "total_population": {"sum": {"field": "population"}}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            geo_grid_aggregation.fast_field_names(),
+            HashSet::from(["location".to_string(), "population".to_string()])
+        );
+        let fruit =
+            collect_geo_grid(&searcher, &geo_grid_aggregation, &aggregation_limits).unwrap();
+        let result = geo_grid_aggregation
+            .finalize(fruit.clone(), &aggregation_limits)
+            .unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "cities": {
+                    "buckets": [
+                        {"key": "u09", "doc_count": 2},
+                        {"key": "gcp", "doc_count": 1},
+                    ]
+                },
+                "total_population": {"value": 60.0},
+            })
+        );
+
+        let geo_grid_aggregation = GeoGridAggregation {
+            size: 1,
+            sibling_aggs: Aggregations::default(),
+            ..geo_grid_aggregation
+        };
+        let fruit =
+            collect_geo_grid(&searcher, &geo_grid_aggregation, &aggregation_limits).unwrap();
+        let merged_fruit = geo_grid_aggregation
+            .merge_fruits(vec![fruit.clone(), fruit])
+            .unwrap();
+        let result = geo_grid_aggregation
+            .finalize(merged_fruit, &aggregation_limits)
+            .unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "cities": {
+                    "buckets": [
+                        {"key": "u09", "doc_count": 4},
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_geo_grid_aggregation_too_many_cells() {
+        let mut schema_builder = Schema::builder();
+        let location = schema_builder.add_u64_field("location", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_with_num_threads(1, 50_000_000).unwrap();
+        // Points spread over a grid of 260 x 260 cells.
+        for lat_idx in 0..260 {
+            for lon_idx in 0..260 {
+                let lat = -80.0 + lat_idx as f64 * 0.6;
+                let lon = -170.0 + lon_idx as f64 * 1.3;
+                let geo_point = GeoPoint::new(lat, lon).unwrap();
+                index_writer
+                    .add_document(doc!(location => geo_point.encode()))
+                    .unwrap();
+            }
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let aggregation_limits = AggregationLimitsGuard::new(None, None);
+
+        let geo_grid_aggregation: GeoGridAggregation = serde_json::from_str(
+            r#"{"cities": {"geohash_grid": {"field": "location", "precision": 8}}}"#,
+        )
+        .unwrap();
+        let error =
+            collect_geo_grid(&searcher, &geo_grid_aggregation, &aggregation_limits).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("geo grid aggregation has more than 65000 cells"),
+            "{error}"
+        );
+
+        let geo_grid_aggregation: GeoGridAggregation = serde_json::from_str(
+            r#"{"cities": {"geohash_grid": {"field": "location", "precision": 2}}}"#,
+        )
+        .unwrap();
+        collect_geo_grid(&searcher, &geo_grid_aggregation, &aggregation_limits).unwrap();
+    }
+}
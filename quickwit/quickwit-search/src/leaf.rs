@@ -34,7 +34,9 @@ use quickwit_proto::search::{
     CountHits, LeafSearchRequest, LeafSearchResponse, PartialHit, ResourceStats, SearchRequest,
     SortOrder, SortValue, SplitIdAndFooterOffsets, SplitSearchError,
 };
-use quickwit_query::query_ast::{BoolQuery, QueryAst, QueryAstTransformer, RangeQuery, TermQuery};
+use quickwit_query::query_ast::{
+    bind_ids_queries_to_split, BoolQuery, QueryAst, QueryAstTransformer, RangeQuery, TermQuery,
+};
 use quickwit_query::tokenizers::TokenizerManager;
 use quickwit_storage::{
     wrap_storage_with_cache, BundleStorage, ByteRangeCache, MemorySizedCache, OwnedBytes,
@@ -412,6 +414,7 @@ async fn leaf_search_single_split(
 
     let query_ast: QueryAst = serde_json::from_str(search_request.query_ast.as_str())
         .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+    let query_ast = bind_ids_queries_to_split(query_ast, &split.split_id);
 
     // CanSplitDoBetter or rewrite_request may have changed the request to be a count only request
     // This may be the case for AllQuery with a sort by date and time filter, where the current
@@ -935,7 +938,10 @@ impl CanSplitDoBetter {
             }
         }
 
-        if request.sort_fields.is_empty() {
+        if request.collapse_field.is_some() {
+            // Any split may hold the best hit for a not yet collected collapse value.
+            CanSplitDoBetter::Uninformative
+        } else if request.sort_fields.is_empty() {
             CanSplitDoBetter::SplitIdHigher(None)
         } else if let Some((sort_by, timestamp_field)) =
             request.sort_fields.first().zip(timestamp_field_name)
@@ -251,6 +251,8 @@ mod tests {
                 segment_ord: 0,
                 sort_value: Some(SortValue::U64(0u64).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
                 split_id: "split_1".to_string(),
             }],
             resource_stats: None,
@@ -342,6 +344,8 @@ mod tests {
                 segment_ord: 0,
                 sort_value: Some(SortValue::U64(0).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
                 split_id: "split_1".to_string(),
             }],
             resource_stats: Some(ResourceStats::default()),
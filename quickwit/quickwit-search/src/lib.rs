@@ -22,6 +22,7 @@
 #![allow(clippy::bool_assert_comparison)]
 #![deny(clippy::disallowed_methods)]
 
+mod async_search;
 mod client;
 mod cluster_client;
 mod collector;
@@ -29,13 +30,16 @@ mod error;
 mod fetch_docs;
 mod filters;
 mod find_trace_ids_collector;
+mod geo_grid_collector;
 mod leaf;
 mod leaf_cache;
 mod list_fields;
 mod list_fields_cache;
 mod list_terms;
+mod nested_aggregation_collector;
 mod retry;
 mod root;
+mod runtime_terms_collector;
 mod scroll_context;
 mod search_job_placer;
 mod search_response_rest;
@@ -66,6 +70,8 @@ use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::{Arc, OnceLock};
 
 pub use find_trace_ids_collector::FindTraceIdsCollector;
+pub use geo_grid_collector::{GeoGridAggregation, GeoGridType};
+pub use nested_aggregation_collector::{NestedAggregation, ReverseNestedAggregation};
 use quickwit_config::SearcherConfig;
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_metastore::{
@@ -77,9 +83,14 @@ use quickwit_proto::search::{
 };
 use quickwit_proto::types::IndexUid;
 use quickwit_storage::StorageResolver;
+pub use runtime_terms_collector::RuntimeTermsAggregation;
 pub use service::SearcherContext;
 use tantivy::DocAddress;
 
+pub use crate::async_search::{
+    AsyncSearchResponse, DEFAULT_ASYNC_SEARCH_KEEP_ALIVE,
+    DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT, MAX_ASYNC_SEARCH_KEEP_ALIVE,
+};
 pub use crate::client::{
     create_search_client_from_channel, create_search_client_from_grpc_addr, SearchServiceClient,
 };
@@ -91,7 +102,9 @@ pub use crate::root::{
     IndexMetasForLeafSearch, SearchJob,
 };
 pub use crate::search_job_placer::{Job, SearchJobPlacer};
-pub use crate::search_response_rest::{SearchPlanResponseRest, SearchResponseRest};
+pub use crate::search_response_rest::{
+    AsyncSearchResponseRest, SearchPlanResponseRest, SearchResponseRest,
+};
 pub use crate::search_stream::root_search_stream;
 pub use crate::service::{MockSearchService, SearchService, SearchServiceImpl};
 
@@ -251,14 +264,47 @@ pub async fn resolve_index_patterns(
 fn convert_document_to_json_string(
     named_field_doc: NamedFieldDocument,
     doc_mapper: &DocMapper,
+    source_fields: &[String],
 ) -> anyhow::Result<String> {
     let NamedFieldDocument(named_field_doc_map) = named_field_doc;
-    let doc_json_map = doc_mapper.doc_to_json(named_field_doc_map)?;
+    let mut doc_json_map = doc_mapper.doc_to_json(named_field_doc_map)?;
+    if !source_fields.is_empty() {
+        let mut projected_doc_json_map = serde_json::Map::new();
+        for source_field in source_fields {
+            project_json_field(&doc_json_map, source_field, &mut projected_doc_json_map);
+        }
+        doc_json_map = projected_doc_json_map;
+    }
     let content_json =
         serde_json::to_string(&doc_json_map).expect("Json serialization should never fail.");
     Ok(content_json)
 }
 
+/// Copies the value of a field from `doc_json_map` to `projected_doc_json_map`. Nested fields are
+/// designated by their dot-separated path, unless a field contains a dot in its name.
+fn project_json_field(
+    doc_json_map: &serde_json::Map<String, serde_json::Value>,
+    field: &str,
+    projected_doc_json_map: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    if let Some(value) = doc_json_map.get(field) {
+        projected_doc_json_map.insert(field.to_string(), value.clone());
+        return;
+    }
+    let Some((head, tail)) = field.split_once('.') else {
+        return;
+    };
+    let Some(serde_json::Value::Object(sub_doc_json_map)) = doc_json_map.get(head) else {
+        return;
+    };
+    if let serde_json::Value::Object(projected_sub_doc_json_map) = projected_doc_json_map
+        .entry(head)
+        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+    {
+        project_json_field(sub_doc_json_map, tail, projected_sub_doc_json_map);
+    }
+}
+
 /// Starts a search node, aka a `searcher`.
 pub async fn start_searcher_service(
     metastore: MetastoreServiceClient,
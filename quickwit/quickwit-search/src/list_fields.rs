@@ -209,7 +209,7 @@ fn matches_any_pattern(field_name: &str, field_patterns: &[String]) -> bool {
 }
 
 /// Supports up to 1 wildcard.
-fn matches_pattern(field_pattern: &str, field_name: &str) -> bool {
+pub(crate) fn matches_pattern(field_pattern: &str, field_name: &str) -> bool {
     match field_pattern.find('*') {
         None => field_pattern == field_name,
         Some(index) => {
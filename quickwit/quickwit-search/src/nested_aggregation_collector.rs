@@ -0,0 +1,477 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use quickwit_common::shared_consts::NESTED_PATH_FIELD_NAME;
+use quickwit_query::query_ast::NestedDocsReader;
+use quickwit_query::OneFieldMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+use tantivy::aggregation::agg_req::{get_fast_field_names, Aggregations};
+use tantivy::aggregation::agg_result::AggregationResults;
+use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+use tantivy::aggregation::{AggregationLimitsGuard, AggregationSegmentCollector};
+use tantivy::collector::SegmentCollector;
+use tantivy::{DocId, SegmentOrdinal, SegmentReader, TantivyError};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NestedAggregationRequest {
+    nested: NestedParams,
+    #[serde(default, alias = "aggregations")]
+    aggs: serde_json::Map<String, JsonValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NestedParams {
+    path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReverseNestedAggregationRequest {
+    #[allow(dead_code)]
+    reverse_nested: ReverseNestedParams,
+    #[serde(default, alias = "aggregations")]
+    aggs: Option<Aggregations>,
+}
+
+/// Joining back a specific nested field with `path` is not supported: `reverse_nested` always
+/// joins back the root documents.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReverseNestedParams {}
+
+/// Runs sub-aggregations over the objects of a nested field of the documents matching a query.
+///
+/// The sub-aggregations are regular aggregations, plus at most one `reverse_nested`
+/// aggregation, which runs its own sub-aggregations over the documents having at least one
+/// object in the nested field. A nested aggregation cannot be mixed with other aggregations in
+/// the same request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NestedAggregation {
+    /// The name of the aggregation in the response.
+    pub name: String,
+    /// The path of the nested field.
+    pub path: String,
+    /// The sub-aggregations run over the objects of the nested field.
+    pub aggs: Aggregations,
+    /// The `reverse_nested` sub-aggregation, if any.
+    pub reverse_nested: Option<ReverseNestedAggregation>,
+}
+
+/// Runs sub-aggregations over the documents having at least one object in a nested field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReverseNestedAggregation {
+    /// The name of the aggregation in the response.
+    pub name: String,
+    /// The sub-aggregations run over the documents.
+    pub aggs: Aggregations,
+}
+
+impl<'de> Deserialize<'de> for NestedAggregation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let one_field_map = OneFieldMap::<NestedAggregationRequest>::deserialize(deserializer)?;
+        NestedAggregation::from_request(one_field_map).map_err(serde::de::Error::custom)
+    }
+}
+
+impl NestedAggregation {
+    fn from_request(
+        one_field_map: OneFieldMap<NestedAggregationRequest>,
+    ) -> Result<NestedAggregation, String> {
+        let mut sub_aggs = one_field_map.value.aggs;
+        let reverse_nested_names: Vec<String> = sub_aggs
+            .iter()
+            .filter(|(_, sub_agg)| sub_agg.get("reverse_nested").is_some())
+            .map(|(name, _)| name.clone())
+            .collect();
+        if reverse_nested_names.len() > 1 {
+            return Err(format!(
+                "nested aggregation `{}` can have at most one reverse_nested sub-aggregation",
+                one_field_map.field
+            ));
+        }
+        let reverse_nested = if let Some(name) = reverse_nested_names.into_iter().next() {
+            let reverse_nested_json = sub_aggs
+                .remove(&name)
+                .expect("sub-aggregation should exist");
+            let request: ReverseNestedAggregationRequest =
+                serde_json::from_value(reverse_nested_json).map_err(|error| error.to_string())?;
+            Some(ReverseNestedAggregation {
+                name,
+                aggs: request.aggs.unwrap_or_default(),
+            })
+        } else {
+            None
+        };
+        let aggs: Aggregations = serde_json::from_value(JsonValue::Object(sub_aggs))
+            .map_err(|error| error.to_string())?;
+        Ok(NestedAggregation {
+            name: one_field_map.field,
+            path: one_field_map.value.nested.path,
+            aggs,
+            reverse_nested,
+        })
+    }
+}
+
+/// Intermediate result of a [`NestedAggregation`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NestedAggregationFruit {
+    doc_count: u64,
+    aggs: IntermediateAggregationResults,
+    reverse_nested_doc_count: u64,
+    reverse_nested_aggs: IntermediateAggregationResults,
+}
+
+impl NestedAggregation {
+    /// The names of the fast fields accessed by this collector.
+    pub fn fast_field_names(&self) -> HashSet<String> {
+        let mut fast_field_names = get_fast_field_names(&self.aggs);
+        if let Some(reverse_nested) = &self.reverse_nested {
+            fast_field_names.extend(get_fast_field_names(&reverse_nested.aggs));
+        }
+        fast_field_names.insert(NESTED_PATH_FIELD_NAME.to_string());
+        fast_field_names
+    }
+
+    pub(crate) fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+        aggregation_limits: &AggregationLimitsGuard,
+    ) -> tantivy::Result<NestedAggregationSegmentCollector> {
+        let nested_docs_opt = if let Some(nested_docs) = NestedDocsReader::open(segment_reader)? {
+            nested_docs
+                .nested_path_ord(&self.path)?
+                .map(|nested_path_ord| (nested_docs, nested_path_ord))
+        } else {
+            None
+        };
+        let aggs_collector = AggregationSegmentCollector::from_agg_req_and_reader(
+            &self.aggs,
+            segment_reader,
+            segment_ord,
+            aggregation_limits,
+        )?;
+        let reverse_nested_aggs_collector_opt = self
+            .reverse_nested
+            .as_ref()
+            .map(|reverse_nested| {
+                AggregationSegmentCollector::from_agg_req_and_reader(
+                    &reverse_nested.aggs,
+                    segment_reader,
+                    segment_ord,
+                    aggregation_limits,
+                )
+            })
+            .transpose()?;
+        Ok(NestedAggregationSegmentCollector {
+            nested_docs_opt,
+            doc_count: 0,
+            aggs_collector,
+            reverse_nested_doc_count: 0,
+            reverse_nested_aggs_collector_opt,
+        })
+    }
+
+    /// Merges `other` into `fruit`.
+    pub(crate) fn merge_fruit(
+        fruit: &mut NestedAggregationFruit,
+        other: NestedAggregationFruit,
+    ) -> tantivy::Result<()> {
+        fruit.doc_count += other.doc_count;
+        fruit.aggs.merge_fruits(other.aggs)?;
+        fruit.reverse_nested_doc_count += other.reverse_nested_doc_count;
+        fruit
+            .reverse_nested_aggs
+            .merge_fruits(other.reverse_nested_aggs)?;
+        Ok(())
+    }
+
+    pub(crate) fn merge_fruits(
+        &self,
+        fruits: Vec<NestedAggregationFruit>,
+    ) -> tantivy::Result<NestedAggregationFruit> {
+        let mut merged_fruit = NestedAggregationFruit::default();
+        for fruit in fruits {
+            Self::merge_fruit(&mut merged_fruit, fruit)?;
+        }
+        Ok(merged_fruit)
+    }
+
+    /// Builds the final aggregation result, in the Elasticsearch format.
+    pub(crate) fn finalize(
+        &self,
+        fruit: NestedAggregationFruit,
+        aggregation_limits: &AggregationLimitsGuard,
+    ) -> tantivy::Result<JsonValue> {
+        let mut result = final_aggregation_result(
+            fruit.doc_count,
+            fruit.aggs,
+            self.aggs.clone(),
+            aggregation_limits,
+        )?;
+        if let Some(reverse_nested) = &self.reverse_nested {
+            let reverse_nested_result = final_aggregation_result(
+                fruit.reverse_nested_doc_count,
+                fruit.reverse_nested_aggs,
+                reverse_nested.aggs.clone(),
+                aggregation_limits,
+            )?;
+            result.insert(
+                reverse_nested.name.clone(),
+                JsonValue::Object(reverse_nested_result),
+            );
+        }
+        Ok(serde_json::json!({ &self.name: result }))
+    }
+}
+
+fn final_aggregation_result(
+    doc_count: u64,
+    intermediate_results: IntermediateAggregationResults,
+    aggs: Aggregations,
+    aggregation_limits: &AggregationLimitsGuard,
+) -> tantivy::Result<serde_json::Map<String, JsonValue>> {
+    let aggregation_results: AggregationResults =
+        intermediate_results.into_final_result(aggs, aggregation_limits.clone())?;
+    let JsonValue::Object(mut result) = serde_json::to_value(aggregation_results)
+        .map_err(|error| TantivyError::InternalError(error.to_string()))?
+    else {
+        return Err(TantivyError::InternalError(
+            "aggregation results should be serialized as a JSON object".to_string(),
+        ));
+    };
+    result.insert("doc_count".to_string(), JsonValue::from(doc_count));
+    Ok(result)
+}
+
+/// Segment collector of the [`NestedAggregation`].
+///
+/// The query only matches root documents: the collector feeds their child documents to the
+/// sub-aggregations.
+pub struct NestedAggregationSegmentCollector {
+    /// The layout of the nested documents and the term ordinal of the nested path, if the
+    /// segment contains objects of the nested field.
+    nested_docs_opt: Option<(NestedDocsReader, u64)>,
+    doc_count: u64,
+    aggs_collector: AggregationSegmentCollector,
+    reverse_nested_doc_count: u64,
+    reverse_nested_aggs_collector_opt: Option<AggregationSegmentCollector>,
+}
+
+impl NestedAggregationSegmentCollector {
+    pub(crate) fn collect(&mut self, root_doc: DocId) {
+        let Some((nested_docs, nested_path_ord)) = &self.nested_docs_opt else {
+            return;
+        };
+        let mut has_nested_docs = false;
+        for child_doc in nested_docs.children(root_doc) {
+            if nested_docs.doc_nested_path_ord(child_doc) == Some(*nested_path_ord) {
+                self.doc_count += 1;
+                self.aggs_collector.collect(child_doc, 0.0);
+                has_nested_docs = true;
+            }
+        }
+        if has_nested_docs {
+            self.reverse_nested_doc_count += 1;
+            if let Some(reverse_nested_aggs_collector) = &mut self.reverse_nested_aggs_collector_opt
+            {
+                reverse_nested_aggs_collector.collect(root_doc, 0.0);
+            }
+        }
+    }
+
+    pub(crate) fn collect_block(&mut self, root_docs: &[DocId]) {
+        for root_doc in root_docs {
+            self.collect(*root_doc);
+        }
+    }
+
+    pub(crate) fn harvest(self) -> tantivy::Result<NestedAggregationFruit> {
+        let reverse_nested_aggs =
+            if let Some(reverse_nested_aggs_collector) = self.reverse_nested_aggs_collector_opt {
+                reverse_nested_aggs_collector.harvest()?
+            } else {
+                IntermediateAggregationResults::default()
+            };
+        Ok(NestedAggregationFruit {
+            doc_count: self.doc_count,
+            aggs: self.aggs_collector.harvest()?,
+            reverse_nested_doc_count: self.reverse_nested_doc_count,
+            reverse_nested_aggs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::query::{EnableScoring, Query, TermQuery};
+    use tantivy::schema::{IndexRecordOption, Schema, FAST, STRING};
+    use tantivy::{doc, DocSet, Index, IndexWriter, TantivyDocument, Term, TERMINATED};
+
+    use super::*;
+    use crate::QuickwitAggregations;
+
+    #[test]
+    fn test_nested_aggregation_deserialize() {
+        let aggregation: QuickwitAggregations = serde_json::from_str(
+            r#"{
+                "items": {
+                    "nested": {"path": "items"},
+                    "aggs": {
+                        "skus": {"terms": {"field": "items.sku"}},
+                        "orders": {
+                            "reverse_nested": {},
+                            "aggs": {"customers": {"terms": {"field": "customer"}}}
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let QuickwitAggregations::NestedAggregation(nested_aggregation) = aggregation else {
+            panic!("expected a nested aggregation, got {aggregation:?}");
+        };
+        assert_eq!(nested_aggregation.name, "items");
+        assert_eq!(nested_aggregation.path, "items");
+        assert!(nested_aggregation.aggs.contains_key("skus"));
+        let reverse_nested = nested_aggregation.reverse_nested.as_ref().unwrap();
+        assert_eq!(reverse_nested.name, "orders");
+        assert!(reverse_nested.aggs.contains_key("customers"));
+        assert_eq!(
+            nested_aggregation.fast_field_names(),
+            HashSet::from_iter([
+                "items.sku".to_string(),
+                "customer".to_string(),
+                NESTED_PATH_FIELD_NAME.to_string()
+            ])
+        );
+
+        let error = serde_json::from_str::<NestedAggregation>(
+            r#"{
+                "items": {
+                    "nested": {"path": "items"},
+                    "aggs": {"a": {"reverse_nested": {}}, "b": {"reverse_nested": {}}}
+                }
+            }"#,
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("can have at most one reverse_nested sub-aggregation"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_nested_aggregation_collect() {
+        let mut schema_builder = Schema::builder();
+        let nested_path_field =
+            schema_builder.add_text_field(NESTED_PATH_FIELD_NAME, STRING | FAST);
+        let customer_field = schema_builder.add_text_field("customer", STRING | FAST);
+        let sku_field = schema_builder.add_text_field("items.sku", STRING | FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer: IndexWriter<TantivyDocument> =
+            index.writer_with_num_threads(1, 15_000_000).unwrap();
+        let orders: [(&str, &[&str]); 3] =
+            [("acme", &["A", "B"]), ("initech", &["A"]), ("acme", &[])];
+        for (customer, skus) in orders {
+            for sku in skus {
+                index_writer
+                    .add_document(doc!(nested_path_field => "items", sku_field => *sku))
+                    .unwrap();
+            }
+            index_writer
+                .add_document(doc!(customer_field => customer))
+                .unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let segment_reader = searcher.segment_reader(0);
+
+        let nested_aggregation: NestedAggregation = serde_json::from_str(
+            r#"{
+                "items": {
+                    "nested": {"path": "items"},
+                    "aggs": {
+                        "skus": {"terms": {"field": "items.sku"}},
+                        "orders": {
+                            "reverse_nested": {},
+                            "aggs": {"customers": {"terms": {"field": "customer"}}}
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let aggregation_limits = AggregationLimitsGuard::new(None, None);
+        let mut segment_collector = nested_aggregation
+            .for_segment(0, segment_reader, &aggregation_limits)
+            .unwrap();
+        // Only the root documents reach the collector.
+        let weight = TermQuery::new(
+            Term::from_field_text(nested_path_field, "items"),
+            IndexRecordOption::Basic,
+        )
+        .weight(EnableScoring::disabled_from_searcher(&searcher))
+        .unwrap();
+        let mut child_docs = weight.scorer(segment_reader, 1.0).unwrap();
+        let mut root_docs = Vec::new();
+        for doc in 0..segment_reader.max_doc() {
+            if child_docs.doc() == doc {
+                child_docs.advance();
+            } else {
+                root_docs.push(doc);
+            }
+        }
+        assert_eq!(child_docs.doc(), TERMINATED);
+        segment_collector.collect_block(&root_docs);
+        let fruit = segment_collector.harvest().unwrap();
+        let merged_fruit = nested_aggregation
+            .merge_fruits(vec![fruit.clone(), fruit])
+            .unwrap();
+        let result = nested_aggregation
+            .finalize(merged_fruit, &aggregation_limits)
+            .unwrap();
+        assert_eq!(result["items"]["doc_count"], 6);
+        assert_eq!(
+            result["items"]["skus"]["buckets"],
+            serde_json::json!([
+                {"key": "A", "doc_count": 4},
+                {"key": "B", "doc_count": 2},
+            ])
+        );
+        assert_eq!(result["items"]["orders"]["doc_count"], 4);
+        assert_eq!(
+            result["items"]["orders"]["customers"]["buckets"],
+            serde_json::json!([
+                {"key": "acme", "doc_count": 2},
+                {"key": "initech", "doc_count": 2},
+            ])
+        );
+    }
+}
@@ -18,19 +18,23 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::OnceLock;
 use std::time::Duration;
 
 use anyhow::Context;
 use futures::future::try_join_all;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
 use itertools::Itertools;
 use quickwit_common::pretty::PrettySample;
+use quickwit_common::retry::Retryable;
 use quickwit_common::shared_consts;
 use quickwit_common::uri::Uri;
 use quickwit_config::build_doc_mapper;
 use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
-use quickwit_doc_mapper::DYNAMIC_FIELD_NAME;
+use quickwit_doc_mapper::{DocMapper, DYNAMIC_FIELD_NAME};
 use quickwit_metastore::{IndexMetadata, ListIndexesMetadataResponseExt, SplitMetadata};
 use quickwit_proto::metastore::{
     ListIndexesMetadataRequest, MetastoreService, MetastoreServiceClient,
@@ -38,24 +42,31 @@ use quickwit_proto::metastore::{
 use quickwit_proto::search::{
     FetchDocsRequest, FetchDocsResponse, Hit, LeafHit, LeafRequestRef, LeafSearchRequest,
     LeafSearchResponse, PartialHit, SearchPlanResponse, SearchRequest, SearchResponse,
-    SnippetRequest, SortDatetimeFormat, SortField, SortValue, SplitIdAndFooterOffsets,
+    SnippetRequest, SortDatetimeFormat, SortField, SortOrder, SortValue, SplitIdAndFooterOffsets,
+    SplitSearchError,
 };
 use quickwit_proto::types::{IndexUid, SplitId};
 use quickwit_query::query_ast::{
-    BoolQuery, QueryAst, QueryAstVisitor, RangeQuery, TermQuery, TermSetQuery,
+    contains_knn_query, resolve_runtime_fields, top_level_knn_k, BoolQuery, QueryAst,
+    QueryAstVisitor, RangeQuery, TermQuery, TermSetQuery,
 };
+use quickwit_query::runtime_field::{parse_runtime_mappings, RuntimeMappings};
 use serde::{Deserialize, Serialize};
 use tantivy::aggregation::agg_result::AggregationResults;
 use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::collector::Collector;
 use tantivy::schema::{Field, FieldEntry, FieldType, Schema};
 use tantivy::TantivyError;
+use tokio::sync::watch;
 use tracing::{debug, info_span, instrument};
 
 use crate::cluster_client::ClusterClient;
 use crate::collector::{make_merge_collector, QuickwitAggregations};
 use crate::find_trace_ids_collector::Span;
+use crate::geo_grid_collector::GeoGridFruit;
 use crate::metrics::SEARCH_METRICS;
+use crate::nested_aggregation_collector::NestedAggregationFruit;
+use crate::runtime_terms_collector::{resolve_runtime_field_aggregation, RuntimeTermCounts};
 use crate::scroll_context::{ScrollContext, ScrollKeyAndStartOffset};
 use crate::search_job_placer::{group_by, group_jobs_by_index_id, Job};
 use crate::search_response_rest::StorageRequestCount;
@@ -194,6 +205,7 @@ fn validate_request_and_build_metadata(
     )?;
     let query_ast: QueryAst = serde_json::from_str(&search_request.query_ast)
         .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+    let runtime_mappings = parse_request_runtime_mappings(search_request)?;
     let mut indexes_meta_for_leaf_search: HashMap<IndexUid, IndexMetasForLeafSearch> =
         HashMap::new();
     let mut query_ast_resolved_opt: Option<QueryAst> = None;
@@ -211,6 +223,7 @@ fn validate_request_and_build_metadata(
         let query_ast_resolved_for_index = query_ast
             .clone()
             .parse_user_query(doc_mapper.default_search_fields())
+            .and_then(|query_ast| resolve_runtime_fields(query_ast, &runtime_mappings))
             // We convert the error to return a 400 to the user (and not a 500).
             .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
 
@@ -245,7 +258,8 @@ fn validate_request_and_build_metadata(
 
         // Validate request against the current index schema.
         let schema = doc_mapper.schema();
-        validate_request(&schema, &doc_mapper.timestamp_field_name(), search_request)?;
+        validate_request(&doc_mapper, search_request)?;
+        validate_runtime_fields(&schema, &runtime_mappings)?;
 
         validate_sort_field_types(
             &schema,
@@ -282,6 +296,53 @@ fn validate_request_and_build_metadata(
     })
 }
 
+/// Parses the runtime mappings of a search request. Returns empty mappings if there are none.
+fn parse_request_runtime_mappings(
+    search_request: &SearchRequest,
+) -> crate::Result<RuntimeMappings> {
+    let Some(runtime_mappings_json) = &search_request.runtime_mappings else {
+        return Ok(RuntimeMappings::default());
+    };
+    parse_runtime_mappings(runtime_mappings_json)
+        .map_err(|err| SearchError::InvalidQuery(err.to_string()))
+}
+
+/// Checks that the runtime fields only read fast fields of the schema.
+///
+/// The values of runtime fields are computed from the fast fields of the documents, whether they
+/// are used in the query, in aggregations or returned with the hits.
+fn validate_runtime_fields(
+    schema: &Schema,
+    runtime_mappings: &RuntimeMappings,
+) -> crate::Result<()> {
+    for (field_name, runtime_field) in runtime_mappings {
+        let compiled_runtime_field = runtime_field
+            .compile()
+            .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+        compiled_runtime_field
+            .check_input_fields(field_name, schema)
+            .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Rewrites an aggregation on a runtime field so that leaves receive the definition of the field.
+fn resolve_runtime_fields_in_aggregation(search_request: &mut SearchRequest) -> crate::Result<()> {
+    let Some(aggregation_request) = &search_request.aggregation_request else {
+        return Ok(());
+    };
+    let runtime_mappings = parse_request_runtime_mappings(search_request)?;
+    if runtime_mappings.is_empty() {
+        return Ok(());
+    }
+    if let Some(aggregation_request_resolved) =
+        resolve_runtime_field_aggregation(aggregation_request, &runtime_mappings)?
+    {
+        search_request.aggregation_request = Some(aggregation_request_resolved);
+    }
+    Ok(())
+}
+
 /// Validate sort field types.
 fn validate_sort_field_types(
     schema: &Schema,
@@ -320,6 +381,10 @@ fn validate_requested_snippet_fields(
     snippet_fields: &[String],
 ) -> anyhow::Result<()> {
     for field_name in snippet_fields {
+        // Field patterns are resolved against the schema of each split when fetching docs.
+        if field_name.contains('*') {
+            continue;
+        }
         let field_entry = schema
             .get_field(field_name)
             .map(|field| schema.get_field_entry(field))?;
@@ -365,17 +430,21 @@ fn simplify_search_request_for_scroll_api(req: &SearchRequest) -> crate::Result<
         aggregation_request: None,
         // We remove the snippet fields. This feature is not supported for scroll requests.
         snippet_fields: Vec::new(),
+        snippet_options: None,
         // We remove the scroll ttl parameter. It is irrelevant to process later request
         scroll_ttl_secs: None,
         search_after: None,
         // request is simplified after initial query, and we cache the hit count, so we don't need
         // to recompute it afterward.
         count_hits: quickwit_proto::search::CountHits::Underestimate as i32,
+        collapse_field: req.collapse_field.clone(),
+        runtime_mappings: req.runtime_mappings.clone(),
+        allow_partial_search_results: req.allow_partial_search_results,
+        source_fields: req.source_fields.clone(),
     })
 }
 
 /// Validates sort fields and search after values.
-/// - validate sort fields length.
 /// - search after values must be set for all sort fields.
 fn validate_sort_by_fields_and_search_after(
     sort_fields: &[SortField],
@@ -384,12 +453,6 @@ fn validate_sort_by_fields_and_search_after(
     if sort_fields.is_empty() {
         return Ok(());
     }
-    if sort_fields.len() > 2 {
-        return Err(SearchError::InvalidArgument(format!(
-            "sort by field must be up to 2 fields, got {}",
-            sort_fields.len()
-        )));
-    }
     let Some(search_after_partial_hit) = search_after.as_ref() else {
         return Ok(());
     };
@@ -407,19 +470,10 @@ fn validate_sort_by_fields_and_search_after(
         ));
     }
 
-    let mut search_after_sort_value_count = 0;
     // TODO: we could validate if the search after sort value types of consistent with the sort
     // field types.
-    if let Some(sort_by_value) = search_after_partial_hit.sort_value.as_ref() {
-        sort_by_value.sort_value.context("sort value must be set")?;
-        search_after_sort_value_count += 1;
-    }
-    if let Some(sort_by_value_2) = search_after_partial_hit.sort_value2.as_ref() {
-        sort_by_value_2
-            .sort_value
-            .context("sort value must be set")?;
-        search_after_sort_value_count += 1;
-    }
+    // A sort value may be unset: it stands for a hit without a value for the sort field.
+    let search_after_sort_value_count = search_after_partial_hit.sort_by_values().flatten().count();
     if search_after_sort_value_count != sort_fields_without_doc_count {
         return Err(SearchError::InvalidArgument(format!(
             "`search_after` must have the same number of sort values as sort by fields {:?}",
@@ -455,11 +509,6 @@ fn validate_sort_by_field_type(
     has_timestamp_format: bool,
 ) -> crate::Result<()> {
     let field_name = sort_by_field_entry.name();
-    if matches!(sort_by_field_entry.field_type(), FieldType::Str(_)) {
-        return Err(SearchError::InvalidArgument(format!(
-            "sort by field on type text is currently not supported `{field_name}`"
-        )));
-    }
     if !sort_by_field_entry.is_fast() {
         return Err(SearchError::InvalidArgument(format!(
             "sort by field must be a fast field, please add the fast property to your field \
@@ -496,12 +545,26 @@ fn check_is_fast_field(
     Ok(())
 }
 
-fn validate_request(
-    schema: &Schema,
-    timestamp_field_name: &Option<&str>,
-    search_request: &SearchRequest,
-) -> crate::Result<()> {
-    if timestamp_field_name.is_none()
+/// Validates that the collapse field is a text fast field.
+fn validate_collapse_field(schema: &Schema, collapse_field: &str) -> crate::Result<()> {
+    let dynamic_field = schema.get_field(DYNAMIC_FIELD_NAME).ok();
+    check_is_fast_field(schema, collapse_field, dynamic_field)?;
+    let (field, _path) = schema
+        .find_field_with_default(collapse_field, dynamic_field)
+        .expect("the collapse field should exist");
+    match schema.get_field_entry(field).field_type() {
+        FieldType::Str(_) | FieldType::JsonObject(_) => Ok(()),
+        other => Err(SearchError::InvalidArgument(format!(
+            "the collapse field `{collapse_field}` must be of type `Str`, got `{}`",
+            other.value_type().name()
+        ))),
+    }
+}
+
+fn validate_request(doc_mapper: &DocMapper, search_request: &SearchRequest) -> crate::Result<()> {
+    let schema = doc_mapper.schema();
+
+    if doc_mapper.timestamp_field_name().is_none()
         && (search_request.start_timestamp.is_some() || search_request.end_timestamp.is_some())
     {
         return Err(SearchError::InvalidQuery(format!(
@@ -511,7 +574,7 @@ fn validate_request(
         )));
     }
 
-    validate_requested_snippet_fields(schema, &search_request.snippet_fields)?;
+    validate_requested_snippet_fields(&schema, &search_request.snippet_fields)?;
 
     if let Some(agg) = search_request.aggregation_request.as_ref() {
         let aggs: QuickwitAggregations = serde_json::from_str(agg).map_err(|_err| {
@@ -520,11 +583,20 @@ fn validate_request(
             SearchError::InvalidAggregationRequest(err.to_string())
         })?;
 
+        if let QuickwitAggregations::GeoGridAggregation(geo_grid_aggregation) = &aggs {
+            if !doc_mapper.is_geo_point_field(&geo_grid_aggregation.field) {
+                return Err(SearchError::InvalidAggregationRequest(format!(
+                    "field `{}` is not a geo_point field",
+                    geo_grid_aggregation.field
+                )));
+            }
+        }
+
         // ensure that the required fast fields are indeed configured as fast fields.
         let fast_field_names = aggs.fast_field_names();
         let dynamic_field = schema.get_field(DYNAMIC_FIELD_NAME).ok();
         for fast_field_name in &fast_field_names {
-            check_is_fast_field(schema, fast_field_name, dynamic_field)?;
+            check_is_fast_field(&schema, fast_field_name, dynamic_field)?;
         }
     };
 
@@ -542,6 +614,38 @@ fn validate_request(
         )));
     }
 
+    if let Some(collapse_field) = &search_request.collapse_field {
+        if search_request.scroll_ttl_secs.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "collapse cannot be used in a scroll context".to_string(),
+            ));
+        }
+        if search_request.search_after.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "collapse cannot be used with search_after".to_string(),
+            ));
+        }
+        validate_collapse_field(&schema, collapse_field)?;
+    }
+
+    Ok(())
+}
+
+fn validate_knn_sort(search_request: &SearchRequest) -> crate::Result<()> {
+    if search_request.search_after.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "`search_after` is not supported with k-NN queries".to_string(),
+        ));
+    }
+    if let Some(first_sort_field) = search_request.sort_fields.first() {
+        if first_sort_field.field_name != "_score"
+            || first_sort_field.sort_order() != SortOrder::Desc
+        {
+            return Err(SearchError::InvalidArgument(
+                "k-NN queries only support sorting by descending `_score`".to_string(),
+            ));
+        }
+    }
     Ok(())
 }
 
@@ -567,6 +671,7 @@ async fn search_partial_hits_phase_with_scroll(
     mut search_request: SearchRequest,
     split_metadatas: &[SplitMetadata],
     cluster_client: &ClusterClient,
+    partial_results_tx_opt: Option<&PartialResultsSender>,
 ) -> crate::Result<(LeafSearchResponse, Option<ScrollKeyAndStartOffset>)> {
     let scroll_ttl_opt = get_scroll_ttl_duration(&search_request)?;
 
@@ -585,6 +690,7 @@ async fn search_partial_hits_phase_with_scroll(
             &search_request,
             split_metadatas,
             cluster_client,
+            None,
         )
         .await?;
         let cached_partial_hits = leaf_search_resp.partial_hits.clone();
@@ -630,6 +736,7 @@ async fn search_partial_hits_phase_with_scroll(
             &search_request,
             split_metadatas,
             cluster_client,
+            partial_results_tx_opt,
         )
         .await?;
         Ok((leaf_search_resp, None))
@@ -724,8 +831,111 @@ fn is_top_5pct_memory_intensive(num_bytes: u64, split_num_docs: u64) -> bool {
     is_memory_intensive
 }
 
+/// Applies the `allow_partial_search_results` policy of the search request to the outcome of a
+/// leaf search request (retries included):
+/// - with partial results allowed, a leaf request failing as a whole is turned into a response in
+///   which all of its splits are reported as failed, retryable if the error of the request is;
+/// - with partial results disallowed, a response with failed splits is turned into an error, so
+///   that the root search fails without waiting for the other leaf requests.
+async fn apply_partial_search_results_policy(
+    leaf_search_future: impl Future<Output = crate::Result<LeafSearchResponse>>,
+    split_ids: Vec<SplitId>,
+    allow_partial_search_results_opt: Option<bool>,
+) -> crate::Result<LeafSearchResponse> {
+    match (leaf_search_future.await, allow_partial_search_results_opt) {
+        (Ok(leaf_search_response), Some(false)) => {
+            if let Some(search_error) =
+                SearchError::from_split_errors(&leaf_search_response.failed_splits)
+            {
+                return Err(search_error);
+            }
+            Ok(leaf_search_response)
+        }
+        (Err(search_error), Some(true)) => {
+            let error = search_error.to_string();
+            let retryable_error = search_error.is_retryable();
+            let num_attempted_splits = split_ids.len() as u64;
+            let failed_splits = split_ids
+                .into_iter()
+                .map(|split_id| SplitSearchError {
+                    error: error.clone(),
+                    split_id,
+                    retryable_error,
+                })
+                .collect();
+            Ok(LeafSearchResponse {
+                failed_splits,
+                num_attempted_splits,
+                ..Default::default()
+            })
+        }
+        (leaf_search_result, _) => leaf_search_result,
+    }
+}
+
+/// Sender through which a root search publishes its partial results while its leaf search
+/// requests complete. Partial results hold the number of hits and the aggregations of the splits
+/// searched so far, but no hits.
+pub(crate) type PartialResultsSender = watch::Sender<Option<SearchResponse>>;
+
+/// Awaits the leaf search requests like `try_join_all`, and publishes the merged results of the
+/// leaf search responses received so far each time a leaf search request completes.
+async fn collect_leaf_search_responses_with_partial_results(
+    leaf_request_tasks: Vec<impl Future<Output = crate::Result<LeafSearchResponse>>>,
+    search_request: &SearchRequest,
+    searcher_context: &SearcherContext,
+    partial_results_tx: &PartialResultsSender,
+) -> crate::Result<Vec<LeafSearchResponse>> {
+    let mut leaf_request_futures: FuturesUnordered<_> = leaf_request_tasks.into_iter().collect();
+    let mut leaf_search_responses = Vec::with_capacity(leaf_request_futures.len());
+    let mut merged_leaf_search_response_opt: Option<LeafSearchResponse> = None;
+
+    while let Some(leaf_search_result) = leaf_request_futures.next().await {
+        let leaf_search_response = leaf_search_result?;
+
+        let mut partial_leaf_search_response = leaf_search_response.clone();
+        // Partial results do not include hits.
+        partial_leaf_search_response.partial_hits.clear();
+        let leaf_search_results: Vec<tantivy::Result<LeafSearchResponse>> =
+            merged_leaf_search_response_opt
+                .take()
+                .into_iter()
+                .chain([partial_leaf_search_response])
+                .map(Ok)
+                .collect();
+        let merge_collector =
+            make_merge_collector(search_request, &searcher_context.get_aggregation_limits())?;
+        let merged_leaf_search_response = crate::search_thread_pool()
+            .run_cpu_intensive(move || merge_collector.merge_fruits(leaf_search_results))
+            .await
+            .context("failed to merge leaf search responses")?
+            .map_err(|error: TantivyError| crate::SearchError::Internal(error.to_string()))?;
+        let aggregation = finalize_aggregation_if_any(
+            search_request,
+            merged_leaf_search_response
+                .intermediate_aggregation_result
+                .clone(),
+            searcher_context,
+        )?;
+        partial_results_tx.send_replace(Some(SearchResponse {
+            num_hits: merged_leaf_search_response.num_hits,
+            aggregation,
+            failed_splits: merged_leaf_search_response.failed_splits.clone(),
+            num_successful_splits: merged_leaf_search_response.num_successful_splits,
+            ..Default::default()
+        }));
+        merged_leaf_search_response_opt = Some(merged_leaf_search_response);
+        leaf_search_responses.push(leaf_search_response);
+    }
+    Ok(leaf_search_responses)
+}
+
 /// If this method fails for some splits, a partial search response is returned, with the list of
-/// faulty splits in the failed_splits field.
+/// faulty splits in the failed_splits field. See [`apply_partial_search_results_policy`] for how
+/// the `allow_partial_search_results` parameter of the request alters this behavior.
+///
+/// If `partial_results_tx_opt` is set, the partial results of the search are published through it
+/// as the leaf search requests complete.
 #[instrument(level = "debug", skip_all)]
 pub(crate) async fn search_partial_hits_phase(
     searcher_context: &SearcherContext,
@@ -733,6 +943,7 @@ pub(crate) async fn search_partial_hits_phase(
     search_request: &SearchRequest,
     split_metadatas: &[SplitMetadata],
     cluster_client: &ClusterClient,
+    partial_results_tx_opt: Option<&PartialResultsSender>,
 ) -> crate::Result<LeafSearchResponse> {
     let leaf_search_responses: Vec<LeafSearchResponse> =
         if is_metadata_count_request(search_request) {
@@ -750,9 +961,30 @@ pub(crate) async fn search_partial_hits_phase(
                     indexes_metas_for_leaf_search,
                     client_jobs,
                 )?;
-                leaf_request_tasks.push(cluster_client.leaf_search(leaf_request, client.clone()));
+                let split_ids: Vec<SplitId> = leaf_request
+                    .leaf_requests
+                    .iter()
+                    .flat_map(|leaf_request_ref| leaf_request_ref.split_offsets.iter())
+                    .map(|split_offsets| split_offsets.split_id.clone())
+                    .collect();
+                let leaf_search_future = cluster_client.leaf_search(leaf_request, client.clone());
+                leaf_request_tasks.push(apply_partial_search_results_policy(
+                    leaf_search_future,
+                    split_ids,
+                    search_request.allow_partial_search_results,
+                ));
+            }
+            if let Some(partial_results_tx) = partial_results_tx_opt {
+                collect_leaf_search_responses_with_partial_results(
+                    leaf_request_tasks,
+                    search_request,
+                    searcher_context,
+                    partial_results_tx,
+                )
+                .await?
+            } else {
+                try_join_all(leaf_request_tasks).await?
             }
-            try_join_all(leaf_request_tasks).await?
         };
 
     // Creates a collector which merges responses into one
@@ -806,6 +1038,7 @@ pub(crate) fn get_snippet_request(search_request: &SearchRequest) -> Option<Snip
     Some(SnippetRequest {
         snippet_fields: search_request.snippet_fields.clone(),
         query_ast_resolved: search_request.query_ast.clone(),
+        snippet_options: search_request.snippet_options.clone(),
     })
 }
 
@@ -842,6 +1075,8 @@ pub(crate) async fn fetch_docs_phase(
     for (client, client_jobs) in assigned_fetch_docs_jobs {
         let fetch_jobs_requests = jobs_to_fetch_docs_requests(
             snippet_request.clone(),
+            &search_request.source_fields,
+            &search_request.runtime_mappings,
             indexes_metas_for_leaf_search,
             client_jobs,
         )?;
@@ -867,19 +1102,18 @@ pub(crate) async fn fetch_docs_phase(
             )
         })
         .collect();
-    let mut sort_field_iter = search_request.sort_fields.iter();
-    let sort_field_1_datetime_format_opt: Option<SortDatetimeFormat> =
-        get_sort_field_datetime_format(sort_field_iter.next())?;
-    let sort_field_2_datetime_format_opt: Option<SortDatetimeFormat> =
-        get_sort_field_datetime_format(sort_field_iter.next())?;
+    let sort_fields_datetime_formats: Vec<Option<SortDatetimeFormat>> = search_request
+        .sort_fields
+        .iter()
+        .map(get_sort_field_datetime_format)
+        .try_collect()?;
     let mut hits_with_position: Vec<(usize, Hit)> = leaf_hits
         .map(|leaf_hit| {
             build_hit_with_position(
                 leaf_hit,
                 &split_id_to_index_id_map,
                 &hit_order,
-                &sort_field_1_datetime_format_opt,
-                &sort_field_2_datetime_format_opt,
+                &sort_fields_datetime_formats,
             )
         })
         .try_collect()?;
@@ -889,7 +1123,6 @@ pub(crate) async fn fetch_docs_phase(
         .into_iter()
         .map(|(_position, hit)| hit)
         .collect();
-
     Ok(hits)
 }
 
@@ -897,8 +1130,7 @@ fn build_hit_with_position(
     mut leaf_hit: LeafHit,
     split_id_to_index_id_map: &HashMap<&SplitId, &str>,
     hit_order: &HashMap<(String, u32, u32), usize>,
-    sort_field_1_datetime_format_opt: &Option<SortDatetimeFormat>,
-    sort_field_2_datetime_format_opt: &Option<SortDatetimeFormat>,
+    sort_fields_datetime_formats: &[Option<SortDatetimeFormat>],
 ) -> crate::Result<(usize, Hit)> {
     let partial_hit_ref = leaf_hit
         .partial_hit
@@ -909,22 +1141,16 @@ fn build_hit_with_position(
         partial_hit_ref.segment_ord,
         partial_hit_ref.doc_id,
     );
-    let sort_value_opt = partial_hit_ref
-        .sort_value
-        .as_mut()
-        .and_then(|sort_field| sort_field.sort_value.as_mut());
-    if let Some(sort_by_value) = sort_value_opt {
-        if let Some(output_datetime_format) = &sort_field_1_datetime_format_opt {
-            convert_sort_datetime_value(sort_by_value, *output_datetime_format)?;
-        }
-    }
-    let sort_value_2_opt = partial_hit_ref
-        .sort_value2
-        .as_mut()
-        .and_then(|sort_field| sort_field.sort_value.as_mut());
-    if let Some(sort_by_value) = sort_value_2_opt {
-        if let Some(output_datetime_format) = &sort_field_2_datetime_format_opt {
-            convert_sort_datetime_value(sort_by_value, *output_datetime_format)?;
+    for (sort_by_value_opt, datetime_format_opt) in partial_hit_ref
+        .sort_by_values_mut()
+        .zip(sort_fields_datetime_formats)
+    {
+        let sort_value_opt =
+            sort_by_value_opt.and_then(|sort_by_value| sort_by_value.sort_value.as_mut());
+        if let (Some(sort_value), Some(output_datetime_format)) =
+            (sort_value_opt, datetime_format_opt)
+        {
+            convert_sort_datetime_value(sort_value, *output_datetime_format)?;
         }
     }
     let position = *hit_order.get(&key).expect("hit order must be present");
@@ -940,20 +1166,19 @@ fn build_hit_with_position(
             partial_hit: leaf_hit.partial_hit,
             snippet: leaf_hit.leaf_snippet_json,
             index_id,
+            fields_json: leaf_hit.leaf_fields_json,
         },
     ))
 }
 
 fn get_sort_field_datetime_format(
-    sort_field: Option<&SortField>,
+    sort_field: &SortField,
 ) -> crate::Result<Option<SortDatetimeFormat>> {
-    if let Some(sort_field) = sort_field {
-        if let Some(sort_field_datetime_format_int) = &sort_field.sort_datetime_format {
-            let sort_field_datetime_format =
-                SortDatetimeFormat::from_i32(*sort_field_datetime_format_int)
-                    .context("invalid sort datetime format")?;
-            return Ok(Some(sort_field_datetime_format));
-        }
+    if let Some(sort_field_datetime_format_int) = &sort_field.sort_datetime_format {
+        let sort_field_datetime_format =
+            SortDatetimeFormat::from_i32(*sort_field_datetime_format_int)
+                .context("invalid sort datetime format")?;
+        return Ok(Some(sort_field_datetime_format));
     }
     Ok(None)
 }
@@ -970,6 +1195,7 @@ async fn root_search_aux(
     search_request: SearchRequest,
     split_metadatas: Vec<SplitMetadata>,
     cluster_client: &ClusterClient,
+    partial_results_tx_opt: Option<&PartialResultsSender>,
 ) -> crate::Result<SearchResponse> {
     debug!(split_metadatas = ?PrettySample::new(&split_metadatas, 5));
     let (first_phase_result, scroll_key_and_start_offset_opt): (
@@ -981,6 +1207,7 @@ async fn root_search_aux(
         search_request.clone(),
         &split_metadatas[..],
         cluster_client,
+        partial_results_tx_opt,
     )
     .await?;
 
@@ -1033,6 +1260,42 @@ fn finalize_aggregation(
             let aggs: Vec<Span> = postcard::from_bytes(&intermediate_aggregation_result_bytes)?;
             serde_json::to_string(&aggs)?
         }
+        QuickwitAggregations::GeoGridAggregation(geo_grid_aggregation) => {
+            // The merge collector has already merged the intermediate results.
+            let fruit: GeoGridFruit = if let Some(intermediate_aggregation_result_bytes) =
+                intermediate_aggregation_result_bytes_opt
+            {
+                postcard::from_bytes(&intermediate_aggregation_result_bytes)?
+            } else {
+                Default::default()
+            };
+            let aggregation_limits = searcher_context.get_aggregation_limits();
+            serde_json::to_string(&geo_grid_aggregation.finalize(fruit, &aggregation_limits)?)?
+        }
+        QuickwitAggregations::NestedAggregation(nested_aggregation) => {
+            // The merge collector has already merged the intermediate results.
+            let fruit: NestedAggregationFruit = if let Some(intermediate_aggregation_result_bytes) =
+                intermediate_aggregation_result_bytes_opt
+            {
+                postcard::from_bytes(&intermediate_aggregation_result_bytes)?
+            } else {
+                Default::default()
+            };
+            let aggregation_limits = searcher_context.get_aggregation_limits();
+            serde_json::to_string(&nested_aggregation.finalize(fruit, &aggregation_limits)?)?
+        }
+        QuickwitAggregations::RuntimeTermsAggregation(runtime_terms_aggregation) => {
+            // The merge collector has already merged the intermediate results.
+            let term_counts: RuntimeTermCounts =
+                if let Some(intermediate_aggregation_result_bytes) =
+                    intermediate_aggregation_result_bytes_opt
+                {
+                    postcard::from_bytes(&intermediate_aggregation_result_bytes)?
+                } else {
+                    Default::default()
+                };
+            serde_json::to_string(&runtime_terms_aggregation.finalize(term_counts))?
+        }
         QuickwitAggregations::TantivyAggregations(aggregations) => {
             let intermediate_aggregation_results =
                 if let Some(intermediate_aggregation_result_bytes) =
@@ -1124,6 +1387,24 @@ async fn refine_and_list_matches(
         .collect_vec();
     search_request.query_ast = serde_json::to_string(&query_ast_resolved)?;
 
+    // The hits of a top-level k-NN query are truncated to the `k` best hits of all splits, which
+    // are only the nearest neighbors when hits are sorted by similarity.
+    if top_level_knn_k(&query_ast_resolved).is_some() {
+        validate_knn_sort(search_request)?;
+    }
+    // The nearest neighbors are only meaningful when hits are sorted by similarity, so that they
+    // can be merged across splits.
+    if search_request.sort_fields.is_empty()
+        && search_request.search_after.is_none()
+        && contains_knn_query(&query_ast_resolved)
+    {
+        search_request.sort_fields.push(SortField {
+            field_name: "_score".to_string(),
+            sort_order: SortOrder::Desc as i32,
+            sort_datetime_format: None,
+        });
+    }
+
     // convert search_after datetime values from input datetime format to nanos.
     convert_search_after_datetime_values(search_request, &sort_fields_is_datetime)?;
 
@@ -1158,10 +1439,47 @@ async fn refine_and_list_matches(
 /// 4. Builds the response with docs and returns.
 #[instrument(skip_all)]
 pub async fn root_search(
+    searcher_context: &SearcherContext,
+    search_request: SearchRequest,
+    metastore: MetastoreServiceClient,
+    cluster_client: &ClusterClient,
+) -> crate::Result<SearchResponse> {
+    root_search_inner(
+        searcher_context,
+        search_request,
+        metastore,
+        cluster_client,
+        None,
+    )
+    .await
+}
+
+/// Performs a distributed search like [`root_search`], publishing the partial results of the
+/// search through `partial_results_tx` while the leaf search requests complete.
+#[instrument(skip_all)]
+pub(crate) async fn root_search_with_partial_results(
+    searcher_context: &SearcherContext,
+    search_request: SearchRequest,
+    metastore: MetastoreServiceClient,
+    cluster_client: &ClusterClient,
+    partial_results_tx: &PartialResultsSender,
+) -> crate::Result<SearchResponse> {
+    root_search_inner(
+        searcher_context,
+        search_request,
+        metastore,
+        cluster_client,
+        Some(partial_results_tx),
+    )
+    .await
+}
+
+async fn root_search_inner(
     searcher_context: &SearcherContext,
     mut search_request: SearchRequest,
     mut metastore: MetastoreServiceClient,
     cluster_client: &ClusterClient,
+    partial_results_tx_opt: Option<&PartialResultsSender>,
 ) -> crate::Result<SearchResponse> {
     let start_instant = tokio::time::Instant::now();
     let list_indexes_metadatas_request = ListIndexesMetadataRequest {
@@ -1185,12 +1503,14 @@ pub async fn root_search(
             search_request,
             Vec::new(),
             cluster_client,
+            partial_results_tx_opt,
         )
         .await?;
         search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
         return Ok(search_response);
     }
 
+    resolve_runtime_fields_in_aggregation(&mut search_request)?;
     let request_metadata = validate_request_and_build_metadata(&indexes_metadata, &search_request)?;
     let split_metadatas = refine_and_list_matches(
         &mut metastore,
@@ -1214,6 +1534,7 @@ pub async fn root_search(
         search_request,
         split_metadatas,
         cluster_client,
+        partial_results_tx_opt,
     )
     .await;
 
@@ -1267,6 +1588,7 @@ pub async fn search_plan(
     )
     .map_err(|err| SearchError::Internal(format!("failed to build doc mapper. cause: {err}")))?;
 
+    resolve_runtime_fields_in_aggregation(&mut search_request)?;
     let request_metadata = validate_request_and_build_metadata(&indexes_metadata, &search_request)?;
     let split_metadatas = refine_and_list_matches(
         &mut metastore,
@@ -1367,12 +1689,10 @@ fn convert_search_after_datetime_values(
         }
     }
     if let Some(partial_hit) = search_request.search_after.as_mut() {
-        let search_after_values = [
-            partial_hit.sort_value.as_mut(),
-            partial_hit.sort_value2.as_mut(),
-        ];
-        for (sort_field, search_after_value_opt) in
-            search_request.sort_fields.iter().zip(search_after_values)
+        for (sort_field, search_after_value_opt) in search_request
+            .sort_fields
+            .iter()
+            .zip(partial_hit.sort_by_values_mut())
         {
             let Some(search_after_sort_by_value) = search_after_value_opt else {
                 continue;
@@ -1721,6 +2041,8 @@ pub fn jobs_to_leaf_request(
 /// Builds a list of [`FetchDocsRequest`], one per index, from a list of [`FetchDocsJob`].
 pub fn jobs_to_fetch_docs_requests(
     snippet_request_opt: Option<SnippetRequest>,
+    source_fields: &[String],
+    runtime_mappings_opt: &Option<String>,
     indexes_metas_for_leaf_search: &IndexesMetasForLeafSearch,
     jobs: Vec<FetchDocsJob>,
 ) -> crate::Result<Vec<FetchDocsRequest>> {
@@ -1753,6 +2075,8 @@ pub fn jobs_to_fetch_docs_requests(
                 index_uri: index_meta.index_uri.to_string(),
                 snippet_request: snippet_request_opt.clone(),
                 doc_mapper: index_meta.doc_mapper_str.clone(),
+                source_fields: source_fields.to_vec(),
+                runtime_mappings: runtime_mappings_opt.clone(),
             };
             fetch_docs_requests.push(fetch_docs_req);
 
@@ -1776,11 +2100,9 @@ mod tests {
     use quickwit_proto::metastore::{
         ListIndexesMetadataResponse, ListSplitsResponse, MockMetastoreService,
     };
-    use quickwit_proto::search::{
-        ScrollRequest, SortByValue, SortOrder, SortValue, SplitSearchError,
-    };
+    use quickwit_proto::search::{ScrollRequest, SortByValue, SortOrder, SortValue};
     use quickwit_query::query_ast::{qast_helper, qast_json_helper, query_ast_from_user_text};
-    use tantivy::schema::{FAST, STORED, TEXT};
+    use tantivy::schema::{FAST, STORED, STRING, TEXT};
 
     use super::*;
     use crate::{searcher_pool_for_test, MockSearchService};
@@ -1816,6 +2138,9 @@ mod tests {
             field_is_not_text_err.to_string(),
             "the snippet field `ip` must be of type `Str`, got `IpAddr`"
         );
+        // Field patterns are resolved when fetching docs.
+        check_snippet_fields_validation(&["*".to_string()]).unwrap();
+        check_snippet_fields_validation(&["doesnotexist*".to_string()]).unwrap();
     }
 
     #[test]
@@ -1840,6 +2165,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_collapse_field() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("title", TEXT);
+        schema_builder.add_text_field("host", STRING | FAST);
+        schema_builder.add_u64_field("timestamp", FAST | STORED);
+        let schema = schema_builder.build();
+        validate_collapse_field(&schema, "host").unwrap();
+        let field_not_fast_err = validate_collapse_field(&schema, "title").unwrap_err();
+        assert_eq!(
+            field_not_fast_err.to_string(),
+            "Invalid argument: Field \"title\" is not configured as a fast field"
+        );
+        let field_is_not_text_err = validate_collapse_field(&schema, "timestamp").unwrap_err();
+        assert_eq!(
+            field_is_not_text_err.to_string(),
+            "Invalid argument: the collapse field `timestamp` must be of type `Str`, got `U64`"
+        );
+        let field_doesnotexist_err = validate_collapse_field(&schema, "doesnotexist").unwrap_err();
+        assert_eq!(
+            field_doesnotexist_err.to_string(),
+            "Invalid argument: Field \"doesnotexist\" does not exist"
+        );
+    }
+
     fn index_metadata_for_multi_indexes_test(index_id: &str, index_uri: &str) -> IndexMetadata {
         let index_uri = Uri::from_str(index_uri).unwrap();
         let doc_mapping_json = r#"{
@@ -1870,6 +2220,7 @@ mod tests {
             doc_mapping,
             indexing_settings,
             search_settings,
+            ingest_settings: Default::default(),
             retention_policy_opt: Default::default(),
         })
     }
@@ -1931,6 +2282,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_request_and_build_metadatas_fail_with_geo_grid_on_non_geo_point_field() {
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("body:test", &[]),
+            max_hits: 10,
+            aggregation_request: Some(
+                r#"{"cells": {"geohash_grid": {"field": "response_time"}}}"#.to_string(),
+            ),
+            ..Default::default()
+        };
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let search_error =
+            validate_request_and_build_metadata(&[index_metadata], &search_request).unwrap_err();
+        assert_eq!(
+            search_error.to_string(),
+            "invalid aggregation request: field `response_time` is not a geo_point field"
+        );
+    }
+
     #[test]
     fn test_validate_request_and_build_metadatas_fail_with_different_timestamps() {
         let search_request = quickwit_proto::search::SearchRequest {
@@ -2042,6 +2413,7 @@ mod tests {
             doc_mapping,
             indexing_settings,
             search_settings,
+            ingest_settings: Default::default(),
             retention_policy_opt: Default::default(),
         })
     }
@@ -2273,6 +2645,8 @@ mod tests {
             sort_value2: Some(SortByValue {
                 sort_value: Some(SortValue::U64(2)),
             }),
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: "".to_string(),
             segment_ord: 0,
             doc_id: 0,
@@ -2299,6 +2673,8 @@ mod tests {
                 sort_value: Some(SortValue::U64(1)),
             }),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: "split1".to_string(),
             segment_ord: 1,
             doc_id: 1,
@@ -2313,6 +2689,7 @@ mod tests {
         let id_field = schema_builder.add_u64_field("id", FAST);
         let no_fast_field = schema_builder.add_u64_field("no_fast", STORED);
         let text_field = schema_builder.add_text_field("text", STORED);
+        let keyword_field = schema_builder.add_text_field("keyword", FAST);
         let schema = schema_builder.build();
         {
             let sort_by_field_entry = schema.get_field_entry(timestamp_field);
@@ -2340,12 +2717,17 @@ mod tests {
         }
         {
             let sort_by_field_entry = schema.get_field_entry(text_field);
-            let error = validate_sort_by_field_type(sort_by_field_entry, true).unwrap_err();
+            let error = validate_sort_by_field_type(sort_by_field_entry, false).unwrap_err();
             assert_eq!(
                 error.to_string(),
-                "Invalid argument: sort by field on type text is currently not supported `text`"
+                "Invalid argument: sort by field must be a fast field, please add the fast \
+                 property to your field `text`"
             );
         }
+        {
+            let sort_by_field_entry = schema.get_field_entry(keyword_field);
+            validate_sort_by_field_type(sort_by_field_entry, false).unwrap();
+        }
     }
 
     #[test]
@@ -2368,6 +2750,8 @@ mod tests {
                 sort_value: Some(SortValue::U64(1)),
             }),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: "split1".to_string(),
             segment_ord: 1,
             doc_id: 1,
@@ -2401,6 +2785,8 @@ mod tests {
                 sort_value: Some(SortValue::U64(1)),
             }),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: "".to_string(),
             segment_ord: 1,
             doc_id: 1,
@@ -2434,6 +2820,8 @@ mod tests {
                 sort_value: Some(SortValue::U64(1)),
             }),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: "split1".to_string(),
             segment_ord: 1,
             doc_id: 1,
@@ -2463,8 +2851,7 @@ mod tests {
     }
 
     #[test]
-    fn test_validate_sort_by_fields_and_search_after_invalid_3() {
-        // 3 sort fields is not possible.
+    fn test_validate_sort_by_fields_and_search_after_3_fields() {
         let sort_fields = vec![
             SortField {
                 field_name: "timestamp".to_string(),
@@ -2472,21 +2859,43 @@ mod tests {
                 sort_datetime_format: Some(SortDatetimeFormat::UnixTimestampMillis as i32),
             },
             SortField {
-                field_name: "timestamp".to_string(),
+                field_name: "id".to_string(),
                 sort_order: 0,
-                sort_datetime_format: Some(SortDatetimeFormat::UnixTimestampMillis as i32),
+                sort_datetime_format: None,
             },
             SortField {
-                field_name: "timestamp".to_string(),
+                field_name: "_score".to_string(),
                 sort_order: 0,
-                sort_datetime_format: Some(SortDatetimeFormat::UnixTimestampMillis as i32),
+                sort_datetime_format: None,
             },
         ];
-        let error = validate_sort_by_fields_and_search_after(&sort_fields, &None).unwrap_err();
+        validate_sort_by_fields_and_search_after(&sort_fields, &None).unwrap();
+
+        let mut partial_hit = PartialHit {
+            sort_value: Some(SortByValue {
+                sort_value: Some(SortValue::I64(1)),
+            }),
+            sort_value2: Some(SortByValue {
+                sort_value: Some(SortValue::U64(2)),
+            }),
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
+            split_id: "".to_string(),
+            segment_ord: 0,
+            doc_id: 0,
+        };
+        let error =
+            validate_sort_by_fields_and_search_after(&sort_fields, &Some(partial_hit.clone()))
+                .unwrap_err();
         assert_eq!(
             error.to_string(),
-            "Invalid argument: sort by field must be up to 2 fields, got 3"
+            "Invalid argument: `search_after` must have the same number of sort values as sort by \
+             fields [\"timestamp\", \"id\", \"_score\"]"
         );
+        partial_hit.extra_sort_values.push(SortByValue {
+            sort_value: Some(SortValue::F64(0.5)),
+        });
+        validate_sort_by_fields_and_search_after(&sort_fields, &Some(partial_hit)).unwrap();
     }
 
     fn mock_partial_hit(
@@ -2497,6 +2906,8 @@ mod tests {
         quickwit_proto::search::PartialHit {
             sort_value: Some(SortValue::U64(sort_value).into()),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: split_id.to_string(),
             segment_ord: 1,
             doc_id,
@@ -2511,6 +2922,8 @@ mod tests {
         quickwit_proto::search::PartialHit {
             sort_value: sort_value.map(|sort_value| SortValue::U64(sort_value).into()),
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: split_id.to_string(),
             segment_ord: 1,
             doc_id,
@@ -2532,10 +2945,85 @@ mod tests {
                 .expect("Json serialization should not fail"),
                 partial_hit: Some(req),
                 leaf_snippet_json: None,
+                leaf_fields_json: None,
             })
             .collect()
     }
 
+    #[tokio::test]
+    async fn test_collect_leaf_search_responses_with_partial_results() {
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("test", &["body"]),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let searcher_context = SearcherContext::for_test();
+        let (partial_results_tx, mut partial_results_rx) = watch::channel(None);
+        let leaf_request_tasks = vec![
+            futures::future::ready(Ok(LeafSearchResponse {
+                num_hits: 2,
+                partial_hits: vec![
+                    mock_partial_hit("split1", 3, 1),
+                    mock_partial_hit("split1", 1, 2),
+                ],
+                num_attempted_splits: 1,
+                num_successful_splits: 1,
+                ..Default::default()
+            })),
+            futures::future::ready(Ok(LeafSearchResponse {
+                num_hits: 1,
+                partial_hits: vec![mock_partial_hit("split2", 2, 1)],
+                failed_splits: vec![SplitSearchError {
+                    error: "mock_error".to_string(),
+                    split_id: "split3".to_string(),
+                    retryable_error: false,
+                }],
+                num_attempted_splits: 2,
+                num_successful_splits: 1,
+                ..Default::default()
+            })),
+        ];
+        let leaf_search_responses = collect_leaf_search_responses_with_partial_results(
+            leaf_request_tasks,
+            &search_request,
+            &searcher_context,
+            &partial_results_tx,
+        )
+        .await
+        .unwrap();
+        assert_eq!(leaf_search_responses.len(), 2);
+        assert_eq!(
+            leaf_search_responses
+                .iter()
+                .map(|leaf_search_response| leaf_search_response.partial_hits.len())
+                .sum::<usize>(),
+            3
+        );
+        assert!(partial_results_rx.has_changed().unwrap());
+
+        let partial_search_response = partial_results_rx.borrow_and_update().clone().unwrap();
+        assert_eq!(partial_search_response.num_hits, 3);
+        assert!(partial_search_response.hits.is_empty());
+        assert_eq!(partial_search_response.num_successful_splits, 2);
+        assert_eq!(partial_search_response.failed_splits.len(), 1);
+        assert_eq!(partial_search_response.failed_splits[0].split_id, "split3");
+
+        let leaf_request_tasks = vec![futures::future::ready(Err(SearchError::Internal(
+            "mock_error".to_string(),
+        )))];
+        let search_error = collect_leaf_search_responses_with_partial_results(
+            leaf_request_tasks,
+            &search_request,
+            &searcher_context,
+            &partial_results_tx,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(search_error, SearchError::Internal(_)));
+        assert!(!partial_results_rx.has_changed().unwrap());
+    }
+
     #[tokio::test]
     async fn test_root_search_offset_out_of_bounds_1085() -> anyhow::Result<()> {
         let search_request = quickwit_proto::search::SearchRequest {
@@ -2925,6 +3413,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: Some(SortValue::U64(2u64).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split1".to_string(),
                             segment_ord: 0,
                             doc_id: 0,
@@ -2932,6 +3422,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: None,
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split1".to_string(),
                             segment_ord: 0,
                             doc_id: 1,
@@ -2959,6 +3451,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: Some(SortValue::I64(-1i64).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split2".to_string(),
                             segment_ord: 0,
                             doc_id: 1,
@@ -2966,6 +3460,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: Some(SortValue::I64(1i64).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split2".to_string(),
                             segment_ord: 0,
                             doc_id: 0,
@@ -2973,6 +3469,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: None,
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split2".to_string(),
                             segment_ord: 0,
                             doc_id: 2,
@@ -3015,6 +3513,8 @@ mod tests {
                 doc_id: 1,
                 sort_value: Some(SortValue::I64(-1i64).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3025,6 +3525,8 @@ mod tests {
                 doc_id: 0,
                 sort_value: Some(SortValue::I64(1i64).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3035,6 +3537,8 @@ mod tests {
                 doc_id: 0,
                 sort_value: Some(SortValue::U64(2u64).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3045,6 +3549,8 @@ mod tests {
                 doc_id: 1,
                 sort_value: None,
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3055,6 +3561,8 @@ mod tests {
                 doc_id: 2,
                 sort_value: None,
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         Ok(())
@@ -3107,6 +3615,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: Some(SortValue::U64(2u64).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split1".to_string(),
                             segment_ord: 0,
                             doc_id: 0,
@@ -3114,6 +3624,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: None,
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split1".to_string(),
                             segment_ord: 0,
                             doc_id: 1,
@@ -3141,6 +3653,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: Some(SortValue::I64(1i64).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split2".to_string(),
                             segment_ord: 0,
                             doc_id: 0,
@@ -3148,6 +3662,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: Some(SortValue::I64(-1i64).into()),
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split2".to_string(),
                             segment_ord: 0,
                             doc_id: 1,
@@ -3155,6 +3671,8 @@ mod tests {
                         quickwit_proto::search::PartialHit {
                             sort_value: None,
                             sort_value2: None,
+                            extra_sort_values: Vec::new(),
+                            collapse_value: None,
                             split_id: "split2".to_string(),
                             segment_ord: 0,
                             doc_id: 2,
@@ -3197,6 +3715,8 @@ mod tests {
                 doc_id: 0,
                 sort_value: Some(SortValue::U64(2u64).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3207,6 +3727,8 @@ mod tests {
                 doc_id: 0,
                 sort_value: Some(SortValue::I64(1i64).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3217,6 +3739,8 @@ mod tests {
                 doc_id: 1,
                 sort_value: Some(SortValue::I64(-1i64).into()),
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3227,6 +3751,8 @@ mod tests {
                 doc_id: 2,
                 sort_value: None,
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         assert_eq!(
@@ -3237,6 +3763,8 @@ mod tests {
                 doc_id: 1,
                 sort_value: None,
                 sort_value2: None,
+                extra_sort_values: Vec::new(),
+                collapse_value: None,
             }
         );
         Ok(())
@@ -3646,6 +4174,140 @@ mod tests {
         assert_eq!(search_response.failed_splits.len(), 1);
     }
 
+    fn mock_metastore_with_single_split() -> MockMetastoreService {
+        let mut mock_metastore = MockMetastoreService::new();
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///test-index");
+        let index_uid = index_metadata.index_uid.clone();
+        mock_metastore
+            .expect_list_indexes_metadata()
+            .returning(move |_index_ids_query| {
+                Ok(ListIndexesMetadataResponse::for_test(vec![
+                    index_metadata.clone()
+                ]))
+            });
+        mock_metastore
+            .expect_list_splits()
+            .returning(move |_filter| {
+                let splits = vec![MockSplitBuilder::new("split1")
+                    .with_index_uid(&index_uid)
+                    .build()];
+                let splits_response = ListSplitsResponse::try_from_splits(splits).unwrap();
+                Ok(ServiceStream::from(vec![Ok(splits_response)]))
+            });
+        mock_metastore
+    }
+
+    #[tokio::test]
+    async fn test_root_search_allow_partial_search_results_leaf_request_failure() {
+        for allow_partial_search_results in [None, Some(true), Some(false)] {
+            let search_request = quickwit_proto::search::SearchRequest {
+                index_id_patterns: vec!["test-index".to_string()],
+                query_ast: qast_json_helper("test", &["body"]),
+                max_hits: 10,
+                allow_partial_search_results,
+                ..Default::default()
+            };
+            let mut mock_search_service = MockSearchService::new();
+            mock_search_service.expect_leaf_search().returning(
+                |_leaf_search_req: quickwit_proto::search::LeafSearchRequest| {
+                    Err(SearchError::Internal("mock_error".to_string()))
+                },
+            );
+            mock_search_service.expect_fetch_docs().returning(
+                |_fetch_docs_req: quickwit_proto::search::FetchDocsRequest| {
+                    Ok(quickwit_proto::search::FetchDocsResponse { hits: Vec::new() })
+                },
+            );
+            let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+            let search_job_placer = SearchJobPlacer::new(searcher_pool);
+            let cluster_client = ClusterClient::new(search_job_placer.clone());
+            let search_result = root_search(
+                &SearcherContext::for_test(),
+                search_request,
+                MetastoreServiceClient::from_mock(mock_metastore_with_single_split()),
+                &cluster_client,
+            )
+            .await;
+            if allow_partial_search_results == Some(true) {
+                let search_response = search_result.unwrap();
+                assert_eq!(search_response.num_hits, 0);
+                assert_eq!(search_response.num_successful_splits, 0);
+                assert_eq!(search_response.failed_splits.len(), 1);
+                assert_eq!(search_response.failed_splits[0].split_id, "split1");
+                assert!(search_response.failed_splits[0]
+                    .error
+                    .contains("mock_error"));
+                assert!(search_response.failed_splits[0].retryable_error);
+            } else {
+                search_result.unwrap_err();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_partial_search_results_policy_retryable_error() {
+        let leaf_search_future =
+            futures::future::ready(Err(SearchError::InvalidQuery("mock_error".to_string())));
+        let leaf_search_response = apply_partial_search_results_policy(
+            leaf_search_future,
+            vec!["split1".to_string(), "split2".to_string()],
+            Some(true),
+        )
+        .await
+        .unwrap();
+        assert_eq!(leaf_search_response.num_attempted_splits, 2);
+        assert_eq!(leaf_search_response.failed_splits.len(), 2);
+        assert!(!leaf_search_response.failed_splits[0].retryable_error);
+
+        let leaf_search_future =
+            futures::future::ready(Err(SearchError::Unavailable("mock_error".to_string())));
+        let leaf_search_response = apply_partial_search_results_policy(
+            leaf_search_future,
+            vec!["split1".to_string()],
+            Some(true),
+        )
+        .await
+        .unwrap();
+        assert!(leaf_search_response.failed_splits[0].retryable_error);
+    }
+
+    #[tokio::test]
+    async fn test_root_search_disallow_partial_search_results_failed_split() {
+        let search_request = quickwit_proto::search::SearchRequest {
+            index_id_patterns: vec!["test-index".to_string()],
+            query_ast: qast_json_helper("test", &["body"]),
+            max_hits: 10,
+            allow_partial_search_results: Some(false),
+            ..Default::default()
+        };
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().returning(
+            |_leaf_search_req: quickwit_proto::search::LeafSearchRequest| {
+                Ok(quickwit_proto::search::LeafSearchResponse {
+                    failed_splits: vec![SplitSearchError {
+                        error: "mock_error".to_string(),
+                        split_id: "split1".to_string(),
+                        retryable_error: false,
+                    }],
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        let searcher_pool = searcher_pool_for_test([("127.0.0.1:1001", mock_search_service)]);
+        let search_job_placer = SearchJobPlacer::new(searcher_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let search_error = root_search(
+            &SearcherContext::for_test(),
+            search_request,
+            MetastoreServiceClient::from_mock(mock_metastore_with_single_split()),
+            &cluster_client,
+        )
+        .await
+        .unwrap_err();
+        assert!(search_error.to_string().contains("split1"));
+    }
+
     #[tokio::test]
     async fn test_root_search_one_splits_two_nodes_but_one_is_failing_for_split(
     ) -> anyhow::Result<()> {
@@ -0,0 +1,410 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+
+use quickwit_query::runtime_field::{
+    FastFieldDocReader, RuntimeField, RuntimeFieldEvaluator, RuntimeMappings, RuntimeValue,
+};
+use quickwit_query::OneFieldMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::{DocId, Score, SegmentReader, TantivyError};
+
+use crate::SearchError;
+
+/// Number of documents per runtime field value, indexed by the string representation of the
+/// value.
+pub type RuntimeTermCounts = HashMap<String, u64>;
+
+const DEFAULT_RUNTIME_TERMS_SIZE: usize = 10;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum RuntimeTermsAggregationRequest {
+    RuntimeTerms(RuntimeTermsParams),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuntimeTermsParams {
+    field: String,
+    #[serde(default)]
+    size: Option<usize>,
+    runtime_field: RuntimeField,
+}
+
+/// Buckets the documents matching a query by the values of a runtime field, counting the number
+/// of documents for each value.
+///
+/// Root searchers rewrite the `terms` aggregations on a runtime field into this aggregation, so
+/// that leaves receive the definition of the field. Sub-aggregations are not supported, and the
+/// aggregation cannot be mixed with other aggregations in the same request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeTermsAggregation {
+    /// The name of the aggregation in the response.
+    pub name: String,
+    /// The name of the runtime field.
+    pub field: String,
+    /// The maximum number of buckets returned.
+    pub size: usize,
+    /// The definition of the runtime field.
+    pub runtime_field: RuntimeField,
+}
+
+impl<'de> Deserialize<'de> for RuntimeTermsAggregation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let one_field_map =
+            OneFieldMap::<RuntimeTermsAggregationRequest>::deserialize(deserializer)?;
+        let RuntimeTermsAggregationRequest::RuntimeTerms(params) = one_field_map.value;
+        Ok(RuntimeTermsAggregation {
+            name: one_field_map.field,
+            field: params.field,
+            size: params.size.unwrap_or(DEFAULT_RUNTIME_TERMS_SIZE),
+            runtime_field: params.runtime_field,
+        })
+    }
+}
+
+/// Rewrites a `terms` aggregation on a runtime field into a `runtime_terms` aggregation.
+///
+/// Returns `None` if the aggregation request does not target a runtime field.
+pub(crate) fn resolve_runtime_field_aggregation(
+    aggregation_request: &str,
+    runtime_mappings: &RuntimeMappings,
+) -> crate::Result<Option<String>> {
+    let Ok(serde_json::Value::Object(aggregations)) =
+        serde_json::from_str::<serde_json::Value>(aggregation_request)
+    else {
+        return Ok(None);
+    };
+    let targets_runtime_field = |aggregation: &serde_json::Value| {
+        aggregation
+            .pointer("/terms/field")
+            .and_then(|field| field.as_str())
+            .is_some_and(|field| runtime_mappings.contains_key(field))
+    };
+    if !aggregations.values().any(targets_runtime_field) {
+        return Ok(None);
+    }
+    let invalid_aggregation_error = || {
+        SearchError::InvalidAggregationRequest(
+            "an aggregation on a runtime field must be the only aggregation of the request and \
+             cannot have sub-aggregations"
+                .to_string(),
+        )
+    };
+    if aggregations.len() != 1 {
+        return Err(invalid_aggregation_error());
+    }
+    let (name, aggregation) = aggregations.into_iter().next().expect("one aggregation");
+    let serde_json::Value::Object(aggregation) = aggregation else {
+        return Err(invalid_aggregation_error());
+    };
+    let Some(serde_json::Value::Object(terms_params)) = aggregation.get("terms") else {
+        return Err(invalid_aggregation_error());
+    };
+    if aggregation.len() != 1 {
+        return Err(invalid_aggregation_error());
+    }
+    let field = terms_params
+        .get("field")
+        .and_then(|field| field.as_str())
+        .unwrap_or_default();
+    let runtime_field = &runtime_mappings[field];
+    let mut runtime_terms_params = serde_json::json!({
+        "field": field,
+        "runtime_field": runtime_field,
+    });
+    for (key, value) in terms_params {
+        match key.as_str() {
+            "field" => {}
+            "size" => {
+                runtime_terms_params["size"] = value.clone();
+            }
+            _ => {
+                return Err(SearchError::InvalidAggregationRequest(format!(
+                    "unsupported parameter `{key}` for a terms aggregation on runtime field \
+                     `{field}`"
+                )));
+            }
+        }
+    }
+    let runtime_terms_aggregation = serde_json::json!({
+        name: { "runtime_terms": runtime_terms_params }
+    });
+    Ok(Some(runtime_terms_aggregation.to_string()))
+}
+
+#[derive(Serialize)]
+struct RuntimeTermsBucket {
+    key: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_as_string: Option<String>,
+    doc_count: u64,
+}
+
+#[derive(Serialize)]
+struct RuntimeTermsAggregationResult {
+    doc_count_error_upper_bound: u64,
+    sum_other_doc_count: u64,
+    buckets: Vec<RuntimeTermsBucket>,
+}
+
+impl RuntimeTermsAggregation {
+    /// The names of the fast fields accessed by this collector.
+    pub fn fast_field_names(&self) -> HashSet<String> {
+        // Invalid scripts are reported when the runtime mappings are parsed.
+        let Ok(compiled_runtime_field) = self.runtime_field.compile() else {
+            return HashSet::new();
+        };
+        compiled_runtime_field
+            .input_fields()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Adds the counts of `other` to `counts`.
+    pub(crate) fn merge_term_counts(counts: &mut RuntimeTermCounts, other: RuntimeTermCounts) {
+        for (term, count) in other {
+            *counts.entry(term).or_default() += count;
+        }
+    }
+
+    fn bucket(&self, term: String, doc_count: u64) -> RuntimeTermsBucket {
+        let field_type = self.runtime_field.field_type;
+        let (key, key_as_string) = match field_type.coerce(&serde_json::Value::String(term)) {
+            Some(RuntimeValue::Boolean(bool_val)) => (
+                serde_json::Value::from(bool_val as u64),
+                Some(bool_val.to_string()),
+            ),
+            Some(value) => (value.to_json(), None),
+            None => (serde_json::Value::Null, None),
+        };
+        RuntimeTermsBucket {
+            key,
+            key_as_string,
+            doc_count,
+        }
+    }
+
+    /// Builds the final aggregation result: the `size` most frequent values, sorted by
+    /// decreasing document count.
+    pub(crate) fn finalize(&self, counts: RuntimeTermCounts) -> serde_json::Value {
+        let mut term_counts: Vec<(String, u64)> = counts.into_iter().collect();
+        term_counts.sort_unstable_by(|(left_term, left_count), (right_term, right_count)| {
+            right_count
+                .cmp(left_count)
+                .then_with(|| left_term.cmp(right_term))
+        });
+        let sum_other_doc_count = term_counts
+            .iter()
+            .skip(self.size)
+            .map(|(_term, count)| count)
+            .sum();
+        term_counts.truncate(self.size);
+        let buckets = term_counts
+            .into_iter()
+            .map(|(term, doc_count)| self.bucket(term, doc_count))
+            .collect();
+        let result = RuntimeTermsAggregationResult {
+            doc_count_error_upper_bound: 0,
+            sum_other_doc_count,
+            buckets,
+        };
+        serde_json::json!({ &self.name: result })
+    }
+}
+
+fn runtime_value_to_term(value: &RuntimeValue) -> String {
+    match value {
+        RuntimeValue::Keyword(text) => text.clone(),
+        RuntimeValue::Long(val) => val.to_string(),
+        RuntimeValue::Double(val) => val.to_string(),
+        RuntimeValue::Boolean(bool_val) => bool_val.to_string(),
+    }
+}
+
+impl Collector for RuntimeTermsAggregation {
+    type Fruit = RuntimeTermCounts;
+    type Child = RuntimeTermsSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: u32,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let evaluator = self
+            .runtime_field
+            .compile()
+            .map_err(|error| {
+                TantivyError::InvalidArgument(format!(
+                    "invalid runtime field `{}`: {error}",
+                    self.field
+                ))
+            })?
+            .evaluator();
+        let fast_field_doc_reader =
+            FastFieldDocReader::open(segment_reader, evaluator.input_fields())?;
+        Ok(RuntimeTermsSegmentCollector {
+            evaluator,
+            fast_field_doc_reader,
+            counts: RuntimeTermCounts::default(),
+        })
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut merged_counts = RuntimeTermCounts::default();
+        for segment_fruit in segment_fruits {
+            Self::merge_term_counts(&mut merged_counts, segment_fruit);
+        }
+        Ok(merged_counts)
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+/// Segment collector of the [`RuntimeTermsAggregation`].
+pub struct RuntimeTermsSegmentCollector {
+    evaluator: RuntimeFieldEvaluator,
+    fast_field_doc_reader: FastFieldDocReader,
+    counts: RuntimeTermCounts,
+}
+
+impl SegmentCollector for RuntimeTermsSegmentCollector {
+    type Fruit = RuntimeTermCounts;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let Ok(json_doc) = self.fast_field_doc_reader.read_doc(doc) else {
+            return;
+        };
+        let terms: HashSet<String> = self
+            .evaluator
+            .evaluate(&json_doc)
+            .iter()
+            .map(runtime_value_to_term)
+            .collect();
+        // A document is counted once per distinct value.
+        for term in terms {
+            *self.counts.entry(term).or_default() += 1;
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QuickwitAggregations;
+
+    fn runtime_mappings() -> RuntimeMappings {
+        serde_json::from_str(
+            r#"{"status_class": {"type": "long", "script": "to_int!(.status) / 100"}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_runtime_field_aggregation() {
+        let runtime_mappings = runtime_mappings();
+        assert!(resolve_runtime_field_aggregation(
+            r#"{"hosts": {"terms": {"field": "host"}}}"#,
+            &runtime_mappings
+        )
+        .unwrap()
+        .is_none());
+
+        let aggregation_request = resolve_runtime_field_aggregation(
+            r#"{"classes": {"terms": {"field": "status_class", "size": 3}}}"#,
+            &runtime_mappings,
+        )
+        .unwrap()
+        .unwrap();
+        let aggregation: QuickwitAggregations = serde_json::from_str(&aggregation_request).unwrap();
+        let QuickwitAggregations::RuntimeTermsAggregation(runtime_terms_aggregation) = aggregation
+        else {
+            panic!("expected a runtime terms aggregation, got {aggregation:?}");
+        };
+        assert_eq!(
+            runtime_terms_aggregation,
+            RuntimeTermsAggregation {
+                name: "classes".to_string(),
+                field: "status_class".to_string(),
+                size: 3,
+                runtime_field: runtime_mappings["status_class"].clone(),
+            }
+        );
+
+        let error = resolve_runtime_field_aggregation(
+            r#"{
+                "classes": {"terms": {"field": "status_class"}},
+                "hosts": {"terms": {"field": "host"}}
+            }"#,
+            &runtime_mappings,
+        )
+        .unwrap_err();
+        assert!(matches!(error, SearchError::InvalidAggregationRequest(_)));
+
+        let error = resolve_runtime_field_aggregation(
+            r#"{"classes": {"terms": {"field": "status_class", "order": {"_key": "asc"}}}}"#,
+            &runtime_mappings,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unsupported parameter `order`"));
+    }
+
+    #[test]
+    fn test_runtime_terms_aggregation_finalize() {
+        let runtime_terms_aggregation = RuntimeTermsAggregation {
+            name: "classes".to_string(),
+            field: "status_class".to_string(),
+            size: 2,
+            runtime_field: runtime_mappings()["status_class"].clone(),
+        };
+        let mut counts =
+            RuntimeTermCounts::from_iter([("2".to_string(), 10), ("5".to_string(), 3)]);
+        RuntimeTermsAggregation::merge_term_counts(
+            &mut counts,
+            RuntimeTermCounts::from_iter([("4".to_string(), 3), ("5".to_string(), 1)]),
+        );
+        assert_eq!(
+            runtime_terms_aggregation.finalize(counts),
+            serde_json::json!({
+                "classes": {
+                    "doc_count_error_upper_bound": 0,
+                    "sum_other_doc_count": 3,
+                    "buckets": [
+                        {"key": 2, "doc_count": 10},
+                        {"key": 5, "doc_count": 4},
+                    ]
+                }
+            })
+        );
+    }
+}
@@ -117,6 +117,7 @@ impl ScrollContext {
             &self.search_request,
             &self.split_metadatas[..],
             cluster_client,
+            None,
         )
         .await?;
         self.cached_partial_hits_start_offset = start_offset;
@@ -253,6 +254,8 @@ mod tests {
         let partial_hit = PartialHit {
             sort_value: None,
             sort_value2: None,
+            extra_sort_values: Vec::new(),
+            collapse_value: None,
             split_id: "split".to_string(),
             segment_ord: 1,
             doc_id: 2,
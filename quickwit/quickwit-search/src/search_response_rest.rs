@@ -20,11 +20,12 @@
 use std::convert::TryFrom;
 
 use quickwit_common::truncate_str;
-use quickwit_proto::search::SearchResponse;
+use quickwit_proto::search::{SearchResponse, SplitSearchError};
 use quickwit_query::query_ast::QueryAst;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
+use crate::async_search::AsyncSearchResponse;
 use crate::error::SearchError;
 
 /// SearchResponseRest represents the response returned by the REST search API
@@ -40,6 +41,11 @@ pub struct SearchResponseRest {
     #[schema(value_type = Vec<Object>)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snippets: Option<Vec<JsonValue>>,
+    /// Values of the runtime fields of the request, one object per hit mapping each runtime
+    /// field to its array of values.
+    #[schema(value_type = Vec<Object>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<JsonValue>>,
     /// Elapsed time.
     pub elapsed_time_micros: u64,
     /// Search errors.
@@ -48,6 +54,19 @@ pub struct SearchResponseRest {
     #[schema(value_type = Object)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aggregations: Option<JsonValue>,
+    /// Number of splits that could not be searched. Only present when some splits failed, which
+    /// requires `allow_failed_splits` to be set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub num_failed_splits: u64,
+    /// Splits that could not be searched, along with the reason of their failure.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_splits: Vec<SplitSearchError>,
+}
+
+fn is_zero(num: &u64) -> bool {
+    *num == 0
 }
 
 impl TryFrom<SearchResponse> for SearchResponseRest {
@@ -56,6 +75,7 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
     fn try_from(search_response: SearchResponse) -> Result<Self, Self::Error> {
         let mut documents = Vec::with_capacity(search_response.hits.len());
         let mut snippets = Vec::new();
+        let mut fields = Vec::new();
         for hit in search_response.hits {
             let document: JsonValue = serde_json::from_str(&hit.json).map_err(|err| {
                 SearchError::Internal(format!(
@@ -75,6 +95,14 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
                     })?;
                 snippets.push(snippet_opt);
             }
+            if let Some(fields_json) = hit.fields_json {
+                let hit_fields: JsonValue = serde_json::from_str(&fields_json).map_err(|err| {
+                    SearchError::Internal(format!(
+                        "failed to serialize fields `{fields_json}` to JSON: `{err}`"
+                    ))
+                })?;
+                fields.push(hit_fields);
+            }
         }
 
         let snippet_opt = if !snippets.is_empty() {
@@ -83,6 +111,12 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
             None
         };
 
+        let fields_opt = if !fields.is_empty() {
+            Some(fields)
+        } else {
+            None
+        };
+
         let aggregations_opt = if let Some(aggregation_json) = search_response.aggregation {
             let aggregation: JsonValue = serde_json::from_str(&aggregation_json)
                 .map_err(|err| SearchError::Internal(err.to_string()))?;
@@ -95,9 +129,71 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
             num_hits: search_response.num_hits,
             hits: documents,
             snippets: snippet_opt,
+            fields: fields_opt,
             elapsed_time_micros: search_response.elapsed_time_micros,
             errors: search_response.errors,
             aggregations: aggregations_opt,
+            num_failed_splits: search_response.failed_splits.len() as u64,
+            failed_splits: search_response.failed_splits,
+        })
+    }
+}
+
+/// AsyncSearchResponseRest represents the response returned by the REST async search API
+/// and is meant to be serialized into JSON.
+#[derive(Serialize, Deserialize, PartialEq, Debug, utoipa::ToSchema)]
+pub struct AsyncSearchResponseRest {
+    /// ID of the async search, used to poll or delete it.
+    pub id: String,
+    /// Whether the search is still running.
+    pub is_running: bool,
+    /// Whether the response is incomplete because the search is still running, failed, or
+    /// could not search some splits.
+    pub is_partial: bool,
+    /// Time at which the search was submitted, in milliseconds since the Unix epoch.
+    pub start_time_in_millis: i64,
+    /// Time after which the search and its results are deleted, in milliseconds since the Unix
+    /// epoch.
+    pub expiration_time_in_millis: i64,
+    /// Time at which the search completed, in milliseconds since the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_time_in_millis: Option<i64>,
+    /// Response of the search once it completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<SearchResponseRest>,
+    /// Error of the search if it failed, or if some splits could not be searched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TryFrom<AsyncSearchResponse> for AsyncSearchResponseRest {
+    type Error = SearchError;
+
+    fn try_from(async_search_response: AsyncSearchResponse) -> Result<Self, Self::Error> {
+        let mut error_opt = async_search_response
+            .error
+            .map(|search_error| search_error.to_string());
+        let response_opt = if let Some(search_response) = async_search_response.response {
+            if let Some(search_error) =
+                SearchError::from_split_errors(&search_response.failed_splits[..])
+            {
+                error_opt = Some(search_error.to_string());
+            }
+            Some(SearchResponseRest::try_from(search_response)?)
+        } else {
+            None
+        };
+        let is_partial = async_search_response.is_running || error_opt.is_some();
+
+        Ok(AsyncSearchResponseRest {
+            id: async_search_response.id,
+            is_running: async_search_response.is_running,
+            is_partial,
+            start_time_in_millis: async_search_response.start_time_in_millis,
+            expiration_time_in_millis: async_search_response.expiration_time_in_millis,
+            completion_time_in_millis: async_search_response.completion_time_in_millis,
+            response: response_opt,
+            error: error_opt,
         })
     }
 }
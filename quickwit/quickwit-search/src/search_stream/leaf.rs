@@ -29,6 +29,7 @@ use quickwit_proto::search::{
     LeafSearchStreamResponse, OutputFormat, SearchRequest, SearchStreamRequest,
     SplitIdAndFooterOffsets,
 };
+use quickwit_query::query_ast::bind_ids_queries_to_split;
 use quickwit_storage::{ByteRangeCache, Storage};
 use tantivy::columnar::{DynamicColumn, HasAssociatedColumnType};
 use tantivy::fastfield::Column;
@@ -162,6 +163,7 @@ async fn leaf_search_stream_single_split(
     let search_request = Arc::new(SearchRequest::try_from(stream_request.clone())?);
     let query_ast = serde_json::from_str(&search_request.query_ast)
         .map_err(|err| SearchError::InvalidQuery(err.to_string()))?;
+    let query_ast = bind_ids_queries_to_split(query_ast, &split.split_id);
     let (query, mut warmup_info) = doc_mapper.query(split_schema.clone(), &query_ast, false)?;
     let reader = index
         .reader_builder()
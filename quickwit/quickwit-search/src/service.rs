@@ -43,6 +43,9 @@ use tantivy::aggregation::AggregationLimitsGuard;
 use tokio::sync::Semaphore;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+use crate::async_search::{
+    delete_async_search, get_async_search, submit_async_search, AsyncSearchResponse,
+};
 use crate::leaf::multi_leaf_search;
 use crate::leaf_cache::LeafSearchCache;
 use crate::list_fields::{leaf_list_fields, root_list_fields};
@@ -154,6 +157,28 @@ pub trait SearchService: 'static + Send + Sync {
 
     /// Describe how a search would be processed.
     async fn search_plan(&self, request: SearchRequest) -> crate::Result<SearchPlanResponse>;
+
+    /// Starts a root search in the background and returns its state once it completes or after
+    /// `wait_for_completion_timeout`, whichever comes first.
+    ///
+    /// The state of the search is stored in the distributed KV store for `keep_alive`. It can be
+    /// polled from any node with `get_async_search(..)`. `owner_opt` is the name of the principal
+    /// submitting the search, if authentication is enabled.
+    async fn submit_async_search(
+        &self,
+        request: SearchRequest,
+        keep_alive: Duration,
+        wait_for_completion_timeout: Duration,
+        owner_opt: Option<String>,
+    ) -> crate::Result<AsyncSearchResponse>;
+
+    /// Returns the state of an async search. Checking that the caller is allowed to access it is
+    /// left to the caller.
+    async fn get_async_search(&self, async_search_id: String)
+        -> crate::Result<AsyncSearchResponse>;
+
+    /// Deletes an async search, cancelling it if it is still running.
+    async fn delete_async_search(&self, async_search_id: String) -> crate::Result<()>;
 }
 
 impl SearchServiceImpl {
@@ -229,6 +254,8 @@ impl SearchService for SearchServiceImpl {
             &fetch_docs_request.split_offsets,
             doc_mapper,
             snippet_request_opt,
+            &fetch_docs_request.source_fields,
+            fetch_docs_request.runtime_mappings.as_deref(),
         )
         .await?;
 
@@ -365,6 +392,36 @@ impl SearchService for SearchServiceImpl {
         let search_plan = search_plan(search_request, self.metastore.clone()).await?;
         Ok(search_plan)
     }
+
+    async fn submit_async_search(
+        &self,
+        search_request: SearchRequest,
+        keep_alive: Duration,
+        wait_for_completion_timeout: Duration,
+        owner_opt: Option<String>,
+    ) -> crate::Result<AsyncSearchResponse> {
+        submit_async_search(
+            search_request,
+            keep_alive,
+            wait_for_completion_timeout,
+            owner_opt,
+            self.searcher_context.clone(),
+            self.metastore.clone(),
+            self.cluster_client.clone(),
+        )
+        .await
+    }
+
+    async fn get_async_search(
+        &self,
+        async_search_id: String,
+    ) -> crate::Result<AsyncSearchResponse> {
+        get_async_search(&async_search_id, &self.cluster_client).await
+    }
+
+    async fn delete_async_search(&self, async_search_id: String) -> crate::Result<()> {
+        delete_async_search(&async_search_id, &self.cluster_client).await
+    }
 }
 
 pub(crate) async fn scroll(
@@ -26,8 +26,8 @@ use quickwit_doc_mapper::DocMapper;
 use quickwit_indexing::TestSandbox;
 use quickwit_opentelemetry::otlp::TraceId;
 use quickwit_proto::search::{
-    LeafListTermsResponse, ListTermsRequest, SearchRequest, SortByValue, SortField, SortOrder,
-    SortValue,
+    Hit, LeafListTermsResponse, ListTermsRequest, SearchRequest, SnippetOptions, SortByValue,
+    SortField, SortOrder, SortValue,
 };
 use quickwit_query::query_ast::{
     qast_helper, qast_json_helper, query_ast_from_user_text, QueryAst,
@@ -87,6 +87,74 @@ async fn test_single_node_simple() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "vrl")]
+#[tokio::test]
+async fn test_single_node_runtime_fields() -> anyhow::Result<()> {
+    let index_id = "single-node-runtime-fields";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: status
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let docs = vec![
+        json!({"body": "service unavailable", "status": 503}),
+        json!({"body": "ok", "status": 200}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let runtime_mappings =
+        r#"{"status_class": {"type": "long", "script": "to_int!(.status) / 100"}}"#;
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("status_class:5", &[]),
+        runtime_mappings: Some(runtime_mappings.to_string()),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 1);
+    let hit = &single_node_result.hits[0];
+    // The runtime field values are returned next to the document, which is left untouched.
+    let hit_json: JsonValue = serde_json::from_str(&hit.json)?;
+    assert_eq!(
+        hit_json,
+        json!({"body": "service unavailable", "status": 503})
+    );
+    let hit_fields: JsonValue = serde_json::from_str(hit.fields_json.as_deref().unwrap())?;
+    assert_eq!(hit_fields, json!({"status_class": [5]}));
+
+    // Runtime fields can only read fast fields.
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("*", &[]),
+        runtime_mappings: Some(
+            r#"{"upper_body": {"type": "keyword", "script": "upcase!(.body)"}}"#.to_string(),
+        ),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let error = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("reads field `body`, which is not a fast field"));
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_termset() -> anyhow::Result<()> {
     let index_id = "single-node-termset-1";
@@ -183,6 +251,60 @@ async fn test_single_search_with_snippet() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_search_with_snippet_options() -> anyhow::Result<()> {
+    let index_id = "single-node-with-snippet-options";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+              - name: category
+                type: text
+                stored: false
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let docs = vec![
+        json!({"title": "beagle", "body": "The beagle is a breed of small scent hound.", "category": "beagle"}),
+        json!({"title": "lisa", "body": "Lisa is a character in `The Simpsons` animated tv series."}),
+    ];
+    test_sandbox.add_documents(docs.clone()).await?;
+    let search_request = SearchRequest {
+        index_id_patterns: vec![index_id.to_string()],
+        query_ast: qast_json_helper("beagle", &["title", "body"]),
+        snippet_fields: vec!["*".to_string()],
+        snippet_options: Some(SnippetOptions {
+            pre_tag: Some("<em>".to_string()),
+            post_tag: Some("</em>".to_string()),
+            max_num_chars: None,
+            max_num_snippets: Some(1),
+        }),
+        max_hits: 2,
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        search_request,
+        test_sandbox.metastore(),
+        test_sandbox.storage_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 1);
+    assert_eq!(single_node_result.hits.len(), 1);
+
+    let highlight_json: JsonValue =
+        serde_json::from_str(single_node_result.hits[0].snippet.as_ref().unwrap())?;
+    // `category` is not stored, so it does not match the `*` pattern.
+    let expected_json: JsonValue = json!({
+        "title": ["<em>beagle</em>"],
+        "body": ["The <em>beagle</em> is a breed of small scent hound"]
+    });
+    assert_json_eq!(highlight_json, expected_json);
+
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 async fn slop_search_and_check(
     test_sandbox: &TestSandbox,
     index_id: &str,
@@ -910,8 +1032,8 @@ async fn test_sort_by_2_field() {
 }
 
 #[tokio::test]
-async fn test_single_node_invalid_sorting_with_query() {
-    let index_id = "single-node-invalid-sorting";
+async fn test_single_node_sorting_with_keyword_field() {
+    let index_id = "single-node-keyword-sorting";
     let doc_mapping_yaml = r#"
             field_mappings:
               - name: description
@@ -943,17 +1065,59 @@ async fn test_single_node_invalid_sorting_with_query() {
         ..Default::default()
     };
     let single_node_response = single_node_search(
-        search_request,
+        search_request.clone(),
         test_sandbox.metastore(),
         test_sandbox.storage_resolver(),
     )
-    .await;
-    assert!(single_node_response.is_err());
-    let error_msg = single_node_response.unwrap_err().to_string();
+    .await
+    .unwrap();
+    let mut expected_descriptions: Vec<String> =
+        (1..=30).map(|i| format!("city info-{i}")).collect();
+    expected_descriptions.sort_by(|left, right| right.cmp(left));
+    let descriptions = |hits: &[Hit]| -> Vec<String> {
+        hits.iter()
+            .map(|hit| {
+                let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+                hit_json["description"].as_str().unwrap().to_string()
+            })
+            .collect()
+    };
+    assert_eq!(single_node_response.num_hits, 30);
     assert_eq!(
-        error_msg,
-        "Invalid argument: sort by field on type text is currently not supported `description`"
+        descriptions(&single_node_response.hits),
+        &expected_descriptions[..15]
     );
+    let fifth_hit = single_node_response.hits[4].partial_hit.clone().unwrap();
+    assert_eq!(
+        fifth_hit.sort_value(),
+        Some(SortValue::Str(expected_descriptions[4].clone()))
+    );
+
+    // The sort values of the hits can be used as `search_after`, also when the value does not
+    // exist in the index.
+    for (search_after_value, expected_start) in [
+        (expected_descriptions[4].clone(), 5),
+        (format!("{}a", expected_descriptions[4]), 4),
+    ] {
+        let search_request = SearchRequest {
+            search_after: Some(PartialHit {
+                sort_value: Some(SortValue::Str(search_after_value).into()),
+                ..Default::default()
+            }),
+            ..search_request.clone()
+        };
+        let single_node_response = single_node_search(
+            search_request,
+            test_sandbox.metastore(),
+            test_sandbox.storage_resolver(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            descriptions(&single_node_response.hits),
+            &expected_descriptions[expected_start..expected_start + 15]
+        );
+    }
     test_sandbox.assert_quit().await;
 }
 
@@ -1205,7 +1369,7 @@ fn test_convert_leaf_hit_aux(
     let default_doc_mapper: DocMapper = serde_json::from_value(default_doc_mapper_json).unwrap();
     let named_field_doc = json_to_named_field_doc(document_json);
     let hit_json_str =
-        convert_document_to_json_string(named_field_doc, &default_doc_mapper).unwrap();
+        convert_document_to_json_string(named_field_doc, &default_doc_mapper, &[]).unwrap();
     let hit_json: JsonValue = serde_json::from_str(&hit_json_str).unwrap();
     assert_eq!(hit_json, expected_hit_json);
 }
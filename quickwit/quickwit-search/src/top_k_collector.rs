@@ -18,13 +18,16 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
-use quickwit_common::binary_heap::TopK;
+use quickwit_common::binary_heap::{SortKeyMapper, TopK};
 use quickwit_proto::search::{PartialHit, SortOrder};
 use quickwit_proto::types::SplitId;
-use tantivy::{DocId, Score};
+use tantivy::columnar::StrColumn;
+use tantivy::{DocId, Score, SegmentReader};
 
 use crate::collector::{
     HitSortingMapper, SegmentPartialHit, SegmentPartialHitSortingKey,
@@ -187,6 +190,7 @@ impl<
         SegmentPartialHit {
             sort_value: self.value1.into_option_u64(),
             sort_value2: self.value2.into_option_u64(),
+            other_sort_values: Vec::new(),
             doc_id: self.doc_id,
         }
     }
@@ -198,22 +202,28 @@ pub fn specialized_top_k_segment_collector(
     leaf_max_hits: usize,
     segment_ord: u32,
     search_after_option: Option<PartialHit>,
-    order1: SortOrder,
-    order2: SortOrder,
+    sort_key_mapper: HitSortingMapper,
+    collapser_opt: Option<SegmentCollapser>,
 ) -> Box<dyn QuickwitSegmentTopKCollector> {
     // TODO: Add support for search_after to the specialized collector.
     // Eventually we may want to remove the generic collector to reduce complexity.
-    if search_after_option.is_some() || score_extractor.is_score() {
+    // The specialized collector only handles up to two sorting keys and doesn't collapse hits.
+    if search_after_option.is_some()
+        || score_extractor.is_score()
+        || !score_extractor.others.is_empty()
+        || collapser_opt.is_some()
+    {
         return Box::new(GenericQuickwitSegmentTopKCollector::new(
             split_id,
             score_extractor,
             leaf_max_hits,
             segment_ord,
             search_after_option,
-            order1,
-            order2,
+            sort_key_mapper,
+            collapser_opt,
         ));
     }
+    let HitSortingMapper { order1, order2, .. } = sort_key_mapper;
 
     let sort_first_by_ff = score_extractor.first.is_fast_field();
     let sort_second_by_ff = score_extractor
@@ -606,6 +616,7 @@ impl<
                     self.segment_ord,
                     &self.hit_fetcher.first,
                     &self.hit_fetcher.second,
+                    &[],
                 )
             })
             .collect()
@@ -624,6 +635,8 @@ pub(crate) struct GenericQuickwitSegmentTopKCollector {
     precomp_search_after_order: Ordering,
     sort_values1: Box<[Option<u64>; COLLECT_BLOCK_BUFFER_LEN]>,
     sort_values2: Box<[Option<u64>; COLLECT_BLOCK_BUFFER_LEN]>,
+    // If set, hits are collected in the collapser instead of `top_k_hits`.
+    collapser: Option<SegmentCollapser>,
 }
 
 impl GenericQuickwitSegmentTopKCollector {
@@ -633,10 +646,10 @@ impl GenericQuickwitSegmentTopKCollector {
         leaf_max_hits: usize,
         segment_ord: u32,
         search_after_option: Option<PartialHit>,
-        order1: SortOrder,
-        order2: SortOrder,
+        sort_key_mapper: HitSortingMapper,
+        collapser: Option<SegmentCollapser>,
     ) -> Self {
-        let sort_key_mapper = HitSortingMapper { order1, order2 };
+        let order1 = sort_key_mapper.order1;
         let precomp_search_after_order = match &search_after_option {
             Some(search_after) if !search_after.split_id.is_empty() => order1
                 .compare(&split_id, &search_after.split_id)
@@ -645,7 +658,7 @@ impl GenericQuickwitSegmentTopKCollector {
             _ => Ordering::Equal,
         };
         let search_after =
-            SearchAfterSegment::new(search_after_option, order1, order2, &score_extractor);
+            SearchAfterSegment::new(search_after_option, &sort_key_mapper, &score_extractor);
 
         GenericQuickwitSegmentTopKCollector {
             split_id,
@@ -662,19 +675,19 @@ impl GenericQuickwitSegmentTopKCollector {
                 .into_boxed_slice()
                 .try_into()
                 .unwrap(),
+            collapser,
         }
     }
     #[inline]
-    /// Generic top k collection, that includes search_after handling
+    /// Generic top k collection, that includes search_after and collapse handling
     ///
     /// Outside of the collector to circumvent lifetime issues.
     fn collect_top_k_vals(
-        doc_id: DocId,
-        sort_value: Option<u64>,
-        sort_value2: Option<u64>,
+        hit: SegmentPartialHit,
         search_after: &Option<SearchAfterSegment>,
         precomp_search_after_order: Ordering,
         top_k_hits: &mut TopK<SegmentPartialHit, SegmentPartialHitSortingKey, HitSortingMapper>,
+        collapser: &mut Option<SegmentCollapser>,
     ) {
         if let Some(search_after) = &search_after {
             let search_after_value1 = search_after.sort_value;
@@ -682,11 +695,23 @@ impl GenericQuickwitSegmentTopKCollector {
             let orders = &top_k_hits.sort_key_mapper;
             let mut cmp_result = orders
                 .order1
-                .compare_opt(&sort_value, &search_after_value1)
+                .compare_opt(&hit.sort_value, &search_after_value1)
                 .then_with(|| {
                     orders
                         .order2
-                        .compare_opt(&sort_value2, &search_after_value2)
+                        .compare_opt(&hit.sort_value2, &search_after_value2)
+                })
+                .then_with(|| {
+                    orders
+                        .other_orders
+                        .iter()
+                        .zip(&hit.other_sort_values)
+                        .zip(&search_after.other_sort_values)
+                        .map(|((order, sort_value), search_after_value)| {
+                            order.compare_opt(sort_value, search_after_value)
+                        })
+                        .find(|order| order.is_ne())
+                        .unwrap_or(Ordering::Equal)
                 });
             if search_after.compare_on_equal {
                 // TODO actually it's not first, it should be what's in _shard_doc then first then
@@ -696,19 +721,17 @@ impl GenericQuickwitSegmentTopKCollector {
                     .then(precomp_search_after_order)
                     // We compare doc_id only if sort_value1, sort_value2, split_id and segment_ord
                     // are equal.
-                    .then_with(|| order.compare(&doc_id, &search_after.doc_id))
+                    .then_with(|| order.compare(&hit.doc_id, &search_after.doc_id))
             }
 
             if cmp_result != Ordering::Less {
                 return;
             }
         }
-
-        let hit = SegmentPartialHit {
-            sort_value,
-            sort_value2,
-            doc_id,
-        };
+        if let Some(collapser) = collapser {
+            collapser.add_hit(hit, &top_k_hits.sort_key_mapper);
+            return;
+        }
         top_k_hits.add_entry(hit);
     }
 }
@@ -719,21 +742,34 @@ impl QuickwitSegmentTopKCollector for GenericQuickwitSegmentTopKCollector {
             &mut self.sort_values1[..],
             &mut self.sort_values2[..],
         );
-        if self.search_after.is_some() {
-            // Search after not optimized for block collection yet
+        if self.search_after.is_some()
+            || !self.score_extractor.others.is_empty()
+            || self.collapser.is_some()
+        {
+            // Search after, sorting by more than two keys and collapsing are not optimized for
+            // block collection yet
             for ((doc_id, sort_value), sort_value2) in docs
                 .iter()
                 .cloned()
                 .zip(self.sort_values1.iter().cloned())
                 .zip(self.sort_values2.iter().cloned())
             {
-                Self::collect_top_k_vals(
-                    doc_id,
+                // There are no scores in the block collection case.
+                let other_sort_values = self
+                    .score_extractor
+                    .extract_other_typed_sort_values(doc_id, 0.0);
+                let hit = SegmentPartialHit {
                     sort_value,
                     sort_value2,
+                    other_sort_values,
+                    doc_id,
+                };
+                Self::collect_top_k_vals(
+                    hit,
                     &self.search_after,
                     self.precomp_search_after_order,
                     &mut self.top_k_hits,
+                    &mut self.collapser,
                 );
             }
         } else {
@@ -748,6 +784,7 @@ impl QuickwitSegmentTopKCollector for GenericQuickwitSegmentTopKCollector {
                     let hit = SegmentPartialHit {
                         sort_value: None,
                         sort_value2: None,
+                        other_sort_values: Vec::new(),
                         doc_id,
                     };
                     self.top_k_hits.add_entry(hit);
@@ -768,6 +805,7 @@ impl QuickwitSegmentTopKCollector for GenericQuickwitSegmentTopKCollector {
                     let hit = SegmentPartialHit {
                         sort_value,
                         sort_value2: None,
+                        other_sort_values: Vec::new(),
                         doc_id,
                     };
                     self.top_k_hits.add_entry(hit);
@@ -784,6 +822,7 @@ impl QuickwitSegmentTopKCollector for GenericQuickwitSegmentTopKCollector {
                 let hit = SegmentPartialHit {
                     sort_value,
                     sort_value2,
+                    other_sort_values: Vec::new(),
                     doc_id,
                 };
                 self.top_k_hits.add_entry(hit);
@@ -795,48 +834,122 @@ impl QuickwitSegmentTopKCollector for GenericQuickwitSegmentTopKCollector {
     fn collect_top_k(&mut self, doc_id: DocId, score: Score) {
         let (sort_value, sort_value2): (Option<u64>, Option<u64>) =
             self.score_extractor.extract_typed_sort_value(doc_id, score);
-        Self::collect_top_k_vals(
-            doc_id,
+        let other_sort_values = self
+            .score_extractor
+            .extract_other_typed_sort_values(doc_id, score);
+        let hit = SegmentPartialHit {
             sort_value,
             sort_value2,
+            other_sort_values,
+            doc_id,
+        };
+        Self::collect_top_k_vals(
+            hit,
             &self.search_after,
             self.precomp_search_after_order,
             &mut self.top_k_hits,
+            &mut self.collapser,
         );
     }
 
     fn get_top_k(&self) -> Vec<PartialHit> {
-        self.top_k_hits
-            .clone()
+        let mut top_k_hits = self.top_k_hits.clone();
+        if let Some(collapser) = &self.collapser {
+            top_k_hits.add_entries(collapser.best_hits.values().map(|(_, hit)| hit.clone()));
+        }
+        top_k_hits
             .finalize()
             .into_iter()
             .map(|segment_partial_hit: SegmentPartialHit| {
-                segment_partial_hit.into_partial_hit(
+                let collapse_value = self
+                    .collapser
+                    .as_ref()
+                    .and_then(|collapser| collapser.collapse_value(segment_partial_hit.doc_id));
+                let mut partial_hit = segment_partial_hit.into_partial_hit(
                     self.split_id.clone(),
                     self.segment_ord,
                     &self.score_extractor.first,
                     &self.score_extractor.second,
-                )
+                    &self.score_extractor.others,
+                );
+                partial_hit.collapse_value = collapse_value;
+                partial_hit
             })
             .collect()
     }
 }
 
+/// Keeps the best hit of a segment for each distinct value of a text fast field.
+///
+/// Documents without a value for the field are collapsed together.
+pub(crate) struct SegmentCollapser {
+    // `None` if no document of the segment has a value for the field.
+    str_column_opt: Option<StrColumn>,
+    // Best hit for each term ordinal of the field, along with its sort key.
+    best_hits: HashMap<Option<u64>, (SegmentPartialHitSortingKey, SegmentPartialHit)>,
+}
+
+impl SegmentCollapser {
+    pub fn for_segment(
+        collapse_field: &str,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let str_column_opt = segment_reader.fast_fields().str(collapse_field)?;
+        Ok(SegmentCollapser {
+            str_column_opt,
+            best_hits: HashMap::new(),
+        })
+    }
+
+    fn term_ord(&self, doc_id: DocId) -> Option<u64> {
+        // Multivalued documents are collapsed on their first value.
+        self.str_column_opt
+            .as_ref()
+            .and_then(|str_column| str_column.term_ords(doc_id).next())
+    }
+
+    fn add_hit(&mut self, hit: SegmentPartialHit, sort_key_mapper: &HitSortingMapper) {
+        let sort_key = sort_key_mapper.get_sort_key(&hit);
+        match self.best_hits.entry(self.term_ord(hit.doc_id)) {
+            Entry::Occupied(mut entry) => {
+                if sort_key > entry.get().0 {
+                    entry.insert((sort_key, hit));
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((sort_key, hit));
+            }
+        }
+    }
+
+    fn collapse_value(&self, doc_id: DocId) -> Option<String> {
+        let term_ord = self.term_ord(doc_id)?;
+        let str_column = self.str_column_opt.as_ref()?;
+        let mut collapse_value = String::new();
+        str_column
+            .ord_to_str(term_ord, &mut collapse_value)
+            .expect("failed to lookup collapse value in the column term dictionary");
+        Some(collapse_value)
+    }
+}
+
 /// Search After, but the sort values are converted to the u64 fast field representation.
 pub(crate) struct SearchAfterSegment {
     sort_value: Option<u64>,
     sort_value2: Option<u64>,
+    other_sort_values: Vec<Option<u64>>,
     compare_on_equal: bool,
     doc_id: DocId,
 }
 impl SearchAfterSegment {
     pub fn new(
         search_after_opt: Option<PartialHit>,
-        sort_order1: SortOrder,
-        sort_order2: SortOrder,
+        sort_key_mapper: &HitSortingMapper,
         score_extractor: &SortingFieldExtractorPair,
     ) -> Option<Self> {
         let search_after = search_after_opt?;
+        let sort_order1 = sort_key_mapper.order1;
+        let sort_order2 = sort_key_mapper.order2;
         let mut sort_value = None;
         if let Some(search_after_sort_value) = search_after
             .sort_value
@@ -868,9 +981,23 @@ impl SearchAfterSegment {
                 sort_value2 = Some(new_value);
             }
         }
+        let other_sort_values = score_extractor
+            .others
+            .iter()
+            .zip(&sort_key_mapper.other_orders)
+            .enumerate()
+            .map(|(idx, (extractor, sort_order))| {
+                let search_after_sort_value = search_after
+                    .extra_sort_values
+                    .get(idx)
+                    .and_then(|sort_value| sort_value.sort_value.clone())?;
+                extractor.convert_to_u64_ff_val(search_after_sort_value, *sort_order)
+            })
+            .collect();
         Some(Self {
             sort_value,
             sort_value2,
+            other_sort_values,
             compare_on_equal: !search_after.split_id.is_empty(),
             doc_id: search_after.doc_id,
         })
@@ -0,0 +1,817 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use hyper::header::AUTHORIZATION;
+use hyper::HeaderMap;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use lru::LruCache;
+use quickwit_config::{AuthConfig, JwtAuthConfig, RoleConfig};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use super::{AuthError, Principal};
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Number of successfully verified basic credentials kept in memory to avoid running the
+/// (purposefully slow) bcrypt verification on every request.
+const VERIFIED_BASIC_CREDENTIALS_CACHE_CAPACITY: usize = 1_000;
+
+/// Authenticates requests using the methods configured in the `auth` section of the node config.
+pub(crate) struct Authenticator {
+    principals_by_api_key: HashMap<String, Principal>,
+    basic_users_opt: Option<HashMap<String, BasicUser>>,
+    // Verified in place of the password hash of unknown users, so that authenticating an unknown
+    // user costs as much as authenticating a known one and does not reveal which users exist.
+    dummy_password_hash: String,
+    jwt_verifier_opt: Option<JwtVerifier>,
+    roles: HashMap<String, RoleConfig>,
+    // Keyed by the SHA-256 digest of the credentials, so that passwords are not kept in memory.
+    verified_basic_credentials: Mutex<LruCache<[u8; 32], Principal>>,
+}
+
+struct BasicUser {
+    password_hash: String,
+    principal: Principal,
+}
+
+struct JwtVerifier {
+    keys: Vec<JwtKey>,
+    issuer_opt: Option<String>,
+    audience_opt: Option<String>,
+    roles_claim: String,
+}
+
+struct JwtKey {
+    key_id_opt: Option<String>,
+    algorithm_opt: Option<Algorithm>,
+    decoding_key: DecodingKey,
+}
+
+enum Credentials<'a> {
+    ApiKey(&'a str),
+    Basic { username: String, password: String },
+    Bearer(&'a str),
+}
+
+impl Authenticator {
+    /// Builds an authenticator from the auth config, loading the credentials and JWKS files.
+    pub fn try_new(auth_config: &AuthConfig) -> anyhow::Result<Self> {
+        let roles: HashMap<String, RoleConfig> = auth_config
+            .roles
+            .iter()
+            .map(|role| (role.name.clone(), role.clone()))
+            .collect();
+        let mut principals_by_api_key = HashMap::with_capacity(auth_config.api_keys.len());
+
+        for api_key in &auth_config.api_keys {
+            let principal = build_principal(api_key.name.clone(), &api_key.roles, &roles)?;
+            principals_by_api_key.insert(api_key.key.clone(), principal);
+        }
+        let basic_users_opt = auth_config
+            .basic
+            .as_ref()
+            .map(|basic_config| load_basic_users(&basic_config.credentials_path, &roles))
+            .transpose()?;
+        let dummy_password_hash = match &basic_users_opt {
+            Some(basic_users) => dummy_password_hash(basic_users)?,
+            None => String::new(),
+        };
+        let jwt_verifier_opt = auth_config
+            .jwt
+            .as_ref()
+            .map(JwtVerifier::try_new)
+            .transpose()?;
+        let verified_basic_credentials = Mutex::new(LruCache::new(
+            NonZeroUsize::new(VERIFIED_BASIC_CREDENTIALS_CACHE_CAPACITY).unwrap(),
+        ));
+        let authenticator = Authenticator {
+            principals_by_api_key,
+            basic_users_opt,
+            dummy_password_hash,
+            jwt_verifier_opt,
+            roles,
+            verified_basic_credentials,
+        };
+        Ok(authenticator)
+    }
+
+    /// Returns the challenges sent in the `WWW-Authenticate` header of the 401 responses.
+    pub fn challenges(&self) -> Vec<&'static str> {
+        let mut challenges = Vec::new();
+
+        if self.basic_users_opt.is_some() {
+            challenges.push(r#"Basic realm="quickwit", charset="UTF-8""#);
+        }
+        if self.jwt_verifier_opt.is_some() || !self.principals_by_api_key.is_empty() {
+            challenges.push(r#"Bearer realm="quickwit""#);
+        }
+        challenges
+    }
+
+    pub async fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        match extract_credentials(headers)? {
+            Credentials::ApiKey(api_key) => self.authenticate_api_key(api_key),
+            Credentials::Basic { username, password } => {
+                self.authenticate_basic(username, password).await
+            }
+            Credentials::Bearer(token) => match &self.jwt_verifier_opt {
+                // Static API keys are also accepted as bearer tokens, which is the only kind of
+                // token many clients know how to send.
+                Some(jwt_verifier) if looks_like_jwt(token) => {
+                    jwt_verifier.verify(token, &self.roles)
+                }
+                _ => self.authenticate_api_key(token),
+            },
+        }
+    }
+
+    fn authenticate_api_key(&self, api_key: &str) -> Result<Principal, AuthError> {
+        self.principals_by_api_key
+            .get(api_key)
+            .cloned()
+            .ok_or_else(|| AuthError::InvalidCredentials("unknown API key".to_string()))
+    }
+
+    async fn authenticate_basic(
+        &self,
+        username: String,
+        password: String,
+    ) -> Result<Principal, AuthError> {
+        let Some(basic_users) = &self.basic_users_opt else {
+            return Err(AuthError::InvalidCredentials(
+                "basic authentication is not enabled".to_string(),
+            ));
+        };
+        let invalid_credentials_error =
+            || AuthError::InvalidCredentials("invalid username or password".to_string());
+
+        let Some(basic_user) = basic_users.get(&username) else {
+            verify_password(password, self.dummy_password_hash.clone()).await?;
+            return Err(invalid_credentials_error());
+        };
+        let credentials_digest: [u8; 32] = Sha256::new()
+            .chain_update(username.as_bytes())
+            .chain_update(b":")
+            .chain_update(password.as_bytes())
+            .finalize()
+            .into();
+
+        if let Some(principal) = self
+            .verified_basic_credentials
+            .lock()
+            .unwrap()
+            .get(&credentials_digest)
+        {
+            return Ok(principal.clone());
+        }
+        let is_valid = verify_password(password, basic_user.password_hash.clone()).await?;
+
+        if !is_valid {
+            return Err(invalid_credentials_error());
+        }
+        self.verified_basic_credentials
+            .lock()
+            .unwrap()
+            .put(credentials_digest, basic_user.principal.clone());
+        Ok(basic_user.principal.clone())
+    }
+}
+
+async fn verify_password(password: String, password_hash: String) -> Result<bool, AuthError> {
+    let is_valid = tokio::task::spawn_blocking(move || bcrypt::verify(password, &password_hash))
+        .await
+        .map_err(|join_error| {
+            AuthError::InvalidCredentials(format!("failed to verify password: {join_error}"))
+        })?
+        .unwrap_or(false);
+    Ok(is_valid)
+}
+
+/// Hashes a dummy password with the highest cost among the password hashes of the users.
+fn dummy_password_hash(basic_users: &HashMap<String, BasicUser>) -> anyhow::Result<String> {
+    let cost = basic_users
+        .values()
+        .filter_map(|basic_user| bcrypt_cost(&basic_user.password_hash))
+        .max()
+        .unwrap_or(bcrypt::DEFAULT_COST);
+    let dummy_password_hash =
+        bcrypt::hash("quickwit-dummy-password", cost).context("failed to hash dummy password")?;
+    Ok(dummy_password_hash)
+}
+
+/// Returns the cost of a bcrypt hash of the form `$<version>$<cost>$<salt and hash>`.
+fn bcrypt_cost(password_hash: &str) -> Option<u32> {
+    password_hash.split('$').nth(2)?.parse().ok()
+}
+
+fn extract_credentials(headers: &HeaderMap) -> Result<Credentials, AuthError> {
+    if let Some(api_key_header) = headers.get(API_KEY_HEADER) {
+        let api_key = api_key_header.to_str().map_err(|_| {
+            AuthError::InvalidCredentials(format!("`{API_KEY_HEADER}` header is not valid ASCII"))
+        })?;
+        return Ok(Credentials::ApiKey(api_key));
+    }
+    let Some(authorization_header) = headers.get(AUTHORIZATION) else {
+        return Err(AuthError::MissingCredentials);
+    };
+    let authorization = authorization_header.to_str().map_err(|_| {
+        AuthError::InvalidCredentials("`authorization` header is not valid ASCII".to_string())
+    })?;
+    let (scheme, value) = authorization
+        .split_once(' ')
+        .map(|(scheme, value)| (scheme, value.trim()))
+        .ok_or_else(|| {
+            AuthError::InvalidCredentials("malformed `authorization` header".to_string())
+        })?;
+
+    if scheme.eq_ignore_ascii_case("basic") {
+        let decoded_value = BASE64_STANDARD
+            .decode(value)
+            .ok()
+            .and_then(|decoded_value| String::from_utf8(decoded_value).ok())
+            .ok_or_else(|| {
+                AuthError::InvalidCredentials("malformed basic credentials".to_string())
+            })?;
+        let (username, password) = decoded_value.split_once(':').ok_or_else(|| {
+            AuthError::InvalidCredentials("malformed basic credentials".to_string())
+        })?;
+        Ok(Credentials::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    } else if scheme.eq_ignore_ascii_case("bearer") {
+        Ok(Credentials::Bearer(value))
+    } else if scheme.eq_ignore_ascii_case("apikey") {
+        Ok(Credentials::ApiKey(value))
+    } else {
+        Err(AuthError::InvalidCredentials(format!(
+            "unsupported authorization scheme `{scheme}`"
+        )))
+    }
+}
+
+fn looks_like_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+fn build_principal(
+    name: String,
+    role_names: &[String],
+    roles: &HashMap<String, RoleConfig>,
+) -> anyhow::Result<Principal> {
+    let mut principal_roles = Vec::with_capacity(role_names.len());
+
+    for role_name in role_names {
+        let Some(role) = roles.get(role_name) else {
+            bail!("principal `{name}` references unknown role `{role_name}`");
+        };
+        principal_roles.push(role.clone());
+    }
+    Ok(Principal::new(name, principal_roles))
+}
+
+/// Parses a credentials file. Each line has the form
+/// `<username>:<bcrypt hash>[:<role>,<role>...]`. Empty lines and lines starting with `#` are
+/// ignored.
+fn parse_basic_users(
+    credentials: &str,
+    roles: &HashMap<String, RoleConfig>,
+) -> anyhow::Result<HashMap<String, BasicUser>> {
+    let mut basic_users = HashMap::new();
+
+    for (line_idx, line) in credentials.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_number = line_idx + 1;
+        let mut parts = line.splitn(3, ':');
+
+        let username = parts.next().unwrap_or_default();
+        let password_hash = parts.next().unwrap_or_default();
+        let role_names: Vec<String> = parts
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|role_name| !role_name.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if username.is_empty() || password_hash.is_empty() {
+            bail!("malformed credentials on line {line_number}");
+        }
+        if !password_hash.starts_with("$2") {
+            bail!(
+                "unsupported password hash for user `{username}` on line {line_number}: only \
+                 bcrypt hashes are supported"
+            );
+        }
+        let principal = build_principal(username.to_string(), &role_names, roles)?;
+        let basic_user = BasicUser {
+            password_hash: password_hash.to_string(),
+            principal,
+        };
+        if basic_users
+            .insert(username.to_string(), basic_user)
+            .is_some()
+        {
+            bail!("user `{username}` is defined more than once");
+        }
+    }
+    Ok(basic_users)
+}
+
+fn load_basic_users(
+    credentials_path: &Path,
+    roles: &HashMap<String, RoleConfig>,
+) -> anyhow::Result<HashMap<String, BasicUser>> {
+    let credentials = std::fs::read_to_string(credentials_path).with_context(|| {
+        format!(
+            "failed to read credentials file `{}`",
+            credentials_path.display()
+        )
+    })?;
+    parse_basic_users(&credentials, roles).with_context(|| {
+        format!(
+            "failed to parse credentials file `{}`",
+            credentials_path.display()
+        )
+    })
+}
+
+impl JwtVerifier {
+    fn try_new(jwt_config: &JwtAuthConfig) -> anyhow::Result<Self> {
+        let jwks_json = std::fs::read(&jwt_config.jwks_path).with_context(|| {
+            format!(
+                "failed to read JWKS file `{}`",
+                jwt_config.jwks_path.display()
+            )
+        })?;
+        let jwk_set: JwkSet = serde_json::from_slice(&jwks_json).with_context(|| {
+            format!(
+                "failed to parse JWKS file `{}`",
+                jwt_config.jwks_path.display()
+            )
+        })?;
+        Self::from_jwk_set(jwk_set, jwt_config)
+    }
+
+    fn from_jwk_set(jwk_set: JwkSet, jwt_config: &JwtAuthConfig) -> anyhow::Result<Self> {
+        let mut keys = Vec::with_capacity(jwk_set.keys.len());
+
+        for jwk in &jwk_set.keys {
+            let key_id_opt = jwk.common.key_id.clone();
+            let key_id = key_id_opt.as_deref().unwrap_or("<none>");
+
+            // Key sets often include encryption keys, which cannot be used to verify signatures.
+            let algorithm_opt = match jwk.common.key_algorithm {
+                Some(key_algorithm) => {
+                    let Ok(algorithm) = Algorithm::from_str(&key_algorithm.to_string()) else {
+                        warn!(
+                            key_id=%key_id,
+                            algorithm=%key_algorithm,
+                            "skipping JSON web key with unsupported algorithm"
+                        );
+                        continue;
+                    };
+                    Some(algorithm)
+                }
+                None => None,
+            };
+            let decoding_key = DecodingKey::from_jwk(jwk)
+                .with_context(|| format!("invalid JSON web key `{key_id}`"))?;
+            keys.push(JwtKey {
+                key_id_opt,
+                algorithm_opt,
+                decoding_key,
+            });
+        }
+        if keys.is_empty() {
+            bail!("JWKS must contain at least one key");
+        }
+        Ok(JwtVerifier {
+            keys,
+            issuer_opt: jwt_config.issuer.clone(),
+            audience_opt: jwt_config.audience.clone(),
+            roles_claim: jwt_config.roles_claim.clone(),
+        })
+    }
+
+    fn verify(
+        &self,
+        token: &str,
+        roles: &HashMap<String, RoleConfig>,
+    ) -> Result<Principal, AuthError> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|error| AuthError::InvalidCredentials(format!("invalid JWT: {error}")))?;
+
+        let key = match &header.kid {
+            Some(key_id) => self
+                .keys
+                .iter()
+                .find(|key| key.key_id_opt.as_ref() == Some(key_id)),
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| {
+            AuthError::InvalidCredentials("JWT is not signed by a known key".to_string())
+        })?;
+
+        if key
+            .algorithm_opt
+            .is_some_and(|algorithm| algorithm != header.alg)
+        {
+            return Err(AuthError::InvalidCredentials(
+                "JWT algorithm does not match the algorithm of the key".to_string(),
+            ));
+        }
+        let mut validation = Validation::new(header.alg);
+
+        if let Some(issuer) = &self.issuer_opt {
+            validation.set_issuer(&[issuer]);
+            validation.required_spec_claims.insert("iss".to_string());
+        }
+        if let Some(audience) = &self.audience_opt {
+            validation.set_audience(&[audience]);
+            validation.required_spec_claims.insert("aud".to_string());
+        } else {
+            validation.validate_aud = false;
+        }
+        let claims = jsonwebtoken::decode::<JsonMap<String, JsonValue>>(
+            token,
+            &key.decoding_key,
+            &validation,
+        )
+        .map_err(|error| AuthError::InvalidCredentials(format!("invalid JWT: {error}")))?
+        .claims;
+
+        let name = claims
+            .get("sub")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("anonymous")
+            .to_string();
+        let principal_roles = find_claim(&claims, &self.roles_claim)
+            .map(role_names_from_claim)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|role_name| {
+                let role_opt = roles.get(role_name).cloned();
+                if role_opt.is_none() {
+                    debug!(principal=%name, role=%role_name, "ignoring unknown role");
+                }
+                role_opt
+            })
+            .collect();
+        Ok(Principal::new(name, principal_roles))
+    }
+}
+
+/// Finds a claim by name or, for nested claims, by dot-separated path
+/// (e.g. `realm_access.roles`).
+fn find_claim<'a>(claims: &'a JsonMap<String, JsonValue>, claim: &str) -> Option<&'a JsonValue> {
+    if let Some(claim_value) = claims.get(claim) {
+        return Some(claim_value);
+    }
+    let mut path = claim.split('.');
+    let mut claim_value = claims.get(path.next()?)?;
+
+    for key in path {
+        claim_value = claim_value.as_object()?.get(key)?;
+    }
+    Some(claim_value)
+}
+
+fn role_names_from_claim(claim_value: &JsonValue) -> Vec<&str> {
+    match claim_value {
+        JsonValue::String(role_names) => role_names.split_whitespace().collect(),
+        JsonValue::Array(role_names) => role_names.iter().filter_map(JsonValue::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use hyper::header::HeaderValue;
+    use jsonwebtoken::{EncodingKey, Header};
+    use quickwit_config::{ApiKeyConfig, AuthPrivilege, BasicAuthConfig};
+    use serde_json::json;
+
+    use super::*;
+
+    const JWT_SECRET: &[u8] = b"my-jwt-signing-secret";
+
+    fn auth_config_for_test() -> AuthConfig {
+        AuthConfig {
+            api_keys: vec![ApiKeyConfig {
+                name: "ingest-bot".to_string(),
+                key: "my-secret-key".to_string(),
+                roles: vec!["logs-writer".to_string()],
+            }],
+            roles: vec![
+                RoleConfig {
+                    name: "logs-writer".to_string(),
+                    index_patterns: vec!["logs-*".to_string()],
+                    privileges: vec![AuthPrivilege::Ingest],
+                },
+                RoleConfig {
+                    name: "reader".to_string(),
+                    index_patterns: vec!["*".to_string()],
+                    privileges: vec![AuthPrivilege::Read],
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn headers(header_name: &'static str, header_value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header_name, HeaderValue::from_str(header_value).unwrap());
+        headers
+    }
+
+    fn jwt_config_for_test() -> JwtAuthConfig {
+        JwtAuthConfig {
+            jwks_path: "jwks.json".into(),
+            issuer: Some("https://auth.example.com".to_string()),
+            audience: None,
+            roles_claim: "realm_access.roles".to_string(),
+        }
+    }
+
+    fn jwk_set_for_test() -> JwkSet {
+        serde_json::from_value(json!({
+            "keys": [{
+                "kty": "oct",
+                "kid": "test-key",
+                "alg": "HS256",
+                "k": BASE64_URL_SAFE_NO_PAD.encode(JWT_SECRET),
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn encode_jwt(claims: JsonValue, key_id: &str) -> String {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(key_id.to_string());
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(JWT_SECRET)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_api_key() {
+        let authenticator = Authenticator::try_new(&auth_config_for_test()).unwrap();
+
+        let principal = authenticator
+            .authenticate(&headers("x-api-key", "my-secret-key"))
+            .await
+            .unwrap();
+        assert_eq!(principal.name(), "ingest-bot");
+        principal
+            .authorize(AuthPrivilege::Ingest, &["logs-app"])
+            .unwrap();
+
+        for authorization in ["Bearer my-secret-key", "ApiKey my-secret-key"] {
+            let principal = authenticator
+                .authenticate(&headers("authorization", authorization))
+                .await
+                .unwrap();
+            assert_eq!(principal.name(), "ingest-bot");
+        }
+        let error = authenticator
+            .authenticate(&headers("x-api-key", "my-wrong-key"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AuthError::InvalidCredentials(_)));
+
+        let error = authenticator
+            .authenticate(&HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AuthError::MissingCredentials));
+
+        let error = authenticator
+            .authenticate(&headers("authorization", "Digest username=foo"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid authentication credentials: unsupported authorization scheme `Digest`"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_basic() {
+        let password_hash = bcrypt::hash("my-password", 4).unwrap();
+        let mut credentials_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(credentials_file, "# username:hash:roles").unwrap();
+        writeln!(credentials_file, "alice:{password_hash}:reader").unwrap();
+        writeln!(credentials_file, "bob:{password_hash}").unwrap();
+        credentials_file.flush().unwrap();
+
+        let auth_config = AuthConfig {
+            basic: Some(BasicAuthConfig {
+                credentials_path: credentials_file.path().to_path_buf(),
+            }),
+            ..auth_config_for_test()
+        };
+        let authenticator = Authenticator::try_new(&auth_config).unwrap();
+        assert_eq!(
+            authenticator.challenges(),
+            [
+                r#"Basic realm="quickwit", charset="UTF-8""#,
+                r#"Bearer realm="quickwit""#
+            ]
+        );
+        let basic_credentials = |username: &str, password: &str| {
+            let encoded_credentials = BASE64_STANDARD.encode(format!("{username}:{password}"));
+            headers("authorization", &format!("Basic {encoded_credentials}"))
+        };
+        // The second authentication hits the cache of verified credentials.
+        for _ in 0..2 {
+            let principal = authenticator
+                .authenticate(&basic_credentials("alice", "my-password"))
+                .await
+                .unwrap();
+            assert_eq!(principal.name(), "alice");
+            principal
+                .authorize(AuthPrivilege::Read, &["logs-app"])
+                .unwrap();
+        }
+        let principal = authenticator
+            .authenticate(&basic_credentials("bob", "my-password"))
+            .await
+            .unwrap();
+        principal
+            .authorize(AuthPrivilege::Read, &["logs-app"])
+            .unwrap_err();
+
+        let error = authenticator
+            .authenticate(&basic_credentials("alice", "my-wrong-password"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid authentication credentials: invalid username or password"
+        );
+        let error = authenticator
+            .authenticate(&basic_credentials("carol", "my-password"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid authentication credentials: invalid username or password"
+        );
+        assert_eq!(bcrypt_cost(&authenticator.dummy_password_hash), Some(4));
+    }
+
+    #[test]
+    fn test_bcrypt_cost() {
+        let password_hash = bcrypt::hash("my-password", 5).unwrap();
+        assert_eq!(bcrypt_cost(&password_hash), Some(5));
+        assert_eq!(bcrypt_cost("not-a-hash"), None);
+    }
+
+    #[test]
+    fn test_parse_basic_users() {
+        let roles = auth_config_for_test()
+            .roles
+            .into_iter()
+            .map(|role| (role.name.clone(), role))
+            .collect();
+        let error = parse_basic_users("alice:secret", &roles).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "unsupported password hash for user `alice` on line 1: only bcrypt hashes are \
+             supported"
+        );
+        let error = parse_basic_users("\nalice", &roles).err().unwrap();
+        assert_eq!(error.to_string(), "malformed credentials on line 2");
+
+        let error = parse_basic_users("alice:$2y$05$hash:admin", &roles)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "principal `alice` references unknown role `admin`"
+        );
+        let basic_users =
+            parse_basic_users("alice:$2y$05$hash:reader, logs-writer\n", &roles).unwrap();
+        assert_eq!(basic_users["alice"].principal.roles.len(), 2);
+    }
+
+    #[test]
+    fn test_jwt_verifier() {
+        let roles: HashMap<String, RoleConfig> = auth_config_for_test()
+            .roles
+            .into_iter()
+            .map(|role| (role.name.clone(), role))
+            .collect();
+        let jwt_verifier =
+            JwtVerifier::from_jwk_set(jwk_set_for_test(), &jwt_config_for_test()).unwrap();
+        let expires_at = jsonwebtoken::get_current_timestamp() + 3_600;
+
+        let token = encode_jwt(
+            json!({
+                "sub": "alice",
+                "iss": "https://auth.example.com",
+                "exp": expires_at,
+                "realm_access": { "roles": ["reader", "unknown-role"] }
+            }),
+            "test-key",
+        );
+        assert!(looks_like_jwt(&token));
+        let principal = jwt_verifier.verify(&token, &roles).unwrap();
+        assert_eq!(principal.name(), "alice");
+        assert_eq!(principal.roles.len(), 1);
+        principal
+            .authorize(AuthPrivilege::Read, &["logs-app"])
+            .unwrap();
+
+        let token = encode_jwt(
+            json!({
+                "sub": "alice",
+                "iss": "https://auth.example.com",
+                "exp": expires_at,
+            }),
+            "unknown-key",
+        );
+        let error = jwt_verifier.verify(&token, &roles).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid authentication credentials: JWT is not signed by a known key"
+        );
+        let token = encode_jwt(
+            json!({
+                "sub": "alice",
+                "iss": "https://evil.example.com",
+                "exp": expires_at,
+            }),
+            "test-key",
+        );
+        jwt_verifier.verify(&token, &roles).unwrap_err();
+
+        let token = encode_jwt(
+            json!({
+                "sub": "alice",
+                "iss": "https://auth.example.com",
+                "exp": expires_at - 7_200,
+            }),
+            "test-key",
+        );
+        jwt_verifier.verify(&token, &roles).unwrap_err();
+    }
+
+    #[test]
+    fn test_find_claim() {
+        let claims = json!({
+            "roles": "reader writer",
+            "https://example.com/roles": ["admin"],
+            "realm_access": { "roles": ["reader"] },
+        });
+        let claims = claims.as_object().unwrap();
+        assert_eq!(
+            role_names_from_claim(find_claim(claims, "roles").unwrap()),
+            ["reader", "writer"]
+        );
+        assert_eq!(
+            role_names_from_claim(find_claim(claims, "https://example.com/roles").unwrap()),
+            ["admin"]
+        );
+        assert_eq!(
+            role_names_from_claim(find_claim(claims, "realm_access.roles").unwrap()),
+            ["reader"]
+        );
+        assert!(find_claim(claims, "realm_access.groups").is_none());
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Authentication and authorization of the REST API.
+//!
+//! Requests are authenticated by the [`AuthLayer`] before reaching the warp routes. The layer
+//! also checks the privileges required by the route, which it derives from the request method and
+//! path (see [`route_access`]). The authenticated [`Principal`] is inserted into the request
+//! extensions so that the handlers targeting indexes listed in the request body (`_bulk`,
+//! `_msearch`) can authorize the request themselves.
+
+mod authenticator;
+mod route_access;
+
+use std::fmt;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+pub(crate) use authenticator::Authenticator;
+use futures::future::BoxFuture;
+use hyper::header::{HeaderValue, WWW_AUTHENTICATE};
+use hyper::{Body, Request, Response, StatusCode};
+use quickwit_config::{AuthPrivilege, RoleConfig};
+use tower::{Layer, Service};
+use warp::Reply;
+
+use self::route_access::route_access;
+use crate::rest_api_response::{RestApiError, RestApiResponse};
+use crate::BodyFormat;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub(crate) enum AuthError {
+    #[error("missing authentication credentials")]
+    MissingCredentials,
+    #[error("invalid authentication credentials: {0}")]
+    InvalidCredentials(String),
+    #[error("{0}")]
+    Forbidden(String),
+}
+
+impl AuthError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::MissingCredentials | AuthError::InvalidCredentials(_) => {
+                StatusCode::UNAUTHORIZED
+            }
+            AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+/// An authenticated API key, user, or token bearer, along with the roles granted to it.
+#[derive(Clone)]
+pub(crate) struct Principal {
+    name: String,
+    roles: Arc<[RoleConfig]>,
+}
+
+impl fmt::Debug for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Principal")
+            .field("name", &self.name)
+            .field(
+                "roles",
+                &self.roles.iter().map(|role| &role.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Principal {
+    pub fn new(name: String, roles: Vec<RoleConfig>) -> Self {
+        Principal {
+            name,
+            roles: roles.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Checks that the roles of the principal grant `privilege` on all the indexes matched by
+    /// the index ID patterns.
+    pub fn authorize<S: AsRef<str>>(
+        &self,
+        privilege: AuthPrivilege,
+        index_id_patterns: &[S],
+    ) -> Result<(), AuthError> {
+        for index_id_pattern in index_id_patterns {
+            let index_id_pattern = index_id_pattern.as_ref();
+
+            // Exclusion patterns can only narrow down the set of targeted indexes.
+            if index_id_pattern.starts_with('-') {
+                continue;
+            }
+            let index_id_pattern = if index_id_pattern == "_all" {
+                "*"
+            } else {
+                index_id_pattern
+            };
+            let is_authorized = self.roles.iter().any(|role| {
+                role.privileges
+                    .iter()
+                    .any(|granted_privilege| granted_privilege.implies(privilege))
+                    && role
+                        .index_patterns
+                        .iter()
+                        .any(|granted_pattern| pattern_covers(granted_pattern, index_id_pattern))
+            });
+            if !is_authorized {
+                let message = format!(
+                    "principal `{}` does not have the `{}` privilege on `{index_id_pattern}`",
+                    self.name,
+                    privilege_name(privilege),
+                );
+                return Err(AuthError::Forbidden(message));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn privilege_name(privilege: AuthPrivilege) -> &'static str {
+    match privilege {
+        AuthPrivilege::Read => "read",
+        AuthPrivilege::Ingest => "ingest",
+        AuthPrivilege::Admin => "admin",
+    }
+}
+
+/// Returns whether all the index IDs matched by `requested_pattern` are also matched by
+/// `granted_pattern`. Both patterns may contain the `*` wildcard.
+fn pattern_covers(granted_pattern: &str, requested_pattern: &str) -> bool {
+    let requested = requested_pattern.as_bytes();
+    // `covers[i]` holds whether the prefix of the granted pattern processed so far covers
+    // `requested[..i]`.
+    let mut covers = vec![false; requested.len() + 1];
+    covers[0] = true;
+
+    for &granted_byte in granted_pattern.as_bytes() {
+        if granted_byte == b'*' {
+            // A wildcard covers any sequence of characters, including wildcards.
+            for i in 1..=requested.len() {
+                covers[i] |= covers[i - 1];
+            }
+        } else {
+            // A literal character cannot cover a wildcard of the requested pattern.
+            for i in (1..=requested.len()).rev() {
+                covers[i] = covers[i - 1] && requested[i - 1] == granted_byte;
+            }
+            covers[0] = false;
+        }
+    }
+    covers[requested.len()]
+}
+
+/// Authenticates and authorizes the requests sent to the REST API.
+#[derive(Clone)]
+pub(crate) struct AuthLayer {
+    authenticator_opt: Option<Arc<Authenticator>>,
+}
+
+impl AuthLayer {
+    /// Creates a new [`AuthLayer`]. Requests are let through untouched when `authenticator_opt`
+    /// is `None`.
+    pub fn new(authenticator_opt: Option<Arc<Authenticator>>) -> Self {
+        AuthLayer { authenticator_opt }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        AuthService {
+            inner: service,
+            authenticator_opt: self.authenticator_opt.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct AuthService<S> {
+    inner: S,
+    authenticator_opt: Option<Arc<Authenticator>>,
+}
+
+impl<S> Service<Request<Body>> for AuthService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response<Body>, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        // The service that was polled ready is the one that must handle the request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        let Some(authenticator) = self.authenticator_opt.clone() else {
+            return Box::pin(inner.call(request));
+        };
+        Box::pin(async move {
+            let Some(required_privileges) =
+                route_access(request.method(), request.uri().path(), request.headers())
+            else {
+                return inner.call(request).await;
+            };
+            let auth_result = authenticator
+                .authenticate(request.headers())
+                .await
+                .and_then(|principal| {
+                    for (privilege, index_id_patterns) in &required_privileges {
+                        principal.authorize(*privilege, index_id_patterns)?;
+                    }
+                    Ok(principal)
+                });
+            match auth_result {
+                Ok(principal) => {
+                    request.extensions_mut().insert(principal);
+                    inner.call(request).await
+                }
+                Err(auth_error) => Ok(make_auth_error_response(auth_error, &authenticator)),
+            }
+        })
+    }
+}
+
+fn make_auth_error_response(
+    auth_error: AuthError,
+    authenticator: &Authenticator,
+) -> Response<Body> {
+    let status_code = auth_error.status_code();
+    let rest_api_error = RestApiError {
+        status_code,
+        message: auth_error.to_string(),
+    };
+    let mut response =
+        RestApiResponse::new::<(), _>(&Err(rest_api_error), status_code, BodyFormat::default())
+            .into_response();
+
+    if status_code == StatusCode::UNAUTHORIZED {
+        for challenge in authenticator.challenges() {
+            response
+                .headers_mut()
+                .append(WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use quickwit_config::{ApiKeyConfig, AuthConfig};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn role(name: &str, index_patterns: &[&str], privileges: &[AuthPrivilege]) -> RoleConfig {
+        RoleConfig {
+            name: name.to_string(),
+            index_patterns: index_patterns
+                .iter()
+                .map(|index_pattern| index_pattern.to_string())
+                .collect(),
+            privileges: privileges.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_pattern_covers() {
+        assert!(pattern_covers("*", "*"));
+        assert!(pattern_covers("*", "logs"));
+        assert!(pattern_covers("*", "logs-*"));
+        assert!(pattern_covers("logs", "logs"));
+        assert!(pattern_covers("logs-*", "logs-"));
+        assert!(pattern_covers("logs-*", "logs-app"));
+        assert!(pattern_covers("logs-*", "logs-*"));
+        assert!(pattern_covers("logs-*", "logs-app-*"));
+        assert!(pattern_covers("logs-*-prod", "logs-app*-prod"));
+        assert!(pattern_covers("*-prod", "logs-*-prod"));
+
+        assert!(!pattern_covers("logs", "logs-app"));
+        assert!(!pattern_covers("logs-*", "logs"));
+        assert!(!pattern_covers("logs-*", "*"));
+        assert!(!pattern_covers("logs-*", "traces-*"));
+        assert!(!pattern_covers("logs-app", "logs-*"));
+        assert!(!pattern_covers("logs-*-prod", "logs-*"));
+        assert!(!pattern_covers("", "logs"));
+    }
+
+    #[test]
+    fn test_principal_authorize() {
+        let principal = Principal::new(
+            "alice".to_string(),
+            vec![
+                role("logs-reader", &["logs-*"], &[AuthPrivilege::Read]),
+                role("app-admin", &["app"], &[AuthPrivilege::Admin]),
+            ],
+        );
+        principal
+            .authorize(AuthPrivilege::Read, &["logs-app", "logs-db-*"])
+            .unwrap();
+        principal.authorize(AuthPrivilege::Read, &["app"]).unwrap();
+        principal
+            .authorize(AuthPrivilege::Ingest, &["app"])
+            .unwrap();
+        principal
+            .authorize(AuthPrivilege::Read, &["logs-*", "-logs-db"])
+            .unwrap();
+
+        let error = principal
+            .authorize(AuthPrivilege::Ingest, &["logs-app"])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "principal `alice` does not have the `ingest` privilege on `logs-app`"
+        );
+        assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+
+        let error = principal
+            .authorize(AuthPrivilege::Read, &["logs-app", "traces"])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "principal `alice` does not have the `read` privilege on `traces`"
+        );
+        principal
+            .authorize(AuthPrivilege::Read, &["_all"])
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_auth_service() {
+        let auth_config = AuthConfig {
+            api_keys: vec![ApiKeyConfig {
+                name: "logs-reader".to_string(),
+                key: "my-secret-key".to_string(),
+                roles: vec!["logs-reader".to_string()],
+            }],
+            roles: vec![role("logs-reader", &["logs-*"], &[AuthPrivilege::Read])],
+            ..Default::default()
+        };
+        let authenticator = Arc::new(Authenticator::try_new(&auth_config).unwrap());
+        let service = AuthLayer::new(Some(authenticator)).layer(tower::service_fn(
+            |request: Request<Body>| async move {
+                let principal_name = request
+                    .extensions()
+                    .get::<Principal>()
+                    .map(|principal| principal.name().to_string())
+                    .unwrap_or_default();
+                Ok::<_, Infallible>(Response::new(Body::from(principal_name)))
+            },
+        ));
+        let request = |path: &str, api_key_opt: Option<&'static str>| {
+            let mut request_builder = Request::get(path);
+            if let Some(api_key) = api_key_opt {
+                request_builder = request_builder.header("x-api-key", api_key);
+            }
+            request_builder.body(Body::empty()).unwrap()
+        };
+        let response = service
+            .clone()
+            .oneshot(request("/health/livez", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = service
+            .clone()
+            .oneshot(request("/api/v1/logs-app/search", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers().get(WWW_AUTHENTICATE).unwrap(),
+            r#"Bearer realm="quickwit""#
+        );
+
+        let response = service
+            .clone()
+            .oneshot(request("/api/v1/traces/search", Some("my-secret-key")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(response.headers().get(WWW_AUTHENTICATE).is_none());
+
+        let response = service
+            .oneshot(request("/api/v1/logs-app/search", Some("my-secret-key")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "logs-reader");
+    }
+}
@@ -0,0 +1,405 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Privileges required by the routes of the REST API.
+//!
+//! Warp routes are tried in order and some of them overlap (for instance,
+//! `/api/v1/indexes/search` is both the search endpoint of the `indexes` index and the
+//! describe endpoint of the `search` index), so the privileges required by all the candidate
+//! routes are checked. Unknown routes require the `admin` privilege on all indexes.
+
+use hyper::header::HeaderMap;
+use hyper::Method;
+use percent_encoding::percent_decode_str;
+use quickwit_config::AuthPrivilege;
+use quickwit_opentelemetry::otlp::OtelSignal;
+
+/// List of privileges the principal must be granted on the associated index ID patterns.
+pub(super) type RequiredPrivileges = Vec<(AuthPrivilege, Vec<String>)>;
+
+/// Returns the privileges required to access a route, or `None` if the route is public.
+pub(super) fn route_access(
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+) -> Option<RequiredPrivileges> {
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match segments.as_slice() {
+        ["api", "developer", ..] => Some(require(AuthPrivilege::Admin, "*")),
+        ["api", "v1", api_v1_segments @ ..] => {
+            Some(api_v1_route_access(method, api_v1_segments, headers))
+        }
+        // The UI, the API docs, the health checks, and the metrics are not protected.
+        _ => None,
+    }
+}
+
+fn api_v1_route_access(
+    method: &Method,
+    segments: &[&str],
+    headers: &HeaderMap,
+) -> RequiredPrivileges {
+    let mut required_privileges_opt: Option<RequiredPrivileges> = None;
+
+    let candidate_route_accesses = [
+        root_route_access(method, segments, headers),
+        index_route_access(method, segments),
+    ];
+    for required_privileges in candidate_route_accesses.into_iter().flatten() {
+        required_privileges_opt
+            .get_or_insert_with(Vec::new)
+            .extend(required_privileges);
+    }
+    required_privileges_opt.unwrap_or_else(|| require(AuthPrivilege::Admin, "*"))
+}
+
+/// Handles the routes that start with a fixed path segment.
+fn root_route_access(
+    method: &Method,
+    segments: &[&str],
+    headers: &HeaderMap,
+) -> Option<RequiredPrivileges> {
+    let is_read_method = is_read_method(method);
+
+    let required_privileges = match segments {
+        ["version"] | ["analyze"] | ["parse-query"] => authenticated(),
+        ["cluster", ..] | ["config", ..] | ["indexing", ..] | ["templates", ..] => {
+            require(AuthPrivilege::Admin, "*")
+        }
+        ["indexes"] if is_read_method => require(AuthPrivilege::Read, "*"),
+        ["indexes"] => require(AuthPrivilege::Admin, "*"),
+        ["indexes", index_id] | ["indexes", index_id, "describe" | "splits"] if is_read_method => {
+            require(AuthPrivilege::Read, index_id)
+        }
+        ["indexes", index_id, "sources", ..] if is_read_method => {
+            require(AuthPrivilege::Read, index_id)
+        }
+        ["indexes", index_id, ..] => require(AuthPrivilege::Admin, index_id),
+        ["_reindex", index_id, _] if is_read_method => require(AuthPrivilege::Read, index_id),
+        // Reindexing reads and writes the indexes listed in the request body.
+        ["_reindex"] => require(AuthPrivilege::Admin, "*"),
+        // The handlers check that the principal submitted the async search and is still granted
+        // access to the indexes it targets.
+        ["_async_search", _] => authenticated(),
+        ["_elastic", ..] => elastic_route_access(method, &segments[1..]),
+        ["otlp", "v1", "logs"] => otlp_route_access(OtelSignal::Logs, headers),
+        ["otlp", "v1", "traces"] => otlp_route_access(OtelSignal::Traces, headers),
+        ["otlp", "v1", "metrics"] => otlp_route_access(OtelSignal::Metrics, headers),
+        // The privileges on the indexes of the `FROM` clause are checked by the handler.
+        ["_sql"] => authenticated(),
+        _ => return None,
+    };
+    Some(required_privileges)
+}
+
+/// Handles the routes of the Elasticsearch-compatible API, `/api/v1/_elastic/*`.
+fn elastic_route_access(method: &Method, segments: &[&str]) -> RequiredPrivileges {
+    match segments {
+        [index_id_patterns] if method == Method::DELETE => {
+            require(AuthPrivilege::Admin, index_id_patterns)
+        }
+        // The privileges on the indexes targeted by bulk and multi-search requests are checked
+        // by the handlers, because the indexes are listed in the request body.
+        [] | ["_bulk"] | [_, "_bulk"] | ["_msearch"] => authenticated(),
+        // Scroll IDs can only be obtained by running a search on authorized indexes.
+        ["_search", "scroll"] => authenticated(),
+        // The handlers check that the principal submitted the async search and is still granted
+        // access to the indexes it targets.
+        ["_async_search", _] => authenticated(),
+        ["_search"] | ["_field_caps"] | ["_stats"] | ["_cat", "indices"] => {
+            require(AuthPrivilege::Read, "*")
+        }
+        ["_resolve", "index", index_id_patterns] | ["_cat", "indices", index_id_patterns] => {
+            require(AuthPrivilege::Read, index_id_patterns)
+        }
+        [index_id_patterns, "_search" | "_async_search" | "_count" | "_field_caps" | "_stats"] => {
+            require(AuthPrivilege::Read, index_id_patterns)
+        }
+        _ => require(AuthPrivilege::Admin, "*"),
+    }
+}
+
+fn otlp_route_access(otel_signal: OtelSignal, headers: &HeaderMap) -> RequiredPrivileges {
+    let index_id = headers
+        .get(otel_signal.header_name())
+        .and_then(|header_value| header_value.to_str().ok())
+        .unwrap_or(otel_signal.default_index_id());
+    require(AuthPrivilege::Ingest, index_id)
+}
+
+/// Handles the routes that start with an index ID or index ID patterns, `/api/v1/{index}/*`.
+fn index_route_access(method: &Method, segments: &[&str]) -> Option<RequiredPrivileges> {
+    let [index_id_patterns, rest_segments @ ..] = segments else {
+        return None;
+    };
+    let privilege = match rest_segments {
+        ["search"] | ["search", "stream"] | ["search-plan"] | ["_async_search"] | ["tail"] => {
+            AuthPrivilege::Read
+        }
+        ["jaeger", ..] => AuthPrivilege::Read,
+        ["ingest"]
+        | ["ingest-v2"]
+        | ["sources", _, "push"]
+        | ["otlp", "v1", "logs" | "traces" | "metrics"] => AuthPrivilege::Ingest,
+        ["delete-tasks"] if is_read_method(method) => AuthPrivilege::Read,
+        ["delete-tasks"] => AuthPrivilege::Admin,
+        _ => return None,
+    };
+    Some(require(privilege, index_id_patterns))
+}
+
+fn is_read_method(method: &Method) -> bool {
+    method == Method::GET || method == Method::HEAD
+}
+
+/// The route is accessible to any authenticated principal.
+fn authenticated() -> RequiredPrivileges {
+    Vec::new()
+}
+
+fn require(privilege: AuthPrivilege, index_id_patterns: &str) -> RequiredPrivileges {
+    let index_id_patterns = percent_decode_str(index_id_patterns)
+        .decode_utf8_lossy()
+        .split(',')
+        .map(|index_id_pattern| index_id_pattern.to_string())
+        .collect();
+    vec![(privilege, index_id_patterns)]
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+
+    use super::*;
+
+    #[track_caller]
+    fn assert_route_access(
+        method: Method,
+        path: &str,
+        expected_privileges_opt: Option<&[(AuthPrivilege, &[&str])]>,
+    ) {
+        let required_privileges_opt = route_access(&method, path, &HeaderMap::new());
+        let expected_privileges_opt = expected_privileges_opt.map(|expected_privileges| {
+            expected_privileges
+                .iter()
+                .map(|(privilege, index_id_patterns)| {
+                    let index_id_patterns = index_id_patterns
+                        .iter()
+                        .map(|index_id_pattern| index_id_pattern.to_string())
+                        .collect();
+                    (*privilege, index_id_patterns)
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            required_privileges_opt, expected_privileges_opt,
+            "{method} {path}"
+        );
+    }
+
+    #[test]
+    fn test_route_access_public_routes() {
+        for path in [
+            "/",
+            "/ui/search",
+            "/health/livez",
+            "/metrics",
+            "/openapi.json",
+        ] {
+            assert_route_access(Method::GET, path, None);
+        }
+    }
+
+    #[test]
+    fn test_route_access_api_v1() {
+        use AuthPrivilege::*;
+
+        assert_route_access(Method::GET, "/api/v1/version", Some(&[]));
+        assert_route_access(Method::POST, "/api/v1/_sql", Some(&[]));
+        assert_route_access(Method::GET, "/api/v1/cluster", Some(&[(Admin, &["*"])]));
+        assert_route_access(
+            Method::GET,
+            "/api/developer/pprof/start",
+            Some(&[(Admin, &["*"])]),
+        );
+        assert_route_access(Method::GET, "/api/v1/unknown", Some(&[(Admin, &["*"])]));
+
+        assert_route_access(Method::GET, "/api/v1/indexes", Some(&[(Read, &["*"])]));
+        assert_route_access(Method::POST, "/api/v1/indexes", Some(&[(Admin, &["*"])]));
+        assert_route_access(
+            Method::GET,
+            "/api/v1/indexes/logs/describe",
+            Some(&[(Read, &["logs"])]),
+        );
+        assert_route_access(
+            Method::DELETE,
+            "/api/v1/indexes/logs",
+            Some(&[(Admin, &["logs"])]),
+        );
+        assert_route_access(
+            Method::PUT,
+            "/api/v1/indexes/logs/sources/kafka/toggle",
+            Some(&[(Admin, &["logs"])]),
+        );
+        assert_route_access(
+            Method::GET,
+            "/api/v1/indexes/logs/sources/kafka/dead-letters",
+            Some(&[(Read, &["logs"])]),
+        );
+        assert_route_access(
+            Method::POST,
+            "/api/v1/indexes/logs/sources/kafka/dead-letters/replay",
+            Some(&[(Admin, &["logs"])]),
+        );
+        assert_route_access(
+            Method::GET,
+            "/api/v1/logs-*,traces/search",
+            Some(&[(Read, &["logs-*", "traces"])]),
+        );
+        assert_route_access(
+            Method::GET,
+            "/api/v1/logs-%2A%2Ctraces/search",
+            Some(&[(Read, &["logs-*", "traces"])]),
+        );
+        assert_route_access(
+            Method::POST,
+            "/api/v1/logs/ingest",
+            Some(&[(Ingest, &["logs"])]),
+        );
+        assert_route_access(
+            Method::POST,
+            "/api/v1/logs/sources/webhook/push",
+            Some(&[(Ingest, &["logs"])]),
+        );
+        assert_route_access(
+            Method::POST,
+            "/api/v1/logs/delete-tasks",
+            Some(&[(Admin, &["logs"])]),
+        );
+        assert_route_access(
+            Method::GET,
+            "/api/v1/traces/jaeger/api/services",
+            Some(&[(Read, &["traces"])]),
+        );
+        assert_route_access(Method::POST, "/api/v1/_reindex", Some(&[(Admin, &["*"])]));
+        assert_route_access(
+            Method::GET,
+            "/api/v1/_reindex/logs-v2/reindex-logs",
+            Some(&[(Read, &["logs-v2"])]),
+        );
+        assert_route_access(
+            Method::POST,
+            "/api/v1/logs/_async_search",
+            Some(&[(Read, &["logs"])]),
+        );
+        assert_route_access(
+            Method::DELETE,
+            "/api/v1/_async_search/01HAV29D4XY3D462FS3D8K5Q2H",
+            Some(&[]),
+        );
+        // Ambiguous route: describe the `search` index or search the `indexes` index.
+        assert_route_access(
+            Method::GET,
+            "/api/v1/indexes/search",
+            Some(&[(Read, &["search"]), (Read, &["indexes"])]),
+        );
+    }
+
+    #[test]
+    fn test_route_access_elastic() {
+        use AuthPrivilege::*;
+
+        assert_route_access(Method::GET, "/api/v1/_elastic", Some(&[]));
+        assert_route_access(Method::POST, "/api/v1/_elastic/_bulk", Some(&[]));
+        assert_route_access(Method::POST, "/api/v1/_elastic/logs/_bulk", Some(&[]));
+        assert_route_access(Method::POST, "/api/v1/_elastic/_msearch", Some(&[]));
+        assert_route_access(
+            Method::POST,
+            "/api/v1/_elastic/_search",
+            Some(&[(Read, &["*"])]),
+        );
+        assert_route_access(
+            Method::POST,
+            "/api/v1/_elastic/logs-*/_async_search",
+            Some(&[(Read, &["logs-*"])]),
+        );
+        assert_route_access(
+            Method::GET,
+            "/api/v1/_elastic/_async_search/01HAV29D4XY3D462FS3D8K5Q2H",
+            Some(&[]),
+        );
+        assert_route_access(
+            Method::POST,
+            "/api/v1/_elastic/logs-*/_search",
+            Some(&[(Read, &["logs-*"])]),
+        );
+        assert_route_access(
+            Method::GET,
+            "/api/v1/_elastic/_cat/indices/logs-*",
+            Some(&[(Read, &["logs-*"])]),
+        );
+        assert_route_access(
+            Method::DELETE,
+            "/api/v1/_elastic/logs",
+            Some(&[(Admin, &["logs"])]),
+        );
+        assert_route_access(
+            Method::DELETE,
+            "/api/v1/_elastic/_bulk",
+            Some(&[(Admin, &["_bulk"])]),
+        );
+    }
+
+    #[test]
+    fn test_route_access_otlp() {
+        let required_privileges =
+            route_access(&Method::POST, "/api/v1/otlp/v1/logs", &HeaderMap::new()).unwrap();
+        assert_eq!(
+            required_privileges,
+            [(
+                AuthPrivilege::Ingest,
+                vec![OtelSignal::Logs.default_index_id().to_string()]
+            )]
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            OtelSignal::Traces.header_name(),
+            HeaderValue::from_static("my-traces"),
+        );
+        let required_privileges =
+            route_access(&Method::POST, "/api/v1/otlp/v1/traces", &headers).unwrap();
+        assert_eq!(
+            required_privileges,
+            [(AuthPrivilege::Ingest, vec!["my-traces".to_string()])]
+        );
+        let required_privileges =
+            route_access(&Method::POST, "/api/v1/otlp/v1/metrics", &HeaderMap::new()).unwrap();
+        assert_eq!(
+            required_privileges,
+            [(
+                AuthPrivilege::Ingest,
+                vec![OtelSignal::Metrics.default_index_id().to_string()]
+            )]
+        );
+    }
+}
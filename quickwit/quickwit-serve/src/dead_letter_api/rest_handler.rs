@@ -0,0 +1,1021 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use quickwit_common::rate_limited_tracing::rate_limited_error;
+use quickwit_config::{
+    disable_ingest_v1, enable_ingest_v2, AuthPrivilege, DeadLetterQueueConfig, SourceConfig,
+    SourceInputFormat, INGEST_V2_SOURCE_ID,
+};
+use quickwit_indexing::dead_letter_queue::{DeadLetter, DeadLetterPosition};
+use quickwit_ingest::{
+    CommitType, DocBatchBuilder, DocBatchV2Builder, IngestRequest, IngestService,
+    IngestServiceClient, IngestServiceError,
+};
+use quickwit_metastore::IndexMetadataResponseExt;
+use quickwit_proto::ingest::router::{
+    IngestRequestV2, IngestRouterService, IngestRouterServiceClient, IngestSubrequest,
+};
+use quickwit_proto::ingest::CommitTypeV2;
+use quickwit_proto::metastore::{
+    DeleteQuery, DeleteTask, EntityKind, IndexMetadataRequest, ListDeleteTasksRequest,
+    MetastoreError, MetastoreService, MetastoreServiceClient,
+};
+use quickwit_proto::search::SearchRequest;
+use quickwit_proto::types::{DocUidGenerator, IndexId, IndexUid, SourceId};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use quickwit_query::query_ast::{BoolQuery, QueryAst, TermQuery, TermSetQuery};
+use quickwit_search::{SearchError, SearchService};
+use serde::{Deserialize, Serialize};
+use warp::{Filter, Rejection};
+
+use crate::auth::Principal;
+use crate::format::extract_format_from_qs;
+use crate::rest_api_response::into_rest_api_response;
+use crate::with_arg;
+
+/// Maximum number of dead letters replayed by a single replay request.
+const MAX_REPLAYED_DEAD_LETTERS: u64 = 1_000;
+
+const DEAD_LETTER_ID_FIELD: &str = "dead_letter_id";
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(list_dead_letters, replay_dead_letters))]
+pub struct DeadLetterApi;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(components(schemas(
+    DeadLetter,
+    DeadLetterPosition,
+    DeadLetterList,
+    ReplayDeadLettersRequest,
+    ReplayDeadLettersResponse,
+)))]
+pub struct DeadLetterApiSchemas;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeadLetterApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+    #[error(transparent)]
+    Ingest(#[from] IngestServiceError),
+    #[error(transparent)]
+    Metastore(#[from] MetastoreError),
+    #[error(transparent)]
+    Search(#[from] SearchError),
+}
+
+impl ServiceError for DeadLetterApiError {
+    fn error_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::BadRequest(_) => ServiceErrorCode::BadRequest,
+            Self::Forbidden(_) => ServiceErrorCode::Forbidden,
+            Self::Internal(message) => {
+                rate_limited_error!(limit_per_min = 6, "dead letter API error: {message}");
+                ServiceErrorCode::Internal
+            }
+            Self::Ingest(error) => error.error_code(),
+            Self::Metastore(error) => error.error_code(),
+            Self::Search(error) => error.error_code(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListDeadLettersQueryParams {
+    /// Maximum number of dead letters to return.
+    #[serde(default = "default_max_hits")]
+    pub max_hits: u64,
+    /// Number of dead letters to skip, for pagination.
+    #[serde(default)]
+    pub start_offset: u64,
+}
+
+fn default_max_hits() -> u64 {
+    20
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeadLetterList {
+    /// Total number of dead letters of the source.
+    pub num_hits: u64,
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+#[derive(Debug, Default, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayDeadLettersRequest {
+    /// IDs of the dead letters to replay. All the dead letters of the source, up to 1000, are
+    /// replayed if empty.
+    #[serde(default)]
+    pub dead_letter_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ReplayDeadLettersResponse {
+    pub num_replayed_dead_letters: u64,
+}
+
+pub(crate) fn dead_letter_api_handlers(
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+    ingest_service: IngestServiceClient,
+    ingest_router: IngestRouterServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    list_dead_letters_handler(metastore.clone(), search_service.clone())
+        .or(replay_dead_letters_handler(
+            metastore,
+            search_service,
+            ingest_service,
+            ingest_router,
+        ))
+        .boxed()
+}
+
+fn list_dead_letters_handler(
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "sources" / String / "dead-letters")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::ext::optional::<Principal>())
+        .and(with_arg(metastore))
+        .and(with_arg(search_service))
+        .then(list_dead_letters)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+fn replay_dead_letters_handler(
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+    ingest_service: IngestServiceClient,
+    ingest_router: IngestRouterServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "sources" / String / "dead-letters" / "replay")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(warp::ext::optional::<Principal>())
+        .and(with_arg(metastore))
+        .and(with_arg(search_service))
+        .and(with_arg(ingest_service))
+        .and(with_arg(ingest_router))
+        .then(replay_dead_letters)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Sources",
+    path = "/indexes/{index_id}/sources/{source_id}/dead-letters",
+    responses(
+        (status = 200, description = "Successfully fetched the dead letters of the source.", body = DeadLetterList)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the source."),
+        ("source_id" = String, Path, description = "The ID of the source."),
+        ListDeadLettersQueryParams,
+    )
+)]
+/// List dead letters
+///
+/// Returns the documents of the source rejected by the doc processor that have not been replayed
+/// yet. Requires the read privilege on the dead letter queue index.
+async fn list_dead_letters(
+    index_id: IndexId,
+    source_id: SourceId,
+    query_params: ListDeadLettersQueryParams,
+    principal_opt: Option<Principal>,
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+) -> Result<DeadLetterList, DeadLetterApiError> {
+    let source_config = fetch_source_config(&metastore, &index_id, &source_id).await?;
+    let dead_letter_index_id = dead_letter_queue_index_id(&source_config)?;
+    authorize_dead_letter_queue(
+        principal_opt.as_ref(),
+        AuthPrivilege::Read,
+        dead_letter_index_id,
+    )?;
+    let dead_letter_index_uid = fetch_index_uid(&metastore, dead_letter_index_id).await?;
+    let replayed_dead_letter_ids =
+        fetch_replayed_dead_letter_ids(&metastore, dead_letter_index_uid).await?;
+    let query_ast = dead_letters_query_ast(&index_id, &source_id, &[], replayed_dead_letter_ids);
+
+    search_dead_letters(
+        &*search_service,
+        dead_letter_index_id,
+        query_ast,
+        query_params.max_hits,
+        query_params.start_offset,
+    )
+    .await
+}
+
+#[utoipa::path(
+    post,
+    tag = "Sources",
+    path = "/indexes/{index_id}/sources/{source_id}/dead-letters/replay",
+    request_body = ReplayDeadLettersRequest,
+    responses(
+        (status = 200, description = "Successfully replayed the dead letters of the source.", body = ReplayDeadLettersResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the source."),
+        ("source_id" = String, Path, description = "The ID of the source."),
+    )
+)]
+/// Replay dead letters
+///
+/// Re-ingests the payloads of the dead letters into the index via the ingest API, then deletes
+/// the replayed dead letters from the dead letter queue. The dead letters targeted by a delete task
+/// are not replayed again. Requires the admin privilege on the dead letter queue index.
+#[allow(clippy::too_many_arguments)]
+async fn replay_dead_letters(
+    index_id: IndexId,
+    source_id: SourceId,
+    replay_request: ReplayDeadLettersRequest,
+    principal_opt: Option<Principal>,
+    metastore: MetastoreServiceClient,
+    search_service: Arc<dyn SearchService>,
+    ingest_service: IngestServiceClient,
+    ingest_router: IngestRouterServiceClient,
+) -> Result<ReplayDeadLettersResponse, DeadLetterApiError> {
+    let source_config = fetch_source_config(&metastore, &index_id, &source_id).await?;
+    let dead_letter_index_id = dead_letter_queue_index_id(&source_config)?;
+
+    // The payloads are sent to the ingest API, which only accepts JSON documents and bypasses the
+    // transform of the source.
+    if !matches!(
+        source_config.input_format,
+        SourceInputFormat::Json | SourceInputFormat::Csv | SourceInputFormat::Parquet
+    ) {
+        let message = format!(
+            "replaying the dead letters of source `{source_id}` is not supported: input format \
+             must be `json`, `csv`, or `parquet`"
+        );
+        return Err(DeadLetterApiError::BadRequest(message));
+    }
+    if source_config.transform_config.is_some() {
+        let message = format!(
+            "replaying the dead letters of source `{source_id}` is not supported: source has a \
+             transform"
+        );
+        return Err(DeadLetterApiError::BadRequest(message));
+    }
+    authorize_dead_letter_queue(
+        principal_opt.as_ref(),
+        AuthPrivilege::Admin,
+        dead_letter_index_id,
+    )?;
+    let dead_letter_index_uid = fetch_index_uid(&metastore, dead_letter_index_id).await?;
+    // The delete tasks are created synchronously in the metastore but applied asynchronously by
+    // the janitor: the dead letters they target are excluded so that they are never replayed twice.
+    let replayed_dead_letter_ids =
+        fetch_replayed_dead_letter_ids(&metastore, dead_letter_index_uid.clone()).await?;
+    let max_hits = if replay_request.dead_letter_ids.is_empty() {
+        MAX_REPLAYED_DEAD_LETTERS
+    } else {
+        replay_request.dead_letter_ids.len() as u64
+    };
+    let query_ast = dead_letters_query_ast(
+        &index_id,
+        &source_id,
+        &replay_request.dead_letter_ids,
+        replayed_dead_letter_ids,
+    );
+    let dead_letter_list = search_dead_letters(
+        &*search_service,
+        dead_letter_index_id,
+        query_ast,
+        max_hits,
+        0,
+    )
+    .await?;
+    let dead_letters = dead_letter_list.dead_letters;
+
+    if dead_letters.is_empty() {
+        return Ok(ReplayDeadLettersResponse {
+            num_replayed_dead_letters: 0,
+        });
+    }
+    let mut payloads = Vec::with_capacity(dead_letters.len());
+
+    for dead_letter in &dead_letters {
+        let payload = dead_letter.decode_payload().map_err(|error| {
+            DeadLetterApiError::Internal(format!(
+                "failed to decode payload of dead letter `{}`: {error}",
+                dead_letter.dead_letter_id
+            ))
+        })?;
+        payloads.push(payload);
+    }
+    ingest_payloads(index_id, payloads, ingest_service, ingest_router).await?;
+
+    let dead_letter_ids: BTreeSet<String> = dead_letters
+        .iter()
+        .map(|dead_letter| dead_letter.dead_letter_id.clone())
+        .collect();
+    delete_dead_letters(&metastore, dead_letter_index_uid, dead_letter_ids).await?;
+
+    Ok(ReplayDeadLettersResponse {
+        num_replayed_dead_letters: dead_letters.len() as u64,
+    })
+}
+
+async fn fetch_source_config(
+    metastore: &MetastoreServiceClient,
+    index_id: &str,
+    source_id: &str,
+) -> Result<SourceConfig, DeadLetterApiError> {
+    let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.to_string());
+    let mut index_metadata = metastore
+        .index_metadata(index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?;
+    let source_config = index_metadata.sources.remove(source_id).ok_or_else(|| {
+        MetastoreError::NotFound(EntityKind::Source {
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+        })
+    })?;
+    Ok(source_config)
+}
+
+fn dead_letter_queue_index_id(source_config: &SourceConfig) -> Result<&str, DeadLetterApiError> {
+    match &source_config.dead_letter_queue_config {
+        Some(DeadLetterQueueConfig::Index { index_id }) => Ok(index_id),
+        Some(DeadLetterQueueConfig::Storage { .. }) => {
+            let message = format!(
+                "dead letter queue of source `{}` is stored in a storage: only dead letter queues \
+                 stored in an index can be listed and replayed",
+                source_config.source_id
+            );
+            Err(DeadLetterApiError::BadRequest(message))
+        }
+        None => {
+            let message = format!(
+                "source `{}` has no dead letter queue",
+                source_config.source_id
+            );
+            Err(DeadLetterApiError::BadRequest(message))
+        }
+    }
+}
+
+/// Checks that the principal, if authentication is enabled, is granted `privilege` on the dead
+/// letter queue index. The dead letter queue index is not part of the route, so it cannot be
+/// authorized by the auth layer.
+fn authorize_dead_letter_queue(
+    principal_opt: Option<&Principal>,
+    privilege: AuthPrivilege,
+    dead_letter_index_id: &str,
+) -> Result<(), DeadLetterApiError> {
+    let Some(principal) = principal_opt else {
+        return Ok(());
+    };
+    principal
+        .authorize(privilege, &[dead_letter_index_id])
+        .map_err(|auth_error| DeadLetterApiError::Forbidden(auth_error.to_string()))
+}
+
+async fn fetch_index_uid(
+    metastore: &MetastoreServiceClient,
+    index_id: &str,
+) -> Result<IndexUid, DeadLetterApiError> {
+    let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.to_string());
+    let index_uid = metastore
+        .index_metadata(index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?
+        .index_uid;
+    Ok(index_uid)
+}
+
+/// Returns the IDs of the dead letters targeted by the delete tasks of the dead letter queue index,
+/// i.e. the dead letters already replayed.
+async fn fetch_replayed_dead_letter_ids(
+    metastore: &MetastoreServiceClient,
+    dead_letter_index_uid: IndexUid,
+) -> Result<BTreeSet<String>, DeadLetterApiError> {
+    let list_delete_tasks_request = ListDeleteTasksRequest::new(dead_letter_index_uid, 0);
+    let delete_tasks = metastore
+        .list_delete_tasks(list_delete_tasks_request)
+        .await?
+        .delete_tasks;
+    Ok(replayed_dead_letter_ids(&delete_tasks))
+}
+
+fn replayed_dead_letter_ids(delete_tasks: &[DeleteTask]) -> BTreeSet<String> {
+    let mut replayed_dead_letter_ids = BTreeSet::new();
+
+    for delete_task in delete_tasks {
+        let Some(delete_query) = &delete_task.delete_query else {
+            continue;
+        };
+        // Only the delete tasks created by the replay endpoint target dead letters by ID.
+        let Ok(QueryAst::TermSet(mut term_set_query)) =
+            serde_json::from_str::<QueryAst>(&delete_query.query_ast)
+        else {
+            continue;
+        };
+        if let Some(dead_letter_ids) = term_set_query.terms_per_field.remove(DEAD_LETTER_ID_FIELD) {
+            replayed_dead_letter_ids.extend(dead_letter_ids);
+        }
+    }
+    replayed_dead_letter_ids
+}
+
+fn dead_letter_ids_query_ast(dead_letter_ids: BTreeSet<String>) -> QueryAst {
+    let terms_per_field = HashMap::from([(DEAD_LETTER_ID_FIELD.to_string(), dead_letter_ids)]);
+    TermSetQuery { terms_per_field }.into()
+}
+
+/// Builds the query matching the dead letters of a source, optionally restricted to the given dead
+/// letter IDs, and excluding the dead letters already replayed.
+fn dead_letters_query_ast(
+    index_id: &str,
+    source_id: &str,
+    dead_letter_ids: &[String],
+    replayed_dead_letter_ids: BTreeSet<String>,
+) -> QueryAst {
+    let mut bool_query = BoolQuery {
+        must: vec![
+            TermQuery {
+                field: "index_id".to_string(),
+                value: index_id.to_string(),
+            }
+            .into(),
+            TermQuery {
+                field: "source_id".to_string(),
+                value: source_id.to_string(),
+            }
+            .into(),
+        ],
+        ..Default::default()
+    };
+    if !dead_letter_ids.is_empty() {
+        let dead_letter_ids = dead_letter_ids.iter().cloned().collect();
+        bool_query
+            .must
+            .push(dead_letter_ids_query_ast(dead_letter_ids));
+    }
+    if !replayed_dead_letter_ids.is_empty() {
+        bool_query
+            .must_not
+            .push(dead_letter_ids_query_ast(replayed_dead_letter_ids));
+    }
+    bool_query.into()
+}
+
+async fn search_dead_letters(
+    search_service: &dyn SearchService,
+    dead_letter_index_id: &str,
+    query_ast: QueryAst,
+    max_hits: u64,
+    start_offset: u64,
+) -> Result<DeadLetterList, DeadLetterApiError> {
+    let query_ast = serde_json::to_string(&query_ast)
+        .map_err(|error| DeadLetterApiError::Internal(error.to_string()))?;
+    let search_request = SearchRequest {
+        index_id_patterns: vec![dead_letter_index_id.to_string()],
+        query_ast,
+        max_hits,
+        start_offset,
+        ..Default::default()
+    };
+    let search_response = search_service.root_search(search_request).await?;
+
+    let dead_letters = search_response
+        .hits
+        .iter()
+        .map(|hit| serde_json::from_str::<DeadLetter>(&hit.json))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| {
+            DeadLetterApiError::Internal(format!(
+                "failed to deserialize dead letter from index `{dead_letter_index_id}`: {error}"
+            ))
+        })?;
+    Ok(DeadLetterList {
+        num_hits: search_response.num_hits,
+        dead_letters,
+    })
+}
+
+async fn ingest_payloads(
+    index_id: IndexId,
+    payloads: Vec<Bytes>,
+    ingest_service: IngestServiceClient,
+    ingest_router: IngestRouterServiceClient,
+) -> Result<(), DeadLetterApiError> {
+    if enable_ingest_v2() {
+        let mut doc_batch_builder = DocBatchV2Builder::default();
+        let mut doc_uid_generator = DocUidGenerator::default();
+
+        for payload in &payloads {
+            doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), &payload[..]);
+        }
+        let subrequest = IngestSubrequest {
+            subrequest_id: 0,
+            index_id,
+            source_id: INGEST_V2_SOURCE_ID.to_string(),
+            doc_batch: doc_batch_builder.build(),
+        };
+        let ingest_request = IngestRequestV2 {
+            commit_type: CommitTypeV2::Auto as i32,
+            subrequests: vec![subrequest],
+        };
+        let mut ingest_response = ingest_router
+            .ingest(ingest_request)
+            .await
+            .map_err(IngestServiceError::from)?;
+
+        if let Some(ingest_failure) = ingest_response.failures.pop() {
+            return Err(IngestServiceError::from(ingest_failure).into());
+        }
+        return Ok(());
+    }
+    if disable_ingest_v1() {
+        let message = "ingest v1 is disabled: environment variable `QW_DISABLE_INGEST_V1` is set";
+        return Err(DeadLetterApiError::Internal(message.to_string()));
+    }
+    let mut doc_batch_builder = DocBatchBuilder::new(index_id);
+
+    for payload in &payloads {
+        doc_batch_builder.ingest_doc(&payload[..]);
+    }
+    let ingest_request = IngestRequest {
+        doc_batches: vec![doc_batch_builder.build()],
+        commit: CommitType::Auto.into(),
+    };
+    ingest_service.ingest(ingest_request).await?;
+    Ok(())
+}
+
+/// Creates a delete task removing the replayed dead letters from the dead letter queue index.
+async fn delete_dead_letters(
+    metastore: &MetastoreServiceClient,
+    dead_letter_index_uid: IndexUid,
+    dead_letter_ids: BTreeSet<String>,
+) -> Result<(), DeadLetterApiError> {
+    let query_ast = dead_letter_ids_query_ast(dead_letter_ids);
+    let query_ast_json = serde_json::to_string(&query_ast)
+        .map_err(|error| DeadLetterApiError::Internal(error.to_string()))?;
+    let delete_query = DeleteQuery {
+        index_uid: Some(dead_letter_index_uid),
+        start_timestamp: None,
+        end_timestamp: None,
+        query_ast: query_ast_json,
+    };
+    metastore.create_delete_task(delete_query).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_common::uri::Uri;
+    use quickwit_config::{IngestApiConfig, RoleConfig, SourceParams, TransformConfig};
+    use quickwit_metastore::IndexMetadata;
+    use quickwit_proto::metastore::{
+        DeleteTask, IndexMetadataResponse, ListDeleteTasksResponse, MockMetastoreService,
+    };
+    use quickwit_proto::search::{Hit, SearchResponse};
+    use quickwit_search::MockSearchService;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::ingest_api::setup_ingest_service;
+
+    fn dead_letter_for_test(dead_letter_id: &str, payload: &str) -> DeadLetter {
+        DeadLetter {
+            dead_letter_id: dead_letter_id.to_string(),
+            timestamp: 1_700_000_000,
+            index_id: "my-index".to_string(),
+            source_id: "my-source".to_string(),
+            error_kind: "doc_mapper_error".to_string(),
+            error: "doc mapper parse error: the document must contain field \"timestamp\""
+                .to_string(),
+            positions: Vec::new(),
+            payload: payload.to_string(),
+            payload_base64: false,
+        }
+    }
+
+    fn mock_metastore_for_test(
+        dead_letter_queue_config_opt: Option<DeadLetterQueueConfig>,
+    ) -> MockMetastoreService {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore
+            .expect_index_metadata()
+            .returning(move |request| {
+                let index_id = request.index_id.unwrap();
+                let mut index_metadata =
+                    IndexMetadata::for_test(&index_id, &format!("ram:///indexes/{index_id}"));
+
+                if index_id == "my-index" {
+                    let mut source_config =
+                        SourceConfig::for_test("my-source", SourceParams::void());
+                    source_config.dead_letter_queue_config = dead_letter_queue_config_opt.clone();
+                    index_metadata.add_source(source_config).unwrap();
+                }
+                Ok(IndexMetadataResponse::try_from_index_metadata(&index_metadata).unwrap())
+            });
+        mock_metastore
+    }
+
+    fn dead_letter_api_handlers_for_test(
+        mock_metastore: MockMetastoreService,
+        mock_search_service: MockSearchService,
+        ingest_service: IngestServiceClient,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+        dead_letter_api_handlers(
+            MetastoreServiceClient::from_mock(mock_metastore),
+            Arc::new(mock_search_service),
+            ingest_service,
+            IngestRouterServiceClient::mocked(),
+        )
+    }
+
+    fn term_query_ast(field: &str, value: &str) -> QueryAst {
+        TermQuery {
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_dead_letters_query_ast() {
+        assert_eq!(
+            dead_letters_query_ast("my-index", "my-source", &[], BTreeSet::new()),
+            QueryAst::from(BoolQuery {
+                must: vec![
+                    term_query_ast("index_id", "my-index"),
+                    term_query_ast("source_id", "my-source"),
+                ],
+                ..Default::default()
+            })
+        );
+        // The IDs are matched verbatim: they cannot alter the structure of the query.
+        let dead_letter_ids = vec!["01HZ".to_string(), r#"01J0" OR index_id:"*"#.to_string()];
+        let replayed_dead_letter_ids = BTreeSet::from(["01HX".to_string()]);
+        assert_eq!(
+            dead_letters_query_ast(
+                "my-index",
+                "my-source",
+                &dead_letter_ids,
+                replayed_dead_letter_ids.clone()
+            ),
+            QueryAst::from(BoolQuery {
+                must: vec![
+                    term_query_ast("index_id", "my-index"),
+                    term_query_ast("source_id", "my-source"),
+                    dead_letter_ids_query_ast(dead_letter_ids.iter().cloned().collect()),
+                ],
+                must_not: vec![dead_letter_ids_query_ast(replayed_dead_letter_ids)],
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_replayed_dead_letter_ids() {
+        let delete_task = |query_ast: &QueryAst| DeleteTask {
+            create_timestamp: 0,
+            opstamp: 1,
+            delete_query: Some(DeleteQuery {
+                index_uid: Some(IndexUid::for_test("my-dlq", 0)),
+                start_timestamp: None,
+                end_timestamp: None,
+                query_ast: serde_json::to_string(query_ast).unwrap(),
+            }),
+        };
+        let delete_tasks = [
+            delete_task(&dead_letter_ids_query_ast(BTreeSet::from([
+                "01HX".to_string(),
+                "01HY".to_string(),
+            ]))),
+            delete_task(&term_query_ast("error_kind", "json_parse_error")),
+            delete_task(&dead_letter_ids_query_ast(BTreeSet::from([
+                "01HZ".to_string()
+            ]))),
+        ];
+        assert_eq!(
+            replayed_dead_letter_ids(&delete_tasks),
+            BTreeSet::from(["01HX".to_string(), "01HY".to_string(), "01HZ".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_dead_letters() {
+        let mut mock_metastore = mock_metastore_for_test(Some(DeadLetterQueueConfig::Index {
+            index_id: "my-dlq".to_string(),
+        }));
+        mock_metastore
+            .expect_list_delete_tasks()
+            .returning(|_| Ok(ListDeleteTasksResponse::default()));
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                let expected_query_ast =
+                    dead_letters_query_ast("my-index", "my-source", &[], BTreeSet::new());
+                search_request.index_id_patterns == ["my-dlq"]
+                    && search_request.max_hits == 5
+                    && search_request.start_offset == 10
+                    && serde_json::from_str::<QueryAst>(&search_request.query_ast).unwrap()
+                        == expected_query_ast
+            })
+            .returning(|_| {
+                let dead_letter = dead_letter_for_test("01HZ", r#"{"body": "foo"}"#);
+                let hit = Hit {
+                    json: serde_json::to_string(&dead_letter).unwrap(),
+                    ..Default::default()
+                };
+                Ok(SearchResponse {
+                    num_hits: 11,
+                    hits: vec![hit],
+                    ..Default::default()
+                })
+            });
+        let (universe, _temp_dir, ingest_service, _) =
+            setup_ingest_service(&[], &IngestApiConfig::default()).await;
+        let handlers =
+            dead_letter_api_handlers_for_test(mock_metastore, mock_search_service, ingest_service);
+
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters?max_hits=5&start_offset=10")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let dead_letter_list: DeadLetterList = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(dead_letter_list.num_hits, 11);
+        assert_eq!(dead_letter_list.dead_letters.len(), 1);
+        assert_eq!(dead_letter_list.dead_letters[0].dead_letter_id, "01HZ");
+
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/unknown-source/dead-letters")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 404);
+
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_list_dead_letters_requires_index_dead_letter_queue() {
+        let (universe, _temp_dir, ingest_service, _) =
+            setup_ingest_service(&[], &IngestApiConfig::default()).await;
+
+        let handlers = dead_letter_api_handlers_for_test(
+            mock_metastore_for_test(None),
+            MockSearchService::new(),
+            ingest_service.clone(),
+        );
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json["message"],
+            "source `my-source` has no dead letter queue"
+        );
+
+        let handlers = dead_letter_api_handlers_for_test(
+            mock_metastore_for_test(Some(DeadLetterQueueConfig::Storage {
+                uri: Uri::for_test("s3://my-bucket/dead-letters"),
+            })),
+            MockSearchService::new(),
+            ingest_service,
+        );
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json["message"],
+            "dead letter queue of source `my-source` is stored in a storage: only dead letter \
+             queues stored in an index can be listed and replayed"
+        );
+
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters/replay")
+            .method("POST")
+            .json(&true)
+            .body("{}")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_dead_letters() {
+        let mut mock_metastore = mock_metastore_for_test(Some(DeadLetterQueueConfig::Index {
+            index_id: "my-dlq".to_string(),
+        }));
+        // "01HX" was replayed by a previous request, but its delete task is not applied yet.
+        mock_metastore
+            .expect_list_delete_tasks()
+            .withf(|request| request.index_uid().index_id == "my-dlq")
+            .returning(|request| {
+                let query_ast = dead_letter_ids_query_ast(BTreeSet::from(["01HX".to_string()]));
+                let delete_task = DeleteTask {
+                    create_timestamp: 0,
+                    opstamp: 1,
+                    delete_query: Some(DeleteQuery {
+                        index_uid: request.index_uid,
+                        start_timestamp: None,
+                        end_timestamp: None,
+                        query_ast: serde_json::to_string(&query_ast).unwrap(),
+                    }),
+                };
+                Ok(ListDeleteTasksResponse {
+                    delete_tasks: vec![delete_task],
+                })
+            });
+        mock_metastore
+            .expect_create_delete_task()
+            .withf(|delete_query| {
+                let expected_query_ast = dead_letter_ids_query_ast(BTreeSet::from([
+                    "01HZ".to_string(),
+                    "01J0".to_string(),
+                ]));
+                delete_query.index_uid().index_id == "my-dlq"
+                    && serde_json::from_str::<QueryAst>(&delete_query.query_ast).unwrap()
+                        == expected_query_ast
+            })
+            .returning(|delete_query| {
+                Ok(DeleteTask {
+                    create_timestamp: 0,
+                    opstamp: 1,
+                    delete_query: Some(delete_query),
+                })
+            });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                let expected_query_ast = dead_letters_query_ast(
+                    "my-index",
+                    "my-source",
+                    &[],
+                    BTreeSet::from(["01HX".to_string()]),
+                );
+                search_request.index_id_patterns == ["my-dlq"]
+                    && search_request.max_hits == MAX_REPLAYED_DEAD_LETTERS
+                    && serde_json::from_str::<QueryAst>(&search_request.query_ast).unwrap()
+                        == expected_query_ast
+            })
+            .returning(|_| {
+                let hits = [
+                    dead_letter_for_test("01HZ", r#"{"body": "foo"}"#),
+                    dead_letter_for_test("01J0", r#"{"body": "bar"}"#),
+                ]
+                .iter()
+                .map(|dead_letter| Hit {
+                    json: serde_json::to_string(dead_letter).unwrap(),
+                    ..Default::default()
+                })
+                .collect();
+                Ok(SearchResponse {
+                    num_hits: 2,
+                    hits,
+                    ..Default::default()
+                })
+            });
+        let (universe, _temp_dir, ingest_service, _) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let handlers = dead_letter_api_handlers_for_test(
+            mock_metastore,
+            mock_search_service,
+            ingest_service.clone(),
+        );
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters/replay")
+            .method("POST")
+            .json(&true)
+            .body("{}")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let replay_response: ReplayDeadLettersResponse =
+            serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(replay_response.num_replayed_dead_letters, 2);
+
+        let fetch_response = ingest_service
+            .tail(quickwit_ingest::TailRequest {
+                index_id: "my-index".to_string(),
+            })
+            .await
+            .unwrap();
+        let doc_batch = fetch_response.doc_batch.unwrap();
+        assert_eq!(doc_batch.num_docs(), 2);
+
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_require_dead_letter_queue_privileges() {
+        let (universe, _temp_dir, ingest_service, _) =
+            setup_ingest_service(&[], &IngestApiConfig::default()).await;
+        let handlers = dead_letter_api_handlers_for_test(
+            mock_metastore_for_test(Some(DeadLetterQueueConfig::Index {
+                index_id: "my-dlq".to_string(),
+            })),
+            MockSearchService::new(),
+            ingest_service,
+        );
+        // The principal is granted access to the index of the source only.
+        let principal = Principal::new(
+            "alice".to_string(),
+            vec![RoleConfig {
+                name: "admin".to_string(),
+                index_patterns: vec!["my-index".to_string()],
+                privileges: vec![AuthPrivilege::Admin],
+            }],
+        );
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters")
+            .extension(principal.clone())
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 403);
+
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters/replay")
+            .method("POST")
+            .extension(principal)
+            .json(&true)
+            .body("{}")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 403);
+
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_dead_letters_rejects_sources_with_transform() {
+        let mut mock_metastore = MockMetastoreService::new();
+        mock_metastore.expect_index_metadata().returning(|_| {
+            let mut index_metadata = IndexMetadata::for_test("my-index", "ram:///indexes/my-index");
+            let mut source_config = SourceConfig::for_test("my-source", SourceParams::void());
+            source_config.transform_config = Some(TransformConfig::for_test(".message = 1"));
+            source_config.dead_letter_queue_config = Some(DeadLetterQueueConfig::Index {
+                index_id: "my-dlq".to_string(),
+            });
+            index_metadata.add_source(source_config).unwrap();
+            Ok(IndexMetadataResponse::try_from_index_metadata(&index_metadata).unwrap())
+        });
+        let (universe, _temp_dir, ingest_service, _) =
+            setup_ingest_service(&[], &IngestApiConfig::default()).await;
+        let handlers = dead_letter_api_handlers_for_test(
+            mock_metastore,
+            MockSearchService::new(),
+            ingest_service,
+        );
+        let resp = warp::test::request()
+            .path("/indexes/my-index/sources/my-source/dead-letters/replay")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"dead_letter_ids": ["01HZ"]}"#)
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        universe.assert_quit().await;
+    }
+}
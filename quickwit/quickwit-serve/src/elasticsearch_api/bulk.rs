@@ -17,12 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::time::Instant;
 
+use bytes::Bytes;
 use bytesize::ByteSize;
 use hyper::StatusCode;
-use quickwit_config::{disable_ingest_v1, enable_ingest_v2};
+use quickwit_config::{disable_ingest_v1, enable_ingest_v2, AuthPrivilege};
 use quickwit_ingest::{
     CommitType, DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient,
 };
@@ -30,14 +31,18 @@ use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::types::IndexId;
 use warp::{Filter, Rejection};
 
-use super::bulk_v2::{elastic_bulk_ingest_v2, ElasticBulkResponse};
+use super::bulk_v2::{
+    elastic_bulk_ingest_v2, ElasticBulkAction, ElasticBulkItem, ElasticBulkResponse,
+};
+use crate::auth::Principal;
+use crate::elasticsearch_api::authorize_indexes;
 use crate::elasticsearch_api::filter::{elastic_bulk_filter, elastic_index_bulk_filter};
-use crate::elasticsearch_api::make_elastic_api_response;
 use crate::elasticsearch_api::model::{BulkAction, ElasticBulkOptions, ElasticsearchError};
 use crate::format::extract_format_from_qs;
 use crate::ingest_api::lines;
 use crate::rest::recover_fn;
-use crate::{with_arg, Body};
+use crate::rest_api_response::RestApiResponse;
+use crate::{with_arg, Body, BodyFormat};
 
 /// POST `_elastic/_bulk`
 pub fn es_compat_bulk_handler(
@@ -48,11 +53,21 @@ pub fn es_compat_bulk_handler(
     elastic_bulk_filter(content_length_limit)
         .and(with_arg(ingest_service))
         .and(with_arg(ingest_router))
-        .then(|body, bulk_options, ingest_service, ingest_router| {
-            elastic_ingest_bulk(None, body, bulk_options, ingest_service, ingest_router)
-        })
+        .and(warp::ext::optional::<Principal>())
+        .then(
+            |body, bulk_options, ingest_service, ingest_router, principal_opt| {
+                elastic_ingest_bulk(
+                    None,
+                    body,
+                    bulk_options,
+                    ingest_service,
+                    ingest_router,
+                    principal_opt,
+                )
+            },
+        )
         .and(extract_format_from_qs())
-        .map(make_elastic_api_response)
+        .map(make_elastic_bulk_api_response)
         .recover(recover_fn)
 }
 
@@ -65,30 +80,64 @@ pub fn es_compat_index_bulk_handler(
     elastic_index_bulk_filter(content_length_limit)
         .and(with_arg(ingest_service))
         .and(with_arg(ingest_router))
+        .and(warp::ext::optional::<Principal>())
         .then(
-            |index_id, body, bulk_options, ingest_service, ingest_router| {
+            |index_id, body, bulk_options, ingest_service, ingest_router, principal_opt| {
                 elastic_ingest_bulk(
                     Some(index_id),
                     body,
                     bulk_options,
                     ingest_service,
                     ingest_router,
+                    principal_opt,
                 )
             },
         )
         .and(extract_format_from_qs())
-        .map(make_elastic_api_response)
+        .map(make_elastic_bulk_api_response)
         .recover(recover_fn)
         .boxed()
 }
 
+/// Builds the response of a bulk request. When all the actions of the request were rejected because
+/// the targeted indexes exceeded their ingest quotas, the whole response is a `429 Too Many
+/// Requests`. In any case, the `Retry-After` header is set if some actions were rejected for that
+/// reason.
+fn make_elastic_bulk_api_response(
+    bulk_result: Result<ElasticBulkResponse, ElasticsearchError>,
+    body_format: BodyFormat,
+) -> RestApiResponse {
+    let (status_code, retry_after_opt) = match &bulk_result {
+        Ok(bulk_response) => {
+            let all_actions_rate_limited = bulk_response.retry_after_opt.is_some()
+                && bulk_response.actions.iter().all(|action| {
+                    let (ElasticBulkAction::Create(item) | ElasticBulkAction::Index(item)) = action;
+                    item.status == StatusCode::TOO_MANY_REQUESTS
+                });
+            let status_code = if all_actions_rate_limited {
+                StatusCode::TOO_MANY_REQUESTS
+            } else {
+                StatusCode::OK
+            };
+            (status_code, bulk_response.retry_after_opt)
+        }
+        Err(error) => (error.status, None),
+    };
+    RestApiResponse::new(&bulk_result, status_code, body_format).with_retry_after(retry_after_opt)
+}
+
 async fn elastic_ingest_bulk(
     default_index_id: Option<IndexId>,
     body: Body,
     bulk_options: ElasticBulkOptions,
     ingest_service: IngestServiceClient,
     ingest_router: IngestRouterServiceClient,
+    principal_opt: Option<Principal>,
 ) -> Result<ElasticBulkResponse, ElasticsearchError> {
+    if principal_opt.is_some() {
+        let index_ids = bulk_request_index_ids(default_index_id.as_ref(), &body.content);
+        authorize_indexes(principal_opt.as_ref(), AuthPrivilege::Ingest, &index_ids)?;
+    }
     if enable_ingest_v2() || bulk_options.enable_ingest_v2 {
         return elastic_bulk_ingest_v2(default_index_id, body, bulk_options, ingest_router).await;
     }
@@ -101,6 +150,7 @@ async fn elastic_ingest_bulk(
     }
     let now = Instant::now();
     let mut doc_batch_builders = HashMap::new();
+    let mut actions = Vec::new();
     let mut lines = lines(&body.content).enumerate();
 
     while let Some((line_number, line)) = lines.next() {
@@ -121,8 +171,10 @@ async fn elastic_ingest_bulk(
         // when ingesting on /my-index/_bulk, if _index: is set to something else than my-index,
         // ES honors it and create the doc in the requested index. That is, `my-index` is a default
         // value in case _index: is missing, but not a constraint on each sub-action.
-        let index_id = action
-            .into_index_id()
+        let action_kind = action.kind();
+        let meta = action.into_meta();
+        let index_id = meta
+            .index_id
             .or_else(|| default_index_id.clone())
             .ok_or_else(|| {
                 ElasticsearchError::new(
@@ -133,9 +185,20 @@ async fn elastic_ingest_bulk(
             })?;
         let doc_batch_builder = doc_batch_builders
             .entry(index_id.clone())
-            .or_insert(DocBatchBuilder::new(index_id));
+            .or_insert(DocBatchBuilder::new(index_id.clone()));
 
         doc_batch_builder.ingest_doc(source);
+
+        // With ingest v1, the documents are only validated later by the indexing pipelines: the
+        // documents are reported as accepted in the ingest queue, not as created, since their
+        // outcome is unknown at this point.
+        let item = ElasticBulkItem {
+            index_id,
+            es_doc_id: meta.es_doc_id,
+            status: StatusCode::ACCEPTED,
+            error: None,
+        };
+        actions.push(ElasticBulkAction::new(action_kind, item));
     }
     let doc_batches = doc_batch_builders
         .into_values()
@@ -153,18 +216,36 @@ async fn elastic_ingest_bulk(
     let bulk_response = ElasticBulkResponse {
         took_millis,
         errors,
-        actions: Vec::new(),
+        actions,
+        retry_after_opt: None,
     };
     Ok(bulk_response)
 }
 
+/// Returns the IDs of the indexes targeted by the actions of a bulk request. Malformed actions are
+/// skipped: they are reported when the request is processed.
+fn bulk_request_index_ids(default_index_id: Option<&IndexId>, body: &Bytes) -> Vec<IndexId> {
+    let mut index_ids = BTreeSet::new();
+
+    for action_line in lines(body).step_by(2) {
+        let Ok(action) = serde_json::from_slice::<BulkAction>(action_line) else {
+            continue;
+        };
+        if let Some(index_id) = action.into_index_id().or_else(|| default_index_id.cloned()) {
+            index_ids.insert(index_id);
+        }
+    }
+    index_ids.into_iter().collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
     use std::time::Duration;
 
+    use bytes::Bytes;
     use hyper::StatusCode;
-    use quickwit_config::{IngestApiConfig, NodeConfig};
+    use quickwit_config::{AuthPrivilege, IngestApiConfig, NodeConfig, RoleConfig};
     use quickwit_index_management::IndexService;
     use quickwit_ingest::{FetchRequest, IngestServiceClient, SuggestTruncateRequest};
     use quickwit_metastore::metastore_for_test;
@@ -172,11 +253,17 @@ mod tests {
     use quickwit_proto::metastore::MetastoreServiceClient;
     use quickwit_search::MockSearchService;
     use quickwit_storage::StorageResolver;
+    use warp::Reply;
 
-    use crate::elasticsearch_api::bulk_v2::ElasticBulkResponse;
+    use super::{bulk_request_index_ids, make_elastic_bulk_api_response};
+    use crate::auth::Principal;
+    use crate::elasticsearch_api::bulk_v2::{
+        ElasticBulkAction, ElasticBulkItem, ElasticBulkResponse,
+    };
     use crate::elasticsearch_api::elastic_api_handlers;
     use crate::elasticsearch_api::model::ElasticsearchError;
     use crate::ingest_api::setup_ingest_service;
+    use crate::BodyFormat;
 
     #[tokio::test]
     async fn test_bulk_api_returns_404_if_index_id_does_not_exist() {
@@ -243,6 +330,15 @@ mod tests {
         assert_eq!(resp.status(), 200);
         let bulk_response: ElasticBulkResponse = serde_json::from_slice(resp.body()).unwrap();
         assert!(!bulk_response.errors);
+        assert_eq!(bulk_response.actions.len(), 3);
+
+        let ElasticBulkAction::Create(item) = &bulk_response.actions[1] else {
+            panic!("expected create action");
+        };
+        assert_eq!(item.index_id, "my-index-2");
+        assert_eq!(item.es_doc_id.as_deref(), Some("1"));
+        assert_eq!(item.status, StatusCode::ACCEPTED);
+        assert!(item.error.is_none());
         universe.assert_quit().await;
     }
 
@@ -521,4 +617,107 @@ mod tests {
             "Malformed action/metadata line [#0]. Details: `expected value at line 1 column 57`"
         );
     }
+
+    #[tokio::test]
+    async fn test_bulk_api_returns_403_if_principal_cannot_ingest() {
+        let config = Arc::new(NodeConfig::for_test());
+        let search_service = Arc::new(MockSearchService::new());
+        let ingest_service = IngestServiceClient::mocked();
+        let ingest_router = IngestRouterServiceClient::mocked();
+        let index_service =
+            IndexService::new(metastore_for_test(), StorageResolver::unconfigured());
+        let elastic_api_handlers = elastic_api_handlers(
+            config,
+            search_service,
+            ingest_service,
+            ingest_router,
+            MetastoreServiceClient::mocked(),
+            index_service,
+        );
+        let principal = Principal::new(
+            "ingest-bot".to_string(),
+            vec![RoleConfig {
+                name: "logs-writer".to_string(),
+                index_patterns: vec!["logs-*".to_string()],
+                privileges: vec![AuthPrivilege::Ingest],
+            }],
+        );
+        let payload = r#"
+            { "create" : { "_id" : "1"} }
+            {"id": 1, "message": "push"}
+            { "create" : { "_index" : "traces", "_id" : "2"} }
+            {"id": 2, "message": "push"}"#;
+        let resp = warp::test::request()
+            .path("/_elastic/logs-app/_bulk")
+            .method("POST")
+            .extension(principal)
+            .body(payload)
+            .reply(&elastic_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 403);
+        let es_error: ElasticsearchError = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(es_error.error.ty.unwrap(), "security_exception");
+        assert_eq!(
+            es_error.error.reason.unwrap(),
+            "principal `ingest-bot` does not have the `ingest` privilege on `traces`"
+        );
+    }
+
+    #[test]
+    fn test_bulk_request_index_ids() {
+        let payload = Bytes::from_static(
+            br#"
+            { "create" : { "_index" : "my-index-2", "_id" : "1"} }
+            {"id": 1, "message": "push"}
+            { "create" : { "_id" : "2"} }
+            {"id": 2, "message": "push"}
+            { "index" : { "_index" : "my-index-2" } }
+            {"id": 3, "message": "push"}
+            {"create": {"_index": "my-index-3"},}
+            {"id": 4, "message": "push"}"#,
+        );
+        let default_index_id = "my-index-1".to_string();
+        let index_ids = bulk_request_index_ids(Some(&default_index_id), &payload);
+        assert_eq!(index_ids, ["my-index-1", "my-index-2"]);
+
+        let index_ids = bulk_request_index_ids(None, &payload);
+        assert_eq!(index_ids, ["my-index-2"]);
+    }
+
+    #[test]
+    fn test_make_elastic_bulk_api_response_retry_after() {
+        let make_action = |status: StatusCode| {
+            ElasticBulkAction::Index(ElasticBulkItem {
+                index_id: "my-index".to_string(),
+                es_doc_id: None,
+                status,
+                error: None,
+            })
+        };
+        let bulk_response = ElasticBulkResponse {
+            took_millis: 0,
+            errors: true,
+            actions: vec![make_action(StatusCode::TOO_MANY_REQUESTS)],
+            retry_after_opt: Some(Duration::from_millis(2_500)),
+        };
+        let response = make_elastic_bulk_api_response(Ok(bulk_response), BodyFormat::default())
+            .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get("retry-after").unwrap(), "3");
+
+        // Partially rejected requests succeed but still advertise when to retry.
+        let bulk_response = ElasticBulkResponse {
+            took_millis: 0,
+            errors: true,
+            actions: vec![
+                make_action(StatusCode::CREATED),
+                make_action(StatusCode::TOO_MANY_REQUESTS),
+            ],
+            retry_after_opt: Some(Duration::from_millis(500)),
+        };
+        let response = make_elastic_bulk_api_response(Ok(bulk_response), BodyFormat::default())
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("retry-after").unwrap(), "1");
+    }
 }
@@ -18,7 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::HashMap;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use hyper::StatusCode;
 use quickwit_common::rate_limited_error;
@@ -27,12 +27,14 @@ use quickwit_ingest::IngestRequestV2Builder;
 use quickwit_proto::ingest::router::{
     IngestFailureReason, IngestResponseV2, IngestRouterService, IngestRouterServiceClient,
 };
-use quickwit_proto::ingest::CommitTypeV2;
+use quickwit_proto::ingest::{CommitTypeV2, ParseFailureReason};
 use quickwit_proto::types::{DocUid, IndexId};
 use serde::{Deserialize, Serialize};
 
 use super::model::ElasticException;
-use crate::elasticsearch_api::model::{BulkAction, ElasticBulkOptions, ElasticsearchError};
+use crate::elasticsearch_api::model::{
+    BulkAction, BulkActionKind, ElasticBulkOptions, ElasticsearchError,
+};
 use crate::ingest_api::lines;
 use crate::Body;
 
@@ -43,6 +45,10 @@ pub(crate) struct ElasticBulkResponse {
     pub errors: bool,
     #[serde(rename = "items")]
     pub actions: Vec<ElasticBulkAction>,
+    /// How long the client should wait before retrying the actions rejected because the targeted
+    /// indexes exceeded one of their ingest quotas.
+    #[serde(skip)]
+    pub retry_after_opt: Option<Duration>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +59,16 @@ pub(crate) enum ElasticBulkAction {
     Index(ElasticBulkItem),
 }
 
+impl ElasticBulkAction {
+    /// Builds the response item of an action, keyed by the kind of the action as in the request.
+    pub(crate) fn new(action_kind: BulkActionKind, item: ElasticBulkItem) -> Self {
+        match action_kind {
+            BulkActionKind::Create => Self::Create(item),
+            BulkActionKind::Index => Self::Index(item),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct ElasticBulkItem {
     #[serde(rename = "_index")]
@@ -78,6 +94,7 @@ type ElasticDocId = String;
 #[derive(Debug)]
 struct DocHandle {
     doc_position: usize,
+    action_kind: BulkActionKind,
     doc_uid: DocUid,
     es_doc_id: Option<ElasticDocId>,
     // Whether the document failed to parse. When the struct is instantiated, this value is set to
@@ -111,6 +128,7 @@ pub(crate) async fn elastic_bulk_ingest_v2(
                 Some(ElasticException::ActionRequestValidation),
             )
         })?;
+        let action_kind = action.kind();
         let meta = action.into_meta();
         // When ingesting into `/my-index/_bulk`, if `_index` is set to something other than
         // `my-index`, ES honors it and creates the doc for the requested index. That is,
@@ -130,6 +148,7 @@ pub(crate) async fn elastic_bulk_ingest_v2(
 
         let doc_handle = DocHandle {
             doc_position: action_count,
+            action_kind,
             doc_uid,
             es_doc_id: meta.es_doc_id,
             is_parse_failure: false,
@@ -167,6 +186,7 @@ fn make_elastic_bulk_response_v2(
 ) -> Result<ElasticBulkResponse, ElasticsearchError> {
     let mut positioned_actions: Vec<(usize, ElasticBulkAction)> = Vec::with_capacity(action_count);
     let mut errors = false;
+    let mut retry_after_opt: Option<Duration> = None;
 
     // Populate the items for each `IngestSuccess` subresponse. They may be partially successful and
     // contain some parse failures.
@@ -207,9 +227,17 @@ fn make_elastic_bulk_response_v2(
             let doc_handle = &mut doc_handles[doc_handle_idx];
             doc_handle.is_parse_failure = true;
 
+            // Documents that do not match the doc mapping of the index are reported as mapping
+            // conflicts, the other ones as malformed documents.
+            let exception = match parse_failure.reason() {
+                ParseFailureReason::InvalidSchema => ElasticException::MapperParsing,
+                ParseFailureReason::InvalidJson | ParseFailureReason::Unspecified => {
+                    ElasticException::DocumentParsing
+                }
+            };
             let error = ElasticBulkError {
                 index_id: Some(index_id.clone()),
-                exception: ElasticException::DocumentParsing,
+                exception,
                 reason: parse_failure.message,
             };
             let item = ElasticBulkItem {
@@ -218,7 +246,7 @@ fn make_elastic_bulk_response_v2(
                 status: StatusCode::BAD_REQUEST,
                 error: Some(error),
             };
-            let action = ElasticBulkAction::Index(item);
+            let action = ElasticBulkAction::new(doc_handle.action_kind, item);
             positioned_actions.push((doc_handle.doc_position, action));
         }
         // Populate the remaining successful items.
@@ -232,7 +260,7 @@ fn make_elastic_bulk_response_v2(
                 status: StatusCode::CREATED,
                 error: None,
             };
-            let action = ElasticBulkAction::Index(item);
+            let action = ElasticBulkAction::new(doc_handle.action_kind, item);
             positioned_actions.push((doc_handle.doc_position, action));
         }
     }
@@ -240,6 +268,11 @@ fn make_elastic_bulk_response_v2(
     for failure in ingest_response_v2.failures {
         errors = true;
 
+        if let Some(retry_after_ms) = failure.retry_after_ms {
+            let retry_after = Duration::from_millis(retry_after_ms);
+            retry_after_opt = retry_after_opt.max(Some(retry_after));
+        }
+
         // Find the doc handles for the subrequest.
         let doc_handles =
             remove_doc_handles(&mut per_subrequest_doc_handles, failure.subrequest_id)
@@ -278,6 +311,16 @@ fn make_elastic_bulk_response_v2(
                 format!("no shards available [{}]", failure.index_id),
                 StatusCode::TOO_MANY_REQUESTS,
             ),
+            IngestFailureReason::IndexRateLimited => (
+                ElasticException::RateLimited,
+                format!("index rate limiting [{}]", failure.index_id),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            IngestFailureReason::IndexWalQuotaExceeded => (
+                ElasticException::RateLimited,
+                format!("index WAL quota exceeded [{}]", failure.index_id),
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
             reason => {
                 let pretty_reason = reason
                     .as_str_name()
@@ -304,7 +347,7 @@ fn make_elastic_bulk_response_v2(
                 status,
                 error: Some(error),
             };
-            let action = ElasticBulkAction::Index(item);
+            let action = ElasticBulkAction::new(doc_handle.action_kind, item);
             positioned_actions.push((doc_handle.doc_position, action));
         }
     }
@@ -330,6 +373,7 @@ fn make_elastic_bulk_response_v2(
         took_millis,
         errors,
         actions,
+        retry_after_opt,
     };
     Ok(bulk_response)
 }
@@ -481,6 +525,10 @@ mod tests {
 
         let bulk_response: ElasticBulkResponse = serde_json::from_slice(response.body()).unwrap();
         assert!(!bulk_response.errors);
+        assert!(bulk_response
+            .actions
+            .iter()
+            .all(|action| matches!(action, ElasticBulkAction::Create(_))));
 
         let mut items = bulk_response
             .actions
@@ -671,12 +719,14 @@ mod tests {
                             index_id: "my-index-1".to_string(),
                             source_id: INGEST_V2_SOURCE_ID.to_string(),
                             reason: IngestFailureReason::IndexNotFound as i32,
+                            retry_after_ms: None,
                         },
                         IngestFailure {
                             subrequest_id: 1,
                             index_id: "my-index-2".to_string(),
                             source_id: INGEST_V2_SOURCE_ID.to_string(),
                             reason: IngestFailureReason::IndexNotFound as i32,
+                            retry_after_ms: None,
                         },
                     ],
                 })
@@ -737,6 +787,7 @@ mod tests {
                 index_id: "test-index-bar".to_string(),
                 source_id: "test-source".to_string(),
                 reason: IngestFailureReason::IndexNotFound as i32,
+                retry_after_ms: None,
             }],
         };
         let per_request_doc_handles = HashMap::from_iter([
@@ -745,12 +796,14 @@ mod tests {
                 vec![
                     DocHandle {
                         doc_position: 0,
+                        action_kind: BulkActionKind::Index,
                         doc_uid: DocUid::for_test(0),
                         es_doc_id: Some("0".to_string()),
                         is_parse_failure: false,
                     },
                     DocHandle {
                         doc_position: 1,
+                        action_kind: BulkActionKind::Index,
                         doc_uid: DocUid::for_test(1),
                         es_doc_id: Some("1".to_string()),
                         is_parse_failure: false,
@@ -761,6 +814,7 @@ mod tests {
                 1,
                 vec![DocHandle {
                     doc_position: 2,
+                    action_kind: BulkActionKind::Index,
                     doc_uid: DocUid::for_test(2),
                     es_doc_id: Some("2".to_string()),
                     is_parse_failure: false,
@@ -800,6 +854,171 @@ mod tests {
         assert_eq!(error.index_id.as_ref().unwrap(), "test-index-bar");
         assert_eq!(error.exception, ElasticException::IndexNotFound);
         assert_eq!(error.reason, "no such index [test-index-bar]");
+        assert!(response.retry_after_opt.is_none());
+    }
+
+    #[test]
+    fn test_make_elastic_bulk_response_v2_parse_failures() {
+        let ingest_response_v2 = IngestResponseV2 {
+            successes: vec![IngestSuccess {
+                subrequest_id: 0,
+                index_uid: Some(IndexUid::for_test("test-index-foo", 0)),
+                source_id: "test-source".to_string(),
+                shard_id: Some(ShardId::from(0)),
+                replication_position_inclusive: Some(Position::offset(0u64)),
+                num_ingested_docs: 1,
+                parse_failures: vec![
+                    ParseFailure {
+                        doc_uid: Some(DocUid::for_test(2)),
+                        reason: ParseFailureReason::InvalidSchema as i32,
+                        message: "failed to parse value `foo` for field `ts`".to_string(),
+                    },
+                    ParseFailure {
+                        doc_uid: Some(DocUid::for_test(1)),
+                        reason: ParseFailureReason::InvalidJson as i32,
+                        message: "failed to parse JSON document".to_string(),
+                    },
+                ],
+            }],
+            failures: Vec::new(),
+        };
+        let per_request_doc_handles = HashMap::from_iter([(
+            0,
+            vec![
+                DocHandle {
+                    doc_position: 0,
+                    action_kind: BulkActionKind::Create,
+                    doc_uid: DocUid::for_test(0),
+                    es_doc_id: None,
+                    is_parse_failure: false,
+                },
+                DocHandle {
+                    doc_position: 1,
+                    action_kind: BulkActionKind::Index,
+                    doc_uid: DocUid::for_test(1),
+                    es_doc_id: None,
+                    is_parse_failure: false,
+                },
+                DocHandle {
+                    doc_position: 2,
+                    action_kind: BulkActionKind::Create,
+                    doc_uid: DocUid::for_test(2),
+                    es_doc_id: Some("2".to_string()),
+                    is_parse_failure: false,
+                },
+            ],
+        )]);
+        let response = make_elastic_bulk_response_v2(
+            ingest_response_v2,
+            per_request_doc_handles,
+            Instant::now(),
+            3,
+        )
+        .unwrap();
+
+        assert!(response.errors);
+        assert_eq!(response.actions.len(), 3);
+
+        assert!(matches!(response.actions[0], ElasticBulkAction::Create(_)));
+        assert_eq!(response.actions[0].status(), StatusCode::CREATED);
+        assert!(response.actions[0].error().is_none());
+
+        assert!(matches!(response.actions[1], ElasticBulkAction::Index(_)));
+        assert_eq!(response.actions[1].status(), StatusCode::BAD_REQUEST);
+        let error = response.actions[1].error().unwrap();
+        assert_eq!(error.exception, ElasticException::DocumentParsing);
+        assert_eq!(error.reason, "failed to parse JSON document");
+
+        assert!(matches!(response.actions[2], ElasticBulkAction::Create(_)));
+        assert_eq!(response.actions[2].es_doc_id(), Some("2"));
+        assert_eq!(response.actions[2].status(), StatusCode::BAD_REQUEST);
+        let error = response.actions[2].error().unwrap();
+        assert_eq!(error.index_id.as_ref().unwrap(), "test-index-foo");
+        assert_eq!(error.exception, ElasticException::MapperParsing);
+        assert_eq!(error.reason, "failed to parse value `foo` for field `ts`");
+
+        let response_json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            response_json["items"][2],
+            serde_json::json!({
+                "create": {
+                    "_index": "test-index-foo",
+                    "_id": "2",
+                    "status": 400,
+                    "error": {
+                        "index": "test-index-foo",
+                        "type": "mapper_parsing_exception",
+                        "reason": "failed to parse value `foo` for field `ts`",
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_make_elastic_bulk_response_v2_quota_exceeded() {
+        let ingest_response_v2 = IngestResponseV2 {
+            successes: Vec::new(),
+            failures: vec![
+                IngestFailure {
+                    subrequest_id: 0,
+                    index_id: "test-index-foo".to_string(),
+                    source_id: "test-source".to_string(),
+                    reason: IngestFailureReason::IndexRateLimited as i32,
+                    retry_after_ms: Some(500),
+                },
+                IngestFailure {
+                    subrequest_id: 1,
+                    index_id: "test-index-bar".to_string(),
+                    source_id: "test-source".to_string(),
+                    reason: IngestFailureReason::IndexWalQuotaExceeded as i32,
+                    retry_after_ms: Some(10_000),
+                },
+            ],
+        };
+        let per_request_doc_handles = HashMap::from_iter([
+            (
+                0,
+                vec![DocHandle {
+                    doc_position: 0,
+                    action_kind: BulkActionKind::Index,
+                    doc_uid: DocUid::for_test(0),
+                    es_doc_id: None,
+                    is_parse_failure: false,
+                }],
+            ),
+            (
+                1,
+                vec![DocHandle {
+                    doc_position: 1,
+                    action_kind: BulkActionKind::Index,
+                    doc_uid: DocUid::for_test(1),
+                    es_doc_id: None,
+                    is_parse_failure: false,
+                }],
+            ),
+        ]);
+        let response = make_elastic_bulk_response_v2(
+            ingest_response_v2,
+            per_request_doc_handles,
+            Instant::now(),
+            2,
+        )
+        .unwrap();
+
+        assert!(response.errors);
+        assert_eq!(response.actions.len(), 2);
+        assert_eq!(response.retry_after_opt, Some(Duration::from_secs(10)));
+
+        assert_eq!(response.actions[0].status(), StatusCode::TOO_MANY_REQUESTS);
+        let error = response.actions[0].error().unwrap();
+        assert_eq!(error.exception, ElasticException::RateLimited);
+        assert_eq!(error.reason, "index rate limiting [test-index-foo]");
+
+        assert_eq!(response.actions[1].status(), StatusCode::TOO_MANY_REQUESTS);
+        let error = response.actions[1].error().unwrap();
+        assert_eq!(error.exception, ElasticException::RateLimited);
+        assert_eq!(error.reason, "index WAL quota exceeded [test-index-bar]");
     }
 
     #[tokio::test]
@@ -233,6 +233,29 @@ pub(crate) fn elastic_index_search_filter(
         .and(json_or_empty())
 }
 
+#[utoipa::path(post, tag = "Search", path = "/{index}/_async_search")]
+pub(crate) fn elastic_index_async_search_filter(
+) -> impl Filter<Extract = (Vec<String>, SearchQueryParams, SearchBody), Error = Rejection> + Clone
+{
+    warp::path!("_elastic" / String / "_async_search")
+        .and_then(extract_index_id_patterns)
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(json_or_empty())
+}
+
+#[utoipa::path(get, tag = "Search", path = "/_async_search/{id}")]
+pub(crate) fn elastic_get_async_search_filter(
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_async_search" / String).and(warp::get())
+}
+
+#[utoipa::path(delete, tag = "Search", path = "/_async_search/{id}")]
+pub(crate) fn elastic_delete_async_search_filter(
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_async_search" / String).and(warp::delete())
+}
+
 #[utoipa::path(post, tag = "Search", path = "/_msearch")]
 pub(crate) fn elastic_multi_search_filter(
 ) -> impl Filter<Extract = (Bytes, MultiSearchQueryParams), Error = Rejection> + Clone {
@@ -28,14 +28,16 @@ use std::sync::Arc;
 use bulk::{es_compat_bulk_handler, es_compat_index_bulk_handler};
 pub use filter::ElasticCompatibleApi;
 use hyper::StatusCode;
-use quickwit_config::NodeConfig;
+use quickwit_config::{AuthPrivilege, NodeConfig};
 use quickwit_index_management::IndexService;
 use quickwit_ingest::IngestServiceClient;
 use quickwit_proto::ingest::router::IngestRouterServiceClient;
 use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_search::SearchService;
 pub use rest_handler::{
-    es_compat_cat_indices_handler, es_compat_cluster_info_handler, es_compat_delete_index_handler,
+    es_compat_cat_indices_handler, es_compat_cluster_info_handler,
+    es_compat_delete_async_search_handler, es_compat_delete_index_handler,
+    es_compat_get_async_search_handler, es_compat_index_async_search_handler,
     es_compat_index_cat_indices_handler, es_compat_index_count_handler,
     es_compat_index_field_capabilities_handler, es_compat_index_multi_search_handler,
     es_compat_index_search_handler, es_compat_index_stats_handler, es_compat_resolve_index_handler,
@@ -44,7 +46,8 @@ pub use rest_handler::{
 use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection};
 
-use crate::elasticsearch_api::model::ElasticsearchError;
+use crate::auth::Principal;
+use crate::elasticsearch_api::model::{ElasticException, ElasticsearchError};
 use crate::rest::recover_fn;
 use crate::rest_api_response::RestApiResponse;
 use crate::{BodyFormat, BuildInfo};
@@ -78,6 +81,12 @@ pub fn elastic_api_handlers(
         .or(es_compat_index_search_handler(search_service.clone()))
         .or(es_compat_index_count_handler(search_service.clone()))
         .or(es_compat_scroll_handler(search_service.clone()))
+        .or(es_compat_index_async_search_handler(search_service.clone()))
+        .or(es_compat_get_async_search_handler(search_service.clone()))
+        .or(es_compat_delete_async_search_handler(
+            search_service.clone(),
+        ))
+        .boxed()
         .or(es_compat_index_multi_search_handler(search_service.clone()))
         .or(es_compat_index_field_capabilities_handler(
             search_service.clone(),
@@ -134,6 +143,27 @@ fn make_elastic_api_response<T: serde::Serialize>(
     RestApiResponse::new(&elasticsearch_result, status_code, body_format)
 }
 
+/// Checks that the principal, if authentication is enabled, is granted `privilege` on the indexes
+/// targeted by a request whose indexes are not part of the route, such as `_bulk` or `_msearch`.
+fn authorize_indexes<S: AsRef<str>>(
+    principal_opt: Option<&Principal>,
+    privilege: AuthPrivilege,
+    index_id_patterns: &[S],
+) -> Result<(), ElasticsearchError> {
+    let Some(principal) = principal_opt else {
+        return Ok(());
+    };
+    principal
+        .authorize(privilege, index_id_patterns)
+        .map_err(|auth_error| {
+            ElasticsearchError::new(
+                auth_error.status_code(),
+                auth_error.to_string(),
+                Some(ElasticException::Security),
+            )
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -146,9 +176,9 @@ mod tests {
     use quickwit_metastore::metastore_for_test;
     use quickwit_proto::ingest::router::IngestRouterServiceClient;
     use quickwit_proto::metastore::MetastoreServiceClient;
-    use quickwit_search::MockSearchService;
+    use quickwit_search::{AsyncSearchResponse, MockSearchService};
     use quickwit_storage::StorageResolver;
-    use serde_json::Value as JsonValue;
+    use serde_json::{json, Value as JsonValue};
     use warp::Filter;
 
     use super::elastic_api_handlers;
@@ -489,4 +519,125 @@ mod tests {
             .await;
         assert_eq!(resp.status(), 200);
     }
+
+    #[tokio::test]
+    async fn test_async_search_api() {
+        let config = Arc::new(NodeConfig::for_test());
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_submit_async_search()
+            .with(
+                predicate::function(|search_request: &quickwit_proto::search::SearchRequest| {
+                    search_request.index_id_patterns == vec!["index-1".to_string()]
+                        && search_request.max_hits == 5
+                }),
+                predicate::eq(std::time::Duration::from_secs(10 * 60)),
+                predicate::eq(std::time::Duration::ZERO),
+                predicate::eq(None),
+            )
+            .returning(|_, _, _, _| {
+                Ok(AsyncSearchResponse {
+                    id: "01HAV29D4XY3D462FS3D8K5Q2H".to_string(),
+                    owner_opt: None,
+                    index_id_patterns: vec!["index-1".to_string()],
+                    is_running: true,
+                    start_time_in_millis: 1_000,
+                    expiration_time_in_millis: 601_000,
+                    completion_time_in_millis: None,
+                    response: Some(quickwit_proto::search::SearchResponse {
+                        num_hits: 3,
+                        num_successful_splits: 1,
+                        ..Default::default()
+                    }),
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_get_async_search()
+            .returning(|async_search_id| {
+                Ok(AsyncSearchResponse {
+                    id: async_search_id,
+                    owner_opt: None,
+                    index_id_patterns: vec!["index-1".to_string()],
+                    is_running: false,
+                    start_time_in_millis: 1_000,
+                    expiration_time_in_millis: 601_000,
+                    completion_time_in_millis: Some(3_000),
+                    response: Some(quickwit_proto::search::SearchResponse {
+                        num_hits: 7,
+                        num_successful_splits: 2,
+                        ..Default::default()
+                    }),
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_delete_async_search()
+            .times(1)
+            .returning(|_| Ok(()));
+        let index_service =
+            IndexService::new(metastore_for_test(), StorageResolver::unconfigured());
+        let es_search_api_handler = super::elastic_api_handlers(
+            config,
+            Arc::new(mock_search_service),
+            ingest_service_client(),
+            IngestRouterServiceClient::mocked(),
+            MetastoreServiceClient::mocked(),
+            index_service,
+        );
+        let resp = warp::test::request()
+            .path(
+                "/_elastic/index-1/_async_search?keep_alive=10m&wait_for_completion_timeout=0s&\
+                 keep_on_completion=true",
+            )
+            .method("POST")
+            .json(&json!({"query": {"match_all": {}}, "size": 5}))
+            .reply(&es_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_json_include!(
+            actual: resp_json,
+            expected: json!({
+                "id": "01HAV29D4XY3D462FS3D8K5Q2H",
+                "is_partial": true,
+                "is_running": true,
+                "start_time_in_millis": 1_000,
+                "expiration_time_in_millis": 601_000,
+                "response": {
+                    "hits": {"total": {"value": 3}, "hits": []},
+                    "_shards": {"successful": 1},
+                },
+            })
+        );
+
+        let resp = warp::test::request()
+            .path("/_elastic/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+            .method("GET")
+            .reply(&es_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_json_include!(
+            actual: resp_json,
+            expected: json!({
+                "is_partial": false,
+                "is_running": false,
+                "completion_time_in_millis": 3_000,
+                "response": {
+                    "took": 2_000,
+                    "hits": {"total": {"value": 7}},
+                },
+            })
+        );
+
+        let resp = warp::test::request()
+            .path("/_elastic/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+            .method("DELETE")
+            .reply(&es_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp_json, json!({"acknowledged": true}));
+    }
 }
@@ -0,0 +1,44 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Serialize;
+
+use super::ElasticsearchResponse;
+
+/// Returns JSON in the format:
+///
+/// {
+///   "id": "01HAV29D4XY3D462FS3D8K5Q2H",
+///   "is_partial": true,
+///   "is_running": true,
+///   "start_time_in_millis": 1700000000000,
+///   "expiration_time_in_millis": 1700003600000,
+///   "response": {...}
+/// }
+#[derive(Serialize, Debug)]
+pub struct ElasticsearchAsyncSearchResponse {
+    pub id: String,
+    pub is_partial: bool,
+    pub is_running: bool,
+    pub start_time_in_millis: i64,
+    pub expiration_time_in_millis: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_time_in_millis: Option<i64>,
+    pub response: ElasticsearchResponse,
+}
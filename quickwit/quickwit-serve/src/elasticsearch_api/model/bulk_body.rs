@@ -27,7 +27,21 @@ pub enum BulkAction {
     Index(BulkActionMeta),
 }
 
+/// Kind of a bulk action, echoed by the corresponding item of the bulk response.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BulkActionKind {
+    Create,
+    Index,
+}
+
 impl BulkAction {
+    pub fn kind(&self) -> BulkActionKind {
+        match self {
+            BulkAction::Create(_) => BulkActionKind::Create,
+            BulkAction::Index(_) => BulkActionKind::Index,
+        }
+    }
+
     pub fn into_index_id(self) -> Option<IndexId> {
         match self {
             BulkAction::Index(meta) => meta.index_id,
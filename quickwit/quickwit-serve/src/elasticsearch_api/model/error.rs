@@ -153,9 +153,13 @@ pub enum ElasticException {
     IllegalArgument,
     #[serde(rename = "index_not_found_exception")]
     IndexNotFound,
+    #[serde(rename = "mapper_parsing_exception")]
+    MapperParsing,
     // This is an exception proper to Quickwit.
     #[serde(rename = "rate_limited_exception")]
     RateLimited,
+    #[serde(rename = "security_exception")]
+    Security,
     // This is an exception proper to Quickwit.
     #[serde(rename = "source_not_found_exception")]
     SourceNotFound,
@@ -170,8 +174,10 @@ impl ElasticException {
             Self::DocumentParsing => "document_parsing_exception",
             Self::Internal => "internal_exception",
             Self::RateLimited => "rate_limited_exception",
+            Self::Security => "security_exception",
             Self::IllegalArgument => "illegal_argument_exception",
             Self::IndexNotFound => "index_not_found_exception",
+            Self::MapperParsing => "mapper_parsing_exception",
             Self::SourceNotFound => "source_not_found_exception",
             Self::Timeout => "timeout_exception",
         }
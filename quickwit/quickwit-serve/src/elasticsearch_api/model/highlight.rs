@@ -0,0 +1,267 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use quickwit_proto::search::SnippetOptions;
+use quickwit_search::SearchError;
+use serde::{Deserialize, Deserializer};
+
+// Highlight doc: https://www.elastic.co/guide/en/elasticsearch/reference/current/highlighting.html
+
+const DEFAULT_ELASTIC_PRE_TAG: &str = "<em>";
+const DEFAULT_ELASTIC_POST_TAG: &str = "</em>";
+
+/// Highlighting options that can be set either globally or per field.
+///
+/// Other highlighting options (`type`, `encoder`, `order`, ...) are accepted but ignored.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+pub struct HighlightOptions {
+    #[serde(default)]
+    pub pre_tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub post_tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub fragment_size: Option<u64>,
+    #[serde(default)]
+    pub number_of_fragments: Option<u64>,
+}
+
+impl HighlightOptions {
+    /// Returns the options obtained by overriding `self` with the options set in `overrides`.
+    fn merge(&self, overrides: &HighlightOptions) -> HighlightOptions {
+        HighlightOptions {
+            pre_tags: overrides.pre_tags.clone().or_else(|| self.pre_tags.clone()),
+            post_tags: overrides
+                .post_tags
+                .clone()
+                .or_else(|| self.post_tags.clone()),
+            fragment_size: overrides.fragment_size.or(self.fragment_size),
+            number_of_fragments: overrides.number_of_fragments.or(self.number_of_fragments),
+        }
+    }
+}
+
+/// The `highlight` section of an Elasticsearch search request body.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct HighlightParams {
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_highlight_fields")]
+    pub fields: BTreeMap<String, HighlightOptions>,
+    #[serde(flatten)]
+    pub options: HighlightOptions,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HighlightFieldsForDeser {
+    Object(BTreeMap<String, HighlightOptions>),
+    // ES also accepts an array of single-field objects, to express an order between fields.
+    Array(Vec<BTreeMap<String, HighlightOptions>>),
+}
+
+fn deserialize_highlight_fields<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, HighlightOptions>, D::Error>
+where D: Deserializer<'de> {
+    let highlight_fields = match HighlightFieldsForDeser::deserialize(deserializer)? {
+        HighlightFieldsForDeser::Object(fields) => fields,
+        HighlightFieldsForDeser::Array(fields_array) => {
+            fields_array.into_iter().flatten().collect()
+        }
+    };
+    Ok(highlight_fields)
+}
+
+impl HighlightParams {
+    /// Translates the highlight parameters into the snippet fields and options of a
+    /// [`quickwit_proto::search::SearchRequest`].
+    ///
+    /// Snippet options are shared by all the snippet fields, so the options defined per field
+    /// must resolve to the same values.
+    pub fn to_snippet_fields_and_options(
+        &self,
+    ) -> Result<(Vec<String>, Option<SnippetOptions>), SearchError> {
+        if self.fields.is_empty() {
+            return Ok((Vec::new(), None));
+        }
+        let mut options_opt: Option<HighlightOptions> = None;
+
+        for (field_name, field_options) in &self.fields {
+            let options = self.options.merge(field_options);
+
+            match &options_opt {
+                Some(previous_options) if previous_options != &options => {
+                    return Err(SearchError::InvalidArgument(format!(
+                        "highlight options of field `{field_name}` differ from the options of the \
+                         other fields. only highlight options common to all fields are supported"
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    options_opt = Some(options);
+                }
+            }
+        }
+        let snippet_fields: Vec<String> = self.fields.keys().cloned().collect();
+        let options = options_opt.unwrap_or_default();
+        let pre_tag = options
+            .pre_tags
+            .and_then(|pre_tags| pre_tags.into_iter().next())
+            .unwrap_or_else(|| DEFAULT_ELASTIC_PRE_TAG.to_string());
+        let post_tag = options
+            .post_tags
+            .and_then(|post_tags| post_tags.into_iter().next())
+            .unwrap_or_else(|| DEFAULT_ELASTIC_POST_TAG.to_string());
+
+        // Setting `number_of_fragments` to 0 highlights the whole content of the field.
+        let (max_num_chars, max_num_snippets) = if options.number_of_fragments == Some(0) {
+            (Some(u32::MAX), None)
+        } else {
+            let max_num_chars = options
+                .fragment_size
+                .map(|fragment_size| fragment_size.min(u32::MAX as u64) as u32);
+            let max_num_snippets = options
+                .number_of_fragments
+                .map(|number_of_fragments| number_of_fragments.min(u32::MAX as u64) as u32);
+            (max_num_chars, max_num_snippets)
+        };
+        let snippet_options = SnippetOptions {
+            pre_tag: Some(pre_tag),
+            post_tag: Some(post_tag),
+            max_num_chars,
+            max_num_snippets,
+        };
+        Ok((snippet_fields, Some(snippet_options)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_params_deser() {
+        let highlight_params: HighlightParams = serde_json::from_str(
+            r#"{
+                "pre_tags": ["<mark>"],
+                "post_tags": ["</mark>"],
+                "fragment_size": 50,
+                "type": "plain",
+                "fields": {
+                    "title": {},
+                    "body": { "number_of_fragments": 2 }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(highlight_params.fields.len(), 2);
+        assert_eq!(highlight_params.fields["body"].number_of_fragments, Some(2));
+        assert_eq!(
+            highlight_params.options.pre_tags,
+            Some(vec!["<mark>".to_string()])
+        );
+        assert_eq!(highlight_params.options.fragment_size, Some(50));
+
+        let highlight_params: HighlightParams = serde_json::from_str(
+            r#"{
+                "fields": [{ "title": {} }, { "body": {} }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            highlight_params.fields.keys().collect::<Vec<_>>(),
+            ["body", "title"]
+        );
+    }
+
+    #[test]
+    fn test_highlight_params_to_snippet_options() {
+        let highlight_params: HighlightParams = serde_json::from_str(
+            r#"{
+                "fields": { "title": {}, "body": { "fragment_size": 50 } },
+                "fragment_size": 50,
+                "number_of_fragments": 3
+            }"#,
+        )
+        .unwrap();
+        let (snippet_fields, snippet_options) =
+            highlight_params.to_snippet_fields_and_options().unwrap();
+        assert_eq!(snippet_fields, ["body", "title"]);
+        assert_eq!(
+            snippet_options.unwrap(),
+            SnippetOptions {
+                pre_tag: Some("<em>".to_string()),
+                post_tag: Some("</em>".to_string()),
+                max_num_chars: Some(50),
+                max_num_snippets: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_highlight_params_whole_field() {
+        let highlight_params: HighlightParams = serde_json::from_str(
+            r#"{
+                "fields": { "*": {} },
+                "pre_tags": ["@HIGHLIGHT@"],
+                "post_tags": ["@/HIGHLIGHT@"],
+                "number_of_fragments": 0
+            }"#,
+        )
+        .unwrap();
+        let (snippet_fields, snippet_options) =
+            highlight_params.to_snippet_fields_and_options().unwrap();
+        assert_eq!(snippet_fields, ["*"]);
+        assert_eq!(
+            snippet_options.unwrap(),
+            SnippetOptions {
+                pre_tag: Some("@HIGHLIGHT@".to_string()),
+                post_tag: Some("@/HIGHLIGHT@".to_string()),
+                max_num_chars: Some(u32::MAX),
+                max_num_snippets: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_highlight_params_conflicting_field_options() {
+        let highlight_params: HighlightParams = serde_json::from_str(
+            r#"{
+                "fields": { "title": { "fragment_size": 20 }, "body": { "fragment_size": 50 } }
+            }"#,
+        )
+        .unwrap();
+        let error = highlight_params
+            .to_snippet_fields_and_options()
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("highlight options of field `title`"));
+    }
+
+    #[test]
+    fn test_highlight_params_no_fields() {
+        let highlight_params = HighlightParams::default();
+        let (snippet_fields, snippet_options) =
+            highlight_params.to_snippet_fields_and_options().unwrap();
+        assert!(snippet_fields.is_empty());
+        assert!(snippet_options.is_none());
+    }
+}
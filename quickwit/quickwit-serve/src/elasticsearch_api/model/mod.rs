@@ -17,18 +17,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod async_search;
 mod bulk_body;
 mod bulk_query_params;
 mod cat_indices;
 mod error;
 mod field_capability;
+mod highlight;
 mod multi_search;
 mod scroll;
 mod search_body;
 mod search_query_params;
+mod search_response;
+mod source_filter;
 mod stats;
 
-pub use bulk_body::BulkAction;
+pub use async_search::ElasticsearchAsyncSearchResponse;
+pub use bulk_body::{BulkAction, BulkActionKind};
 pub use bulk_query_params::ElasticBulkOptions;
 pub use cat_indices::{
     CatIndexQueryParams, ElasticsearchCatIndexResponse, ElasticsearchResolveIndexEntryResponse,
@@ -39,14 +44,17 @@ pub use field_capability::{
     build_list_field_request_for_es_api, convert_to_es_field_capabilities_response,
     FieldCapabilityQueryParams, FieldCapabilityRequestBody, FieldCapabilityResponse,
 };
+pub use highlight::HighlightParams;
 pub use multi_search::{
     MultiSearchHeader, MultiSearchQueryParams, MultiSearchResponse, MultiSearchSingleResponse,
 };
 use quickwit_proto::search::{SortDatetimeFormat, SortOrder};
 pub use scroll::ScrollQueryParams;
-pub use search_body::SearchBody;
+pub use search_body::{CollapseParams, SearchBody};
 pub use search_query_params::{DeleteQueryParams, SearchQueryParams, SearchQueryParamsCount};
+pub use search_response::{ElasticHit, ElasticHits, ElasticsearchResponse};
 use serde::{Deserialize, Serialize};
+pub use source_filter::SourceFilter;
 pub use stats::{ElasticsearchStatsResponse, StatsResponseEntry};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
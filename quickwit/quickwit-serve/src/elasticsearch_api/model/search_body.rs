@@ -21,11 +21,14 @@ use std::collections::BTreeSet;
 use std::fmt;
 
 use quickwit_proto::search::SortOrder;
-use quickwit_query::{ElasticQueryDsl, OneFieldMap};
+use quickwit_query::runtime_field::RuntimeMappings;
+use quickwit_query::{ElasticKnnSearch, ElasticQueryDsl, OneFieldMap};
 use serde::de::{MapAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_with::formats::PreferMany;
+use serde_with::{serde_as, OneOrMany};
 
-use super::ElasticDateFormat;
+use super::{ElasticDateFormat, HighlightParams, SourceFilter};
 use crate::elasticsearch_api::model::{default_elasticsearch_sort_order, SortField};
 use crate::elasticsearch_api::TrackTotalHits;
 
@@ -66,6 +69,7 @@ struct FieldSortParams {
     pub date_format: Option<ElasticDateFormat>,
 }
 
+#[serde_as]
 #[derive(Debug, Default, Clone, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct SearchBody {
@@ -75,6 +79,9 @@ pub struct SearchBody {
     pub size: Option<u64>,
     #[serde(default)]
     pub query: Option<ElasticQueryDsl>,
+    #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
+    #[serde(default)]
+    pub knn: Vec<ElasticKnnSearch>,
     #[serde(default)]
     #[serde(deserialize_with = "deserialize_field_sorts")]
     pub sort: Option<Vec<SortField>>,
@@ -86,20 +93,33 @@ pub struct SearchBody {
     pub stored_fields: Option<BTreeSet<String>>,
     #[serde(default)]
     pub search_after: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub highlight: Option<HighlightParams>,
+    #[serde(default)]
+    pub _source: Option<SourceFilter>,
+    #[serde(default)]
+    pub collapse: Option<CollapseParams>,
+    #[serde(default)]
+    pub runtime_mappings: Option<RuntimeMappings>,
 
     // Ignored values, only here for compatibility with OpenSearch Dashboards.
     #[serde(default)]
-    pub _source: serde::de::IgnoredAny,
-    #[serde(default)]
     pub docvalue_fields: serde::de::IgnoredAny,
     #[serde(default)]
     pub script_fields: serde::de::IgnoredAny,
     #[serde(default)]
-    pub highlight: serde::de::IgnoredAny,
-    #[serde(default)]
     pub version: serde::de::IgnoredAny,
 }
 
+/// Field collapsing parameters.
+///
+/// Only the best hit for each distinct value of `field` is returned.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CollapseParams {
+    pub field: String,
+}
+
 struct FieldSortVecVisitor;
 
 #[derive(Deserialize)]
@@ -261,6 +281,47 @@ mod tests {
         assert_eq!(field_sorts[3].order, SortOrder::Asc);
     }
 
+    #[test]
+    fn test_highlight() {
+        let json = r#"
+        {
+            "highlight": {
+                "pre_tags": ["<mark>"],
+                "post_tags": ["</mark>"],
+                "fields": { "body": {} }
+            }
+        }
+        "#;
+        let search_body: SearchBody = serde_json::from_str(json).unwrap();
+        let highlight = search_body.highlight.unwrap();
+        assert_eq!(highlight.fields.len(), 1);
+        assert!(highlight.fields.contains_key("body"));
+        assert_eq!(highlight.options.pre_tags, Some(vec!["<mark>".to_string()]));
+        assert_eq!(
+            highlight.options.post_tags,
+            Some(vec!["</mark>".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_source_filter() {
+        let json = r#"
+        {
+            "_source": {
+                "includes": ["actor.*"],
+                "excludes": "actor.id"
+            }
+        }
+        "#;
+        let search_body: SearchBody = serde_json::from_str(json).unwrap();
+        let source_filter = search_body._source.unwrap();
+        assert_eq!(source_filter.includes, ["actor.*"]);
+        assert_eq!(source_filter.excludes, ["actor.id"]);
+
+        let search_body: SearchBody = serde_json::from_str(r#"{ "_source": false }"#).unwrap();
+        assert!(search_body._source.unwrap().disabled);
+    }
+
     #[test]
     fn test_unknown_field_behaviour() {
         let json = r#"
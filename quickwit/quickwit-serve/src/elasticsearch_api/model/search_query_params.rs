@@ -95,6 +95,12 @@ pub struct SearchQueryParams {
     pub ignore_throttled: Option<bool>,
     #[serde(default)]
     pub ignore_unavailable: Option<bool>,
+    /// Only used by async searches.
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Only used by async searches. Async searches are always kept until they expire.
+    #[serde(default)]
+    pub keep_on_completion: Option<bool>,
     #[serde(default)]
     pub lenient: Option<bool>,
     #[serde(default)]
@@ -153,6 +159,9 @@ pub struct SearchQueryParams {
     pub typed_keys: Option<bool>,
     #[serde(default)]
     pub version: Option<bool>,
+    /// Only used by async searches.
+    #[serde(default)]
+    pub wait_for_completion_timeout: Option<String>,
 }
 
 #[serde_with::skip_serializing_none]
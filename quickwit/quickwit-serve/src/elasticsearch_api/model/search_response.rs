@@ -0,0 +1,74 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use elasticsearch_dsl::{ShardStatistics, Source, TotalHits};
+use serde::{Deserialize, Serialize};
+
+/// Search response of the Elasticsearch-compatible API.
+///
+/// It mirrors `elasticsearch_dsl::SearchResponse`, except that its hits omit the `_source` key
+/// when the source is disabled with `_source: false`, as Elasticsearch does.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ElasticsearchResponse {
+    pub took: u32,
+    pub timed_out: bool,
+    #[serde(rename = "_shards")]
+    pub shards: ShardStatistics,
+    pub hits: ElasticHits,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregations: Option<serde_json::Value>,
+    #[serde(default)]
+    #[serde(rename = "_scroll_id")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scroll_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ElasticHits {
+    pub total: Option<TotalHits>,
+    pub max_score: Option<f32>,
+    pub hits: Vec<ElasticHit>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ElasticHit {
+    #[serde(rename = "_index")]
+    pub index: String,
+    #[serde(rename = "_id")]
+    pub id: String,
+    #[serde(rename = "_score")]
+    pub score: Option<f32>,
+    /// `None` if the source is disabled.
+    #[serde(default)]
+    #[serde(rename = "_source")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub highlight: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<serde_json::Value>,
+}
@@ -0,0 +1,610 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+// Source filtering doc: https://www.elastic.co/guide/en/elasticsearch/reference/current/search-fields.html#source-filtering
+
+/// Describes which parts of the documents `_source` should be returned in the hits.
+///
+/// Include and exclude patterns are dotted paths, in which `*` matches any sequence of
+/// characters (dots included). A pattern matching an object matches its whole subtree.
+/// Excludes take precedence over includes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "SourceFilterForDeser")]
+pub struct SourceFilter {
+    /// If `true`, the `_source` is omitted from the hits.
+    pub disabled: bool,
+    /// If empty, all fields are included.
+    pub includes: Vec<String>,
+    pub excludes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrStrings {
+    String(String),
+    Strings(Vec<String>),
+}
+
+impl From<StringOrStrings> for Vec<String> {
+    fn from(string_or_strings: StringOrStrings) -> Self {
+        match string_or_strings {
+            StringOrStrings::String(string) => vec![string],
+            StringOrStrings::Strings(strings) => strings,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SourceFilterForDeser {
+    Enabled(bool),
+    Includes(StringOrStrings),
+    Object {
+        #[serde(default)]
+        #[serde(alias = "include")]
+        includes: Option<StringOrStrings>,
+        #[serde(default)]
+        #[serde(alias = "exclude")]
+        excludes: Option<StringOrStrings>,
+    },
+}
+
+impl From<SourceFilterForDeser> for SourceFilter {
+    fn from(for_deser: SourceFilterForDeser) -> SourceFilter {
+        match for_deser {
+            SourceFilterForDeser::Enabled(enabled) => SourceFilter {
+                disabled: !enabled,
+                ..Default::default()
+            },
+            SourceFilterForDeser::Includes(includes) => SourceFilter {
+                includes: includes.into(),
+                ..Default::default()
+            },
+            SourceFilterForDeser::Object { includes, excludes } => SourceFilter {
+                disabled: false,
+                includes: includes.map(Vec::from).unwrap_or_default(),
+                excludes: excludes.map(Vec::from).unwrap_or_default(),
+            },
+        }
+    }
+}
+
+impl SourceFilter {
+    /// Builds the source filter of a search request.
+    ///
+    /// The `_source`, `_source_includes` and `_source_excludes` query string parameters take
+    /// priority over the `_source` parameter of the request body.
+    pub fn from_params(
+        source_body_opt: Option<SourceFilter>,
+        source_param_opt: Option<&[String]>,
+        source_includes_param_opt: Option<&[String]>,
+        source_excludes_param_opt: Option<&[String]>,
+    ) -> SourceFilter {
+        let mut source_filter = source_body_opt.unwrap_or_default();
+
+        match source_param_opt {
+            Some([enabled]) if enabled == "true" || enabled == "false" => {
+                source_filter = SourceFilter {
+                    disabled: enabled == "false",
+                    ..Default::default()
+                };
+            }
+            Some(includes) => {
+                source_filter = SourceFilter {
+                    includes: includes.to_vec(),
+                    ..Default::default()
+                };
+            }
+            None => {}
+        }
+        if let Some(includes) = source_includes_param_opt {
+            source_filter.disabled = false;
+            source_filter.includes = includes.to_vec();
+        }
+        if let Some(excludes) = source_excludes_param_opt {
+            source_filter.disabled = false;
+            source_filter.excludes = excludes.to_vec();
+        }
+        source_filter
+    }
+
+    /// Returns true if the filter leaves documents untouched.
+    pub fn is_noop(&self) -> bool {
+        !self.disabled && self.includes.is_empty() && self.excludes.is_empty()
+    }
+
+    /// Filters the `_source` of a document in place.
+    pub fn filter(&self, source: &mut JsonValue) {
+        if self.is_noop() {
+            return;
+        }
+        let JsonValue::Object(json_obj) = source else {
+            return;
+        };
+        let include_patterns: Vec<PathPattern> = self
+            .includes
+            .iter()
+            .map(|include| PathPattern::new(include))
+            .collect();
+        let exclude_patterns: Vec<PathPattern> = self
+            .excludes
+            .iter()
+            .map(|exclude| PathPattern::new(exclude))
+            .collect();
+        let include_states: Option<Vec<PatternState>> = if include_patterns.is_empty() {
+            // No include patterns: everything is included.
+            None
+        } else {
+            Some(include_patterns.iter().map(PathPattern::start).collect())
+        };
+        let exclude_states: Vec<PatternState> =
+            exclude_patterns.iter().map(PathPattern::start).collect();
+        *json_obj = filter_object(
+            std::mem::take(json_obj),
+            &include_patterns,
+            include_states.as_deref(),
+            &exclude_patterns,
+            &exclude_states,
+        );
+    }
+}
+
+/// Filters the entries of a JSON object.
+///
+/// `include_states` is `None` if the object is entirely included.
+fn filter_object(
+    json_obj: JsonMap<String, JsonValue>,
+    include_patterns: &[PathPattern],
+    include_states: Option<&[PatternState]>,
+    exclude_patterns: &[PathPattern],
+    exclude_states: &[PatternState],
+) -> JsonMap<String, JsonValue> {
+    let mut filtered_json_obj = JsonMap::with_capacity(json_obj.len());
+
+    for (key, value) in json_obj {
+        let key_exclude_states: Vec<PatternState> =
+            advance_all(exclude_patterns, exclude_states, &key);
+        if key_exclude_states
+            .iter()
+            .zip(exclude_patterns)
+            .any(|(state, pattern)| pattern.is_match(state))
+        {
+            continue;
+        }
+        let key_include_states: Option<Vec<PatternState>> = match include_states {
+            Some(include_states) => {
+                let key_include_states = advance_all(include_patterns, include_states, &key);
+                let is_fully_included = key_include_states
+                    .iter()
+                    .zip(include_patterns)
+                    .any(|(state, pattern)| pattern.is_match(state));
+                if is_fully_included {
+                    None
+                } else if key_include_states.iter().all(PatternState::is_dead) {
+                    continue;
+                } else {
+                    Some(key_include_states)
+                }
+            }
+            None => None,
+        };
+        if let Some(filtered_value) = filter_value(
+            value,
+            include_patterns,
+            key_include_states.as_deref(),
+            exclude_patterns,
+            &key_exclude_states,
+        ) {
+            filtered_json_obj.insert(key, filtered_value);
+        }
+    }
+    filtered_json_obj
+}
+
+/// Filters a JSON value located at a path for which the pattern states are given.
+///
+/// Returns `None` if the value should be removed.
+fn filter_value(
+    value: JsonValue,
+    include_patterns: &[PathPattern],
+    include_states: Option<&[PatternState]>,
+    exclude_patterns: &[PathPattern],
+    exclude_states: &[PatternState],
+) -> Option<JsonValue> {
+    let is_fully_included = include_states.is_none();
+    match value {
+        JsonValue::Object(json_obj) => {
+            // Entering an object adds a `.` to the path.
+            let child_include_states: Option<Vec<PatternState>> = include_states
+                .map(|include_states| advance_all(include_patterns, include_states, "."));
+            let child_exclude_states = advance_all(exclude_patterns, exclude_states, ".");
+            let filtered_json_obj = filter_object(
+                json_obj,
+                include_patterns,
+                child_include_states.as_deref(),
+                exclude_patterns,
+                &child_exclude_states,
+            );
+            if is_fully_included || !filtered_json_obj.is_empty() {
+                Some(JsonValue::Object(filtered_json_obj))
+            } else {
+                None
+            }
+        }
+        JsonValue::Array(json_values) => {
+            // Array elements share the path of the array.
+            let filtered_json_values: Vec<JsonValue> = json_values
+                .into_iter()
+                .filter_map(|json_value| {
+                    filter_value(
+                        json_value,
+                        include_patterns,
+                        include_states,
+                        exclude_patterns,
+                        exclude_states,
+                    )
+                })
+                .collect();
+            if is_fully_included || !filtered_json_values.is_empty() {
+                Some(JsonValue::Array(filtered_json_values))
+            } else {
+                None
+            }
+        }
+        leaf_value => {
+            if is_fully_included {
+                Some(leaf_value)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn advance_all(patterns: &[PathPattern], states: &[PatternState], text: &str) -> Vec<PatternState> {
+    patterns
+        .iter()
+        .zip(states)
+        .map(|(pattern, state)| pattern.advance(state, text))
+        .collect()
+}
+
+/// A path pattern in which `*` matches any sequence of characters.
+///
+/// Patterns are matched incrementally, one path segment at a time, by tracking the set of
+/// positions in the pattern that can be reached after consuming the path so far.
+struct PathPattern {
+    pattern: Vec<char>,
+}
+
+/// The set of reachable positions in a [`PathPattern`]. An empty set means that neither the
+/// current path nor any of its descendants can match the pattern.
+#[derive(Clone)]
+struct PatternState {
+    positions: Vec<usize>,
+}
+
+impl PatternState {
+    fn is_dead(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+impl PathPattern {
+    fn new(pattern: &str) -> PathPattern {
+        PathPattern {
+            pattern: pattern.chars().collect(),
+        }
+    }
+
+    fn start(&self) -> PatternState {
+        let mut positions = vec![0];
+        self.close(&mut positions);
+        PatternState { positions }
+    }
+
+    /// Adds the positions reachable by skipping `*` without consuming any character.
+    fn close(&self, positions: &mut Vec<usize>) {
+        let mut idx = 0;
+        while idx < positions.len() {
+            let position = positions[idx];
+            if self.pattern.get(position) == Some(&'*') && !positions.contains(&(position + 1)) {
+                positions.push(position + 1);
+            }
+            idx += 1;
+        }
+    }
+
+    fn advance(&self, state: &PatternState, text: &str) -> PatternState {
+        let mut positions = state.positions.clone();
+
+        for c in text.chars() {
+            let mut next_positions: Vec<usize> = Vec::with_capacity(positions.len());
+
+            for &position in &positions {
+                let next_position = match self.pattern.get(position) {
+                    Some('*') => position,
+                    Some(pattern_char) if *pattern_char == c => position + 1,
+                    _ => continue,
+                };
+                if !next_positions.contains(&next_position) {
+                    next_positions.push(next_position);
+                }
+            }
+            self.close(&mut next_positions);
+            positions = next_positions;
+
+            if positions.is_empty() {
+                break;
+            }
+        }
+        PatternState { positions }
+    }
+
+    fn is_match(&self, state: &PatternState) -> bool {
+        state.positions.contains(&self.pattern.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[track_caller]
+    fn test_source_filter_aux(
+        source: JsonValue,
+        includes: &[&str],
+        excludes: &[&str],
+        expected_source: JsonValue,
+    ) {
+        let source_filter = SourceFilter {
+            disabled: false,
+            includes: includes.iter().map(|include| include.to_string()).collect(),
+            excludes: excludes.iter().map(|exclude| exclude.to_string()).collect(),
+        };
+        let mut source = source;
+        source_filter.filter(&mut source);
+        assert_eq!(source, expected_source);
+    }
+
+    #[test]
+    fn test_source_filter_deser() {
+        let source_filter: SourceFilter = serde_json::from_str("false").unwrap();
+        assert!(source_filter.disabled);
+
+        let source_filter: SourceFilter = serde_json::from_str("true").unwrap();
+        assert!(source_filter.is_noop());
+
+        let source_filter: SourceFilter = serde_json::from_str(r#""actor.*""#).unwrap();
+        assert_eq!(source_filter.includes, ["actor.*"]);
+
+        let source_filter: SourceFilter = serde_json::from_str(r#"["actor", "repo"]"#).unwrap();
+        assert_eq!(source_filter.includes, ["actor", "repo"]);
+
+        let source_filter: SourceFilter =
+            serde_json::from_str(r#"{"includes": ["actor"], "excludes": "actor.id"}"#).unwrap();
+        assert_eq!(source_filter.includes, ["actor"]);
+        assert_eq!(source_filter.excludes, ["actor.id"]);
+
+        let source_filter: SourceFilter =
+            serde_json::from_str(r#"{"exclude": ["actor.id"]}"#).unwrap();
+        assert!(source_filter.includes.is_empty());
+        assert_eq!(source_filter.excludes, ["actor.id"]);
+    }
+
+    #[test]
+    fn test_source_filter_from_params() {
+        let source_body = SourceFilter {
+            disabled: false,
+            includes: vec!["actor".to_string()],
+            excludes: vec!["actor.id".to_string()],
+        };
+        let source_filter = SourceFilter::from_params(Some(source_body.clone()), None, None, None);
+        assert_eq!(source_filter, source_body);
+
+        let source_filter = SourceFilter::from_params(
+            Some(source_body.clone()),
+            Some(&["false".to_string()]),
+            None,
+            None,
+        );
+        assert!(source_filter.disabled);
+
+        let source_filter = SourceFilter::from_params(
+            Some(source_body.clone()),
+            Some(&["repo".to_string()]),
+            None,
+            None,
+        );
+        assert_eq!(source_filter.includes, ["repo"]);
+        assert!(source_filter.excludes.is_empty());
+
+        let source_filter = SourceFilter::from_params(
+            Some(source_body),
+            None,
+            None,
+            Some(&["actor.login".to_string()]),
+        );
+        assert_eq!(source_filter.includes, ["actor"]);
+        assert_eq!(source_filter.excludes, ["actor.login"]);
+    }
+
+    #[test]
+    fn test_include_fields() {
+        test_source_filter_aux(
+            json!({
+                "app": { "id": 123, "name": "Blub" },
+                "user": { "id": 456, "name": "Fred" }
+            }),
+            &["app.id"],
+            &[],
+            json!({
+                "app": { "id": 123 }
+            }),
+        );
+        test_source_filter_aux(
+            json!({
+                "app": { "id": 123, "name": "Blub" },
+                "app.id": { "id": 123, "name": "Blub" },
+                "user": { "id": 456, "name": "Fred" }
+            }),
+            &["app", "app.id"],
+            &[],
+            json!({
+                "app": { "id": 123, "name": "Blub" },
+                "app.id": { "id": 123, "name": "Blub" },
+            }),
+        );
+    }
+
+    #[test]
+    fn test_exclude_fields() {
+        test_source_filter_aux(
+            json!({
+                "app": { "id": 123, "name": "Blub" },
+                "user": { "id": 456, "name": "Fred" }
+            }),
+            &[],
+            &["app.name", "user.id"],
+            json!({
+                "app": { "id": 123 },
+                "user": { "name": "Fred" }
+            }),
+        );
+    }
+
+    #[test]
+    fn test_include_and_exclude_fields() {
+        test_source_filter_aux(
+            json!({
+                "app": { "id": 123, "name": "Blub", "version": "1.0" },
+                "user": { "id": 456, "name": "Fred", "email": "john@example.com" }
+            }),
+            &["app", "user.name", "user.email"],
+            &["app.version", "user.email"],
+            json!({
+                "app": { "id": 123, "name": "Blub" },
+                "user": { "name": "Fred" }
+            }),
+        );
+    }
+
+    #[test]
+    fn test_no_includes_or_excludes() {
+        test_source_filter_aux(
+            json!({ "app": { "id": 123, "name": "Blub" } }),
+            &[],
+            &[],
+            json!({ "app": { "id": 123, "name": "Blub" } }),
+        );
+    }
+
+    #[test]
+    fn test_wildcard_fields() {
+        let source = json!({
+            "app": { "id": 123, "name": "Blub", "owner": { "id": 1, "name": "Fred" } },
+            "user": { "id": 456, "name": "Fred" },
+            "user_agent": "curl"
+        });
+        test_source_filter_aux(
+            source.clone(),
+            &["app.*"],
+            &[],
+            json!({
+                "app": { "id": 123, "name": "Blub", "owner": { "id": 1, "name": "Fred" } },
+            }),
+        );
+        test_source_filter_aux(
+            source.clone(),
+            &["*.id"],
+            &[],
+            json!({
+                "app": { "id": 123, "owner": { "id": 1 } },
+                "user": { "id": 456 },
+            }),
+        );
+        test_source_filter_aux(
+            source.clone(),
+            &["user*"],
+            &[],
+            json!({
+                "user": { "id": 456, "name": "Fred" },
+                "user_agent": "curl"
+            }),
+        );
+        test_source_filter_aux(
+            source.clone(),
+            &["app"],
+            &["app.*.name", "*.id"],
+            json!({
+                "app": { "name": "Blub", "owner": {} },
+            }),
+        );
+        test_source_filter_aux(source, &[], &["*"], json!({}));
+    }
+
+    #[test]
+    fn test_arrays() {
+        let source = json!({
+            "commits": [
+                { "sha": "abc", "author": { "name": "Fred", "email": "fred@example.com" } },
+                { "sha": "def", "author": { "name": "Paul" } }
+            ],
+            "tags": ["a", "b"]
+        });
+        test_source_filter_aux(
+            source.clone(),
+            &["commits.author.name", "tags"],
+            &[],
+            json!({
+                "commits": [
+                    { "author": { "name": "Fred" } },
+                    { "author": { "name": "Paul" } }
+                ],
+                "tags": ["a", "b"]
+            }),
+        );
+        test_source_filter_aux(
+            source.clone(),
+            &["commits.*.email"],
+            &[],
+            json!({
+                "commits": [
+                    { "author": { "email": "fred@example.com" } }
+                ]
+            }),
+        );
+        test_source_filter_aux(
+            source,
+            &[],
+            &["commits.sha", "tags"],
+            json!({
+                "commits": [
+                    { "author": { "name": "Fred", "email": "fred@example.com" } },
+                    { "author": { "name": "Paul" } }
+                ]
+            }),
+        );
+    }
+}
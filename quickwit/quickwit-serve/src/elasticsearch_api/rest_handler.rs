@@ -17,55 +17,64 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::from_utf8;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use bytes::Bytes;
-use elasticsearch_dsl::search::{Hit as ElasticHit, SearchResponse as ElasticsearchResponse};
-use elasticsearch_dsl::{HitsMetadata, ShardStatistics, Source, TotalHits, TotalHitsRelation};
+use elasticsearch_dsl::{
+    ErrorCause, ShardFailure, ShardStatistics, Source, TotalHits, TotalHitsRelation,
+};
 use futures_util::StreamExt;
 use hyper::StatusCode;
 use itertools::Itertools;
 use quickwit_common::truncate_str;
-use quickwit_config::{validate_index_id_pattern, NodeConfig};
+use quickwit_config::{validate_index_id_pattern, AuthPrivilege, NodeConfig};
 use quickwit_index_management::IndexService;
 use quickwit_metastore::*;
 use quickwit_proto::metastore::MetastoreServiceClient;
 use quickwit_proto::search::{
     CountHits, ListFieldsResponse, PartialHit, ScrollRequest, SearchResponse, SortByValue,
-    SortDatetimeFormat,
+    SortDatetimeFormat, SplitSearchError,
 };
 use quickwit_proto::types::IndexUid;
 use quickwit_query::query_ast::{BoolQuery, QueryAst, UserInputQuery};
 use quickwit_query::BooleanOperand;
-use quickwit_search::{list_all_splits, resolve_index_patterns, SearchError, SearchService};
+use quickwit_search::{
+    list_all_splits, resolve_index_patterns, AsyncSearchResponse, SearchError, SearchService,
+    DEFAULT_ASYNC_SEARCH_KEEP_ALIVE, DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use time::OffsetDateTime;
 use warp::{Filter, Rejection};
 
 use super::filter::{
-    elastic_cat_indices_filter, elastic_cluster_info_filter, elastic_delete_index_filter,
-    elastic_field_capabilities_filter, elastic_index_cat_indices_filter,
-    elastic_index_count_filter, elastic_index_field_capabilities_filter,
-    elastic_index_search_filter, elastic_index_stats_filter, elastic_multi_search_filter,
-    elastic_resolve_index_filter, elastic_scroll_filter, elastic_stats_filter,
-    elasticsearch_filter,
+    elastic_cat_indices_filter, elastic_cluster_info_filter, elastic_delete_async_search_filter,
+    elastic_delete_index_filter, elastic_field_capabilities_filter,
+    elastic_get_async_search_filter, elastic_index_async_search_filter,
+    elastic_index_cat_indices_filter, elastic_index_count_filter,
+    elastic_index_field_capabilities_filter, elastic_index_search_filter,
+    elastic_index_stats_filter, elastic_multi_search_filter, elastic_resolve_index_filter,
+    elastic_scroll_filter, elastic_stats_filter, elasticsearch_filter,
 };
 use super::model::{
     build_list_field_request_for_es_api, convert_to_es_field_capabilities_response,
-    CatIndexQueryParams, DeleteQueryParams, ElasticsearchCatIndexResponse, ElasticsearchError,
+    CatIndexQueryParams, DeleteQueryParams, ElasticHit, ElasticHits,
+    ElasticsearchAsyncSearchResponse, ElasticsearchCatIndexResponse, ElasticsearchError,
     ElasticsearchResolveIndexEntryResponse, ElasticsearchResolveIndexResponse,
-    ElasticsearchStatsResponse, FieldCapabilityQueryParams, FieldCapabilityRequestBody,
-    FieldCapabilityResponse, MultiSearchHeader, MultiSearchQueryParams, MultiSearchResponse,
-    MultiSearchSingleResponse, ScrollQueryParams, SearchBody, SearchQueryParams,
-    SearchQueryParamsCount, StatsResponseEntry,
+    ElasticsearchResponse, ElasticsearchStatsResponse, FieldCapabilityQueryParams,
+    FieldCapabilityRequestBody, FieldCapabilityResponse, MultiSearchHeader, MultiSearchQueryParams,
+    MultiSearchResponse, MultiSearchSingleResponse, ScrollQueryParams, SearchBody,
+    SearchQueryParams, SearchQueryParamsCount, SourceFilter, StatsResponseEntry,
 };
-use super::{make_elastic_api_response, TrackTotalHits};
+use super::{authorize_indexes, make_elastic_api_response, TrackTotalHits};
+use crate::auth::Principal;
 use crate::format::BodyFormat;
 use crate::rest::recover_fn;
 use crate::rest_api_response::{RestApiError, RestApiResponse};
+use crate::search_api::{authorize_async_search, parse_async_search_duration};
 use crate::{with_arg, BuildInfo};
 
 /// Elastic compatible cluster info handler.
@@ -210,6 +219,45 @@ pub fn es_compat_index_search_handler(
         .boxed()
 }
 
+/// POST _elastic/{index}/_async_search
+pub fn es_compat_index_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_index_async_search_filter()
+        .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
+        .then(es_compat_index_async_search)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// GET _elastic/_async_search/{id}
+pub fn es_compat_get_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_get_async_search_filter()
+        .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
+        .then(es_compat_get_async_search)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
+/// DELETE _elastic/_async_search/{id}
+pub fn es_compat_delete_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    elastic_delete_async_search_filter()
+        .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
+        .then(es_compat_delete_async_search)
+        .map(|result| make_elastic_api_response(result, BodyFormat::default()))
+        .recover(recover_fn)
+        .boxed()
+}
+
 /// GET or POST _elastic/{index}/_count
 pub fn es_compat_index_count_handler(
     search_service: Arc<dyn SearchService>,
@@ -228,6 +276,7 @@ pub fn es_compat_index_multi_search_handler(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     elastic_multi_search_filter()
         .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
         .then(es_compat_index_multi_search)
         .map(|result: Result<MultiSearchResponse, ElasticsearchError>| {
             let status_code = match &result {
@@ -258,22 +307,42 @@ fn build_request_for_es_api(
     search_body: SearchBody,
 ) -> Result<(quickwit_proto::search::SearchRequest, bool), ElasticsearchError> {
     let default_operator = search_params.default_operator.unwrap_or(BooleanOperand::Or);
+    let allow_partial_search_results = search_params.allow_partial_search_results();
     // The query string, if present, takes priority over what can be in the request
     // body.
-    let mut query_ast = if let Some(q) = &search_params.q {
+    let query_ast_opt: Option<QueryAst> = if let Some(q) = &search_params.q {
         let user_text_query = UserInputQuery {
             user_text: q.to_string(),
             default_fields: None,
             default_operator,
             lenient: false,
         };
-        user_text_query.into()
+        Some(user_text_query.into())
     } else if let Some(query_dsl) = search_body.query {
-        query_dsl
+        let query_ast = query_dsl
             .try_into()
-            .map_err(|err: anyhow::Error| SearchError::InvalidQuery(err.to_string()))?
+            .map_err(|err: anyhow::Error| SearchError::InvalidQuery(err.to_string()))?;
+        Some(query_ast)
     } else {
-        QueryAst::MatchAll
+        None
+    };
+
+    let mut query_ast = if search_body.knn.is_empty() {
+        query_ast_opt.unwrap_or(QueryAst::MatchAll)
+    } else {
+        // Like in Elasticsearch, the nearest neighbors are combined with the hits of the query,
+        // and their scores are summed.
+        let mut should: Vec<QueryAst> = query_ast_opt.into_iter().collect();
+        for knn_search in search_body.knn {
+            let knn_query_ast: QueryAst = knn_search
+                .try_into()
+                .map_err(|err: anyhow::Error| SearchError::InvalidQuery(err.to_string()))?;
+            should.push(knn_query_ast);
+        }
+        QueryAst::Bool(BoolQuery {
+            should,
+            ..Default::default()
+        })
     };
 
     if let Some(extra_filters) = &search_params.extra_filters {
@@ -333,11 +402,6 @@ fn build_request_for_es_api(
         })
         .take_while_inclusive(|sort_field| !is_doc_field(sort_field))
         .collect();
-    if sort_fields.len() >= 3 {
-        return Err(ElasticsearchError::from(SearchError::InvalidArgument(
-            format!("only up to two sort fields supported at the moment. got {sort_fields:?}"),
-        )));
-    }
 
     let scroll_duration: Option<Duration> = search_params.parse_scroll_ttl()?;
     let scroll_ttl_secs: Option<u32> = scroll_duration.map(|duration| duration.as_secs() as u32);
@@ -345,6 +409,12 @@ fn build_request_for_es_api(
     let has_doc_id_field = sort_fields.iter().any(is_doc_field);
     let search_after = partial_hit_from_search_after_param(search_body.search_after, &sort_fields)?;
 
+    let (snippet_fields, snippet_options) = if let Some(highlight) = &search_body.highlight {
+        highlight.to_snippet_fields_and_options()?
+    } else {
+        (Vec::new(), None)
+    };
+
     Ok((
         quickwit_proto::search::SearchRequest {
             index_id_patterns,
@@ -355,10 +425,18 @@ fn build_request_for_es_api(
             sort_fields,
             start_timestamp: None,
             end_timestamp: None,
-            snippet_fields: Vec::new(),
+            snippet_fields,
+            snippet_options,
             scroll_ttl_secs,
             search_after,
             count_hits,
+            collapse_field: search_body.collapse.map(|collapse| collapse.field),
+            runtime_mappings: search_body.runtime_mappings.map(|runtime_mappings| {
+                serde_json::to_string(&runtime_mappings)
+                    .expect("runtime mappings should be JSON serializable")
+            }),
+            allow_partial_search_results: Some(allow_partial_search_results),
+            source_fields: Vec::new(),
         },
         has_doc_id_field,
     ))
@@ -368,6 +446,16 @@ fn is_doc_field(field: &quickwit_proto::search::SortField) -> bool {
     field.field_name == "_shard_doc" || field.field_name == "_doc"
 }
 
+/// Returns the number of sort values of the hits of a search request. The doc field, if any, is
+/// always the last sort field and is rendered separately.
+fn num_sort_values(search_request: &quickwit_proto::search::SearchRequest) -> usize {
+    search_request
+        .sort_fields
+        .iter()
+        .filter(|sort_field| !is_doc_field(sort_field))
+        .count()
+}
+
 fn partial_hit_from_search_after_param(
     search_after: Vec<serde_json::Value>,
     sort_order: &[quickwit_proto::search::SortField],
@@ -415,12 +503,7 @@ fn partial_hit_from_search_after_param(
                     None,
                 )
             })?;
-            // TODO make cleaner once we support Vec
-            if parsed_search_after.sort_value.is_none() {
-                parsed_search_after.sort_value = Some(value);
-            } else {
-                parsed_search_after.sort_value2 = Some(value);
-            }
+            parsed_search_after.push_sort_value(value);
         }
     }
     Ok(Some(parsed_search_after))
@@ -459,19 +542,26 @@ async fn es_compat_index_search(
                 .to_string(),
         )));
     }
-    let _source_excludes = search_params._source_excludes.clone();
-    let _source_includes = search_params._source_includes.clone();
+    let source_filter = SourceFilter::from_params(
+        search_body._source.clone(),
+        search_params._source.as_deref(),
+        search_params._source_includes.as_deref(),
+        search_params._source_excludes.as_deref(),
+    );
     let start_instant = Instant::now();
     let allow_partial_search_results = search_params.allow_partial_search_results();
     let (search_request, append_shard_doc) =
         build_request_for_es_api(index_id_patterns, search_params, search_body)?;
+    let collapse_field_opt = search_request.collapse_field.clone();
+    let num_sort_values = num_sort_values(&search_request);
     let search_response: SearchResponse = search_service.root_search(search_request).await?;
     let elapsed = start_instant.elapsed();
     let mut search_response_rest: ElasticsearchResponse = convert_to_es_search_response(
         search_response,
         append_shard_doc,
-        _source_excludes,
-        _source_includes,
+        Some(num_sort_values),
+        &source_filter,
+        collapse_field_opt.as_deref(),
         allow_partial_search_results,
     )?;
     search_response_rest.took = elapsed.as_millis() as u32;
@@ -488,6 +578,101 @@ pub struct ElasticsearchDeleteResponse {
     pub acknowledged: bool,
 }
 
+async fn es_compat_index_async_search(
+    index_id_patterns: Vec<String>,
+    search_params: SearchQueryParams,
+    search_body: SearchBody,
+    search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
+) -> Result<ElasticsearchAsyncSearchResponse, ElasticsearchError> {
+    let keep_alive = parse_async_search_duration(
+        "keep_alive",
+        search_params.keep_alive.as_deref(),
+        DEFAULT_ASYNC_SEARCH_KEEP_ALIVE,
+    )?;
+    let wait_for_completion_timeout = parse_async_search_duration(
+        "wait_for_completion_timeout",
+        search_params.wait_for_completion_timeout.as_deref(),
+        DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT,
+    )?;
+    let source_filter = SourceFilter::from_params(
+        search_body._source.clone(),
+        search_params._source.as_deref(),
+        search_params._source_includes.as_deref(),
+        search_params._source_excludes.as_deref(),
+    );
+    let allow_partial_search_results = search_params.allow_partial_search_results();
+    let (search_request, append_shard_doc) =
+        build_request_for_es_api(index_id_patterns, search_params, search_body)?;
+    let collapse_field_opt = search_request.collapse_field.clone();
+    let num_sort_values = num_sort_values(&search_request);
+    let owner_opt = principal_opt.map(|principal| principal.name().to_string());
+    let async_search_response = search_service
+        .submit_async_search(
+            search_request,
+            keep_alive,
+            wait_for_completion_timeout,
+            owner_opt,
+        )
+        .await?;
+    convert_to_es_async_search_response(
+        async_search_response,
+        append_shard_doc,
+        Some(num_sort_values),
+        &source_filter,
+        collapse_field_opt.as_deref(),
+        allow_partial_search_results,
+    )
+}
+
+async fn get_authorized_async_search(
+    async_search_id: String,
+    search_service: &dyn SearchService,
+    principal_opt: Option<&Principal>,
+) -> Result<AsyncSearchResponse, ElasticsearchError> {
+    let async_search_response = search_service.get_async_search(async_search_id).await?;
+    authorize_async_search(&async_search_response, principal_opt).map_err(|rest_api_error| {
+        ElasticsearchError::new(rest_api_error.status_code, rest_api_error.message, None)
+    })?;
+    Ok(async_search_response)
+}
+
+async fn es_compat_get_async_search(
+    async_search_id: String,
+    search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
+) -> Result<ElasticsearchAsyncSearchResponse, ElasticsearchError> {
+    let async_search_response =
+        get_authorized_async_search(async_search_id, &*search_service, principal_opt.as_ref())
+            .await?;
+    // Like for scroll requests, the sort fields and the source filter of the initial request are
+    // not known, so missing sort values are omitted and sources are not filtered. Partial results
+    // are allowed, because the search fails on the first failed split otherwise.
+    convert_to_es_async_search_response(
+        async_search_response,
+        false,
+        None,
+        &SourceFilter::default(),
+        None,
+        true,
+    )
+}
+
+async fn es_compat_delete_async_search(
+    async_search_id: String,
+    search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
+) -> Result<ElasticsearchDeleteResponse, ElasticsearchError> {
+    get_authorized_async_search(
+        async_search_id.clone(),
+        &*search_service,
+        principal_opt.as_ref(),
+    )
+    .await?;
+    search_service.delete_async_search(async_search_id).await?;
+    Ok(ElasticsearchDeleteResponse { acknowledged: true })
+}
+
 async fn es_compat_delete_index(
     index_id_patterns: Vec<String>,
     query_params: DeleteQueryParams,
@@ -621,132 +806,91 @@ async fn es_compat_index_field_capabilities(
     Ok(search_response_rest)
 }
 
-fn filter_source(
-    value: &mut serde_json::Value,
-    _source_excludes: &Option<Vec<String>>,
-    _source_includes: &Option<Vec<String>>,
-) {
-    fn remove_path(value: &mut serde_json::Value, path: &str) {
-        for (prefix, suffix) in generate_path_variants_with_suffix(path) {
-            match value {
-                serde_json::Value::Object(ref mut map) => {
-                    if let Some(suffix) = suffix {
-                        if let Some(sub_value) = map.get_mut(prefix) {
-                            remove_path(sub_value, suffix);
-                            return;
-                        }
-                    } else {
-                        map.remove(prefix);
-                    }
-                }
-                _ => continue,
-            }
-        }
-    }
-    fn retain_includes(
-        value: &mut serde_json::Value,
-        current_path: &str,
-        include_paths: &Vec<String>,
-    ) {
-        if let Some(ref mut map) = value.as_object_mut() {
-            map.retain(|key, sub_value| {
-                let path = if current_path.is_empty() {
-                    key.to_string()
-                } else {
-                    format!("{}.{}", current_path, key)
-                };
-
-                if include_paths.contains(&path) {
-                    // Exact match keep whole node
-                    return true;
-                }
-                // Check if the path is sub path of any allowed path
-                for allowed_path in include_paths {
-                    if allowed_path.starts_with(path.as_str()) {
-                        retain_includes(sub_value, &path, include_paths);
-                        return true;
-                    }
-                }
-                false
-            });
-        }
-    }
-
-    // Remove fields that are not included
-    if let Some(includes) = _source_includes {
-        retain_includes(value, "", includes);
-    }
-
-    // Remove fields that are excluded
-    if let Some(excludes) = _source_excludes {
-        for exclude in excludes {
-            remove_path(value, exclude);
-        }
-    }
-}
-
-/// "app.id.name" -> [("app", Some("id.name")), ("app.id", Some("name")), ("app.id.name", None)]
-fn generate_path_variants_with_suffix(input: &str) -> Vec<(&str, Option<&str>)> {
-    let mut variants = Vec::new();
-
-    // Iterate over each character in the input.
-    for (idx, ch) in input.char_indices() {
-        if ch == '.' {
-            // If a dot is found, create a variant using the current slice and the remainder of the
-            // string.
-            let prefix = &input[0..idx];
-            let suffix = if idx + 1 < input.len() {
-                Some(&input[idx + 1..])
-            } else {
-                None
-            };
-            variants.push((prefix, suffix));
-        }
-    }
-
-    variants.push((&input[0..], None));
-
-    variants
-}
-
+/// Converts a Quickwit hit into an Elasticsearch hit.
+///
+/// If `num_sort_values` is set, missing sort values are rendered as `null` so that the `sort`
+/// array can be passed back as `search_after`. Otherwise, they are omitted.
 fn convert_hit(
     hit: quickwit_proto::search::Hit,
     append_shard_doc: bool,
-    _source_excludes: &Option<Vec<String>>,
-    _source_includes: &Option<Vec<String>>,
+    num_sort_values: Option<usize>,
+    source_filter: &SourceFilter,
+    collapse_field_opt: Option<&str>,
 ) -> ElasticHit {
-    let mut json: serde_json::Value = serde_json::from_str(&hit.json).unwrap_or(json!({}));
-    filter_source(&mut json, _source_excludes, _source_includes);
-    let source =
-        Source::from_string(serde_json::to_string(&json).unwrap_or_else(|_| "{}".to_string()))
-            .unwrap_or_else(|_| Source::from_string("{}".to_string()).unwrap());
+    let source_opt = if source_filter.disabled {
+        None
+    } else {
+        let mut json: serde_json::Value = serde_json::from_str(&hit.json).unwrap_or(json!({}));
+        source_filter.filter(&mut json);
+        let source =
+            Source::from_string(serde_json::to_string(&json).unwrap_or_else(|_| "{}".to_string()))
+                .unwrap_or_else(|_| Source::from_string("{}".to_string()).unwrap());
+        Some(source)
+    };
+
+    let highlight = hit
+        .snippet
+        .and_then(|snippet_json| {
+            serde_json::from_str::<BTreeMap<String, Vec<String>>>(&snippet_json).ok()
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_field_name, fragments)| !fragments.is_empty())
+        .collect();
 
     let mut sort = Vec::new();
+    let mut fields: Vec<(String, serde_json::Value)> = Vec::new();
+    let mut id = String::new();
     if let Some(partial_hit) = hit.partial_hit {
-        if let Some(sort_value) = partial_hit.sort_value {
-            sort.push(sort_value.into_json());
-        }
-        if let Some(sort_value2) = partial_hit.sort_value2 {
-            sort.push(sort_value2.into_json());
+        // Quickwit documents do not have a built-in identifier: hits are identified by their
+        // address, which the `ids` query accepts.
+        let global_doc_address = quickwit_search::GlobalDocAddress::from_partial_hit(&partial_hit);
+        if let Some(num_sort_values) = num_sort_values {
+            sort.extend(
+                partial_hit
+                    .sort_by_values()
+                    .chain(std::iter::repeat(None))
+                    .take(num_sort_values)
+                    .map(|sort_value_opt| {
+                        sort_value_opt
+                            .map(|sort_value| sort_value.clone().into_json())
+                            .unwrap_or(serde_json::Value::Null)
+                    }),
+            );
+        } else {
+            sort.extend(
+                partial_hit
+                    .sort_by_values()
+                    .flatten()
+                    .filter(|sort_value| sort_value.sort_value.is_some())
+                    .map(|sort_value| sort_value.clone().into_json()),
+            );
         }
         if append_shard_doc {
-            sort.push(serde_json::Value::String(
-                quickwit_search::GlobalDocAddress::from_partial_hit(&partial_hit).to_string(),
-            ));
+            sort.push(serde_json::Value::String(global_doc_address.to_string()));
+        }
+        id = global_doc_address.to_string();
+        // Like Elasticsearch, we return the value of the collapse field in the hit fields.
+        if let Some((collapse_field, collapse_value)) =
+            collapse_field_opt.zip(partial_hit.collapse_value)
+        {
+            fields.push((collapse_field.to_string(), json!([collapse_value])));
         }
     }
+    // Like Elasticsearch, we return the values of the runtime fields in the hit fields.
+    if let Some(runtime_field_values) = hit.fields_json.and_then(|fields_json| {
+        serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&fields_json).ok()
+    }) {
+        fields.extend(runtime_field_values);
+    }
 
     ElasticHit {
-        fields: Default::default(),
-        explanation: None,
         index: hit.index_id,
-        id: "".to_string(),
+        id,
         score: None,
-        nested: None,
-        source,
-        highlight: Default::default(),
-        inner_hits: Default::default(),
-        matched_queries: Vec::default(),
+        source: source_opt,
+        highlight,
+        fields: fields.into_iter().collect(),
         sort,
     }
 }
@@ -755,6 +899,7 @@ async fn es_compat_index_multi_search(
     payload: Bytes,
     multi_search_params: MultiSearchQueryParams,
     search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
 ) -> Result<MultiSearchResponse, ElasticsearchError> {
     let mut search_requests = Vec::new();
     let str_payload = from_utf8(&payload)
@@ -782,6 +927,11 @@ async fn es_compat_index_multi_search(
                 ))
             })?;
         }
+        authorize_indexes(
+            principal_opt.as_ref(),
+            AuthPrivilege::Read,
+            &request_header.index,
+        )?;
         let index_ids_patterns = request_header.index.clone();
         let search_body = payload_lines
             .next()
@@ -797,46 +947,48 @@ async fn es_compat_index_multi_search(
                     ))
                 })
             })?;
+        let source_filter = SourceFilter::from_params(
+            search_body._source.clone(),
+            None,
+            multi_search_params._source_includes.as_deref(),
+            multi_search_params._source_excludes.as_deref(),
+        );
         let mut search_query_params = SearchQueryParams::from(request_header);
-        if let Some(_source_excludes) = &multi_search_params._source_excludes {
-            search_query_params._source_excludes = Some(_source_excludes.to_vec());
-        }
-        if let Some(_source_includes) = &multi_search_params._source_includes {
-            search_query_params._source_includes = Some(_source_includes.to_vec());
-        }
         if let Some(extra_filters) = &multi_search_params.extra_filters {
             search_query_params.extra_filters = Some(extra_filters.to_vec());
         }
-        let es_request =
+        let (search_request, append_shard_doc) =
             build_request_for_es_api(index_ids_patterns, search_query_params, search_body)?;
-        search_requests.push(es_request);
+        search_requests.push((search_request, append_shard_doc, source_filter));
     }
 
     // TODO: forced to do weird referencing to work around https://github.com/rust-lang/rust/issues/100905
     // otherwise append_shard_doc is captured by ref, and we get lifetime issues
-    let futures = search_requests
-        .into_iter()
-        .map(|(search_request, append_shard_doc)| {
-            let search_service = &search_service;
-            let _source_excludes = multi_search_params._source_excludes.clone();
-            let _source_includes = multi_search_params._source_includes.clone();
-            async move {
-                let start_instant = Instant::now();
-                let search_response: SearchResponse =
-                    search_service.clone().root_search(search_request).await?;
-                let elapsed = start_instant.elapsed();
-                let mut search_response_rest: ElasticsearchResponse =
-                    convert_to_es_search_response(
-                        search_response,
-                        append_shard_doc,
-                        _source_excludes,
-                        _source_includes,
-                        true, //< allow_partial_results. Set to to true to match ES's behavior.
-                    )?;
-                search_response_rest.took = elapsed.as_millis() as u32;
-                Ok::<_, ElasticsearchError>(search_response_rest)
-            }
-        });
+    let futures =
+        search_requests
+            .into_iter()
+            .map(|(search_request, append_shard_doc, source_filter)| {
+                let search_service = &search_service;
+                async move {
+                    let start_instant = Instant::now();
+                    let collapse_field_opt = search_request.collapse_field.clone();
+                    let num_sort_values = num_sort_values(&search_request);
+                    let search_response: SearchResponse =
+                        search_service.clone().root_search(search_request).await?;
+                    let elapsed = start_instant.elapsed();
+                    let mut search_response_rest: ElasticsearchResponse =
+                        convert_to_es_search_response(
+                            search_response,
+                            append_shard_doc,
+                            Some(num_sort_values),
+                            &source_filter,
+                            collapse_field_opt.as_deref(),
+                            true, //< allow_partial_results. Set to to true to match ES's behavior.
+                        )?;
+                    search_response_rest.took = elapsed.as_millis() as u32;
+                    Ok::<_, ElasticsearchError>(search_response_rest)
+                }
+            });
     let max_concurrent_searches =
         multi_search_params.max_concurrent_searches.unwrap_or(10) as usize;
     let search_responses = futures::stream::iter(futures)
@@ -880,8 +1032,16 @@ async fn es_scroll(
     // However, passing that parameter is cumbersome, so we cut some corner and forbid the
     // use of scroll requests in combination with allow_partial_results set to false.
     let allow_failed_splits = true;
-    let mut search_response_rest: ElasticsearchResponse =
-        convert_to_es_search_response(search_response, false, None, None, allow_failed_splits)?;
+    // The sort fields of the initial request are not known either, so missing sort values are
+    // omitted.
+    let mut search_response_rest: ElasticsearchResponse = convert_to_es_search_response(
+        search_response,
+        false,
+        None,
+        &SourceFilter::default(),
+        None,
+        allow_failed_splits,
+    )?;
     search_response_rest.took = start_instant.elapsed().as_millis() as u32;
     Ok(search_response_rest)
 }
@@ -944,8 +1104,9 @@ fn convert_to_es_stats_response(
 fn convert_to_es_search_response(
     resp: SearchResponse,
     append_shard_doc: bool,
-    _source_excludes: Option<Vec<String>>,
-    _source_includes: Option<Vec<String>>,
+    num_sort_values: Option<usize>,
+    source_filter: &SourceFilter,
+    collapse_field_opt: Option<&str>,
     allow_partial_results: bool,
 ) -> Result<ElasticsearchResponse, ElasticsearchError> {
     if !allow_partial_results || resp.num_successful_splits == 0 {
@@ -956,7 +1117,15 @@ fn convert_to_es_search_response(
     let hits: Vec<ElasticHit> = resp
         .hits
         .into_iter()
-        .map(|hit| convert_hit(hit, append_shard_doc, &_source_excludes, &_source_includes))
+        .map(|hit| {
+            convert_hit(
+                hit,
+                append_shard_doc,
+                num_sort_values,
+                source_filter,
+                collapse_field_opt,
+            )
+        })
         .collect();
     let aggregations: Option<serde_json::Value> = if let Some(aggregation_json) = resp.aggregation {
         serde_json::from_str(&aggregation_json).ok()
@@ -968,7 +1137,7 @@ fn convert_to_es_search_response(
     let num_total_splits = num_successful_splits + num_failed_splits;
     Ok(ElasticsearchResponse {
         timed_out: false,
-        hits: HitsMetadata {
+        hits: ElasticHits {
             total: Some(TotalHits {
                 value: resp.num_hits,
                 relation: TotalHitsRelation::Equal,
@@ -984,12 +1153,74 @@ fn convert_to_es_search_response(
             successful: num_successful_splits,
             skipped: 0u32,
             failed: num_failed_splits,
-            failures: Vec::new(),
+            failures: resp
+                .failed_splits
+                .into_iter()
+                .map(convert_split_search_error)
+                .collect(),
         },
         ..Default::default()
     })
 }
 
+fn convert_to_es_async_search_response(
+    async_search_response: AsyncSearchResponse,
+    append_shard_doc: bool,
+    num_sort_values: Option<usize>,
+    source_filter: &SourceFilter,
+    collapse_field_opt: Option<&str>,
+    allow_partial_results: bool,
+) -> Result<ElasticsearchAsyncSearchResponse, ElasticsearchError> {
+    if let Some(search_error) = async_search_response.error {
+        return Err(ElasticsearchError::from(search_error));
+    }
+    // Running searches hold the partial results of the splits searched so far, if any.
+    let search_response = async_search_response.response.unwrap_or_default();
+    let mut response = convert_to_es_search_response(
+        search_response,
+        append_shard_doc,
+        num_sort_values,
+        source_filter,
+        collapse_field_opt,
+        allow_partial_results,
+    )?;
+    let end_time_in_millis = async_search_response
+        .completion_time_in_millis
+        .unwrap_or_else(|| (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64);
+    response.took = (end_time_in_millis - async_search_response.start_time_in_millis).max(0) as u32;
+
+    Ok(ElasticsearchAsyncSearchResponse {
+        id: async_search_response.id,
+        is_partial: async_search_response.is_running,
+        is_running: async_search_response.is_running,
+        start_time_in_millis: async_search_response.start_time_in_millis,
+        expiration_time_in_millis: async_search_response.expiration_time_in_millis,
+        completion_time_in_millis: async_search_response.completion_time_in_millis,
+        response,
+    })
+}
+
+fn convert_split_search_error(split_search_error: SplitSearchError) -> ShardFailure {
+    ShardFailure {
+        index: None,
+        node: None,
+        reason: ErrorCause {
+            reason: Some(format!(
+                "failed to search split `{}`: {}",
+                split_search_error.split_id, split_search_error.error
+            )),
+            caused_by: None,
+            root_cause: Vec::new(),
+            stack_trace: None,
+            suppressed: Vec::new(),
+            ty: None,
+            additional_details: Default::default(),
+        },
+        shard: None,
+        status: None,
+    }
+}
+
 pub(crate) fn str_lines(body: &str) -> impl Iterator<Item = &str> {
     body.lines()
         .map(|line| line.trim())
@@ -999,7 +1230,7 @@ pub(crate) fn str_lines(body: &str) -> impl Iterator<Item = &str> {
 #[cfg(test)]
 mod tests {
     use hyper::StatusCode;
-    use quickwit_proto::search::SplitSearchError;
+    use quickwit_proto::search::SortValue;
 
     use super::{partial_hit_from_search_after_param, *};
 
@@ -1049,135 +1280,224 @@ mod tests {
     }
 
     #[test]
-    fn test_single_element() {
-        let input = "app";
-        let expected = vec![("app", None)];
-        assert_eq!(generate_path_variants_with_suffix(input), expected);
+    fn test_partial_hit_from_search_after_param_multiple_sort_fields() {
+        let search_after = vec![json!(1), json!("3"), json!(2.5), json!("split_id:1:2")];
+        let sort_order = ["field1", "field2", "field3", "_shard_doc"].map(|field_name| {
+            quickwit_proto::search::SortField {
+                field_name: field_name.to_string(),
+                sort_order: 1,
+                sort_datetime_format: None,
+            }
+        });
+        let partial_hit = partial_hit_from_search_after_param(search_after, &sort_order)
+            .unwrap()
+            .unwrap();
+        let sort_values: Vec<serde_json::Value> = partial_hit
+            .sort_by_values()
+            .flatten()
+            .map(|sort_value| sort_value.into_json())
+            .collect();
+        assert_eq!(sort_values, vec![json!(1), json!(3), json!(2.5)]);
+        assert_eq!(partial_hit.split_id, "split_id");
+        assert_eq!(partial_hit.segment_ord, 1);
+        assert_eq!(partial_hit.doc_id, 2);
     }
 
     #[test]
-    fn test_two_elements() {
-        let input = "app.id";
-        let expected = vec![("app", Some("id")), ("app.id", None)];
-        assert_eq!(generate_path_variants_with_suffix(input), expected);
+    fn test_build_request_for_es_api_with_highlight() {
+        let search_body: SearchBody = serde_json::from_value(json!({
+            "query": { "match": { "body": "beagle" } },
+            "highlight": { "fields": { "body": {} }, "fragment_size": 20 }
+        }))
+        .unwrap();
+        let (search_request, _) = build_request_for_es_api(
+            vec!["my-index".to_string()],
+            SearchQueryParams::default(),
+            search_body,
+        )
+        .unwrap();
+        assert_eq!(search_request.snippet_fields, ["body"]);
+        let snippet_options = search_request.snippet_options.unwrap();
+        assert_eq!(snippet_options.pre_tag.unwrap(), "<em>");
+        assert_eq!(snippet_options.post_tag.unwrap(), "</em>");
+        assert_eq!(snippet_options.max_num_chars, Some(20));
     }
 
     #[test]
-    fn test_multiple_elements() {
-        let input = "app.id.name";
-        let expected = vec![
-            ("app", Some("id.name")),
-            ("app.id", Some("name")),
-            ("app.id.name", None),
-        ];
-        assert_eq!(generate_path_variants_with_suffix(input), expected);
+    fn test_build_request_for_es_api_with_knn() {
+        let search_body: SearchBody = serde_json::from_value(json!({
+            "query": { "match": { "body": "beagle" } },
+            "knn": {
+                "field": "embedding",
+                "query_vector": [0.5, 1.0],
+                "k": 5,
+                "num_candidates": 50
+            }
+        }))
+        .unwrap();
+        let (search_request, _) = build_request_for_es_api(
+            vec!["my-index".to_string()],
+            SearchQueryParams::default(),
+            search_body,
+        )
+        .unwrap();
+        let query_ast: QueryAst = serde_json::from_str(&search_request.query_ast).unwrap();
+        let QueryAst::Bool(bool_query) = query_ast else {
+            panic!()
+        };
+        assert!(bool_query.must.is_empty());
+        assert_eq!(bool_query.should.len(), 2);
+        assert!(matches!(bool_query.should[0], QueryAst::FullText(_)));
+        assert!(matches!(bool_query.should[1], QueryAst::Knn(_)));
     }
 
     #[test]
-    fn test_include_fields1() {
-        let mut fields = json!({
-            "app": { "id": 123, "name": "Blub" },
-            "user": { "id": 456, "name": "Fred" }
-        });
-
-        let includes = Some(vec!["app.id".to_string()]);
-        filter_source(&mut fields, &None, &includes);
-
-        let expected = json!({
-            "app": { "id": 123 }
-        });
-
-        assert_eq!(fields, expected);
+    fn test_build_request_for_es_api_with_runtime_mappings() {
+        let search_body: SearchBody = serde_json::from_value(json!({
+            "query": { "term": { "status_class": 5 } },
+            "runtime_mappings": {
+                "status_class": {
+                    "type": "long",
+                    "script": { "source": "to_int!(.status) / 100" }
+                }
+            }
+        }))
+        .unwrap();
+        let (search_request, _) = build_request_for_es_api(
+            vec!["my-index".to_string()],
+            SearchQueryParams::default(),
+            search_body,
+        )
+        .unwrap();
+        let runtime_mappings: serde_json::Value =
+            serde_json::from_str(search_request.runtime_mappings.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            runtime_mappings,
+            json!({
+                "status_class": {
+                    "type": "long",
+                    "script": { "source": "to_int!(.status) / 100" }
+                }
+            })
+        );
     }
-    #[test]
-    fn test_include_fields2() {
-        let mut fields = json!({
-            "app": { "id": 123, "name": "Blub" },
-            "app.id": { "id": 123, "name": "Blub" },
-            "user": { "id": 456, "name": "Fred" }
-        });
-
-        let includes = Some(vec!["app".to_string(), "app.id".to_string()]);
-        filter_source(&mut fields, &None, &includes);
 
-        let expected = json!({
-            "app": { "id": 123, "name": "Blub" },
-            "app.id": { "id": 123, "name": "Blub" },
-        });
+    #[test]
+    fn test_build_request_for_es_api_with_collapse() {
+        let search_body: SearchBody = serde_json::from_value(json!({
+            "query": { "match_all": {} },
+            "collapse": { "field": "user_id" }
+        }))
+        .unwrap();
+        let (search_request, _) = build_request_for_es_api(
+            vec!["my-index".to_string()],
+            SearchQueryParams::default(),
+            search_body,
+        )
+        .unwrap();
+        assert_eq!(search_request.collapse_field.as_deref(), Some("user_id"));
 
-        assert_eq!(fields, expected);
+        let hit = quickwit_proto::search::Hit {
+            json: r#"{"user_id": "kimchy"}"#.to_string(),
+            partial_hit: Some(PartialHit {
+                collapse_value: Some("kimchy".to_string()),
+                ..Default::default()
+            }),
+            index_id: "my-index".to_string(),
+            ..Default::default()
+        };
+        let elastic_hit = convert_hit(hit, false, None, &SourceFilter::default(), Some("user_id"));
+        let elastic_hit_json = serde_json::to_value(&elastic_hit).unwrap();
+        assert_eq!(elastic_hit_json["fields"], json!({"user_id": ["kimchy"]}));
     }
 
     #[test]
-    fn test_exclude_fields() {
-        let mut fields = json!({
-            "app": {
-                "id": 123,
-                "name": "Blub"
-            },
-            "user": {
-                "id": 456,
-                "name": "Fred"
-            }
-        });
-
-        let excludes = Some(vec!["app.name".to_string(), "user.id".to_string()]);
-        filter_source(&mut fields, &excludes, &None);
-
-        let expected = json!({
-            "app": {
-                "id": 123
-            },
-            "user": {
-                "name": "Fred"
-            }
-        });
-
-        assert_eq!(fields, expected);
+    fn test_convert_hit_with_runtime_fields() {
+        let hit = quickwit_proto::search::Hit {
+            json: r#"{"status": 503}"#.to_string(),
+            fields_json: Some(r#"{"status_class": [5]}"#.to_string()),
+            index_id: "my-index".to_string(),
+            ..Default::default()
+        };
+        let elastic_hit = convert_hit(hit, false, None, &SourceFilter::default(), None);
+        let elastic_hit_json = serde_json::to_value(&elastic_hit).unwrap();
+        assert_eq!(elastic_hit_json["_source"], json!({"status": 503}));
+        assert_eq!(elastic_hit_json["fields"], json!({"status_class": [5]}));
     }
 
     #[test]
-    fn test_include_and_exclude_fields() {
-        let mut fields = json!({
-            "app": { "id": 123, "name": "Blub", "version": "1.0" },
-            "user": { "id": 456, "name": "Fred", "email": "john@example.com" }
-        });
-
-        let includes = Some(vec![
-            "app".to_string(),
-            "user.name".to_string(),
-            "user.email".to_string(),
-        ]);
-        let excludes = Some(vec!["app.version".to_string(), "user.email".to_string()]);
-        filter_source(&mut fields, &excludes, &includes);
-
-        let expected = json!({
-            "app": { "id": 123, "name": "Blub" },
-            "user": { "name": "Fred" }
-        });
-
-        assert_eq!(fields, expected);
+    fn test_convert_hit_with_highlight() {
+        let hit = quickwit_proto::search::Hit {
+            json: r#"{"title": "beagle", "body": "The beagle is a breed of small scent hound."}"#
+                .to_string(),
+            snippet: Some(
+                r#"{"title": [], "body": ["The <em>beagle</em> is a breed"]}"#.to_string(),
+            ),
+            index_id: "my-index".to_string(),
+            ..Default::default()
+        };
+        let elastic_hit = convert_hit(hit, false, None, &SourceFilter::default(), None);
+        assert_eq!(elastic_hit.highlight.len(), 1);
+        assert_eq!(
+            elastic_hit.highlight["body"],
+            ["The <em>beagle</em> is a breed"]
+        );
     }
 
     #[test]
-    fn test_no_includes_or_excludes() {
-        let mut fields = json!({
-            "app": {
-                "id": 123,
-                "name": "Blub"
-            }
-        });
-
-        filter_source(&mut fields, &None, &None);
+    fn test_convert_hit_with_source_filter() {
+        let hit = quickwit_proto::search::Hit {
+            json: r#"{"actor": {"id": 1, "login": "fred"}, "repo": {"name": "quickwit"}}"#
+                .to_string(),
+            index_id: "my-index".to_string(),
+            ..Default::default()
+        };
+        let source_filter = SourceFilter {
+            disabled: false,
+            includes: vec!["actor.*".to_string()],
+            excludes: vec!["actor.id".to_string()],
+        };
+        let elastic_hit = convert_hit(hit.clone(), false, None, &source_filter, None);
+        let elastic_hit_json = serde_json::to_value(&elastic_hit).unwrap();
+        assert_eq!(
+            elastic_hit_json["_source"],
+            json!({"actor": {"login": "fred"}})
+        );
 
-        let expected = json!({
-            "app": {
-                "id": 123,
-                "name": "Blub"
-            }
-        });
+        let source_filter = SourceFilter {
+            disabled: true,
+            ..Default::default()
+        };
+        let elastic_hit = convert_hit(hit, false, None, &source_filter, None);
+        let elastic_hit_json = serde_json::to_value(&elastic_hit).unwrap();
+        assert!(elastic_hit_json.get("_source").is_none());
+    }
 
-        assert_eq!(fields, expected);
+    #[test]
+    fn test_convert_hit_with_missing_sort_values() {
+        let hit = quickwit_proto::search::Hit {
+            json: "{}".to_string(),
+            partial_hit: Some(PartialHit {
+                sort_value: None,
+                sort_value2: Some(SortByValue {
+                    sort_value: Some(SortValue::Str("kimchy".to_string())),
+                }),
+                extra_sort_values: vec![SortByValue { sort_value: None }],
+                split_id: "split-1".to_string(),
+                doc_id: 3,
+                ..Default::default()
+            }),
+            index_id: "my-index".to_string(),
+            ..Default::default()
+        };
+        let elastic_hit = convert_hit(hit.clone(), false, Some(4), &SourceFilter::default(), None);
+        assert_eq!(elastic_hit.id, "split-1:00000000:00000003");
+        assert_eq!(
+            elastic_hit.sort,
+            vec![json!(null), json!("kimchy"), json!(null), json!(null)]
+        );
+        let elastic_hit = convert_hit(hit, false, None, &SourceFilter::default(), None);
+        assert_eq!(elastic_hit.sort, vec![json!("kimchy")]);
     }
 
     // We test that the behavior of allow partial search results.
@@ -1194,7 +1514,15 @@ mod tests {
                 failed_splits: vec![split_error.clone()],
                 ..Default::default()
             };
-            convert_to_es_search_response(search_response, false, None, None, false).unwrap_err();
+            convert_to_es_search_response(
+                search_response,
+                false,
+                None,
+                &SourceFilter::default(),
+                None,
+                false,
+            )
+            .unwrap_err();
         }
         {
             let search_response = SearchResponse {
@@ -1204,9 +1532,23 @@ mod tests {
             };
             // if we allow partial search results, this should not fail, but we report the presence
             // of failed splits in the fail shard response.
-            let es_search_resp =
-                convert_to_es_search_response(search_response, false, None, None, true).unwrap();
+            let es_search_resp = convert_to_es_search_response(
+                search_response,
+                false,
+                None,
+                &SourceFilter::default(),
+                None,
+                true,
+            )
+            .unwrap();
+            assert_eq!(es_search_resp.shards.total, 2);
+            assert_eq!(es_search_resp.shards.successful, 1);
             assert_eq!(es_search_resp.shards.failed, 1);
+            assert_eq!(es_search_resp.shards.failures.len(), 1);
+            assert_eq!(
+                es_search_resp.shards.failures[0].reason.reason.as_deref(),
+                Some("failed to search split `some-split-id`: some-error")
+            );
         }
         {
             let search_response = SearchResponse {
@@ -1215,7 +1557,15 @@ mod tests {
             };
             // Event if we allow partial search results, with a fail and no success, we have a
             // failure.
-            convert_to_es_search_response(search_response, false, None, None, true).unwrap_err();
+            convert_to_es_search_response(
+                search_response,
+                false,
+                None,
+                &SourceFilter::default(),
+                None,
+                true,
+            )
+            .unwrap_err();
         }
         {
             // Not having any splits (no failure + no success) is not considered a failure.
@@ -1225,6 +1575,7 @@ mod tests {
                     search_response,
                     false,
                     None,
+                    &SourceFilter::default(),
                     None,
                     allow_partial,
                 )
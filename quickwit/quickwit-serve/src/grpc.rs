@@ -21,6 +21,7 @@ use std::collections::BTreeSet;
 use std::error::Error;
 use std::sync::Arc;
 
+use anyhow::Context;
 use bytesize::ByteSize;
 use quickwit_cluster::cluster_grpc_server;
 use quickwit_common::tower::BoxFutureInfaillible;
@@ -29,6 +30,7 @@ use quickwit_proto::developer::DeveloperServiceClient;
 use quickwit_proto::indexing::IndexingServiceClient;
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPluginServer;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsServiceServer;
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::metrics_service_server::MetricsServiceServer;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceServiceServer;
 use quickwit_proto::search::search_service_server::SearchServiceServer;
 use quickwit_proto::tonic::codegen::CompressionEncoding;
@@ -39,6 +41,7 @@ use tracing::*;
 
 use crate::developer_api::DeveloperApiServer;
 use crate::search_api::GrpcSearchAdapter;
+use crate::tls::{tls_incoming, ReloadableTlsAcceptor, GRPC_ALPN_PROTOCOLS};
 use crate::{QuickwitServices, INDEXING_GRPC_SERVER_METRICS_LAYER};
 
 /// Starts and binds gRPC services to `grpc_listen_addr`.
@@ -142,6 +145,15 @@ pub(crate) async fn start_grpc_server(
         } else {
             None
         };
+    let otlp_metrics_grpc_service =
+        if let Some(otlp_metrics_service) = services.otlp_metrics_service_opt.clone() {
+            enabled_grpc_services.insert("otlp-metrics");
+            let metrics_service = MetricsServiceServer::new(otlp_metrics_service)
+                .accept_compressed(CompressionEncoding::Gzip);
+            Some(metrics_service)
+        } else {
+            None
+        };
     // Mount gRPC search service if `QuickwitService::Searcher` is enabled on node.
     let search_grpc_service = if services
         .node_config
@@ -186,18 +198,40 @@ pub(crate) async fn start_grpc_server(
         .add_optional_service(metastore_grpc_service)
         .add_optional_service(otlp_log_grpc_service)
         .add_optional_service(otlp_trace_grpc_service)
+        .add_optional_service(otlp_metrics_grpc_service)
         .add_optional_service(search_grpc_service);
 
+    let tls_acceptor_opt = services
+        .node_config
+        .grpc_config
+        .tls
+        .as_ref()
+        .map(|tls_config| ReloadableTlsAcceptor::load(tls_config, GRPC_ALPN_PROTOCOLS))
+        .transpose()
+        .context("failed to load gRPC server TLS configuration")?;
     let grpc_listen_addr = tcp_listener.local_addr()?;
     info!(
         enabled_grpc_services=?enabled_grpc_services,
         grpc_listen_addr=?grpc_listen_addr,
+        tls=tls_acceptor_opt.is_some(),
         "Starting gRPC server listening on {grpc_listen_addr}."
     );
-    // nodelay=true and keepalive=None are the default values for Server::builder()
-    let tcp_incoming = TcpIncoming::from_listener(tcp_listener, true, None)
-        .map_err(|err: Box<dyn Error + Send + Sync>| anyhow::anyhow!(err))?;
-    let serve_fut = server_router.serve_with_incoming_shutdown(tcp_incoming, shutdown_signal);
+    let serve_fut = async move {
+        if let Some(tls_acceptor) = tls_acceptor_opt {
+            let tls_incoming = tls_incoming(tcp_listener, tls_acceptor);
+            server_router
+                .serve_with_incoming_shutdown(tls_incoming, shutdown_signal)
+                .await?;
+        } else {
+            // nodelay=true and keepalive=None are the default values for Server::builder()
+            let tcp_incoming = TcpIncoming::from_listener(tcp_listener, true, None)
+                .map_err(|err: Box<dyn Error + Send + Sync>| anyhow::anyhow!(err))?;
+            server_router
+                .serve_with_incoming_shutdown(tcp_incoming, shutdown_signal)
+                .await?;
+        }
+        anyhow::Ok(())
+    };
     let (serve_res, _trigger_res) = tokio::join!(serve_fut, readiness_trigger);
     serve_res?;
     Ok(())
@@ -17,17 +17,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 
 use bytes::Bytes;
+use quickwit_common::rand::append_random_suffix;
 use quickwit_common::uri::Uri;
 use quickwit_config::{
-    load_index_config_update, load_source_config_from_user_config, validate_index_id_pattern,
-    ConfigFormat, FileSourceParams, NodeConfig, SourceConfig, SourceParams, CLI_SOURCE_ID,
+    build_doc_mapper, load_index_config_update, load_source_config_from_user_config,
+    validate_index_id_pattern, ConfigFormat, FileSourceParams, NodeConfig, ReindexSourceParams,
+    SourceConfig, SourceInputFormat, SourceParams, TransformConfig, CLI_SOURCE_ID,
     INGEST_API_SOURCE_ID,
 };
 use quickwit_doc_mapper::{analyze_text, TokenizerConfig};
 use quickwit_index_management::{IndexService, IndexServiceError};
+use quickwit_indexing::source::{
+    build_reindex_query_ast, validate_reindex_source_index, ReindexProgress,
+};
 use quickwit_metastore::{
     IndexMetadata, IndexMetadataResponseExt, ListIndexesMetadataResponseExt, ListSplitsQuery,
     ListSplitsRequestExt, MetastoreServiceStreamSplitsExt, Split, SplitInfo, SplitState,
@@ -68,8 +74,16 @@ use crate::with_arg;
         reset_source_checkpoint,
         toggle_source,
         delete_source,
+        reindex,
+        get_reindex_status,
     ),
-    components(schemas(ToggleSource, SplitsForDeletion, IndexStats))
+    components(schemas(
+        ToggleSource,
+        SplitsForDeletion,
+        IndexStats,
+        ReindexRequest,
+        ReindexStatus
+    ))
 )]
 pub struct IndexApi;
 
@@ -109,6 +123,10 @@ pub fn index_management_handlers(
         .or(delete_source_handler(index_service.metastore()))
         .or(get_source_shards_handler(index_service.metastore()))
         .boxed()
+        // Reindex handlers.
+        .or(reindex_handler(index_service.clone()))
+        .or(get_reindex_status_handler(index_service.metastore()))
+        .boxed()
         // Tokenizer handlers.
         .or(analyze_request_handler())
         // Parse query into query AST handler.
@@ -603,6 +621,7 @@ async fn update_index(
         &new_index_config.retention_policy_opt,
         &new_index_config.indexing_settings,
         &new_index_config.doc_mapping,
+        &new_index_config.ingest_settings,
     )?;
     let update_resp = metastore.update_index(update_request).await?;
     Ok(update_resp.deserialize_index_metadata()?)
@@ -980,6 +999,231 @@ async fn get_source_shards(
     Ok(shards)
 }
 
+/// Request rewriting the documents of an index, or of a time slice of it, into another index.
+#[derive(Debug, Clone, Deserialize, Serialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReindexRequest {
+    /// ID of the index whose documents are reindexed.
+    pub source_index_id: IndexId,
+    /// ID of the index receiving the documents. It must exist.
+    pub target_index_id: IndexId,
+    /// Query in the Quickwit query language selecting the documents to reindex.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Only the documents with a timestamp greater than or equal to this timestamp (in seconds)
+    /// are reindexed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_timestamp: Option<i64>,
+    /// Only the documents with a timestamp strictly lower than this timestamp (in seconds) are
+    /// reindexed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_timestamp: Option<i64>,
+    /// VRL transform applied to the documents before they are indexed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformConfig>,
+}
+
+/// Progress of a reindex job.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize, utoipa::ToSchema)]
+pub struct ReindexStatus {
+    /// ID of the index receiving the documents.
+    pub index_id: IndexId,
+    /// ID of the source of the target index running the job.
+    pub source_id: SourceId,
+    /// ID of the index whose documents are reindexed.
+    pub source_index_id: IndexId,
+    /// Number of documents indexed and published into the target index.
+    pub num_docs_reindexed: u64,
+    /// Whether all the documents to reindex are published. The source of the job is deleted
+    /// shortly after.
+    pub completed: bool,
+}
+
+fn reindex_handler(
+    index_service: IndexService,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_reindex")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(index_service))
+        .then(reindex)
+        .map(log_failure("failed to start reindex"))
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    post,
+    tag = "Reindex",
+    path = "/_reindex",
+    request_body = ReindexRequest,
+    responses(
+        (status = 200, description = "Successfully started the reindex job.", body = ReindexStatus)
+    ),
+)]
+/// Reindexes the documents of an index into another index.
+///
+/// The job runs as a `reindex` source of the target index, scheduled on the indexers like any
+/// other source. It reads the documents of the source index page by page through the search API
+/// and checkpoints its progress after each page. The source deletes itself once the job is
+/// completed.
+async fn reindex(
+    reindex_request: ReindexRequest,
+    mut index_service: IndexService,
+) -> Result<ReindexStatus, IndexServiceError> {
+    info!(
+        source_index_id = %reindex_request.source_index_id,
+        target_index_id = %reindex_request.target_index_id,
+        "reindex"
+    );
+    if reindex_request.source_index_id == reindex_request.target_index_id {
+        return Err(IndexServiceError::InvalidConfig(anyhow::anyhow!(
+            "the source and target indexes of a reindex must be different"
+        )));
+    }
+    if let (Some(start_timestamp), Some(end_timestamp)) = (
+        reindex_request.start_timestamp,
+        reindex_request.end_timestamp,
+    ) {
+        if start_timestamp >= end_timestamp {
+            return Err(IndexServiceError::InvalidConfig(anyhow::anyhow!(
+                "`start_timestamp` must be strictly lower than `end_timestamp`"
+            )));
+        }
+    }
+    let source_index_metadata_request =
+        IndexMetadataRequest::for_index_id(reindex_request.source_index_id.clone());
+    let source_index_config = index_service
+        .metastore()
+        .index_metadata(source_index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?
+        .index_config;
+    validate_reindex_source_index(&source_index_config)
+        .map_err(IndexServiceError::InvalidConfig)?;
+    let target_index_metadata_request =
+        IndexMetadataRequest::for_index_id(reindex_request.target_index_id.clone());
+    let target_index_uid: IndexUid = index_service
+        .metastore()
+        .index_metadata(target_index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?
+        .index_uid;
+
+    let reindex_params = ReindexSourceParams {
+        source_index_id: reindex_request.source_index_id,
+        query: reindex_request.query,
+        start_timestamp: reindex_request.start_timestamp,
+        end_timestamp: reindex_request.end_timestamp,
+    };
+    // Fails early on invalid queries rather than on the indexers.
+    let doc_mapper = build_doc_mapper(
+        &source_index_config.doc_mapping,
+        &source_index_config.search_settings,
+    )
+    .map_err(IndexServiceError::InvalidConfig)?;
+    let query_ast = build_reindex_query_ast(&reindex_params, &doc_mapper)
+        .map_err(IndexServiceError::InvalidConfig)?;
+    doc_mapper
+        .query(doc_mapper.schema(), &query_ast, true)
+        .map_err(|error| IndexServiceError::InvalidConfig(error.into()))?;
+
+    let source_config = SourceConfig {
+        source_id: append_random_suffix(&format!("reindex-{}", reindex_params.source_index_id)),
+        num_pipelines: NonZeroUsize::MIN,
+        enabled: true,
+        source_params: SourceParams::Reindex(reindex_params.clone()),
+        transform_config: reindex_request.transform,
+        input_format: SourceInputFormat::Json,
+        dead_letter_queue_config: None,
+    };
+    // Goes through the user config validation, which compiles the VRL script of the transform.
+    let source_config_json =
+        serde_json::to_vec(&source_config).expect("source config should be JSON serializable");
+    let source_config =
+        load_source_config_from_user_config(ConfigFormat::Json, &source_config_json)
+            .map_err(IndexServiceError::InvalidConfig)?;
+    let source_config = index_service
+        .add_source(target_index_uid, source_config)
+        .await?;
+    Ok(ReindexStatus {
+        index_id: reindex_request.target_index_id,
+        source_id: source_config.source_id,
+        source_index_id: reindex_params.source_index_id,
+        num_docs_reindexed: 0,
+        completed: false,
+    })
+}
+
+fn get_reindex_status_handler(
+    metastore: MetastoreServiceClient,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_reindex" / String / String)
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_reindex_status)
+        .and(extract_format_from_qs())
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Reindex",
+    path = "/_reindex/{index_id}/{source_id}",
+    responses(
+        (status = 200, description = "Successfully fetched the reindex job progress.", body = ReindexStatus)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The ID of the target index of the job."),
+        ("source_id" = String, Path, description = "The ID of the source running the job."),
+    )
+)]
+/// Gets the progress of a reindex job from the checkpoint of its source. Once the job is
+/// completed, its source is deleted and the job is no longer found.
+async fn get_reindex_status(
+    index_id: IndexId,
+    source_id: SourceId,
+    metastore: MetastoreServiceClient,
+) -> MetastoreResult<ReindexStatus> {
+    info!(index_id = %index_id, source_id = %source_id, "get-reindex-status");
+    let index_metadata_request = IndexMetadataRequest::for_index_id(index_id.to_string());
+    let index_metadata = metastore
+        .index_metadata(index_metadata_request)
+        .await?
+        .deserialize_index_metadata()?;
+    let source_not_found = || {
+        MetastoreError::NotFound(EntityKind::Source {
+            index_id: index_id.clone(),
+            source_id: source_id.clone(),
+        })
+    };
+    let Some(SourceParams::Reindex(reindex_params)) = index_metadata
+        .sources
+        .get(&source_id)
+        .map(|source_config| &source_config.source_params)
+    else {
+        return Err(source_not_found());
+    };
+    let source_checkpoint = index_metadata
+        .checkpoint
+        .source_checkpoint(&source_id)
+        .ok_or_else(source_not_found)?;
+    let reindex_progress = ReindexProgress::from_checkpoint(source_checkpoint);
+    Ok(ReindexStatus {
+        index_id,
+        source_id,
+        source_index_id: reindex_params.source_index_id.clone(),
+        num_docs_reindexed: reindex_progress.num_docs_reindexed,
+        completed: reindex_progress.completed,
+    })
+}
+
 #[derive(Debug, Deserialize, utoipa::IntoParams, utoipa::ToSchema)]
 struct AnalyzeRequest {
     /// The tokenizer to use.
@@ -1708,6 +1952,118 @@ mod tests {
         assert!(indexes.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_reindex() {
+        let metastore = metastore_for_test();
+        let index_service = IndexService::new(metastore.clone(), StorageResolver::unconfigured());
+        let mut node_config = NodeConfig::for_test();
+        node_config.default_index_root_uri = Uri::for_test("file:///default-index-root-uri");
+        let index_management_handler =
+            super::index_management_handlers(index_service, Arc::new(node_config));
+        for (index_id, store_source) in [("logs", true), ("logs-v2", false)] {
+            let index_config_body = format!(
+                r#"{{"version": "0.7", "index_id": "{index_id}", "doc_mapping": {{"mode": "strict", "field_mappings":[{{"name": "body", "type": "text"}}, {{"name": "ts", "type": "datetime", "fast": true}}], "timestamp_field": "ts", "store_source": {store_source}}}}}"#
+            );
+            let resp = warp::test::request()
+                .path("/indexes")
+                .method("POST")
+                .body(index_config_body)
+                .reply(&index_management_handler)
+                .await;
+            assert_eq!(resp.status(), 200);
+        }
+        let resp = warp::test::request()
+            .path("/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source_index_id": "logs",
+                "target_index_id": "logs",
+            }))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        // The documents are read from their `_source` field.
+        let resp = warp::test::request()
+            .path("/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source_index_id": "logs-v2",
+                "target_index_id": "logs",
+            }))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source_index_id": "logs",
+                "target_index_id": "logs-v2",
+                "query": "severity:ERROR",
+            }))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/_reindex")
+            .method("POST")
+            .json(&serde_json::json!({
+                "source_index_id": "logs",
+                "target_index_id": "logs-v2",
+                "query": "body:error",
+                "start_timestamp": 1_700_000_000,
+                "transform": {"script": ".body = downcase(string!(.body))"},
+            }))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let reindex_status: ReindexStatus = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(reindex_status.index_id, "logs-v2");
+        assert!(reindex_status.source_id.starts_with("reindex-logs-"));
+        assert_eq!(reindex_status.source_index_id, "logs");
+
+        let index_metadata = metastore
+            .index_metadata(IndexMetadataRequest::for_index_id("logs-v2".to_string()))
+            .await
+            .unwrap()
+            .deserialize_index_metadata()
+            .unwrap();
+        let source_config = index_metadata
+            .sources
+            .get(&reindex_status.source_id)
+            .unwrap();
+        assert_eq!(
+            source_config.source_params,
+            SourceParams::Reindex(ReindexSourceParams {
+                source_index_id: "logs".to_string(),
+                query: Some("body:error".to_string()),
+                start_timestamp: Some(1_700_000_000),
+                end_timestamp: None,
+            })
+        );
+        assert!(source_config.transform_config.is_some());
+
+        let resp = warp::test::request()
+            .path(&format!("/_reindex/logs-v2/{}", reindex_status.source_id))
+            .method("GET")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let reindex_status: ReindexStatus = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(reindex_status.num_docs_reindexed, 0);
+        assert!(!reindex_status.completed);
+
+        let resp = warp::test::request()
+            .path(&format!("/_reindex/logs-v2/{INGEST_API_SOURCE_ID}"))
+            .method("GET")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+
     #[tokio::test]
     async fn test_create_index_with_yaml() {
         let metastore = metastore_for_test();
@@ -17,8 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use bytes::{Buf, Bytes};
+use quickwit_cluster::Cluster;
 use quickwit_config::{disable_ingest_v1, IngestApiConfig, INGEST_V2_SOURCE_ID};
+use quickwit_indexing::source::{push_to_http_source, HttpSourceError, HttpSourcePushResponse};
 use quickwit_ingest::{
     CommitType, DocBatchBuilder, DocBatchV2Builder, FetchResponse, IngestRequest, IngestResponse,
     IngestService, IngestServiceClient, IngestServiceError, TailRequest,
@@ -27,9 +31,9 @@ use quickwit_proto::ingest::router::{
     IngestRequestV2, IngestResponseV2, IngestRouterService, IngestRouterServiceClient,
     IngestSubrequest,
 };
-use quickwit_proto::ingest::CommitTypeV2;
-use quickwit_proto::types::{DocUidGenerator, IndexId};
-use serde::Deserialize;
+use quickwit_proto::ingest::{CommitTypeV2, ParseFailureReason};
+use quickwit_proto::types::{DocUid, DocUidGenerator, IndexId, SourceId};
+use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection};
 
 use crate::decompression::get_body_bytes;
@@ -38,7 +42,7 @@ use crate::rest_api_response::into_rest_api_response;
 use crate::{with_arg, Body, BodyFormat};
 
 #[derive(utoipa::OpenApi)]
-#[openapi(paths(ingest, tail_endpoint,))]
+#[openapi(paths(ingest, tail_endpoint, http_source_push))]
 pub struct IngestApi;
 
 #[derive(utoipa::OpenApi)]
@@ -46,10 +50,39 @@ pub struct IngestApi;
     quickwit_ingest::DocBatch,
     quickwit_ingest::FetchResponse,
     quickwit_ingest::IngestResponse,
+    RestIngestResponse,
+    RestParseFailure,
     quickwit_ingest::CommitType,
+    quickwit_indexing::source::HttpSourcePushResponse,
 )))]
 pub struct IngestApiSchemas;
 
+/// Response of the ingest-v2 endpoint, itemizing the documents rejected by the doc mapper.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+pub struct RestIngestResponse {
+    /// Number of documents in the request.
+    pub num_docs_for_processing: u64,
+    /// Number of documents persisted in the write-ahead log.
+    pub num_ingested_docs: u64,
+    /// Number of documents rejected by the doc mapper.
+    pub num_rejected_docs: u64,
+    /// Rejected documents, in the order of the request.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parse_failures: Vec<RestParseFailure>,
+}
+
+/// A document of an ingest-v2 request rejected by the doc mapper.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+pub struct RestParseFailure {
+    /// Zero-based position of the document among the documents of the request.
+    pub doc_position: usize,
+    /// `invalid_json` if the document is not a JSON object, `invalid_schema` if it does not
+    /// match the doc mapping of the index.
+    pub reason: String,
+    pub message: String,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 struct IngestOptions {
     #[serde(alias = "commit")]
@@ -121,30 +154,38 @@ fn ingest_v2_handler(
     ingest_v2_filter(config)
         .and(with_arg(ingest_router))
         .then(ingest_v2)
-        .and(with_arg(BodyFormat::default()))
-        .map(into_rest_api_response)
+        .map(|(result, retry_after_opt)| {
+            into_rest_api_response(result, BodyFormat::default()).with_retry_after(retry_after_opt)
+        })
         .boxed()
 }
 
+/// Returns the result of the ingest request along with how long the client should wait before
+/// retrying when the index exceeded one of its ingest quotas.
 async fn ingest_v2(
     index_id: IndexId,
     body: Body,
     ingest_options: IngestV2Options,
     ingest_router: IngestRouterServiceClient,
-) -> Result<IngestResponse, IngestServiceError> {
+) -> (
+    Result<RestIngestResponse, IngestServiceError>,
+    Option<Duration>,
+) {
     let mut doc_batch_builder = DocBatchV2Builder::default();
     let mut doc_uid_generator = DocUidGenerator::default();
+    let mut doc_uids = Vec::new();
 
     for doc in lines(&body.content) {
-        doc_batch_builder.add_doc(doc_uid_generator.next_doc_uid(), doc);
+        let doc_uid = doc_uid_generator.next_doc_uid();
+        doc_batch_builder.add_doc(doc_uid, doc);
+        doc_uids.push(doc_uid);
     }
     let doc_batch_opt = doc_batch_builder.build();
 
     let Some(doc_batch) = doc_batch_opt else {
-        let response = IngestResponse::default();
-        return Ok(response);
+        let response = RestIngestResponse::default();
+        return (Ok(response), None);
     };
-    let num_docs = doc_batch.num_docs();
 
     let subrequest = IngestSubrequest {
         subrequest_id: 0,
@@ -156,14 +197,28 @@ async fn ingest_v2(
         commit_type: ingest_options.commit_type as i32,
         subrequests: vec![subrequest],
     };
-    let response = ingest_router.ingest(request).await?;
-    convert_ingest_response_v2(response, num_docs)
+    let response = match ingest_router.ingest(request).await {
+        Ok(response) => response,
+        Err(ingest_error) => return (Err(ingest_error.into()), None),
+    };
+    let retry_after_opt = response
+        .failures
+        .iter()
+        .filter_map(|failure| failure.retry_after_ms)
+        .max()
+        .map(Duration::from_millis);
+    (
+        convert_ingest_response_v2(response, doc_uids),
+        retry_after_opt,
+    )
 }
 
+/// Converts the response of the router into a REST response. `doc_uids` are the UIDs of the
+/// documents of the request, in order.
 fn convert_ingest_response_v2(
     mut response: IngestResponseV2,
-    num_docs: usize,
-) -> Result<IngestResponse, IngestServiceError> {
+    doc_uids: Vec<DocUid>,
+) -> Result<RestIngestResponse, IngestServiceError> {
     let num_responses = response.successes.len() + response.failures.len();
     if num_responses != 1 {
         return Err(IngestServiceError::Internal(format!(
@@ -171,9 +226,46 @@ fn convert_ingest_response_v2(
             num_responses
         )));
     }
-    if response.successes.pop().is_some() {
-        return Ok(IngestResponse {
+    if let Some(success) = response.successes.pop() {
+        // Doc UIDs are generated in increasing order, but we do not rely on it.
+        let mut doc_positions: Vec<(DocUid, usize)> = doc_uids
+            .into_iter()
+            .enumerate()
+            .map(|(doc_position, doc_uid)| (doc_uid, doc_position))
+            .collect();
+        let num_docs = doc_positions.len();
+        doc_positions.sort_unstable();
+
+        let mut parse_failures = Vec::with_capacity(success.parse_failures.len());
+
+        for parse_failure in success.parse_failures {
+            let failed_doc_uid = parse_failure.doc_uid();
+            let Ok(idx) =
+                doc_positions.binary_search_by_key(&failed_doc_uid, |(doc_uid, _)| *doc_uid)
+            else {
+                return Err(IngestServiceError::Internal(format!(
+                    "could not find doc `{failed_doc_uid}` in ingest request"
+                )));
+            };
+            let reason = match parse_failure.reason() {
+                ParseFailureReason::InvalidJson => "invalid_json",
+                ParseFailureReason::InvalidSchema => "invalid_schema",
+                ParseFailureReason::Unspecified => "unspecified",
+            };
+            let rest_parse_failure = RestParseFailure {
+                doc_position: doc_positions[idx].1,
+                reason: reason.to_string(),
+                message: parse_failure.message,
+            };
+            parse_failures.push(rest_parse_failure);
+        }
+        parse_failures.sort_unstable_by_key(|parse_failure| parse_failure.doc_position);
+
+        return Ok(RestIngestResponse {
             num_docs_for_processing: num_docs as u64,
+            num_ingested_docs: success.num_ingested_docs as u64,
+            num_rejected_docs: parse_failures.len() as u64,
+            parse_failures,
         });
     }
     let ingest_failure = response.failures.pop().unwrap();
@@ -218,6 +310,102 @@ async fn ingest(
     Ok(ingest_response)
 }
 
+fn http_source_push_filter(
+    config: IngestApiConfig,
+) -> impl Filter<Extract = (String, String, Body, IngestOptions), Error = Rejection> + Clone {
+    warp::path!(String / "sources" / String / "push")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(
+            config.content_length_limit.as_u64(),
+        ))
+        .and(get_body_bytes())
+        .and(serde_qs::warp::query::<IngestOptions>(
+            serde_qs::Config::default(),
+        ))
+}
+
+pub(crate) fn http_source_push_handler(
+    cluster: Cluster,
+    config: IngestApiConfig,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    http_source_push_filter(config)
+        .and(with_arg(cluster))
+        .then(http_source_push)
+        .and(with_arg(BodyFormat::default()))
+        .map(into_rest_api_response)
+        .boxed()
+}
+
+#[utoipa::path(
+    post,
+    tag = "Ingest",
+    path = "/{index_id}/sources/{source_id}/push",
+    request_body(content = String, description = "Documents to push, in the input format of the source", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Successfully checkpointed documents.", body = HttpSourcePushResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the HTTP source."),
+        ("source_id" = String, Path, description = "The source ID of the HTTP source."),
+        ("commit" = Option<CommitType>, Query, description = "Force a commit after the documents are indexed."),
+    )
+)]
+/// Push documents to an HTTP source
+///
+/// The response is returned once the documents are checkpointed. The documents must be pushed to
+/// the node running the indexing pipeline of the source.
+async fn http_source_push(
+    index_id: IndexId,
+    source_id: SourceId,
+    body: Body,
+    ingest_options: IngestOptions,
+    cluster: Cluster,
+) -> Result<HttpSourcePushResponse, HttpSourceError> {
+    let force_commit = ingest_options.commit_type == CommitType::Force;
+
+    match push_to_http_source(&index_id, &source_id, body.content, force_commit).await {
+        Err(HttpSourceError::NotRunning {
+            index_id,
+            source_id,
+        }) => Err(http_source_not_running_error(&cluster, index_id, source_id).await),
+        push_result => push_result,
+    }
+}
+
+/// Looks up the indexing tasks published by the nodes of the cluster to name the node running the
+/// indexing pipeline of an HTTP source that is not running on this node.
+async fn http_source_not_running_error(
+    cluster: &Cluster,
+    index_id: IndexId,
+    source_id: SourceId,
+) -> HttpSourceError {
+    let owning_node_opt = cluster
+        .ready_nodes()
+        .await
+        .into_iter()
+        .filter(|node| {
+            !node.is_self_node()
+                && node.indexing_tasks().iter().any(|indexing_task| {
+                    indexing_task.index_uid().index_id == index_id
+                        && indexing_task.source_id == source_id
+                })
+        })
+        .min_by(|left, right| left.node_id().cmp(right.node_id()));
+
+    let Some(owning_node) = owning_node_opt else {
+        return HttpSourceError::NotRunning {
+            index_id,
+            source_id,
+        };
+    };
+    HttpSourceError::RunningOnOtherNode {
+        index_id,
+        source_id,
+        node_id: owning_node.node_id().to_owned(),
+        host: owning_node.grpc_advertise_addr().ip(),
+    }
+}
+
 pub fn tail_handler(
     ingest_service: IngestServiceClient,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
@@ -270,15 +458,24 @@ pub(crate) mod tests {
 
     use bytes::Bytes;
     use quickwit_actors::{Mailbox, Universe};
-    use quickwit_config::IngestApiConfig;
+    use quickwit_cluster::{create_cluster_for_test, ChannelTransport};
+    use quickwit_config::{IngestApiConfig, INGEST_V2_SOURCE_ID};
     use quickwit_ingest::{
         init_ingest_api, CreateQueueIfNotExistsRequest, FetchRequest, FetchResponse,
         IngestApiService, IngestResponse, IngestServiceClient, SuggestTruncateRequest,
         QUEUES_DIR_NAME,
     };
-    use quickwit_proto::ingest::router::IngestRouterServiceClient;
+    use quickwit_proto::indexing::IndexingTask;
+    use quickwit_proto::ingest::router::{
+        IngestFailure, IngestFailureReason, IngestResponseV2, IngestRouterServiceClient,
+        IngestSuccess, MockIngestRouterService,
+    };
+    use quickwit_proto::ingest::{ParseFailure, ParseFailureReason};
+    use quickwit_proto::types::{IndexUid, PipelineUid};
 
-    use super::ingest_api_handlers;
+    use super::{
+        http_source_push_handler, ingest_api_handlers, RestIngestResponse, RestParseFailure,
+    };
     use crate::ingest_api::lines;
 
     #[test]
@@ -407,6 +604,118 @@ pub(crate) mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_ingest_v2_api_returns_429_with_retry_after_if_quota_exceeded() {
+        let (universe, _temp_dir, ingest_service, _) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|request| {
+                let failure = IngestFailure {
+                    subrequest_id: request.subrequests[0].subrequest_id,
+                    index_id: "my-index".to_string(),
+                    source_id: INGEST_V2_SOURCE_ID.to_string(),
+                    reason: IngestFailureReason::IndexRateLimited as i32,
+                    retry_after_ms: Some(1_500),
+                };
+                Ok(IngestResponseV2 {
+                    successes: Vec::new(),
+                    failures: vec![failure],
+                })
+            });
+        let ingest_router = IngestRouterServiceClient::from_mock(mock_ingest_router);
+        let ingest_api_handlers =
+            ingest_api_handlers(ingest_router, ingest_service, IngestApiConfig::default());
+        let resp = warp::test::request()
+            .path("/my-index/ingest-v2")
+            .method("POST")
+            .body(r#"{"id": 1, "message": "push"}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 429);
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "2");
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_v2_api_reports_parse_failures_per_document() {
+        let (universe, _temp_dir, ingest_service, _) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .once()
+            .returning(|request| {
+                let subrequest = &request.subrequests[0];
+                let doc_uids = &subrequest.doc_batch.as_ref().unwrap().doc_uids;
+                assert_eq!(doc_uids.len(), 3);
+
+                let success = IngestSuccess {
+                    subrequest_id: subrequest.subrequest_id,
+                    num_ingested_docs: 1,
+                    parse_failures: vec![
+                        ParseFailure {
+                            doc_uid: Some(doc_uids[2]),
+                            reason: ParseFailureReason::InvalidSchema as i32,
+                            message: "failed to parse value `foo` for field `ts`".to_string(),
+                        },
+                        ParseFailure {
+                            doc_uid: Some(doc_uids[1]),
+                            reason: ParseFailureReason::InvalidJson as i32,
+                            message: "failed to parse JSON document".to_string(),
+                        },
+                    ],
+                    ..Default::default()
+                };
+                Ok(IngestResponseV2 {
+                    successes: vec![success],
+                    failures: Vec::new(),
+                })
+            });
+        let ingest_router = IngestRouterServiceClient::from_mock(mock_ingest_router);
+        let ingest_api_handlers =
+            ingest_api_handlers(ingest_router, ingest_service, IngestApiConfig::default());
+        let payload = r#"
+            {"ts": 1, "message": "push"}
+            {"ts": 2, "message": "push"
+            {"ts": "foo", "message": "push"}"#;
+        let resp = warp::test::request()
+            .path("/my-index/ingest-v2")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let ingest_response: RestIngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        let expected_ingest_response = RestIngestResponse {
+            num_docs_for_processing: 3,
+            num_ingested_docs: 1,
+            num_rejected_docs: 2,
+            parse_failures: vec![
+                RestParseFailure {
+                    doc_position: 1,
+                    reason: "invalid_json".to_string(),
+                    message: "failed to parse JSON document".to_string(),
+                },
+                RestParseFailure {
+                    doc_position: 2,
+                    reason: "invalid_schema".to_string(),
+                    message: "failed to parse value `foo` for field `ts`".to_string(),
+                },
+            ],
+        };
+        assert_eq!(ingest_response, expected_ingest_response);
+
+        // The response remains readable by clients expecting the legacy response.
+        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 3);
+
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_ingest_api_return_413_if_above_content_limit() {
         let config: IngestApiConfig =
@@ -427,6 +736,72 @@ pub(crate) mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_http_source_push_returns_404_if_source_not_running() {
+        let transport = ChannelTransport::default();
+        let cluster = create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+            .await
+            .unwrap();
+        let http_source_push_handler =
+            http_source_push_handler(cluster.clone(), IngestApiConfig::default());
+        let resp = warp::test::request()
+            .path("/my-index/sources/my-http-source/push")
+            .method("POST")
+            .body(r#"{"id": 1, "message": "push"}"#)
+            .reply(&http_source_push_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+        let body = str::from_utf8(resp.body()).unwrap();
+        assert!(body.contains(
+            "HTTP source `my-http-source` of index `my-index` is not running on this node\""
+        ));
+
+        let other_cluster = create_cluster_for_test(
+            vec![cluster.gossip_listen_addr().to_string()],
+            &["indexer"],
+            &transport,
+            true,
+        )
+        .await
+        .unwrap();
+        let indexing_task = IndexingTask {
+            pipeline_uid: Some(PipelineUid::for_test(1u128)),
+            index_uid: Some(IndexUid::for_test("my-index", 0)),
+            source_id: "my-http-source".to_string(),
+            shard_ids: Vec::new(),
+            params_fingerprint: 0,
+        };
+        other_cluster
+            .update_self_node_indexing_tasks(&[indexing_task])
+            .await;
+        cluster
+            .wait_for_ready_members(
+                |members| {
+                    members
+                        .iter()
+                        .any(|member| !member.indexing_tasks.is_empty())
+                },
+                Duration::from_secs(30),
+            )
+            .await
+            .unwrap();
+
+        let resp = warp::test::request()
+            .path("/my-index/sources/my-http-source/push")
+            .method("POST")
+            .body(r#"{"id": 1, "message": "push"}"#)
+            .reply(&http_source_push_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+        let body = str::from_utf8(resp.body()).unwrap();
+        let expected_message = format!(
+            "HTTP source `my-http-source` of index `my-index` is not running on this node but on \
+             node `{}`",
+            other_cluster.self_node_id()
+        );
+        assert!(body.contains(&expected_message));
+    }
+
     #[tokio::test]
     async fn test_ingest_api_blocks_when_wait_is_specified() {
         let (universe, _temp_dir, ingest_service_client, ingest_service_mailbox) =
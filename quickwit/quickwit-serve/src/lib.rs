@@ -19,8 +19,10 @@
 
 #![recursion_limit = "256"]
 
+mod auth;
 mod build_info;
 mod cluster_api;
+mod dead_letter_api;
 mod decompression;
 mod delete_task_api;
 mod developer_api;
@@ -43,8 +45,10 @@ mod rest;
 mod rest_api_response;
 mod search_api;
 pub(crate) mod simple_list;
+mod sql_api;
 pub mod tcp_listener;
 mod template_api;
+mod tls;
 mod ui_handler;
 
 use std::collections::{HashMap, HashSet};
@@ -57,6 +61,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{bail, Context};
+use async_trait::async_trait;
 use bytesize::ByteSize;
 pub(crate) use decompression::Body;
 pub use format::BodyFormat;
@@ -85,6 +90,7 @@ use quickwit_control_plane::{IndexerNodeInfo, IndexerPool};
 use quickwit_index_management::{IndexService as IndexManager, IndexServiceError};
 use quickwit_indexing::actors::IndexingService;
 use quickwit_indexing::models::ShardPositionsService;
+use quickwit_indexing::source::ReindexSearcher;
 use quickwit_indexing::start_indexing_service;
 use quickwit_ingest::{
     get_idle_shard_timeout, setup_local_shards_update_listener, start_ingest_api_service,
@@ -96,7 +102,9 @@ use quickwit_janitor::{start_janitor_service, JanitorService};
 use quickwit_metastore::{
     ControlPlaneMetastore, ListIndexesMetadataResponseExt, MetastoreResolver,
 };
-use quickwit_opentelemetry::otlp::{OtlpGrpcLogsService, OtlpGrpcTracesService};
+use quickwit_opentelemetry::otlp::{
+    OtlpGrpcLogsService, OtlpGrpcMetricsService, OtlpGrpcTracesService,
+};
 use quickwit_proto::control_plane::ControlPlaneServiceClient;
 use quickwit_proto::indexing::{IndexingServiceClient, ShardPositionsUpdate};
 use quickwit_proto::ingest::ingester::{
@@ -109,7 +117,7 @@ use quickwit_proto::metastore::{
     EntityKind, ListIndexesMetadataRequest, MetastoreError, MetastoreService,
     MetastoreServiceClient,
 };
-use quickwit_proto::search::ReportSplitsRequest;
+use quickwit_proto::search::{ReportSplitsRequest, SearchRequest, SearchResponse};
 use quickwit_proto::types::NodeId;
 use quickwit_search::{
     create_search_client_from_channel, start_searcher_service, SearchJobPlacer, SearchService,
@@ -124,7 +132,9 @@ use tracing::{debug, error, info, warn};
 use warp::{Filter, Rejection};
 
 pub use crate::build_info::{BuildInfo, RuntimeInfo};
-pub use crate::index_api::{ListSplitsQueryParams, ListSplitsResponse};
+pub use crate::index_api::{
+    ListSplitsQueryParams, ListSplitsResponse, ReindexRequest, ReindexStatus,
+};
 pub use crate::metrics::SERVE_METRICS;
 use crate::rate_modulator::RateModulator;
 #[cfg(test)]
@@ -197,6 +207,7 @@ struct QuickwitServices {
     pub jaeger_service_opt: Option<JaegerService>,
     pub otlp_logs_service_opt: Option<OtlpGrpcLogsService>,
     pub otlp_traces_service_opt: Option<OtlpGrpcTracesService>,
+    pub otlp_metrics_service_opt: Option<OtlpGrpcMetricsService>,
     /// We do have a search service even on nodes that are not running `search`.
     /// It is only used to serve the rest API calls and will only execute
     /// the root requests.
@@ -396,6 +407,11 @@ pub async fn serve_quickwit(
     shutdown_signal: BoxFutureInfaillible<()>,
     env_filter_reload_fn: EnvFilterReloadFn,
 ) -> anyhow::Result<HashMap<String, ActorExitStatus>> {
+    // The gRPC clients must be configured before the cluster creates the channels to the other
+    // nodes.
+    if let Some(grpc_tls_config) = &node_config.grpc_config.tls {
+        tls::set_grpc_client_tls_config(grpc_tls_config.clone());
+    }
     let cluster = start_cluster_service(&node_config)
         .await
         .context("failed to start cluster service")?;
@@ -501,6 +517,47 @@ pub async fn serve_quickwit(
         .await
         .context("failed to start ingest v1 service")?;
 
+    let split_cache_root_directory: PathBuf =
+        node_config.data_dir_path.join("searcher-split-cache");
+    let split_cache_opt: Option<Arc<SplitCache>> =
+        if let Some(split_cache_limits) = node_config.searcher_config.split_cache {
+            let split_cache = SplitCache::with_root_path(
+                split_cache_root_directory,
+                storage_resolver.clone(),
+                split_cache_limits,
+            )
+            .context("failed to load searcher split cache")?;
+            Some(split_cache)
+        } else {
+            None
+        };
+
+    let searcher_context = Arc::new(SearcherContext::new(
+        node_config.searcher_config.clone(),
+        split_cache_opt,
+    ));
+
+    let (search_job_placer, search_service) = setup_searcher(
+        &node_config,
+        cluster.change_stream(),
+        metastore_through_control_plane.clone(),
+        storage_resolver.clone(),
+        searcher_context,
+    )
+    .await
+    .context("failed to start searcher service")?;
+
+    // Setup ingest service v2.
+    let (ingest_router, ingest_router_service, ingester_opt) = setup_ingest_v2(
+        &node_config,
+        &cluster,
+        &event_broker,
+        control_plane_client.clone(),
+        ingester_pool.clone(),
+    )
+    .await
+    .context("failed to start ingest v2 service")?;
+
     let indexing_service_opt = if node_config.is_service_enabled(QuickwitService::Indexer) {
         let indexing_service = start_indexing_service(
             &universe,
@@ -508,9 +565,11 @@ pub async fn serve_quickwit(
             runtimes_config.num_threads_blocking,
             cluster.clone(),
             metastore_through_control_plane.clone(),
-            ingester_pool.clone(),
+            ingester_pool,
+            ingest_router_service.clone(),
             storage_resolver.clone(),
             event_broker.clone(),
+            Arc::new(SearchServiceReindexSearcher(search_service.clone())),
         )
         .await
         .context("failed to start indexing service")?;
@@ -527,17 +586,6 @@ pub async fn serve_quickwit(
         indexing_service_opt.clone(),
     );
 
-    // Setup ingest service v2.
-    let (ingest_router, ingest_router_service, ingester_opt) = setup_ingest_v2(
-        &node_config,
-        &cluster,
-        &event_broker,
-        control_plane_client.clone(),
-        ingester_pool,
-    )
-    .await
-    .context("failed to start ingest v2 service")?;
-
     if node_config.is_service_enabled(QuickwitService::Indexer)
         || node_config.is_service_enabled(QuickwitService::ControlPlane)
     {
@@ -566,10 +614,14 @@ pub async fn serve_quickwit(
             let otel_traces_index_config =
                 OtlpGrpcTracesService::index_config(&node_config.default_index_root_uri)
                     .context("failed to load OTEL traces index config")?;
+            let otel_metrics_index_config =
+                OtlpGrpcMetricsService::index_config(&node_config.default_index_root_uri)
+                    .context("failed to load OTEL metrics index config")?;
 
             for (index_name, index_config) in [
                 ("OTEL logs", otel_logs_index_config),
                 ("OTEL traces", otel_traces_index_config),
+                ("OTEL metrics", otel_metrics_index_config),
             ] {
                 match index_manager.create_index(index_config, false).await {
                     Ok(_)
@@ -581,35 +633,6 @@ pub async fn serve_quickwit(
             }
         }
     }
-    let split_cache_root_directory: PathBuf =
-        node_config.data_dir_path.join("searcher-split-cache");
-    let split_cache_opt: Option<Arc<SplitCache>> =
-        if let Some(split_cache_limits) = node_config.searcher_config.split_cache {
-            let split_cache = SplitCache::with_root_path(
-                split_cache_root_directory,
-                storage_resolver.clone(),
-                split_cache_limits,
-            )
-            .context("failed to load searcher split cache")?;
-            Some(split_cache)
-        } else {
-            None
-        };
-
-    let searcher_context = Arc::new(SearcherContext::new(
-        node_config.searcher_config.clone(),
-        split_cache_opt,
-    ));
-
-    let (search_job_placer, search_service) = setup_searcher(
-        &node_config,
-        cluster.change_stream(),
-        metastore_through_control_plane.clone(),
-        storage_resolver.clone(),
-        searcher_context,
-    )
-    .await
-    .context("failed to start searcher service")?;
 
     // The control plane listens for local shards updates to learn about each shard's ingestion
     // throughput. Ingesters (routers) do so to update their shard table.
@@ -680,6 +703,14 @@ pub async fn serve_quickwit(
         None
     };
 
+    let otlp_metrics_service_opt = if node_config.is_service_enabled(QuickwitService::Indexer)
+        && node_config.indexer_config.enable_otlp_endpoint
+    {
+        Some(OtlpGrpcMetricsService::new(ingest_router_service.clone()))
+    } else {
+        None
+    };
+
     let grpc_listen_addr = node_config.grpc_listen_addr;
     let rest_listen_addr = node_config.rest_config.listen_addr;
     let quickwit_services: Arc<QuickwitServices> = Arc::new(QuickwitServices {
@@ -701,6 +732,7 @@ pub async fn serve_quickwit(
         jaeger_service_opt,
         otlp_logs_service_opt,
         otlp_traces_service_opt,
+        otlp_metrics_service_opt,
         search_service,
         env_filter_reload_fn,
     });
@@ -871,6 +903,18 @@ async fn setup_ingest_v2(
     );
     ingest_router.subscribe();
 
+    // Every node runs an ingest router, and the routers enforce the rate quotas of the indexes
+    // without coordination, so they split them evenly among the nodes of the cluster.
+    let mut cluster_change_stream = cluster.change_stream();
+    let cluster_clone = cluster.clone();
+    let ingest_router_clone = ingest_router.clone();
+    tokio::spawn(async move {
+        while cluster_change_stream.next().await.is_some() {
+            let num_routers = cluster_clone.ready_nodes().await.len();
+            ingest_router_clone.set_num_routers(num_routers).await;
+        }
+    });
+
     let ingest_router_service = IngestRouterServiceClient::tower()
         .stack_layer(INGEST_GRPC_SERVER_METRICS_LAYER.clone())
         .build(ingest_router.clone());
@@ -976,6 +1020,18 @@ async fn setup_ingest_v2(
     Ok((ingest_router, ingest_router_service, ingester_opt))
 }
 
+/// Reads the documents of the source indexes of the reindex sources with the search service of
+/// the node.
+struct SearchServiceReindexSearcher(Arc<dyn SearchService>);
+
+#[async_trait]
+impl ReindexSearcher for SearchServiceReindexSearcher {
+    async fn root_search(&self, search_request: SearchRequest) -> anyhow::Result<SearchResponse> {
+        let search_response = self.0.root_search(search_request).await?;
+        Ok(search_response)
+    }
+}
+
 async fn setup_searcher(
     node_config: &NodeConfig,
     cluster_change_stream: ClusterChangeStream,
@@ -28,6 +28,7 @@ use utoipa::openapi::Tag;
 use utoipa::OpenApi;
 
 use crate::cluster_api::ClusterApi;
+use crate::dead_letter_api::{DeadLetterApi, DeadLetterApiSchemas};
 use crate::delete_task_api::DeleteTaskApi;
 use crate::developer_api::DeveloperApi;
 use crate::elasticsearch_api::ElasticCompatibleApi;
@@ -40,6 +41,7 @@ use crate::metrics_api::MetricsApi;
 use crate::node_info_handler::NodeInfoApi;
 use crate::otlp_api::OtlpApi;
 use crate::search_api::SearchApi;
+use crate::sql_api::SqlApi;
 use crate::template_api::IndexTemplateApi;
 
 /// Builds the OpenApi docs structure using the registered/merged docs.
@@ -73,6 +75,7 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
         Tag::new("Delete Tasks"),
         Tag::new("Node Health"),
         Tag::new("Sources"),
+        Tag::new("Reindex"),
         Tag::new("Get Metrics"),
         Tag::new("Cluster Info"),
         Tag::new("Node Info"),
@@ -86,6 +89,7 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
 
     // Routing
     docs_base.merge_components_and_paths(ClusterApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(DeadLetterApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(DeleteTaskApi::openapi().with_path_prefix("/api/v1"));
     docs_base
         .merge_components_and_paths(DeveloperApi::openapi().with_path_prefix("/api/developer"));
@@ -101,6 +105,7 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
     docs_base.merge_components_and_paths(MetricsApi::openapi().with_path_prefix("/metrics"));
     docs_base.merge_components_and_paths(NodeInfoApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(SearchApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(SqlApi::openapi().with_path_prefix("/api/v1"));
 
     // Schemas
     docs_base.merge_components_and_paths(MetastoreApiSchemas::openapi());
@@ -109,6 +114,7 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
     docs_base.merge_components_and_paths(DocMapperApiSchemas::openapi());
     docs_base.merge_components_and_paths(IndexingApiSchemas::openapi());
     docs_base.merge_components_and_paths(IngestApiSchemas::openapi());
+    docs_base.merge_components_and_paths(DeadLetterApiSchemas::openapi());
 
     docs_base
 }
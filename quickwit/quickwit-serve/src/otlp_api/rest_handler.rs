@@ -18,11 +18,17 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use quickwit_common::rate_limited_error;
-use quickwit_opentelemetry::otlp::{OtelSignal, OtlpGrpcLogsService, OtlpGrpcTracesService};
+use quickwit_opentelemetry::otlp::{
+    OtelSignal, OtlpGrpcLogsService, OtlpGrpcMetricsService, OtlpGrpcTracesService,
+};
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsService;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::{
     ExportLogsServiceRequest, ExportLogsServiceResponse,
 };
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::metrics_service_server::MetricsService;
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::{
+    ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceService;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::{
     ExportTraceServiceRequest, ExportTraceServiceResponse,
@@ -43,7 +49,9 @@ use crate::{require, with_arg, Body, BodyFormat};
     otlp_default_logs_handler,
     otlp_logs_handler,
     otlp_default_traces_handler,
-    otlp_ingest_traces_handler
+    otlp_ingest_traces_handler,
+    otlp_default_metrics_handler,
+    otlp_metrics_handler
 ))]
 pub struct OtlpApi;
 
@@ -51,11 +59,14 @@ pub struct OtlpApi;
 pub(crate) fn otlp_ingest_api_handlers(
     otlp_logs_service: Option<OtlpGrpcLogsService>,
     otlp_traces_service: Option<OtlpGrpcTracesService>,
+    otlp_metrics_service: Option<OtlpGrpcMetricsService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     otlp_default_logs_handler(otlp_logs_service.clone())
         .or(otlp_default_traces_handler(otlp_traces_service.clone()).recover(recover_fn))
+        .or(otlp_default_metrics_handler(otlp_metrics_service.clone()).recover(recover_fn))
         .or(otlp_logs_handler(otlp_logs_service).recover(recover_fn))
         .or(otlp_ingest_traces_handler(otlp_traces_service).recover(recover_fn))
+        .or(otlp_metrics_handler(otlp_metrics_service).recover(recover_fn))
         .boxed()
 }
 
@@ -183,6 +194,68 @@ pub(crate) fn otlp_ingest_traces_handler(
         .boxed()
 }
 
+/// Open Telemetry REST/Protobuf metrics ingest endpoint.
+#[utoipa::path(
+    post,
+    tag = "Open Telemetry",
+    path = "/otlp/v1/metrics",
+    request_body(content = String, description = "`ExportMetricsServiceRequest` protobuf message", content_type = "application/x-protobuf"),
+    responses(
+        (status = 200, description = "Successfully exported metrics.", body = ExportMetricsServiceResponse)
+    ),
+)]
+pub(crate) fn otlp_default_metrics_handler(
+    otlp_metrics_service: Option<OtlpGrpcMetricsService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    require(otlp_metrics_service)
+        .and(warp::path!("otlp" / "v1" / "metrics"))
+        .and(warp::header::exact_ignore_case(
+            "content-type",
+            "application/x-protobuf",
+        ))
+        .and(warp::header::optional::<String>(
+            OtelSignal::Metrics.header_name(),
+        ))
+        .and(warp::post())
+        .and(get_body_bytes())
+        .then(
+            |otlp_metrics_service, index_id: Option<String>, body| async move {
+                let index_id =
+                    index_id.unwrap_or_else(|| OtelSignal::Metrics.default_index_id().to_string());
+                otlp_ingest_metrics(otlp_metrics_service, index_id, body).await
+            },
+        )
+        .and(with_arg(BodyFormat::default()))
+        .map(into_rest_api_response)
+        .boxed()
+}
+/// Open Telemetry REST/Protobuf metrics ingest endpoint.
+#[utoipa::path(
+    post,
+    tag = "Open Telemetry",
+    path = "/{index}/otlp/v1/metrics",
+    request_body(content = String, description = "`ExportMetricsServiceRequest` protobuf message", content_type = "application/x-protobuf"),
+    responses(
+        (status = 200, description = "Successfully exported metrics.", body = ExportMetricsServiceResponse)
+    ),
+)]
+pub(crate) fn otlp_metrics_handler(
+    otlp_metrics_service: Option<OtlpGrpcMetricsService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    require(otlp_metrics_service)
+        .and(warp::path!(String / "otlp" / "v1" / "metrics"))
+        .and(warp::header::exact_ignore_case(
+            "content-type",
+            "application/x-protobuf",
+        ))
+        .and(warp::post())
+        .and(get_body_bytes())
+        .then(otlp_ingest_metrics)
+        .and(with_arg(BodyFormat::default()))
+        .map(into_rest_api_response)
+        .boxed()
+}
+
 #[derive(Debug, Clone, thiserror::Error, Serialize)]
 pub enum OtlpApiError {
     #[error("invalid OTLP request: {0}")]
@@ -247,6 +320,28 @@ async fn otlp_ingest_traces(
     Ok(response.into_inner())
 }
 
+async fn otlp_ingest_metrics(
+    otlp_metrics_service: OtlpGrpcMetricsService,
+    index_id: IndexId,
+    body: Body,
+) -> Result<ExportMetricsServiceResponse, OtlpApiError> {
+    let export_metrics_request: ExportMetricsServiceRequest =
+        prost::Message::decode(&body.content[..])
+            .map_err(|err| OtlpApiError::InvalidPayload(err.to_string()))?;
+    let mut request = tonic::Request::new(export_metrics_request);
+    let index = index_id
+        .try_into()
+        .map_err(|_| OtlpApiError::InvalidPayload("invalid index id".to_string()))?;
+    request
+        .metadata_mut()
+        .insert(OtelSignal::Metrics.header_name(), index);
+    let response = otlp_metrics_service
+        .export(request)
+        .await
+        .map_err(|err| OtlpApiError::Ingest(err.to_string()))?;
+    Ok(response.into_inner())
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Write;
@@ -256,7 +351,8 @@ mod tests {
     use prost::Message;
     use quickwit_ingest::CommitType;
     use quickwit_opentelemetry::otlp::{
-        make_resource_spans_for_test, OtlpGrpcLogsService, OtlpGrpcTracesService,
+        make_resource_spans_for_test, OtlpGrpcLogsService, OtlpGrpcMetricsService,
+        OtlpGrpcTracesService,
     };
     use quickwit_proto::ingest::router::{
         IngestResponseV2, IngestRouterServiceClient, IngestSuccess, MockIngestRouterService,
@@ -264,10 +360,17 @@ mod tests {
     use quickwit_proto::opentelemetry::proto::collector::logs::v1::{
         ExportLogsServiceRequest, ExportLogsServiceResponse,
     };
+    use quickwit_proto::opentelemetry::proto::collector::metrics::v1::{
+        ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+    };
     use quickwit_proto::opentelemetry::proto::collector::trace::v1::{
         ExportTraceServiceRequest, ExportTraceServiceResponse,
     };
     use quickwit_proto::opentelemetry::proto::logs::v1::{LogRecord, ResourceLogs, ScopeLogs};
+    use quickwit_proto::opentelemetry::proto::metrics::v1::number_data_point::Value as NumberValue;
+    use quickwit_proto::opentelemetry::proto::metrics::v1::{
+        metric, Gauge, Metric, NumberDataPoint, ResourceMetrics, ScopeMetrics,
+    };
     use quickwit_proto::opentelemetry::proto::resource::v1::Resource;
     use warp::Filter;
 
@@ -359,7 +462,8 @@ mod tests {
         };
         let body = export_logs_request.encode_to_vec();
         let otlp_traces_api_handler =
-            otlp_ingest_api_handlers(Some(logs_service), Some(traces_service)).recover(recover_fn);
+            otlp_ingest_api_handlers(Some(logs_service), Some(traces_service), None)
+                .recover(recover_fn);
         {
             // Test default otlp endpoint
             let resp = warp::test::request()
@@ -505,7 +609,8 @@ mod tests {
         };
         let body = export_trace_request.encode_to_vec();
         let otlp_traces_api_handler =
-            otlp_ingest_api_handlers(Some(logs_service), Some(traces_service)).recover(recover_fn);
+            otlp_ingest_api_handlers(Some(logs_service), Some(traces_service), None)
+                .recover(recover_fn);
         {
             // Test default otlp endpoint
             let resp = warp::test::request()
@@ -569,4 +674,134 @@ mod tests {
             assert_eq!(actual_response.partial_success.unwrap().rejected_spans, 0);
         }
     }
+
+    #[tokio::test]
+    async fn test_otlp_ingest_metrics_handler() {
+        let mut mock_ingest_router = MockIngestRouterService::new();
+        mock_ingest_router
+            .expect_ingest()
+            .times(2)
+            .withf(|request| {
+                if request.subrequests.len() == 1 {
+                    let subrequest = &request.subrequests[0];
+                    subrequest.doc_batch.is_some()
+                        && subrequest.doc_batch.as_ref().unwrap().doc_lengths.len() == 2
+                        && subrequest.index_id
+                            == quickwit_opentelemetry::otlp::OTEL_METRICS_INDEX_ID
+                } else {
+                    false
+                }
+            })
+            .returning(|_| {
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        num_ingested_docs: 2,
+                        ..Default::default()
+                    }],
+                    failures: Vec::new(),
+                })
+            });
+        mock_ingest_router
+            .expect_ingest()
+            .times(1)
+            .withf(|request| {
+                if request.subrequests.len() == 1 {
+                    let subrequest = &request.subrequests[0];
+                    subrequest.doc_batch.is_some()
+                        && subrequest.doc_batch.as_ref().unwrap().doc_lengths.len() == 2
+                        && subrequest.index_id == "otel-metrics-v0_6"
+                } else {
+                    false
+                }
+            })
+            .returning(|_| {
+                Ok(IngestResponseV2 {
+                    successes: vec![IngestSuccess {
+                        num_ingested_docs: 2,
+                        ..Default::default()
+                    }],
+                    failures: Vec::new(),
+                })
+            });
+        let ingest_router = IngestRouterServiceClient::from_mock(mock_ingest_router);
+        let metrics_service = OtlpGrpcMetricsService::new(ingest_router);
+        let export_metrics_request = ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(Resource {
+                    attributes: Vec::new(),
+                    dropped_attributes_count: 0,
+                }),
+                scope_metrics: vec![ScopeMetrics {
+                    scope: None,
+                    metrics: vec![Metric {
+                        name: "cpu.utilization".to_string(),
+                        description: "".to_string(),
+                        unit: "1".to_string(),
+                        data: Some(metric::Data::Gauge(Gauge {
+                            data_points: vec![
+                                NumberDataPoint {
+                                    time_unix_nano: 1704036033047000000,
+                                    value: Some(NumberValue::AsDouble(0.25)),
+                                    ..Default::default()
+                                },
+                                NumberDataPoint {
+                                    time_unix_nano: 1704036034047000000,
+                                    value: Some(NumberValue::AsDouble(0.5)),
+                                    ..Default::default()
+                                },
+                            ],
+                        })),
+                    }],
+                    schema_url: "".to_string(),
+                }],
+                schema_url: "".to_string(),
+            }],
+        };
+        let body = export_metrics_request.encode_to_vec();
+        let otlp_metrics_api_handler =
+            otlp_ingest_api_handlers(None, None, Some(metrics_service)).recover(recover_fn);
+        {
+            // Test default otlp endpoint
+            let resp = warp::test::request()
+                .path("/otlp/v1/metrics")
+                .method("POST")
+                .header("content-type", "application/x-protobuf")
+                .body(body.clone())
+                .reply(&otlp_metrics_api_handler)
+                .await;
+            assert_eq!(resp.status(), 200);
+            let actual_response: ExportMetricsServiceResponse =
+                serde_json::from_slice(resp.body()).unwrap();
+            assert_eq!(
+                actual_response
+                    .partial_success
+                    .unwrap()
+                    .rejected_data_points,
+                0
+            );
+        }
+        {
+            // Test default otlp endpoint with compression
+            let resp = warp::test::request()
+                .path("/otlp/v1/metrics")
+                .method("POST")
+                .header("content-type", "application/x-protobuf")
+                .header("content-encoding", "gzip")
+                .body(compress(&body))
+                .reply(&otlp_metrics_api_handler)
+                .await;
+            assert_eq!(resp.status(), 200);
+        }
+        {
+            // Test endpoint with given index ID through path.
+            let resp = warp::test::request()
+                .path("/otel-metrics-v0_6/otlp/v1/metrics")
+                .method("POST")
+                .header("content-type", "application/x-protobuf")
+                .body(body)
+                .reply(&otlp_metrics_api_handler)
+                .await;
+            assert_eq!(resp.status(), 200);
+        }
+    }
 }
@@ -20,6 +20,7 @@
 use std::fmt::Formatter;
 use std::sync::Arc;
 
+use anyhow::Context;
 use hyper::http::HeaderValue;
 use hyper::{http, Method, StatusCode};
 use quickwit_common::tower::BoxFutureInfaillible;
@@ -34,7 +35,9 @@ use tracing::{error, info};
 use warp::filters::log::Info;
 use warp::{redirect, Filter, Rejection, Reply};
 
+use crate::auth::{AuthLayer, Authenticator};
 use crate::cluster_api::cluster_handler;
+use crate::dead_letter_api::dead_letter_api_handlers;
 use crate::decompression::{CorruptedData, UnsupportedEncoding};
 use crate::delete_task_api::delete_task_api_handlers;
 use crate::developer_api::developer_api_routes;
@@ -42,17 +45,20 @@ use crate::elasticsearch_api::elastic_api_handlers;
 use crate::health_check_api::health_check_handlers;
 use crate::index_api::index_management_handlers;
 use crate::indexing_api::indexing_get_handler;
-use crate::ingest_api::ingest_api_handlers;
+use crate::ingest_api::{http_source_push_handler, ingest_api_handlers};
 use crate::jaeger_api::jaeger_api_handlers;
 use crate::metrics_api::metrics_handler;
 use crate::node_info_handler::node_info_handler;
 use crate::otlp_api::otlp_ingest_api_handlers;
 use crate::rest_api_response::{RestApiError, RestApiResponse};
 use crate::search_api::{
+    async_search_delete_handler, async_search_get_handler, async_search_submit_handler,
     search_get_handler, search_plan_get_handler, search_plan_post_handler, search_post_handler,
     search_stream_handler,
 };
+use crate::sql_api::sql_handler;
 use crate::template_api::index_template_api_handlers;
+use crate::tls::{tls_incoming, ReloadableTlsAcceptor, REST_ALPN_PROTOCOLS};
 use crate::ui_handler::ui_handler;
 use crate::{BodyFormat, BuildInfo, QuickwitServices, RuntimeInfo};
 
@@ -203,6 +209,14 @@ pub(crate) async fn start_rest_server(
     let compression_predicate = CompressionPredicate::from_env().and(NotForContentType::IMAGES);
     let cors = build_cors(&quickwit_services.node_config.rest_config.cors_allow_origins);
 
+    let auth_config = &quickwit_services.node_config.auth_config;
+    let authenticator_opt = if auth_config.is_enabled() {
+        let authenticator = Authenticator::try_new(auth_config)
+            .context("failed to load authentication configuration")?;
+        Some(Arc::new(authenticator))
+    } else {
+        None
+    };
     let service = ServiceBuilder::new()
         .layer(
             CompressionLayer::new()
@@ -212,15 +226,24 @@ pub(crate) async fn start_rest_server(
                 .compress_when(compression_predicate),
         )
         .layer(cors)
+        .layer(AuthLayer::new(authenticator_opt))
         .service(warp_service);
 
+    let tls_acceptor_opt = quickwit_services
+        .node_config
+        .rest_config
+        .tls
+        .as_ref()
+        .map(|tls_config| ReloadableTlsAcceptor::load(tls_config, REST_ALPN_PROTOCOLS))
+        .transpose()
+        .context("failed to load REST server TLS configuration")?;
     let rest_listen_addr = tcp_listener.local_addr()?;
     info!(
         rest_listen_addr=?rest_listen_addr,
+        tls=tls_acceptor_opt.is_some(),
         "Starting REST server listening on {rest_listen_addr}."
     );
 
-    let rest_listener_std = tcp_listener.into_std()?;
     // `graceful_shutdown()` seems to be blocking in presence of existing connections.
     // The following approach of dropping the serve supposedly is not bullet proof, but it seems to
     // work in our unit test.
@@ -229,10 +252,21 @@ pub(crate) async fn start_rest_server(
     // https://github.com/hyperium/hyper/issues/2386
 
     let serve_fut = async move {
-        tokio::select! {
-             res = hyper::Server::from_tcp(rest_listener_std)?.serve(Shared::new(service)) => { res }
-             _ = shutdown_signal => { Ok(()) }
+        if let Some(tls_acceptor) = tls_acceptor_opt {
+            let tls_incoming =
+                hyper::server::accept::from_stream(tls_incoming(tcp_listener, tls_acceptor));
+            tokio::select! {
+                 res = hyper::Server::builder(tls_incoming).serve(Shared::new(service)) => { res? }
+                 _ = shutdown_signal => {}
+            }
+        } else {
+            let rest_listener_std = tcp_listener.into_std()?;
+            tokio::select! {
+                 res = hyper::Server::from_tcp(rest_listener_std)?.serve(Shared::new(service)) => { res? }
+                 _ = shutdown_signal => {}
+            }
         }
+        anyhow::Ok(())
     };
     let (serve_res, _trigger_res) = tokio::join!(serve_fut, readiness_trigger);
     serve_res?;
@@ -246,7 +280,10 @@ fn search_routes(
         .or(search_post_handler(search_service.clone()))
         .or(search_plan_get_handler(search_service.clone()))
         .or(search_plan_post_handler(search_service.clone()))
-        .or(search_stream_handler(search_service))
+        .or(search_stream_handler(search_service.clone()))
+        .or(async_search_submit_handler(search_service.clone()))
+        .or(async_search_get_handler(search_service.clone()))
+        .or(async_search_delete_handler(search_service))
         .recover(recover_fn)
         .boxed()
 }
@@ -284,9 +321,15 @@ fn api_v1_routes(
             quickwit_services.node_config.ingest_api_config.clone(),
         ))
         .boxed()
+        .or(http_source_push_handler(
+            quickwit_services.cluster.clone(),
+            quickwit_services.node_config.ingest_api_config.clone(),
+        ))
+        .boxed()
         .or(otlp_ingest_api_handlers(
             quickwit_services.otlp_logs_service_opt.clone(),
             quickwit_services.otlp_traces_service_opt.clone(),
+            quickwit_services.otlp_metrics_service_opt.clone(),
         ))
         .boxed()
         .or(index_management_handlers(
@@ -298,6 +341,13 @@ fn api_v1_routes(
             quickwit_services.metastore_client.clone(),
         ))
         .boxed()
+        .or(dead_letter_api_handlers(
+            quickwit_services.metastore_client.clone(),
+            quickwit_services.search_service.clone(),
+            quickwit_services.ingest_service.clone(),
+            quickwit_services.ingest_router_service.clone(),
+        ))
+        .boxed()
         .or(jaeger_api_handlers(
             quickwit_services.jaeger_service_opt.clone(),
         ))
@@ -305,6 +355,8 @@ fn api_v1_routes(
         .or(index_template_api_handlers(
             quickwit_services.metastore_client.clone(),
         ))
+        .boxed()
+        .or(sql_handler(quickwit_services.search_service.clone()))
         .boxed(),
     )
 }
@@ -739,6 +791,7 @@ mod tests {
             janitor_service_opt: None,
             otlp_logs_service_opt: None,
             otlp_traces_service_opt: None,
+            otlp_metrics_service_opt: None,
             metastore_client,
             metastore_server_opt: None,
             node_config: Arc::new(node_config.clone()),
@@ -17,10 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use hyper::header::CONTENT_TYPE;
+use std::time::Duration;
+
+use hyper::header::{CONTENT_TYPE, RETRY_AFTER};
 use hyper::http::HeaderValue;
 use hyper::{Body, Response, StatusCode};
 use quickwit_proto::ServiceError;
+use quickwit_search::SearchError;
 use serde::{self, Serialize};
 use warp::Reply;
 
@@ -37,6 +40,15 @@ pub(crate) struct RestApiError {
     pub message: String,
 }
 
+impl From<SearchError> for RestApiError {
+    fn from(search_error: SearchError) -> Self {
+        RestApiError {
+            status_code: search_error.error_code().http_status_code(),
+            message: search_error.to_string(),
+        }
+    }
+}
+
 /// Makes a JSON API response from a result.
 /// The error is wrapped into an [`RestApiError`] to publicly expose
 /// a consistent error format.
@@ -59,6 +71,7 @@ pub(crate) fn into_rest_api_response<T: serde::Serialize, E: ServiceError>(
 pub struct RestApiResponse {
     status_code: StatusCode,
     inner: Result<Vec<u8>, ()>,
+    retry_after_opt: Option<Duration>,
 }
 
 impl RestApiResponse {
@@ -68,7 +81,17 @@ impl RestApiResponse {
         body_format: BodyFormat,
     ) -> Self {
         let inner = body_format.result_to_vec(result);
-        RestApiResponse { status_code, inner }
+        RestApiResponse {
+            status_code,
+            inner,
+            retry_after_opt: None,
+        }
+    }
+
+    /// Sets the `Retry-After` header of the response, rounded up to the next second.
+    pub fn with_retry_after(mut self, retry_after_opt: Option<Duration>) -> Self {
+        self.retry_after_opt = retry_after_opt;
+        self
     }
 }
 
@@ -81,6 +104,12 @@ impl Reply for RestApiResponse {
                 response
                     .headers_mut()
                     .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                if let Some(retry_after) = self.retry_after_opt {
+                    let retry_after_secs = retry_after.as_millis().div_ceil(1_000).max(1);
+                    response
+                        .headers_mut()
+                        .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs as u64));
+                }
                 *response.status_mut() = self.status_code;
                 response
             }
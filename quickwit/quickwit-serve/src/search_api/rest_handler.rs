@@ -19,17 +19,22 @@
 
 use std::convert::TryFrom;
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::stream::StreamExt;
 use hyper::header::HeaderValue;
 use hyper::HeaderMap;
 use percent_encoding::percent_decode_str;
-use quickwit_config::validate_index_id_pattern;
+use quickwit_config::{validate_index_id_pattern, AuthPrivilege};
 use quickwit_proto::search::{CountHits, OutputFormat, SortField, SortOrder};
 use quickwit_proto::types::IndexId;
 use quickwit_proto::ServiceError;
 use quickwit_query::query_ast::query_ast_from_user_text;
-use quickwit_search::{SearchError, SearchPlanResponseRest, SearchResponseRest, SearchService};
+use quickwit_search::{
+    AsyncSearchResponse, AsyncSearchResponseRest, SearchError, SearchPlanResponseRest,
+    SearchResponseRest, SearchService, DEFAULT_ASYNC_SEARCH_KEEP_ALIVE,
+    DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT,
+};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value as JsonValue;
 use tracing::info;
@@ -37,7 +42,8 @@ use warp::hyper::header::CONTENT_TYPE;
 use warp::hyper::StatusCode;
 use warp::{reply, Filter, Rejection, Reply};
 
-use crate::rest_api_response::into_rest_api_response;
+use crate::auth::Principal;
+use crate::rest_api_response::{into_rest_api_response, RestApiError, RestApiResponse};
 use crate::simple_list::{from_simple_list, to_simple_list};
 use crate::{with_arg, BodyFormat};
 
@@ -49,8 +55,12 @@ use crate::{with_arg, BodyFormat};
         search_stream_handler,
         search_plan_get_handler,
         search_plan_post_handler,
+        async_search_submit_handler,
+        async_search_get_handler,
+        async_search_delete_handler,
     ),
     components(schemas(
+        AsyncSearchResponseRest,
         BodyFormat,
         OutputFormat,
         SearchRequestQueryString,
@@ -243,6 +253,16 @@ pub struct SearchRequestQueryString {
     #[schema(value_type = bool)]
     #[serde(default)]
     pub allow_failed_splits: bool,
+    /// If set, only the best hit for each distinct value of this text fast field is returned.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse_field: Option<String>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// Fields computed at query time by a script, indexed by field name.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_mappings: Option<JsonValue>,
 }
 
 mod count_hits_from_bool {
@@ -286,6 +306,7 @@ pub fn search_request_from_api_request(
         index_id_patterns,
         query_ast: query_ast_json,
         snippet_fields: search_request.snippet_fields.unwrap_or_default(),
+        snippet_options: None,
         start_timestamp: search_request.start_timestamp,
         end_timestamp: search_request.end_timestamp,
         max_hits: search_request.max_hits,
@@ -297,6 +318,12 @@ pub fn search_request_from_api_request(
         scroll_ttl_secs: None,
         search_after: None,
         count_hits: search_request.count_all.into(),
+        collapse_field: search_request.collapse_field,
+        runtime_mappings: search_request
+            .runtime_mappings
+            .map(|runtime_mappings| runtime_mappings.to_string()),
+        allow_partial_search_results: Some(search_request.allow_failed_splits),
+        source_fields: Vec::new(),
     };
     Ok(search_request)
 }
@@ -502,6 +529,243 @@ pub fn search_plan_post_handler(
         .then(search_plan)
 }
 
+/// Query string parameters of the async search API.
+#[derive(Debug, Default, Eq, PartialEq, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct AsyncSearchQueryParams {
+    /// Duration during which the search and its results are kept, e.g. `30m` (by default 1h, at
+    /// most 24h).
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Duration to wait for the search to complete before returning its ID, e.g. `5s` (by
+    /// default 1s).
+    #[serde(default)]
+    pub wait_for_completion_timeout: Option<String>,
+}
+
+pub(crate) fn parse_async_search_duration(
+    param_name: &str,
+    duration_str_opt: Option<&str>,
+    default_duration: Duration,
+) -> Result<Duration, SearchError> {
+    let Some(duration_str) = duration_str_opt else {
+        return Ok(default_duration);
+    };
+    humantime::parse_duration(duration_str).map_err(|_| {
+        SearchError::InvalidArgument(format!("invalid `{param_name}` duration: `{duration_str}`"))
+    })
+}
+
+/// Checks that the principal, if authentication is enabled, submitted the async search and is still
+/// granted the read privilege on the indexes it targets. The async searches submitted by other
+/// principals are reported as not found.
+pub(crate) fn authorize_async_search(
+    async_search_response: &AsyncSearchResponse,
+    principal_opt: Option<&Principal>,
+) -> Result<(), RestApiError> {
+    let Some(principal) = principal_opt else {
+        return Ok(());
+    };
+    if async_search_response.owner_opt.as_deref() != Some(principal.name()) {
+        let search_error = SearchError::AsyncSearchNotFound(async_search_response.id.clone());
+        return Err(search_error.into());
+    }
+    principal
+        .authorize(
+            AuthPrivilege::Read,
+            &async_search_response.index_id_patterns,
+        )
+        .map_err(|auth_error| RestApiError {
+            status_code: auth_error.status_code(),
+            message: auth_error.to_string(),
+        })
+}
+
+fn make_async_search_response<T: Serialize>(
+    result: Result<T, RestApiError>,
+    body_format: BodyFormat,
+) -> RestApiResponse {
+    let status_code = match &result {
+        Ok(_) => StatusCode::OK,
+        Err(error) => error.status_code,
+    };
+    RestApiResponse::new(&result, status_code, body_format)
+}
+
+async fn async_search_submit_endpoint(
+    index_id_patterns: Vec<String>,
+    query_params: AsyncSearchQueryParams,
+    search_request: SearchRequestQueryString,
+    search_service: &dyn SearchService,
+    principal_opt: Option<&Principal>,
+) -> Result<AsyncSearchResponseRest, SearchError> {
+    let keep_alive = parse_async_search_duration(
+        "keep_alive",
+        query_params.keep_alive.as_deref(),
+        DEFAULT_ASYNC_SEARCH_KEEP_ALIVE,
+    )?;
+    let wait_for_completion_timeout = parse_async_search_duration(
+        "wait_for_completion_timeout",
+        query_params.wait_for_completion_timeout.as_deref(),
+        DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT,
+    )?;
+    let search_request = search_request_from_api_request(index_id_patterns, search_request)?;
+    let owner_opt = principal_opt.map(|principal| principal.name().to_string());
+    let async_search_response = search_service
+        .submit_async_search(
+            search_request,
+            keep_alive,
+            wait_for_completion_timeout,
+            owner_opt,
+        )
+        .await?;
+    AsyncSearchResponseRest::try_from(async_search_response)
+}
+
+async fn async_search_submit(
+    index_id_patterns: Vec<String>,
+    query_params: AsyncSearchQueryParams,
+    search_request: SearchRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
+) -> impl warp::Reply {
+    info!(request =? search_request, "async-search");
+    let body_format = search_request.format;
+    let result = async_search_submit_endpoint(
+        index_id_patterns,
+        query_params,
+        search_request,
+        &*search_service,
+        principal_opt.as_ref(),
+    )
+    .await;
+    into_rest_api_response(result, body_format)
+}
+
+async fn async_search_get(
+    async_search_id: String,
+    search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
+) -> impl warp::Reply {
+    let result: Result<AsyncSearchResponseRest, RestApiError> = async {
+        let async_search_response = search_service.get_async_search(async_search_id).await?;
+        authorize_async_search(&async_search_response, principal_opt.as_ref())?;
+        let async_search_response_rest = AsyncSearchResponseRest::try_from(async_search_response)?;
+        Ok(async_search_response_rest)
+    }
+    .await;
+    make_async_search_response(result, BodyFormat::default())
+}
+
+async fn async_search_delete(
+    async_search_id: String,
+    search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
+) -> impl warp::Reply {
+    let result: Result<(), RestApiError> = async {
+        let async_search_response = search_service
+            .get_async_search(async_search_id.clone())
+            .await?;
+        authorize_async_search(&async_search_response, principal_opt.as_ref())?;
+        search_service.delete_async_search(async_search_id).await?;
+        Ok(())
+    }
+    .await;
+    make_async_search_response(result, BodyFormat::default())
+}
+
+fn async_search_submit_filter() -> impl Filter<
+    Extract = (
+        Vec<String>,
+        AsyncSearchQueryParams,
+        SearchRequestQueryString,
+    ),
+    Error = Rejection,
+> + Clone {
+    warp::path!(String / "_async_search")
+        .and_then(extract_index_id_patterns)
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/{index_id}/_async_search",
+    request_body = SearchRequestQueryString,
+    responses(
+        (status = 200, description = "Successfully submitted async search.", body = AsyncSearchResponseRest)
+    ),
+    params(
+        AsyncSearchQueryParams,
+        ("index_id" = String, Path, description = "The index ID to search."),
+    )
+)]
+/// Submit Async Search
+///
+/// Starts a search in the background. The response contains the results if the search
+/// completes within `wait_for_completion_timeout`, and the ID used to poll the search
+/// otherwise.
+pub fn async_search_submit_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    async_search_submit_filter()
+        .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
+        .then(async_search_submit)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/_async_search/{async_search_id}",
+    responses(
+        (status = 200, description = "Successfully fetched async search.", body = AsyncSearchResponseRest)
+    ),
+    params(
+        ("async_search_id" = String, Path, description = "The ID of the async search."),
+    )
+)]
+/// Get Async Search
+///
+/// Returns the state of an async search, including its results once it completed.
+pub fn async_search_get_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_async_search" / String)
+        .and(warp::get())
+        .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
+        .then(async_search_get)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Search",
+    path = "/_async_search/{async_search_id}",
+    responses(
+        (status = 200, description = "Successfully deleted async search.")
+    ),
+    params(
+        ("async_search_id" = String, Path, description = "The ID of the async search."),
+    )
+)]
+/// Delete Async Search
+///
+/// Deletes an async search and its results, cancelling the search if it is still running.
+pub fn async_search_delete_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_async_search" / String)
+        .and(warp::delete())
+        .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
+        .then(async_search_delete)
+}
+
 /// This struct represents the search stream query passed to
 /// the REST API.
 #[derive(Deserialize, Debug, Eq, PartialEq, utoipa::IntoParams)]
@@ -629,6 +893,8 @@ mod tests {
     use assert_json_diff::{assert_json_eq, assert_json_include};
     use bytes::Bytes;
     use mockall::predicate;
+    use quickwit_config::RoleConfig;
+    use quickwit_proto::search::SplitSearchError;
     use quickwit_search::{MockSearchService, SearchError};
     use serde_json::{json, Value as JsonValue};
 
@@ -644,6 +910,11 @@ mod tests {
             .or(search_stream_handler(mock_search_service_in_arc.clone()))
             .or(search_plan_get_handler(mock_search_service_in_arc.clone()))
             .or(search_plan_post_handler(mock_search_service_in_arc.clone()))
+            .or(async_search_submit_handler(
+                mock_search_service_in_arc.clone(),
+            ))
+            .or(async_search_get_handler(mock_search_service_in_arc.clone()))
+            .or(async_search_delete_handler(mock_search_service_in_arc))
             .recover(recover_fn)
     }
 
@@ -676,9 +947,12 @@ mod tests {
             num_hits: 55,
             hits: Vec::new(),
             snippets: None,
+            fields: None,
             elapsed_time_micros: 0u64,
             errors: Vec::new(),
             aggregations: None,
+            num_failed_splits: 0,
+            failed_splits: Vec::new(),
         };
         let search_response_json: JsonValue = serde_json::to_value(search_response)?;
         let expected_search_response_json: JsonValue = json!({
@@ -1090,6 +1364,50 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_allow_failed_splits() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_root_search().returning(
+            |search_request: quickwit_proto::search::SearchRequest| {
+                assert!(search_request.allow_partial_search_results.is_some());
+                Ok(quickwit_proto::search::SearchResponse {
+                    num_hits: 10,
+                    num_successful_splits: 1,
+                    failed_splits: vec![SplitSearchError {
+                        error: "some-error".to_string(),
+                        split_id: "some-split-id".to_string(),
+                        retryable_error: true,
+                    }],
+                    ..Default::default()
+                })
+            },
+        );
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 500);
+
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&allow_failed_splits=true")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "num_hits": 10,
+            "num_failed_splits": 1,
+            "failed_splits": [{
+                "error": "some-error",
+                "split_id": "some-split-id",
+                "retryable_error": true,
+            }],
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_start_offset_and_num_hits_parameter() -> anyhow::Result<()> {
         let mut mock_search_service = MockSearchService::new();
@@ -1168,6 +1486,205 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_async_search_api() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_submit_async_search()
+            .with(
+                predicate::function(|search_request: &quickwit_proto::search::SearchRequest| {
+                    search_request.index_id_patterns == vec!["my-index".to_string()]
+                }),
+                predicate::eq(Duration::from_secs(10 * 60)),
+                predicate::eq(DEFAULT_ASYNC_SEARCH_WAIT_FOR_COMPLETION_TIMEOUT),
+                predicate::eq(None),
+            )
+            .returning(|_, _, _, _| {
+                Ok(AsyncSearchResponse {
+                    id: "01HAV29D4XY3D462FS3D8K5Q2H".to_string(),
+                    owner_opt: None,
+                    index_id_patterns: vec!["my-index".to_string()],
+                    is_running: true,
+                    start_time_in_millis: 1_000,
+                    expiration_time_in_millis: 601_000,
+                    completion_time_in_millis: None,
+                    response: None,
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_get_async_search()
+            .with(predicate::eq("01HAV29D4XY3D462FS3D8K5Q2H".to_string()))
+            .returning(|async_search_id| {
+                Ok(AsyncSearchResponse {
+                    id: async_search_id,
+                    owner_opt: None,
+                    index_id_patterns: vec!["my-index".to_string()],
+                    is_running: false,
+                    start_time_in_millis: 1_000,
+                    expiration_time_in_millis: 601_000,
+                    completion_time_in_millis: Some(2_000),
+                    response: Some(quickwit_proto::search::SearchResponse {
+                        num_hits: 1,
+                        ..Default::default()
+                    }),
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_delete_async_search()
+            .returning(|async_search_id| Err(SearchError::AsyncSearchNotFound(async_search_id)));
+        let rest_search_api_handler = search_handler(mock_search_service);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/my-index/_async_search?keep_alive=10m")
+            .json(&json!({"query": "*", "max_hits": 10}))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        let response_json: JsonValue = serde_json::from_slice(response.body()).unwrap();
+        assert_json_eq!(
+            response_json,
+            json!({
+                "id": "01HAV29D4XY3D462FS3D8K5Q2H",
+                "is_running": true,
+                "is_partial": true,
+                "start_time_in_millis": 1_000,
+                "expiration_time_in_millis": 601_000,
+            })
+        );
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/my-index/_async_search?keep_alive=forever")
+            .json(&json!({"query": "*"}))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 400);
+
+        let response = warp::test::request()
+            .path("/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        let response_json: JsonValue = serde_json::from_slice(response.body()).unwrap();
+        assert_json_include!(
+            actual: response_json,
+            expected: json!({
+                "is_running": false,
+                "is_partial": false,
+                "completion_time_in_millis": 2_000,
+                "response": {"num_hits": 1, "hits": []},
+            })
+        );
+
+        let response = warp::test::request()
+            .method("DELETE")
+            .path("/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_rest_async_search_api_authorizes_access() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_submit_async_search()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::always(),
+                predicate::eq(Some("alice".to_string())),
+            )
+            .returning(|_, _, _, owner_opt| {
+                Ok(AsyncSearchResponse {
+                    id: "01HAV29D4XY3D462FS3D8K5Q2H".to_string(),
+                    owner_opt,
+                    index_id_patterns: vec!["my-index".to_string()],
+                    is_running: true,
+                    start_time_in_millis: 1_000,
+                    expiration_time_in_millis: 601_000,
+                    completion_time_in_millis: None,
+                    response: None,
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_get_async_search()
+            .returning(|async_search_id| {
+                Ok(AsyncSearchResponse {
+                    id: async_search_id,
+                    owner_opt: Some("alice".to_string()),
+                    index_id_patterns: vec!["my-index".to_string()],
+                    is_running: true,
+                    start_time_in_millis: 1_000,
+                    expiration_time_in_millis: 601_000,
+                    completion_time_in_millis: None,
+                    response: None,
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_delete_async_search()
+            .times(1)
+            .returning(|_| Ok(()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+
+        let principal = |name: &str, index_pattern: &str| {
+            Principal::new(
+                name.to_string(),
+                vec![RoleConfig {
+                    name: "reader".to_string(),
+                    index_patterns: vec![index_pattern.to_string()],
+                    privileges: vec![AuthPrivilege::Read],
+                }],
+            )
+        };
+        let response = warp::test::request()
+            .method("POST")
+            .path("/my-index/_async_search")
+            .extension(principal("alice", "my-index"))
+            .json(&json!({"query": "*"}))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+
+        // The async searches of other principals are not visible.
+        for method in ["GET", "DELETE"] {
+            let response = warp::test::request()
+                .method(method)
+                .path("/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+                .extension(principal("bob", "*"))
+                .reply(&rest_search_api_handler)
+                .await;
+            assert_eq!(response.status(), 404);
+        }
+        // The owner must still be granted access to the indexes.
+        let response = warp::test::request()
+            .path("/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+            .extension(principal("alice", "other-index"))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 403);
+
+        let response = warp::test::request()
+            .path("/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+            .extension(principal("alice", "my-*"))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+
+        let response = warp::test::request()
+            .method("DELETE")
+            .path("/_async_search/01HAV29D4XY3D462FS3D8K5Q2H")
+            .extension(principal("alice", "my-index"))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+    }
+
     #[tokio::test]
     async fn test_rest_search_stream_api() {
         let mut mock_search_service = MockSearchService::new();
@@ -1282,6 +1799,7 @@ mod tests {
                     partial_hit: None,
                     snippet: Some(r#"{"title": [], "body": ["foo <em>bar</em> baz"]}"#.to_string()),
                     index_id: "quickwit-demo-index".to_string(),
+                    fields_json: None,
                 }],
                 num_hits: 1,
                 elapsed_time_micros: 16,
@@ -0,0 +1,914 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Parser of the restricted SQL dialect accepted by the `_sql` endpoint:
+//!
+//! ```sql
+//! SELECT <select_item>, ... FROM <index_id_patterns>
+//! [WHERE <condition>]
+//! [GROUP BY <field>, ...]
+//! [ORDER BY <field_or_column> [ASC|DESC], ...]
+//! [LIMIT <num_rows>]
+//! ```
+//!
+//! The `WHERE` clause is directly compiled into a [`QueryAst`].
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::ops::Bound;
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use quickwit_proto::search::SortOrder;
+use quickwit_query::query_ast::{
+    BoolQuery, FieldPresenceQuery, FullTextParams, FullTextQuery, QueryAst, RangeQuery, TermQuery,
+    TermSetQuery, WildcardQuery,
+};
+use quickwit_query::{BooleanOperand, JsonLiteral, MatchAllOrNone};
+
+const RESERVED_KEYWORDS: &[&str] = &[
+    "AND", "AS", "ASC", "BETWEEN", "BY", "DESC", "FALSE", "FROM", "GROUP", "IN", "IS", "LIKE",
+    "LIMIT", "NOT", "NULL", "OR", "ORDER", "SELECT", "TRUE", "WHERE",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SqlQuery {
+    pub select_items: Vec<SelectItem>,
+    pub index_id_patterns: Vec<String>,
+    pub query_ast: QueryAst,
+    pub group_by: Vec<String>,
+    pub order_by: Vec<OrderByItem>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SelectItem {
+    /// `*`, all the fields of the documents.
+    Wildcard,
+    Field {
+        field: String,
+        alias: Option<String>,
+    },
+    Aggregate {
+        function: AggregateFunction,
+        /// `None` for `COUNT(*)`.
+        field_opt: Option<String>,
+        alias: Option<String>,
+    },
+}
+
+impl SelectItem {
+    /// Name of the column in the response.
+    pub fn column_name(&self) -> String {
+        match self {
+            SelectItem::Wildcard => "*".to_string(),
+            SelectItem::Field { field, alias } => alias.clone().unwrap_or_else(|| field.clone()),
+            SelectItem::Aggregate {
+                function,
+                field_opt,
+                alias,
+            } => alias
+                .clone()
+                .unwrap_or_else(|| aggregate_column_name(*function, field_opt.as_deref())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        let function = match name.to_ascii_uppercase().as_str() {
+            "COUNT" => AggregateFunction::Count,
+            "SUM" => AggregateFunction::Sum,
+            "AVG" => AggregateFunction::Avg,
+            "MIN" => AggregateFunction::Min,
+            "MAX" => AggregateFunction::Max,
+            _ => return None,
+        };
+        Some(function)
+    }
+}
+
+impl fmt::Display for AggregateFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Avg => "avg",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+        };
+        f.write_str(name)
+    }
+}
+
+fn aggregate_column_name(function: AggregateFunction, field_opt: Option<&str>) -> String {
+    format!("{function}({})", field_opt.unwrap_or("*"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OrderByItem {
+    /// Field or column name (alias or aggregate expression, e.g. `count(*)`) to sort on.
+    pub key: String,
+    pub sort_order: SortOrder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    QuotedIdentifier(String),
+    String(String),
+    Number(String),
+    Comma,
+    LeftParen,
+    RightParen,
+    Star,
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Semicolon,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{word}`"),
+            Token::QuotedIdentifier(identifier) => write!(f, "`\"{identifier}\"`"),
+            Token::String(string) => write!(f, "`'{string}'`"),
+            Token::Number(number) => write!(f, "`{number}`"),
+            Token::Comma => f.write_str("`,`"),
+            Token::LeftParen => f.write_str("`(`"),
+            Token::RightParen => f.write_str("`)`"),
+            Token::Star => f.write_str("`*`"),
+            Token::Eq => f.write_str("`=`"),
+            Token::NotEq => f.write_str("`!=`"),
+            Token::Lt => f.write_str("`<`"),
+            Token::Lte => f.write_str("`<=`"),
+            Token::Gt => f.write_str("`>`"),
+            Token::Gte => f.write_str("`>=`"),
+            Token::Semicolon => f.write_str("`;`"),
+        }
+    }
+}
+
+fn is_word_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_word_char(c: char) -> bool {
+    // Index ID patterns and field paths can contain `-`, `*` and `.`.
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '*' | '.')
+}
+
+fn tokenize(sql: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if is_word_start(c) {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !is_word_char(c) {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+            continue;
+        }
+        if c.is_ascii_digit() || c == '-' {
+            let mut number = String::new();
+            number.push(c);
+            chars.next();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_digit() && c != '.' {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            if number == "-" {
+                bail!("unexpected character `-`");
+            }
+            tokens.push(Token::Number(number));
+            continue;
+        }
+        chars.next();
+        let token = match c {
+            '\'' | '"' | '`' => {
+                // Quotes are escaped by doubling them.
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next_c) if next_c == c => {
+                            if chars.peek() == Some(&c) {
+                                quoted.push(c);
+                                chars.next();
+                            } else {
+                                break;
+                            }
+                        }
+                        Some(next_c) => quoted.push(next_c),
+                        None => bail!("unterminated quoted string `{c}{quoted}`"),
+                    }
+                }
+                if c == '\'' {
+                    Token::String(quoted)
+                } else {
+                    Token::QuotedIdentifier(quoted)
+                }
+            }
+            ',' => Token::Comma,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '*' => Token::Star,
+            ';' => Token::Semicolon,
+            '=' => Token::Eq,
+            '!' if chars.peek() == Some(&'=') => {
+                chars.next();
+                Token::NotEq
+            }
+            '<' => match chars.peek() {
+                Some('=') => {
+                    chars.next();
+                    Token::Lte
+                }
+                Some('>') => {
+                    chars.next();
+                    Token::NotEq
+                }
+                _ => Token::Lt,
+            },
+            '>' => {
+                if chars.peek() == Some(&'=') {
+                    chars.next();
+                    Token::Gte
+                } else {
+                    Token::Gt
+                }
+            }
+            _ => bail!("unexpected character `{c}`"),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Parses a SQL query.
+pub(crate) fn parse_sql_query(sql: &str) -> anyhow::Result<SqlQuery> {
+    let tokens = tokenize(sql)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    parser.parse_query()
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    fn next_token(&mut self, expected: &str) -> anyhow::Result<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .with_context(|| format!("expected {expected}, found end of query"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> anyhow::Result<()> {
+        let token = self.next_token(keyword)?;
+        if !matches!(&token, Token::Word(word) if word.eq_ignore_ascii_case(keyword)) {
+            bail!("expected `{keyword}`, found {token}");
+        }
+        Ok(())
+    }
+
+    fn consume(&mut self, expected_token: &Token) -> bool {
+        if self.peek() == Some(expected_token) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected_token: Token) -> anyhow::Result<()> {
+        let token = self.next_token(&expected_token.to_string())?;
+        if token != expected_token {
+            bail!("expected {expected_token}, found {token}");
+        }
+        Ok(())
+    }
+
+    fn parse_identifier(&mut self) -> anyhow::Result<String> {
+        match self.next_token("identifier")? {
+            Token::Word(word) => {
+                if is_reserved_keyword(&word) {
+                    bail!("expected identifier, found keyword `{word}`");
+                }
+                Ok(word)
+            }
+            Token::QuotedIdentifier(identifier) => Ok(identifier),
+            token => bail!("expected identifier, found {token}"),
+        }
+    }
+
+    fn parse_alias(&mut self) -> anyhow::Result<Option<String>> {
+        if self.consume_keyword("AS") {
+            return self.parse_identifier().map(Some);
+        }
+        match self.peek() {
+            Some(Token::Word(word)) if !is_reserved_keyword(word) => {
+                self.parse_identifier().map(Some)
+            }
+            Some(Token::QuotedIdentifier(_)) => self.parse_identifier().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_query(&mut self) -> anyhow::Result<SqlQuery> {
+        self.expect_keyword("SELECT")?;
+        let mut select_items = vec![self.parse_select_item()?];
+        while self.consume(&Token::Comma) {
+            select_items.push(self.parse_select_item()?);
+        }
+        self.expect_keyword("FROM")?;
+        let index_id_patterns = self
+            .parse_identifier()?
+            .split(',')
+            .map(|index_id_pattern| index_id_pattern.trim().to_string())
+            .collect();
+
+        let query_ast = if self.consume_keyword("WHERE") {
+            self.parse_or()?
+        } else {
+            QueryAst::MatchAll
+        };
+        let mut group_by = Vec::new();
+        if self.consume_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.parse_identifier()?);
+            while self.consume(&Token::Comma) {
+                group_by.push(self.parse_identifier()?);
+            }
+        }
+        let mut order_by = Vec::new();
+        if self.consume_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            order_by.push(self.parse_order_by_item()?);
+            while self.consume(&Token::Comma) {
+                order_by.push(self.parse_order_by_item()?);
+            }
+        }
+        let limit = if self.consume_keyword("LIMIT") {
+            match self.next_token("number")? {
+                Token::Number(number) => Some(
+                    number
+                        .parse::<u64>()
+                        .with_context(|| format!("invalid limit `{number}`"))?,
+                ),
+                token => bail!("expected number, found {token}"),
+            }
+        } else {
+            None
+        };
+        self.consume(&Token::Semicolon);
+        if let Some(token) = self.peek() {
+            bail!("unexpected {token} at the end of the query");
+        }
+        Ok(SqlQuery {
+            select_items,
+            index_id_patterns,
+            query_ast,
+            group_by,
+            order_by,
+            limit,
+        })
+    }
+
+    /// Returns the aggregate function if the next tokens are a call to an aggregate function.
+    fn peek_aggregate_function(&self) -> Option<AggregateFunction> {
+        let Some(Token::Word(word)) = self.peek() else {
+            return None;
+        };
+        if self.peek_nth(1) != Some(&Token::LeftParen) {
+            return None;
+        }
+        AggregateFunction::from_name(word)
+    }
+
+    /// Parses `<function>(<field>)` or `COUNT(*)`.
+    fn parse_aggregate_call(
+        &mut self,
+        function: AggregateFunction,
+    ) -> anyhow::Result<Option<String>> {
+        self.pos += 1;
+        self.expect(Token::LeftParen)?;
+        let field_opt = if self.consume(&Token::Star) {
+            if function != AggregateFunction::Count {
+                bail!("`*` can only be used with the `count` function");
+            }
+            None
+        } else {
+            Some(self.parse_identifier()?)
+        };
+        self.expect(Token::RightParen)?;
+        Ok(field_opt)
+    }
+
+    fn parse_select_item(&mut self) -> anyhow::Result<SelectItem> {
+        if self.consume(&Token::Star) {
+            return Ok(SelectItem::Wildcard);
+        }
+        if let Some(function) = self.peek_aggregate_function() {
+            let field_opt = self.parse_aggregate_call(function)?;
+            let alias = self.parse_alias()?;
+            return Ok(SelectItem::Aggregate {
+                function,
+                field_opt,
+                alias,
+            });
+        }
+        let field = self.parse_identifier()?;
+        let alias = self.parse_alias()?;
+        Ok(SelectItem::Field { field, alias })
+    }
+
+    fn parse_order_by_item(&mut self) -> anyhow::Result<OrderByItem> {
+        let key = if let Some(function) = self.peek_aggregate_function() {
+            let field_opt = self.parse_aggregate_call(function)?;
+            aggregate_column_name(function, field_opt.as_deref())
+        } else {
+            self.parse_identifier()?
+        };
+        let sort_order = if self.consume_keyword("DESC") {
+            SortOrder::Desc
+        } else {
+            self.consume_keyword("ASC");
+            SortOrder::Asc
+        };
+        Ok(OrderByItem { key, sort_order })
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<QueryAst> {
+        let mut should = vec![self.parse_and()?];
+        while self.consume_keyword("OR") {
+            should.push(self.parse_and()?);
+        }
+        if should.len() == 1 {
+            return Ok(should.pop().unwrap());
+        }
+        Ok(BoolQuery {
+            should,
+            ..Default::default()
+        }
+        .into())
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<QueryAst> {
+        let mut must = vec![self.parse_not()?];
+        while self.consume_keyword("AND") {
+            must.push(self.parse_not()?);
+        }
+        if must.len() == 1 {
+            return Ok(must.pop().unwrap());
+        }
+        Ok(BoolQuery {
+            must,
+            ..Default::default()
+        }
+        .into())
+    }
+
+    fn parse_not(&mut self) -> anyhow::Result<QueryAst> {
+        if self.consume_keyword("NOT") {
+            let query_ast = self.parse_not()?;
+            return Ok(negate(query_ast));
+        }
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> anyhow::Result<QueryAst> {
+        if self.consume(&Token::LeftParen) {
+            let query_ast = self.parse_or()?;
+            self.expect(Token::RightParen)?;
+            return Ok(query_ast);
+        }
+        if self.peek_keyword("MATCH") && self.peek_nth(1) == Some(&Token::LeftParen) {
+            self.pos += 2;
+            let field = self.parse_identifier()?;
+            self.expect(Token::Comma)?;
+            let text = match self.next_token("string")? {
+                Token::String(text) => text,
+                token => bail!("expected string, found {token}"),
+            };
+            self.expect(Token::RightParen)?;
+            let full_text_query = FullTextQuery {
+                field,
+                text,
+                params: FullTextParams {
+                    tokenizer: None,
+                    mode: BooleanOperand::And.into(),
+                    zero_terms_query: MatchAllOrNone::MatchNone,
+                },
+                lenient: false,
+            };
+            return Ok(full_text_query.into());
+        }
+        let field = self.parse_identifier()?;
+
+        if self.consume_keyword("IS") {
+            let is_not = self.consume_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            let field_presence_query: QueryAst = FieldPresenceQuery { field }.into();
+            if is_not {
+                return Ok(field_presence_query);
+            }
+            return Ok(negate(field_presence_query));
+        }
+        let is_not = self.consume_keyword("NOT");
+        let query_ast = if self.consume_keyword("IN") {
+            self.expect(Token::LeftParen)?;
+            let mut terms = BTreeSet::new();
+            terms.insert(literal_to_term(self.parse_literal()?));
+            while self.consume(&Token::Comma) {
+                terms.insert(literal_to_term(self.parse_literal()?));
+            }
+            self.expect(Token::RightParen)?;
+            TermSetQuery {
+                terms_per_field: HashMap::from([(field, terms)]),
+            }
+            .into()
+        } else if self.consume_keyword("BETWEEN") {
+            let lower_bound = self.parse_literal()?;
+            self.expect_keyword("AND")?;
+            let upper_bound = self.parse_literal()?;
+            RangeQuery {
+                field,
+                lower_bound: Bound::Included(lower_bound),
+                upper_bound: Bound::Included(upper_bound),
+            }
+            .into()
+        } else if self.consume_keyword("LIKE") {
+            let pattern = match self.next_token("string")? {
+                Token::String(pattern) => pattern,
+                token => bail!("expected string, found {token}"),
+            };
+            like_query(field, &pattern)?
+        } else if is_not {
+            bail!("expected `IN`, `BETWEEN` or `LIKE` after `NOT`");
+        } else {
+            let operator = self.next_token("comparison operator")?;
+            let literal = self.parse_literal()?;
+            match operator {
+                Token::Eq => term_query(field, literal),
+                Token::NotEq => negate(term_query(field, literal)),
+                Token::Lt => range_query(field, Bound::Unbounded, Bound::Excluded(literal)),
+                Token::Lte => range_query(field, Bound::Unbounded, Bound::Included(literal)),
+                Token::Gt => range_query(field, Bound::Excluded(literal), Bound::Unbounded),
+                Token::Gte => range_query(field, Bound::Included(literal), Bound::Unbounded),
+                token => bail!("expected comparison operator, found {token}"),
+            }
+        };
+        if is_not {
+            return Ok(negate(query_ast));
+        }
+        Ok(query_ast)
+    }
+
+    fn parse_literal(&mut self) -> anyhow::Result<JsonLiteral> {
+        match self.next_token("literal")? {
+            Token::String(string) => Ok(JsonLiteral::String(string)),
+            Token::Number(number) => {
+                let number = serde_json::Number::from_str(&number)
+                    .with_context(|| format!("invalid number `{number}`"))?;
+                Ok(JsonLiteral::Number(number))
+            }
+            Token::Word(word) if word.eq_ignore_ascii_case("TRUE") => Ok(JsonLiteral::Bool(true)),
+            Token::Word(word) if word.eq_ignore_ascii_case("FALSE") => Ok(JsonLiteral::Bool(false)),
+            token => bail!("expected literal, found {token}"),
+        }
+    }
+}
+
+fn is_reserved_keyword(word: &str) -> bool {
+    RESERVED_KEYWORDS
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+fn negate(query_ast: QueryAst) -> QueryAst {
+    BoolQuery {
+        must_not: vec![query_ast],
+        ..Default::default()
+    }
+    .into()
+}
+
+fn literal_to_term(literal: JsonLiteral) -> String {
+    match literal {
+        JsonLiteral::String(string) => string,
+        JsonLiteral::Number(number) => number.to_string(),
+        JsonLiteral::Bool(boolean) => boolean.to_string(),
+    }
+}
+
+fn term_query(field: String, literal: JsonLiteral) -> QueryAst {
+    TermQuery {
+        field,
+        value: literal_to_term(literal),
+    }
+    .into()
+}
+
+fn range_query(
+    field: String,
+    lower_bound: Bound<JsonLiteral>,
+    upper_bound: Bound<JsonLiteral>,
+) -> QueryAst {
+    RangeQuery {
+        field,
+        lower_bound,
+        upper_bound,
+    }
+    .into()
+}
+
+/// Converts a `LIKE` pattern into a term query or, if it ends with `%`, a prefix query.
+fn like_query(field: String, pattern: &str) -> anyhow::Result<QueryAst> {
+    let (prefix, is_prefix_pattern) = match pattern.strip_suffix('%') {
+        Some(prefix) => (prefix, true),
+        None => (pattern, false),
+    };
+    if prefix.contains(['%', '_']) {
+        bail!("unsupported `LIKE` pattern `{pattern}`: only a trailing `%` wildcard is supported");
+    }
+    if !is_prefix_pattern {
+        return Ok(TermQuery {
+            field,
+            value: prefix.to_string(),
+        }
+        .into());
+    }
+    let mut value = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '\\') {
+            value.push('\\');
+        }
+        value.push(c);
+    }
+    value.push('*');
+    Ok(WildcardQuery {
+        field,
+        value,
+        lenient: false,
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens =
+            tokenize("SELECT \"my field\", count(*) FROM logs-* WHERE a>=-1.5 AND b <> 'it''s'")
+                .unwrap();
+        assert_eq!(
+            tokens,
+            [
+                Token::Word("SELECT".to_string()),
+                Token::QuotedIdentifier("my field".to_string()),
+                Token::Comma,
+                Token::Word("count".to_string()),
+                Token::LeftParen,
+                Token::Star,
+                Token::RightParen,
+                Token::Word("FROM".to_string()),
+                Token::Word("logs-*".to_string()),
+                Token::Word("WHERE".to_string()),
+                Token::Word("a".to_string()),
+                Token::Gte,
+                Token::Number("-1.5".to_string()),
+                Token::Word("AND".to_string()),
+                Token::Word("b".to_string()),
+                Token::NotEq,
+                Token::String("it's".to_string()),
+            ]
+        );
+        let error = tokenize("SELECT 'foo").unwrap_err();
+        assert_eq!(error.to_string(), "unterminated quoted string `'foo`");
+    }
+
+    #[test]
+    fn test_parse_sql_query() {
+        let sql_query = parse_sql_query(
+            "select service, count(*) as num_errors, avg(latency) from \"otel-logs-v0_7\" where \
+             severity_text = 'ERROR' group by service order by num_errors desc limit 10;",
+        )
+        .unwrap();
+        assert_eq!(
+            sql_query,
+            SqlQuery {
+                select_items: vec![
+                    SelectItem::Field {
+                        field: "service".to_string(),
+                        alias: None,
+                    },
+                    SelectItem::Aggregate {
+                        function: AggregateFunction::Count,
+                        field_opt: None,
+                        alias: Some("num_errors".to_string()),
+                    },
+                    SelectItem::Aggregate {
+                        function: AggregateFunction::Avg,
+                        field_opt: Some("latency".to_string()),
+                        alias: None,
+                    },
+                ],
+                index_id_patterns: vec!["otel-logs-v0_7".to_string()],
+                query_ast: term_query(
+                    "severity_text".to_string(),
+                    JsonLiteral::String("ERROR".to_string())
+                ),
+                group_by: vec!["service".to_string()],
+                order_by: vec![OrderByItem {
+                    key: "num_errors".to_string(),
+                    sort_order: SortOrder::Desc,
+                }],
+                limit: Some(10),
+            }
+        );
+        assert_eq!(sql_query.select_items[2].column_name(), "avg(latency)");
+
+        let sql_query = parse_sql_query("SELECT * FROM logs ORDER BY count(*)").unwrap();
+        assert_eq!(sql_query.select_items, [SelectItem::Wildcard]);
+        assert_eq!(sql_query.query_ast, QueryAst::MatchAll);
+        assert_eq!(sql_query.order_by[0].key, "count(*)");
+        assert_eq!(sql_query.order_by[0].sort_order, SortOrder::Asc);
+    }
+
+    #[test]
+    fn test_parse_sql_query_where_clause() {
+        let sql_query = parse_sql_query(
+            "SELECT * FROM logs WHERE NOT (status >= 500 OR status IS NULL) AND host NOT IN ('a', \
+             'b') AND ts BETWEEN 1 AND 2 AND path LIKE '/api%' AND MATCH(body, 'foo bar')",
+        )
+        .unwrap();
+        let expected_query_ast: QueryAst = BoolQuery {
+            must: vec![
+                negate(
+                    BoolQuery {
+                        should: vec![
+                            range_query(
+                                "status".to_string(),
+                                Bound::Included(JsonLiteral::Number(500u64.into())),
+                                Bound::Unbounded,
+                            ),
+                            negate(
+                                FieldPresenceQuery {
+                                    field: "status".to_string(),
+                                }
+                                .into(),
+                            ),
+                        ],
+                        ..Default::default()
+                    }
+                    .into(),
+                ),
+                negate(
+                    TermSetQuery {
+                        terms_per_field: HashMap::from([(
+                            "host".to_string(),
+                            BTreeSet::from(["a".to_string(), "b".to_string()]),
+                        )]),
+                    }
+                    .into(),
+                ),
+                range_query(
+                    "ts".to_string(),
+                    Bound::Included(JsonLiteral::Number(1u64.into())),
+                    Bound::Included(JsonLiteral::Number(2u64.into())),
+                ),
+                WildcardQuery {
+                    field: "path".to_string(),
+                    value: "/api*".to_string(),
+                    lenient: false,
+                }
+                .into(),
+                FullTextQuery {
+                    field: "body".to_string(),
+                    text: "foo bar".to_string(),
+                    params: FullTextParams {
+                        tokenizer: None,
+                        mode: BooleanOperand::And.into(),
+                        zero_terms_query: MatchAllOrNone::MatchNone,
+                    },
+                    lenient: false,
+                }
+                .into(),
+            ],
+            ..Default::default()
+        }
+        .into();
+        assert_eq!(sql_query.query_ast, expected_query_ast);
+    }
+
+    #[test]
+    fn test_parse_sql_query_errors() {
+        for (sql, expected_error) in [
+            ("SELECT", "expected identifier, found end of query"),
+            ("SELECT a", "expected FROM, found end of query"),
+            ("SELECT a FROM", "expected identifier, found end of query"),
+            (
+                "SELECT a FROM logs LIMIT",
+                "expected number, found end of query",
+            ),
+            (
+                "SELECT a FROM logs WHERE",
+                "expected identifier, found end of query",
+            ),
+            (
+                "SELECT a FROM logs WHERE b",
+                "expected comparison operator, found end of query",
+            ),
+            (
+                "SELECT a FROM logs WHERE b = c",
+                "expected literal, found `c`",
+            ),
+            (
+                "SELECT a FROM logs foo bar",
+                "unexpected `foo` at the end of the query",
+            ),
+            (
+                "SELECT a FROM select",
+                "expected identifier, found keyword `select`",
+            ),
+            (
+                "SELECT sum(*) FROM logs",
+                "`*` can only be used with the `count` function",
+            ),
+            (
+                "SELECT a FROM logs WHERE b LIKE '%foo'",
+                "unsupported `LIKE` pattern `%foo`: only a trailing `%` wildcard is supported",
+            ),
+        ] {
+            let error = parse_sql_query(sql).unwrap_err();
+            assert_eq!(error.to_string(), expected_error, "{sql}");
+        }
+    }
+}
@@ -0,0 +1,644 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Translation of a parsed SQL query into a search request, and of the search response into a
+//! table.
+//!
+//! Queries without `GROUP BY` clause nor aggregate functions are run as regular searches, the
+//! selected fields being extracted from the hits. Other queries are run as aggregation requests:
+//! each `GROUP BY` field maps to a nested `terms` aggregation and each aggregate function to a
+//! metric aggregation.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use anyhow::{bail, Context};
+use quickwit_proto::search::{CountHits, SearchRequest, SearchResponse, SortField, SortOrder};
+use quickwit_search::SearchError;
+use serde::Serialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::parser::{AggregateFunction, SelectItem, SqlQuery};
+
+/// Number of rows returned by queries without `GROUP BY` clause nor `LIMIT`.
+const DEFAULT_LIMIT: u64 = 100;
+
+/// Maximum number of groups per `GROUP BY` field.
+const MAX_GROUPS_PER_FIELD: u32 = 10_000;
+
+/// Tabular result of a SQL query.
+#[derive(Debug, Clone, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct SqlResponse {
+    pub columns: Vec<String>,
+    #[schema(value_type = Vec<Vec<Object>>)]
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+/// Describes how to build the [`SqlResponse`] from the [`SearchResponse`].
+#[derive(Debug)]
+pub(crate) struct SqlPlan {
+    select_items: Vec<SelectItem>,
+    group_by: Vec<String>,
+    /// Column index and sort order of the `ORDER BY` keys applied on aggregation results.
+    order_by: Vec<(usize, SortOrder)>,
+    limit_opt: Option<usize>,
+}
+
+impl SqlPlan {
+    fn is_aggregation(&self) -> bool {
+        !self.group_by.is_empty()
+            || self
+                .select_items
+                .iter()
+                .any(|select_item| matches!(select_item, SelectItem::Aggregate { .. }))
+    }
+}
+
+fn group_aggregation_name(group_ord: usize) -> String {
+    format!("group_{group_ord}")
+}
+
+fn metric_aggregation_name(column_ord: usize) -> String {
+    format!("column_{column_ord}")
+}
+
+/// Builds the search request running the SQL query.
+pub(crate) fn plan_sql_query(sql_query: SqlQuery) -> anyhow::Result<(SearchRequest, SqlPlan)> {
+    let SqlQuery {
+        select_items,
+        index_id_patterns,
+        query_ast,
+        group_by,
+        order_by,
+        limit,
+    } = sql_query;
+    let query_ast = serde_json::to_string(&query_ast).context("failed to serialize query AST")?;
+
+    let mut plan = SqlPlan {
+        select_items,
+        group_by,
+        order_by: Vec::new(),
+        limit_opt: None,
+    };
+    if !plan.is_aggregation() {
+        // The `ORDER BY` clause is run by the search. Column aliases are resolved to fields.
+        let sort_fields = order_by
+            .into_iter()
+            .map(|order_by_item| {
+                let field_name = plan
+                    .select_items
+                    .iter()
+                    .find_map(|select_item| match select_item {
+                        SelectItem::Field {
+                            field,
+                            alias: Some(alias),
+                        } if *alias == order_by_item.key => Some(field.clone()),
+                        _ => None,
+                    })
+                    .unwrap_or(order_by_item.key);
+                SortField {
+                    field_name,
+                    sort_order: order_by_item.sort_order as i32,
+                    sort_datetime_format: None,
+                }
+            })
+            .collect();
+        // Only the selected fields are fetched, unless the whole documents are selected.
+        let source_fields = if plan
+            .select_items
+            .iter()
+            .any(|select_item| matches!(select_item, SelectItem::Wildcard))
+        {
+            Vec::new()
+        } else {
+            plan.select_items
+                .iter()
+                .filter_map(|select_item| match select_item {
+                    SelectItem::Field { field, .. } => Some(field.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let search_request = SearchRequest {
+            index_id_patterns,
+            query_ast,
+            max_hits: limit.unwrap_or(DEFAULT_LIMIT),
+            sort_fields,
+            source_fields,
+            ..Default::default()
+        };
+        return Ok((search_request, plan));
+    }
+
+    let column_names: Vec<String> = plan
+        .select_items
+        .iter()
+        .map(|select_item| select_item.column_name())
+        .collect();
+    let mut metric_aggregations = JsonMap::new();
+    let mut count_all = false;
+
+    for (column_ord, select_item) in plan.select_items.iter().enumerate() {
+        match select_item {
+            SelectItem::Wildcard => {
+                bail!("`*` cannot be selected along with aggregate functions or `GROUP BY`")
+            }
+            SelectItem::Field { field, .. } => {
+                if !plan.group_by.contains(field) {
+                    bail!(
+                        "field `{field}` must appear in the `GROUP BY` clause or be used in an \
+                         aggregate function"
+                    );
+                }
+            }
+            SelectItem::Aggregate {
+                function,
+                field_opt: Some(field),
+                ..
+            } => {
+                let aggregation_type = match function {
+                    AggregateFunction::Count => "value_count",
+                    AggregateFunction::Sum => "sum",
+                    AggregateFunction::Avg => "avg",
+                    AggregateFunction::Min => "min",
+                    AggregateFunction::Max => "max",
+                };
+                metric_aggregations.insert(
+                    metric_aggregation_name(column_ord),
+                    json!({ aggregation_type: { "field": field } }),
+                );
+            }
+            SelectItem::Aggregate {
+                field_opt: None, ..
+            } => {
+                // `COUNT(*)` is the document count of the group, or the number of hits.
+                count_all |= plan.group_by.is_empty();
+            }
+        }
+    }
+    // The innermost `terms` aggregation holds the metric aggregations.
+    let mut aggregations = metric_aggregations;
+    for (group_ord, field) in plan.group_by.iter().enumerate().rev() {
+        let mut group_aggregation = json!({
+            "terms": {
+                "field": field,
+                "size": MAX_GROUPS_PER_FIELD,
+            }
+        });
+        if !aggregations.is_empty() {
+            group_aggregation["aggs"] = JsonValue::Object(aggregations);
+        }
+        aggregations = JsonMap::new();
+        aggregations.insert(group_aggregation_name(group_ord), group_aggregation);
+    }
+    let aggregation_request = if aggregations.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&aggregations).context("failed to serialize aggregations")?)
+    };
+    // The `ORDER BY` clause is applied on the rows built from the aggregation results.
+    plan.order_by = order_by
+        .into_iter()
+        .map(|order_by_item| {
+            let column_ord = column_names
+                .iter()
+                .position(|column_name| *column_name == order_by_item.key)
+                .with_context(|| {
+                    format!(
+                        "`ORDER BY` key `{}` must be a selected column",
+                        order_by_item.key
+                    )
+                })?;
+            Ok((column_ord, order_by_item.sort_order))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    plan.limit_opt = limit.map(|limit| limit as usize);
+
+    let count_hits = if count_all {
+        CountHits::CountAll
+    } else {
+        CountHits::Underestimate
+    };
+    let search_request = SearchRequest {
+        index_id_patterns,
+        query_ast,
+        max_hits: 0,
+        aggregation_request,
+        count_hits: count_hits as i32,
+        ..Default::default()
+    };
+    Ok((search_request, plan))
+}
+
+/// Builds the table answering the SQL query from the search response.
+///
+/// Returns an error rather than an incomplete table if a `GROUP BY` field has more than
+/// [`MAX_GROUPS_PER_FIELD`] groups.
+pub(crate) fn build_sql_response(
+    plan: &SqlPlan,
+    search_response: SearchResponse,
+) -> Result<SqlResponse, SearchError> {
+    if !plan.is_aggregation() {
+        return build_sql_response_from_hits(plan, search_response)
+            .map_err(|error| SearchError::Internal(error.to_string()));
+    }
+    let columns = plan
+        .select_items
+        .iter()
+        .map(|select_item| select_item.column_name())
+        .collect();
+    let aggregation_results: JsonValue = match &search_response.aggregation {
+        Some(aggregation_json) => serde_json::from_str(aggregation_json).map_err(|error| {
+            SearchError::Internal(format!(
+                "failed to deserialize aggregation results: {error}"
+            ))
+        })?,
+        None => JsonValue::Object(JsonMap::new()),
+    };
+    let mut rows = Vec::new();
+    let mut group_keys = Vec::with_capacity(plan.group_by.len());
+    collect_rows(
+        plan,
+        &aggregation_results,
+        search_response.num_hits,
+        &mut group_keys,
+        &mut rows,
+    )?;
+    for (column_ord, sort_order) in plan.order_by.iter().rev() {
+        rows.sort_by(|left_row, right_row| {
+            let ordering = compare_json_values(&left_row[*column_ord], &right_row[*column_ord]);
+            match sort_order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+    }
+    if let Some(limit) = plan.limit_opt {
+        rows.truncate(limit);
+    }
+    Ok(SqlResponse { columns, rows })
+}
+
+/// Flattens the nested `terms` aggregation results into rows, one per innermost bucket.
+fn collect_rows(
+    plan: &SqlPlan,
+    bucket: &JsonValue,
+    doc_count: u64,
+    group_keys: &mut Vec<JsonValue>,
+    rows: &mut Vec<Vec<JsonValue>>,
+) -> Result<(), SearchError> {
+    let group_ord = group_keys.len();
+    if group_ord < plan.group_by.len() {
+        let group_aggregation = &bucket[group_aggregation_name(group_ord)];
+        if group_aggregation["sum_other_doc_count"]
+            .as_u64()
+            .unwrap_or(0)
+            > 0
+        {
+            return Err(SearchError::InvalidQuery(format!(
+                "`GROUP BY` field `{}` has more than {MAX_GROUPS_PER_FIELD} groups",
+                plan.group_by[group_ord]
+            )));
+        }
+        let Some(sub_buckets) = group_aggregation["buckets"].as_array() else {
+            return Ok(());
+        };
+        for sub_bucket in sub_buckets {
+            let key = sub_bucket
+                .get("key_as_string")
+                .or_else(|| sub_bucket.get("key"))
+                .cloned()
+                .unwrap_or(JsonValue::Null);
+            let sub_doc_count = sub_bucket["doc_count"].as_u64().unwrap_or(0);
+            group_keys.push(key);
+            collect_rows(plan, sub_bucket, sub_doc_count, group_keys, rows)?;
+            group_keys.pop();
+        }
+        return Ok(());
+    }
+    let row = plan
+        .select_items
+        .iter()
+        .enumerate()
+        .map(|(column_ord, select_item)| match select_item {
+            SelectItem::Field { field, .. } => plan
+                .group_by
+                .iter()
+                .position(|group_by_field| group_by_field == field)
+                .map(|group_ord| group_keys[group_ord].clone())
+                .unwrap_or(JsonValue::Null),
+            SelectItem::Aggregate {
+                field_opt: None, ..
+            } => JsonValue::from(doc_count),
+            SelectItem::Aggregate { .. } => {
+                bucket[metric_aggregation_name(column_ord)]["value"].clone()
+            }
+            SelectItem::Wildcard => JsonValue::Null,
+        })
+        .collect();
+    rows.push(row);
+    Ok(())
+}
+
+fn build_sql_response_from_hits(
+    plan: &SqlPlan,
+    search_response: SearchResponse,
+) -> anyhow::Result<SqlResponse> {
+    let docs: Vec<JsonValue> = search_response
+        .hits
+        .iter()
+        .map(|hit| serde_json::from_str(&hit.json).context("failed to deserialize hit"))
+        .collect::<anyhow::Result<_>>()?;
+
+    // Columns are made of the selected fields, `*` expanding to the fields of the documents.
+    let mut columns: Vec<(String, Option<String>)> = Vec::new();
+    for select_item in &plan.select_items {
+        match select_item {
+            SelectItem::Wildcard => {
+                let mut seen_fields = HashSet::new();
+                for doc in &docs {
+                    let Some(doc_object) = doc.as_object() else {
+                        continue;
+                    };
+                    for field in doc_object.keys() {
+                        if seen_fields.insert(field.clone()) {
+                            columns.push((field.clone(), Some(field.clone())));
+                        }
+                    }
+                }
+            }
+            SelectItem::Field { field, .. } => {
+                columns.push((select_item.column_name(), Some(field.clone())));
+            }
+            SelectItem::Aggregate { .. } => columns.push((select_item.column_name(), None)),
+        }
+    }
+    let rows = docs
+        .iter()
+        .map(|doc| {
+            columns
+                .iter()
+                .map(|(_, field_opt)| {
+                    field_opt
+                        .as_deref()
+                        .and_then(|field| get_field_value(doc, field))
+                        .cloned()
+                        .unwrap_or(JsonValue::Null)
+                })
+                .collect()
+        })
+        .collect();
+    let columns = columns
+        .into_iter()
+        .map(|(column_name, _)| column_name)
+        .collect();
+    Ok(SqlResponse { columns, rows })
+}
+
+/// Returns the value of a field of the document, following the path of nested objects.
+fn get_field_value<'a>(doc: &'a JsonValue, field: &str) -> Option<&'a JsonValue> {
+    let doc_object = doc.as_object()?;
+    if let Some(value) = doc_object.get(field) {
+        return Some(value);
+    }
+    let (head, tail) = field.split_once('.')?;
+    get_field_value(doc_object.get(head)?, tail)
+}
+
+/// Orders numbers before strings, and null values last.
+fn compare_json_values(left: &JsonValue, right: &JsonValue) -> Ordering {
+    match (left, right) {
+        (JsonValue::Number(left), JsonValue::Number(right)) => {
+            let left = left.as_f64().unwrap_or(f64::NAN);
+            let right = right.as_f64().unwrap_or(f64::NAN);
+            left.total_cmp(&right)
+        }
+        (JsonValue::String(left), JsonValue::String(right)) => left.cmp(right),
+        (JsonValue::Null, JsonValue::Null) => Ordering::Equal,
+        (JsonValue::Null, _) => Ordering::Greater,
+        (_, JsonValue::Null) => Ordering::Less,
+        (JsonValue::Number(_), _) => Ordering::Less,
+        (_, JsonValue::Number(_)) => Ordering::Greater,
+        _ => left.to_string().cmp(&right.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::search::Hit;
+
+    use super::*;
+    use crate::sql_api::parser::parse_sql_query;
+
+    fn plan_sql(sql: &str) -> (SearchRequest, SqlPlan) {
+        plan_sql_query(parse_sql_query(sql).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_plan_sql_query_hits() {
+        let (search_request, plan) =
+            plan_sql("SELECT ts AS time, body FROM logs WHERE level = 'ERROR' ORDER BY time DESC");
+        assert_eq!(search_request.index_id_patterns, ["logs"]);
+        assert_eq!(search_request.max_hits, DEFAULT_LIMIT);
+        assert!(search_request.aggregation_request.is_none());
+        assert_eq!(search_request.source_fields, ["ts", "body"]);
+        assert_eq!(
+            search_request.sort_fields,
+            [SortField {
+                field_name: "ts".to_string(),
+                sort_order: SortOrder::Desc as i32,
+                sort_datetime_format: None,
+            }]
+        );
+        let query_ast: JsonValue = serde_json::from_str(&search_request.query_ast).unwrap();
+        assert_eq!(
+            query_ast,
+            json!({"type": "term", "field": "level", "value": "ERROR"})
+        );
+        let search_response = SearchResponse {
+            hits: vec![
+                Hit {
+                    json: r#"{"attributes": {"a": 1}, "body": "foo", "ts": 2}"#.to_string(),
+                    ..Default::default()
+                },
+                Hit {
+                    json: r#"{"ts": 1}"#.to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let sql_response = build_sql_response(&plan, search_response.clone()).unwrap();
+        assert_eq!(sql_response.columns, ["time", "body"]);
+        assert_eq!(
+            sql_response.rows,
+            [
+                vec![json!(2), json!("foo")],
+                vec![json!(1), JsonValue::Null]
+            ]
+        );
+
+        let (search_request, plan) = plan_sql("SELECT attributes.a, * FROM logs LIMIT 2");
+        assert!(search_request.source_fields.is_empty());
+        let sql_response = build_sql_response(&plan, search_response).unwrap();
+        assert_eq!(
+            sql_response.columns,
+            ["attributes.a", "attributes", "body", "ts"]
+        );
+        assert_eq!(
+            sql_response.rows,
+            [
+                vec![json!(1), json!({"a": 1}), json!("foo"), json!(2)],
+                vec![JsonValue::Null, JsonValue::Null, JsonValue::Null, json!(1)]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_sql_query_aggregations() {
+        let (search_request, plan) = plan_sql(
+            "SELECT service, level, count(*) AS num_logs, max(latency) FROM logs GROUP BY \
+             service, level ORDER BY num_logs DESC, service LIMIT 3",
+        );
+        assert_eq!(search_request.max_hits, 0);
+        assert_eq!(search_request.count_hits, CountHits::Underestimate as i32);
+        let aggregation_request: JsonValue =
+            serde_json::from_str(search_request.aggregation_request.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            aggregation_request,
+            json!({
+                "group_0": {
+                    "terms": {"field": "service", "size": MAX_GROUPS_PER_FIELD},
+                    "aggs": {
+                        "group_1": {
+                            "terms": {"field": "level", "size": MAX_GROUPS_PER_FIELD},
+                            "aggs": {
+                                "column_3": {"max": {"field": "latency"}}
+                            }
+                        }
+                    }
+                }
+            })
+        );
+        let aggregation_results = json!({
+            "group_0": {
+                "buckets": [
+                    {
+                        "key": "api",
+                        "doc_count": 5,
+                        "group_1": {
+                            "buckets": [
+                                {"key": "INFO", "doc_count": 3, "column_3": {"value": 10.0}},
+                                {"key": "ERROR", "doc_count": 2, "column_3": {"value": 20.0}},
+                            ]
+                        }
+                    },
+                    {
+                        "key": "db",
+                        "doc_count": 3,
+                        "group_1": {
+                            "buckets": [
+                                {"key": "INFO", "doc_count": 3, "column_3": {"value": null}},
+                            ]
+                        }
+                    }
+                ]
+            }
+        });
+        let search_response = SearchResponse {
+            num_hits: 8,
+            aggregation: Some(aggregation_results.to_string()),
+            ..Default::default()
+        };
+        let sql_response = build_sql_response(&plan, search_response).unwrap();
+        assert_eq!(
+            sql_response.columns,
+            ["service", "level", "num_logs", "max(latency)"]
+        );
+        assert_eq!(
+            sql_response.rows,
+            [
+                vec![json!("api"), json!("INFO"), json!(3), json!(10.0)],
+                vec![json!("db"), json!("INFO"), json!(3), JsonValue::Null],
+                vec![json!("api"), json!("ERROR"), json!(2), json!(20.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_sql_response_too_many_groups() {
+        let (_, plan) = plan_sql("SELECT service, count(*) FROM logs GROUP BY service");
+        let aggregation_results = json!({
+            "group_0": {
+                "buckets": [{"key": "api", "doc_count": 5}],
+                "sum_other_doc_count": 3,
+            }
+        });
+        let search_response = SearchResponse {
+            num_hits: 8,
+            aggregation: Some(aggregation_results.to_string()),
+            ..Default::default()
+        };
+        let error = build_sql_response(&plan, search_response).unwrap_err();
+        assert!(matches!(error, SearchError::InvalidQuery(_)));
+        assert_eq!(
+            error.to_string(),
+            "`GROUP BY` field `service` has more than 10000 groups"
+        );
+    }
+
+    #[test]
+    fn test_plan_sql_query_global_aggregations() {
+        let (search_request, plan) = plan_sql("SELECT count(*), avg(latency) FROM logs");
+        assert_eq!(search_request.count_hits, CountHits::CountAll as i32);
+        let search_response = SearchResponse {
+            num_hits: 8,
+            aggregation: Some(json!({"column_1": {"value": 1.5}}).to_string()),
+            ..Default::default()
+        };
+        let sql_response = build_sql_response(&plan, search_response).unwrap();
+        assert_eq!(sql_response.columns, ["count(*)", "avg(latency)"]);
+        assert_eq!(sql_response.rows, [vec![json!(8), json!(1.5)]]);
+
+        let (search_request, _) = plan_sql("SELECT count(*) FROM logs");
+        assert!(search_request.aggregation_request.is_none());
+    }
+
+    #[test]
+    fn test_plan_sql_query_errors() {
+        for (sql, expected_error) in [
+            (
+                "SELECT *, count(*) FROM logs",
+                "`*` cannot be selected along with aggregate functions or `GROUP BY`",
+            ),
+            (
+                "SELECT service, count(*) FROM logs",
+                "field `service` must appear in the `GROUP BY` clause or be used in an aggregate \
+                 function",
+            ),
+            (
+                "SELECT count(*) FROM logs GROUP BY service ORDER BY service",
+                "`ORDER BY` key `service` must be a selected column",
+            ),
+        ] {
+            let error = plan_sql_query(parse_sql_query(sql).unwrap()).unwrap_err();
+            assert_eq!(error.to_string(), expected_error, "{sql}");
+        }
+    }
+}
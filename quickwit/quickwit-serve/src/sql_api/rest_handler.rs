@@ -0,0 +1,317 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::http::HeaderValue;
+use hyper::StatusCode;
+use quickwit_config::AuthPrivilege;
+use quickwit_search::{SearchError, SearchService};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tracing::info;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use super::parser::parse_sql_query;
+use super::planner::{build_sql_response, plan_sql_query, SqlResponse};
+use crate::auth::Principal;
+use crate::format::BodyFormat;
+use crate::rest_api_response::{RestApiError, RestApiResponse};
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(sql_handler),
+    components(schemas(SqlRequest, SqlResponse, SqlResponseFormat))
+)]
+pub(crate) struct SqlApi;
+
+/// SQL query request.
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SqlRequest {
+    /// The SQL query, e.g. `SELECT service, count(*) FROM logs GROUP BY service`.
+    pub query: String,
+}
+
+/// Output format of the SQL query results.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Eq, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SqlResponseFormat {
+    Json,
+    #[default]
+    PrettyJson,
+    Csv,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+struct SqlQueryString {
+    /// The output format of the results.
+    #[serde(default)]
+    format: SqlResponseFormat,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/_sql",
+    request_body = SqlRequest,
+    responses(
+        (status = 200, description = "Successfully executed the SQL query.", body = SqlResponse)
+    ),
+    params(SqlQueryString),
+)]
+/// SQL Query
+///
+/// Runs a `SELECT ... FROM ... WHERE ... GROUP BY ... ORDER BY ... LIMIT ...` query and returns
+/// the results as a table.
+pub(crate) fn sql_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_sql")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .and(warp::ext::optional::<Principal>())
+        .then(sql)
+}
+
+async fn sql(
+    sql_request: SqlRequest,
+    sql_query_string: SqlQueryString,
+    search_service: Arc<dyn SearchService>,
+    principal_opt: Option<Principal>,
+) -> Response {
+    info!(query = %sql_request.query, "sql");
+    let result = sql_endpoint(sql_request, &*search_service, principal_opt.as_ref()).await;
+    let body_format = match sql_query_string.format {
+        SqlResponseFormat::Json => BodyFormat::Json,
+        SqlResponseFormat::PrettyJson => BodyFormat::PrettyJson,
+        SqlResponseFormat::Csv => match result {
+            Ok(sql_response) => return make_csv_response(&sql_response),
+            // Errors are always returned as JSON.
+            Err(_) => BodyFormat::Json,
+        },
+    };
+    let status_code = match &result {
+        Ok(_) => StatusCode::OK,
+        Err(error) => error.status_code,
+    };
+    RestApiResponse::new(&result, status_code, body_format).into_response()
+}
+
+async fn sql_endpoint(
+    sql_request: SqlRequest,
+    search_service: &dyn SearchService,
+    principal_opt: Option<&Principal>,
+) -> Result<SqlResponse, RestApiError> {
+    let sql_query = parse_sql_query(&sql_request.query)
+        .map_err(|error| SearchError::InvalidQuery(format!("invalid SQL query: {error}")))?;
+    // The indexes are not part of the route, so they cannot be authorized by the auth layer.
+    if let Some(principal) = principal_opt {
+        principal
+            .authorize(AuthPrivilege::Read, &sql_query.index_id_patterns)
+            .map_err(|auth_error| RestApiError {
+                status_code: auth_error.status_code(),
+                message: auth_error.to_string(),
+            })?;
+    }
+    let (search_request, sql_plan) = plan_sql_query(sql_query)
+        .map_err(|error| SearchError::InvalidQuery(format!("invalid SQL query: {error}")))?;
+    let search_response = search_service.root_search(search_request).await?;
+
+    if let Some(search_error) = SearchError::from_split_errors(&search_response.failed_splits) {
+        return Err(search_error.into());
+    }
+    let sql_response = build_sql_response(&sql_plan, search_response)?;
+    Ok(sql_response)
+}
+
+fn make_csv_response(sql_response: &SqlResponse) -> Response {
+    let mut csv_writer = csv::Writer::from_writer(Vec::new());
+    let write_result = csv_writer
+        .write_record(&sql_response.columns)
+        .and_then(|_| {
+            for row in &sql_response.rows {
+                csv_writer.write_record(row.iter().map(csv_field))?;
+            }
+            Ok(())
+        })
+        .map_err(|error| error.to_string())
+        .and_then(|_| csv_writer.into_inner().map_err(|error| error.to_string()));
+
+    match write_result {
+        Ok(body) => {
+            let mut response = Response::new(body.into());
+            response
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("text/csv"));
+            response
+        }
+        Err(error_message) => {
+            let rest_api_error = RestApiError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+                message: format!("failed to write CSV response: {error_message}"),
+            };
+            RestApiResponse::new::<(), _>(
+                &Err(rest_api_error),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BodyFormat::Json,
+            )
+            .into_response()
+        }
+    }
+}
+
+/// Strings are written as is, null values as empty fields, and other values as JSON.
+fn csv_field(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(text) => text.clone(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::RoleConfig;
+    use quickwit_proto::search::{CountHits, SearchResponse};
+    use quickwit_search::MockSearchService;
+    use serde_json::json;
+
+    use super::*;
+
+    fn mock_search_service() -> MockSearchService {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                search_request.index_id_patterns == ["logs"]
+                    && search_request.count_hits == CountHits::Underestimate as i32
+                    && search_request.max_hits == 0
+            })
+            .returning(|_| {
+                let aggregation = json!({
+                    "group_0": {
+                        "buckets": [
+                            {"key": "api", "doc_count": 5},
+                            {"key": "db, primary", "doc_count": 3},
+                        ]
+                    }
+                });
+                Ok(SearchResponse {
+                    num_hits: 8,
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        mock_search_service
+    }
+
+    #[tokio::test]
+    async fn test_sql_handler() {
+        let handler = sql_handler(Arc::new(mock_search_service()));
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_sql?format=json")
+            .json(&json!({
+                "query": "SELECT service, count(*) AS num_logs FROM logs WHERE level = 'ERROR' \
+                          GROUP BY service"
+            }))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({
+                "columns": ["service", "num_logs"],
+                "rows": [["api", 5], ["db, primary", 3]],
+            })
+        );
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_sql?format=csv")
+            .json(&json!({"query": "SELECT service, count(*) FROM logs GROUP BY service"}))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()[CONTENT_TYPE], "text/csv");
+        assert_eq!(resp.body(), "service,count(*)\napi,5\n\"db, primary\",3\n");
+    }
+
+    #[tokio::test]
+    async fn test_sql_handler_errors() {
+        let handler = sql_handler(Arc::new(MockSearchService::new()));
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&json!({"query": "SELECT service, count(*) FROM logs"}))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json["message"],
+            "invalid SQL query: field `service` must appear in the `GROUP BY` clause or be used \
+             in an aggregate function"
+        );
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&json!({"query": "SELECT * FROM"}))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_sql_handler_authorizes_indexes() {
+        let principal = Principal::new(
+            "analyst".to_string(),
+            vec![RoleConfig {
+                name: "logs-reader".to_string(),
+                index_patterns: vec!["logs".to_string()],
+                privileges: vec![AuthPrivilege::Read],
+            }],
+        );
+        let handler = sql_handler(Arc::new(MockSearchService::new()));
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .extension(principal)
+            .json(&json!({"query": "SELECT count(*) FROM secrets"}))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 403);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json["message"],
+            "principal `analyst` does not have the `read` privilege on `secrets`"
+        );
+    }
+}
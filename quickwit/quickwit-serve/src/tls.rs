@@ -0,0 +1,520 @@
+// Copyright (C) 2024 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context};
+use futures::{Stream, StreamExt};
+use quickwit_common::spawn_named_task;
+use quickwit_config::TlsConfig;
+use quickwit_proto::tonic::transport::{Certificate, ClientTlsConfig, Identity};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{self, RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, error, info, warn};
+
+/// Interval at which the certificate, key, and CA files are checked for modifications.
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const MAX_CONCURRENT_TLS_HANDSHAKES: usize = 256;
+
+pub(crate) const GRPC_ALPN_PROTOCOLS: &[&[u8]] = &[b"h2"];
+
+pub(crate) const REST_ALPN_PROTOCOLS: &[&[u8]] = &[b"h2", b"http/1.1"];
+
+/// Accepts TLS connections with a server configuration that is reloaded whenever the certificate,
+/// key, or CA files are modified, so that rotated certificates are served without restarting the
+/// node.
+#[derive(Clone)]
+pub(crate) struct ReloadableTlsAcceptor {
+    inner: Arc<InnerReloadableTlsAcceptor>,
+}
+
+struct InnerReloadableTlsAcceptor {
+    tls_config: TlsConfig,
+    alpn_protocols: Vec<Vec<u8>>,
+    state: RwLock<TlsAcceptorState>,
+}
+
+struct TlsAcceptorState {
+    tls_acceptor: TlsAcceptor,
+    files_modified_at: Vec<Option<SystemTime>>,
+}
+
+impl ReloadableTlsAcceptor {
+    /// Loads the TLS configuration and spawns a task that reloads it when the files change.
+    pub fn load(tls_config: &TlsConfig, alpn_protocols: &[&[u8]]) -> anyhow::Result<Self> {
+        let alpn_protocols: Vec<Vec<u8>> = alpn_protocols
+            .iter()
+            .map(|alpn_protocol| alpn_protocol.to_vec())
+            .collect();
+        // We read the modification times before loading the files, so that a file modified
+        // while being loaded gets reloaded on the next check.
+        let files_modified_at = files_modified_at(tls_config);
+        let server_config = load_server_config(tls_config, &alpn_protocols)?;
+        let state = TlsAcceptorState {
+            tls_acceptor: TlsAcceptor::from(Arc::new(server_config)),
+            files_modified_at,
+        };
+        let inner = InnerReloadableTlsAcceptor {
+            tls_config: tls_config.clone(),
+            alpn_protocols,
+            state: RwLock::new(state),
+        };
+        let reloadable_tls_acceptor = Self {
+            inner: Arc::new(inner),
+        };
+        spawn_named_task(
+            reload_loop(Arc::downgrade(&reloadable_tls_acceptor.inner)),
+            "tls_reload_loop",
+        );
+        Ok(reloadable_tls_acceptor)
+    }
+
+    fn tls_acceptor(&self) -> TlsAcceptor {
+        self.inner
+            .state
+            .read()
+            .expect("lock should not be poisoned")
+            .tls_acceptor
+            .clone()
+    }
+
+    /// Reloads the TLS configuration if one of its files was modified since the last load.
+    /// Returns `true` if the configuration was reloaded.
+    fn reload_if_modified(&self) -> anyhow::Result<bool> {
+        let tls_config = &self.inner.tls_config;
+        let files_modified_at = files_modified_at(tls_config);
+
+        if self
+            .inner
+            .state
+            .read()
+            .expect("lock should not be poisoned")
+            .files_modified_at
+            == files_modified_at
+        {
+            return Ok(false);
+        }
+        // On error, we keep serving the previous configuration and retry on the next check,
+        // which happens when the files are being rotated one at a time for instance.
+        let server_config = load_server_config(tls_config, &self.inner.alpn_protocols)?;
+        let mut state_guard = self
+            .inner
+            .state
+            .write()
+            .expect("lock should not be poisoned");
+        state_guard.tls_acceptor = TlsAcceptor::from(Arc::new(server_config));
+        state_guard.files_modified_at = files_modified_at;
+        Ok(true)
+    }
+}
+
+async fn reload_loop(inner_weak: Weak<InnerReloadableTlsAcceptor>) {
+    let mut interval = tokio::time::interval(TLS_RELOAD_INTERVAL);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let Some(inner) = inner_weak.upgrade() else {
+            return;
+        };
+        let reloadable_tls_acceptor = ReloadableTlsAcceptor { inner };
+
+        match reloadable_tls_acceptor.reload_if_modified() {
+            Ok(true) => {
+                info!(
+                    cert_path=%reloadable_tls_acceptor.inner.tls_config.cert_path.display(),
+                    "reloaded TLS configuration"
+                );
+            }
+            Ok(false) => {}
+            Err(error) => {
+                error!(%error, "failed to reload TLS configuration");
+            }
+        }
+    }
+}
+
+fn files_modified_at(tls_config: &TlsConfig) -> Vec<Option<SystemTime>> {
+    [
+        Some(&tls_config.cert_path),
+        Some(&tls_config.key_path),
+        tls_config.ca_path.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|path| {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    })
+    .collect()
+}
+
+fn load_server_config(
+    tls_config: &TlsConfig,
+    alpn_protocols: &[Vec<u8>],
+) -> anyhow::Result<ServerConfig> {
+    let certs = load_certs(&tls_config.cert_path)?;
+    let private_key = load_private_key(&tls_config.key_path)?;
+
+    let config_builder = ServerConfig::builder().with_safe_defaults();
+    let config_builder = if tls_config.validate_client {
+        let ca_path = tls_config
+            .ca_path
+            .as_ref()
+            .context("CA certificates are required to validate client certificates")?;
+        let mut root_cert_store = RootCertStore::empty();
+
+        for ca_cert in load_certs(ca_path)? {
+            root_cert_store.add(&ca_cert).with_context(|| {
+                format!("failed to add CA certificate from `{}`", ca_path.display())
+            })?;
+        }
+        let client_cert_verifier = AllowAnyAuthenticatedClient::new(root_cert_store).boxed();
+        config_builder.with_client_cert_verifier(client_cert_verifier)
+    } else {
+        config_builder.with_no_client_auth()
+    };
+    let mut server_config = config_builder
+        .with_single_cert(certs, private_key)
+        .with_context(|| {
+            format!(
+                "failed to load certificate `{}` with private key `{}`",
+                tls_config.cert_path.display(),
+                tls_config.key_path.display()
+            )
+        })?;
+    server_config.alpn_protocols = alpn_protocols.to_vec();
+    Ok(server_config)
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<rustls::Certificate>> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("failed to open certificate file `{}`", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .with_context(|| format!("failed to parse certificate file `{}`", path.display()))?;
+    if certs.is_empty() {
+        bail!("no certificate found in `{}`", path.display());
+    }
+    Ok(certs.into_iter().map(rustls::Certificate).collect())
+}
+
+fn load_private_key(path: &Path) -> anyhow::Result<rustls::PrivateKey> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("failed to open private key file `{}`", path.display()))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("failed to parse private key file `{}`", path.display()))?;
+
+    for item in items {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(rustls::PrivateKey(key)),
+            _ => {}
+        }
+    }
+    bail!("no private key found in `{}`", path.display());
+}
+
+/// Turns a TCP listener into a stream of TLS connections.
+///
+/// TLS handshakes are performed concurrently. Failed handshakes are logged and skipped so that a
+/// misbehaving client does not bring the server down.
+pub(crate) fn tls_incoming(
+    tcp_listener: TcpListener,
+    reloadable_tls_acceptor: ReloadableTlsAcceptor,
+) -> impl Stream<Item = io::Result<TlsStream<TcpStream>>> + Send + 'static {
+    futures::stream::unfold(tcp_listener, |tcp_listener| async move {
+        let accept_res = tcp_listener.accept().await;
+        Some((accept_res, tcp_listener))
+    })
+    .filter_map(|accept_res| async move {
+        match accept_res {
+            Ok((tcp_stream, _peer_addr)) => Some(tcp_stream),
+            Err(error) => {
+                // Errors such as running out of file descriptors are usually transient: we back
+                // off for a bit rather than spinning on them.
+                warn!(%error, "failed to accept TCP connection");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                None
+            }
+        }
+    })
+    .map(move |tcp_stream| {
+        let tls_acceptor = reloadable_tls_acceptor.tls_acceptor();
+        async move {
+            let peer_addr_opt = tcp_stream.peer_addr().ok();
+            // Matches the default of the plaintext servers.
+            if let Err(error) = tcp_stream.set_nodelay(true) {
+                debug!(%error, "failed to set TCP_NODELAY");
+            }
+            match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls_acceptor.accept(tcp_stream)).await
+            {
+                Ok(Ok(tls_stream)) => Some(tls_stream),
+                Ok(Err(error)) => {
+                    debug!(peer_addr=?peer_addr_opt, %error, "TLS handshake failed");
+                    None
+                }
+                Err(_) => {
+                    debug!(peer_addr=?peer_addr_opt, "TLS handshake timed out");
+                    None
+                }
+            }
+        }
+    })
+    .buffer_unordered(MAX_CONCURRENT_TLS_HANDSHAKES)
+    .filter_map(|tls_stream_opt| async move { tls_stream_opt.map(Ok) })
+}
+
+/// Builds the TLS configuration used by the gRPC clients to connect to the other nodes of the
+/// cluster. The node presents its own certificate to its peers, which is required when they
+/// validate client certificates.
+pub(crate) fn load_grpc_client_tls_config(
+    tls_config: &TlsConfig,
+) -> anyhow::Result<ClientTlsConfig> {
+    let ca_path = tls_config
+        .ca_path
+        .as_ref()
+        .context("CA certificates are required to verify the certificates of the other nodes")?;
+    let ca_pem = fs::read(ca_path)
+        .with_context(|| format!("failed to read CA certificate file `{}`", ca_path.display()))?;
+    let cert_pem = fs::read(&tls_config.cert_path).with_context(|| {
+        format!(
+            "failed to read certificate file `{}`",
+            tls_config.cert_path.display()
+        )
+    })?;
+    let key_pem = fs::read(&tls_config.key_path).with_context(|| {
+        format!(
+            "failed to read private key file `{}`",
+            tls_config.key_path.display()
+        )
+    })?;
+    let mut client_tls_config = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(ca_pem))
+        .identity(Identity::from_pem(cert_pem, key_pem));
+
+    if let Some(expected_name) = &tls_config.expected_name {
+        client_tls_config = client_tls_config.domain_name(expected_name.clone());
+    }
+    Ok(client_tls_config)
+}
+
+/// Enables TLS for the gRPC channels connecting to the other nodes of the cluster.
+pub(crate) fn set_grpc_client_tls_config(tls_config: TlsConfig) {
+    // Certificate files are read each time a channel is created to pick up rotated certificates.
+    let provider =
+        Box::new(move |_socket_addr: SocketAddr| load_grpc_client_tls_config(&tls_config));
+    if !quickwit_common::tower::set_client_tls_config_provider(provider) {
+        warn!("gRPC client TLS configuration already set, ignoring new configuration");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::{ClientConfig, ServerName};
+    use tokio_rustls::TlsConnector;
+
+    use super::*;
+
+    fn get_tls_filepath(filename: &str) -> PathBuf {
+        PathBuf::from(format!(
+            "{}/resources/tests/tls/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            filename
+        ))
+    }
+
+    fn tls_config_for_test(validate_client: bool) -> TlsConfig {
+        TlsConfig {
+            cert_path: get_tls_filepath("node.crt"),
+            key_path: get_tls_filepath("node.key"),
+            ca_path: Some(get_tls_filepath("ca.crt")),
+            validate_client,
+            expected_name: None,
+        }
+    }
+
+    fn client_config_for_test(with_client_cert: bool) -> ClientConfig {
+        let mut root_cert_store = RootCertStore::empty();
+
+        for ca_cert in load_certs(&get_tls_filepath("ca.crt")).unwrap() {
+            root_cert_store.add(&ca_cert).unwrap();
+        }
+        let config_builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_cert_store);
+
+        if with_client_cert {
+            let certs = load_certs(&get_tls_filepath("node.crt")).unwrap();
+            let private_key = load_private_key(&get_tls_filepath("node.key")).unwrap();
+            config_builder
+                .with_client_auth_cert(certs, private_key)
+                .unwrap()
+        } else {
+            config_builder.with_no_client_auth()
+        }
+    }
+
+    /// Sends a ping over TLS to a server accepting connections with `tls_incoming` and returns
+    /// the response.
+    async fn ping_aux(tls_config: &TlsConfig, client_config: ClientConfig) -> io::Result<Vec<u8>> {
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_addr = tcp_listener.local_addr().unwrap();
+        let reloadable_tls_acceptor =
+            ReloadableTlsAcceptor::load(tls_config, REST_ALPN_PROTOCOLS).unwrap();
+
+        tokio::spawn(async move {
+            let mut incoming = Box::pin(tls_incoming(tcp_listener, reloadable_tls_acceptor));
+
+            while let Some(Ok(mut tls_stream)) = incoming.next().await {
+                let mut buffer = [0u8; 4];
+                tls_stream.read_exact(&mut buffer).await.unwrap();
+                tls_stream.write_all(b"pong").await.unwrap();
+                tls_stream.shutdown().await.unwrap();
+            }
+        });
+        let tls_connector = TlsConnector::from(Arc::new(client_config));
+        let tcp_stream = TcpStream::connect(listen_addr).await?;
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let mut tls_stream = tls_connector.connect(server_name, tcp_stream).await?;
+        tls_stream.write_all(b"ping").await?;
+
+        let mut response = Vec::new();
+        tls_stream.read_to_end(&mut response).await?;
+        Ok(response)
+    }
+
+    #[test]
+    fn test_load_server_config() {
+        let tls_config = tls_config_for_test(true);
+        let server_config = load_server_config(&tls_config, &[b"h2".to_vec()]).unwrap();
+        assert_eq!(server_config.alpn_protocols, [b"h2".to_vec()]);
+
+        let tls_config = TlsConfig {
+            key_path: get_tls_filepath("node.crt"),
+            ..tls_config_for_test(false)
+        };
+        let error = load_server_config(&tls_config, &[]).unwrap_err();
+        assert!(error.to_string().contains("no private key found in `"));
+
+        let tls_config = TlsConfig {
+            cert_path: get_tls_filepath("does-not-exist.crt"),
+            ..tls_config_for_test(false)
+        };
+        let error = load_server_config(&tls_config, &[]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("failed to open certificate file `"));
+
+        let tls_config = TlsConfig {
+            cert_path: get_tls_filepath("node.key"),
+            ..tls_config_for_test(false)
+        };
+        let error = load_server_config(&tls_config, &[]).unwrap_err();
+        assert!(error.to_string().contains("no certificate found in `"));
+    }
+
+    #[test]
+    fn test_load_grpc_client_tls_config() {
+        load_grpc_client_tls_config(&tls_config_for_test(true)).unwrap();
+
+        let tls_config = TlsConfig {
+            ca_path: None,
+            ..tls_config_for_test(false)
+        };
+        load_grpc_client_tls_config(&tls_config).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_tls_incoming() {
+        let tls_config = tls_config_for_test(false);
+        let response = ping_aux(&tls_config, client_config_for_test(false))
+            .await
+            .unwrap();
+        assert_eq!(response, b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_tls_incoming_validate_client() {
+        let tls_config = tls_config_for_test(true);
+        let response = ping_aux(&tls_config, client_config_for_test(true))
+            .await
+            .unwrap();
+        assert_eq!(response, b"pong");
+
+        ping_aux(&tls_config, client_config_for_test(false))
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_reloadable_tls_acceptor_reloads_modified_files() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let cert_path = tempdir.path().join("node.crt");
+        let key_path = tempdir.path().join("node.key");
+        fs::copy(get_tls_filepath("node.crt"), &cert_path).unwrap();
+        fs::copy(get_tls_filepath("node.key"), &key_path).unwrap();
+
+        let tls_config = TlsConfig {
+            cert_path: cert_path.clone(),
+            key_path: key_path.clone(),
+            ca_path: None,
+            validate_client: false,
+            expected_name: None,
+        };
+        let reloadable_tls_acceptor =
+            ReloadableTlsAcceptor::load(&tls_config, GRPC_ALPN_PROTOCOLS).unwrap();
+        assert!(!reloadable_tls_acceptor.reload_if_modified().unwrap());
+
+        // We set the modification times explicitly because the resolution of the file system
+        // clock may be too coarse to tell the copies apart.
+        let touch = |path: &Path, offset_secs: u64| {
+            let file = fs::File::options().write(true).open(path).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(offset_secs))
+                .unwrap();
+        };
+        // An invalid certificate is not loaded, and the previous configuration is kept.
+        fs::write(&cert_path, "not a certificate").unwrap();
+        touch(&cert_path, 60);
+        reloadable_tls_acceptor.reload_if_modified().unwrap_err();
+
+        fs::copy(get_tls_filepath("node-rotated.crt"), &cert_path).unwrap();
+        fs::copy(get_tls_filepath("node-rotated.key"), &key_path).unwrap();
+        touch(&cert_path, 120);
+        touch(&key_path, 120);
+        assert!(reloadable_tls_acceptor.reload_if_modified().unwrap());
+        assert!(!reloadable_tls_acceptor.reload_if_modified().unwrap());
+    }
+}